@@ -2,13 +2,17 @@ use crate::{allow_std_io::AllowStdIo,
             error::{Error,
                     Result},
             hab_http::ApiClient,
+            mirror::Mirror,
+            rate_limit::RateLimiter,
             response,
             BuildOnUpload,
             DisplayProgress,
+            Job,
+            JobLog,
             OriginInfoResponse,
             OriginKeyIdent,
             OriginMemberRoleResponse,
-            OriginSecret,
+            OriginSecretsResponse,
             Package,
             PendingOriginInvitationsResponse,
             ReverseDependencies,
@@ -16,7 +20,12 @@ use crate::{allow_std_io::AllowStdIo,
             UserOriginInvitationsResponse};
 use broadcast::BroadcastWriter;
 use bytes::BytesMut;
-use futures::stream::TryStreamExt;
+use chrono::{DateTime,
+             Utc};
+use futures::{pin_mut,
+              stream::{self,
+                       Stream,
+                       TryStreamExt}};
 use habitat_core::{crypto::keys::AnonymousBox,
                    fs::{AtomicWriter,
                         Permissions,
@@ -28,17 +37,22 @@ use habitat_core::{crypto::keys::AnonymousBox,
                    package::{Identifiable,
                              PackageArchive,
                              PackageIdent,
-                             PackageTarget},
+                             PackageTarget,
+                             VersionReq},
                    util,
                    ChannelIdent};
 use percent_encoding::{percent_encode,
                        AsciiSet,
                        CONTROLS};
-use reqwest::{header::CONTENT_LENGTH,
+use reqwest::{header::{CONTENT_LENGTH,
+                       CONTENT_TYPE,
+                       DATE},
               Body,
               IntoUrl,
               RequestBuilder,
+              Response,
               StatusCode};
+use serde::de::DeserializeOwned;
 use std::{fs::{self,
                File},
           future::Future,
@@ -126,7 +140,9 @@ pub struct OriginChannelIdent {
     pub name: String,
 }
 
-pub struct BuilderAPIClient(ApiClient);
+/// A client for the Builder API. If created with a `file://` or `s3://` endpoint the client instead
+/// reads from a static mirror of Builder; see the `mirror` module for the expected layout.
+pub struct BuilderAPIClient(ApiClient, RateLimiter, Option<Mirror>);
 
 impl BuilderAPIClient {
     pub fn new<U>(endpoint: U,
@@ -137,12 +153,15 @@ impl BuilderAPIClient {
         where U: IntoUrl
     {
         let mut endpoint = endpoint.into_url().map_err(Error::ReqwestError)?;
-        if !endpoint.cannot_be_a_base() && endpoint.path() == "/" {
+        let mirror = Mirror::from_endpoint(&mut endpoint)?;
+        if mirror.is_none() && !endpoint.cannot_be_a_base() && endpoint.path() == "/" {
             endpoint.set_path(DEFAULT_API_PATH);
         }
         let client = BuilderAPIClient(
             ApiClient::new(endpoint, product, version, fs_root_path)
                 .map_err(Error::HabitatHttpClient)?,
+            RateLimiter::default(),
+            mirror,
         );
         Ok(client)
     }
@@ -157,6 +176,15 @@ impl BuilderAPIClient {
         Self::new(endpoint, product, version, fs_root_path)
     }
 
+    /// Send a request to Builder, waiting out and retrying on any rate limiting. Mirrors do not
+    /// implement the Builder API so every request sent to them is rejected.
+    async fn send(&self, rb: RequestBuilder) -> Result<Response> {
+        if self.2.is_some() {
+            return Err(Error::MirrorUnsupported);
+        }
+        self.1.send(rb).await
+    }
+
     fn maybe_add_authz(&self, rb: RequestBuilder, token: Option<&str>) -> RequestBuilder {
         match token {
             Some(token) => rb.bearer_auth(token),
@@ -172,32 +200,19 @@ impl BuilderAPIClient {
                           progress: Option<Box<dyn DisplayProgress>>)
                           -> Result<PathBuf> {
         debug!("Downloading file to path: {}", dst_path.display());
-        let resp = self.maybe_add_authz(rb, token).send().await?;
+        let resp = self.send(self.maybe_add_authz(rb, token)).await?;
         let resp = response::ok_if(resp, &[StatusCode::OK]).await?;
 
-        fs::create_dir_all(&dst_path)?;
         let file_name = response::get_header(&resp, X_FILENAME)?;
-        let dst_file_path = dst_path.join(file_name);
-        let w = AtomicWriter::new_with_permissions(&dst_file_path, permissions)?;
-        let content_length = response::get_header(&resp, CONTENT_LENGTH);
-        let mut body = Cursor::new(resp.bytes().await?);
-        // Blocking IO is used because of `DisplayProgress` which relies on the `Write` trait.
-        task::spawn_blocking(move || {
-            w.with_writer(|mut f| {
-                 // There will be no CONTENT_LENGTH header if an on prem
-                 // builder is using chunked transfer encoding
-                 match (progress, content_length) {
-                     (Some(mut progress), Ok(content_length)) => {
-                         let size = content_length.parse().map_err(Error::ParseIntError)?;
-                         progress.size(size);
-                         let mut writer = BroadcastWriter::new(&mut f, progress);
-                         io::copy(&mut body, &mut writer).map_err(Error::IO)
-                     }
-                     _ => io::copy(&mut body, &mut f).map_err(Error::IO),
-                 }
-             })?;
-            Ok(dst_file_path)
-        }).await?
+        let content_length =
+            response::get_header(&resp, CONTENT_LENGTH).ok()
+                                                       .and_then(|l| l.parse().ok());
+        let body = Cursor::new(resp.bytes().await?);
+        write_download(body,
+                       content_length,
+                       &dst_path.join(file_name),
+                       permissions,
+                       progress).await
     }
 
     async fn upload_body(src_path: &Path,
@@ -234,7 +249,7 @@ impl BuilderAPIClient {
                       .get_with_custom_url(&package_search(search_term), |url| {
                           url.set_query(Some(&format!("range={:?}&distinct=true", range)));
                       });
-        let resp = self.maybe_add_authz(req, token).send().await?;
+        let resp = self.send(self.maybe_add_authz(req, token)).await?;
         let status = resp.status();
         debug!("Response Status: {:?}", status);
 
@@ -260,17 +275,66 @@ impl BuilderAPIClient {
                                         -> Result<(Vec<PackageIdent>, usize)>
         where F: Future<Output = Result<(PackageResults<PackageIdent>, bool)>>
     {
+        let pages = pages(|range| search_with_range(self, search_term, token, range));
+        pin_mut!(pages);
+
         let mut packages = Vec::new();
-        loop {
-            let (mut package_results, more_to_come) =
-                search_with_range(self, search_term, token, packages.len()).await?;
+        let mut total_count = 0;
+        while let Some(mut package_results) = pages.try_next().await? {
+            total_count = package_results.total_count as usize;
             packages.append(&mut package_results.data);
 
-            if packages.len() >= limit || !more_to_come {
-                packages.truncate(limit);
-                return Ok((packages, package_results.total_count as usize));
+            if packages.len() >= limit {
+                break;
             }
         }
+        packages.truncate(limit);
+        Ok((packages, total_count))
+    }
+
+    /// Fetches a single page of a ranged Builder listing. Builder responds with `206 Partial
+    /// Content` when there are more results available beyond the returned range.
+    async fn fetch_range<T>(&self,
+                            path: String,
+                            range: usize,
+                            target: Option<PackageTarget>,
+                            token: Option<&str>)
+                            -> Result<(PackageResults<T>, bool)>
+        where T: DeserializeOwned
+    {
+        debug!("Fetching {} with range {}", path, range);
+        let req = self.0.get_with_custom_url(&path, |url| {
+                            let mut query = url.query_pairs_mut();
+                            query.append_pair("range", &range.to_string());
+                            if let Some(target) = target {
+                                query.append_pair("target", &target.to_string());
+                            }
+                        });
+        let resp = self.send(self.maybe_add_authz(req, token)).await?;
+        let resp = response::ok_if(resp, &[StatusCode::OK, StatusCode::PARTIAL_CONTENT]).await?;
+        let more_to_come = resp.status() == StatusCode::PARTIAL_CONTENT;
+
+        let encoded = resp.text().await.map_err(Error::BadResponseBody)?;
+        trace!(target: "habitat_http_client::api_client::fetch_range", "{:?}", encoded);
+
+        Ok((serde_json::from_str(&encoded)?, more_to_come))
+    }
+
+    /// Checks that Builder is up and answering requests. Returns the time according to Builder,
+    /// taken from the `Date` header of its response, if it sent one.
+    ///
+    /// # Failures
+    ///
+    /// * Remote Builder is not available
+    pub async fn status(&self) -> Result<Option<DateTime<Utc>>> {
+        let resp = self.send(self.0.get("status")).await?;
+        let resp = response::ok_if(resp, &[StatusCode::OK]).await?;
+        let date = resp.headers()
+                       .get(DATE)
+                       .and_then(|date| date.to_str().ok())
+                       .and_then(|date| DateTime::parse_from_rfc2822(date).ok())
+                       .map(|date| date.with_timezone(&Utc));
+        Ok(date)
     }
 
     /// Retrieves the status of every group job in an origin
@@ -292,7 +356,7 @@ impl BuilderAPIClient {
                .append_pair("limit", &limit.to_string());
         };
 
-        let resp = self.0.get_with_custom_url(&path, custom).send().await?;
+        let resp = self.send(self.0.get_with_custom_url(&path, custom)).await?;
         let resp = response::ok_if(resp, &[StatusCode::OK]).await?;
 
         Ok(resp.json().await?)
@@ -317,7 +381,7 @@ impl BuilderAPIClient {
                .append_pair("include_projects", &include_projects.to_string());
         };
 
-        let resp = self.0.get_with_custom_url(&path, custom).send().await?;
+        let resp = self.send(self.0.get_with_custom_url(&path, custom)).await?;
         let resp = response::ok_if(resp, &[StatusCode::OK]).await?;
 
         Ok(resp.json().await?)
@@ -344,10 +408,9 @@ impl BuilderAPIClient {
                .append_pair("target", &target.to_string());
         };
 
-        let resp = self.0
-                       .post_with_custom_url(&path, custom)
-                       .bearer_auth(token)
-                       .send()
+        let resp = self.send(self.0
+                                 .post_with_custom_url(&path, custom)
+                                 .bearer_auth(token))
                        .await?;
         debug!("Response Status: {:?}", resp.status());
 
@@ -372,12 +435,11 @@ impl BuilderAPIClient {
 
         let url = format!("rdeps/{}", ident);
 
-        let resp = self.0
-                       .get_with_custom_url(&url, |u| {
-                           u.set_query(Some(&format!("target={}", &target.to_string())))
-                       })
-                       .bearer_auth(token)
-                       .send()
+        let resp = self.send(self.0
+                                 .get_with_custom_url(&url, |u| {
+                                     u.set_query(Some(&format!("target={}", &target.to_string())))
+                                 })
+                                 .bearer_auth(token))
                        .await?;
         let resp = response::ok_if(resp, &[StatusCode::OK]).await?;
 
@@ -413,11 +475,7 @@ impl BuilderAPIClient {
                           if promote { "promote" } else { "demote" },
                           channel);
 
-        response::ok_if_unit(self.0
-                                 .post(&url)
-                                 .bearer_auth(token)
-                                 .json(&body)
-                                 .send()
+        response::ok_if_unit(self.send(self.0.post(&url).bearer_auth(token).json(&body))
                                  .await?,
                              &[StatusCode::NO_CONTENT]).await
     }
@@ -432,10 +490,40 @@ impl BuilderAPIClient {
 
         let url = format!("jobs/group/{}/cancel", group_id);
 
-        response::ok_if_unit(self.0.post(&url).bearer_auth(token).send().await?,
+        response::ok_if_unit(self.send(self.0.post(&url).bearer_auth(token)).await?,
                              &[StatusCode::NO_CONTENT]).await
     }
 
+    /// Retrieves the build log of a job beginning at the given line offset
+    ///
+    /// # Failures
+    ///
+    /// * Job does not exist
+    /// * Remote Builder is not available
+    pub async fn get_job_log(&self,
+                             job_id: u64,
+                             start: u64,
+                             token: Option<&str>)
+                             -> Result<JobLog> {
+        debug!("Retrieving log for job {} starting at line {}",
+               job_id, start);
+
+        let path = format!("jobs/{}/log", job_id);
+
+        let custom = |url: &mut Url| {
+            url.query_pairs_mut()
+               .append_pair("start", &start.to_string())
+               .append_pair("color", "false");
+        };
+
+        let resp = self.send(self.maybe_add_authz(self.0.get_with_custom_url(&path, custom),
+                                                  token))
+                       .await?;
+        let resp = response::ok_if(resp, &[StatusCode::OK]).await?;
+
+        Ok(resp.json().await?)
+    }
+
     /// Download a public encryption key from a remote Builder to the given filepath.
     ///
     /// # Failures
@@ -469,11 +557,7 @@ impl BuilderAPIClient {
             "name": origin,
         });
 
-        response::ok_if_unit(self.0
-                                 .post("depot/origins")
-                                 .bearer_auth(token)
-                                 .json(&body)
-                                 .send()
+        response::ok_if_unit(self.send(self.0.post("depot/origins").bearer_auth(token).json(&body))
                                  .await?,
                              &[StatusCode::CREATED]).await
     }
@@ -497,15 +581,40 @@ impl BuilderAPIClient {
             "value": secret.to_string()
         });
 
-        response::ok_if_unit(self.0
-                                 .post(&path)
-                                 .bearer_auth(token)
-                                 .json(&body)
-                                 .send()
+        response::ok_if_unit(self.send(self.0.post(&path).bearer_auth(token).json(&body))
                                  .await?,
                              &[StatusCode::CREATED]).await
     }
 
+    /// Replace the value of an origin secret, creating the secret if it does not exist yet.
+    ///
+    /// Builder has no endpoint for modifying a secret, nor does it accept a second secret of the
+    /// same name, so any existing secret is deleted and recreated. Should the new value fail to
+    /// upload, the secret is gone; the error says so.
+    ///
+    /// # Failures
+    ///
+    /// * Remote Builder is not available
+    /// * The new value could not be uploaded after the previous one was deleted
+    pub async fn update_origin_secret(&self,
+                                      origin: &Origin,
+                                      token: &str,
+                                      key_name: &str,
+                                      secret: &AnonymousBox)
+                                      -> Result<()> {
+        let deleted = match self.delete_origin_secret(origin, token, key_name).await {
+            Ok(()) => true,
+            Err(Error::APIError(StatusCode::NOT_FOUND, _)) => false,
+            Err(e) => return Err(e),
+        };
+        match self.create_origin_secret(origin, token, key_name, secret)
+                  .await
+        {
+            Err(e) if deleted => Err(Error::SecretReplaceFailed(key_name.to_string(), Box::new(e))),
+            result => result,
+        }
+    }
+
     /// Delete a secret for an origin
     ///
     /// # Failures
@@ -523,7 +632,7 @@ impl BuilderAPIClient {
         // Originally, we only returned an Ok result if the response was StatusCode::NO_CONTENT
         // (HTTP 204). However the Bldr API appears to always have returned HTTP 200. We'll accept
         // either as indicators of a successful operation moving forward.
-        response::ok_if_unit(self.0.delete(&path).bearer_auth(token).send().await?,
+        response::ok_if_unit(self.send(self.0.delete(&path).bearer_auth(token)).await?,
                              &[StatusCode::NO_CONTENT, StatusCode::OK]).await
     }
 
@@ -538,7 +647,7 @@ impl BuilderAPIClient {
 
         let path = format!("depot/origins/{}", origin);
 
-        response::ok_if_unit(self.0.get(&path).bearer_auth(token).send().await?,
+        response::ok_if_unit(self.send(self.0.get(&path).bearer_auth(token)).await?,
                              &[StatusCode::OK]).await
     }
 
@@ -554,7 +663,7 @@ impl BuilderAPIClient {
 
         let path = format!("depot/origins/{}", origin);
 
-        response::ok_if_unit(self.0.delete(&path).bearer_auth(token).send().await?,
+        response::ok_if_unit(self.send(self.0.delete(&path).bearer_auth(token)).await?,
                              &[StatusCode::NO_CONTENT]).await
     }
 
@@ -576,7 +685,7 @@ impl BuilderAPIClient {
 
         let path = format!("depot/origins/{}/transfer/{}", origin, account);
 
-        response::ok_if_unit(self.0.post(&path).bearer_auth(token).send().await?,
+        response::ok_if_unit(self.send(self.0.post(&path).bearer_auth(token)).await?,
                              &[StatusCode::NO_CONTENT]).await
     }
 
@@ -594,7 +703,7 @@ impl BuilderAPIClient {
 
         let path = format!("depot/origins/{}/depart", origin);
 
-        response::ok_if_unit(self.0.post(&path).bearer_auth(token).send().await?,
+        response::ok_if_unit(self.send(self.0.post(&path).bearer_auth(token)).await?,
                              &[StatusCode::NO_CONTENT]).await
     }
 
@@ -623,7 +732,7 @@ impl BuilderAPIClient {
 
         let path = format!("depot/origins/{}/invitations/{}", origin, invitation_id);
 
-        response::ok_if_unit(self.0.put(&path).bearer_auth(token).send().await?,
+        response::ok_if_unit(self.send(self.0.put(&path).bearer_auth(token)).await?,
                              &[StatusCode::NO_CONTENT]).await
     }
 
@@ -655,7 +764,7 @@ impl BuilderAPIClient {
         let path = format!("depot/origins/{}/invitations/{}/ignore",
                            origin, invitation_id);
 
-        response::ok_if_unit(self.0.put(&path).bearer_auth(token).send().await?,
+        response::ok_if_unit(self.send(self.0.put(&path).bearer_auth(token)).await?,
                              &[StatusCode::NO_CONTENT]).await
     }
 
@@ -680,7 +789,7 @@ impl BuilderAPIClient {
                                        -> Result<UserOriginInvitationsResponse> {
         let path = "user/invitations";
 
-        let resp = self.0.get(&path).bearer_auth(token).send().await?;
+        let resp = self.send(self.0.get(&path).bearer_auth(token)).await?;
         let resp = response::ok_if(resp, &[StatusCode::OK]).await?;
 
         Ok(resp.json().await?)
@@ -699,7 +808,7 @@ impl BuilderAPIClient {
     pub async fn origin_info(&self, token: &str, origin: &str) -> Result<OriginInfoResponse> {
         let path = format!("depot/origins/{}", origin);
 
-        let resp = self.0.get(&path).bearer_auth(token).send().await?;
+        let resp = self.send(self.0.get(&path).bearer_auth(token)).await?;
         let resp = response::ok_if(resp, &[StatusCode::OK]).await?;
 
         Ok(resp.json().await?)
@@ -729,7 +838,7 @@ impl BuilderAPIClient {
         debug!("Retrieving pending invitations in origin {}", origin);
         let path = format!("depot/origins/{}/invitations", origin);
 
-        let resp = self.0.get(&path).bearer_auth(token).send().await?;
+        let resp = self.send(self.0.get(&path).bearer_auth(token)).await?;
         let resp = response::ok_if(resp, &[StatusCode::OK]).await?;
 
         Ok(resp.json().await?)
@@ -762,7 +871,7 @@ impl BuilderAPIClient {
 
         let path = format!("depot/origins/{}/invitations/{}", origin, invitation_id);
 
-        response::ok_if_unit(self.0.delete(&path).bearer_auth(token).send().await?,
+        response::ok_if_unit(self.send(self.0.delete(&path).bearer_auth(token)).await?,
                              &[StatusCode::NO_CONTENT]).await
     }
 
@@ -795,7 +904,7 @@ impl BuilderAPIClient {
         let path = format!("depot/origins/{}/users/{}/invitations",
                            origin, invitee_account);
 
-        response::ok_if_unit(self.0.post(&path).bearer_auth(token).send().await?,
+        response::ok_if_unit(self.send(self.0.post(&path).bearer_auth(token)).await?,
                              &[StatusCode::CREATED]).await
     }
 
@@ -805,18 +914,34 @@ impl BuilderAPIClient {
     ///
     /// * Remote Builder is not available
     pub async fn list_origin_secrets(&self, origin: &Origin, token: &str) -> Result<Vec<String>> {
+        Ok(self.list_origin_secrets_metadata(origin, token)
+               .await?
+               .0
+               .into_iter()
+               .map(|s| s.name)
+               .collect())
+    }
+
+    /// List the secrets of an origin along with who created them and when. Secret values are
+    /// never returned decrypted.
+    ///
+    /// # Failures
+    ///
+    /// * Remote Builder is not available
+    pub async fn list_origin_secrets_metadata(&self,
+                                              origin: &Origin,
+                                              token: &str)
+                                              -> Result<OriginSecretsResponse> {
         debug!("Listing origin secret: {}", origin);
 
         let path = format!("depot/origins/{}/secret", origin);
-        let resp = self.0.get(&path).bearer_auth(token).send().await?;
+        let resp = self.send(self.0.get(&path).bearer_auth(token)).await?;
         let resp = response::ok_if(resp, &[StatusCode::OK]).await?;
 
         let encoded = resp.text().await.map_err(Error::BadResponseBody)?;
         trace!(target: "habitat_http_client::api_client::list_origin_secrets", "{:?}", encoded);
 
-        Ok(serde_json::from_str::<Vec<OriginSecret>>(&encoded)?.into_iter()
-                                                               .map(|s| s.name)
-                                                               .collect())
+        Ok(serde_json::from_str::<OriginSecretsResponse>(&encoded)?)
     }
 
     /// Download a public key from a remote Builder to the given filepath.
@@ -833,6 +958,15 @@ impl BuilderAPIClient {
                                       dst_path: &'a Path,
                                       progress: Option<Box<dyn DisplayProgress>>)
                                       -> Result<PathBuf> {
+        if let Some(mirror) = &self.2 {
+            return mirror.fetch_origin_key(&self.0,
+                                           origin,
+                                           revision,
+                                           dst_path,
+                                           DEFAULT_PUBLIC_KEY_PERMISSIONS,
+                                           progress)
+                         .await;
+        }
         self.download(self.0
                           .get(&format!("depot/origins/{}/keys/{}", origin, revision)),
                       dst_path.as_ref(),
@@ -865,9 +999,12 @@ impl BuilderAPIClient {
     }
 
     pub async fn show_origin_keys(&self, origin: &Origin) -> Result<Vec<OriginKeyIdent>> {
+        if let Some(mirror) = &self.2 {
+            return mirror.show_origin_keys(&self.0, origin.as_ref()).await;
+        }
         debug!("Showing origin keys: {}", origin);
 
-        let resp = self.0.get(&origin_keys_path(origin)).send().await?;
+        let resp = self.send(self.0.get(&origin_keys_path(origin))).await?;
         let resp = response::ok_if(resp, &[StatusCode::OK]).await?;
 
         let encoded = resp.text().await.map_err(Error::BadResponseBody)?;
@@ -876,6 +1013,44 @@ impl BuilderAPIClient {
         Ok(serde_json::from_str::<Vec<OriginKeyIdent>>(&encoded)?)
     }
 
+    /// Return the revocation list of an origin's signing keys, as the JSON document it was
+    /// uploaded as.
+    ///
+    /// # Failures
+    ///
+    /// * Remote Builder is not available
+    /// * The origin has no revocation list
+    pub async fn get_origin_revocations(&self, origin: &Origin) -> Result<String> {
+        debug!("Retrieving revocation list of origin: {}", origin);
+
+        let resp = self.send(self.0.get(&origin_revocations_path(origin)))
+                       .await?;
+        let resp = response::ok_if(resp, &[StatusCode::OK]).await?;
+        resp.text().await.map_err(Error::BadResponseBody)
+    }
+
+    /// Replace the revocation list of an origin's signing keys with a signed JSON document.
+    ///
+    /// # Failures
+    ///
+    /// * Remote Builder is not available
+    /// * The list is not signed with a key of the origin
+    pub async fn put_origin_revocations(&self,
+                                        origin: &Origin,
+                                        token: &str,
+                                        revocations: String)
+                                        -> Result<()> {
+        debug!("Uploading revocation list of origin: {}", origin);
+
+        let resp = self.send(self.0
+                                 .put(&origin_revocations_path(origin))
+                                 .bearer_auth(token)
+                                 .header(CONTENT_TYPE, "application/json")
+                                 .body(revocations))
+                       .await?;
+        response::ok_if_unit(resp, &[StatusCode::OK, StatusCode::CREATED]).await
+    }
+
     /// Return a list of channels for a given package
     ///
     /// # Failures
@@ -892,6 +1067,10 @@ impl BuilderAPIClient {
             return Err(Error::IdentNotFullyQualified);
         }
 
+        if let Some(mirror) = &self.2 {
+            return mirror.package_channels(&self.0, (ident, target)).await;
+        }
+
         let path = package_channels_path(ident);
 
         let custom = |url: &mut Url| {
@@ -899,8 +1078,8 @@ impl BuilderAPIClient {
                .append_pair("target", &target.to_string());
         };
 
-        let resp = self.maybe_add_authz(self.0.get_with_custom_url(&path, custom), token)
-                       .send()
+        let resp = self.send(self.maybe_add_authz(self.0.get_with_custom_url(&path, custom),
+                                                  token))
                        .await?;
         let resp = response::ok_if(resp, &[StatusCode::OK]).await?;
 
@@ -932,11 +1111,7 @@ impl BuilderAPIClient {
 
         let path = format!("depot/origins/{}/keys/{}", &origin, &revision);
         let body = Self::upload_body(src_path, progress).await?;
-        let resp = self.0
-                       .post(&path)
-                       .bearer_auth(token)
-                       .body(body)
-                       .send()
+        let resp = self.send(self.0.post(&path).bearer_auth(token).body(body))
                        .await?;
         response::ok_if_unit(resp, &[StatusCode::OK, StatusCode::CREATED]).await
     }
@@ -962,11 +1137,7 @@ impl BuilderAPIClient {
 
         let path = format!("depot/origins/{}/secret_keys/{}", &origin, &revision);
         let body = Self::upload_body(src_path, progress).await?;
-        let resp = self.0
-                       .post(&path)
-                       .bearer_auth(token)
-                       .body(body)
-                       .send()
+        let resp = self.send(self.0.post(&path).bearer_auth(token).body(body))
                        .await?;
         response::ok_if_unit(resp, &[StatusCode::OK]).await
     }
@@ -998,6 +1169,15 @@ impl BuilderAPIClient {
             return Err(Error::IdentNotFullyQualified);
         }
 
+        if let Some(mirror) = &self.2 {
+            return mirror.fetch_package(&self.0,
+                                        (ident, target),
+                                        dst_path,
+                                        DEFAULT_CACHED_ARTIFACT_PERMISSIONS,
+                                        progress)
+                         .await;
+        }
+
         let req_builder = self.0.get_with_custom_url(&package_download(ident), |u| {
                                     u.set_query(Some(&format!("target={}", target)))
                                 });
@@ -1029,15 +1209,21 @@ impl BuilderAPIClient {
             return Err(Error::IdentNotFullyQualified);
         }
 
+        if let Some(mirror) = &self.2 {
+            return mirror.show_package_metadata(&self.0,
+                                                (package, target),
+                                                &ChannelIdent::unstable())
+                         .await
+                         .map(|_| ());
+        }
+
         let url = channel_package_path(&ChannelIdent::unstable(), package);
 
-        response::ok_if_unit(self.maybe_add_authz(self.0.get_with_custom_url(&url, |u| {
+        response::ok_if_unit(self.send(self.maybe_add_authz(self.0.get_with_custom_url(&url, |u| {
                                                             u.set_query(Some(&format!("target={}",
                                                                                       target)))
                                                         }),
-                                                  token)
-                                 .send()
-                                 .await?,
+                                                  token)).await?,
                              &[StatusCode::OK]).await
     }
 
@@ -1080,18 +1266,33 @@ impl BuilderAPIClient {
         debug!("Retrieving package metadata for {}, target {}",
                package, target);
 
+        if let Some(mirror) = &self.2 {
+            return mirror.show_package_metadata(&self.0, (package, target), channel)
+                         .await;
+        }
+
+        let resolved;
+        let package = if package.version().map_or(false, VersionReq::is_version_req) {
+            resolved = self.latest_in_version_req((package, target), channel, token)
+                           .await?;
+            &resolved
+        } else {
+            package
+        };
+
         let mut url = channel_package_path(channel, package);
 
         if !package.fully_qualified() {
             url.push_str("/latest");
         }
 
-        let resp = self.maybe_add_authz(self.0.get_with_custom_url(&url, |u| {
-                                                  u.set_query(Some(&format!("target={}", target)))
-                                              }),
-                                        token)
-                       .send()
-                       .await?;
+        let resp =
+            self.send(self.maybe_add_authz(self.0
+                                               .get_with_custom_url(&url, |u| {
+                                                   u.set_query(Some(&format!("target={}", target)))
+                                               }),
+                                           token))
+                .await?;
         let resp = response::ok_if(resp, &[StatusCode::OK]).await?;
 
         let encoded = resp.text().await.map_err(Error::BadResponseBody)?;
@@ -1101,6 +1302,36 @@ impl BuilderAPIClient {
         Ok(package)
     }
 
+    /// Returns the latest package in a channel whose version is in the range given by the
+    /// version of `package`, such as `core/redis/^6.2`.
+    ///
+    /// # Failures
+    ///
+    /// * No package in the channel is in the range
+    /// * Remote Builder is not available
+    async fn latest_in_version_req(&self,
+                                   (package, target): (&PackageIdent, PackageTarget),
+                                   channel: &ChannelIdent,
+                                   token: Option<&str>)
+                                   -> Result<PackageIdent> {
+        let path = format!("depot/channels/{}/{}/pkgs/{}",
+                           package.origin(),
+                           channel,
+                           package.name());
+        let candidates = paginate(|range| {
+                             self.fetch_range(path.clone(), range, Some(target), token)
+                         }).try_collect::<Vec<PackageIdent>>()
+                           .await?;
+        candidates.into_iter()
+                  .filter(|candidate| candidate.satisfies(package))
+                  .max_by(PackageIdent::by_parts_cmp)
+                  .ok_or_else(|| {
+                      Error::APIError(StatusCode::NOT_FOUND,
+                                      format!("No package in the {} channel matches {}",
+                                              channel, package))
+                  })
+    }
+
     /// Upload a package to a remote Builder.
     ///
     /// # Failures
@@ -1143,16 +1374,49 @@ impl BuilderAPIClient {
         debug!("Reading from {}", &pa.path.display());
         let body = Self::upload_body(&pa.path, progress).await?;
 
-        let resp = self.0
-                       .post_with_custom_url(&path, custom)
-                       .bearer_auth(token)
-                       .body(body)
-                       .send()
+        let resp = self.send(self.0
+                                 .post_with_custom_url(&path, custom)
+                                 .bearer_auth(token)
+                                 .body(body))
                        .await?;
 
         response::ok_if_unit(resp, &[StatusCode::OK, StatusCode::CREATED]).await
     }
 
+    /// Upload the provenance attestation of a package to a remote Builder, to be stored along
+    /// with the package.
+    ///
+    /// # Failures
+    ///
+    /// * Remote Builder is not available
+    /// * File cannot be read
+    ///
+    /// # Panics
+    ///
+    /// * Authorization token was not set on client
+    pub async fn put_package_provenance<'a>(&'a self,
+                                            (package, target): (&'a PackageIdent, PackageTarget),
+                                            src_path: &'a Path,
+                                            token: &'a str,
+                                            progress: Option<Box<dyn DisplayProgress>>)
+                                            -> Result<()> {
+        debug!("Uploading provenance of package {}, target {}",
+               package, target);
+
+        let path = format!("{}/provenance", package_path(package));
+        let custom = |url: &mut Url| {
+            url.query_pairs_mut()
+               .append_pair("target", &target.to_string());
+        };
+        let body = Self::upload_body(src_path, progress).await?;
+        let resp = self.send(self.0
+                                 .post_with_custom_url(&path, custom)
+                                 .bearer_auth(token)
+                                 .body(body))
+                       .await?;
+        response::ok_if_unit(resp, &[StatusCode::OK, StatusCode::CREATED]).await
+    }
+
     /// Delete a package from Builder
     ///
     /// # Failures
@@ -1173,10 +1437,9 @@ impl BuilderAPIClient {
                .append_pair("target", &target.to_string());
         };
 
-        response::ok_if_unit(self.0
-                                 .delete_with_custom_url(&path, custom)
-                                 .bearer_auth(token)
-                                 .send()
+        response::ok_if_unit(self.send(self.0
+                                           .delete_with_custom_url(&path, custom)
+                                           .bearer_auth(token))
                                  .await?,
                              &[StatusCode::NO_CONTENT]).await
     }
@@ -1209,10 +1472,9 @@ impl BuilderAPIClient {
                .append_pair("target", &target.to_string());
         };
 
-        response::ok_if_unit(self.0
-                                 .put_with_custom_url(&path, custom)
-                                 .bearer_auth(token)
-                                 .send()
+        response::ok_if_unit(self.send(self.0
+                                           .put_with_custom_url(&path, custom)
+                                           .bearer_auth(token))
                                  .await?,
                              &[StatusCode::OK]).await
     }
@@ -1245,10 +1507,9 @@ impl BuilderAPIClient {
                .append_pair("target", &target.to_string());
         };
 
-        response::ok_if_unit(self.0
-                                 .put_with_custom_url(&path, custom)
-                                 .bearer_auth(token)
-                                 .send()
+        response::ok_if_unit(self.send(self.0
+                                           .put_with_custom_url(&path, custom)
+                                           .bearer_auth(token))
                                  .await?,
                              &[StatusCode::OK]).await
     }
@@ -1266,7 +1527,7 @@ impl BuilderAPIClient {
         debug!("Creating channel {} for origin {}", channel, origin);
 
         let path = format!("depot/channels/{}/{}", origin, channel);
-        response::ok_if_unit(self.0.post(&path).bearer_auth(token).send().await?,
+        response::ok_if_unit(self.send(self.0.post(&path).bearer_auth(token)).await?,
                              &[StatusCode::CREATED]).await
     }
 
@@ -1283,7 +1544,7 @@ impl BuilderAPIClient {
         debug!("Deleting channel {} for origin {}", channel, origin);
 
         let path = format!("depot/channels/{}/{}", origin, channel);
-        response::ok_if_unit(self.0.delete(&path).bearer_auth(token).send().await?,
+        response::ok_if_unit(self.send(self.0.delete(&path).bearer_auth(token)).await?,
                              &[StatusCode::OK]).await
     }
 
@@ -1303,13 +1564,12 @@ impl BuilderAPIClient {
 
         let path = format!("depot/channels/{}/{}/pkgs/promote", origin, source_channel);
 
-        response::ok_if_unit(self.0
-                                 .put_with_custom_url(&path, |url| {
-                                     url.query_pairs_mut()
-                                        .append_pair("channel", target_channel.as_str());
-                                 })
-                                 .bearer_auth(token)
-                                 .send()
+        response::ok_if_unit(self.send(self.0
+                                           .put_with_custom_url(&path, |url| {
+                                               url.query_pairs_mut()
+                                                  .append_pair("channel", target_channel.as_str());
+                                           })
+                                           .bearer_auth(token))
                                  .await?,
                              &[StatusCode::OK]).await
     }
@@ -1330,13 +1590,12 @@ impl BuilderAPIClient {
 
         let path = format!("depot/channels/{}/{}/pkgs/demote", origin, source_channel);
 
-        response::ok_if_unit(self.0
-                                 .put_with_custom_url(&path, |url| {
-                                     url.query_pairs_mut()
-                                        .append_pair("channel", target_channel.as_str());
-                                 })
-                                 .bearer_auth(token)
-                                 .send()
+        response::ok_if_unit(self.send(self.0
+                                           .put_with_custom_url(&path, |url| {
+                                               url.query_pairs_mut()
+                                                  .append_pair("channel", target_channel.as_str());
+                                           })
+                                           .bearer_auth(token))
                                  .await?,
                              &[StatusCode::OK]).await
     }
@@ -1355,6 +1614,66 @@ impl BuilderAPIClient {
             .await
     }
 
+    /// Returns a stream of every package matching a search term. Unlike `search_package` the
+    /// results are not limited; further pages are requested from Builder as the stream is
+    /// consumed.
+    ///
+    /// # Failures
+    ///
+    /// * Remote depot unavailable
+    pub fn search_packages<'a>(&'a self,
+                               search_term: &'a str,
+                               token: Option<&'a str>)
+                               -> impl Stream<Item = Result<PackageIdent>> + 'a {
+        paginate(move |range| self.seach_package_with_range(search_term, token, range))
+    }
+
+    /// Returns a stream of every package in an origin, following Builder's pagination as the
+    /// stream is consumed
+    ///
+    /// # Failures
+    ///
+    /// * Remote Builder is not available
+    pub fn list_origin_packages<'a>(&'a self,
+                                    origin: &Origin,
+                                    token: Option<&'a str>)
+                                    -> impl Stream<Item = Result<PackageIdent>> + 'a {
+        let path = format!("depot/pkgs/{}", origin);
+        paginate(move |range| self.fetch_range(path.clone(), range, None, token))
+    }
+
+    /// Returns a stream of every package in an origin's channel, following Builder's pagination as
+    /// the stream is consumed
+    ///
+    /// # Failures
+    ///
+    /// * Remote Builder is not available
+    /// * Channel does not exist
+    pub fn list_channel_packages<'a>(&'a self,
+                                     origin: &Origin,
+                                     channel: &ChannelIdent,
+                                     token: Option<&'a str>)
+                                     -> impl Stream<Item = Result<PackageIdent>> + 'a {
+        let path = format!("depot/channels/{}/{}/pkgs", origin, channel);
+        paginate(move |range| self.fetch_range(path.clone(), range, None, token))
+    }
+
+    /// Returns a stream of every build job of a project, following Builder's pagination as the
+    /// stream is consumed
+    ///
+    /// # Failures
+    ///
+    /// * Remote Builder is not available
+    /// * Project does not exist
+    pub fn list_project_jobs<'a>(&'a self,
+                                 origin: &Origin,
+                                 name: &str,
+                                 token: Option<&'a str>)
+                                 -> impl Stream<Item = Result<Job>> + 'a {
+        let path = format!("projects/{}/{}/jobs", origin, name);
+        paginate(move |range| self.fetch_range(path.clone(), range, None, token))
+    }
+
     /// Return a list of channels for a given origin
     ///
     /// # Failures
@@ -1368,12 +1687,11 @@ impl BuilderAPIClient {
 
         let path = format!("depot/channels/{}", origin);
         let resp = if include_sandbox_channels {
-            self.0
-                .get_with_custom_url(&path, |url| url.set_query(Some("sandbox=true")))
-                .send()
+            self.send(self.0
+                          .get_with_custom_url(&path, |url| url.set_query(Some("sandbox=true"))))
                 .await?
         } else {
-            self.0.get(&path).send().await?
+            self.send(self.0.get(&path)).await?
         };
         debug!("Response Status: {:?}", resp.status());
 
@@ -1402,7 +1720,7 @@ impl BuilderAPIClient {
                member_account, origin);
 
         let path = format!("depot/origins/{}/users/{}/role", origin, member_account);
-        let resp = self.0.get(&path).bearer_auth(token).send().await?;
+        let resp = self.send(self.0.get(&path).bearer_auth(token)).await?;
         let resp = response::ok_if(resp, &[StatusCode::OK]).await?;
 
         Ok(resp.json().await?)
@@ -1425,12 +1743,12 @@ impl BuilderAPIClient {
                member_account, role, origin);
 
         let path = format!("depot/origins/{}/users/{}/role", origin, member_account);
-        response::ok_if_unit(self.0
-                                 .put_with_custom_url(&path, |url| {
-                                     url.query_pairs_mut().append_pair("role", &role.to_string());
-                                 })
-                                 .bearer_auth(token)
-                                 .send()
+        response::ok_if_unit(self.send(self.0
+                                           .put_with_custom_url(&path, |url| {
+                                               url.query_pairs_mut()
+                                                  .append_pair("role", &role.to_string());
+                                           })
+                                           .bearer_auth(token))
                                  .await?,
                              &[StatusCode::NO_CONTENT]).await
     }
@@ -1471,8 +1789,79 @@ macro_rules! retry_builder_api {
     };
 }
 
+/// Write downloaded content to `dst_file_path`, reporting progress if the length is known.
+pub(crate) async fn write_download<R>(mut body: R,
+                                      content_length: Option<u64>,
+                                      dst_file_path: &Path,
+                                      permissions: Permissions,
+                                      progress: Option<Box<dyn DisplayProgress>>)
+                                      -> Result<PathBuf>
+    where R: io::Read + Send + 'static
+{
+    if let Some(dst_path) = dst_file_path.parent() {
+        fs::create_dir_all(dst_path)?;
+    }
+    let dst_file_path = dst_file_path.to_path_buf();
+    let w = AtomicWriter::new_with_permissions(&dst_file_path, permissions)?;
+    // Blocking IO is used because of `DisplayProgress` which relies on the `Write` trait.
+    task::spawn_blocking(move || {
+        w.with_writer(|mut f| {
+             // There will be no CONTENT_LENGTH header if an on prem
+             // builder is using chunked transfer encoding
+             match (progress, content_length) {
+                 (Some(mut progress), Some(content_length)) => {
+                     progress.size(content_length);
+                     let mut writer = BroadcastWriter::new(&mut f, progress);
+                     io::copy(&mut body, &mut writer).map_err(Error::IO)
+                 }
+                 _ => io::copy(&mut body, &mut f).map_err(Error::IO),
+             }
+         })?;
+        Ok(dst_file_path)
+    }).await?
+}
+
+/// Turn a function which fetches a single page of a ranged Builder listing into a stream of
+/// pages. The next page is only requested once the previous page has been consumed and Builder
+/// indicated that more results remain.
+fn pages<T, F, Fut>(fetch_page: F) -> impl Stream<Item = Result<PackageResults<T>>>
+    where F: Fn(usize) -> Fut,
+          Fut: Future<Output = Result<(PackageResults<T>, bool)>>
+{
+    stream::try_unfold(Some(0), move |range| {
+        let page = range.map(&fetch_page);
+        async move {
+            match page {
+                Some(page) => {
+                    let (results, more_to_come) = page.await?;
+                    let next = if more_to_come && !results.data.is_empty() {
+                        Some(results.range_start as usize + results.data.len())
+                    } else {
+                        None
+                    };
+                    Ok(Some((results, next)))
+                }
+                None => Ok(None),
+            }
+        }
+    })
+}
+
+/// Like `pages` but flattens the pages into a stream of the individual results.
+fn paginate<T, F, Fut>(fetch_page: F) -> impl Stream<Item = Result<T>>
+    where F: Fn(usize) -> Fut,
+          Fut: Future<Output = Result<(PackageResults<T>, bool)>>
+{
+    pages(fetch_page).map_ok(|page| stream::iter(page.data.into_iter().map(Ok)))
+                     .try_flatten()
+}
+
 fn origin_keys_path(origin: &Origin) -> String { format!("depot/origins/{}/keys", origin) }
 
+fn origin_revocations_path(origin: &Origin) -> String {
+    format!("depot/origins/{}/revocations", origin)
+}
+
 fn package_download(package: &PackageIdent) -> String {
     format!("{}/download", package_path(package))
 }
@@ -1647,6 +2036,25 @@ mod tests {
         assert_eq!(r.1, 0);
     }
 
+    #[tokio::test]
+    async fn paginate_follows_every_page() {
+        let client = BuilderAPIClient::new("http://test.com", "", "", None).expect("valid client");
+
+        let sample_data = vec!["one_a", "one_b", "one_c", "one_d", "one_e", "two_a", "two_b",
+                               "two_c", "two_d", "two_e",];
+
+        let searcher = seach_generator(sample_data.as_slice(), 2);
+        let items = paginate(|range| searcher(&client, "_", None, range));
+        let r = items.try_collect::<Vec<_>>().await.expect("valid pages");
+        assert_eq!(r.iter().map(|i| i.name.clone()).collect::<Vec<_>>(),
+                   sample_data);
+
+        let searcher = seach_generator(sample_data.as_slice(), 2);
+        let items = paginate(|range| searcher(&client, "does_not_exist", None, range));
+        let r = items.try_collect::<Vec<_>>().await.expect("valid pages");
+        assert!(r.is_empty());
+    }
+
     #[tokio::test]
     #[ignore = "takes too long to run regularly; should run on CI"]
     async fn package_search_large() {
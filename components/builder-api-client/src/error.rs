@@ -70,11 +70,15 @@ pub enum Error {
     KeyReadError(PathBuf, io::Error),
     MissingHeader(String),
     InvalidHeader(String),
+    InvalidMirror(String),
+    MirrorRead(PathBuf, io::Error),
+    MirrorUnsupported,
     NoFilePart,
     PackageReadError(PathBuf, io::Error),
     ParseIntError(num::ParseIntError),
     IdentNotFullyQualified,
     UploadFailed(String),
+    SecretReplaceFailed(String, Box<Error>),
     UrlParseError(url::ParseError),
     WriteSyncFailed,
     NotSupported,
@@ -103,6 +107,15 @@ impl fmt::Display for Error {
             }
             Error::MissingHeader(ref s) => format!("Response is missing a required header: {}", s),
             Error::InvalidHeader(ref s) => format!("Response header is invalid: {}", s),
+            Error::InvalidMirror(ref s) => format!("Invalid Builder mirror URL: {}", s),
+            Error::MirrorRead(ref p, ref e) => {
+                format!("Failed to read from Builder mirror, {}, {}", p.display(), e)
+            }
+            Error::MirrorUnsupported => {
+                "The specified operation is not supported by Builder mirrors. Mirrors can only be \
+                 used to download packages and public origin keys."
+                                                                   .to_string()
+            }
             Error::NoFilePart => "An invalid path was passed - we needed a filename, and this \
                                   path does not have one"
                                                          .to_string(),
@@ -116,6 +129,12 @@ impl fmt::Display for Error {
                                                                             .to_string()
             }
             Error::UploadFailed(ref s) => format!("Upload failed: {}", s),
+            Error::SecretReplaceFailed(ref name, ref e) => {
+                format!("The previous value of origin secret {} was deleted, but its new value \
+                         could not be uploaded: {}. The secret no longer exists on Builder; \
+                         upload it again.",
+                        name, e)
+            }
             Error::UrlParseError(ref e) => format!("{}", e),
             Error::WriteSyncFailed => {
                 "Could not write to destination; perhaps the disk is full?".to_string()
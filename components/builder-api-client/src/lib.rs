@@ -5,6 +5,8 @@ use habitat_core::{self as hab_core,
                                        TabularText}};
 use habitat_http_client as hab_http;
 
+#[macro_use]
+extern crate lazy_static;
 #[macro_use]
 extern crate log;
 
@@ -17,6 +19,8 @@ extern crate serde_json;
 mod allow_std_io;
 pub mod builder;
 pub mod error;
+mod mirror;
+mod rate_limit;
 pub mod response;
 
 use std::str::FromStr;
@@ -39,7 +43,11 @@ pub use crate::{builder::{BuilderAPIClient,
                           API_RETRY_DELAY},
                 error::{APIFailure,
                         Error,
-                        Result}};
+                        Result},
+                mirror::S3_ENDPOINT_ENVVAR,
+                rate_limit::{set_rate_limit_observer,
+                             RateLimitObserver,
+                             RATE_LIMIT_RETRY_COUNT}};
 
 pub trait DisplayProgress: Write + Send + Sync {
     fn size(&mut self, size: u64);
@@ -118,6 +126,38 @@ impl fmt::Display for SchedulerResponse {
     }
 }
 
+/// A single build job of a Builder project
+#[derive(Clone, Default, Deserialize)]
+pub struct Job {
+    pub id:                String,
+    pub state:             String,
+    pub origin:            String,
+    pub name:              String,
+    #[serde(default)]
+    pub version:           Option<String>,
+    #[serde(default)]
+    pub release:           Option<String>,
+    #[serde(default)]
+    pub target:            Option<String>,
+    #[serde(default)]
+    pub created_at:        Option<String>,
+    #[serde(default)]
+    pub build_started_at:  Option<String>,
+    #[serde(default)]
+    pub build_finished_at: Option<String>,
+}
+
+/// A portion of the build log for a single job, as returned by Builder. The `start` and `stop`
+/// fields are line offsets, so a subsequent request beginning at `stop` will only return lines
+/// which have been added since.
+#[derive(Clone, Default, Deserialize)]
+pub struct JobLog {
+    pub start:       u64,
+    pub stop:        u64,
+    pub content:     Vec<String>,
+    pub is_complete: bool,
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct OriginPrivateSigningKey {
     #[serde(with = "util::serde::string")]
@@ -290,10 +330,36 @@ impl TabularText for OriginInfoResponse {
 
 #[derive(Clone, Deserialize)]
 pub struct OriginSecret {
-    pub id:        String,
-    pub origin_id: String,
-    pub name:      String,
-    pub value:     String,
+    pub id:         String,
+    pub origin_id:  String,
+    pub name:       String,
+    pub value:      String,
+    #[serde(default, alias = "owner_id")]
+    pub created_by: Option<String>,
+    #[serde(default)]
+    pub created_at: Option<String>,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct OriginSecretsResponse(pub Vec<OriginSecret>);
+
+impl TabularText for OriginSecretsResponse {
+    fn as_tabbed(&self) -> std::result::Result<String, habitat_core::error::Error> {
+        let tw = tabw().padding(2).minwidth(5);
+        if !self.0.is_empty() {
+            let mut body = Vec::new();
+            body.push(String::from("Name\tCreated By\tCreated At"));
+            for secret in self.0.iter() {
+                body.push(format!("{}\t{}\t{}",
+                                  secret.name,
+                                  secret.created_by.as_deref().unwrap_or("-"),
+                                  secret.created_at.as_deref().unwrap_or("-")));
+            }
+            tabify(tw, &body.join("\n"))
+        } else {
+            Ok(String::from(""))
+        }
+    }
 }
 
 #[derive(Clone, Debug, Deserialize)]
@@ -0,0 +1,323 @@
+//! Static, read-only mirrors of Builder.
+//!
+//! A mirror is a directory tree either on local disk (`file:///path/to/mirror`) or in an S3
+//! bucket (`s3://bucket/prefix`) with the following layout:
+//!
+//! ```text
+//! index.json
+//! pkgs/<origin>-<name>-<version>-<release>-<target>.hart
+//! keys/<origin>-<revision>.pub
+//! ```
+//!
+//! `index.json` lists every package artifact in the mirror along with the channels it has been
+//! promoted to, and every public origin key. This allows the latest package in a channel to be
+//! resolved without a running Builder, so a mirror can be served from any static file host or
+//! used completely offline.
+use crate::{builder::write_download,
+            error::{Error,
+                    Result},
+            hab_core::{self,
+                       fs::Permissions,
+                       package::{Identifiable,
+                                 PackageArchive,
+                                 PackageIdent,
+                                 PackageTarget},
+                       ChannelIdent},
+            hab_http::ApiClient,
+            response,
+            DisplayProgress,
+            OriginKeyIdent,
+            Package};
+use reqwest::{header::CONTENT_LENGTH,
+              StatusCode};
+use std::{fs::{self,
+               File},
+          io::Cursor,
+          path::{Path,
+                 PathBuf}};
+use url::Url;
+
+const INDEX_FILE: &str = "index.json";
+
+/// The environment variable used to override the endpoint of S3 mirrors, for example when
+/// mirroring to an S3 compatible service other than AWS.
+pub const S3_ENDPOINT_ENVVAR: &str = "HAB_BLDR_S3_ENDPOINT";
+
+#[derive(Debug)]
+pub enum Mirror {
+    /// A mirror on the local filesystem rooted at the given path
+    File(PathBuf),
+    /// A mirror read over HTTP(S), such as an S3 bucket. The endpoint of the client's `ApiClient`
+    /// is the root of the mirror.
+    Remote,
+}
+
+#[derive(Deserialize)]
+struct MirrorIndex {
+    #[serde(default)]
+    packages: Vec<MirrorPackage>,
+    #[serde(default)]
+    keys:     Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct MirrorPackage {
+    ident:    PackageIdent,
+    target:   PackageTarget,
+    #[serde(default)]
+    channels: Vec<ChannelIdent>,
+    #[serde(default)]
+    checksum: String,
+    #[serde(default)]
+    deps:     Vec<PackageIdent>,
+    #[serde(default)]
+    tdeps:    Vec<PackageIdent>,
+}
+
+impl MirrorIndex {
+    /// The latest package in `channel` which satisfies `ident`
+    fn latest(self,
+              ident: &PackageIdent,
+              target: PackageTarget,
+              channel: &ChannelIdent)
+              -> Option<MirrorPackage> {
+        self.packages
+            .into_iter()
+            .filter(|p| p.target == target && p.ident.satisfies(ident) && p.in_channel(channel))
+            .max_by(|a, b| a.ident.cmp(&b.ident))
+    }
+}
+
+impl MirrorPackage {
+    /// Every package is in the unstable channel, whether or not the index says so.
+    fn in_channel(&self, channel: &ChannelIdent) -> bool {
+        *channel == ChannelIdent::unstable() || self.channels.contains(channel)
+    }
+}
+
+impl From<MirrorPackage> for Package {
+    fn from(package: MirrorPackage) -> Self {
+        Package { ident:    package.ident,
+                  checksum: package.checksum,
+                  manifest: String::new(),
+                  deps:     package.deps,
+                  tdeps:    package.tdeps,
+                  exposes:  Vec::new(),
+                  config:   String::new(), }
+    }
+}
+
+impl Mirror {
+    /// Determine if `endpoint` refers to a mirror rather than a Builder API. S3 URLs are
+    /// rewritten in place to the HTTPS endpoint of the bucket.
+    pub fn from_endpoint(endpoint: &mut Url) -> Result<Option<Self>> {
+        match endpoint.scheme() {
+            "file" => {
+                let path = endpoint.to_file_path()
+                                   .map_err(|_| Error::InvalidMirror(endpoint.to_string()))?;
+                Ok(Some(Mirror::File(path)))
+            }
+            "s3" => {
+                *endpoint = s3_http_endpoint(endpoint)?;
+                Ok(Some(Mirror::Remote))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Returns the latest package in `channel` which satisfies `ident`.
+    pub async fn show_package_metadata(&self,
+                                       api: &ApiClient,
+                                       (ident, target): (&PackageIdent, PackageTarget),
+                                       channel: &ChannelIdent)
+                                       -> Result<Package> {
+        self.index(api)
+            .await?
+            .latest(ident, target, channel)
+            .map(Package::from)
+            .ok_or_else(|| not_found(ident))
+    }
+
+    /// Returns the channels a fully qualified package is in.
+    pub async fn package_channels(&self,
+                                  api: &ApiClient,
+                                  (ident, target): (&PackageIdent, PackageTarget))
+                                  -> Result<Vec<String>> {
+        let index = self.index(api).await?;
+        let package = index.packages
+                           .into_iter()
+                           .find(|p| p.target == target && p.ident == *ident)
+                           .ok_or_else(|| not_found(ident))?;
+        let mut channels = vec![ChannelIdent::unstable().to_string()];
+        channels.extend(package.channels
+                               .iter()
+                               .filter(|c| **c != ChannelIdent::unstable())
+                               .map(ToString::to_string));
+        Ok(channels)
+    }
+
+    pub async fn show_origin_keys(&self,
+                                  api: &ApiClient,
+                                  origin: &str)
+                                  -> Result<Vec<OriginKeyIdent>> {
+        let index = self.index(api).await?;
+        Ok(index.keys
+                .iter()
+                .filter_map(|key| format!("{}.pub", key).parse::<OriginKeyIdent>().ok())
+                .filter(|key| key.origin == origin)
+                .collect())
+    }
+
+    pub async fn fetch_package(&self,
+                               api: &ApiClient,
+                               (ident, target): (&PackageIdent, PackageTarget),
+                               dst_path: &Path,
+                               permissions: Permissions,
+                               progress: Option<Box<dyn DisplayProgress>>)
+                               -> Result<PackageArchive> {
+        let file_name = ident.archive_name_with_target(target)?;
+        let path = self.download(api,
+                                 &format!("pkgs/{}", file_name),
+                                 &dst_path.join(&file_name),
+                                 permissions,
+                                 progress)
+                       .await?;
+        Ok(PackageArchive::new(path)?)
+    }
+
+    pub async fn fetch_origin_key(&self,
+                                  api: &ApiClient,
+                                  origin: &str,
+                                  revision: &str,
+                                  dst_path: &Path,
+                                  permissions: Permissions,
+                                  progress: Option<Box<dyn DisplayProgress>>)
+                                  -> Result<PathBuf> {
+        let file_name = format!("{}-{}.pub", origin, revision);
+        self.download(api,
+                      &format!("keys/{}", file_name),
+                      &dst_path.join(&file_name),
+                      permissions,
+                      progress)
+            .await
+    }
+
+    async fn index(&self, api: &ApiClient) -> Result<MirrorIndex> {
+        let index = match self {
+            Mirror::File(root) => {
+                let path = root.join(INDEX_FILE);
+                fs::read(&path).map_err(|e| Error::MirrorRead(path, e))?
+            }
+            Mirror::Remote => {
+                let resp = api.get(INDEX_FILE).send().await?;
+                response::ok_if(resp, &[StatusCode::OK]).await?
+                                                        .bytes()
+                                                        .await?
+                                                        .to_vec()
+            }
+        };
+        Ok(serde_json::from_slice(&index)?)
+    }
+
+    async fn download(&self,
+                      api: &ApiClient,
+                      path: &str,
+                      dst_file_path: &Path,
+                      permissions: Permissions,
+                      progress: Option<Box<dyn DisplayProgress>>)
+                      -> Result<PathBuf> {
+        debug!("Downloading {} from mirror to {}",
+               path,
+               dst_file_path.display());
+        match self {
+            Mirror::File(root) => {
+                let src = root.join(path);
+                let file = File::open(&src).map_err(|e| Error::MirrorRead(src.clone(), e))?;
+                let len = file.metadata()
+                              .map_err(|e| Error::MirrorRead(src, e))?
+                              .len();
+                write_download(file, Some(len), dst_file_path, permissions, progress).await
+            }
+            Mirror::Remote => {
+                let resp = api.get(path).send().await?;
+                let resp = response::ok_if(resp, &[StatusCode::OK]).await?;
+                let content_length =
+                    response::get_header(&resp, CONTENT_LENGTH).ok()
+                                                               .and_then(|l| l.parse().ok());
+                let body = Cursor::new(resp.bytes().await?);
+                write_download(body, content_length, dst_file_path, permissions, progress).await
+            }
+        }
+    }
+}
+
+/// Missing packages are reported the same way Builder reports them so callers do not need to
+/// distinguish between mirrors and Builder.
+fn not_found(ident: &PackageIdent) -> Error {
+    Error::APIError(StatusCode::NOT_FOUND,
+                    format!("{} was not found in the mirror", ident))
+}
+
+/// Convert `s3://bucket/prefix` into the HTTPS endpoint serving that prefix of the bucket.
+fn s3_http_endpoint(url: &Url) -> Result<Url> {
+    let bucket = url.host_str()
+                    .ok_or_else(|| Error::InvalidMirror(url.to_string()))?;
+    let base = match hab_core::env::var(S3_ENDPOINT_ENVVAR) {
+        Ok(endpoint) => format!("{}/{}", endpoint.trim_end_matches('/'), bucket),
+        Err(_) => format!("https://{}.s3.amazonaws.com", bucket),
+    };
+    let mut endpoint = Url::parse(&base)?;
+    let path = format!("{}{}",
+                       endpoint.path().trim_end_matches('/'),
+                       url.path().trim_end_matches('/'));
+    endpoint.set_path(&path);
+    Ok(endpoint)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn s3_urls_map_to_bucket_endpoints() {
+        let url = Url::parse("s3://my-bucket/habitat/mirror/").unwrap();
+        assert_eq!(s3_http_endpoint(&url).unwrap().as_str(),
+                   "https://my-bucket.s3.amazonaws.com/habitat/mirror");
+
+        let url = Url::parse("s3://my-bucket").unwrap();
+        assert_eq!(s3_http_endpoint(&url).unwrap().as_str(),
+                   "https://my-bucket.s3.amazonaws.com/");
+    }
+
+    #[test]
+    fn latest_package_in_channel_is_resolved_from_the_index() {
+        let index = r#"{
+            "packages": [
+              { "ident": { "origin": "core", "name": "zlib",
+                           "version": "1.2.11", "release": "20200101000000" },
+                "target": "x86_64-linux", "channels": ["stable"] },
+              { "ident": { "origin": "core", "name": "zlib",
+                           "version": "1.2.12", "release": "20200201000000" },
+                "target": "x86_64-linux" }
+            ]
+        }"#;
+        let index = || serde_json::from_str::<MirrorIndex>(index).unwrap();
+        let ident = PackageIdent::from_str("core/zlib").unwrap();
+        let target = PackageTarget::from_str("x86_64-linux").unwrap();
+
+        let stable = index().latest(&ident, target, &ChannelIdent::stable())
+                            .unwrap();
+        assert_eq!(stable.ident.to_string(), "core/zlib/1.2.11/20200101000000");
+        let unstable = index().latest(&ident, target, &ChannelIdent::unstable())
+                              .unwrap();
+        assert_eq!(unstable.ident.to_string(),
+                   "core/zlib/1.2.12/20200201000000");
+        let versioned = PackageIdent::from_str("core/zlib/1.2.11").unwrap();
+        assert!(index().latest(&versioned, target, &ChannelIdent::unstable())
+                       .is_some());
+        let target = PackageTarget::from_str("x86_64-windows").unwrap();
+        assert!(index().latest(&ident, target, &ChannelIdent::unstable())
+                       .is_none());
+    }
+}
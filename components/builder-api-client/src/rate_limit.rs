@@ -0,0 +1,190 @@
+//! Handling of rate limiting by Builder.
+//!
+//! When Builder responds with `429 Too Many Requests` the request is held back for the period
+//! given in the `Retry-After` (or `X-RateLimit-Reset`) header and then sent again. The hold applies
+//! to every request sent through the same client, so a bulk operation queues up behind the limit
+//! rather than each of its requests independently running into it.
+use crate::error::Result;
+use chrono::DateTime;
+use reqwest::{header::{HeaderMap,
+                       RETRY_AFTER},
+              RequestBuilder,
+              Response,
+              StatusCode};
+use std::{sync::{Mutex,
+                 RwLock},
+          time::{Duration,
+                 Instant,
+                 SystemTime,
+                 UNIX_EPOCH}};
+use tokio::time;
+
+/// The number of times a single request is retried after being rate limited before the `429`
+/// response is handed back to the caller.
+pub const RATE_LIMIT_RETRY_COUNT: usize = 5;
+
+/// The delay used when Builder rate limits a request without saying when to try again.
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(30);
+
+/// The longest we are willing to wait on a single rate limited request.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(300);
+
+const X_RATELIMIT_RESET: &str = "x-ratelimit-reset";
+
+/// Receives notice whenever requests are being delayed because Builder is rate limiting the
+/// client. This allows consumers to surface the delay to the user instead of appearing to hang.
+pub trait RateLimitObserver: Send + Sync {
+    fn rate_limited(&self, delay: Duration);
+}
+
+impl<F> RateLimitObserver for F where F: Fn(Duration) + Send + Sync
+{
+    fn rate_limited(&self, delay: Duration) { self(delay) }
+}
+
+lazy_static! {
+    static ref OBSERVER: RwLock<Option<Box<dyn RateLimitObserver>>> = RwLock::new(None);
+}
+
+/// Register the observer notified when any Builder client is rate limited, replacing any
+/// previously registered observer.
+pub fn set_rate_limit_observer<O>(observer: O)
+    where O: RateLimitObserver + 'static
+{
+    *OBSERVER.write().expect("Rate limit observer lock poisoned") = Some(Box::new(observer));
+}
+
+fn notify(delay: Duration) {
+    warn!("Builder is rate limiting requests, retrying in {} seconds",
+          delay.as_secs());
+    if let Some(observer) = OBSERVER.read()
+                                    .expect("Rate limit observer lock poisoned")
+                                    .as_ref()
+    {
+        observer.rate_limited(delay);
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    /// No request will be sent before this instant.
+    hold_until: Mutex<Option<Instant>>,
+}
+
+impl RateLimiter {
+    /// Send a request, first waiting out any active rate limit. If Builder rate limits the
+    /// request it is retried after the requested delay. Requests whose bodies cannot be cloned
+    /// (ie streaming uploads) are only sent once.
+    pub async fn send(&self, mut request: RequestBuilder) -> Result<Response> {
+        let mut attempt = 0;
+        loop {
+            self.wait().await;
+            let retry = request.try_clone();
+            let response = request.send().await?;
+            if response.status() != StatusCode::TOO_MANY_REQUESTS {
+                return Ok(response);
+            }
+
+            attempt += 1;
+            match retry {
+                Some(retry) if attempt <= RATE_LIMIT_RETRY_COUNT => request = retry,
+                _ => return Ok(response),
+            }
+            let delay = retry_after(response.headers(), SystemTime::now());
+            self.hold_for(delay);
+            notify(delay);
+        }
+    }
+
+    async fn wait(&self) {
+        let hold_until = *self.hold_until.lock().expect("Rate limiter lock poisoned");
+        if let Some(hold_until) = hold_until {
+            let now = Instant::now();
+            if hold_until > now {
+                time::delay_for(hold_until - now).await;
+            }
+        }
+    }
+
+    fn hold_for(&self, delay: Duration) {
+        let until = Instant::now() + delay;
+        let mut hold_until = self.hold_until.lock().expect("Rate limiter lock poisoned");
+        if hold_until.map_or(true, |current| current < until) {
+            *hold_until = Some(until);
+        }
+    }
+}
+
+/// Determine how long to wait before retrying a rate limited request. `Retry-After` may either be
+/// a number of seconds or an HTTP date. `X-RateLimit-Reset` is the Unix time at which the limit
+/// resets.
+fn retry_after(headers: &HeaderMap, now: SystemTime) -> Duration {
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+    // The delay until the given Unix time
+    let until = |secs: i64| {
+        (UNIX_EPOCH + Duration::from_secs(secs.max(0) as u64)).duration_since(now)
+                                                              .unwrap_or_default()
+    };
+
+    let delay = if let Some(value) = header(RETRY_AFTER.as_str()).map(str::trim) {
+        match value.parse::<u64>() {
+            Ok(secs) => Some(Duration::from_secs(secs)),
+            Err(_) => {
+                DateTime::parse_from_rfc2822(value).ok()
+                                                   .map(|date| until(date.timestamp()))
+            }
+        }
+    } else {
+        header(X_RATELIMIT_RESET).and_then(|v| v.trim().parse::<i64>().ok())
+                                 .map(until)
+    };
+
+    delay.unwrap_or(DEFAULT_RETRY_AFTER).min(MAX_RETRY_AFTER)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn headers(name: &'static str, value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(name, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn retry_after_seconds() {
+        let now = SystemTime::now();
+        assert_eq!(retry_after(&headers("retry-after", "42"), now),
+                   Duration::from_secs(42));
+    }
+
+    #[test]
+    fn retry_after_http_date() {
+        // Sun, 06 Nov 1994 08:49:37 GMT
+        let now = UNIX_EPOCH + Duration::from_secs(784_111_767);
+        assert_eq!(retry_after(&headers("retry-after", "Sun, 06 Nov 1994 08:49:37 GMT"),
+                               now),
+                   Duration::from_secs(10));
+    }
+
+    #[test]
+    fn retry_after_ratelimit_reset() {
+        let now = UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+        assert_eq!(retry_after(&headers("x-ratelimit-reset", "1600000015"), now),
+                   Duration::from_secs(15));
+        assert_eq!(retry_after(&headers("x-ratelimit-reset", "1500000000"), now),
+                   Duration::from_secs(0));
+    }
+
+    #[test]
+    fn retry_after_defaults_and_caps() {
+        let now = SystemTime::now();
+        assert_eq!(retry_after(&HeaderMap::new(), now), DEFAULT_RETRY_AFTER);
+        assert_eq!(retry_after(&headers("retry-after", "garbage"), now),
+                   DEFAULT_RETRY_AFTER);
+        assert_eq!(retry_after(&headers("retry-after", "86400"), now),
+                   MAX_RETRY_AFTER);
+    }
+}
@@ -9,16 +9,25 @@ use crate::{error::{Error,
                     service_config::ServiceConfig,
                     service_file::ServiceFile,
                     Rumor},
+            tls::{ClientStream,
+                  GossipTls},
             ZMQ_CONTEXT};
 use habitat_core::{crypto::keys::RingKey,
                    service::ServiceGroup};
+use std::{net::SocketAddr,
+          time::Duration};
 
-/// Holds a ZMQ Push socket, and an optional ring encryption key.
+/// Holds a ZMQ Push socket or a TLS stream, and an optional ring encryption key.
 pub struct Client {
-    socket:   zmq::Socket,
+    socket:   Socket,
     ring_key: Option<RingKey>,
 }
 
+enum Socket {
+    Zmq(zmq::Socket),
+    Tls(ClientStream),
+}
+
 impl Client {
     /// Connect this client to the address, and optionally encrypt the traffic.
     pub fn new(addr: &str, ring_key: Option<RingKey>) -> Result<Client> {
@@ -35,9 +44,21 @@ impl Client {
               .expect("Failure to set the ZMQ push socket hwm");
         socket.set_sndtimeo(500)
               .expect("Failure to set the ZMQ send timeout");
+        socket.set_ipv6(true)
+              .expect("Failure to enable IPv6 on the ZMQ push socket");
         let to_addr = format!("tcp://{}", addr);
         socket.connect(&to_addr).map_err(Error::ZmqConnectError)?;
-        Ok(Client { socket, ring_key })
+        Ok(Client { socket: Socket::Zmq(socket),
+                    ring_key })
+    }
+
+    /// Connect this client to a server that gossips over mutual TLS, and optionally encrypt the
+    /// traffic.
+    pub fn new_tls(addr: SocketAddr, ring_key: Option<RingKey>, tls: &GossipTls) -> Result<Client> {
+        let stream = tls.connect(addr, Duration::from_millis(500))
+                        .map_err(Error::TlsConnectError)?;
+        Ok(Client { socket: Socket::Tls(stream),
+                    ring_key })
     }
 
     /// Create a departure notification and send it to the server.
@@ -81,6 +102,9 @@ impl Client {
     {
         let bytes = rumor.write_to_bytes()?;
         let wire_msg = message::generate_wire(bytes, self.ring_key.as_ref())?;
-        self.socket.send(&wire_msg, 0).map_err(Error::ZmqSendError)
+        match self.socket {
+            Socket::Zmq(ref socket) => socket.send(&wire_msg, 0).map_err(Error::ZmqSendError),
+            Socket::Tls(ref mut stream) => stream.send(&wire_msg).map_err(Error::TlsSendError),
+        }
     }
 }
@@ -19,6 +19,8 @@ pub enum Error {
     HabitatCore(habitat_core::error::Error),
     IncarnationIO(PathBuf, io::Error),
     IncarnationParse(PathBuf, num::ParseIntError),
+    InvalidCertFile(PathBuf),
+    InvalidKeyFile(PathBuf),
     InvalidRumorShareLimit,
     NonExistentRumor(String, String),
     OsError(io::Error),
@@ -29,6 +31,9 @@ pub enum Error {
     SocketSetReadTimeout(io::Error),
     SocketSetWriteTimeout(io::Error),
     Timeout(String),
+    TlsConfig(rustls::TLSError),
+    TlsConnectError(io::Error),
+    TlsSendError(io::Error),
     UnknownMember(String),
     ZmqConnectError(zmq::Error),
     ZmqSendError(zmq::Error),
@@ -68,6 +73,8 @@ impl fmt::Display for Error {
                         path.display(),
                         err)
             }
+            Error::InvalidCertFile(ref path) => format!("Invalid cert file: {}", path.display()),
+            Error::InvalidKeyFile(ref path) => format!("Invalid key file: {}", path.display()),
             Error::InvalidRumorShareLimit => {
                 "Rumor share limit should be a positive integer".to_string()
             }
@@ -94,6 +101,11 @@ impl fmt::Display for Error {
                 format!("Cannot set UDP socket write timeout: {}", err)
             }
             Error::Timeout(ref msg) => format!("Timed out {}", msg),
+            Error::TlsConfig(ref err) => format!("Invalid gossip TLS configuration: {}", err),
+            Error::TlsConnectError(ref err) => format!("Cannot connect TLS stream: {}", err),
+            Error::TlsSendError(ref err) => {
+                format!("Cannot send message through TLS stream: {}", err)
+            }
             Error::UnknownMember(ref member_id) => format!("Unknown member ID: {}", member_id),
             Error::ZmqConnectError(ref err) => format!("Cannot connect ZMQ socket: {}", err),
             Error::ZmqSendError(ref err) => {
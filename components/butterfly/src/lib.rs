@@ -24,6 +24,9 @@
 //!    than one second).
 //! 1. A 'pull' thread, which takes messages from any push source and applies them locally.
 //!
+//! Gossip travels over ZeroMQ, or over mutually authenticated TLS connections when the server is
+//! given a `tls::GossipTls`.
+//!
 //! Start exploring the code base by following the thread of execution in the `server` module.
 
 #[macro_use]
@@ -45,6 +48,7 @@ pub mod protocol;
 pub mod rumor;
 pub mod server;
 pub mod swim;
+pub mod tls;
 
 pub use crate::server::Server;
 use std::cell::UnsafeCell;
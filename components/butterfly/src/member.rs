@@ -28,7 +28,9 @@ use serde::{de,
 use std::{collections::{hash_map,
                         HashMap},
           fmt,
-          net::SocketAddr,
+          net::{IpAddr,
+                Ipv6Addr,
+                SocketAddr},
           num::ParseIntError,
           ops::Add,
           result,
@@ -145,10 +147,19 @@ impl Member {
     ///
     /// This function panics if the address is un-parseable. In practice, it shouldn't be
     /// un-parseable, since its set from the inbound socket directly.
-    pub fn swim_socket_address(&self) -> SocketAddr {
-        let address_str = format!("{}:{}", self.address, self.swim_port);
-        match address_str.parse() {
-            Ok(addr) => addr,
+    pub fn swim_socket_address(&self) -> SocketAddr { self.socket_address(self.swim_port) }
+
+    /// Returns the gossip socket address of this member.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the address is un-parseable, for the same reasons as
+    /// `swim_socket_address`.
+    pub fn gossip_socket_address(&self) -> SocketAddr { self.socket_address(self.gossip_port) }
+
+    fn socket_address(&self, port: u16) -> SocketAddr {
+        match self.address.parse::<IpAddr>() {
+            Ok(ip) => SocketAddr::new(ip, port),
             Err(e) => {
                 panic!("Cannot parse member {:?} address: {}", self, e);
             }
@@ -156,6 +167,22 @@ impl Member {
     }
 }
 
+/// Returns the address to record for a member whose traffic came from `addr`.
+///
+/// A socket listening on an IPv6 address will often also accept IPv4 traffic, which then appears to
+/// come from an IPv4-mapped IPv6 address (`::ffff:a.b.c.d`). Those are recorded as plain IPv4
+/// addresses, so that members listening only on IPv4 can reach them as well.
+pub fn address_from_socket_addr(addr: SocketAddr) -> String {
+    match addr.ip() {
+        IpAddr::V6(ip) if is_ipv4_mapped(&ip) => {
+            ip.to_ipv4().expect("IPv4-mapped address").to_string()
+        }
+        ip => ip.to_string(),
+    }
+}
+
+fn is_ipv4_mapped(ip: &Ipv6Addr) -> bool { matches!(ip.segments(), [0, 0, 0, 0, 0, 0xffff, ..]) }
+
 impl Default for Member {
     fn default() -> Self {
         Member { id:          Uuid::new_v4().to_simple_ref().to_string(),
@@ -659,6 +686,31 @@ impl MemberList {
     /// * `MemberList::entries` (read)
     pub fn is_empty_mlr(&self) -> bool { self.read_entries().is_empty() }
 
+    /// Returns the number of members other than `exclude_id` which are alive.
+    ///
+    /// # Locking (see locking.md)
+    /// * `MemberList::entries` (read)
+    pub fn alive_peer_count_mlr(&self, exclude_id: &str) -> usize {
+        self.read_entries()
+            .values()
+            .filter(|member_list::Entry { member, health, .. }| {
+                member.id != exclude_id && *health == Health::Alive
+            })
+            .count()
+    }
+
+    /// Returns true if any member other than `exclude_id` is alive.
+    ///
+    /// # Locking (see locking.md)
+    /// * `MemberList::entries` (read)
+    pub fn has_alive_peer_mlr(&self, exclude_id: &str) -> bool {
+        self.read_entries()
+            .values()
+            .any(|member_list::Entry { member, health, .. }| {
+                member.id != exclude_id && *health == Health::Alive
+            })
+    }
+
     /// A randomized list of members to check.
     ///
     /// # Locking (see locking.md)
@@ -698,6 +750,36 @@ impl MemberList {
         }
     }
 
+    /// The `Alive` members listening for SWIM traffic at
+    /// `address:swim_port`.
+    ///
+    /// # Locking (see locking.md)
+    /// * `MemberList::entries` (read)
+    pub fn alive_members_at_mlr(&self, address: &str, swim_port: u16) -> Vec<Member> {
+        self.read_entries()
+            .values()
+            .filter(|member_list::Entry { member, health, .. }| {
+                *health == Health::Alive
+                && member.address == address
+                && member.swim_port == swim_port
+            })
+            .map(|member_list::Entry { member, .. }| member.clone())
+            .collect()
+    }
+
+    /// Whether any member, whatever its health, listens for SWIM
+    /// traffic at `address:swim_port`.
+    ///
+    /// # Locking (see locking.md)
+    /// * `MemberList::entries` (read)
+    pub fn contains_address_mlr(&self, address: &str, swim_port: u16) -> bool {
+        self.read_entries()
+            .values()
+            .any(|member_list::Entry { member, .. }| {
+                member.address == address && member.swim_port == swim_port
+            })
+    }
+
     /// If an owned `Member` is required, use this. If a shared reference is
     /// good enough, use `with_member`.
     ///
@@ -800,6 +882,33 @@ impl MemberList {
         expired
     }
 
+    /// Remove the members that have been `Departed` for at least
+    /// `retention`, returning their IDs.
+    ///
+    /// # Locking (see locking.md)
+    /// * `MemberList::entries` (write)
+    pub fn purge_departed_mlw(&self, retention: Duration) -> Vec<String> {
+        let now = Instant::now();
+        let mut entries = self.write_entries();
+        let purged: Vec<_> = entries.iter()
+                                    .filter(|(_, e)| {
+                                        e.health == Health::Departed
+                                        && now >= e.health_updated_at + retention
+                                    })
+                                    .map(|(id, _)| id.clone())
+                                    .collect();
+        for id in purged.iter() {
+            entries.remove(id);
+        }
+        drop(entries);
+
+        if !purged.is_empty() {
+            self.increment_update_counter();
+        }
+
+        purged
+    }
+
     /// # Locking (see locking.md)
     /// * `MemberList::entries` (read)
     pub fn contains_member_mlr(&self, member_id: &str) -> bool {
@@ -878,7 +987,8 @@ mod tests {
     }
 
     mod member {
-        use crate::member::{Incarnation,
+        use crate::member::{address_from_socket_addr,
+                            Incarnation,
                             Member};
 
         // Sets the uuid to simple, and the incarnation to the default.
@@ -888,6 +998,28 @@ mod tests {
             assert_eq!(member.id.len(), 32);
             assert_eq!(member.incarnation, Incarnation::default());
         }
+
+        #[test]
+        fn socket_addresses_bracket_ipv6_addresses() {
+            let mut member = Member::default();
+            member.address = String::from("fe80::1");
+            member.swim_port = 9638;
+            member.gossip_port = 9639;
+            assert_eq!(member.swim_socket_address(),
+                       "[fe80::1]:9638".parse().unwrap());
+            assert_eq!(member.gossip_socket_address(),
+                       "[fe80::1]:9639".parse().unwrap());
+        }
+
+        #[test]
+        fn ipv4_mapped_addresses_are_recorded_as_ipv4() {
+            assert_eq!(address_from_socket_addr("[::ffff:10.0.0.1]:9638".parse().unwrap()),
+                       "10.0.0.1");
+            assert_eq!(address_from_socket_addr("[fe80::1]:9638".parse().unwrap()),
+                       "fe80::1");
+            assert_eq!(address_from_socket_addr("10.0.0.1:9638".parse().unwrap()),
+                       "10.0.0.1");
+        }
     }
 
     mod membership {
@@ -917,6 +1049,7 @@ mod tests {
                             MemberList,
                             Membership,
                             PINGREQ_TARGETS};
+        use std::time::Duration;
 
         fn populated_member_list(size: u64) -> MemberList {
             let ml = MemberList::new();
@@ -1032,6 +1165,70 @@ mod tests {
             assert_eq!(ml.health_of_mlr(&mcheck), Some(Health::Alive));
         }
 
+        #[test]
+        fn has_alive_peer_ignores_excluded_and_unhealthy_members() {
+            let ml = MemberList::new();
+            let me = Member::default();
+            ml.insert_mlw(me.clone(), Health::Alive);
+            assert!(!ml.has_alive_peer_mlr(&me.id));
+
+            ml.insert_mlw(Member::default(), Health::Confirmed);
+            assert!(!ml.has_alive_peer_mlr(&me.id));
+
+            ml.insert_mlw(Member::default(), Health::Alive);
+            assert!(ml.has_alive_peer_mlr(&me.id));
+        }
+
+        #[test]
+        fn alive_peer_count_ignores_excluded_and_unhealthy_members() {
+            let ml = MemberList::new();
+            let me = Member::default();
+            ml.insert_mlw(me.clone(), Health::Alive);
+            assert_eq!(ml.alive_peer_count_mlr(&me.id), 0);
+
+            ml.insert_mlw(Member::default(), Health::Suspect);
+            ml.insert_mlw(Member::default(), Health::Alive);
+            ml.insert_mlw(Member::default(), Health::Alive);
+            assert_eq!(ml.alive_peer_count_mlr(&me.id), 2);
+        }
+
+        #[test]
+        fn alive_members_at_only_matches_alive_members_at_the_address() {
+            let ml = MemberList::new();
+            let mut alive = Member::default();
+            alive.address = String::from("10.0.0.1");
+            alive.swim_port = 9638;
+            let mut confirmed = alive.clone();
+            confirmed.id = Member::default().id;
+            let mut elsewhere = Member::default();
+            elsewhere.address = String::from("10.0.0.2");
+            elsewhere.swim_port = 9638;
+            ml.insert_mlw(alive.clone(), Health::Alive);
+            ml.insert_mlw(confirmed, Health::Confirmed);
+            ml.insert_mlw(elsewhere, Health::Alive);
+
+            assert_eq!(ml.alive_members_at_mlr("10.0.0.1", 9638), vec![alive]);
+            assert!(ml.contains_address_mlr("10.0.0.2", 9638));
+            assert!(!ml.contains_address_mlr("10.0.0.2", 9639));
+        }
+
+        #[test]
+        fn purge_departed_only_removes_members_departed_long_enough() {
+            let ml = MemberList::new();
+            let alive = Member::default();
+            let departed = Member::default();
+            ml.insert_mlw(alive.clone(), Health::Alive);
+            ml.insert_mlw(departed.clone(), Health::Departed);
+
+            assert!(ml.purge_departed_mlw(Duration::from_secs(3600)).is_empty());
+            assert_eq!(ml.len_mlr(), 2);
+
+            assert_eq!(ml.purge_departed_mlw(Duration::from_secs(0)),
+                       vec![departed.id.clone()]);
+            assert!(ml.contains_member_mlr(&alive.id));
+            assert!(!ml.contains_member_mlr(&departed.id));
+        }
+
         /// Tests of MemberList::insert
         mod insert {
             use crate::member::{Health,
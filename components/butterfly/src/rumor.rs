@@ -301,6 +301,21 @@ mod storage {
             let mut list = self.list.write();
             list.get_mut(key).and_then(|r| r.remove(id));
         }
+
+        /// Remove every rumor started by the member `id`, across all keys.
+        ///
+        /// # Locking (see locking.md)
+        /// * `RumorStore::list` (write)
+        pub fn remove_member_rsw(&self, id: &str) {
+            let mut list = self.list.write();
+            let mut removed = false;
+            for rumors in list.values_mut() {
+                removed |= rumors.remove(id).is_some();
+            }
+            if removed {
+                self.increment_update_counter();
+            }
+        }
     }
 
     impl<R: Rumor> RumorStore<R> {
@@ -15,8 +15,11 @@ use crate::{error::{Error,
             server::Server};
 use byteorder::{ByteOrder,
                 LittleEndian};
-use habitat_core::fs::AtomicWriter;
-use std::{collections::HashMap,
+use habitat_core::{crypto::keys::{HostBox,
+                                  HostKey},
+                   fs::AtomicWriter};
+use std::{borrow::Cow,
+          collections::HashMap,
           fs::{File,
                OpenOptions},
           io::{self,
@@ -28,7 +31,8 @@ use std::{collections::HashMap,
                Write},
           mem,
           path::{Path,
-                 PathBuf}};
+                 PathBuf},
+          str};
 
 const CURRENT_HEADER_VERSION: u8 = 2;
 const OLDEST_HEADER_VERSION: u8 = 1;
@@ -47,6 +51,10 @@ const HEADER_VERSION_1_SIZE: usize = SIZE_OF_HEADER_FIELD * HEADER_VERSION_1_NUM
 const HEADER_VERSION_2_SIZE: usize =
     (SIZE_OF_HEADER_FIELD * HEADER_VERSION_2_NUM_FIELDS) + SIZE_OF_HEADER_FIELD;
 
+/// The rumors which may carry service configuration and files applied with `hab config apply`
+/// and `hab file upload`, and so are encrypted with the Supervisor's host key when it is set.
+const SEALED_RUMORS: &[&str] = &[ServiceConfig::MESSAGE_ID, ServiceFile::MESSAGE_ID];
+
 trait WriteExt: Write {
     fn write_all_with_size(&mut self, buf: &[u8]) -> io::Result<usize> {
         self.write_all(buf)?;
@@ -65,6 +73,9 @@ impl<W: Write> WriteExt for W {}
 /// * Header Version - 1 byte
 /// * Header Body - Variable bytes - see Header
 /// * Rumors - Variable bytes
+///
+/// Each rumor is either its encoded protobuf message or, for the `SEALED_RUMORS` when a host key
+/// is set, a `HostBox` holding that message.
 #[derive(Debug)]
 struct DatFile(PathBuf);
 
@@ -73,10 +84,14 @@ pub struct DatFileReader {
     header:   Header,
     dat_file: DatFile,
     reader:   BufReader<File>,
+    host_key: Option<HostKey>,
 }
 
 #[derive(Debug)]
-pub struct DatFileWriter(DatFile);
+pub struct DatFileWriter {
+    dat_file: DatFile,
+    host_key: Option<HostKey>,
+}
 
 impl DatFileReader {
     /// # Locking (see locking.md)
@@ -121,12 +136,16 @@ impl DatFileReader {
         let header = DatFile::read_header(&data_path, &mut reader)?;
         let dat_file_reader = DatFileReader { header,
                                               dat_file: DatFile(data_path),
-                                              reader };
+                                              reader,
+                                              host_key: None };
         Ok(dat_file_reader)
     }
 
     pub fn path(&self) -> &Path { &self.dat_file.0 }
 
+    /// Use `host_key` to read the rumors which were encrypted with it.
+    pub fn set_host_key(&mut self, host_key: HostKey) { self.host_key = Some(host_key); }
+
     /// # Locking (see locking.md)
     /// * `RumorStore::list` (write)
     /// * `MemberList::entries` (write)
@@ -168,11 +187,18 @@ impl DatFileReader {
         where T: Message<newscast::Rumor>
     {
         let mut rumors = Vec::new();
+        let host_key = self.host_key.as_ref();
+        let path = &self.dat_file.0;
 
         if let Some(offset) = self.header.offset_for_rumor(T::MESSAGE_ID) {
             self.dat_file
                 .read_and_process(&mut self.reader, offset, |r| {
-                    rumors.push(T::from_bytes(&r)?);
+                    match open_rumor(host_key, r) {
+                        Ok(bytes) => rumors.push(T::from_bytes(&bytes)?),
+                        Err(err) => {
+                            warn!("Skipping a rumor persisted in {}, {}", path.display(), err)
+                        }
+                    }
                     Ok(())
                 })?;
         }
@@ -196,9 +222,16 @@ impl DatFileReader {
 }
 
 impl DatFileWriter {
-    pub fn new(data_path: PathBuf) -> Self { DatFileWriter(DatFile(data_path)) }
+    pub fn new(data_path: PathBuf) -> Self {
+        DatFileWriter { dat_file: DatFile(data_path),
+                        host_key: None, }
+    }
+
+    /// Encrypt the `SEALED_RUMORS` with `host_key`, so service configuration and files are not
+    /// kept on disk in plaintext.
+    pub fn set_host_key(&mut self, host_key: HostKey) { self.host_key = Some(host_key); }
 
-    pub fn path(&self) -> &Path { &(self.0).0 }
+    pub fn path(&self) -> &Path { &self.dat_file.0 }
 
     /// # Locking (see locking.md)
     /// * `RumorStore::list` (read)
@@ -317,7 +350,12 @@ impl DatFileWriter {
     {
         let mut total = 0;
         let mut rumor_len = [0; 8];
-        let bytes = rumor.write_to_bytes().unwrap();
+        let mut bytes = rumor.write_to_bytes().unwrap();
+        if let Some(ref host_key) = self.host_key {
+            if SEALED_RUMORS.contains(&T::MESSAGE_ID) {
+                bytes = host_key.seal(&bytes).to_string().into_bytes();
+            }
+        }
         LittleEndian::write_u64(&mut rumor_len, bytes.len() as u64);
         total +=
             writer.write_all_with_size(&rumor_len)
@@ -358,7 +396,7 @@ impl DatFile {
         Ok(header)
     }
 
-    fn read_and_process<F>(&mut self,
+    fn read_and_process<F>(&self,
                            reader: &mut BufReader<File>,
                            offset: u64,
                            mut op: F)
@@ -388,6 +426,24 @@ impl DatFile {
     }
 }
 
+/// The encoded message of a persisted rumor, decrypting it with `host_key` if it was encrypted.
+/// Protobuf messages never start with the `HostBox` version, so plaintext rumors are told apart
+/// from encrypted ones by their content.
+fn open_rumor<'a>(host_key: Option<&HostKey>, bytes: &'a [u8]) -> Result<Cow<'a, [u8]>> {
+    let content = match str::from_utf8(bytes) {
+        Ok(content) if HostBox::is_host_box(content) => content,
+        _ => return Ok(Cow::Borrowed(bytes)),
+    };
+    let message: HostBox = content.parse()?;
+    let host_key = host_key.ok_or_else(|| {
+                               habitat_core::Error::CryptoError(format!("Rumor was encrypted \
+                                                                         with host key {}, which \
+                                                                         is not available",
+                                                                        message.key()))
+                           })?;
+    Ok(Cow::Owned(host_key.open(&message)?))
+}
+
 /// Describes contents and structure of dat file.
 ///
 /// The information in this header is used to enable IO seeking operations on a binary dat
@@ -571,4 +627,37 @@ mod tests {
         assert_eq!(content.header.version, 2);
         assert_eq!(content.header.size, 64);
     }
+
+    #[test]
+    fn service_config_is_encrypted_with_host_key() {
+        let dir = tempdir().expect("temp dir created");
+        let file_path = dir.path().join("test-datfile");
+        let host_key = HostKey::new("host");
+        let service_config_store = RumorStore::default();
+        service_config_store.insert_rsw(ServiceConfig::new("member",
+                                                           "redis.default".parse().unwrap(),
+                                                           b"password = \"hunter2\"".to_vec()));
+
+        let mut writer = DatFileWriter::new(file_path.clone());
+        writer.set_host_key(host_key.clone());
+        writer.write_rsr_mlr(&MemberList::new(),
+                             &RumorStore::default(),
+                             &service_config_store,
+                             &RumorStore::default(),
+                             &RumorStore::default(),
+                             &RumorStore::default(),
+                             &RumorStore::default())
+              .unwrap();
+        let content = fs::read(&file_path).unwrap();
+        assert!(!content.windows(7).any(|w| w == b"hunter2"));
+
+        let mut reader = DatFileReader::read(file_path.clone()).unwrap();
+        assert!(reader.read_rumors::<ServiceConfig>().unwrap().is_empty());
+
+        let mut reader = DatFileReader::read(file_path).unwrap();
+        reader.set_host_key(host_key);
+        let rumors = reader.read_rumors::<ServiceConfig>().unwrap();
+        assert_eq!(rumors.len(), 1);
+        assert_eq!(rumors[0].config, b"password = \"hunter2\"".to_vec());
+    }
 }
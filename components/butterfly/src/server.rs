@@ -39,11 +39,13 @@ use crate::{error::{Error,
                     RumorStore,
                     RumorStoreProxy,
                     RumorType},
-            swim::Ack};
+            swim::Ack,
+            tls::GossipTls};
 use habitat_common::{liveliness_checker,
                      sync::Lock,
                      FeatureFlag};
-use habitat_core::crypto::keys::RingKey;
+use habitat_core::crypto::keys::{HostKey,
+                                 RingKey};
 use prometheus::{HistogramTimer,
                  HistogramVec,
                  IntGauge};
@@ -277,6 +279,7 @@ pub struct Server {
     myself:                   Arc<Myself>,
     pub member_list:          Arc<MemberList>,
     ring_key:                 Arc<Option<RingKey>>,
+    gossip_tls:               Option<GossipTls>,
     rumor_heat:               Arc<RumorHeat>,
     pub service_store:        RumorStore<Service>,
     pub service_config_store: RumorStore<ServiceConfig>,
@@ -289,6 +292,10 @@ pub struct Server {
     suitability_lookup:       Arc<dyn Suitability>,
     data_path:                Option<PathBuf>,
     dat_file:                 Option<Arc<Mutex<DatFileWriter>>>,
+    /// The key the persisted rumors are read with and, if `encrypt_dat_file` is set, the service
+    /// configuration and files among them are encrypted with.
+    host_key:                 Option<HostKey>,
+    encrypt_dat_file:         bool,
     socket:                   Option<UdpSocket>,
     departed:                 Arc<AtomicBool>,
     // These are all here for testing support
@@ -306,6 +313,7 @@ impl Clone for Server {
                  myself:               self.myself.clone(),
                  member_list:          self.member_list.clone(),
                  ring_key:             self.ring_key.clone(),
+                 gossip_tls:           self.gossip_tls.clone(),
                  rumor_heat:           self.rumor_heat.clone(),
                  service_store:        self.service_store.clone(),
                  service_config_store: self.service_config_store.clone(),
@@ -318,6 +326,8 @@ impl Clone for Server {
                  suitability_lookup:   self.suitability_lookup.clone(),
                  data_path:            self.data_path.clone(),
                  dat_file:             self.dat_file.clone(),
+                 host_key:             self.host_key.clone(),
+                 encrypt_dat_file:     self.encrypt_dat_file,
                  departed:             self.departed.clone(),
                  pause:                self.pause.clone(),
                  swim_rounds:          self.swim_rounds.clone(),
@@ -368,6 +378,7 @@ impl Server {
                             myself: Arc::new(myself),
                             member_list: Arc::new(MemberList::new()),
                             ring_key: Arc::new(ring_key),
+                            gossip_tls: None,
                             rumor_heat: Arc::default(),
                             service_store: RumorStore::default(),
                             service_config_store: RumorStore::default(),
@@ -380,6 +391,8 @@ impl Server {
                             suitability_lookup,
                             data_path: data_path.as_ref().map(|p| p.into()),
                             dat_file: None,
+                            host_key: None,
+                            encrypt_dat_file: false,
                             departed: Arc::new(AtomicBool::new(false)),
                             pause: Arc::new(AtomicBool::new(false)),
                             swim_rounds: Arc::new(AtomicIsize::new(0)),
@@ -396,6 +409,20 @@ impl Server {
         }
     }
 
+    /// Gossip with other members over mutually authenticated TLS connections rather than ZMQ.
+    /// Must be called before the server is started.
+    pub fn set_gossip_tls(&mut self, gossip_tls: GossipTls) { self.gossip_tls = Some(gossip_tls); }
+
+    pub fn gossip_tls(&self) -> Option<&GossipTls> { self.gossip_tls.as_ref() }
+
+    /// Read the rumors persisted to disk with `host_key` and, if `encrypt` is set, encrypt the
+    /// service configuration and files among them with it when persisting them again. Must be
+    /// called before the server is started.
+    pub fn set_host_key(&mut self, host_key: HostKey, encrypt: bool) {
+        self.host_key = Some(host_key);
+        self.encrypt_dat_file = encrypt;
+    }
+
     /// Every iteration of the outbound protocol (which means every member has been pinged if they
     /// are available) increments the round. If we exceed an isize in rounds, we reset to 0.
     ///
@@ -472,6 +499,9 @@ impl Server {
                                                                    &self.election_store,
                                                                    &self.update_store,
                                                                    &self.departure_store)?;
+            if let Some(ref host_key) = self.host_key {
+                reader.set_host_key(host_key.clone());
+            }
 
             match reader.read_into_rsw_mlw_rhw_msr(&self) {
                 Ok(_) => {
@@ -482,7 +512,10 @@ impl Server {
                 Err(err) => return Err(err),
             };
 
-            let writer = DatFileWriter::new(dat_path);
+            let mut writer = DatFileWriter::new(dat_path);
+            if let (Some(host_key), true) = (&self.host_key, self.encrypt_dat_file) {
+                writer.set_host_key(host_key.clone());
+            }
             self.dat_file = Some(Arc::new(Mutex::new(writer)));
 
             {
@@ -605,6 +638,27 @@ impl Server {
         }
     }
 
+    /// Start checking whether the members listening at `address:swim_port` are gone by suspecting
+    /// them. A member that is still alive refutes the suspicion, while one that is not is
+    /// confirmed and eventually departed, as with any other member that stops responding. Returns
+    /// the number of members suspected.
+    ///
+    /// # Locking (see locking.md)
+    /// * `MemberList::entries` (write)
+    /// * `RumorHeat::inner` (write)
+    pub fn suspect_members_at_mlw_rhw(&self, address: &str, swim_port: u16) -> usize {
+        let members: Vec<_> = self.member_list
+                                  .alive_members_at_mlr(address, swim_port)
+                                  .into_iter()
+                                  .filter(|member| member.id != self.member_id)
+                                  .collect();
+        let count = members.len();
+        for member in members {
+            self.insert_member_mlw_rhw(member, Health::Suspect);
+        }
+        count
+    }
+
     /// Set our member to departed, then send up to 10 out of order ack messages to other
     /// members to seed our status.
     ///
@@ -1214,6 +1268,24 @@ impl Server {
         message::unwrap_wire(payload, (*self.ring_key).as_ref())
     }
 
+    /// Forget the members that have been departed for at least `retention`, along with the
+    /// service rumors they started, so that they are neither gossiped nor persisted any longer.
+    /// Departure rumors are kept, so a purged member that was departed manually still cannot
+    /// rejoin. Returns the IDs of the purged members.
+    ///
+    /// # Locking (see locking.md)
+    /// * `RumorStore::list` (write)
+    /// * `MemberList::entries` (write)
+    /// * `RumorHeat::inner` (write)
+    pub fn purge_departed_rsw_mlw_rhw(&self, retention: Duration) -> Vec<String> {
+        let purged = self.member_list.purge_departed_mlw(retention);
+        for id in purged.iter() {
+            self.service_store.remove_member_rsw(id);
+            self.rumor_heat.lock_rhw().purge(id);
+        }
+        purged
+    }
+
     /// # Locking (see locking.md)
     /// * `RumorStore::list` (read)
     /// * `MemberList::entries` (read)
@@ -1236,6 +1308,19 @@ impl Server {
         }
     }
 
+    /// A value which changes whenever the membership or any of the rumor stores which are
+    /// persisted to the DatFile change.
+    fn update_counter(&self) -> usize {
+        [self.member_list.get_update_counter(),
+         self.service_store.get_update_counter(),
+         self.service_config_store.get_update_counter(),
+         self.service_file_store.get_update_counter(),
+         self.election_store.get_update_counter(),
+         self.update_store.get_update_counter(),
+         self.departure_store.get_update_counter()].iter()
+                                                   .fold(0, |acc, c| acc.wrapping_add(*c))
+    }
+
     #[allow(dead_code)]
     pub fn is_departed(&self) -> bool { self.departed.load(Ordering::Relaxed) }
 }
@@ -1256,27 +1341,40 @@ fn spawn_persist_thread(name: String, server: Server) -> std::io::Result<()> {
                           .map(|_| ())
 }
 
+/// Rumors and membership are written ahead: the loop checks frequently whether anything
+/// changed since the last write and persists as soon as it has, so that a Supervisor which
+/// crashes rejoins the ring with the elections and service rumors it last knew about. The data
+/// is also rewritten at least once per `HAB_PERSIST_LOOP_PERIOD_SECS`.
 fn persist_loop(server: &Server) -> ! {
     habitat_core::env_config_duration!(PersistLoopPeriod,
                                        HAB_PERSIST_LOOP_PERIOD_SECS => from_secs,
                                        Duration::from_secs(30));
+    habitat_core::env_config_duration!(PersistCheckPeriod,
+                                       HAB_PERSIST_CHECK_PERIOD_MS => from_millis,
+                                       Duration::from_secs(1));
 
     let min_loop_period: Duration = PersistLoopPeriod::configured_value().into();
+    let check_period: Duration = PersistCheckPeriod::configured_value().into();
+    let mut persisted_counter = None;
+    let mut last_persist = Instant::now();
 
     loop {
         liveliness_checker::mark_thread_alive().and_divergent();
 
-        let before_persist = Instant::now();
-        server.persist_data_rsr_mlr();
-        let time_to_persist = before_persist.elapsed();
-        trace!("persist_data took {:?}", time_to_persist);
-        match min_loop_period.checked_sub(time_to_persist) {
-            Some(time_to_wait) => thread::sleep(time_to_wait),
-            None => {
+        let counter = server.update_counter();
+        if persisted_counter != Some(counter) || last_persist.elapsed() >= min_loop_period {
+            let before_persist = Instant::now();
+            server.persist_data_rsr_mlr();
+            let time_to_persist = before_persist.elapsed();
+            trace!("persist_data took {:?}", time_to_persist);
+            if time_to_persist > check_period {
                 warn!("Persisting data took longer than expected: {:?}",
                       time_to_persist)
             }
+            persisted_counter = Some(counter);
+            last_persist = before_persist;
         }
+        thread::sleep(check_period);
     }
 }
 
@@ -1,6 +1,7 @@
 //! Periodically check membership rumors to automatically "time out"
 //! `Suspect` rumors to `Confirmed`, and `Confirmed` rumors to
-//! `Departed`.
+//! `Departed`, and to purge members that have been `Departed` for
+//! longer than the configured retention.
 
 use crate::{rumor::{RumorKey,
                     RumorType},
@@ -41,6 +42,13 @@ fn run_loop(server: &Server, timing: &Timing) -> ! {
                   .start_hot_rumor(RumorKey::new(RumorType::Member, &id, ""));
         }
 
+        if let Some(retention) = timing.departed_retention() {
+            let purged_members = server.purge_departed_rsw_mlw_rhw(retention);
+            if !purged_members.is_empty() {
+                debug!("Purged {} departed members", purged_members.len());
+            }
+        }
+
         thread::sleep(Duration::from_millis(LOOP_DELAY_MS));
     }
 }
@@ -3,7 +3,8 @@
 //! This module handles all the inbound SWIM messages.
 
 use super::AckSender;
-use crate::{member::Health,
+use crate::{member::{self,
+                     Health},
             server::{outbound,
                      Server},
             swim::{Ack,
@@ -153,7 +154,7 @@ fn process_pingreq_mlr_smr_rhw(server: &Server,
                                addr: SocketAddr,
                                mut msg: PingReq) {
     if let Some(target) = server.member_list.get_cloned_mlr(&msg.target.id) {
-        msg.from.address = addr.ip().to_string();
+        msg.from.address = member::address_from_socket_addr(addr);
         let ping_msg = Ping { membership: vec![],
                               from:       server.myself.lock_smr().to_member(),
                               forward_to: Some(msg.from.clone()), };
@@ -185,9 +186,8 @@ fn process_ack_mlw_smw_rhw(server: &Server,
     if msg.forward_to.is_some() && *server.member_id != msg.forward_to.as_ref().unwrap().id {
         let (forward_to_addr, from_addr) = {
             let forward_to = msg.forward_to.as_ref().unwrap();
-            let forward_addr_str = format!("{}:{}", forward_to.address, forward_to.swim_port);
-            let forward_to_addr = match forward_addr_str.parse() {
-                Ok(addr) => addr,
+            let forward_to_addr = match forward_to.address.parse() {
+                Ok(ip) => SocketAddr::new(ip, forward_to.swim_port),
                 Err(e) => {
                     error!("Abandoning Ack forward: cannot parse member address: {}:{}, {}",
                            forward_to.address, forward_to.swim_port, e);
@@ -199,7 +199,7 @@ fn process_ack_mlw_smw_rhw(server: &Server,
                    addr,
                    forward_to.id,
                    forward_to.address,);
-            (forward_to_addr, addr.ip().to_string())
+            (forward_to_addr, member::address_from_socket_addr(addr))
         };
         msg.from.address = from_addr;
         outbound::forward_ack(server, socket, forward_to_addr, msg);
@@ -223,7 +223,7 @@ fn process_ack_mlw_smw_rhw(server: &Server,
 fn process_ping_mlw_smw_rhw(server: &Server, socket: &UdpSocket, addr: SocketAddr, mut msg: Ping) {
     outbound::ack_mlr_smr_rhw(server, socket, &msg.from, addr, msg.forward_to);
     // Populate the member for this sender with its remote address
-    msg.from.address = addr.ip().to_string();
+    msg.from.address = member::address_from_socket_addr(addr);
     trace!("Ping from {}@{}", msg.from.id, addr);
     if msg.from.departed {
         server.insert_member_mlw_rhw(msg.from, Health::Departed);
@@ -3,7 +3,8 @@
 //! This module handles the implementation of the swim probe protocol.
 
 use super::AckReceiver;
-use crate::{member::{Health,
+use crate::{member::{self,
+                     Health,
                      Member},
             rumor::{RumorKey,
                     RumorType},
@@ -20,7 +21,9 @@ use prometheus::{HistogramTimer,
                  IntCounterVec,
                  IntGaugeVec};
 use std::{fmt,
-          net::{SocketAddr,
+          io,
+          net::{IpAddr,
+                SocketAddr,
                 UdpSocket},
           sync::mpsc,
           thread,
@@ -105,6 +108,21 @@ fn run_loop(server: &Server, socket: &UdpSocket, rx_inbound: &AckReceiver, timin
                                       });
                 }
             }
+        } else {
+            // Initial members added after we joined, such as peers
+            // added to a peer watch file, are pinged until they are
+            // part of the member list.
+            server.member_list.with_initial_members_imlr(|member| {
+                                  if !server.member_list
+                                            .contains_address_mlr(&member.address, member.swim_port)
+                                  {
+                                      ping_mlr_smr_rhw(&server,
+                                                       &socket,
+                                                       &member,
+                                                       member.swim_socket_address(),
+                                                       None);
+                                  }
+                              });
         }
 
         if server.paused() {
@@ -237,7 +255,7 @@ fn recv_ack_mlw_rhw(server: &Server,
                 // If this was forwarded to us, we want to retain the address of the member who
                 // sent the ack, not the one we received on the socket.
                 if ack.forward_to.is_none() {
-                    ack.from.address = real_addr.ip().to_string();
+                    ack.from.address = member::address_from_socket_addr(real_addr);
                 }
                 if member.id != ack.from.id {
                     if ack.from.departed {
@@ -342,7 +360,7 @@ fn pingreq(server: &Server, // TODO: eliminate this arg
             return;
         }
     };
-    match socket.send_to(&payload, addr) {
+    match send_to(socket, &payload, addr) {
         Ok(_s) => {
             let label_values = &["pingreq"];
             SWIM_MESSAGES_SENT.with_label_values(label_values).inc();
@@ -395,7 +413,7 @@ pub fn ping_mlr_smr_rhw(server: &Server,
             return;
         }
     };
-    match socket.send_to(&payload, addr) {
+    match send_to(socket, &payload, addr) {
         Ok(_s) => {
             let label_values = &["ping"];
             SWIM_MESSAGES_SENT.with_label_values(label_values).inc();
@@ -430,7 +448,7 @@ pub fn ping(server: &Server,
             return;
         }
     };
-    match socket.send_to(&payload, addr) {
+    match send_to(socket, &payload, addr) {
         Ok(_s) => {
             let label_values = &["ping"];
             SWIM_MESSAGES_SENT.with_label_values(label_values).inc();
@@ -464,7 +482,7 @@ pub fn forward_ack(server: &Server, socket: &UdpSocket, addr: SocketAddr, msg: A
             return;
         }
     };
-    match socket.send_to(&payload, addr) {
+    match send_to(socket, &payload, addr) {
         Ok(_s) => trace!("Forwarded ack to {}@{}", member_id, addr),
         Err(e) => error!("Failed ack to {}@{}: {}", member_id, addr, e),
     }
@@ -500,7 +518,7 @@ pub fn ack_mlr_smr_rhw(server: &Server,
             return;
         }
     };
-    match socket.send_to(&payload, addr) {
+    match send_to(socket, &payload, addr) {
         Ok(_s) => {
             let label_values = &["ack"];
             SWIM_MESSAGES_SENT.with_label_values(label_values).inc();
@@ -511,3 +529,15 @@ pub fn ack_mlr_smr_rhw(server: &Server,
         Err(e) => error!("Failed ack to {}@{}: {}", member_id, addr, e),
     }
 }
+
+/// Send a datagram to `addr`. A socket bound to an IPv6 address reaches IPv4 members through their
+/// IPv4-mapped IPv6 address.
+fn send_to(socket: &UdpSocket, payload: &[u8], addr: SocketAddr) -> io::Result<usize> {
+    let addr = match (socket.local_addr(), addr) {
+        (Ok(SocketAddr::V6(_)), SocketAddr::V4(v4)) => {
+            SocketAddr::new(IpAddr::V6(v4.ip().to_ipv6_mapped()), v4.port())
+        }
+        _ => addr,
+    };
+    socket.send_to(payload, addr)
+}
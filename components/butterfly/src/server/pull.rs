@@ -1,18 +1,32 @@
 //! The pull thread.
 //!
-//! This module handles pulling all the pushed rumors from every member off a ZMQ socket.
+//! This module handles pulling all the pushed rumors from every member off a ZMQ socket, or off
+//! the TLS connections members make when gossip uses mutual TLS.
 
 use crate::{rumor::{RumorEnvelope,
                     RumorKind},
             server::Server,
+            tls::{GossipTls,
+                  ServerStream},
             ZMQ_CONTEXT};
 use habitat_common::liveliness_checker;
 use habitat_core::util::ToI64;
 use prometheus::{IntCounterVec,
                  IntGaugeVec};
-use std::{thread,
+use std::{collections::HashMap,
+          net::{IpAddr,
+                TcpListener},
+          sync::{mpsc,
+                 Arc,
+                 Mutex},
+          thread,
           time::Duration};
 
+/// How long a member may leave a gossip TLS connection idle before it is closed.
+const TLS_READ_TIMEOUT: Duration = Duration::from_secs(30);
+/// How long a member may take to complete the handshake of a gossip TLS connection.
+const TLS_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
 lazy_static! {
     static ref GOSSIP_MESSAGES_RECEIVED: IntCounterVec =
         register_int_counter_vec!("hab_butterfly_gossip_messages_received_total",
@@ -33,19 +47,13 @@ pub fn spawn_thread(name: String, server: Server) -> std::io::Result<()> {
 fn run_loop(server: &Server) -> ! {
     habitat_core::env_config_int!(RecvTimeoutMillis, i32, HAB_PULL_RECV_TIMEOUT_MS, 5_000);
 
-    let socket = (**ZMQ_CONTEXT).as_mut()
-                                .socket(zmq::PULL)
-                                .expect("Failure to create the ZMQ pull socket");
-    socket.set_linger(0)
-          .expect("Failure to set the ZMQ Pull socket to not linger");
-    socket.set_tcp_keepalive(0)
-          .expect("Failure to set the ZMQ Pull socket to not use keepalive");
-    socket.set_rcvtimeo(RecvTimeoutMillis::configured_value().into())
-          .expect("Failure to set the ZMQ Pull socket receive timeout");
-    socket.bind(&format!("tcp://{}", server.gossip_addr()))
-          .expect("Failure to bind the ZMQ Pull socket to the port");
+    let timeout = RecvTimeoutMillis::configured_value().into();
+    let inbox = match server.gossip_tls() {
+        Some(tls) => Inbox::tls(server, tls.clone(), timeout),
+        None => Inbox::zmq(server, timeout),
+    };
     'recv: loop {
-        if let Ok(-1) = socket.get_rcvtimeo() {
+        if inbox.has_infinite_timeout() {
             trace!("Skipping thread liveliness checks due to infinite recv timeout");
         } else {
             liveliness_checker::mark_thread_alive().and_divergent();
@@ -56,16 +64,9 @@ fn run_loop(server: &Server) -> ! {
             continue;
         }
 
-        let msg = match socket.recv_msg(0) {
-            Ok(msg) => msg,
-            Err(e) => {
-                // We intentionally set a timeout above so that `mark_thread_alive` can be
-                // used to show this thread is alive even when there's no data to receive.
-                if e != zmq::Error::EAGAIN {
-                    error!("Error receiving message: {:?}", e);
-                }
-                continue 'recv;
-            }
+        let (msg, peer_subject) = match inbox.recv() {
+            Some(received) => received,
+            None => continue 'recv,
         };
 
         let payload = match server.unwrap_wire(&msg) {
@@ -96,6 +97,20 @@ fn run_loop(server: &Server) -> ! {
             }
         };
 
+        if let (Some(subject), Some(tls)) = (peer_subject, server.gossip_tls()) {
+            if !tls.claim(&proto.from_id, &subject) {
+                warn!("Not processing message from {} - it was sent with another member's \
+                       certificate",
+                      proto.from_id);
+                let label_values = &["impersonated", "failure", "unknown"];
+                GOSSIP_BYTES_RECEIVED.with_label_values(label_values)
+                                     .set(payload.len().to_i64());
+                GOSSIP_MESSAGES_RECEIVED.with_label_values(label_values)
+                                        .inc();
+                continue 'recv;
+            }
+        }
+
         let blocked = server.is_member_blocked_sblr(&proto.from_id);
         let blocked_label = if blocked { "true" } else { "false" };
         let label_values = &[&proto.r#type.to_string(), "success", blocked_label];
@@ -134,3 +149,213 @@ fn run_loop(server: &Server) -> ! {
         }
     }
 }
+
+/// Where pushed rumors arrive: a ZMQ PULL socket, or the TLS connections accepted from members
+/// when the server has a `GossipTls`. Rumors from TLS connections arrive along with the subject
+/// of the certificate the member presented.
+enum Inbox {
+    Zmq(zmq::Socket),
+    Tls(mpsc::Receiver<(Vec<u8>, Vec<u8>)>, Option<Duration>),
+}
+
+impl Inbox {
+    fn zmq(server: &Server, timeout: i32) -> Self {
+        let socket = (**ZMQ_CONTEXT).as_mut()
+                                    .socket(zmq::PULL)
+                                    .expect("Failure to create the ZMQ pull socket");
+        socket.set_linger(0)
+              .expect("Failure to set the ZMQ Pull socket to not linger");
+        socket.set_tcp_keepalive(0)
+              .expect("Failure to set the ZMQ Pull socket to not use keepalive");
+        socket.set_rcvtimeo(timeout)
+              .expect("Failure to set the ZMQ Pull socket receive timeout");
+        socket.set_ipv6(true)
+              .expect("Failure to enable IPv6 on the ZMQ Pull socket");
+        socket.bind(&format!("tcp://{}", server.gossip_addr()))
+              .expect("Failure to bind the ZMQ Pull socket to the port");
+        Inbox::Zmq(socket)
+    }
+
+    fn tls(server: &Server, tls: GossipTls, timeout: i32) -> Self {
+        let listener = TcpListener::bind(server.gossip_addr()).expect("Failure to bind the \
+                                                                       gossip TLS listener to \
+                                                                       the port");
+        let (tx, rx) = mpsc::channel();
+        thread::Builder::new().name(format!("pull-tls-{}", server.name))
+                              .spawn(move || accept_tls_connections(&listener, &tls, &tx))
+                              .expect("Failure to spawn the gossip TLS listener thread");
+        let timeout = if timeout < 0 {
+            None
+        } else {
+            Some(Duration::from_millis(timeout as u64))
+        };
+        Inbox::Tls(rx, timeout)
+    }
+
+    fn has_infinite_timeout(&self) -> bool {
+        match self {
+            Inbox::Zmq(socket) => socket.get_rcvtimeo() == Ok(-1),
+            Inbox::Tls(_, timeout) => timeout.is_none(),
+        }
+    }
+
+    fn recv(&self) -> Option<(Vec<u8>, Option<Vec<u8>>)> {
+        match self {
+            Inbox::Zmq(socket) => {
+                match socket.recv_bytes(0) {
+                    Ok(msg) => Some((msg, None)),
+                    Err(e) => {
+                        // We intentionally set a timeout above so that `mark_thread_alive` can be
+                        // used to show this thread is alive even when there's no data to receive.
+                        if e != zmq::Error::EAGAIN {
+                            error!("Error receiving message: {:?}", e);
+                        }
+                        None
+                    }
+                }
+            }
+            Inbox::Tls(rx, Some(timeout)) => {
+                rx.recv_timeout(*timeout)
+                  .ok()
+                  .map(|(msg, subject)| (msg, Some(subject)))
+            }
+            Inbox::Tls(rx, None) => rx.recv().ok().map(|(msg, subject)| (msg, Some(subject))),
+        }
+    }
+}
+
+/// Accept TLS connections from members, reading the rumors from each on its own thread. Once
+/// `HAB_GOSSIP_TLS_MAX_CONNECTIONS` connections are open, or
+/// `HAB_GOSSIP_TLS_MAX_CONNECTIONS_PER_IP` from the same address, new ones are closed right away,
+/// and a connection that doesn't complete its handshake within `TLS_HANDSHAKE_TIMEOUT` is closed,
+/// so that connections which never complete a handshake can't use up the threads of the
+/// Supervisor.
+fn accept_tls_connections(listener: &TcpListener,
+                          tls: &GossipTls,
+                          tx: &mpsc::Sender<(Vec<u8>, Vec<u8>)>) {
+    habitat_core::env_config_int!(MaxTlsConnections,
+                                  usize,
+                                  HAB_GOSSIP_TLS_MAX_CONNECTIONS,
+                                  256);
+    habitat_core::env_config_int!(MaxTlsConnectionsPerIp,
+                                  usize,
+                                  HAB_GOSSIP_TLS_MAX_CONNECTIONS_PER_IP,
+                                  8);
+
+    let limits = ConnectionLimits { total:  MaxTlsConnections::configured_value().into(),
+                                    per_ip: MaxTlsConnectionsPerIp::configured_value().into(), };
+    let open_connections = Arc::new(Mutex::new(OpenConnections::default()));
+    for sock in listener.incoming() {
+        let sock = match sock {
+            Ok(sock) => sock,
+            Err(e) => {
+                error!("Error accepting gossip TLS connection: {}", e);
+                continue;
+            }
+        };
+        let peer = match sock.peer_addr() {
+            Ok(peer) => peer,
+            Err(e) => {
+                error!("Error reading the address of a gossip TLS connection: {}",
+                       e);
+                continue;
+            }
+        };
+        let connection = match OpenConnection::new(&open_connections, peer.ip(), &limits) {
+            Ok(connection) => connection,
+            Err(reason) => {
+                warn!("Closing gossip TLS connection from {}: {}", peer, reason);
+                continue;
+            }
+        };
+        if let Err(e) = sock.set_read_timeout(Some(TLS_READ_TIMEOUT)) {
+            error!("Error setting the read timeout for gossip from {}: {}",
+                   peer, e);
+            continue;
+        }
+        let stream = tls.accept(sock);
+        let tx = tx.clone();
+        let spawned = thread::Builder::new().name(format!("pull-tls-{}", peer))
+                                            .spawn(move || {
+                                                let _connection = connection;
+                                                if let Err(e) = read_tls_connection(stream, &tx) {
+                                                    warn!("Closing gossip TLS connection from \
+                                                           {}: {}",
+                                                          peer, e);
+                                                }
+                                            });
+        if let Err(e) = spawned {
+            error!("Error spawning a thread for a gossip TLS connection: {}", e);
+        }
+    }
+}
+
+/// Complete the handshake of a connection accepted from a member, then pass on the rumors it
+/// sends until it is closed.
+fn read_tls_connection(mut stream: ServerStream,
+                       tx: &mpsc::Sender<(Vec<u8>, Vec<u8>)>)
+                       -> std::io::Result<()> {
+    let subject = stream.handshake(TLS_HANDSHAKE_TIMEOUT)?;
+    while let Some(msg) = stream.recv()? {
+        if tx.send((msg, subject.clone())).is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// How many gossip TLS connections may be open at once.
+struct ConnectionLimits {
+    total:  usize,
+    per_ip: usize,
+}
+
+/// The gossip TLS connections which are open, in total and from each address.
+#[derive(Default)]
+struct OpenConnections {
+    total:  usize,
+    per_ip: HashMap<IpAddr, usize>,
+}
+
+/// Counts a gossip TLS connection as open for as long as it lives.
+struct OpenConnection {
+    open_connections: Arc<Mutex<OpenConnections>>,
+    ip:               IpAddr,
+}
+
+impl OpenConnection {
+    /// Count a new connection from `ip` as open, unless that would exceed `limits`.
+    fn new(open_connections: &Arc<Mutex<OpenConnections>>,
+           ip: IpAddr,
+           limits: &ConnectionLimits)
+           -> Result<Self, String> {
+        let mut open = open_connections.lock()
+                                       .expect("Open connections lock poisoned");
+        if open.total >= limits.total {
+            return Err(format!("{} connections are open already", limits.total));
+        }
+        let from_ip = open.per_ip.get(&ip).copied().unwrap_or(0);
+        if from_ip >= limits.per_ip {
+            return Err(format!("{} connections from {} are open already", limits.per_ip, ip));
+        }
+        open.per_ip.insert(ip, from_ip + 1);
+        open.total += 1;
+        Ok(OpenConnection { open_connections: Arc::clone(open_connections),
+                            ip })
+    }
+}
+
+impl Drop for OpenConnection {
+    fn drop(&mut self) {
+        let mut open = self.open_connections
+                           .lock()
+                           .expect("Open connections lock poisoned");
+        open.total -= 1;
+        if let Some(from_ip) = open.per_ip.get_mut(&self.ip) {
+            *from_ip -= 1;
+            if *from_ip == 0 {
+                open.per_ip.remove(&self.ip);
+            }
+        }
+    }
+}
@@ -3,7 +3,9 @@
 //! This is the thread for distributing rumors to members. It distributes to `FANOUT` members, no
 //! more often than `Timing::GOSSIP_PERIOD_DEFAULT_MS`.
 
-use crate::{member::{Member,
+use crate::{error::{Error,
+                    Result},
+            member::{Member,
                      Membership},
             rumor::{RumorEnvelope,
                     RumorKey,
@@ -11,6 +13,7 @@ use crate::{member::{Member,
                     RumorType},
             server::{timing::Timing,
                      Server},
+            tls::ClientStream,
             ZMQ_CONTEXT};
 use habitat_common::liveliness_checker;
 use habitat_core::util::ToI64;
@@ -146,22 +149,11 @@ fn run_loop(server: &Server, timing: &Timing) -> ! {
 // expected reward.
 #[allow(clippy::cognitive_complexity)]
 fn send_rumors_rsr_mlr_rhw(server: &Server, member: &Member, rumors: &[RumorKey]) {
-    let socket = (**ZMQ_CONTEXT).as_mut()
-                                .socket(zmq::PUSH)
-                                .expect("Failure to create the ZMQ push socket");
-    socket.set_linger(1000)
-          .expect("Failure to set the ZMQ push socket to not linger");
-    socket.set_tcp_keepalive(0)
-          .expect("Failure to set the ZMQ push socket to not use keepalive");
-    socket.set_immediate(true)
-          .expect("Failure to set the ZMQ push socket to immediate");
-    socket.set_sndhwm(1000)
-          .expect("Failure to set the ZMQ push socket hwm");
-    socket.set_sndtimeo(500)
-          .expect("Failure to set the ZMQ send timeout");
-    let to_addr = format!("{}:{}", member.address, member.gossip_port);
-    match socket.connect(&format!("tcp://{}", to_addr)) {
-        Ok(()) => debug!("Connected push socket to {:?}", member),
+    let mut socket = match PushSocket::connect(server, member) {
+        Ok(socket) => {
+            debug!("Connected push socket to {:?}", member);
+            socket
+        }
         Err(e) => {
             error!("Cannot connect push socket to {:?}: {:?}", member, e);
             let label_values = &["socket_connect", "failure"];
@@ -169,7 +161,7 @@ fn send_rumors_rsr_mlr_rhw(server: &Server, member: &Member, rumors: &[RumorKey]
             GOSSIP_BYTES_SENT.with_label_values(label_values).set(0);
             return;
         }
-    }
+    };
     'rumorlist: for rumor_key in rumors.iter() {
         let rumor_as_bytes = match rumor_key.kind {
             RumorType::Member => {
@@ -303,7 +295,7 @@ fn send_rumors_rsr_mlr_rhw(server: &Server, member: &Member, rumors: &[RumorKey]
                 continue 'rumorlist;
             }
         };
-        match socket.send(&payload, 0) {
+        match socket.send(&payload) {
             Ok(()) => {
                 GOSSIP_MESSAGES_SENT.with_label_values(&[&rumor_key.kind.to_string(), "success"])
                                     .inc();
@@ -312,8 +304,10 @@ fn send_rumors_rsr_mlr_rhw(server: &Server, member: &Member, rumors: &[RumorKey]
                 debug!("Sent rumor {:?} to {:?}", rumor_key, member);
             }
             Err(e) => {
-                warn!("Could not send rumor to {:?} @ {:?}; ZMQ said: {:?}",
-                      member.id, to_addr, e)
+                warn!("Could not send rumor to {:?} @ {:?}: {}",
+                      member.id,
+                      member.gossip_socket_address(),
+                      e)
             }
         }
     }
@@ -323,6 +317,48 @@ fn send_rumors_rsr_mlr_rhw(server: &Server, member: &Member, rumors: &[RumorKey]
           .cool_rumors(&member.id, &rumors);
 }
 
+/// How rumors are sent to a member: over ZMQ, or over TLS when the server has a `GossipTls`.
+enum PushSocket {
+    Zmq(zmq::Socket),
+    Tls(ClientStream),
+}
+
+impl PushSocket {
+    fn connect(server: &Server, member: &Member) -> Result<PushSocket> {
+        let addr = member.gossip_socket_address();
+        if let Some(tls) = server.gossip_tls() {
+            return tls.connect_to_member(addr, &member.id, Duration::from_millis(500))
+                      .map(PushSocket::Tls)
+                      .map_err(Error::TlsConnectError);
+        }
+        let socket = (**ZMQ_CONTEXT).as_mut()
+                                    .socket(zmq::PUSH)
+                                    .expect("Failure to create the ZMQ push socket");
+        socket.set_linger(1000)
+              .expect("Failure to set the ZMQ push socket to not linger");
+        socket.set_tcp_keepalive(0)
+              .expect("Failure to set the ZMQ push socket to not use keepalive");
+        socket.set_immediate(true)
+              .expect("Failure to set the ZMQ push socket to immediate");
+        socket.set_sndhwm(1000)
+              .expect("Failure to set the ZMQ push socket hwm");
+        socket.set_sndtimeo(500)
+              .expect("Failure to set the ZMQ send timeout");
+        socket.set_ipv6(true)
+              .expect("Failure to enable IPv6 on the ZMQ push socket");
+        socket.connect(&format!("tcp://{}", addr))
+              .map_err(Error::ZmqConnectError)?;
+        Ok(PushSocket::Zmq(socket))
+    }
+
+    fn send(&mut self, payload: &[u8]) -> Result<()> {
+        match self {
+            PushSocket::Zmq(socket) => socket.send(payload, 0).map_err(Error::ZmqSendError),
+            PushSocket::Tls(stream) => stream.send(payload).map_err(Error::TlsSendError),
+        }
+    }
+}
+
 /// Given a rumorkey, creates a protobuf rumor for sharing.
 ///
 /// # Locking (see locking.md)
@@ -25,6 +25,9 @@ pub struct Timing {
 
     gossip_interval:     Duration,
     swim_probe_interval: Duration,
+
+    /// How long departed members are kept before they are purged, if at all.
+    departed_retention: Option<Duration>,
 }
 
 impl Default for Timing {
@@ -37,7 +40,8 @@ impl Default for Timing {
                  confirm:             Duration::from_millis(confirm_ms),
                  departure:           Duration::from_millis(DEPARTURE_TIMEOUT_DEFAULT_MS),
                  gossip_interval:     Duration::from_millis(GOSSIP_INTERVAL_DEFAULT_MS),
-                 swim_probe_interval: Duration::from_millis(swim_interval_ms), }
+                 swim_probe_interval: Duration::from_millis(swim_interval_ms),
+                 departed_retention:  None, }
     }
 }
 
@@ -56,6 +60,24 @@ impl Timing {
     /// consider it departed.
     pub fn departure(&self) -> Duration { self.departure }
 
+    /// How long after a member departed before we forget about it
+    /// entirely, if ever.
+    pub fn departed_retention(&self) -> Option<Duration> { self.departed_retention }
+
+    /// Override how long confirmed members linger before they are
+    /// considered departed.
+    pub fn with_departure(mut self, departure: Duration) -> Self {
+        self.departure = departure;
+        self
+    }
+
+    /// Purge departed members once they have been departed for
+    /// `retention`. By default, departed members are kept forever.
+    pub fn with_departed_retention(mut self, retention: Option<Duration>) -> Self {
+        self.departed_retention = retention;
+        self
+    }
+
     /// If the amount of time since `starting_point` is less than a
     /// gossip interval, sleep for the remainder of that gossip interval.
     pub fn sleep_for_remaining_gossip_interval(&self, starting_point: Instant) {
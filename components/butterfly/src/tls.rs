@@ -0,0 +1,511 @@
+//! Mutual TLS for the gossip channel.
+//!
+//! When a `GossipTls` is given to the server, rumors are pushed to members over TLS connections
+//! instead of ZMQ. Both ends of every connection present a certificate, and each accepts the other
+//! only if its certificate was issued by the configured CA and is not listed in the revoked
+//! certificates file. Members are trusted because of who issued their certificate, not the name on
+//! it, so certificates do not need to name the address a Supervisor listens on.
+//!
+//! Each Supervisor's certificate must have a subject of its own, though. The first time a member
+//! ID is seen on a connection, it is bound to the subject of the certificate presented there, and
+//! from then on that member ID is only accepted with that subject, and that subject only with that
+//! member ID. This keeps a member from pushing rumors as, or taking pushed rumors meant for,
+//! another member, while a certificate renewed with the same subject is still accepted.
+//! Connections made with the Supervisor's own certificate are exempt, as they come from its own
+//! clients, which send rumors on behalf of other members, such as departures.
+//!
+//! Each rumor is sent as a frame: its length as a big-endian `u32`, followed by its bytes.
+
+use crate::error::{Error,
+                   Result};
+use byteorder::{BigEndian,
+                ReadBytesExt,
+                WriteBytesExt};
+use rustls::{internal::pemfile,
+             AllowAnyAuthenticatedClient,
+             Certificate,
+             ClientCertVerified,
+             ClientCertVerifier,
+             ClientConfig,
+             ClientSession,
+             DistinguishedNames,
+             OwnedTrustAnchor,
+             PrivateKey,
+             RootCertStore,
+             ServerCertVerified,
+             ServerCertVerifier,
+             ServerConfig,
+             ServerSession,
+             Session,
+             StreamOwned,
+             TLSError};
+use std::{collections::HashMap,
+          fmt,
+          fs::{self,
+               File},
+          io::{self,
+               BufReader,
+               Read,
+               Write},
+          net::{SocketAddr,
+                TcpStream},
+          path::{Path,
+                 PathBuf},
+          result,
+          sync::{Arc,
+                 Mutex},
+          time::{Duration,
+                 Instant,
+                 SystemTime}};
+
+/// The name clients ask for when connecting. It is never checked.
+const SERVER_NAME: &str = "supervisor.habitat";
+
+/// Frames larger than this are refused. The largest rumors, service configurations and files,
+/// are limited to 64KiB.
+const MAX_FRAME_SIZE: u32 = 1024 * 1024;
+
+/// The DER tags of the elements read to find the subject of a certificate.
+const DER_INTEGER: u8 = 0x02;
+const DER_SEQUENCE: u8 = 0x30;
+const DER_VERSION: u8 = 0xa0;
+
+static SIGNATURE_ALGORITHMS: &[&webpki::SignatureAlgorithm] =
+    &[&webpki::ECDSA_P256_SHA256,
+      &webpki::ECDSA_P256_SHA384,
+      &webpki::ECDSA_P384_SHA256,
+      &webpki::ECDSA_P384_SHA384,
+      &webpki::ED25519,
+      &webpki::RSA_PSS_2048_8192_SHA256_LEGACY_KEY,
+      &webpki::RSA_PSS_2048_8192_SHA384_LEGACY_KEY,
+      &webpki::RSA_PSS_2048_8192_SHA512_LEGACY_KEY,
+      &webpki::RSA_PKCS1_2048_8192_SHA256,
+      &webpki::RSA_PKCS1_2048_8192_SHA384,
+      &webpki::RSA_PKCS1_2048_8192_SHA512,
+      &webpki::RSA_PKCS1_3072_8192_SHA384];
+
+/// Where to find this Supervisor's certificate and key, and the certificates it trusts.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TlsConfig {
+    /// PEM-format certificates, the first of which certifies `key_path`.
+    pub cert_path:          PathBuf,
+    /// An RSA or PKCS8-encoded private key in PEM format.
+    pub key_path:           PathBuf,
+    /// PEM-format certificates of the CAs that issue Supervisor certificates.
+    pub ca_cert_path:       PathBuf,
+    /// PEM-format certificates of Supervisors that are no longer trusted. The file is read again
+    /// whenever it changes.
+    pub revoked_certs_path: Option<PathBuf>,
+}
+
+/// The TLS configuration used for both ends of gossip connections.
+#[derive(Clone)]
+pub struct GossipTls {
+    client_config: Arc<ClientConfig>,
+    server_config: Arc<ServerConfig>,
+    own_subject:   Arc<Vec<u8>>,
+    identities:    Arc<Mutex<PeerIdentities>>,
+}
+
+impl fmt::Debug for GossipTls {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GossipTls").finish()
+    }
+}
+
+impl GossipTls {
+    pub fn new(config: &TlsConfig) -> Result<Self> {
+        let roots = load_roots(&config.ca_cert_path)?;
+        let cert_chain = load_certs(&config.cert_path)?;
+        let key = load_key(&config.key_path)?;
+        let own_subject = match subject_of(&cert_chain[0].0) {
+            Some(subject) => subject.to_vec(),
+            None => return Err(Error::InvalidCertFile(config.cert_path.clone())),
+        };
+        let revoked = Arc::new(RevokedCerts::new(config.revoked_certs_path.clone())?);
+
+        let client_verifier =
+            RevocationCheckingClientVerifier { inner:
+                                                   AllowAnyAuthenticatedClient::new(roots.clone()),
+                                               revoked: Arc::clone(&revoked), };
+        let mut server_config = ServerConfig::new(Arc::new(client_verifier));
+        server_config.set_single_cert(cert_chain.clone(), key.clone())
+                     .map_err(Error::TlsConfig)?;
+
+        let mut client_config = ClientConfig::new();
+        client_config.root_store = roots;
+        client_config.set_single_client_cert(cert_chain, key);
+        client_config.dangerous()
+                     .set_certificate_verifier(Arc::new(CaServerVerifier { revoked }));
+
+        Ok(GossipTls { client_config: Arc::new(client_config),
+                       server_config: Arc::new(server_config),
+                       own_subject:   Arc::new(own_subject),
+                       identities:    Arc::default(), })
+    }
+
+    /// Connect to the gossip listener at `addr`. The handshake completes before this returns, so
+    /// a member whose certificate is not trusted is reported as a connection error.
+    pub fn connect(&self, addr: SocketAddr, timeout: Duration) -> io::Result<ClientStream> {
+        let sock = TcpStream::connect_timeout(&addr, timeout)?;
+        sock.set_read_timeout(Some(timeout))?;
+        sock.set_write_timeout(Some(timeout))?;
+        let name = webpki::DNSNameRef::try_from_ascii_str(SERVER_NAME).expect("valid DNS name");
+        let mut stream = StreamOwned::new(ClientSession::new(&self.client_config, name), sock);
+        while stream.sess.is_handshaking() {
+            stream.sess.complete_io(&mut stream.sock)?;
+        }
+        Ok(ClientStream(stream))
+    }
+
+    /// Connect to the gossip listener of the member `member_id` at `addr`, like `connect`, but
+    /// also report a member whose certificate belongs to another member as a connection error.
+    pub fn connect_to_member(&self,
+                             addr: SocketAddr,
+                             member_id: &str,
+                             timeout: Duration)
+                             -> io::Result<ClientStream> {
+        let stream = self.connect(addr, timeout)?;
+        let subject = peer_subject(&(stream.0).sess)?;
+        if !self.claim(member_id, &subject) {
+            return Err(io::Error::new(io::ErrorKind::PermissionDenied,
+                                      format!("certificate does not belong to \
+                                               member {}",
+                                              member_id)));
+        }
+        Ok(stream)
+    }
+
+    /// Whether `member_id` may gossip with a certificate for `subject`, binding the two to each
+    /// other if neither has been seen before.
+    pub fn claim(&self, member_id: &str, subject: &[u8]) -> bool {
+        if subject == self.own_subject.as_slice() {
+            return true;
+        }
+        self.identities
+            .lock()
+            .expect("Peer identities lock poisoned")
+            .claim(member_id, subject)
+    }
+
+    /// Start the server side of a connection accepted on the gossip listener.
+    pub fn accept(&self, sock: TcpStream) -> ServerStream {
+        ServerStream(StreamOwned::new(ServerSession::new(&self.server_config), sock))
+    }
+}
+
+/// A connection to a member's gossip listener.
+pub struct ClientStream(StreamOwned<ClientSession, TcpStream>);
+
+impl ClientStream {
+    pub fn send(&mut self, payload: &[u8]) -> io::Result<()> { write_frame(&mut self.0, payload) }
+}
+
+impl Drop for ClientStream {
+    fn drop(&mut self) {
+        self.0.sess.send_close_notify();
+        let _ = self.0.flush();
+    }
+}
+
+/// A connection accepted from a member.
+pub struct ServerStream(StreamOwned<ServerSession, TcpStream>);
+
+impl ServerStream {
+    /// Complete the handshake within `timeout`, returning the subject of the member's
+    /// certificate.
+    pub fn handshake(&mut self, timeout: Duration) -> io::Result<Vec<u8>> {
+        let read_timeout = self.0.sock.read_timeout()?;
+        let deadline = Instant::now() + timeout;
+        while self.0.sess.is_handshaking() {
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(io::Error::new(io::ErrorKind::TimedOut,
+                                          "handshake did not complete in time"));
+            }
+            self.0.sock.set_read_timeout(Some(deadline - now))?;
+            self.0.sess.complete_io(&mut self.0.sock)?;
+        }
+        self.0.sock.set_read_timeout(read_timeout)?;
+        peer_subject(&self.0.sess)
+    }
+
+    /// Receive the next frame, or `None` once the member has closed the connection.
+    pub fn recv(&mut self) -> io::Result<Option<Vec<u8>>> {
+        match read_frame(&mut self.0) {
+            Ok(frame) => Ok(Some(frame)),
+            Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// The subject of the certificate the other end of a connection presented.
+fn peer_subject<S: Session>(sess: &S) -> io::Result<Vec<u8>> {
+    sess.get_peer_certificates()
+        .and_then(|certs| {
+            certs.first()
+                 .and_then(|cert| subject_of(&cert.0))
+                 .map(<[u8]>::to_vec)
+        })
+        .ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData,
+                           "peer certificate has no readable subject")
+        })
+}
+
+/// The DER-encoded subject of a DER-encoded X.509 certificate.
+fn subject_of(cert: &[u8]) -> Option<&[u8]> {
+    let (cert, _) = der_element(cert, DER_SEQUENCE)?;
+    let (mut tbs_certificate, _) = der_element(cert, DER_SEQUENCE)?;
+    // The version is optional, and explicitly tagged.
+    if tbs_certificate.first() == Some(&DER_VERSION) {
+        tbs_certificate = der_element(tbs_certificate, DER_VERSION)?.1;
+    }
+    let (_serial_number, rest) = der_element(tbs_certificate, DER_INTEGER)?;
+    let (_signature, rest) = der_element(rest, DER_SEQUENCE)?;
+    let (_issuer, rest) = der_element(rest, DER_SEQUENCE)?;
+    let (_validity, rest) = der_element(rest, DER_SEQUENCE)?;
+    der_element(rest, DER_SEQUENCE).map(|(subject, _)| subject)
+}
+
+/// Split the DER element tagged `tag` off the front of `input`, returning its contents and what
+/// follows it.
+fn der_element(input: &[u8], tag: u8) -> Option<(&[u8], &[u8])> {
+    if *input.first()? != tag {
+        return None;
+    }
+    let first_len_byte = *input.get(1)? as usize;
+    let (len, header_len) = if first_len_byte < 0x80 {
+        (first_len_byte, 2)
+    } else {
+        let len_bytes = first_len_byte & 0x7f;
+        if len_bytes == 0 || len_bytes > 4 {
+            return None;
+        }
+        let len = input.get(2..2 + len_bytes)?
+                       .iter()
+                       .fold(0, |len, byte| (len << 8) | *byte as usize);
+        (len, 2 + len_bytes)
+    };
+    let end = header_len.checked_add(len)?;
+    Some((input.get(header_len..end)?, input.get(end..)?))
+}
+
+/// The member ID each certificate subject gossips as, and the other way around.
+#[derive(Default)]
+struct PeerIdentities {
+    by_member:  HashMap<String, Vec<u8>>,
+    by_subject: HashMap<Vec<u8>, String>,
+}
+
+impl PeerIdentities {
+    fn claim(&mut self, member_id: &str, subject: &[u8]) -> bool {
+        match (self.by_member.get(member_id), self.by_subject.get(subject)) {
+            (Some(known_subject), _) if known_subject.as_slice() != subject => false,
+            (_, Some(known_member_id)) if known_member_id != member_id => false,
+            (Some(_), Some(_)) => true,
+            _ => {
+                self.by_member
+                    .insert(member_id.to_string(), subject.to_vec());
+                self.by_subject
+                    .insert(subject.to_vec(), member_id.to_string());
+                true
+            }
+        }
+    }
+}
+
+fn write_frame<W: Write>(writer: &mut W, payload: &[u8]) -> io::Result<()> {
+    writer.write_u32::<BigEndian>(payload.len() as u32)?;
+    writer.write_all(payload)?;
+    writer.flush()
+}
+
+fn read_frame<R: Read>(reader: &mut R) -> io::Result<Vec<u8>> {
+    let len = reader.read_u32::<BigEndian>()?;
+    if len > MAX_FRAME_SIZE {
+        return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                  format!("frame of {} bytes is too large",
+                                          len)));
+    }
+    let mut frame = vec![0; len as usize];
+    reader.read_exact(&mut frame)?;
+    Ok(frame)
+}
+
+// Note that we must explicitly map these errors because rustls returns () as the error from
+// the pemfile functions.
+fn load_certs(path: &Path) -> Result<Vec<Certificate>> {
+    let file = &mut BufReader::new(File::open(path)?);
+    pemfile::certs(file).and_then(|c| if c.is_empty() { Err(()) } else { Ok(c) })
+                        .map_err(|_| Error::InvalidCertFile(path.to_path_buf()))
+}
+
+fn load_key(path: &Path) -> Result<PrivateKey> {
+    let file = &mut BufReader::new(File::open(path)?);
+    let mut keys =
+        pemfile::pkcs8_private_keys(file).map_err(|_| Error::InvalidKeyFile(path.to_path_buf()))?;
+    if keys.is_empty() {
+        let file = &mut BufReader::new(File::open(path)?);
+        keys =
+            pemfile::rsa_private_keys(file).map_err(|_| Error::InvalidKeyFile(path.to_path_buf()))?;
+    }
+    keys.pop()
+        .ok_or_else(|| Error::InvalidKeyFile(path.to_path_buf()))
+}
+
+fn load_roots(path: &Path) -> Result<RootCertStore> {
+    let mut roots = RootCertStore::empty();
+    let file = &mut BufReader::new(File::open(path)?);
+    match roots.add_pem_file(file) {
+        Ok((added, _)) if added > 0 => Ok(roots),
+        _ => Err(Error::InvalidCertFile(path.to_path_buf())),
+    }
+}
+
+/// The certificates of Supervisors that are no longer trusted, kept up to date with the file
+/// they are read from.
+struct RevokedCerts {
+    path:  Option<PathBuf>,
+    certs: Mutex<(Option<SystemTime>, Vec<Certificate>)>,
+}
+
+impl RevokedCerts {
+    fn new(path: Option<PathBuf>) -> Result<Self> {
+        let certs = match path {
+            Some(ref path) => (modified(path), load_certs(path)?),
+            None => (None, Vec::new()),
+        };
+        Ok(RevokedCerts { path,
+                          certs: Mutex::new(certs) })
+    }
+
+    fn check(&self, presented_certs: &[Certificate]) -> result::Result<(), TLSError> {
+        let mut certs = self.certs
+                            .lock()
+                            .expect("Revoked certificates lock poisoned");
+        if let Some(ref path) = self.path {
+            let current = modified(path);
+            if current != certs.0 {
+                match load_certs(path) {
+                    Ok(reloaded) => *certs = (current, reloaded),
+                    Err(e) => error!("Keeping the previous revoked certificates: {}", e),
+                }
+            }
+        }
+        match presented_certs.first() {
+            Some(cert) if certs.1.contains(cert) => {
+                Err(TLSError::General(String::from("Certificate has been revoked")))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Accepts clients with a certificate issued by one of the CAs that has not been revoked.
+struct RevocationCheckingClientVerifier {
+    inner:   Arc<dyn ClientCertVerifier>,
+    revoked: Arc<RevokedCerts>,
+}
+
+impl ClientCertVerifier for RevocationCheckingClientVerifier {
+    fn client_auth_root_subjects(&self) -> DistinguishedNames {
+        self.inner.client_auth_root_subjects()
+    }
+
+    fn verify_client_cert(&self,
+                          presented_certs: &[Certificate])
+                          -> result::Result<ClientCertVerified, TLSError> {
+        self.revoked.check(presented_certs)?;
+        self.inner.verify_client_cert(presented_certs)
+    }
+}
+
+/// Accepts servers with a certificate issued by one of the CAs that has not been revoked,
+/// whatever name the certificate is for.
+struct CaServerVerifier {
+    revoked: Arc<RevokedCerts>,
+}
+
+impl ServerCertVerifier for CaServerVerifier {
+    fn verify_server_cert(&self,
+                          roots: &RootCertStore,
+                          presented_certs: &[Certificate],
+                          _dns_name: webpki::DNSNameRef<'_>,
+                          _ocsp_response: &[u8])
+                          -> result::Result<ServerCertVerified, TLSError> {
+        self.revoked.check(presented_certs)?;
+        let (end_entity, intermediates) = presented_certs.split_first()
+                                                         .ok_or(TLSError::NoCertificatesPresented)?;
+        let cert = webpki::EndEntityCert::from(&end_entity.0).map_err(TLSError::WebPKIError)?;
+        let intermediates: Vec<&[u8]> = intermediates.iter().map(|c| c.0.as_slice()).collect();
+        let anchors: Vec<webpki::TrustAnchor<'_>> = roots.roots
+                                                         .iter()
+                                                         .map(OwnedTrustAnchor::to_trust_anchor)
+                                                         .collect();
+        let now = webpki::Time::try_from(SystemTime::now()).map_err(|_| {
+                                                               TLSError::FailedToGetCurrentTime
+                                                           })?;
+        cert.verify_is_valid_tls_server_cert(SIGNATURE_ALGORITHMS,
+                                             &webpki::TLSServerTrustAnchors(&anchors),
+                                             &intermediates,
+                                             now)
+            .map_err(TLSError::WebPKIError)?;
+        Ok(ServerCertVerified::assertion())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_round_trip() {
+        let mut buf = Vec::new();
+        write_frame(&mut buf, b"rumor").unwrap();
+        write_frame(&mut buf, b"").unwrap();
+        let mut reader = buf.as_slice();
+        assert_eq!(read_frame(&mut reader).unwrap(), b"rumor");
+        assert_eq!(read_frame(&mut reader).unwrap(), b"");
+        assert_eq!(read_frame(&mut reader).unwrap_err().kind(),
+                   io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn subject_is_read_from_certificate() {
+        // A certificate reduced to the fields in front of the subject: version 3, serial number
+        // 1, an empty signature algorithm, issuer and validity, then the subject.
+        let tbs_certificate = [0xa0, 0x03, 0x02, 0x01, 0x02, 0x02, 0x01, 0x01, 0x30, 0x00, 0x30,
+                               0x02, 0x31, 0x00, 0x30, 0x00, 0x30, 0x02, 0x31, 0x01];
+        let mut cert = vec![0x30,
+                            tbs_certificate.len() as u8 + 2,
+                            0x30,
+                            tbs_certificate.len() as u8];
+        cert.extend_from_slice(&tbs_certificate);
+        assert_eq!(subject_of(&cert), Some(&[0x31, 0x01][..]));
+        assert_eq!(subject_of(&cert[..cert.len() - 1]), None);
+    }
+
+    #[test]
+    fn member_ids_are_bound_to_one_subject() {
+        let mut identities = PeerIdentities::default();
+        assert!(identities.claim("alpha", b"CN=alpha"));
+        assert!(identities.claim("alpha", b"CN=alpha"));
+        assert!(!identities.claim("alpha", b"CN=beta"));
+        assert!(!identities.claim("beta", b"CN=alpha"));
+        assert!(identities.claim("beta", b"CN=beta"));
+    }
+
+    #[test]
+    fn oversized_frames_are_refused() {
+        let mut buf = Vec::new();
+        buf.write_u32::<BigEndian>(MAX_FRAME_SIZE + 1).unwrap();
+        assert_eq!(read_frame(&mut buf.as_slice()).unwrap_err().kind(),
+                   io::ErrorKind::InvalidData);
+    }
+}
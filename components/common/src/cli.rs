@@ -18,6 +18,12 @@ use std::{ffi::OsStr,
 pub const RING_ENVVAR: &str = "HAB_RING";
 pub const RING_KEY_ENVVAR: &str = "HAB_RING_KEY";
 pub const CTL_SECRET_ENVVAR: &str = "HAB_CTL_SECRET";
+/// Connect to the CtlGateway over TLS, trusting only the certificate with this SHA-256 fingerprint.
+pub const CTL_TLS_FINGERPRINT_ENVVAR: &str = "HAB_CTL_TLS_FINGERPRINT";
+/// Connect to the CtlGateway over TLS, trusting certificates for this name issued by public CAs.
+pub const CTL_TLS_SERVER_NAME_ENVVAR: &str = "HAB_CTL_TLS_SERVER_NAME";
+/// Give up on a request to the CtlGateway after this many seconds, cancelling it.
+pub const CTL_TIMEOUT_ENVVAR: &str = "HAB_CTL_TIMEOUT";
 
 pub const LISTEN_HTTP_DEFAULT_PORT: u16 = 9631;
 pub const LISTEN_HTTP_DEFAULT_IP: &str = "0.0.0.0";
@@ -5,7 +5,8 @@
 use habitat_core::{fs::{am_i_root,
                         FS_ROOT_PATH},
                    origin::Origin};
-use std::{fs,
+use std::{collections::BTreeMap,
+          fs,
           io,
           path::{Path,
                  PathBuf}};
@@ -42,6 +43,18 @@ pub struct CliConfig {
     pub origin:     Option<Origin>,
     pub ctl_secret: Option<String>,
     pub bldr_url:   Option<String>,
+    /// The Supervisors named with `hab remote add`, by name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub remotes:    BTreeMap<String, Remote>,
+}
+
+/// A remote Supervisor, which `--remote-sup` accepts by name in place of its address.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Remote {
+    /// The address of the Supervisor's Control Gateway
+    pub address:     String,
+    /// The file holding the secret of the Supervisor's Control Gateway, if it isn't the default
+    pub secret_file: Option<PathBuf>,
 }
 
 impl CliConfig {
@@ -29,18 +29,25 @@ use crate::{api_client::{self,
                                  APIError},
                          API_RETRY_COUNT,
                          API_RETRY_DELAY},
+            command::package::relocate::{self,
+                                         RelocateMode},
             error::{Error,
                     Result},
-            templating::hooks::{InstallHook,
-                                PackageMaintenanceHookExt},
+            templating::{hooks::{InstallHook,
+                                 PackageMaintenanceHookExt},
+                         sandbox::InstallHookPolicy},
             ui::{Status,
                  UIWriter}};
+#[cfg(unix)]
+use habitat_core::package::store::Store;
 use habitat_core::{self,
                    crypto::{artifact,
                             keys::{Key,
                                    KeyCache,
                                    NamedRevision,
-                                   PublicOriginSigningKey}},
+                                   PublicOriginSigningKey},
+                            revocation::{self,
+                                         SignedRevocationList}},
                    fs::{cache_key_path,
                         pkg_install_path,
                         AtomicWriter,
@@ -51,10 +58,12 @@ use habitat_core::{self,
                              PackageArchive,
                              PackageIdent,
                              PackageInstall,
-                             PackageTarget},
+                             PackageTarget,
+                             VersionReq},
                    ChannelIdent};
 use reqwest::StatusCode;
-use std::{convert::TryFrom,
+use std::{collections::HashSet,
+          convert::TryFrom,
           fs::{self,
                File},
           io::{self,
@@ -65,6 +74,7 @@ use std::{convert::TryFrom,
           pin::Pin,
           result::Result as StdResult,
           str::FromStr,
+          sync::Mutex,
           time::Duration};
 
 pub const RETRIES: usize = 5;
@@ -308,7 +318,8 @@ pub async fn start<U>(ui: &mut U,
                       token: Option<&str>,
                       install_mode: &InstallMode,
                       local_package_usage: &LocalPackageUsage,
-                      install_hook_mode: InstallHookMode)
+                      install_hook_mode: InstallHookMode,
+                      relocate_mode: RelocateMode)
                       -> Result<PackageInstall>
     where U: UIWriter
 {
@@ -324,7 +335,9 @@ pub async fn start<U>(ui: &mut U,
                              fs_root_path,
                              artifact_cache_path,
                              key_cache,
-                             install_hook_mode };
+                             install_hook_mode,
+                             relocate_mode,
+                             revocations_refreshed: Mutex::new(HashSet::new()) };
 
     match *install_source {
         InstallSource::Ident(ref ident, target) => {
@@ -350,7 +363,8 @@ pub fn type_erased_start<'a, U>(
     token: Option<&'a str>,
     install_mode: &'a InstallMode,
     local_package_usage: &'a LocalPackageUsage,
-    install_hook_mode: InstallHookMode)
+    install_hook_mode: InstallHookMode,
+    relocate_mode: RelocateMode)
     -> Pin<Box<dyn std::future::Future<Output = Result<PackageInstall>> + Send + 'a>>
     where U: UIWriter + Send + Sync
 {
@@ -365,7 +379,8 @@ pub fn type_erased_start<'a, U>(
                    token,
                    install_mode,
                    local_package_usage,
-                   install_hook_mode))
+                   install_hook_mode,
+                   relocate_mode))
 }
 
 pub async fn check_install_hooks<T, P>(ui: &mut T,
@@ -375,6 +390,7 @@ pub async fn check_install_hooks<T, P>(ui: &mut T,
     where T: UIWriter,
           P: AsRef<Path>
 {
+    let policy = InstallHookPolicy::load(fs_root_path.as_ref())?;
     let mut dependencies = package.tdeps()?;
     // tdeps are ordered high to low so we reverse the list to ensure lower level deps
     // are installed first
@@ -383,23 +399,32 @@ pub async fn check_install_hooks<T, P>(ui: &mut T,
         run_install_hook_unless_already_successful(
             ui,
             &PackageInstall::load(&dependency, Some(fs_root_path.as_ref()))?,
+            &policy,
         ).await?;
     }
 
-    run_install_hook_unless_already_successful(ui, &package).await
+    run_install_hook_unless_already_successful(ui, &package, &policy).await
 }
 
 async fn run_install_hook_unless_already_successful<T>(ui: &mut T,
-                                                       package: &PackageInstall)
+                                                       package: &PackageInstall,
+                                                       policy: &InstallHookPolicy)
                                                        -> Result<()>
     where T: UIWriter
 {
     match read_install_hook_status(package.installed_path.join(InstallHook::STATUS_FILE))? {
         Some(0) => Ok(()),
-        _ => InstallHook::find_run_and_error_for_status(ui, package).await,
+        _ => {
+            let sandbox = policy.sandbox(package.ident());
+            InstallHook::find_run_and_error_for_status(ui, package, Some(&sandbox)).await
+        }
     }
 }
 
+fn import_revocations(json: &str, key_cache: &KeyCache) -> Result<Vec<String>> {
+    Ok(revocation::import(&SignedRevocationList::from_json(json)?, key_cache)?)
+}
+
 fn read_install_hook_status(path: PathBuf) -> Result<Option<i32>> {
     match File::open(&path) {
         Ok(file) => {
@@ -419,15 +444,18 @@ fn read_install_hook_status(path: PathBuf) -> Result<Option<i32>> {
 }
 
 struct InstallTask<'a> {
-    install_mode:        &'a InstallMode,
-    local_package_usage: &'a LocalPackageUsage,
-    api_client:          BuilderAPIClient,
-    channel:             &'a ChannelIdent,
-    fs_root_path:        &'a Path,
+    install_mode:          &'a InstallMode,
+    local_package_usage:   &'a LocalPackageUsage,
+    api_client:            BuilderAPIClient,
+    channel:               &'a ChannelIdent,
+    fs_root_path:          &'a Path,
     /// The path to the local artifact cache (e.g., /hab/cache/artifacts)
-    artifact_cache_path: &'a Path,
-    key_cache:           KeyCache,
-    install_hook_mode:   InstallHookMode,
+    artifact_cache_path:   &'a Path,
+    key_cache:             KeyCache,
+    install_hook_mode:     InstallHookMode,
+    relocate_mode:         RelocateMode,
+    /// The origins whose revocation lists were already refreshed from Builder
+    revocations_refreshed: Mutex<HashSet<String>>,
 }
 
 impl<'a> InstallTask<'a> {
@@ -695,6 +723,18 @@ impl<'a> InstallTask<'a> {
                 let temp_dir = temp_package_directory(real_install_path)?;
                 let temp_install_path = &pkg_install_path(ident, Some(temp_dir.path()));
                 artifact.unpack(Some(temp_dir.path()))?;
+                if self.relocate_mode == RelocateMode::Relocate
+                   && cfg!(unix)
+                   && self.fs_root_path != Path::new("/")
+                {
+                    relocate::relocate(ui, temp_install_path, self.fs_root_path)?;
+                }
+                #[cfg(unix)]
+                {
+                    if let Some(store) = Store::open(self.fs_root_path) {
+                        store.dedup(temp_install_path)?;
+                    }
+                }
 
                 if let Err(e) = fs::rename(temp_install_path, real_install_path) {
                     // The rename might fail if the real_install_path
@@ -759,7 +799,10 @@ impl<'a> InstallTask<'a> {
     fn latest_cached_ident(&self, ident: &PackageIdent) -> Result<FullyQualifiedPackageIdent> {
         let filename_glob = {
             let mut ident = ident.clone();
-            if ident.version.is_none() {
+            if ident.version
+                    .as_deref()
+                    .map_or(true, VersionReq::is_version_req)
+            {
                 ident.version = Some(String::from("?*"));
             }
             if ident.release.is_none() {
@@ -784,7 +827,7 @@ impl<'a> InstallTask<'a> {
                 continue;
             }
             let artifact_ident = artifact_ident.unwrap();
-            if artifact_ident.origin == ident.origin && artifact_ident.name == ident.name {
+            if artifact_ident.satisfies(ident) {
                 if latest.is_empty() {
                     latest.push((artifact_ident, artifact));
                 } else if artifact_ident > latest[0].0 {
@@ -882,6 +925,8 @@ impl<'a> InstallTask<'a> {
                                   self.key_cache.as_ref(),
                                   ui.progress())
                 .await?;
+            self.key_cache
+                .pin_public_signing_key(named_revision, false)?;
 
             let key = self.key_cache.public_signing_key(&named_revision)?;
             ui.status(Status::Cached,
@@ -890,6 +935,46 @@ impl<'a> InstallTask<'a> {
         }
     }
 
+    /// Import the revocation list of `origin` from Builder, once per install. This is best
+    /// effort: when Builder has no list for the origin, or an invalid one, the revocations
+    /// already in the key cache still apply.
+    async fn refresh_revocations<T>(&self, ui: &mut T, origin: &str) -> Result<()>
+        where T: UIWriter
+    {
+        if self.is_offline() {
+            return Ok(());
+        }
+        let first_refresh = self.revocations_refreshed
+                                .lock()
+                                .expect("revocations_refreshed lock poisoned")
+                                .insert(origin.to_string());
+        if !first_refresh {
+            return Ok(());
+        }
+        let json = match self.api_client
+                             .get_origin_revocations(&origin.parse()?)
+                             .await
+        {
+            Ok(json) => json,
+            Err(e) => {
+                debug!("No revocation list for {} from Builder: {}", origin, e);
+                return Ok(());
+            }
+        };
+        match import_revocations(&json, &self.key_cache) {
+            Ok(revoked) => {
+                for key in revoked {
+                    ui.warn(format!("The origin key {} has been revoked", key))?;
+                }
+            }
+            Err(e) => {
+                ui.warn(format!("Ignoring the revocation list of the {} origin: {}",
+                                origin, e))?
+            }
+        }
+        Ok(())
+    }
+
     /// Copies the artifact to the local artifact cache directory
     // TODO (CM): Oh, we could just pass in the LocalArchive
     fn store_artifact_in_cache(&self,
@@ -965,6 +1050,8 @@ impl<'a> InstallTask<'a> {
             self.fetch_origin_key(ui, &named_revision, token).await?;
         };
 
+        self.refresh_revocations(ui, named_revision.name()).await?;
+
         artifact::verify(&artifact.path, &self.key_cache)?;
 
         debug!("Verified {} signed by {}", ident, named_revision);
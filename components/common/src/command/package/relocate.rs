@@ -0,0 +1,261 @@
+//! Rewrites an unpacked package so that it runs from beneath a filesystem root other than `/`.
+//!
+//! Packages are built to live in `/hab/pkgs` and that path is baked into what they contain:
+//! scripts name interpreters in `/hab/pkgs` on their `#!` line, ELF executables name their
+//! dynamic loader in `/hab/pkgs` and ELF objects find their libraries through a `RUNPATH` in
+//! `/hab/pkgs`. Relocating a package points each of these beneath the filesystem root the package
+//! is installed under instead.
+//!
+//! ELF files are rewritten with `patchelf`, which must be found on the `PATH`. Without it, only
+//! scripts are relocated.
+
+use crate::{error::{Error,
+                    Result},
+            ui::UIWriter};
+use habitat_core::fs::find_command;
+use std::{fs::{self,
+               File},
+          io::Read,
+          path::{Path,
+                 PathBuf},
+          process::Command};
+
+/// The prefix of the absolute paths that packages are built with
+const HAB_PREFIX: &str = "/hab/";
+const PATCHELF_CMD: &str = "patchelf";
+const ELF_MAGIC: &[u8] = b"\x7fELF";
+/// The longest `#!` line that Linux reads; anything longer is cut short when executed
+const MAX_SHEBANG_LEN: usize = 255;
+
+/// Governs whether installed packages are rewritten to run from beneath their filesystem root
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RelocateMode {
+    /// Point the interpreters and `RUNPATH`s of the package's files beneath the filesystem root
+    /// when it is not `/`
+    Relocate,
+    /// Leave the package's files as they were built, for roots that will later be used as `/`,
+    /// such as those of containers and tarballs
+    Preserve,
+}
+
+impl Default for RelocateMode {
+    fn default() -> Self { RelocateMode::Preserve }
+}
+
+/// Rewrites the files of the package unpacked at `path` to run from beneath `fs_root_path`.
+pub fn relocate<T>(ui: &mut T, path: &Path, fs_root_path: &Path) -> Result<()>
+    where T: UIWriter
+{
+    let prefix = rooted_prefix(fs_root_path);
+    let patchelf = find_command(PATCHELF_CMD);
+    let mut skipped_elf = false;
+    for file in files(path)? {
+        let mut head = Vec::with_capacity(ELF_MAGIC.len());
+        File::open(&file)?.take(ELF_MAGIC.len() as u64)
+                          .read_to_end(&mut head)?;
+        if head.starts_with(b"#!") {
+            relocate_script(&file, &prefix)?;
+        } else if head == ELF_MAGIC {
+            match patchelf {
+                Some(ref patchelf) => relocate_elf(patchelf, &file, &prefix)?,
+                None => skipped_elf = true,
+            }
+        }
+    }
+    if skipped_elf {
+        ui.warn(format!("{} was not found on the PATH, so the binaries in {} still expect to \
+                         run from {}",
+                        PATCHELF_CMD,
+                        path.display(),
+                        HAB_PREFIX))?;
+    }
+    Ok(())
+}
+
+/// The prefix that replaces `HAB_PREFIX` beneath `fs_root_path`
+fn rooted_prefix(fs_root_path: &Path) -> String {
+    format!("{}{}",
+            fs_root_path.to_string_lossy().trim_end_matches('/'),
+            HAB_PREFIX)
+}
+
+/// Returns `path` beneath `prefix` if it is one of the paths packages are built with.
+fn relocated_path(path: &str, prefix: &str) -> Option<String> {
+    if path.starts_with(HAB_PREFIX) {
+        Some(format!("{}{}", prefix, &path[HAB_PREFIX.len()..]))
+    } else {
+        None
+    }
+}
+
+/// Every regular file beneath `path`. Symlinks are left alone, as whatever they point to in the
+/// package is visited on its own.
+fn files(path: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut dirs = vec![path.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                dirs.push(entry.path());
+            } else if file_type.is_file() {
+                files.push(entry.path());
+            }
+        }
+    }
+    Ok(files)
+}
+
+fn relocate_script(path: &Path, prefix: &str) -> Result<()> {
+    let contents = fs::read(path)?;
+    let line_end = contents.iter()
+                           .position(|b| *b == b'\n')
+                           .unwrap_or_else(|| contents.len());
+    let line = match std::str::from_utf8(&contents[2..line_end]) {
+        Ok(line) => line,
+        Err(_) => return Ok(()),
+    };
+    let interpreter_start = line.len() - line.trim_start().len();
+    let interpreter_end = line[interpreter_start..].find(char::is_whitespace)
+                                                   .map_or(line.len(), |i| interpreter_start + i);
+    let interpreter = match relocated_path(&line[interpreter_start..interpreter_end], prefix) {
+        Some(interpreter) => interpreter,
+        None => return Ok(()),
+    };
+    let line = format!("#!{}{}", interpreter, &line[interpreter_end..]);
+    if line.len() > MAX_SHEBANG_LEN {
+        debug!("Not relocating {}, its interpreter line would be too long",
+               path.display());
+        return Ok(());
+    }
+    let mut relocated = line.into_bytes();
+    relocated.extend_from_slice(&contents[line_end..]);
+    writable(path, || Ok(fs::write(path, &relocated)?))
+}
+
+fn relocate_elf(patchelf: &Path, path: &Path, prefix: &str) -> Result<()> {
+    // Only executables have an interpreter, so failing to print one is expected
+    if let Some(interpreter) = patchelf_output(patchelf, "--print-interpreter", path) {
+        if let Some(interpreter) = relocated_path(&interpreter, prefix) {
+            writable(path, || {
+                patchelf_run(patchelf, "--set-interpreter", &interpreter, path)
+            })?;
+        }
+    }
+    if let Some(runpath) = patchelf_output(patchelf, "--print-rpath", path) {
+        let relocated =
+            runpath.split(':')
+                   .map(|entry| relocated_path(entry, prefix).unwrap_or_else(|| entry.to_string()))
+                   .collect::<Vec<_>>()
+                   .join(":");
+        if relocated != runpath {
+            writable(path, || {
+                patchelf_run(patchelf, "--set-rpath", &relocated, path)
+            })?;
+        }
+    }
+    Ok(())
+}
+
+fn patchelf_output(patchelf: &Path, flag: &str, path: &Path) -> Option<String> {
+    let output = Command::new(patchelf).arg(flag).arg(path).output().ok()?;
+    if output.status.success() {
+        Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    } else {
+        None
+    }
+}
+
+fn patchelf_run(patchelf: &Path, flag: &str, value: &str, path: &Path) -> Result<()> {
+    let output = Command::new(patchelf).arg(flag)
+                                       .arg(value)
+                                       .arg(path)
+                                       .output()?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(Error::RelocateFailed(path.to_path_buf(),
+                                  String::from_utf8_lossy(&output.stderr).trim().to_string()))
+    }
+}
+
+/// Runs `f` with `path` made writable, restoring its permissions afterwards. Packages commonly
+/// ship read-only files.
+fn writable<F>(path: &Path, f: F) -> Result<()>
+    where F: FnOnce() -> Result<()>
+{
+    let permissions = fs::metadata(path)?.permissions();
+    if !permissions.readonly() {
+        return f();
+    }
+    let mut writable = permissions.clone();
+    writable.set_readonly(false);
+    fs::set_permissions(path, writable)?;
+    let result = f();
+    fs::set_permissions(path, permissions)?;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn paths_beneath_hab_are_relocated() {
+        let prefix = rooted_prefix(Path::new("/srv/tenant-a/"));
+        assert_eq!(prefix, "/srv/tenant-a/hab/");
+        assert_eq!(relocated_path("/hab/pkgs/core/bash/5.0/20200101000000/bin/bash", &prefix),
+                   Some("/srv/tenant-a/hab/pkgs/core/bash/5.0/20200101000000/bin/bash".to_string()));
+        assert_eq!(relocated_path("/usr/bin/env", &prefix), None);
+        assert_eq!(relocated_path("$ORIGIN/../lib", &prefix), None);
+    }
+
+    #[test]
+    fn script_interpreter_is_relocated() {
+        let dir = TempDir::new().unwrap();
+        let script = dir.path().join("script");
+        fs::write(&script,
+                  "#! /hab/pkgs/core/bash/5.0/20200101000000/bin/bash -e\necho hi\n").unwrap();
+        relocate_script(&script, "/srv/tenant-a/hab/").unwrap();
+        assert_eq!(fs::read_to_string(&script).unwrap(),
+                   "#!/srv/tenant-a/hab/pkgs/core/bash/5.0/20200101000000/bin/bash -e\necho hi\n");
+    }
+
+    #[test]
+    fn script_with_other_interpreter_is_untouched() {
+        let dir = TempDir::new().unwrap();
+        let script = dir.path().join("script");
+        fs::write(&script, "#!/usr/bin/env bash\necho hi\n").unwrap();
+        relocate_script(&script, "/srv/tenant-a/hab/").unwrap();
+        assert_eq!(fs::read_to_string(&script).unwrap(),
+                   "#!/usr/bin/env bash\necho hi\n");
+    }
+
+    #[test]
+    fn script_is_not_relocated_past_the_interpreter_line_limit() {
+        let dir = TempDir::new().unwrap();
+        let script = dir.path().join("script");
+        let contents = "#!/hab/pkgs/core/bash/5.0/20200101000000/bin/bash\n";
+        fs::write(&script, contents).unwrap();
+        let prefix = format!("/{}/hab/", "x".repeat(MAX_SHEBANG_LEN));
+        relocate_script(&script, &prefix).unwrap();
+        assert_eq!(fs::read_to_string(&script).unwrap(), contents);
+    }
+
+    #[test]
+    fn read_only_script_is_relocated() {
+        let dir = TempDir::new().unwrap();
+        let script = dir.path().join("script");
+        fs::write(&script,
+                  "#!/hab/pkgs/core/bash/5.0/20200101000000/bin/bash\n").unwrap();
+        let mut permissions = fs::metadata(&script).unwrap().permissions();
+        permissions.set_readonly(true);
+        fs::set_permissions(&script, permissions).unwrap();
+        relocate_script(&script, "/srv/tenant-a/hab/").unwrap();
+        assert!(fs::metadata(&script).unwrap().permissions().readonly());
+        assert!(fs::read_to_string(&script).unwrap()
+                                           .starts_with("#!/srv/tenant-a/hab/pkgs/"));
+    }
+}
@@ -14,7 +14,8 @@ use std::{env,
           path::PathBuf,
           result,
           str,
-          string};
+          string,
+          time::Duration};
 
 pub const DEFAULT_ERROR_EXIT_CODE: i32 = 1;
 
@@ -77,7 +78,12 @@ pub enum Error {
         hook:          &'static str,
         error:         CommandExecutionError,
     },
+    /// Occurs when a hook is killed for running longer than its timeout.
+    HookTimedOut(&'static str, Duration),
+    /// Occurs when the install hook policy file cannot be parsed
+    InstallHookPolicy(PathBuf, String),
     InvalidEventStreamToken(String),
+    InvalidOutputFormat(String),
     /// Occurs when making lower level IO calls.
     IO(io::Error),
     /// Errors when joining paths :)
@@ -95,6 +101,8 @@ pub enum Error {
     PermissionFailed(String),
     /// When an error occurs serializing rendering context
     RenderContextSerialization(serde_json::Error),
+    /// Occurs when `patchelf` fails to rewrite a file of a package being relocated.
+    RelocateFailed(PathBuf, String),
     RemoteSupResolutionError(String, io::Error),
     RootRequired,
     StatusFileCorrupt(PathBuf),
@@ -173,9 +181,21 @@ impl fmt::Display for Error {
                                 ref error, } => {
                 format!("{} {} hook failed: {}", package_ident, hook, error)
             }
+            Error::HookTimedOut(hook, timeout) => {
+                format!("{} hook did not finish within {}s and was killed",
+                        hook,
+                        timeout.as_secs())
+            }
+            Error::InstallHookPolicy(ref path, ref e) => {
+                format!("Invalid install hook policy {}: {}", path.display(), e)
+            }
             Error::InvalidEventStreamToken(ref s) => {
                 format!("Invalid event stream token provided: '{}'", s)
             }
+            Error::InvalidOutputFormat(ref format) => {
+                format!("Unknown output format '{}', expected one of: text, json",
+                        format)
+            }
             Error::IO(ref err) => format!("{}", err),
             Error::JoinPathsError(ref err) => format!("{}", err),
             Error::NamedPipeTimeoutOnStart(ref group, ref hook, ref err) => {
@@ -204,6 +224,9 @@ impl fmt::Display for Error {
             Error::RenderContextSerialization(ref e) => {
                 format!("Unable to serialize rendering context, {}", e)
             }
+            Error::RelocateFailed(ref path, ref err) => {
+                format!("Unable to relocate {}: {}", path.display(), err)
+            }
             Error::RemoteSupResolutionError(ref sup_addr, ref err) => {
                 format!("Failed to resolve remote supervisor '{}': {}",
                         sup_addr, err,)
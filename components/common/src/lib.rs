@@ -111,6 +111,16 @@ lazy_static! {
 }
 
 impl FeatureFlag {
+    /// The environment variables which enable the flags in `self`, sorted by name.
+    pub fn env_vars(self) -> Vec<&'static str> {
+        let mut env_vars = ENV_VARS.iter()
+                                   .filter(|(feature, _)| self.contains(**feature))
+                                   .map(|(_, env_var)| *env_var)
+                                   .collect::<Vec<_>>();
+        env_vars.sort_unstable();
+        env_vars
+    }
+
     /// If the environment variable for a flag is set to _anything_ but
     /// the empty string, it is activated.
     pub fn from_env<T>(ui: &mut T) -> Self
@@ -19,13 +19,17 @@
 //! with ANSI color codes, but does honor the verbose flag.
 
 use crate::PROGRAM_NAME;
+use chrono::{SecondsFormat,
+             Utc};
 use serde::{ser::SerializeMap,
             Serialize,
             Serializer};
-use std::{fmt,
+use std::{convert::TryFrom,
+          fmt,
           io::{self,
                Write},
           result,
+          str::FromStr,
           sync::{atomic::{AtomicBool,
                           Ordering},
                  Mutex}};
@@ -255,6 +259,175 @@ impl OutputFormat {
     }
 }
 
+/// How the Launcher renders the lines of output it captures from services.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ServiceOutputFormat {
+    /// Prefixed with the service group, like any other Supervisor output.
+    Text,
+    /// One `ServiceOutputLine` JSON object per line.
+    Json,
+}
+
+impl ServiceOutputFormat {
+    pub const VARIANTS: &'static [&'static str] = &["text", "json"];
+}
+
+impl Default for ServiceOutputFormat {
+    fn default() -> Self { ServiceOutputFormat::Text }
+}
+
+impl FromStr for ServiceOutputFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> result::Result<Self, Self::Err> {
+        match value {
+            "text" => Ok(ServiceOutputFormat::Text),
+            "json" => Ok(ServiceOutputFormat::Json),
+            _ => Err(format!("Invalid service output format '{}'", value)),
+        }
+    }
+}
+
+impl fmt::Display for ServiceOutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ServiceOutputFormat::Text => write!(f, "text"),
+            ServiceOutputFormat::Json => write!(f, "json"),
+        }
+    }
+}
+
+/// One of the two output streams captured from a service.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ServiceStream {
+    Stdout,
+    Stderr,
+}
+
+impl ServiceStream {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ServiceStream::Stdout => "stdout",
+            ServiceStream::Stderr => "stderr",
+        }
+    }
+}
+
+impl fmt::Display for ServiceStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { write!(f, "{}", self.as_str()) }
+}
+
+bitflags::bitflags! {
+    /// Where the lines captured from one of a service's output streams are sent.
+    ///
+    /// Parsed from a comma-separated list of target names, such as `console,file`, or `none` to
+    /// discard the stream entirely.
+    #[derive(Deserialize, Serialize)]
+    #[serde(try_from = "String", into = "String")]
+    pub struct OutputTargets: u32 {
+        /// The Launcher's own stdout or stderr, as appropriate.
+        const CONSOLE = 0b0001;
+        /// The service's rotated log file.
+        const FILE    = 0b0010;
+        /// The syslog receiver or journald the service's output is forwarded to, if any.
+        const FORWARD = 0b0100;
+        /// The Supervisor's event stream, as `ServiceOutputEvent`s.
+        const EVENTS  = 0b1000;
+    }
+}
+
+impl OutputTargets {
+    const NAMES: &'static [(&'static str, OutputTargets)] = &[("console", OutputTargets::CONSOLE),
+                                                              ("file", OutputTargets::FILE),
+                                                              ("forward", OutputTargets::FORWARD),
+                                                              ("events", OutputTargets::EVENTS)];
+}
+
+impl Default for OutputTargets {
+    fn default() -> Self { OutputTargets::CONSOLE | OutputTargets::FILE | OutputTargets::FORWARD }
+}
+
+impl FromStr for OutputTargets {
+    type Err = String;
+
+    fn from_str(value: &str) -> result::Result<Self, Self::Err> {
+        if value.trim() == "none" {
+            return Ok(OutputTargets::empty());
+        }
+        let mut targets = OutputTargets::empty();
+        for name in value.split(',').map(str::trim) {
+            match OutputTargets::NAMES.iter().find(|(n, _)| *n == name) {
+                Some((_, target)) => targets |= *target,
+                None => {
+                    return Err(format!("Invalid output target '{}'; expected 'none' or a \
+                                        comma-separated list of console, file, forward \
+                                        and events",
+                                       name))
+                }
+            }
+        }
+        Ok(targets)
+    }
+}
+
+impl fmt::Display for OutputTargets {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "none");
+        }
+        let names = OutputTargets::NAMES.iter()
+                                        .filter(|(_, target)| self.contains(*target))
+                                        .map(|(name, _)| *name)
+                                        .collect::<Vec<_>>();
+        write!(f, "{}", names.join(","))
+    }
+}
+
+impl TryFrom<String> for OutputTargets {
+    type Error = String;
+
+    fn try_from(value: String) -> result::Result<Self, Self::Error> { value.parse() }
+}
+
+impl From<OutputTargets> for String {
+    fn from(value: OutputTargets) -> Self { value.to_string() }
+}
+
+/// A line of output captured from a service, carrying enough context for log aggregation
+/// pipelines to route it without parsing the line itself.
+#[derive(Serialize)]
+pub struct ServiceOutputLine<'a> {
+    pub timestamp: String,
+    pub ident:     &'a str,
+    pub group:     &'a str,
+    /// Either "stdout" or "stderr"
+    pub stream:    &'a str,
+    pub pid:       u32,
+    pub content:   &'a str,
+}
+
+impl<'a> ServiceOutputLine<'a> {
+    pub fn new(ident: &'a str,
+               group: &'a str,
+               stream: &'a str,
+               pid: u32,
+               content: &'a str)
+               -> Self {
+        ServiceOutputLine { timestamp: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+                            ident,
+                            group,
+                            stream,
+                            pid,
+                            content }
+    }
+
+    pub fn to_json(&self) -> String {
+        // unwrap is safe, as we control the inputs
+        serde_json::to_string(self).unwrap()
+    }
+}
+
 #[derive(Clone, Copy, PartialEq)]
 pub enum OutputVerbosity {
     Normal,
@@ -341,7 +514,9 @@ mod tests {
 
     use super::{OutputContext,
                 OutputFormat,
+                OutputTargets,
                 OutputVerbosity,
+                ServiceOutputLine,
                 StructuredOutput};
     use termcolor::{BufferWriter,
                     Color,
@@ -479,4 +654,45 @@ mod tests {
                    }),
                    "JSON output shouldn't have color, even if the colorized flag was set");
     }
+
+    #[test]
+    fn service_output_line_json() {
+        let line = ServiceOutputLine::new("core/redis/4.0.14/20190319155852",
+                                          "redis.default",
+                                          "stderr",
+                                          42,
+                                          "monkeys in the logs");
+
+        let mut actual: serde_json::Value =
+            serde_json::from_str(&line.to_json()).expect("Couldn't parse from JSON");
+        let timestamp = actual.as_object_mut()
+                              .unwrap()
+                              .remove("timestamp")
+                              .expect("timestamp should be present");
+
+        assert!(chrono::DateTime::parse_from_rfc3339(timestamp.as_str().unwrap()).is_ok());
+        assert_eq!(actual,
+                   serde_json::json!({
+                       "ident": "core/redis/4.0.14/20190319155852",
+                       "group": "redis.default",
+                       "stream": "stderr",
+                       "pid": 42,
+                       "content": "monkeys in the logs"
+                   }));
+    }
+
+    #[test]
+    fn output_targets_round_trip() {
+        let targets: OutputTargets = "file, events".parse().unwrap();
+        assert_eq!(targets, OutputTargets::FILE | OutputTargets::EVENTS);
+        assert_eq!(targets.to_string(), "file,events");
+        assert_eq!(OutputTargets::default().to_string(), "console,file,forward");
+
+        let none: OutputTargets = "none".parse().unwrap();
+        assert!(none.is_empty());
+        assert_eq!(none.to_string(), "none");
+
+        assert!("console,syslog".parse::<OutputTargets>().is_err());
+        assert!("".parse::<OutputTargets>().is_err());
+    }
 }
@@ -3,6 +3,7 @@ mod context;
 pub mod helpers;
 pub mod hooks;
 pub mod package;
+pub mod sandbox;
 pub mod test_helpers;
 
 pub use self::context::RenderContext;
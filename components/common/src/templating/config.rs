@@ -13,6 +13,7 @@ use serde::{Serialize,
 use std::{self,
           borrow::Cow,
           clone::Clone,
+          collections::BTreeMap,
           env,
           fs::File,
           io::prelude::*,
@@ -405,6 +406,18 @@ impl CfgRenderer {
         }
     }
 
+    /// Render all configuration files without writing them, returning the rendering of each by
+    /// its path relative to the configuration directory.
+    pub fn render<T>(&self, ctx: &T) -> BTreeMap<String, Result<String>>
+        where T: Serialize
+    {
+        self.0
+            .get_templates()
+            .keys()
+            .map(|template| (template.clone(), self.0.render(template, ctx)))
+            .collect()
+    }
+
     /// Compile and write all configuration files to the configuration directory.
     ///
     /// Returns `true` if the configuration has changed.
@@ -1124,6 +1137,20 @@ mod test {
         load_templates(&file, &PathBuf::new(), TemplateRenderer::new())?;
     }
 
+    #[test]
+    fn render_returns_the_rendering_of_each_template() {
+        let tmp = TempDir::new().expect("create temp dir");
+        create_with_content(tmp.path().join("good.conf"), "message is {{cfg.message}}");
+        create_with_content(tmp.path().join("bad.conf"), "{{noSuchHelper cfg.message}}");
+
+        let renderer = CfgRenderer::new(tmp.path()).expect("create cfg renderer");
+        let rendered = renderer.render(&serde_json::json!({ "cfg": { "message": "Hello" } }));
+        assert_eq!(rendered.keys().collect::<Vec<_>>(),
+                   vec!["bad.conf", "good.conf"]);
+        assert_eq!(rendered["good.conf"].as_ref().unwrap(), "message is Hello");
+        assert!(rendered["bad.conf"].is_err());
+    }
+
     #[tokio::test]
     async fn test_compile_recursive_config_dir() {
         let root = TempDir::new().expect("create temp dir").into_path();
@@ -1,4 +1,6 @@
 use super::{package::Pkg,
+            sandbox::{Sandbox,
+                      WORK_DIR_ENVVARS},
             TemplateRenderer};
 use crate::{error::{Error,
                     Result},
@@ -29,7 +31,10 @@ use std::{ffi::OsStr,
                BufReader},
           path::{Path,
                  PathBuf},
-          result};
+          result,
+          sync::mpsc,
+          thread,
+          time::Duration};
 
 #[cfg(not(windows))]
 pub const HOOK_PERMISSIONS: u32 = 0o755;
@@ -199,54 +204,126 @@ pub trait Hook: fmt::Debug + Sized + Send {
               -> Result<Self::ExitValue>
         where T: ToString
     {
-        self.run_impl(service_group, pkg, svc_encrypted_password)
+        self.run_impl(service_group, pkg, svc_encrypted_password, None, None)
     }
 
+    /// Run a compiled hook, killing it if it is still running after `timeout`. A hook that was
+    /// killed results in `Error::HookTimedOut`.
+    fn run_with_timeout<T>(&self,
+                           service_group: &str,
+                           pkg: &Pkg,
+                           svc_encrypted_password: Option<T>,
+                           timeout: Duration)
+                           -> Result<Self::ExitValue>
+        where T: ToString
+    {
+        self.run_impl(service_group,
+                      pkg,
+                      svc_encrypted_password,
+                      Some(timeout),
+                      None)
+    }
+
+    /// Run a compiled hook restricted by `sandbox`, killing it if it is still running after the
+    /// sandbox's timeout. The hook starts in a temporary directory in the service's `var`
+    /// directory, which is removed once it finishes.
+    fn run_sandboxed(&self,
+                     service_group: &str,
+                     pkg: &Pkg,
+                     sandbox: &Sandbox)
+                     -> Result<Self::ExitValue> {
+        let work_dir = tempfile::Builder::new().prefix(Self::FILE_NAME)
+                                               .tempdir_in(fs::svc_var_path(&pkg.name))?;
+        #[cfg(unix)]
+        {
+            if habitat_core::os::process::can_run_services_as_svc_user() {
+                habitat_core::util::posix_perm::set_owner(work_dir.path(),
+                                                          &pkg.svc_user,
+                                                          &pkg.svc_group)?;
+            }
+        }
+        self.run_impl(service_group,
+                      pkg,
+                      None::<&str>,
+                      Some(sandbox.timeout),
+                      Some((sandbox, work_dir.path())))
+    }
+
+    /// `sandbox` is the sandbox to run the hook in, along with the directory it starts in.
     fn run_impl<T>(&self,
                    service_group: &str,
                    pkg: &Pkg,
-                   svc_encrypted_password: Option<T>)
+                   svc_encrypted_password: Option<T>,
+                   timeout: Option<Duration>,
+                   sandbox: Option<(&Sandbox, &Path)>)
                    -> Result<Self::ExitValue>
         where T: ToString
     {
-        let mut child = Self::exec(self.path(), &pkg, svc_encrypted_password).map_err(|err| {
-                            outputln!(preamble service_group,
+        let mut child =
+            Self::exec(self.path(), &pkg, svc_encrypted_password, sandbox).map_err(|err| {
+                outputln!(preamble service_group,
                                       "Hook failed to run, {}, {}", Self::FILE_NAME, err);
-                            err
-                        })?;
+                err
+            })?;
+        let watchdog = timeout.map(|timeout| Watchdog::start(child.id(), timeout));
         let mut hook_output = HookOutput::new(self.stdout_log_path(), self.stderr_log_path());
         hook_output.output_standard_streams::<Self>(service_group, &mut child);
-        Ok(child.wait()
-                .map_err(|err| {
-                    outputln!(preamble service_group,
-                              "Hook failed to run, {}, {}", Self::FILE_NAME, err);
-                    err
-                })
-                .map(|status| self.handle_exit(pkg, &hook_output, status))?)
+        let status = child.wait().map_err(|err| {
+                                      outputln!(preamble service_group,
+                                                "Hook failed to run, {}, {}", Self::FILE_NAME, err);
+                                      err
+                                  })?;
+        if let (Some(watchdog), Some(timeout)) = (watchdog, timeout) {
+            if watchdog.stop() {
+                outputln!(preamble service_group,
+                          "{} did not finish within {}s and was killed",
+                          Self::FILE_NAME,
+                          timeout.as_secs());
+                return Err(Error::HookTimedOut(Self::FILE_NAME, timeout));
+            }
+        }
+        Ok(self.handle_exit(pkg, &hook_output, status))
     }
 
+    /// Network isolation is not available on Windows, so a sandboxed hook there is only pointed
+    /// at its temporary directory.
     #[cfg(windows)]
-    fn exec<T, S>(path: S, pkg: &Pkg, svc_encrypted_password: Option<T>) -> Result<Child>
+    fn exec<T, S>(path: S,
+                  pkg: &Pkg,
+                  svc_encrypted_password: Option<T>,
+                  sandbox: Option<(&Sandbox, &Path)>)
+                  -> Result<Child>
         where T: ToString,
               S: AsRef<OsStr>
     {
         use habitat_core::util;
 
         let ps_cmd = format!("iex $(gc {} | out-string)", path.as_ref().to_string_lossy());
+        let mut env = pkg.env.to_hash_map();
+        if let Some((_, work_dir)) = sandbox {
+            for var in WORK_DIR_ENVVARS {
+                env.insert(var.to_string(), work_dir.to_string_lossy().into_owned());
+            }
+        }
         Ok(Child::spawn("pwsh.exe",
                         &util::pwsh_args(ps_cmd.as_str()),
-                        &pkg.env.to_hash_map(),
+                        &env,
                         &pkg.svc_user,
                         svc_encrypted_password)?)
     }
 
     #[cfg(unix)]
-    fn exec<T, S>(path: S, pkg: &Pkg, _: Option<T>) -> Result<Child>
+    fn exec<T, S>(path: S,
+                  pkg: &Pkg,
+                  svc_encrypted_password: Option<T>,
+                  sandbox: Option<(&Sandbox, &Path)>)
+                  -> Result<Child>
         where T: ToString,
               S: AsRef<OsStr>
     {
-        use habitat_core::os::{process,
-                               users};
+        use habitat_core::{crypto::svc_password,
+                           os::{process,
+                                users}};
         use nix::unistd::{Gid,
                           Uid};
         use std::ops::Deref;
@@ -269,7 +346,24 @@ pub trait Hook: fmt::Debug + Sized + Send {
             None
         };
 
-        let mut cmd = process::exec::unix::hook_command(path, pkg.env.deref(), ids);
+        let mut env = pkg.env.deref().clone();
+        if let Some(password) = svc_encrypted_password {
+            env.insert(svc_password::SVC_PASSWORD_ENVVAR.to_string(),
+                       svc_password::decrypt(&password.to_string())?);
+        }
+        let mut cmd = match sandbox {
+            Some((sandbox, work_dir)) => {
+                for var in WORK_DIR_ENVVARS {
+                    env.insert(var.to_string(), work_dir.to_string_lossy().into_owned());
+                }
+                process::exec::unix::sandboxed_hook_command(path,
+                                                            env,
+                                                            ids,
+                                                            work_dir,
+                                                            sandbox.network)
+            }
+            None => process::exec::unix::hook_command(path, env, ids),
+        };
         Ok(cmd.spawn()?)
     }
 
@@ -291,6 +385,52 @@ pub trait Hook: fmt::Debug + Sized + Send {
     fn stderr_log_path(&self) -> &Path;
 }
 
+/// Kills a hook that runs for longer than its timeout.
+struct Watchdog {
+    cancel: mpsc::Sender<()>,
+    thread: thread::JoinHandle<bool>,
+}
+
+impl Watchdog {
+    /// Kill the process `pid` once `timeout` elapses, unless stopped before. On Unix, the
+    /// hook's whole process group is killed, so processes it started don't keep its output
+    /// streams open.
+    fn start(pid: u32, timeout: Duration) -> Self {
+        let (cancel, cancelled) = mpsc::channel();
+        let thread = thread::spawn(move || {
+            match cancelled.recv_timeout(timeout) {
+                Err(mpsc::RecvTimeoutError::Timeout) => {
+                    if let Err(err) = Self::kill(pid) {
+                        debug!("Unable to kill hook process {}: {}", pid, err);
+                    }
+                    true
+                }
+                _ => false,
+            }
+        });
+        Watchdog { cancel, thread }
+    }
+
+    #[cfg(unix)]
+    fn kill(pid: u32) -> habitat_core::error::Result<()> {
+        use habitat_core::os::process::{self,
+                                        Signal};
+        // Hooks run in their own process group; see `hook_command`.
+        process::signal(-(pid as process::Pid), Signal::KILL)
+    }
+
+    #[cfg(windows)]
+    fn kill(pid: u32) -> habitat_core::error::Result<()> {
+        habitat_core::os::process::terminate(pid)
+    }
+
+    /// Stop watching the hook, returning whether it was killed.
+    fn stop(self) -> bool {
+        self.cancel.send(()).ok();
+        self.thread.join().unwrap_or(false)
+    }
+}
+
 /// A trait that adds a convenient method for executing one-off hooks
 ///
 /// This trait unifies the logic the `install` and `uninstall` hooks use to execute. These hooks
@@ -306,10 +446,11 @@ pub trait PackageMaintenanceHookExt: Hook<ExitValue = ExitStatus> + Sync {
     /// * find the hook in a given `PackageInstall`
     /// * compile the hook (assuming the hook is compiled in
     ///   `templating::compile_for_package_install`)
-    /// * run the hook
+    /// * run the hook, restricted by `sandbox` if one is given
     /// * return an error if we get a non-zero exit code
     async fn find_run_and_error_for_status<U: UIWriter>(ui: &mut U,
-                                                        package: &PackageInstall)
+                                                        package: &PackageInstall,
+                                                        sandbox: Option<&Sandbox>)
                                                         -> Result<()> {
         let feature_flags = FeatureFlag::from_env(ui);
         let package_name = &package.ident.name;
@@ -337,7 +478,11 @@ pub trait PackageMaintenanceHookExt: Hook<ExitValue = ExitStatus> + Sync {
             #[cfg(not(target_os = "windows"))]
             let pkg = Pkg::from_install(package).await?;
 
-            match hook.run(package_name, &pkg, None::<&str>) {
+            let result = match sandbox {
+                Some(sandbox) => hook.run_sandboxed(package_name, &pkg, sandbox),
+                None => hook.run(package_name, &pkg, None::<&str>),
+            };
+            match result {
                 Ok(exit_status) if exit_status.success() => Ok(()),
                 Ok(exit_status) => {
                     Err(Error::hook_exit_status(pkg.ident.clone(),
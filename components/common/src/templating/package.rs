@@ -3,6 +3,7 @@ use crate::{error::{Error,
             hcore::{fs,
                     os::{process::{ShutdownSignal,
                                    ShutdownTimeout},
+                         seccomp::SeccompProfile,
                          users},
                     package::{FullyQualifiedPackageIdent,
                               PackageIdent,
@@ -93,6 +94,7 @@ pub struct Pkg {
     pub svc_group:               String,
     pub shutdown_signal:         ShutdownSignal,
     pub shutdown_timeout:        ShutdownTimeout,
+    pub seccomp_profile:         Option<SeccompProfile>,
 }
 
 impl Pkg {
@@ -122,6 +124,7 @@ impl Pkg {
                         release: String::from(ident.release()),
                         shutdown_signal: package.shutdown_signal()?.unwrap_or_default(),
                         shutdown_timeout: package.shutdown_timeout()?.unwrap_or_default(),
+                        seccomp_profile: package.seccomp_profile()?,
                         ident };
         Ok(pkg)
     }
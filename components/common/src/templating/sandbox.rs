@@ -0,0 +1,183 @@
+//! The restricted environment that package `install` hooks run in.
+//!
+//! Installing a package must not let its install hook quietly act on the host, so the hook runs
+//! with nothing but its package's environment, starts in a temporary directory that is also its
+//! `HOME` and `TMPDIR` and is removed afterwards, has no network access (on Linux) and is killed
+//! if it runs for too long. The policy in `/hab/etc/install-hooks.toml` loosens or tightens this
+//! for all packages, or for the packages matching an identifier:
+//!
+//! ```toml
+//! timeout = 300
+//!
+//! [packages."core/docker"]
+//! network = true
+//! timeout = 1800
+//! ```
+//!
+//! When several identifiers match a package, the most specific one wins.
+
+use crate::error::{Error,
+                   Result};
+use habitat_core::package::{Identifiable,
+                            PackageIdent};
+use std::{collections::BTreeMap,
+          fs,
+          io,
+          path::Path,
+          str::FromStr,
+          time::Duration};
+
+/// The path of the install hook policy beneath the filesystem root
+pub const INSTALL_HOOK_POLICY_PATH: &str = "hab/etc/install-hooks.toml";
+/// The environment variables that point a sandboxed hook at the temporary directory it starts in
+pub const WORK_DIR_ENVVARS: &[&str] = &["HOME", "TMPDIR", "TMP", "TEMP"];
+const DEFAULT_TIMEOUT_SECS: u64 = 600;
+
+/// How a hook is restricted
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Sandbox {
+    /// Whether the hook may access the network
+    pub network: bool,
+    /// How long the hook may run before it is killed
+    pub timeout: Duration,
+}
+
+impl Default for Sandbox {
+    fn default() -> Self {
+        Sandbox { network: false,
+                  timeout: Duration::from_secs(DEFAULT_TIMEOUT_SECS), }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Rules {
+    network: Option<bool>,
+    /// In seconds
+    timeout: Option<u64>,
+}
+
+impl Rules {
+    fn apply(&self, sandbox: &mut Sandbox) {
+        if let Some(network) = self.network {
+            sandbox.network = network;
+        }
+        if let Some(timeout) = self.timeout {
+            sandbox.timeout = Duration::from_secs(timeout);
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PolicyFile {
+    network:  Option<bool>,
+    timeout:  Option<u64>,
+    #[serde(default)]
+    packages: BTreeMap<String, Rules>,
+}
+
+/// The sandboxes that install hooks run in
+#[derive(Debug, Default)]
+pub struct InstallHookPolicy {
+    default:  Rules,
+    packages: Vec<(PackageIdent, Rules)>,
+}
+
+impl InstallHookPolicy {
+    /// Reads the policy beneath `fs_root_path`. Without a policy file, every install hook runs in
+    /// the default sandbox.
+    pub fn load(fs_root_path: &Path) -> Result<Self> {
+        let path = fs_root_path.join(INSTALL_HOOK_POLICY_PATH);
+        match fs::read_to_string(&path) {
+            Ok(raw) => {
+                raw.parse()
+                   .map_err(|e| Error::InstallHookPolicy(path.clone(), e))
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// The sandbox that the install hook of `ident` runs in.
+    pub fn sandbox(&self, ident: &PackageIdent) -> Sandbox {
+        let mut sandbox = Sandbox::default();
+        self.default.apply(&mut sandbox);
+        let mut matching = self.packages
+                               .iter()
+                               .filter(|(i, _)| ident.satisfies(i))
+                               .collect::<Vec<_>>();
+        matching.sort_by_key(|(i, _)| i.version.is_some() as u8 + i.release.is_some() as u8);
+        for (_, rules) in matching {
+            rules.apply(&mut sandbox);
+        }
+        sandbox
+    }
+}
+
+impl FromStr for InstallHookPolicy {
+    type Err = String;
+
+    fn from_str(raw: &str) -> std::result::Result<Self, Self::Err> {
+        let file: PolicyFile = toml::from_str(raw).map_err(|e| e.to_string())?;
+        let packages = file.packages
+                           .into_iter()
+                           .map(|(ident, rules)| {
+                               PackageIdent::from_str(&ident).map(|ident| (ident, rules))
+                                                             .map_err(|e| e.to_string())
+                           })
+                           .collect::<std::result::Result<_, _>>()?;
+        Ok(InstallHookPolicy { default: Rules { network: file.network,
+                                                timeout: file.timeout, },
+                               packages })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ident(s: &str) -> PackageIdent { PackageIdent::from_str(s).unwrap() }
+
+    #[test]
+    fn without_a_policy_hooks_get_the_default_sandbox() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let policy = InstallHookPolicy::load(dir.path()).unwrap();
+        assert_eq!(policy.sandbox(&ident("core/redis/6.2.5/20210915000000")),
+                   Sandbox::default());
+    }
+
+    #[test]
+    fn most_specific_package_rules_win() {
+        let policy: InstallHookPolicy = r#"
+timeout = 300
+
+[packages."core/docker"]
+network = true
+timeout = 1800
+
+[packages."core/docker/20.10.7"]
+network = false
+"#.parse()
+                                        .unwrap();
+
+        assert_eq!(policy.sandbox(&ident("core/redis/6.2.5/20210915000000")),
+                   Sandbox { network: false,
+                             timeout: Duration::from_secs(300), });
+        assert_eq!(policy.sandbox(&ident("core/docker/20.10.8/20210915000000")),
+                   Sandbox { network: true,
+                             timeout: Duration::from_secs(1800), });
+        assert_eq!(policy.sandbox(&ident("core/docker/20.10.7/20210915000000")),
+                   Sandbox { network: false,
+                             timeout: Duration::from_secs(1800), });
+    }
+
+    #[test]
+    fn invalid_policies_are_rejected() {
+        assert!("timeout = \"soon\"".parse::<InstallHookPolicy>().is_err());
+        assert!("[packages.\"core/docker\"]\nnetwrk = true".parse::<InstallHookPolicy>()
+                                                           .is_err());
+        assert!("[packages.\"core\"]\nnetwork = true".parse::<InstallHookPolicy>()
+                                                     .is_err());
+    }
+}
@@ -1,4 +1,6 @@
 use crate::error::Error;
+use chrono::{NaiveTime,
+             Utc};
 use clap::ArgMatches;
 use native_tls::Certificate;
 use std::{collections::HashMap,
@@ -7,6 +9,7 @@ use std::{collections::HashMap,
           io,
           net::{IpAddr,
                 Ipv4Addr,
+                Ipv6Addr,
                 SocketAddr,
                 SocketAddrV4,
                 ToSocketAddrs},
@@ -326,9 +329,10 @@ impl GossipListenAddr {
     /// former case, but `192.168.1.1` in the latter.
     pub fn local_addr(&self) -> Self {
         let mut addr = *self;
-        if addr.0.ip().is_unspecified() {
-            // TODO (CM): Support IPV6, once we do that more broadly
-            addr.0.set_ip(IpAddr::V4(Ipv4Addr::LOCALHOST));
+        match addr.0.ip() {
+            IpAddr::V4(ip) if ip.is_unspecified() => addr.0.set_ip(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            IpAddr::V6(ip) if ip.is_unspecified() => addr.0.set_ip(IpAddr::V6(Ipv6Addr::LOCALHOST)),
+            _ => {}
         }
         addr
     }
@@ -374,7 +378,7 @@ impl ToSocketAddrs for HttpListenAddr {
     fn to_socket_addrs(&self) -> io::Result<Self::Iter> { self.0.to_socket_addrs() }
 }
 
-habitat_core::env_config_socketaddr!(#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize, Serialize)]
+habitat_core::env_config_socketaddr!(#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Deserialize, Serialize)]
                                      pub ListenCtlAddr,
                                      HAB_LISTEN_CTL,
                                      Ipv4Addr::LOCALHOST, Self::DEFAULT_PORT);
@@ -395,20 +399,20 @@ impl ListenCtlAddr {
     // env_config_socketaddr! (and ultimately env_config!) macro
     // defines one for us.
     pub fn resolve_listen_ctl_addr(input: &str) -> crate::error::Result<ListenCtlAddr> {
-        let listen_ctl_addr = if input.find(':').is_some() {
-            input.to_string()
-        } else {
-            format!("{}:{}", input, ListenCtlAddr::DEFAULT_PORT)
-        };
+        let listen_ctl_addr = with_default_port(input, ListenCtlAddr::DEFAULT_PORT);
 
+        // Prefer an IPv4 address, but accept an IPv6 one when that is all the name resolves to.
         listen_ctl_addr.to_socket_addrs()
-                       .and_then(|mut addrs| {
-                           addrs.find(std::net::SocketAddr::is_ipv4).ok_or_else(|| {
-                                                                        io::Error::new(
-                        io::ErrorKind::AddrNotAvailable,
-                        "Address could not be resolved.",
-                    )
-                                                                    })
+                       .and_then(|addrs| {
+                           let addrs: Vec<SocketAddr> = addrs.collect();
+                           addrs.iter()
+                                .find(|addr| addr.is_ipv4())
+                                .or_else(|| addrs.first())
+                                .copied()
+                                .ok_or_else(|| {
+                                    io::Error::new(io::ErrorKind::AddrNotAvailable,
+                                                   "Address could not be resolved.")
+                                })
                        })
                        .map(ListenCtlAddr::from)
                        .map_err(|e| Error::RemoteSupResolutionError(listen_ctl_addr, e))
@@ -419,10 +423,280 @@ impl AsRef<SocketAddr> for ListenCtlAddr {
     fn as_ref(&self) -> &SocketAddr { &self.0 }
 }
 
+/// An additional address for the ctl gateway to listen on, with its own authentication, such as
+/// `10.0.5.1:9632,tls,secret-file=/hab/sup/default/CTL_SECRET_MGMT`.
+///
+/// With `tls`, connections are encrypted with the certificate of the HTTP gateway. With
+/// `secret-file`, clients authenticate with the secret in that file rather than with the
+/// Supervisor's `CTL_SECRET`.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct CtlListener {
+    pub addr:        SocketAddr,
+    pub tls:         bool,
+    pub secret_file: Option<PathBuf>,
+}
+
+impl FromStr for CtlListener {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        let invalid = |reason: String| {
+            io::Error::new(io::ErrorKind::InvalidInput,
+                           format!("Invalid ctl listener '{}' ({})", s, reason))
+        };
+        let mut parts = s.split(',');
+        let addr = parts.next().unwrap_or_default().trim();
+        let addr = with_default_port(addr, ListenCtlAddr::DEFAULT_PORT);
+        let addr = addr.parse::<SocketAddr>()
+                       .map_err(|_| invalid(format!("'{}' is not an IP[:PORT]", addr)))?;
+        let mut listener = CtlListener { addr,
+                                         tls: false,
+                                         secret_file: None };
+        for option in parts.map(str::trim) {
+            if option == "tls" {
+                listener.tls = true;
+            } else if option.starts_with("secret-file=") {
+                let path = &option["secret-file=".len()..];
+                if path.is_empty() {
+                    return Err(invalid("secret-file needs a path".to_string()));
+                }
+                listener.secret_file = Some(PathBuf::from(path));
+            } else {
+                return Err(invalid(format!("unknown option '{}', expected tls or \
+                                            secret-file=PATH",
+                                           option)));
+            }
+        }
+        Ok(listener)
+    }
+}
+
+impl fmt::Display for CtlListener {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.addr)?;
+        if self.tls {
+            write!(f, ",tls")?;
+        }
+        if let Some(ref secret_file) = self.secret_file {
+            write!(f, ",secret-file={}", secret_file.display())?;
+        }
+        Ok(())
+    }
+}
+
+impl std::convert::TryFrom<String> for CtlListener {
+    type Error = Error;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> { Ok(CtlListener::from_str(&s)?) }
+}
+
+impl Into<String> for CtlListener {
+    fn into(self) -> String { self.to_string() }
+}
+
+/// Add `default_port` to a `host[:port]` string that does not name a port, producing something
+/// that can be resolved with `ToSocketAddrs`. IPv6 addresses may be given bare (`::1`) or in
+/// brackets, with (`[::1]:9638`) or without (`[::1]`) a port.
+pub fn with_default_port(input: &str, default_port: u16) -> String {
+    if let Ok(ip) = input.parse::<IpAddr>() {
+        SocketAddr::new(ip, default_port).to_string()
+    } else if input.starts_with('[') && input.ends_with(']') {
+        format!("{}:{}", input, default_port)
+    } else if input.contains(':') {
+        input.to_string()
+    } else {
+        format!("{}:{}", input, default_port)
+    }
+}
+
+/// A daily window of time, in UTC, such as `02:00-04:00`. A window whose end is earlier than its
+/// start spans midnight.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct UpdateWindow {
+    start: NaiveTime,
+    end:   NaiveTime,
+}
+
+impl UpdateWindow {
+    const TIME_FORMAT: &'static str = "%H:%M";
+
+    /// Whether `time` falls within the window.
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+
+    /// How long it is from `time` until the window next opens; zero if it is already open.
+    pub fn until_open_at(&self, time: NaiveTime) -> Duration {
+        if self.contains(time) {
+            return Duration::from_secs(0);
+        }
+        let wait = self.start.signed_duration_since(time);
+        let wait = if wait < chrono::Duration::zero() {
+            wait + chrono::Duration::days(1)
+        } else {
+            wait
+        };
+        wait.to_std().unwrap_or_default()
+    }
+
+    /// How long it is from now until the window next opens; zero if it is already open.
+    pub fn until_open(&self) -> Duration { self.until_open_at(Utc::now().time()) }
+}
+
+impl FromStr for UpdateWindow {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        let parse = |t: &str| NaiveTime::parse_from_str(t.trim(), Self::TIME_FORMAT).ok();
+        match s.split('-').collect::<Vec<_>>().as_slice() {
+            [start, end] => {
+                match (parse(start), parse(end)) {
+                    (Some(start), Some(end)) if start != end => Ok(Self { start, end }),
+                    _ => {
+                        let e = format!("Invalid update window '{}' (the start and end must be \
+                                         different HH:MM times)",
+                                        s);
+                        Err(io::Error::new(io::ErrorKind::InvalidInput, e))
+                    }
+                }
+            }
+            _ => {
+                let e = format!("Invalid update window '{}' (must be of the form HH:MM-HH:MM)",
+                                s);
+                Err(io::Error::new(io::ErrorKind::InvalidInput, e))
+            }
+        }
+    }
+}
+
+impl fmt::Display for UpdateWindow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f,
+               "{}-{}",
+               self.start.format(Self::TIME_FORMAT),
+               self.end.format(Self::TIME_FORMAT))
+    }
+}
+
+impl std::convert::TryFrom<String> for UpdateWindow {
+    type Error = Error;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> { Ok(UpdateWindow::from_str(&s)?) }
+}
+
+impl Into<String> for UpdateWindow {
+    fn into(self) -> String { self.to_string() }
+}
+
+/// A cloud provider whose instance metadata service the Supervisor can query for the `sys.cloud`
+/// template data.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CloudProvider {
+    Aws,
+    Azure,
+    Gcp,
+}
+
+impl CloudProvider {
+    pub const VARIANTS: &'static [&'static str] = &["aws", "azure", "gcp"];
+}
+
+impl FromStr for CloudProvider {
+    type Err = String;
+
+    fn from_str(value: &str) -> result::Result<Self, Self::Err> {
+        match value {
+            "aws" => Ok(CloudProvider::Aws),
+            "azure" => Ok(CloudProvider::Azure),
+            "gcp" => Ok(CloudProvider::Gcp),
+            _ => Err(format!("Invalid cloud provider '{}'", value)),
+        }
+    }
+}
+
+impl fmt::Display for CloudProvider {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CloudProvider::Aws => write!(f, "aws"),
+            CloudProvider::Azure => write!(f, "azure"),
+            CloudProvider::Gcp => write!(f, "gcp"),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    mod ctl_listener {
+        use super::*;
+
+        #[test]
+        fn parses_and_displays() {
+            let listener: CtlListener =
+                "10.0.5.1,tls,secret-file=/hab/sup/default/CTL_MGMT".parse()
+                                                                    .unwrap();
+            assert_eq!(listener,
+                       CtlListener { addr:        "10.0.5.1:9632".parse().unwrap(),
+                                     tls:         true,
+                                     secret_file: Some("/hab/sup/default/CTL_MGMT".into()), });
+            assert_eq!(listener.to_string(),
+                       "10.0.5.1:9632,tls,secret-file=/hab/sup/default/CTL_MGMT");
+
+            let listener: CtlListener = "[::1]:9000".parse().unwrap();
+            assert_eq!(listener.to_string(), "[::1]:9000");
+        }
+
+        #[test]
+        fn rejects_invalid_listeners() {
+            assert!("ctl.example.com".parse::<CtlListener>().is_err());
+            assert!("10.0.5.1,tsl".parse::<CtlListener>().is_err());
+            assert!("10.0.5.1,secret-file=".parse::<CtlListener>().is_err());
+        }
+    }
+
+    mod update_window {
+        use super::*;
+
+        fn time(t: &str) -> NaiveTime { NaiveTime::parse_from_str(t, "%H:%M").unwrap() }
+
+        #[test]
+        fn parses_and_displays() {
+            let window: UpdateWindow = "02:00-04:30".parse().unwrap();
+            assert_eq!(window.to_string(), "02:00-04:30");
+            assert!("02:00".parse::<UpdateWindow>().is_err());
+            assert!("02:00-25:00".parse::<UpdateWindow>().is_err());
+            assert!("02:00-02:00".parse::<UpdateWindow>().is_err());
+        }
+
+        #[test]
+        fn time_until_open() {
+            let window: UpdateWindow = "02:00-04:00".parse().unwrap();
+            assert_eq!(window.until_open_at(time("03:00")), Duration::from_secs(0));
+            assert_eq!(window.until_open_at(time("01:00")),
+                       Duration::from_secs(3600));
+            assert_eq!(window.until_open_at(time("04:00")),
+                       Duration::from_secs(22 * 3600));
+        }
+
+        #[test]
+        fn windows_can_span_midnight() {
+            let window: UpdateWindow = "23:00-01:00".parse().unwrap();
+            assert!(window.contains(time("23:30")));
+            assert!(window.contains(time("00:30")));
+            assert!(!window.contains(time("12:00")));
+            assert_eq!(window.until_open_at(time("22:00")),
+                       Duration::from_secs(3600));
+        }
+    }
+
     mod auth_token {
         use super::*;
 
@@ -451,6 +725,34 @@ mod tests {
             let local_addr = listen_addr.local_addr();
             assert_eq!(listen_addr, local_addr);
         }
+
+        #[test]
+        fn local_addr_for_gossip_listen_addr_works_for_unspecified_ipv6_address() {
+            let listen_addr = GossipListenAddr::from_str("[::]:9638").unwrap();
+
+            let local_addr = listen_addr.local_addr();
+            assert_eq!(local_addr.0, "[::1]:9638".parse::<SocketAddr>().unwrap());
+        }
+    }
+
+    mod default_port {
+        use super::*;
+
+        #[test]
+        fn default_port_is_added_when_missing() {
+            assert_eq!(with_default_port("1.2.3.4", 89), "1.2.3.4:89");
+            assert_eq!(with_default_port("example.com", 89), "example.com:89");
+            assert_eq!(with_default_port("::1", 89), "[::1]:89");
+            assert_eq!(with_default_port("[::1]", 89), "[::1]:89");
+        }
+
+        #[test]
+        fn given_port_is_kept() {
+            assert_eq!(with_default_port("1.2.3.4:1500", 89), "1.2.3.4:1500");
+            assert_eq!(with_default_port("example.com:1500", 89),
+                       "example.com:1500");
+            assert_eq!(with_default_port("[::1]:1500", 89), "[::1]:1500");
+        }
     }
 
     mod env_config {
@@ -541,5 +843,27 @@ mod tests {
 
             assert_eq!(expected, actual);
         }
+
+        #[test]
+        fn ipv6_is_resolved() {
+            let expected =
+                ListenCtlAddr::from_str("[::1]:8080").expect("Could not create ListenCtlAddr");
+            let actual =
+                ListenCtlAddr::resolve_listen_ctl_addr("[::1]:8080").expect("Could not resolve \
+                                                                             string");
+
+            assert_eq!(expected, actual);
+        }
+
+        #[test]
+        fn port_is_set_to_default_for_bare_ipv6() {
+            let expected =
+                ListenCtlAddr::from_str(&format!("[::1]:{}", ListenCtlAddr::DEFAULT_PORT))
+                    .expect("Could not create ListenCtlAddr");
+            let actual =
+                ListenCtlAddr::resolve_listen_ctl_addr("::1").expect("Could not resolve string");
+
+            assert_eq!(expected, actual);
+        }
     }
 }
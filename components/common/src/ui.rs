@@ -3,6 +3,7 @@ use crate::{api_client::DisplayProgress,
             error::{Error,
                     Result},
             output};
+use serde::Serialize;
 use std::{env,
           fmt,
           fs::{self,
@@ -15,7 +16,9 @@ use std::{env,
                Write},
           process::{self,
                     Command},
-          str::FromStr};
+          str::FromStr,
+          sync::atomic::{AtomicBool,
+                         Ordering}};
 use termcolor::{self,
                 ColorChoice,
                 ColorSpec,
@@ -29,6 +32,61 @@ pub const NOCOLORING_ENVVAR: &str = "HAB_NOCOLORING";
 
 pub const GLYPH_STYLE_ENVVAR: &str = "HAB_GLYPH_STYLE";
 
+static JSON_OUTPUT: AtomicBool = AtomicBool::new(false);
+
+/// The format the results of a command are reported in.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum OutputFormat {
+    /// Messages for people, written to standard output as the command runs.
+    Text,
+    /// A single JSON object with the command's result, written to standard output when the
+    /// command finishes. Messages for people are written to standard error instead.
+    Json,
+}
+
+impl OutputFormat {
+    pub const VARIANTS: &'static [&'static str] = &["text", "json"];
+}
+
+impl Default for OutputFormat {
+    fn default() -> Self { OutputFormat::Text }
+}
+
+impl FromStr for OutputFormat {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value.to_lowercase().as_str() {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            _ => Err(Error::InvalidOutputFormat(value.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutputFormat::Text => write!(f, "text"),
+            OutputFormat::Json => write!(f, "json"),
+        }
+    }
+}
+
+/// Get the OutputFormat command results are to be reported in
+pub fn output_format() -> OutputFormat {
+    if JSON_OUTPUT.load(Ordering::Relaxed) {
+        OutputFormat::Json
+    } else {
+        OutputFormat::Text
+    }
+}
+
+/// Set the OutputFormat command results are to be reported in
+pub fn set_output_format(format: OutputFormat) {
+    JSON_OUTPUT.store(format == OutputFormat::Json, Ordering::Relaxed)
+}
+
 #[derive(Clone, Copy)]
 pub enum Color {
     Plain,
@@ -221,6 +279,7 @@ pub enum Status {
     Promoting,
     Rescinded,
     Rescinding,
+    Revoked,
     Sending,
     Sent,
     Signed,
@@ -277,6 +336,7 @@ impl Status {
             Status::Promoting => (Glyph::RightArrow, "Promoting".into(), Color::Info),
             Status::Rescinded => (Glyph::CheckMark, "Rescinded".into(), Color::Info),
             Status::Rescinding => (Glyph::BoxedX, "Rescinding".into(), Color::Info),
+            Status::Revoked => (Glyph::BoxedX, "Revoked".into(), Color::Info),
             Status::Sending => (Glyph::UpArrow, "Sending".into(), Color::Info),
             Status::Sent => (Glyph::CheckMark, "Sent".into(), Color::Info),
             Status::Signed => (Glyph::CheckMark, "Signed".into(), Color::Important),
@@ -426,6 +486,14 @@ pub trait UIWriter: Send {
         self.out().write_all(b"\n")?;
         self.out().flush()
     }
+
+    /// Report the result of a command for machines to consume. Nothing is written unless the
+    /// output format is `OutputFormat::Json`.
+    fn result<T>(&mut self, _result: &T) -> io::Result<()>
+        where T: Serialize
+    {
+        Ok(())
+    }
 }
 
 /// Console (shell) backed UI.
@@ -507,21 +575,44 @@ impl Default for UI {
 impl UIWriter for UI {
     type ProgressBar = ConsoleProgressBar;
 
-    fn out(&mut self) -> &mut dyn WriteColor { &mut self.shell.out }
+    // When reporting results as JSON, standard output is kept for the result alone.
+    fn out(&mut self) -> &mut dyn WriteColor {
+        match output_format() {
+            OutputFormat::Text => &mut self.shell.out,
+            OutputFormat::Json => &mut self.shell.err,
+        }
+    }
 
     fn err(&mut self) -> &mut dyn WriteColor { &mut self.shell.err }
 
-    fn is_out_a_terminal(&self) -> bool { self.shell.out.is_a_terminal() }
+    fn is_out_a_terminal(&self) -> bool {
+        match output_format() {
+            OutputFormat::Text => self.shell.out.is_a_terminal(),
+            OutputFormat::Json => self.shell.err.is_a_terminal(),
+        }
+    }
 
     fn is_err_a_terminal(&self) -> bool { self.shell.err.is_a_terminal() }
 
     fn progress(&self) -> Option<Box<dyn DisplayProgress>> {
-        if self.is_out_a_terminal() {
+        // The progress bar always draws on standard output
+        if self.is_out_a_terminal() && output_format() == OutputFormat::Text {
             Some(Box::new(Self::ProgressBar::default()))
         } else {
             None
         }
     }
+
+    fn result<T>(&mut self, result: &T) -> io::Result<()>
+        where T: Serialize
+    {
+        if output_format() == OutputFormat::Json {
+            serde_json::to_writer(&mut self.shell.out, result)?;
+            self.shell.out.write_all(b"\n")?;
+            self.shell.out.flush()?;
+        }
+        Ok(())
+    }
 }
 
 impl UIReader for UI {
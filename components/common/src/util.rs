@@ -1 +1,4 @@
+pub mod env_file;
+pub mod log_forward;
 pub mod path;
+pub mod rotating_log;
@@ -0,0 +1,141 @@
+//! Environment files in the dotenv format, whose variables are set in the environment of a
+//! service.
+//!
+//! Each line of an environment file is either blank, a comment starting with `#`, or a
+//! `KEY=VALUE` assignment, optionally preceded by `export`. Values may be quoted: single quotes
+//! keep everything between them as is, while double quotes understand the `\n`, `\t`, `\"` and
+//! `\\` escapes. Unquoted values end at a ` #` comment, and surrounding whitespace is dropped.
+//! Variables are not expanded.
+use std::{collections::BTreeMap,
+          fs,
+          io,
+          path::Path};
+
+/// Read the variables of the environment file at `path`. Later assignments of a variable
+/// override earlier ones.
+pub fn read(path: &Path) -> io::Result<BTreeMap<String, String>> {
+    let contents = fs::read_to_string(path)?;
+    parse(&contents).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+/// Parse the variables of the contents of an environment file.
+pub fn parse(contents: &str) -> Result<BTreeMap<String, String>, String> {
+    let mut vars = BTreeMap::new();
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (key, value) =
+            parse_assignment(line).map_err(|err| format!("line {}: {}", index + 1, err))?;
+        vars.insert(key, value);
+    }
+    Ok(vars)
+}
+
+fn parse_assignment(line: &str) -> Result<(String, String), String> {
+    let line = if line.starts_with("export ") {
+        line["export ".len()..].trim_start()
+    } else {
+        line
+    };
+    let separator = line.find('=')
+                        .ok_or_else(|| format!("expected KEY=VALUE, found '{}'", line))?;
+    let key = line[..separator].trim();
+    if !is_valid_key(key) {
+        return Err(format!("'{}' is not a valid variable name", key));
+    }
+    let value = parse_value(line[separator + 1..].trim_start())?;
+    Ok((key.to_string(), value))
+}
+
+fn is_valid_key(key: &str) -> bool {
+    let mut chars = key.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        _ => false,
+    }
+}
+
+fn parse_value(value: &str) -> Result<String, String> {
+    let (parsed, rest) = if value.starts_with('\'') {
+        let end = value[1..].find('\'')
+                            .ok_or_else(|| "unterminated single-quoted value".to_string())?;
+        (value[1..=end].to_string(), &value[end + 2..])
+    } else if value.starts_with('"') {
+        parse_double_quoted(&value[1..])?
+    } else {
+        let end = value.find(" #").unwrap_or(value.len());
+        return Ok(value[..end].trim_end().to_string());
+    };
+    let rest = rest.trim_start();
+    if rest.is_empty() || rest.starts_with('#') {
+        Ok(parsed)
+    } else {
+        Err(format!("unexpected '{}' after quoted value", rest))
+    }
+}
+
+/// Parse a double-quoted value, past its opening quote, returning it and what follows its
+/// closing quote.
+fn parse_double_quoted(value: &str) -> Result<(String, &str), String> {
+    let mut parsed = String::new();
+    let mut chars = value.char_indices();
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '"' => return Ok((parsed, &value[index + 1..])),
+            '\\' => {
+                match chars.next() {
+                    Some((_, 'n')) => parsed.push('\n'),
+                    Some((_, 't')) => parsed.push('\t'),
+                    Some((_, escaped @ '"')) | Some((_, escaped @ '\\')) => parsed.push(escaped),
+                    Some((_, other)) => {
+                        parsed.push('\\');
+                        parsed.push(other);
+                    }
+                    None => break,
+                }
+            }
+            _ => parsed.push(c),
+        }
+    }
+    Err("unterminated double-quoted value".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_assignments() {
+        let vars = parse(
+                         r#"
+# Database
+export DB_HOST=db.example.com
+DB_PORT = 5432 # the default
+DB_PASSWORD='s3cr#t "quoted"'
+GREETING="hello\n\"world\"" # greeting
+EMPTY=
+DB_PORT=5433
+"#,
+        ).unwrap();
+
+        assert_eq!(vars.len(), 5);
+        assert_eq!(vars["DB_HOST"], "db.example.com");
+        assert_eq!(vars["DB_PORT"], "5433");
+        assert_eq!(vars["DB_PASSWORD"], r#"s3cr#t "quoted""#);
+        assert_eq!(vars["GREETING"], "hello\n\"world\"");
+        assert_eq!(vars["EMPTY"], "");
+    }
+
+    #[test]
+    fn rejects_invalid_lines() {
+        assert!(parse("NO_VALUE").unwrap_err().starts_with("line 1:"));
+        assert!(parse("\n1KEY=value").unwrap_err().starts_with("line 2:"));
+        assert!(parse("KEY='unterminated").is_err());
+        assert!(parse("KEY=\"unterminated").is_err());
+        assert!(parse("KEY=\"value\" trailing").is_err());
+    }
+}
@@ -0,0 +1,434 @@
+//! Forwarding of captured service output to a syslog receiver or the systemd journal.
+//!
+//! Syslog messages are formatted according to RFC 5424 and sent either as one UDP datagram per
+//! message or, over TCP and TLS, using octet-counting framing (RFC 6587 and RFC 5425). Journal
+//! entries use the journal's native datagram protocol.
+use chrono::{SecondsFormat,
+             Utc};
+use native_tls::{TlsConnector,
+                 TlsStream};
+#[cfg(unix)]
+use std::os::unix::net::UnixDatagram;
+use std::{convert::TryFrom,
+          fmt,
+          io::{self,
+               Write},
+          net::{IpAddr,
+                Ipv4Addr,
+                Ipv6Addr,
+                SocketAddr,
+                TcpStream,
+                ToSocketAddrs,
+                UdpSocket},
+          str::FromStr,
+          time::{Duration,
+                 Instant}};
+
+#[cfg(unix)]
+const JOURNALD_SOCKET: &str = "/run/systemd/journal/socket";
+/// The syslog facility services log under (`daemon`).
+const SYSLOG_FACILITY: u8 = 3;
+/// How long connecting to, or sending a message to, a receiver may take.
+const IO_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long to wait before connecting again after a failure.
+const RECONNECT_DELAY: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SyslogTransport {
+    Udp,
+    Tcp,
+    Tls,
+}
+
+impl SyslogTransport {
+    fn scheme(self) -> &'static str {
+        match self {
+            SyslogTransport::Udp => "syslog+udp",
+            SyslogTransport::Tcp => "syslog+tcp",
+            SyslogTransport::Tls => "syslog+tls",
+        }
+    }
+
+    fn default_port(self) -> u16 {
+        match self {
+            SyslogTransport::Udp => 514,
+            SyslogTransport::Tcp => 601,
+            SyslogTransport::Tls => 6514,
+        }
+    }
+}
+
+/// Where captured service output is forwarded to, in addition to the Supervisor's own output.
+///
+/// Written as `journald` or as `syslog+<udp|tcp|tls>://<host>[:<port>]`.
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum LogForward {
+    Syslog {
+        transport: SyslogTransport,
+        host:      String,
+        port:      u16,
+    },
+    Journald,
+}
+
+impl FromStr for LogForward {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if value == "journald" {
+            return Ok(LogForward::Journald);
+        }
+        let invalid = || {
+            format!("'{}' is not a valid log destination; expected 'journald' or \
+                     'syslog+<udp|tcp|tls>://<host>[:<port>]'",
+                    value)
+        };
+        let mut parts = value.splitn(2, "://");
+        let transport = match parts.next() {
+            Some("syslog+udp") => SyslogTransport::Udp,
+            Some("syslog+tcp") => SyslogTransport::Tcp,
+            Some("syslog+tls") => SyslogTransport::Tls,
+            _ => return Err(invalid()),
+        };
+        let address = parts.next().filter(|a| !a.is_empty()).ok_or_else(invalid)?;
+        let (host, port) = split_host_port(address).ok_or_else(invalid)?;
+        Ok(LogForward::Syslog { transport,
+                                host: host.to_string(),
+                                port: port.unwrap_or_else(|| transport.default_port()) })
+    }
+}
+
+impl TryFrom<String> for LogForward {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> { value.parse() }
+}
+
+impl From<LogForward> for String {
+    fn from(value: LogForward) -> Self { value.to_string() }
+}
+
+impl fmt::Display for LogForward {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LogForward::Syslog { transport,
+                                 host,
+                                 port, }
+                if host.contains(':') =>
+            {
+                write!(f, "{}://[{}]:{}", transport.scheme(), host, port)
+            }
+            LogForward::Syslog { transport,
+                                 host,
+                                 port, } => {
+                write!(f, "{}://{}:{}", transport.scheme(), host, port)
+            }
+            LogForward::Journald => write!(f, "journald"),
+        }
+    }
+}
+
+/// Split `host`, `host:port`, `[v6-host]` or `[v6-host]:port`.
+fn split_host_port(address: &str) -> Option<(&str, Option<u16>)> {
+    let (host, port) = if address.starts_with('[') {
+        let end = address.find(']')?;
+        let port = &address[end + 1..];
+        if port.is_empty() {
+            (&address[1..end], None)
+        } else if port.starts_with(':') {
+            (&address[1..end], Some(&port[1..]))
+        } else {
+            return None;
+        }
+    } else {
+        let mut parts = address.splitn(2, ':');
+        (parts.next()?, parts.next())
+    };
+    if host.is_empty() {
+        return None;
+    }
+    match port {
+        Some(port) => Some((host, Some(port.parse().ok()?))),
+        None => Some((host, None)),
+    }
+}
+
+/// The severity a line of output is forwarded with.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Severity {
+    /// Used for lines from stdout
+    Info,
+    /// Used for lines from stderr
+    Error,
+}
+
+impl Severity {
+    fn code(self) -> u8 {
+        match self {
+            Severity::Info => 6,
+            Severity::Error => 3,
+        }
+    }
+}
+
+enum Connection {
+    Udp(UdpSocket),
+    Tcp(TcpStream),
+    Tls(Box<TlsStream<TcpStream>>),
+    #[cfg(unix)]
+    Journald(UnixDatagram),
+}
+
+/// Sends the lines of a single service to a `LogForward` destination. The connection is
+/// established lazily and re-established after any failure, once `RECONNECT_DELAY` has passed;
+/// lines sent in the meantime are dropped. Connecting and sending time out after `IO_TIMEOUT`.
+pub struct LogForwarder {
+    target:     LogForward,
+    identifier: String,
+    pid:        u32,
+    hostname:   String,
+    connection: Option<Connection>,
+    retry_at:   Option<Instant>,
+}
+
+impl LogForwarder {
+    /// `identifier` is the syslog APP-NAME and journal `SYSLOG_IDENTIFIER` of the service,
+    /// usually its service group (e.g. "redis.default").
+    pub fn new(target: LogForward, identifier: &str, pid: u32) -> Self {
+        let hostname = habitat_core::os::net::hostname().unwrap_or_else(|_| "-".to_string());
+        LogForwarder { target,
+                       identifier: identifier.to_string(),
+                       pid,
+                       hostname,
+                       connection: None,
+                       retry_at: None }
+    }
+
+    pub fn target(&self) -> &LogForward { &self.target }
+
+    pub fn send(&mut self, severity: Severity, line: &str) -> io::Result<()> {
+        if self.connection.is_none() {
+            if let Some(retry_at) = self.retry_at {
+                if Instant::now() < retry_at {
+                    return Err(io::Error::new(io::ErrorKind::NotConnected,
+                                              "waiting to reconnect after a failure"));
+                }
+            }
+        }
+        let result = self.try_send(severity, line);
+        if result.is_err() {
+            self.connection = None;
+            self.retry_at = Some(Instant::now() + RECONNECT_DELAY);
+        }
+        result
+    }
+
+    fn try_send(&mut self, severity: Severity, line: &str) -> io::Result<()> {
+        let message = match self.target {
+            LogForward::Syslog { .. } => {
+                syslog_message(severity,
+                               &Utc::now().to_rfc3339_opts(SecondsFormat::Micros, true),
+                               &self.hostname,
+                               &self.identifier,
+                               self.pid,
+                               line)
+            }
+            LogForward::Journald => journald_message(severity, &self.identifier, self.pid, line),
+        };
+        if self.connection.is_none() {
+            self.connection = Some(connect(&self.target)?);
+        }
+        match self.connection {
+            Some(Connection::Udp(ref socket)) => socket.send(&message).map(|_| ()),
+            Some(Connection::Tcp(ref mut stream)) => write_framed(stream, &message),
+            Some(Connection::Tls(ref mut stream)) => write_framed(stream.as_mut(), &message),
+            #[cfg(unix)]
+            Some(Connection::Journald(ref socket)) => socket.send(&message).map(|_| ()),
+            None => unreachable!("connection was just established"),
+        }
+    }
+}
+
+fn connect(target: &LogForward) -> io::Result<Connection> {
+    match target {
+        LogForward::Syslog { transport: SyslogTransport::Udp,
+                             host,
+                             port, } => {
+            let address = resolve(host, *port)?;
+            let unspecified = match address {
+                SocketAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+                SocketAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+            };
+            let socket = UdpSocket::bind(SocketAddr::new(unspecified, 0))?;
+            socket.connect(address)?;
+            socket.set_write_timeout(Some(IO_TIMEOUT))?;
+            Ok(Connection::Udp(socket))
+        }
+        LogForward::Syslog { transport: SyslogTransport::Tcp,
+                             host,
+                             port, } => Ok(Connection::Tcp(connect_tcp(host, *port)?)),
+        LogForward::Syslog { transport: SyslogTransport::Tls,
+                             host,
+                             port, } => {
+            let stream = connect_tcp(host, *port)?;
+            let connector = TlsConnector::new().map_err(|e| {
+                                                   io::Error::new(io::ErrorKind::Other,
+                                                                  e.to_string())
+                                               })?;
+            let stream =
+                connector.connect(host, stream)
+                         .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+            Ok(Connection::Tls(Box::new(stream)))
+        }
+        #[cfg(unix)]
+        LogForward::Journald => {
+            let socket = UnixDatagram::unbound()?;
+            socket.connect(JOURNALD_SOCKET)?;
+            socket.set_write_timeout(Some(IO_TIMEOUT))?;
+            Ok(Connection::Journald(socket))
+        }
+        #[cfg(windows)]
+        LogForward::Journald => {
+            Err(io::Error::new(io::ErrorKind::Other,
+                               "journald is not available on this platform"))
+        }
+    }
+}
+
+/// A TCP connection whose connecting, reads and writes time out after `IO_TIMEOUT`. The read
+/// timeout also bounds the TLS handshake.
+fn connect_tcp(host: &str, port: u16) -> io::Result<TcpStream> {
+    let stream = TcpStream::connect_timeout(&resolve(host, port)?, IO_TIMEOUT)?;
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    stream.set_write_timeout(Some(IO_TIMEOUT))?;
+    Ok(stream)
+}
+
+fn resolve(host: &str, port: u16) -> io::Result<SocketAddr> {
+    (host, port).to_socket_addrs()?.next().ok_or_else(|| {
+                                              io::Error::new(io::ErrorKind::NotFound,
+                                                             format!("Unable to resolve {}", host))
+                                          })
+}
+
+/// Prefix `message` with its length, as required for syslog over a stream.
+fn write_framed(writer: &mut impl Write, message: &[u8]) -> io::Result<()> {
+    write!(writer, "{} ", message.len())?;
+    writer.write_all(message)?;
+    writer.flush()
+}
+
+/// An RFC 5424 message without MSGID or structured data.
+fn syslog_message(severity: Severity,
+                  timestamp: &str,
+                  hostname: &str,
+                  identifier: &str,
+                  pid: u32,
+                  line: &str)
+                  -> Vec<u8> {
+    format!("<{}>1 {} {} {} {} - - {}",
+            SYSLOG_FACILITY * 8 + severity.code(),
+            timestamp,
+            hostname,
+            identifier,
+            pid,
+            line).into_bytes()
+}
+
+/// A journal entry in the native protocol. Values containing newlines have to be length-prefixed.
+fn journald_message(severity: Severity, identifier: &str, pid: u32, line: &str) -> Vec<u8> {
+    let mut message = Vec::new();
+    if line.contains('\n') {
+        message.extend_from_slice(b"MESSAGE\n");
+        message.extend_from_slice(&(line.len() as u64).to_le_bytes());
+        message.extend_from_slice(line.as_bytes());
+        message.push(b'\n');
+    } else {
+        message.extend_from_slice(format!("MESSAGE={}\n", line).as_bytes());
+    }
+    message.extend_from_slice(format!("PRIORITY={}\nSYSLOG_IDENTIFIER={}\nSYSLOG_PID={}\n",
+                                      severity.code(),
+                                      identifier,
+                                      pid).as_bytes());
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn destinations_are_parsed() {
+        assert_eq!("journald".parse::<LogForward>().unwrap(),
+                   LogForward::Journald);
+        assert_eq!("syslog+udp://logs.example.com".parse::<LogForward>()
+                                                  .unwrap(),
+                   LogForward::Syslog { transport: SyslogTransport::Udp,
+                                        host:      "logs.example.com".to_string(),
+                                        port:      514, });
+        assert_eq!("syslog+tls://[::1]:7000".parse::<LogForward>().unwrap(),
+                   LogForward::Syslog { transport: SyslogTransport::Tls,
+                                        host:      "::1".to_string(),
+                                        port:      7000, });
+        assert!("syslog://logs.example.com".parse::<LogForward>().is_err());
+        assert!("syslog+tcp://".parse::<LogForward>().is_err());
+        assert!("syslog+tcp://logs.example.com:http".parse::<LogForward>()
+                                                    .is_err());
+    }
+
+    #[test]
+    fn destinations_round_trip_through_strings() {
+        for value in &["journald",
+                       "syslog+tcp://10.0.0.1:601",
+                       "syslog+tls://[::1]:6514"]
+        {
+            assert_eq!(value.parse::<LogForward>().unwrap().to_string(), *value);
+        }
+    }
+
+    #[test]
+    fn syslog_messages_follow_rfc_5424() {
+        let message = syslog_message(Severity::Error,
+                                     "2020-09-01T12:00:00.000000Z",
+                                     "host1",
+                                     "redis.default",
+                                     42,
+                                     "oh no");
+        assert_eq!(String::from_utf8(message).unwrap(),
+                   "<27>1 2020-09-01T12:00:00.000000Z host1 redis.default 42 - - oh no");
+    }
+
+    #[test]
+    fn lines_are_sent_over_udp() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let port = receiver.local_addr().unwrap().port();
+        let target = LogForward::Syslog { transport: SyslogTransport::Udp,
+                                          host: "127.0.0.1".to_string(),
+                                          port };
+        let mut forwarder = LogForwarder::new(target, "redis.default", 42);
+        forwarder.send(Severity::Info, "hello").unwrap();
+
+        let mut buf = [0; 1024];
+        let len = receiver.recv(&mut buf).unwrap();
+        let message = String::from_utf8_lossy(&buf[..len]);
+        assert!(message.starts_with("<30>1 "));
+        assert!(message.ends_with(" redis.default 42 - - hello"));
+    }
+
+    #[test]
+    fn lines_are_dropped_until_reconnecting() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        drop(listener);
+        let target = LogForward::Syslog { transport: SyslogTransport::Tcp,
+                                          host: "127.0.0.1".to_string(),
+                                          port };
+        let mut forwarder = LogForwarder::new(target, "redis.default", 42);
+
+        assert_ne!(forwarder.send(Severity::Info, "hello").unwrap_err().kind(),
+                   io::ErrorKind::NotConnected);
+        assert_eq!(forwarder.send(Severity::Info, "hello").unwrap_err().kind(),
+                   io::ErrorKind::NotConnected);
+    }
+}
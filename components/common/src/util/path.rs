@@ -1,7 +1,8 @@
-use crate::{command::package::install::{self,
-                                        InstallHookMode,
-                                        InstallMode,
-                                        LocalPackageUsage},
+use crate::{command::package::{install::{self,
+                                         InstallHookMode,
+                                         InstallMode,
+                                         LocalPackageUsage},
+                               relocate::RelocateMode},
             error::{Error,
                     Result},
             ui,
@@ -108,7 +109,8 @@ async fn interpreter_paths() -> Result<Vec<PathBuf>> {
                                                      None,
                                                      &InstallMode::default(),
                                                      &LocalPackageUsage::default(),
-                                                     InstallHookMode::default()).await
+                                                     InstallHookMode::default(),
+                                                     RelocateMode::default()).await
                     {
                         Ok(pkg_install) => pkg_install.paths()?,
                         Err(err) => {
@@ -0,0 +1,149 @@
+//! A line-oriented log file which is rotated once it grows too large or too old.
+//!
+//! Rotated files are renamed with an increasing numeric suffix (`service.log.1` being the most
+//! recent) and only the configured number of them is retained.
+use std::{fs::{self,
+               File,
+               OpenOptions},
+          io::{self,
+               Write},
+          path::{Path,
+                 PathBuf},
+          time::{Duration,
+                 SystemTime}};
+
+/// When to rotate a log file and how many rotated files to keep.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct LogRotation {
+    /// Rotate once the file reaches this size.
+    pub max_bytes: u64,
+    /// Rotate once the file is older than this, if set.
+    pub max_age:   Option<Duration>,
+    /// The number of rotated files to keep. With `0`, no log file is written at all.
+    pub max_files: u32,
+}
+
+impl LogRotation {
+    pub const DEFAULT_MAX_BYTES: u64 = 10 * 1024 * 1024;
+    pub const DEFAULT_MAX_FILES: u32 = 5;
+
+    pub fn is_enabled(&self) -> bool { self.max_files > 0 }
+}
+
+impl Default for LogRotation {
+    fn default() -> Self {
+        Self { max_bytes: Self::DEFAULT_MAX_BYTES,
+               max_age:   None,
+               max_files: Self::DEFAULT_MAX_FILES, }
+    }
+}
+
+pub struct RotatingLog {
+    path:     PathBuf,
+    rotation: LogRotation,
+    file:     File,
+    size:     u64,
+    opened:   SystemTime,
+}
+
+impl RotatingLog {
+    /// Open `path` for appending, creating its parent directory if needed.
+    pub fn open(path: &Path, rotation: LogRotation) -> io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = Self::open_file(path)?;
+        let metadata = file.metadata()?;
+        let opened = metadata.created()
+                             .or_else(|_| metadata.modified())
+                             .unwrap_or_else(|_| SystemTime::now());
+        Ok(Self { path: path.to_path_buf(),
+                  rotation,
+                  file,
+                  size: metadata.len(),
+                  opened })
+    }
+
+    pub fn path(&self) -> &Path { &self.path }
+
+    /// Append `line` to the log, rotating it first if necessary.
+    pub fn write_line(&mut self, line: &str) -> io::Result<()> {
+        if self.needs_rotation() {
+            self.rotate()?;
+        }
+        writeln!(self.file, "{}", line)?;
+        self.size += line.len() as u64 + 1;
+        Ok(())
+    }
+
+    fn needs_rotation(&self) -> bool {
+        self.size > 0
+        && (self.size >= self.rotation.max_bytes
+            || self.rotation.max_age.map_or(false, |max_age| {
+                                        self.opened.elapsed().unwrap_or_default() >= max_age
+                                    }))
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        let max_files = self.rotation.max_files;
+        remove_if_exists(&self.rotated_path(max_files))?;
+        for n in (1..max_files).rev() {
+            rename_if_exists(&self.rotated_path(n), &self.rotated_path(n + 1))?;
+        }
+        rename_if_exists(&self.path, &self.rotated_path(1))?;
+        self.file = Self::open_file(&self.path)?;
+        self.size = 0;
+        self.opened = SystemTime::now();
+        Ok(())
+    }
+
+    fn rotated_path(&self, n: u32) -> PathBuf {
+        let mut name = self.path.file_name().unwrap_or_default().to_os_string();
+        name.push(format!(".{}", n));
+        self.path.with_file_name(name)
+    }
+
+    fn open_file(path: &Path) -> io::Result<File> {
+        OpenOptions::new().create(true).append(true).open(path)
+    }
+}
+
+fn remove_if_exists(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+fn rename_if_exists(from: &Path, to: &Path) -> io::Result<()> {
+    match fs::rename(from, to) {
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn logs_are_rotated_by_size_and_pruned() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("service.log");
+        let rotation = LogRotation { max_bytes: 10,
+                                     max_age:   None,
+                                     max_files: 2, };
+        let mut log = RotatingLog::open(&path, rotation).unwrap();
+        for line in &["first line", "second line", "third line", "fourth line"] {
+            log.write_line(line).unwrap();
+        }
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "fourth line\n");
+        assert_eq!(fs::read_to_string(dir.path().join("service.log.1")).unwrap(),
+                   "third line\n");
+        assert_eq!(fs::read_to_string(dir.path().join("service.log.2")).unwrap(),
+                   "second line\n");
+        assert!(!dir.path().join("service.log.3").exists());
+    }
+}
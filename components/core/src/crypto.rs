@@ -118,10 +118,15 @@
 //! 1. The last line is left empty, meaning that 2 newline characters (`\n`) separate the header
 //!    from the payload
 //!
-//! The remainder of the file is a compressed tarball of the contents to be extracted on disk. At
-//! present, the tarball is compressed using `xz` but is considered an implementation detail. Also
-//! note unlike the format of keys, the compressed tarball is **not** Base64 encoded--it is the
-//! compressed tarball itself.
+//! The remainder of the file is a compressed tarball of the contents to be extracted on disk. The
+//! tarball of a `HART-1` artifact is compressed using `xz`. Also note unlike the format of keys,
+//! the compressed tarball is **not** Base64 encoded--it is the compressed tarball itself.
+//!
+//! A `HART-2` artifact has one more line in its header, after the hashing algorithm, naming the
+//! compression of the tarball: either `xz` or `zstd`. `zstd` tarballs decompress several times
+//! faster, which speeds up the installation of large packages, but they can only be installed by
+//! Habitat releases that read `HART-2` artifacts. Artifacts compressed with `xz` are still written
+//! in the `HART-1` format.
 //!
 //! Note that the BLAKE2b hash functions use a digest length of 32 bytes (256 bits!). More details
 //! about the hashing strategy can be found in the [libsodium hashing
@@ -154,6 +159,18 @@
 //!
 //! Note that the `abc123` would be a Base64 string in a real file.
 //!
+//! The header of a `HART-2` artifact looks like:
+//!
+//! ```text
+//! HART-2
+//! habitat-20160405144945
+//! BLAKE2b
+//! zstd
+//! signed BLAKE2b signature
+//!
+//! <binary-blob>
+//! ```
+//!
 //! It's also possible to extract a plain compressed tarball from a signed Habitat artifact using
 //! the `tail(1)` Unix command:
 //!
@@ -162,13 +179,14 @@
 //! ```
 //!
 //! The above command starts streaming the file to standard out at line 6, skipping the first 5
-//! plaintext lines.
+//! plaintext lines. The tarball of a `HART-2` artifact starts at line 7.
 //!
 //! If the Habitat artifact needs to be extracted on disk without verifying its integrity or
 //! authenticity, this can be accomplished with:
 //!
 //! ```text
 //! tail -n +6 /tmp/somefile.hart | xzcat | tar x -C /
+//! tail -n +7 /tmp/somefile-zstd.hart | zstdcat | tar x -C /
 //! ```
 //!
 //! **Caution!** Working with Habitat artifacts in this manner this is not normally recommended and
@@ -228,6 +246,7 @@ pub const SIG_HASH_TYPE: &str = "BLAKE2b";
 /// at runtime. This is useful for testing.
 pub const CACHE_KEY_PATH_ENV_VAR: &str = "HAB_CACHE_KEY_PATH";
 pub const HART_FORMAT_VERSION: &str = "HART-1";
+pub const HART_V2_FORMAT_VERSION: &str = "HART-2";
 pub const BOX_FORMAT_VERSION: &str = "BOX-1";
 pub const ANONYMOUS_BOX_FORMAT_VERSION: &str = "ANONYMOUS-BOX-1";
 
@@ -242,6 +261,10 @@ pub mod artifact;
 pub mod dpapi;
 mod hash;
 pub mod keys;
+pub mod provenance;
+pub mod revocation;
+#[cfg(not(windows))]
+pub mod svc_password;
 
 pub use hash::Blake2bHash;
 
@@ -2,24 +2,70 @@ use crate::{crypto::{keys::{Key,
                             KeyCache,
                             NamedRevision,
                             SecretOriginSigningKey},
+                     revocation,
                      Blake2bHash,
                      HART_FORMAT_VERSION,
+                     HART_V2_FORMAT_VERSION,
                      SIG_HASH_TYPE},
             error::{Error,
                     Result}};
-use std::{fs::File,
+use std::{fmt,
+          fs::File,
           io::{self,
                prelude::*,
                BufRead,
                BufReader,
                BufWriter},
-          path::Path};
+          path::Path,
+          str::FromStr};
+use xz2::read::XzDecoder;
+
+/// The algorithm the payload of an artifact is compressed with.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Compression {
+    Xz,
+    Zstd,
+}
+
+impl Compression {
+    /// The compression of a compressed tarball, judging by its file extension: `.zst` files are
+    /// compressed with zstd and all others with xz.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("zst") => Compression::Zstd,
+            _ => Compression::Xz,
+        }
+    }
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Compression::Xz => "xz",
+            Compression::Zstd => "zstd",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Compression {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "xz" => Ok(Compression::Xz),
+            "zstd" => Ok(Compression::Zstd),
+            _ => Err(Error::CryptoError(format!("Unsupported compression: {}", value))),
+        }
+    }
+}
 
 pub struct ArtifactHeader {
-    format:    String,
-    signer:    NamedRevision,
-    hash_type: String,
-    signature: Vec<u8>,
+    format:      String,
+    signer:      NamedRevision,
+    hash_type:   String,
+    compression: Compression,
+    signature:   Vec<u8>,
 }
 
 impl ArtifactHeader {
@@ -29,26 +75,57 @@ impl ArtifactHeader {
 
     pub fn hash_type(&self) -> &String { &self.hash_type }
 
+    /// The compression of the payload, which is always xz for `HART-1` artifacts.
+    pub fn compression(&self) -> Compression { self.compression }
+
     /// Provide the signature as a base64-encoded string. This is how
     /// the signature appears in a HART file header, and is the most
     /// convenient form for passing around to external software.
     pub fn encoded_signature(&self) -> String { base64::encode(&self.signature) }
 }
 
-/// Generate and sign a package
+/// Generate and sign a package from a compressed tarball, which is taken to be compressed with
+/// zstd when its file name ends with `.zst` and with xz otherwise.
 pub fn sign<P1: ?Sized, P2: ?Sized>(src: &P1, dst: &P2, key: &SecretOriginSigningKey) -> Result<()>
     where P1: AsRef<Path>,
           P2: AsRef<Path>
+{
+    sign_compressed(src, dst, key, Compression::from_path(src.as_ref()))
+}
+
+/// Generate and sign a package from a tarball compressed with `compression`. Packages compressed
+/// with xz keep the `HART-1` format that every Habitat release can read, while the `HART-2`
+/// format names the compression in its header.
+pub fn sign_compressed<P1: ?Sized, P2: ?Sized>(src: &P1,
+                                               dst: &P2,
+                                               key: &SecretOriginSigningKey,
+                                               compression: Compression)
+                                               -> Result<()>
+    where P1: AsRef<Path>,
+          P2: AsRef<Path>
 {
     let signature = key.sign(src)?;
     let output_file = File::create(dst)?;
     let mut writer = BufWriter::new(&output_file);
-    write!(writer,
-           "{}\n{}\n{}\n{}\n\n",
-           HART_FORMAT_VERSION,
-           key.named_revision(),
-           SIG_HASH_TYPE,
-           base64::encode(&signature))?;
+    match compression {
+        Compression::Xz => {
+            write!(writer,
+                   "{}\n{}\n{}\n{}\n\n",
+                   HART_FORMAT_VERSION,
+                   key.named_revision(),
+                   SIG_HASH_TYPE,
+                   base64::encode(&signature))?
+        }
+        Compression::Zstd => {
+            write!(writer,
+                   "{}\n{}\n{}\n{}\n{}\n\n",
+                   HART_V2_FORMAT_VERSION,
+                   key.named_revision(),
+                   SIG_HASH_TYPE,
+                   compression,
+                   base64::encode(&signature))?
+        }
+    }
     let mut file = File::open(src)?;
     io::copy(&mut file, &mut writer)?;
     Ok(())
@@ -62,6 +139,17 @@ pub fn get_archive_reader<P>(src: P) -> Result<impl BufRead>
     Ok(reader)
 }
 
+/// return a reader of the decompressed .tar bytestream, skipping the signed header
+pub fn get_archive_decoder<P>(src: P) -> Result<Box<dyn Read + Send>>
+    where P: AsRef<Path>
+{
+    let (header, reader) = artifact_header_and_archive(src)?;
+    match header.compression {
+        Compression::Xz => Ok(Box::new(XzDecoder::new(reader))),
+        Compression::Zstd => Ok(Box::new(zstd::stream::read::Decoder::with_buffer(reader)?)),
+    }
+}
+
 /// Read only the header of the artifact, fails if any of the components
 /// are invalid/missing. Each component of the header has it's whitespace
 /// stripped before returning in an `ArtifactHeader` struct
@@ -72,7 +160,7 @@ pub fn get_artifact_header<P>(src: P) -> Result<ArtifactHeader>
     Ok(header)
 }
 
-fn artifact_header_and_archive<P>(path: P) -> Result<(ArtifactHeader, impl BufRead)>
+fn artifact_header_and_archive<P>(path: P) -> Result<(ArtifactHeader, BufReader<File>)>
     where P: AsRef<Path>
 {
     let f = File::open(path)?;
@@ -86,7 +174,7 @@ fn artifact_header_and_archive<P>(path: P) -> Result<(ArtifactHeader, impl BufRe
                                         .to_string()))
     } else {
         let line = line.trim();
-        if line != HART_FORMAT_VERSION {
+        if line != HART_FORMAT_VERSION && line != HART_V2_FORMAT_VERSION {
             Err(Error::CryptoError(format!("Unsupported format version: \
                                             {}",
                                            line)))
@@ -121,7 +209,21 @@ fn artifact_header_and_archive<P>(path: P) -> Result<(ArtifactHeader, impl BufRe
         }
     }?;
 
-    // Fourth line is the base64-encoded signature.
+    // `HART-2` artifacts name the compression of their payload next.
+    let compression = if format == HART_V2_FORMAT_VERSION {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            Err(Error::CryptoError("Corrupt payload, can't read \
+                                    compression"
+                                                .to_string()))
+        } else {
+            line.trim().parse()
+        }?
+    } else {
+        Compression::Xz
+    };
+
+    // Then comes the base64-encoded signature.
     let mut line = String::new();
     let signature = if reader.read_line(&mut line)? == 0 {
         Err(Error::CryptoError("Corrupt payload, can't read signature".to_string()))
@@ -132,7 +234,7 @@ fn artifact_header_and_archive<P>(path: P) -> Result<(ArtifactHeader, impl BufRe
                             })
     }?;
 
-    // Fifth line should be an empty delimiter line.
+    // The header ends with an empty delimiter line.
     let mut line = String::new();
     if reader.read_line(&mut line)? == 0 {
         Err(Error::CryptoError("Corrupt payload, can't find end of \
@@ -156,6 +258,7 @@ fn artifact_header_and_archive<P>(path: P) -> Result<(ArtifactHeader, impl BufRe
     let header = ArtifactHeader { format,
                                   signer: named_revision,
                                   hash_type,
+                                  compression,
                                   signature };
 
     Ok((header, reader))
@@ -167,6 +270,7 @@ pub fn verify<P>(hart_file_path: P, cache: &KeyCache) -> Result<(NamedRevision,
     where P: AsRef<Path>
 {
     let (header, mut reader) = artifact_header_and_archive(hart_file_path)?;
+    revocation::ensure_not_revoked(cache, &header.signer)?;
     let key = cache.public_signing_key(&header.signer)?;
     let hash = key.verify(header.signature.as_slice(), &mut reader)?;
     Ok((key.named_revision().clone(), hash))
@@ -185,6 +289,7 @@ pub fn artifact_signer<P>(hart_file_path: P) -> Result<NamedRevision>
 mod test {
     use super::{super::{test_support::*,
                         HART_FORMAT_VERSION,
+                        HART_V2_FORMAT_VERSION,
                         SIG_HASH_TYPE},
                 *};
 
@@ -383,6 +488,89 @@ mod test {
         assert!(!hart_header.encoded_signature().is_empty());
     }
 
+    #[test]
+    fn zstd_artifacts_are_hart_2() {
+        let (cache, dir) = new_cache();
+        let origin = "unicorn".parse().unwrap();
+        let (_public, secret) = cache.new_signing_pair(&origin).unwrap();
+
+        let src = dir.path().join("src.tar.zst");
+        let dst = dir.path().join("src.signed");
+        let compressed = zstd::stream::encode_all(&b"hearty goodness"[..], 0).unwrap();
+        File::create(&src).unwrap().write_all(&compressed).unwrap();
+        sign(&src, &dst, &secret).unwrap();
+
+        let hart_header = get_artifact_header(&dst).unwrap();
+        assert_eq!(HART_V2_FORMAT_VERSION, hart_header.format());
+        assert_eq!(Compression::Zstd, hart_header.compression());
+        assert!(verify(&dst, &cache).is_ok());
+
+        let mut buffer = String::new();
+        get_archive_decoder(&dst).unwrap()
+                                 .read_to_string(&mut buffer)
+                                 .unwrap();
+        assert_eq!(buffer.as_bytes(), b"hearty goodness");
+    }
+
+    #[test]
+    fn xz_artifacts_stay_hart_1() {
+        let (cache, dir) = new_cache();
+        let origin = "unicorn".parse().unwrap();
+        let (_public, secret) = cache.new_signing_pair(&origin).unwrap();
+
+        let src = dir.path().join("src.tar.xz");
+        let dst = dir.path().join("src.signed");
+        File::create(&src).unwrap()
+                          .write_all(b"hearty goodness")
+                          .unwrap();
+        sign(&src, &dst, &secret).unwrap();
+
+        let hart_header = get_artifact_header(&dst).unwrap();
+        assert_eq!(HART_FORMAT_VERSION, hart_header.format());
+        assert_eq!(Compression::Xz, hart_header.compression());
+    }
+
+    #[test]
+    #[should_panic(expected = "Unsupported compression: lzma")]
+    fn verify_invalid_compression() {
+        let (cache, dir) = new_cache();
+        let origin = "unicorn".parse().unwrap();
+        let (public, _secret) = cache.new_signing_pair(&origin).unwrap();
+
+        let dst = dir.path().join("signed.dat");
+        let mut f = File::create(&dst).unwrap();
+        f.write_all(format!("HART-2\n{}\nBLAKE2b\nlzma\nU3VycHJpc2Uh\n\nuhoh",
+                            public.named_revision()).as_bytes())
+         .unwrap();
+
+        verify(&dst, &cache).unwrap();
+    }
+
+    #[test]
+    fn verify_rejects_revoked_signers() {
+        let (cache, dir) = new_cache();
+        let origin = "unicorn".parse().unwrap();
+        let (public, secret) = cache.new_signing_pair(&origin).unwrap();
+        wait_1_sec();
+        let (_, newer) = cache.new_signing_pair(&origin).unwrap();
+
+        let src = dir.path().join("src.in");
+        let dst = dir.path().join("src.signed");
+        File::create(&src).unwrap()
+                          .write_all(b"hearty goodness")
+                          .unwrap();
+        sign(&src, &dst, &secret).unwrap();
+        verify(&dst, &cache).unwrap();
+
+        let mut list = revocation::RevocationList::from_cache(&cache, &origin).unwrap();
+        list.revoke(public.named_revision(), None).unwrap();
+        revocation::import(&list.sign(&newer).unwrap(), &cache).unwrap();
+        match verify(&dst, &cache) {
+            Err(Error::RevokedKey(..)) => {}
+            result => panic!("Expected RevokedKey, got {:?}", result),
+        }
+    }
+
     mod artifact_header {
         use super::*;
 
@@ -19,11 +19,15 @@ lazy_static::lazy_static! {
 mod util;
 mod cache;
 mod encryption;
+mod host_key;
 mod ring_key;
 mod signing;
 
 pub use cache::KeyCache;
 pub use encryption::*;
+pub use host_key::{HostBox,
+                   HostKey,
+                   HOST_KEY_NAME};
 pub use ring_key::RingKey;
 pub use signing::{generate_signing_key_pair,
                   PublicOriginSigningKey,
@@ -170,8 +174,8 @@ impl fmt::Display for NamedRevision {
 
 ////////////////////////////////////////////////////////////////////////
 
-/// A timestamp string used to identify Habitat keys.
-#[derive(Clone, Debug, PartialEq, Eq)]
+/// A timestamp string used to identify Habitat keys. Revisions are ordered from oldest to newest.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct KeyRevision(String);
 
 impl KeyRevision {
@@ -5,6 +5,8 @@ use crate::{crypto::{hash::Blake2bHash,
                                          BUILDER_KEY_NAME},
                             generate_signing_key_pair,
                             BuilderSecretEncryptionKey,
+                            HostKey,
+                            Key,
                             KeyFile,
                             NamedRevision,
                             OriginPublicEncryptionKey,
@@ -21,11 +23,17 @@ use crate::{crypto::{hash::Blake2bHash,
             fs::AtomicWriter,
             origin::Origin};
 use serde::Deserialize;
-use std::{convert::TryFrom,
+use std::{collections::BTreeMap,
+          convert::TryFrom,
+          fs,
           io::Write,
           path::{Path,
                  PathBuf}};
 
+/// The file in the key cache which records the fingerprint of every public origin signing key
+/// when it is first downloaded.
+const PINNED_KEYS_FILE: &str = "pinned_keys";
+
 /// Represents the location of all Habitat keys (user, service,
 /// origin, signing, and ring) locally on disk, as well as the APIs
 /// for retrieving and storing keys.
@@ -61,6 +69,13 @@ impl KeyCache {
         Ok(key)
     }
 
+    /// Generate a new host key and save it to disk.
+    pub fn new_host_key(&self, name: &str) -> Result<HostKey> {
+        let key = HostKey::new(name);
+        self.write_key(&key)?;
+        Ok(key)
+    }
+
     /// Generate a new origin signing key pair and save both keys to disk.
     pub fn new_signing_pair(&self,
                             origin: &Origin)
@@ -170,6 +185,11 @@ impl KeyCache {
         self.fetch_latest_revision::<RingKey>(name)
     }
 
+    /// Note: name is just the name, not the name + revision
+    pub fn latest_host_key_revision(&self, name: &str) -> Result<HostKey> {
+        self.fetch_latest_revision::<HostKey>(name)
+    }
+
     pub fn latest_secret_origin_signing_key(&self,
                                             origin: &Origin)
                                             -> Result<SecretOriginSigningKey> {
@@ -182,10 +202,49 @@ impl KeyCache {
         self.fetch_latest_revision::<PublicOriginSigningKey>(origin.as_ref())
     }
 
+    /// All revisions of the public signing key of `origin` in the cache, oldest first.
+    pub fn public_origin_signing_key_revisions(&self,
+                                               origin: &Origin)
+                                               -> Result<Vec<PublicOriginSigningKey>> {
+        self.fetch_all_revisions::<PublicOriginSigningKey>(origin.as_ref())
+    }
+
+    /// All revisions of the secret signing key of `origin` in the cache, oldest first.
+    pub fn secret_origin_signing_key_revisions(&self,
+                                               origin: &Origin)
+                                               -> Result<Vec<SecretOriginSigningKey>> {
+        self.fetch_all_revisions::<SecretOriginSigningKey>(origin.as_ref())
+    }
+
     pub fn latest_user_secret_key(&self, user_name: &str) -> Result<UserSecretEncryptionKey> {
         self.fetch_latest_revision::<UserSecretEncryptionKey>(user_name)
     }
 
+    pub fn latest_user_public_key(&self, user_name: &str) -> Result<UserPublicEncryptionKey> {
+        self.fetch_latest_revision::<UserPublicEncryptionKey>(user_name)
+    }
+
+    /// All user public keys in the cache, oldest revision of each user first, optionally only
+    /// those of `user_name`. Service keys, whose names contain an `@`, are left out.
+    pub fn user_public_keys(&self,
+                            user_name: Option<&str>)
+                            -> Result<Vec<UserPublicEncryptionKey>> {
+        let mut keys = match user_name {
+            Some(user_name) => self.fetch_all_revisions::<UserPublicEncryptionKey>(user_name)?,
+            None => {
+                let mut paths =
+                    self.get_all_paths_for("*", <UserPublicEncryptionKey as KeyFile>::extension())?
+                        .collect::<Vec<_>>();
+                paths.sort();
+                paths.into_iter()
+                     .filter_map(|path| UserPublicEncryptionKey::try_from(path).ok())
+                     .collect()
+            }
+        };
+        keys.retain(|key| !key.named_revision().name().contains('@'));
+        Ok(keys)
+    }
+
     pub fn latest_origin_public_encryption_key(&self,
                                                origin: &Origin)
                                                -> Result<OriginPublicEncryptionKey> {
@@ -219,6 +278,16 @@ impl KeyCache {
         self.fetch_specific_revision::<SecretOriginSigningKey>(named_revision)
     }
 
+    pub fn host_key(&self, named_revision: &NamedRevision) -> Result<HostKey> {
+        self.fetch_specific_revision::<HostKey>(named_revision)
+    }
+
+    pub fn user_secret_encryption_key(&self,
+                                      named_revision: &NamedRevision)
+                                      -> Result<UserSecretEncryptionKey> {
+        self.fetch_specific_revision::<UserSecretEncryptionKey>(named_revision)
+    }
+
     pub fn user_public_encryption_key(&self,
                                       named_revision: &NamedRevision)
                                       -> Result<UserPublicEncryptionKey> {
@@ -239,8 +308,67 @@ impl KeyCache {
         self.fetch_specific_revision::<BuilderSecretEncryptionKey>(named_revision)
     }
 
+    /// Check a freshly downloaded public signing key against the fingerprint recorded the first
+    /// time a key with that named revision was downloaded, recording the fingerprint if there is
+    /// none yet. This guards against Builder (or anything between us and Builder) substituting a
+    /// different key for a revision we have already trusted.
+    ///
+    /// If the fingerprints differ and `accept_new_key` is not set, the downloaded key is removed
+    /// from the cache and an error is returned. Otherwise the new fingerprint replaces the old.
+    pub fn pin_public_signing_key(&self,
+                                  named_revision: &NamedRevision,
+                                  accept_new_key: bool)
+                                  -> Result<()> {
+        let key = self.public_signing_key(named_revision)?;
+        let fingerprint = Blake2bHash::from_bytes(key.key()).to_string();
+        let mut pins = self.pinned_keys()?;
+        match pins.get(&named_revision.to_string()).cloned() {
+            Some(pinned) if pinned == fingerprint => Ok(()),
+            Some(pinned) if !accept_new_key => {
+                fs::remove_file(self.path_in_cache(&key))?;
+                Err(Error::PinnedKeyMismatch(named_revision.to_string(),
+                                             pinned,
+                                             fingerprint))
+            }
+            _ => {
+                pins.insert(named_revision.to_string(), fingerprint);
+                self.write_pinned_keys(&pins)
+            }
+        }
+    }
+
     ////////////////////////////////////////////////////////////////////////
 
+    /// Read the recorded key fingerprints. Each line of the file holds a named revision and the
+    /// fingerprint of its key, separated by whitespace.
+    fn pinned_keys(&self) -> Result<BTreeMap<String, String>> {
+        let path = self.0.join(PINNED_KEYS_FILE);
+        if !path.is_file() {
+            return Ok(BTreeMap::new());
+        }
+        Ok(fs::read_to_string(&path)?.lines()
+                                     .filter_map(|line| {
+                                         let mut parts = line.split_whitespace();
+                                         match (parts.next(), parts.next()) {
+                                             (Some(name), Some(fingerprint)) => {
+                                                 Some((name.to_string(), fingerprint.to_string()))
+                                             }
+                                             _ => None,
+                                         }
+                                     })
+                                     .collect())
+    }
+
+    fn write_pinned_keys(&self, pins: &BTreeMap<String, String>) -> Result<()> {
+        let content = pins.iter()
+                          .map(|(name, fingerprint)| format!("{} {}\n", name, fingerprint))
+                          .collect::<String>();
+        let w = AtomicWriter::new_with_permissions(&self.0.join(PINNED_KEYS_FILE),
+                                                   PublicOriginSigningKey::permissions())?;
+        w.with_writer(|f| f.write_all(content.as_bytes()))?;
+        Ok(())
+    }
+
     /// Write a pair of keys to the cache.
     fn write_pair<P, S>(&self, public: &P, secret: &S) -> Result<()>
         where P: KeyFile,
@@ -284,6 +412,20 @@ impl KeyCache {
         }
     }
 
+    /// Fetch every valid revision of the key of type `K` named `name` from the cache, oldest
+    /// first. Keys of other names that merely start with `name` are left out.
+    fn fetch_all_revisions<K>(&self, name: &str) -> Result<Vec<K>>
+        where K: KeyFile + TryFrom<PathBuf, Error = Error>
+    {
+        let mut paths = self.get_all_paths_for(name, <K as KeyFile>::extension())?
+                            .collect::<Vec<_>>();
+        paths.sort();
+        Ok(paths.into_iter()
+                .filter_map(|path| <K as TryFrom<PathBuf>>::try_from(path).ok())
+                .filter(|key| key.named_revision().name() == name)
+                .collect())
+    }
+
     /// Generic retrieval function to grab the key of the specified
     /// type `K` identified by `named_revision`
     fn fetch_specific_revision<K>(&self, named_revision: &NamedRevision) -> Result<K>
@@ -397,6 +539,43 @@ mod test {
         assert!(paths.contains(&k2.own_filename()));
     }
 
+    #[test]
+    fn pin_public_signing_key_rejects_substituted_keys() {
+        let (cache, _dir) = new_cache();
+        let origin = "acme".parse::<Origin>().unwrap();
+        let (public, _secret) = generate_signing_key_pair(&origin);
+        cache.write_key(&public).unwrap();
+        cache.pin_public_signing_key(public.named_revision(), false)
+             .unwrap();
+        // Pinning the same key again is fine
+        cache.pin_public_signing_key(public.named_revision(), false)
+             .unwrap();
+
+        // Swap in a different key claiming the same named revision
+        let (other, _secret) = generate_signing_key_pair(&origin);
+        let substitute = other.to_key_string()
+                              .replace(&other.named_revision().to_string(),
+                                       &public.named_revision().to_string());
+        let key_path = cache.path_in_cache(&public);
+        std::fs::write(&key_path, substitute).unwrap();
+
+        match cache.pin_public_signing_key(public.named_revision(), false) {
+            Err(Error::PinnedKeyMismatch(..)) => {}
+            result => panic!("Expected PinnedKeyMismatch, got {:?}", result),
+        }
+        assert!(!key_path.exists());
+
+        // The new key is trusted once explicitly accepted
+        std::fs::write(&key_path,
+                       other.to_key_string()
+                            .replace(&other.named_revision().to_string(),
+                                     &public.named_revision().to_string())).unwrap();
+        cache.pin_public_signing_key(public.named_revision(), true)
+             .unwrap();
+        cache.pin_public_signing_key(public.named_revision(), false)
+             .unwrap();
+    }
+
     #[test]
     #[should_panic(expected = "No revisions found for")]
     fn latest_cached_revision_nonexistent() {
@@ -524,6 +703,44 @@ mod test {
         assert_cache_round_trip!(SecretOriginSigningKey, secret, cache);
     }
 
+    #[test]
+    fn signing_key_revisions_of_an_origin() {
+        let (cache, _dir) = new_cache();
+        let origin = "my-org".parse().unwrap();
+        let (public, secret) = generate_signing_key_pair(&origin);
+        cache.write_pair(&public, &secret).unwrap();
+        let (other_public, _) = generate_signing_key_pair(&"my-org-other".parse().unwrap());
+        cache.write_key(&other_public).unwrap();
+
+        let publics = cache.public_origin_signing_key_revisions(&origin).unwrap();
+        assert_eq!(publics.len(), 1);
+        assert_eq!(publics[0].named_revision(), public.named_revision());
+        let secrets = cache.secret_origin_signing_key_revisions(&origin).unwrap();
+        assert_eq!(secrets.len(), 1);
+        assert_eq!(secrets[0].named_revision(), secret.named_revision());
+    }
+
+    #[test]
+    fn user_public_keys_leave_out_service_keys() {
+        let (cache, _dir) = new_cache();
+        let (korben, _) = generate_user_encryption_key_pair("korben");
+        cache.write_key(&korben).unwrap();
+        let (leeloo, _) = generate_user_encryption_key_pair("leeloo");
+        cache.write_key(&leeloo).unwrap();
+        let (service, _) = generate_service_encryption_key_pair("my-org", "foo.default");
+        cache.write_key(&service).unwrap();
+
+        let names = |keys: Vec<UserPublicEncryptionKey>| {
+            keys.iter()
+                .map(|key| key.named_revision().name().clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(cache.user_public_keys(None).unwrap()),
+                   vec!["korben", "leeloo"]);
+        assert_eq!(names(cache.user_public_keys(Some("leeloo")).unwrap()),
+                   vec!["leeloo"]);
+    }
+
     mod write_pair {
         use super::*;
 
@@ -69,6 +69,34 @@ impl UserSecretEncryptionKey {
                        ciphertext,
                        nonce)
     }
+
+    /// Encrypt some data with a user's private key for decryption by
+    /// a receiving user's private key.
+    pub fn encrypt_for_user(&self,
+                            data: &[u8],
+                            receiving_user: &UserPublicEncryptionKey)
+                            -> SignedBox {
+        let nonce = primitives::gen_nonce();
+        let ciphertext = primitives::seal(data, &nonce, receiving_user.key(), self.key());
+        SignedBox::new(self.named_revision.clone(),
+                       receiving_user.named_revision().clone(),
+                       ciphertext,
+                       nonce)
+    }
+
+    /// Decrypt a boxed message sent from another user to this one.
+    pub fn decrypt_user_message(&self,
+                                signed_box: &SignedBox,
+                                sending_user: &UserPublicEncryptionKey)
+                                -> Result<Vec<u8>> {
+        primitives::open(signed_box.ciphertext(),
+                         signed_box.nonce(),
+                         sending_user.key(),
+                         self.key()).map_err(|_| {
+            Error::CryptoError("Secret key, public key, and nonce could not decrypt ciphertext"
+                               .to_string())
+        })
+    }
 }
 
 #[cfg(test)]
@@ -94,6 +122,26 @@ mod tests {
         assert_eq!(signed.decryptor(), service.named_revision());
     }
 
+    #[test]
+    fn user_to_user_roundtrip() {
+        let sender_public: UserPublicEncryptionKey =
+            fixture_key("keys/ruby-rhod-20200813204159.pub");
+        let sender_secret: UserSecretEncryptionKey =
+            fixture_key("keys/ruby-rhod-20200813204159.box.key");
+        let (receiver_public, receiver_secret) = generate_user_encryption_key_pair("korben");
+
+        let message = "Multipass!".to_string();
+        let signed = sender_secret.encrypt_for_user(message.as_bytes(), &receiver_public);
+        assert_eq!(signed.encryptor(), sender_public.named_revision());
+        assert_eq!(signed.decryptor(), receiver_public.named_revision());
+
+        let decrypted = receiver_secret.decrypt_user_message(&signed, &sender_public)
+                                       .unwrap();
+        assert_eq!(decrypted, message.as_bytes());
+        assert!(sender_secret.decrypt_user_message(&signed, &sender_public)
+                             .is_err());
+    }
+
     // Choosing to put the "round trip" encryption test over in
     // `service_key.rs`, since it involves both user and service, and
     // service is the one that does the decrypting.
@@ -0,0 +1,209 @@
+use crate::{crypto::{keys::NamedRevision,
+                     SECRET_SYM_KEY_VERSION},
+            error::{Error,
+                    Result},
+            fs::Permissions};
+use std::{fmt,
+          str::FromStr};
+
+/// Version identifier for messages encrypted with a host key.
+const HOST_BOX_FORMAT_VERSION: &str = "HOST-BOX-1";
+
+/// The name of the key in the Supervisor's key cache that it encrypts files at rest with.
+pub const HOST_KEY_NAME: &str = "host";
+
+/// Private module to re-export the various sodiumoxide concepts we
+/// use, to keep them all consolidated and abstracted.
+mod primitives {
+    pub use sodiumoxide::crypto::secretbox::{self,
+                                             gen_key,
+                                             gen_nonce,
+                                             open,
+                                             seal,
+                                             Key,
+                                             Nonce};
+}
+
+gen_key!(
+    /// Symmetric secret that never leaves the host it was generated
+    /// on, used by the Supervisor to encrypt the files it keeps on
+    /// disk, such as service specs.
+    HostKey,
+         key_material: primitives::Key,
+         file_format_version: SECRET_SYM_KEY_VERSION,
+         file_extension: "host.key",
+         file_permissions: crate::fs::DEFAULT_SECRET_KEY_PERMISSIONS);
+
+impl HostKey {
+    /// Generate a new `HostKey` for the given name. Creates a new
+    /// key, but does not write anything to the filesystem.
+    pub fn new(name: &str) -> Self {
+        let named_revision = NamedRevision::new(name.to_string());
+        let key = primitives::gen_key();
+        HostKey { named_revision,
+                  key }
+    }
+
+    /// Encrypts a sequence of bytes.
+    ///
+    /// The return is a tuple of `Vec<u8>`s, the first being a random
+    /// nonce value and the second being the ciphertext. Both are
+    /// needed to decrypt the message.
+    pub fn encrypt(&self, data: &[u8]) -> (Vec<u8>, Vec<u8>) {
+        let nonce = primitives::gen_nonce();
+        (nonce.as_ref().to_vec(), primitives::seal(data, &nonce, &self.key))
+    }
+
+    /// Decrypts a ciphertext using a given nonce value.
+    ///
+    /// The returns the original unencrypted bytes.
+    pub fn decrypt(&self, nonce: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>> {
+        let nonce = primitives::Nonce::from_slice(&nonce).ok_or_else(|| {
+                                                             Error::CryptoError("Invalid size of \
+                                                                                 nonce"
+                                                                                       .to_string())
+                                                         })?;
+
+        primitives::open(ciphertext, &nonce, &self.key).map_err(|_| {
+            Error::CryptoError("Secret key and nonce could not decrypt ciphertext".to_string())
+        })
+    }
+
+    /// Encrypts a sequence of bytes into a message that records
+    /// which key it was encrypted with, suitable for writing to disk.
+    pub fn seal(&self, data: &[u8]) -> HostBox {
+        let (nonce, ciphertext) = self.encrypt(data);
+        HostBox { key: self.named_revision.clone(),
+                  nonce,
+                  ciphertext }
+    }
+
+    /// Decrypts a message encrypted with this key.
+    pub fn open(&self, message: &HostBox) -> Result<Vec<u8>> {
+        if message.key != self.named_revision {
+            return Err(Error::CryptoError(format!("Message was encrypted with \
+                                                   host key {}, not {}",
+                                                  message.key,
+                                                  self.named_revision)));
+        }
+        self.decrypt(&message.nonce, &message.ciphertext)
+    }
+}
+
+/// A message encrypted with a host key.
+#[derive(Debug)]
+pub struct HostBox {
+    /// The host key that was used to encrypt, and thus must also be
+    /// used to decrypt, this message.
+    key:        NamedRevision,
+    /// The cryptographic nonce used to encrypt the message.
+    nonce:      Vec<u8>,
+    /// The encrypted ciphertext of the message.
+    ciphertext: Vec<u8>,
+}
+
+impl HostBox {
+    pub fn key(&self) -> &NamedRevision { &self.key }
+
+    /// Whether `content` looks like a message encrypted with a host
+    /// key, as opposed to plaintext.
+    pub fn is_host_box(content: &str) -> bool {
+        content.lines().next() == Some(HOST_BOX_FORMAT_VERSION)
+    }
+}
+
+impl fmt::Display for HostBox {
+    /// The version and key identifier are in plaintext, while the
+    /// nonce and ciphertext are base64 encoded.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f,
+               "{}\n{}\n{}\n{}",
+               HOST_BOX_FORMAT_VERSION,
+               self.key,
+               base64::encode(&self.nonce),
+               base64::encode(&self.ciphertext))
+    }
+}
+
+impl FromStr for HostBox {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut lines = s.lines();
+
+        lines.next()
+             .ok_or_else(|| Error::CryptoError("Corrupt payload, can't read version".to_string()))
+             .map(|line| {
+                 if line == HOST_BOX_FORMAT_VERSION {
+                     Ok(line)
+                 } else {
+                     Err(Error::CryptoError(format!("Unsupported version: {}", line)))
+                 }
+             })??;
+
+        let key = lines.next()
+                       .ok_or_else(|| {
+                           Error::CryptoError("Corrupt payload, can't read host key identifier"
+                                                                                  .to_string())
+                       })?
+                       .parse()?;
+
+        let nonce =
+            lines.next()
+                 .ok_or_else(|| Error::CryptoError("Corrupt payload, can't read nonce".to_string()))
+                 .map(base64::decode)?
+                 .map_err(|e| Error::CryptoError(format!("Can't decode nonce: {}", e)))?;
+
+        let ciphertext =
+            lines.next()
+                 .ok_or_else(|| {
+                     Error::CryptoError("Corrupt payload, can't read ciphertext".to_string())
+                 })
+                 .map(base64::decode)?
+                 .map_err(|e| Error::CryptoError(format!("Can't decode ciphertext: {}", e)))?;
+
+        Ok(HostBox { key,
+                     nonce,
+                     ciphertext })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::keys::{Key,
+                              KeyFile};
+
+    #[test]
+    fn key_string_roundtrip() {
+        let key = HostKey::new("host");
+        let parsed: HostKey = key.to_key_string().parse().unwrap();
+        assert_eq!(parsed, key);
+    }
+
+    #[test]
+    fn encryption_roundtrip() {
+        let key = HostKey::new("host");
+        let (nonce, ciphertext) = key.encrypt(b"password = \"hunter2\"");
+        let decrypted_message = key.decrypt(&nonce, &ciphertext).unwrap();
+        assert_eq!(decrypted_message, b"password = \"hunter2\"");
+    }
+
+    #[test]
+    fn host_box_roundtrip() {
+        let key = HostKey::new("host");
+        let message = key.seal(b"password = \"hunter2\"").to_string();
+        assert!(HostBox::is_host_box(&message));
+        assert!(!HostBox::is_host_box("password = \"hunter2\""));
+        let message: HostBox = message.parse().unwrap();
+        assert_eq!(message.key(), key.named_revision());
+        assert_eq!(key.open(&message).unwrap(), b"password = \"hunter2\"");
+    }
+
+    #[test]
+    #[should_panic(expected = "Secret key and nonce could not decrypt ciphertext")]
+    fn decrypt_with_another_key() {
+        let (nonce, ciphertext) = HostKey::new("host").encrypt(b"password = \"hunter2\"");
+        HostKey::new("host").decrypt(&nonce, &ciphertext).unwrap();
+    }
+}
@@ -16,7 +16,10 @@ mod primitives {
                                                   SecretKey},
                                         gen_keypair,
                                         sign,
-                                        verify};
+                                        sign_detached,
+                                        verify,
+                                        verify_detached,
+                                        Signature};
 }
 
 /// Given the name of an origin, generate a new signing key pair.
@@ -70,6 +73,19 @@ impl PublicOriginSigningKey {
             Err(Error::CryptoError(msg))
         }
     }
+
+    /// Verify a detached `signature` of `message`, as made by
+    /// `SecretOriginSigningKey::sign_detached`.
+    pub fn verify_detached(&self, signature: &[u8], message: &[u8]) -> Result<()> {
+        let signature = primitives::Signature::from_slice(signature).ok_or_else(|| {
+                            Error::CryptoError("Invalid signature".to_string())
+                        })?;
+        if primitives::verify_detached(&signature, message, &self.key) {
+            Ok(())
+        } else {
+            Err(Error::CryptoError("Verification failed".to_string()))
+        }
+    }
 }
 
 ////////////////////////////////////////////////////////////////////////
@@ -100,6 +116,13 @@ impl SecretOriginSigningKey {
         Ok(self.sign_inner(hex_encoded_hash.to_string().as_bytes()))
     }
 
+    /// Sign `message`, returning only the signature. Unlike with `sign`, the message can't be
+    /// recovered from it.
+    pub fn sign_detached(&self, message: &[u8]) -> Vec<u8> {
+        primitives::sign_detached(message, &self.key).as_ref()
+                                                     .to_vec()
+    }
+
     /// Does the actual heavy lifting of signing a string of bytes.
     ///
     /// Mainly separate to facilitate testing.
@@ -0,0 +1,395 @@
+//! Provenance attestations for Habitat artifacts.
+//!
+//! An attestation records how an artifact was built: the builder that built it, the source
+//! revision its plan was built from, and the packages and sources that went into the build. It is
+//! an [in-toto Statement][1] with a [SLSA Provenance][2] predicate, signed with an origin signing
+//! key in a [DSSE envelope][3]. The attestation of an artifact is kept next to it, in a file named
+//! after the artifact with an `.intoto.json` suffix:
+//!
+//! ```text
+//! acme-redis-3.0.7-21120102031201-x86_64-linux.hart
+//! acme-redis-3.0.7-21120102031201-x86_64-linux.hart.intoto.json
+//! ```
+//!
+//! [1]: https://github.com/in-toto/attestation/blob/main/spec/v0.1.0/statement.md
+//! [2]: https://slsa.dev/provenance/v0.2
+//! [3]: https://github.com/secure-systems-lab/dsse
+
+use crate::{crypto::{artifact,
+                     keys::{Key,
+                            KeyCache,
+                            NamedRevision,
+                            SecretOriginSigningKey},
+                     revocation},
+            error::{Error,
+                    Result},
+            package::{PackageArchive,
+                      PackageIdent}};
+use ::crypto::{digest::Digest,
+               sha2::Sha256};
+use serde_derive::{Deserialize,
+                   Serialize};
+use std::{collections::{BTreeMap,
+                        HashSet},
+          fmt,
+          fs::{self,
+               File},
+          io::Read,
+          path::{Path,
+                 PathBuf}};
+
+pub const STATEMENT_TYPE: &str = "https://in-toto.io/Statement/v0.1";
+pub const PREDICATE_TYPE: &str = "https://slsa.dev/provenance/v0.2";
+/// The type of the payload of the DSSE envelope an attestation is signed in
+pub const PAYLOAD_TYPE: &str = "application/vnd.in-toto+json";
+/// How Habitat artifacts are built, as recorded in their provenance
+pub const BUILD_TYPE: &str = "https://habitat.sh/hab-plan-build@v1";
+/// The suffix of the name of the file holding the attestation of an artifact
+pub const PROVENANCE_SUFFIX: &str = ".intoto.json";
+
+/// Digests of the same content, by the name of their algorithm.
+pub type DigestSet = BTreeMap<String, String>;
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Statement {
+    #[serde(rename = "_type")]
+    pub statement_type: String,
+    pub subject:        Vec<Subject>,
+    #[serde(rename = "predicateType")]
+    pub predicate_type: String,
+    pub predicate:      Provenance,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Subject {
+    pub name:   String,
+    pub digest: DigestSet,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Provenance {
+    pub builder:    Builder,
+    pub build_type: String,
+    #[serde(default)]
+    pub invocation: Invocation,
+    #[serde(default)]
+    pub materials:  Vec<Material>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Builder {
+    pub id: String,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Invocation {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config_source: Option<ConfigSource>,
+}
+
+/// The source the plan of an artifact was built from, such as a git repository and revision.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigSource {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uri:         Option<String>,
+    #[serde(default, skip_serializing_if = "DigestSet::is_empty")]
+    pub digest:      DigestSet,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entry_point: Option<String>,
+}
+
+/// An input of a build: a package the artifact was built with, or a source it was built from.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Material {
+    pub uri:    String,
+    #[serde(default, skip_serializing_if = "DigestSet::is_empty")]
+    pub digest: DigestSet,
+}
+
+impl Statement {
+    /// The provenance of `archive`, built by `builder_id` from `config_source`. The build inputs
+    /// are the source and dependencies recorded in the artifact's metadata.
+    pub fn new(archive: &mut PackageArchive,
+               builder_id: &str,
+               config_source: Option<ConfigSource>)
+               -> Result<Self> {
+        let mut materials = Vec::new();
+        if let Some(material) = source_material(archive.manifest()?) {
+            materials.push(material);
+        }
+        let mut seen = HashSet::new();
+        for dep in archive.tdeps()?.into_iter().chain(archive.build_tdeps()?) {
+            if seen.insert(dep.clone()) {
+                materials.push(Material { uri:    package_uri(&dep),
+                                          digest: DigestSet::new(), });
+            }
+        }
+        let subject = Subject { name:   archive.file_name(),
+                                digest: digests(&archive.path)?, };
+        let predicate = Provenance { builder: Builder { id: builder_id.to_string(), },
+                                     build_type: BUILD_TYPE.to_string(),
+                                     invocation: Invocation { config_source },
+                                     materials };
+        Ok(Statement { statement_type: STATEMENT_TYPE.to_string(),
+                       subject: vec![subject],
+                       predicate_type: PREDICATE_TYPE.to_string(),
+                       predicate })
+    }
+}
+
+/// An attestation signed in a DSSE envelope.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Envelope {
+    pub payload_type: String,
+    /// The Base64-encoded statement
+    pub payload:      String,
+    pub signatures:   Vec<EnvelopeSignature>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct EnvelopeSignature {
+    /// The named revision of the origin key that made the signature
+    pub keyid: String,
+    /// The Base64-encoded signature
+    pub sig:   String,
+}
+
+impl Envelope {
+    pub fn sign(statement: &Statement, key: &SecretOriginSigningKey) -> Result<Self> {
+        let payload =
+            serde_json::to_vec(statement).map_err(|e| error("Can't encode provenance", e))?;
+        let sig = key.sign_detached(&pae(PAYLOAD_TYPE, &payload));
+        let signature = EnvelopeSignature { keyid: key.named_revision().to_string(),
+                                            sig:   base64::encode(&sig), };
+        Ok(Envelope { payload_type: PAYLOAD_TYPE.to_string(),
+                      payload:      base64::encode(&payload),
+                      signatures:   vec![signature], })
+    }
+
+    /// Verify the envelope with the first of its signatures made by a key in `cache`, returning
+    /// the named revision of that key and the statement.
+    pub fn verify(&self, cache: &KeyCache) -> Result<(NamedRevision, Statement)> {
+        if self.payload_type != PAYLOAD_TYPE {
+            return Err(Error::CryptoError(format!("Unsupported provenance \
+                                                   payload type: {}",
+                                                  self.payload_type)));
+        }
+        let payload =
+            base64::decode(&self.payload).map_err(|e| error("Can't decode provenance", e))?;
+        let message = pae(&self.payload_type, &payload);
+        let mut err = Error::CryptoError("Provenance is not signed".to_string());
+        for signature in &self.signatures {
+            let signer = signature.keyid.parse::<NamedRevision>()?;
+            revocation::ensure_not_revoked(cache, &signer)?;
+            let key = match cache.public_signing_key(&signer) {
+                Ok(key) => key,
+                Err(e) => {
+                    err = e;
+                    continue;
+                }
+            };
+            let sig =
+                base64::decode(&signature.sig).map_err(|e| error("Can't decode signature", e))?;
+            key.verify_detached(&sig, &message)?;
+            let statement: Statement =
+                serde_json::from_slice(&payload).map_err(|e| error("Can't parse provenance", e))?;
+            if statement.statement_type != STATEMENT_TYPE
+               || statement.predicate_type != PREDICATE_TYPE
+            {
+                return Err(Error::CryptoError(format!("Unsupported provenance: {} \
+                                                       with {}",
+                                                      statement.statement_type,
+                                                      statement.predicate_type)));
+            }
+            return Ok((signer, statement));
+        }
+        Err(err)
+    }
+}
+
+/// The path of the file holding the attestation of the artifact at `artifact_path`.
+pub fn provenance_path(artifact_path: &Path) -> PathBuf {
+    let mut path = artifact_path.as_os_str().to_owned();
+    path.push(PROVENANCE_SUFFIX);
+    PathBuf::from(path)
+}
+
+/// Attest to the provenance of the artifact at `artifact_path`, signing the attestation with
+/// `key`, and write it next to the artifact. Returns the path of the attestation.
+pub fn attest(artifact_path: &Path,
+              builder_id: &str,
+              config_source: Option<ConfigSource>,
+              key: &SecretOriginSigningKey)
+              -> Result<PathBuf> {
+    let mut archive = PackageArchive::new(artifact_path)?;
+    let statement = Statement::new(&mut archive, builder_id, config_source)?;
+    let envelope = Envelope::sign(&statement, key)?;
+    let path = provenance_path(artifact_path);
+    let json =
+        serde_json::to_string_pretty(&envelope).map_err(|e| error("Can't encode provenance", e))?;
+    fs::write(&path, json)?;
+    Ok(path)
+}
+
+/// Verify the attestation next to the artifact at `artifact_path`: it must be signed by a key of
+/// the artifact's origin and be about this very artifact. Returns the named revision of the key
+/// that signed it, along with its statement.
+pub fn verify(artifact_path: &Path, cache: &KeyCache) -> Result<(NamedRevision, Statement)> {
+    let path = provenance_path(artifact_path);
+    if !path.is_file() {
+        return Err(Error::CryptoError(format!("No provenance attestation \
+                                               found at {}",
+                                              path.display())));
+    }
+    let what = format!("Can't parse provenance attestation {}", path.display());
+    let envelope: Envelope =
+        serde_json::from_str(&fs::read_to_string(&path)?).map_err(|e| error(&what, e))?;
+    let (signer, statement) = envelope.verify(cache)?;
+
+    let header = artifact::get_artifact_header(artifact_path)?;
+    if signer.name() != header.signer().name() {
+        return Err(Error::CryptoError(format!("Provenance is signed by {}, \
+                                               not by a key of the {} origin",
+                                              signer,
+                                              header.signer().name())));
+    }
+    let artifact_digests = digests(artifact_path)?;
+    let about_artifact =
+        statement.subject
+                 .iter()
+                 .any(|subject| subject.digest.get("sha256") == artifact_digests.get("sha256"));
+    if !about_artifact {
+        return Err(Error::CryptoError(format!("Provenance attestation {} is \
+                                               not about {}",
+                                              path.display(),
+                                              artifact_path.display())));
+    }
+    Ok((signer, statement))
+}
+
+fn error(what: &str, e: impl fmt::Display) -> Error {
+    Error::CryptoError(format!("{}: {}", what, e))
+}
+
+/// The DSSE pre-authentication encoding of a payload, which is what gets signed.
+fn pae(payload_type: &str, payload: &[u8]) -> Vec<u8> {
+    let mut pae = format!("DSSEv1 {} {} {} ",
+                          payload_type.len(),
+                          payload_type,
+                          payload.len()).into_bytes();
+    pae.extend_from_slice(payload);
+    pae
+}
+
+fn digests(path: &Path) -> Result<DigestSet> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        let len = file.read(&mut buf)?;
+        if len == 0 {
+            break;
+        }
+        hasher.input(&buf[..len]);
+    }
+    let mut digests = DigestSet::new();
+    digests.insert("sha256".to_string(), hasher.result_str());
+    Ok(digests)
+}
+
+fn package_uri(ident: &PackageIdent) -> String { format!("pkg:habitat/{}", ident) }
+
+/// The source a package was built from, as its manifest records it:
+///
+/// ```text
+/// Source: [https://example.com/redis-3.0.7.tar.gz](https://example.com/redis-3.0.7.tar.gz)
+/// SHA: 7d2fb6c2...
+/// ```
+fn source_material(manifest: &str) -> Option<Material> {
+    let mut uri = None;
+    let mut sha256 = None;
+    for line in manifest.lines() {
+        let line = line.trim();
+        if line.starts_with("Source: [") {
+            uri = line["Source: [".len()..].split(']')
+                                           .next()
+                                           .map(str::to_string);
+        } else if line.starts_with("SHA:") {
+            sha256 = Some(line["SHA:".len()..].trim().to_string());
+        }
+    }
+    let uri = uri.filter(|uri| !uri.is_empty())?;
+    let mut digest = DigestSet::new();
+    if let Some(sha256) = sha256.filter(|sha| !sha.is_empty()) {
+        digest.insert("sha256".to_string(), sha256);
+    }
+    Some(Material { uri, digest })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::crypto::test_support::*;
+
+    const POSSUMS: &str = "happyhumans-possums-8.1.4-20160427165340-x86_64-linux.hart";
+
+    fn artifact_in(dir: &Path) -> PathBuf {
+        let path = dir.join(POSSUMS);
+        fs::copy(fixture(POSSUMS), &path).unwrap();
+        path
+    }
+
+    #[test]
+    fn attestations_are_verified() {
+        let (cache, dir) = new_cache();
+        let origin = "happyhumans".parse().unwrap();
+        let (public, key) = cache.new_signing_pair(&origin).unwrap();
+        let artifact_path = artifact_in(dir.path());
+        let source = ConfigSource { uri: Some("https://github.com/acme/plans".to_string()),
+                                    ..ConfigSource::default() };
+        attest(&artifact_path, "https://ci.example.com", Some(source), &key).unwrap();
+
+        let (signer, statement) = verify(&artifact_path, &cache).unwrap();
+        assert_eq!(&signer, public.named_revision());
+        assert_eq!(statement.subject[0].name, POSSUMS);
+        assert_eq!(statement.predicate.builder.id, "https://ci.example.com");
+        assert_eq!(statement.predicate.materials[0].uri, "nosuchfile.tar.gz");
+    }
+
+    #[test]
+    #[should_panic(expected = "No provenance attestation found")]
+    fn missing_attestations_fail() {
+        let (cache, dir) = new_cache();
+        verify(&artifact_in(dir.path()), &cache).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "Verification failed")]
+    fn tampered_attestations_fail() {
+        let (cache, dir) = new_cache();
+        let origin = "happyhumans".parse().unwrap();
+        let (_public, key) = cache.new_signing_pair(&origin).unwrap();
+        let artifact_path = artifact_in(dir.path());
+        let path = attest(&artifact_path, "https://ci.example.com", None, &key).unwrap();
+
+        let mut envelope: Envelope =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        let mut statement: Statement =
+            serde_json::from_slice(&base64::decode(&envelope.payload).unwrap()).unwrap();
+        statement.predicate.builder.id = "https://evil.example.com".to_string();
+        envelope.payload = base64::encode(&serde_json::to_vec(&statement).unwrap());
+        fs::write(&path, serde_json::to_string(&envelope).unwrap()).unwrap();
+
+        verify(&artifact_path, &cache).unwrap();
+    }
+
+    #[test]
+    fn pae_follows_dsse() {
+        assert_eq!(pae("http://example.com/HelloWorld", b"hello world"),
+                   b"DSSEv1 29 http://example.com/HelloWorld 11 hello world".to_vec());
+    }
+}
@@ -0,0 +1,356 @@
+//! Revocation of origin signing keys.
+//!
+//! When the secret key of an origin signing key revision is compromised, its revision is revoked
+//! by publishing a revocation list: the revoked revisions of an origin, signed with a key of that
+//! origin. Once a list is imported into a key cache, artifacts and provenance attestations signed
+//! by a revoked revision are rejected when verified with that cache, even though the public key
+//! is still there.
+//!
+//! Revocation lists are JSON documents that can be passed around as files or stored on Builder:
+//!
+//! ```json
+//! {
+//!   "format": "HAB-REVOCATIONS-1",
+//!   "origin": "acme",
+//!   "revoked": {
+//!     "acme-20160810182414": {
+//!       "revoked_at": "2021-09-20T12:00:00+00:00",
+//!       "reason": "Secret key leaked in CI logs"
+//!     }
+//!   },
+//!   "signer": "acme-20210920115959",
+//!   "signature": "<signature_base64>"
+//! }
+//! ```
+//!
+//! The signature covers the JSON encoding of the `format`, `origin` and `revoked` fields. A key
+//! may only revoke itself and older revisions, so that a compromised key can't be used to revoke
+//! the keys that replaced it.
+//! Revocations are never undone: importing a list only ever adds to the revoked revisions of a key
+//! cache, which are recorded in its `revoked_keys` file.
+
+use crate::{crypto::keys::{Key,
+                           KeyCache,
+                           KeyFile,
+                           NamedRevision,
+                           PublicOriginSigningKey,
+                           SecretOriginSigningKey},
+            error::{Error,
+                    Result},
+            fs::AtomicWriter,
+            origin::Origin};
+use chrono::Utc;
+use serde_derive::{Deserialize,
+                   Serialize};
+use std::{collections::BTreeMap,
+          fmt,
+          fs,
+          io::Write,
+          path::Path};
+
+pub const REVOCATIONS_FORMAT_VERSION: &str = "HAB-REVOCATIONS-1";
+
+/// The file in the key cache which records every revoked origin signing key revision.
+const REVOKED_KEYS_FILE: &str = "revoked_keys";
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Revocation {
+    /// When the revision was revoked, in RFC 3339 format
+    pub revoked_at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason:     Option<String>,
+}
+
+/// The revoked revisions of an origin's signing keys, keyed by named revision.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct RevocationList {
+    pub format:  String,
+    pub origin:  String,
+    pub revoked: BTreeMap<String, Revocation>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct SignedRevocationList {
+    #[serde(flatten)]
+    pub list:      RevocationList,
+    /// The named revision of the origin key that made the signature
+    pub signer:    String,
+    /// The Base64-encoded signature
+    pub signature: String,
+}
+
+impl RevocationList {
+    /// The revocation list of `origin` as recorded in `cache`.
+    pub fn from_cache(cache: &KeyCache, origin: &Origin) -> Result<Self> {
+        let revoked = revoked_keys(cache)?.into_iter()
+                                          .filter(|(key, _)| is_of_origin(key, origin.as_ref()))
+                                          .collect();
+        Ok(RevocationList { format: REVOCATIONS_FORMAT_VERSION.to_string(),
+                            origin: origin.to_string(),
+                            revoked })
+    }
+
+    /// Add the revocation of `named_revision` to the list, unless it is already revoked.
+    pub fn revoke(&mut self, named_revision: &NamedRevision, reason: Option<&str>) -> Result<()> {
+        if named_revision.name() != &self.origin {
+            return Err(Error::CryptoError(format!("Can't revoke {} in the \
+                                                   revocation list of the {} \
+                                                   origin",
+                                                  named_revision, self.origin)));
+        }
+        self.revoked
+            .entry(named_revision.to_string())
+            .or_insert_with(|| {
+                Revocation { revoked_at: Utc::now().to_rfc3339(),
+                             reason:     reason.map(str::to_string), }
+            });
+        Ok(())
+    }
+
+    pub fn sign(self, key: &SecretOriginSigningKey) -> Result<SignedRevocationList> {
+        if key.named_revision().name() != &self.origin {
+            return Err(Error::CryptoError(format!("Can't sign the revocation \
+                                                   list of the {} origin with {}",
+                                                  self.origin,
+                                                  key.named_revision())));
+        }
+        ensure_may_revoke(key.named_revision(), &self.revoked)?;
+        let signature = key.sign_detached(&self.message()?);
+        Ok(SignedRevocationList { list:      self,
+                                  signer:    key.named_revision().to_string(),
+                                  signature: base64::encode(&signature), })
+    }
+
+    fn message(&self) -> Result<Vec<u8>> {
+        serde_json::to_vec(self).map_err(|e| error("Can't encode revocation list", e))
+    }
+}
+
+impl SignedRevocationList {
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json).map_err(|e| error("Can't parse revocation list", e))
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).map_err(|e| error("Can't encode revocation list", e))
+    }
+
+    /// Verify the list against the keys in `cache`. It must be signed with a key of its origin
+    /// that `cache` doesn't know to be revoked, and which is no older than any key it revokes.
+    pub fn verify(&self, cache: &KeyCache) -> Result<&RevocationList> {
+        if self.list.format != REVOCATIONS_FORMAT_VERSION {
+            return Err(Error::CryptoError(format!("Unsupported revocation list \
+                                                   format: {}",
+                                                  self.list.format)));
+        }
+        let signer = self.signer.parse::<NamedRevision>()?;
+        if signer.name() != &self.list.origin {
+            return Err(Error::CryptoError(format!("The revocation list of the \
+                                                   {} origin is signed by {}",
+                                                  self.list.origin, signer)));
+        }
+        for key in self.list.revoked.keys() {
+            if !is_of_origin(key, &self.list.origin) {
+                return Err(Error::CryptoError(format!("The revocation list of the \
+                                                       {} origin revokes {}",
+                                                      self.list.origin, key)));
+            }
+        }
+        ensure_may_revoke(&signer, &self.list.revoked)?;
+        ensure_not_revoked(cache, &signer)?;
+        let key: PublicOriginSigningKey = cache.public_signing_key(&signer)?;
+        let signature =
+            base64::decode(&self.signature).map_err(|e| error("Can't decode signature", e))?;
+        key.verify_detached(&signature, &self.list.message()?)?;
+        Ok(&self.list)
+    }
+}
+
+/// Verify `signed` and record its revocations in `cache`. Returns the named revisions that were
+/// newly revoked.
+pub fn import(signed: &SignedRevocationList, cache: &KeyCache) -> Result<Vec<String>> {
+    let mut revoked = revoked_keys(cache)?;
+    let added = signed.list
+                      .revoked
+                      .keys()
+                      .filter(|key| !revoked.contains_key(*key))
+                      .cloned()
+                      .collect::<Vec<_>>();
+    // A list with nothing new is fine, even when it was signed by a key it revokes itself
+    if added.is_empty() {
+        return Ok(added);
+    }
+    let list = signed.verify(cache)?;
+    for key in &added {
+        revoked.insert(key.clone(), list.revoked[key].clone());
+    }
+    write_revoked_keys(cache, &revoked)?;
+    Ok(added)
+}
+
+/// Read a revocation list from `path`, verify it and record its revocations in `cache`.
+pub fn import_file(path: &Path, cache: &KeyCache) -> Result<Vec<String>> {
+    import(&SignedRevocationList::from_json(&fs::read_to_string(path)?)?,
+           cache)
+}
+
+/// Fail with `Error::RevokedKey` if `named_revision` has been revoked in `cache`.
+pub fn ensure_not_revoked(cache: &KeyCache, named_revision: &NamedRevision) -> Result<()> {
+    match revoked_keys(cache)?.remove(&named_revision.to_string()) {
+        Some(revocation) => Err(Error::RevokedKey(named_revision.to_string(), revocation.reason)),
+        None => Ok(()),
+    }
+}
+
+/// Fail unless every key in `revoked` is `signer` itself or an older revision.
+fn ensure_may_revoke(signer: &NamedRevision, revoked: &BTreeMap<String, Revocation>) -> Result<()> {
+    for key in revoked.keys() {
+        if key.parse::<NamedRevision>()?.revision() > signer.revision() {
+            return Err(Error::CryptoError(format!("{} can't revoke the newer {}", signer, key)));
+        }
+    }
+    Ok(())
+}
+
+fn is_of_origin(key: &str, origin: &str) -> bool {
+    key.parse::<NamedRevision>()
+       .map(|named_revision| named_revision.name() == origin)
+       .unwrap_or(false)
+}
+
+fn revoked_keys(cache: &KeyCache) -> Result<BTreeMap<String, Revocation>> {
+    let path = cache.as_ref().join(REVOKED_KEYS_FILE);
+    if !path.is_file() {
+        return Ok(BTreeMap::new());
+    }
+    let what = format!("Can't parse {}", path.display());
+    serde_json::from_str(&fs::read_to_string(&path)?).map_err(|e| error(&what, e))
+}
+
+fn write_revoked_keys(cache: &KeyCache, revoked: &BTreeMap<String, Revocation>) -> Result<()> {
+    let json =
+        serde_json::to_string_pretty(revoked).map_err(|e| error("Can't encode revoked keys", e))?;
+    let w = AtomicWriter::new_with_permissions(&cache.as_ref().join(REVOKED_KEYS_FILE),
+                                               PublicOriginSigningKey::permissions())?;
+    w.with_writer(|f| f.write_all(json.as_bytes()))?;
+    Ok(())
+}
+
+fn error(what: &str, e: impl fmt::Display) -> Error {
+    Error::CryptoError(format!("{}: {}", what, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::test_support::*;
+
+    fn origin() -> Origin { "acme".parse().unwrap() }
+
+    #[test]
+    fn imported_revocations_are_enforced() {
+        let (cache, _dir) = new_cache();
+        let (compromised, _) = cache.new_signing_pair(&origin()).unwrap();
+        wait_1_sec();
+        let (_, secret) = cache.new_signing_pair(&origin()).unwrap();
+        ensure_not_revoked(&cache, compromised.named_revision()).unwrap();
+
+        let mut list = RevocationList::from_cache(&cache, &origin()).unwrap();
+        list.revoke(compromised.named_revision(), Some("leaked"))
+            .unwrap();
+        let signed = list.sign(&secret).unwrap();
+        let json = signed.to_json().unwrap();
+        let added = import(&SignedRevocationList::from_json(&json).unwrap(), &cache).unwrap();
+        assert_eq!(added, vec![compromised.named_revision().to_string()]);
+
+        match ensure_not_revoked(&cache, compromised.named_revision()) {
+            Err(Error::RevokedKey(_, Some(reason))) => assert_eq!(reason, "leaked"),
+            result => panic!("Expected RevokedKey, got {:?}", result),
+        }
+        ensure_not_revoked(&cache, secret.named_revision()).unwrap();
+        // Importing the same list again adds nothing
+        assert!(import(&signed, &cache).unwrap().is_empty());
+        // Lists start from what the cache already revoked
+        assert_eq!(RevocationList::from_cache(&cache, &origin()).unwrap()
+                                                                .revoked
+                                                                .len(),
+                   1);
+    }
+
+    #[test]
+    fn tampered_lists_are_rejected() {
+        let (cache, _dir) = new_cache();
+        let (compromised, _) = cache.new_signing_pair(&origin()).unwrap();
+        wait_1_sec();
+        let (_, secret) = cache.new_signing_pair(&origin()).unwrap();
+        let mut list = RevocationList::from_cache(&cache, &origin()).unwrap();
+        list.revoke(compromised.named_revision(), None).unwrap();
+        let mut signed = list.sign(&secret).unwrap();
+        signed.list
+              .revoked
+              .get_mut(&compromised.named_revision().to_string())
+              .unwrap()
+              .reason = Some("tampered".to_string());
+        assert!(import(&signed, &cache).is_err());
+        ensure_not_revoked(&cache, compromised.named_revision()).unwrap();
+    }
+
+    #[test]
+    fn revoked_keys_cannot_sign_lists() {
+        let (cache, _dir) = new_cache();
+        let (first, _) = cache.new_signing_pair(&origin()).unwrap();
+        wait_1_sec();
+        let (_, second) = cache.new_signing_pair(&origin()).unwrap();
+
+        // A key may sign its own revocation, and that list can be imported again
+        let mut list = RevocationList::from_cache(&cache, &origin()).unwrap();
+        list.revoke(second.named_revision(), None).unwrap();
+        let signed = list.sign(&second).unwrap();
+        import(&signed, &cache).unwrap();
+        assert!(import(&signed, &cache).unwrap().is_empty());
+
+        // But nothing else once it is revoked
+        let mut list = RevocationList::from_cache(&cache, &origin()).unwrap();
+        list.revoke(first.named_revision(), None).unwrap();
+        match import(&list.sign(&second).unwrap(), &cache) {
+            Err(Error::RevokedKey(..)) => {}
+            result => panic!("Expected RevokedKey, got {:?}", result),
+        }
+        ensure_not_revoked(&cache, first.named_revision()).unwrap();
+    }
+
+    #[test]
+    fn keys_cannot_revoke_newer_keys() {
+        let (cache, _dir) = new_cache();
+        let (_, older) = cache.new_signing_pair(&origin()).unwrap();
+        wait_1_sec();
+        let (newer, _) = cache.new_signing_pair(&origin()).unwrap();
+        let mut list = RevocationList::from_cache(&cache, &origin()).unwrap();
+        list.revoke(newer.named_revision(), None).unwrap();
+        assert!(list.clone().sign(&older).is_err());
+
+        // Nor can a list signed without checking be imported
+        let signature = older.sign_detached(&list.message().unwrap());
+        let signed = SignedRevocationList { list,
+                                            signer: older.named_revision().to_string(),
+                                            signature: base64::encode(&signature) };
+        assert!(import(&signed, &cache).is_err());
+        ensure_not_revoked(&cache, newer.named_revision()).unwrap();
+    }
+
+    #[test]
+    fn lists_only_revoke_keys_of_their_origin() {
+        let (cache, _dir) = new_cache();
+        let other = "other".parse::<Origin>().unwrap();
+        let (foreign, _) = cache.new_signing_pair(&other).unwrap();
+        let (_, secret) = cache.new_signing_pair(&origin()).unwrap();
+        let mut list = RevocationList::from_cache(&cache, &origin()).unwrap();
+        assert!(list.revoke(foreign.named_revision(), None).is_err());
+
+        list.revoked.insert(foreign.named_revision().to_string(),
+                            Revocation { revoked_at: Utc::now().to_rfc3339(),
+                                         reason:     None, });
+        assert!(import(&list.sign(&secret).unwrap(), &cache).is_err());
+        ensure_not_revoked(&cache, foreign.named_revision()).unwrap();
+    }
+}
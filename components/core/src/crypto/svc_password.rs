@@ -0,0 +1,87 @@
+//! Service passwords on Linux.
+//!
+//! On Windows, the password of a service's user is encrypted with DPAPI (see the `dpapi` module)
+//! so the Supervisor can start the service as that user. Elsewhere, a service given a password is
+//! handed it as the `HAB_SVC_PASSWORD` environment variable of its hooks and run, which suits
+//! secrets a service needs to start but which must not be gossiped like configuration.
+//!
+//! The password is encrypted with the host key of the Supervisor (see `HostKey`) by `hab` before
+//! it is sent to the Supervisor, so it is only ever kept encrypted in service specs and
+//! control gateway messages. As with DPAPI, this means it must be encrypted on the Supervisor's
+//! host, with the Supervisor's key cache: `hab` refuses a password for a remote Supervisor, or
+//! from a user other than root unless the key cache is given explicitly.
+
+use crate::{crypto::keys::{HostBox,
+                           KeyCache,
+                           HOST_KEY_NAME},
+            error::{Error,
+                    Result},
+            fs::CACHE_KEY_PATH};
+use std::sync::RwLock;
+
+/// The environment variable holding the decrypted password of a service.
+pub const SVC_PASSWORD_ENVVAR: &str = "HAB_SVC_PASSWORD";
+
+lazy_static::lazy_static! {
+    static ref KEY_CACHE: RwLock<Option<KeyCache>> = RwLock::new(None);
+}
+
+/// Decrypt passwords with the host keys in `key_cache` rather than the default key cache.
+pub fn init(key_cache: KeyCache) {
+    *KEY_CACHE.write()
+              .expect("Service password key cache lock poisoned") = Some(key_cache);
+}
+
+/// Encrypt `password` with the latest host key in `key_cache`, generating the host key if there
+/// is none yet.
+pub fn encrypt(key_cache: &KeyCache, password: &str) -> Result<String> {
+    let host_key = match key_cache.latest_host_key_revision(HOST_KEY_NAME) {
+        Ok(host_key) => host_key,
+        Err(_) => {
+            key_cache.setup()?;
+            key_cache.new_host_key(HOST_KEY_NAME)?
+        }
+    };
+    Ok(host_key.seal(password.as_bytes()).to_string())
+}
+
+/// Decrypt a password encrypted by `encrypt`, with the host key it was encrypted with.
+pub fn decrypt(encrypted: &str) -> Result<String> {
+    let key_cache = KEY_CACHE.read()
+                             .expect("Service password key cache lock poisoned")
+                             .clone()
+                             .unwrap_or_else(|| KeyCache::new(&*CACHE_KEY_PATH));
+    decrypt_with(&key_cache, encrypted)
+}
+
+fn decrypt_with(key_cache: &KeyCache, encrypted: &str) -> Result<String> {
+    if !HostBox::is_host_box(encrypted) {
+        return Err(Error::CryptoError("The service password was not \
+                                       encrypted with a host key"
+                                                                 .to_string()));
+    }
+    let message: HostBox = encrypted.parse()?;
+    let host_key = key_cache.host_key(message.key())?;
+    Ok(String::from_utf8(host_key.open(&message)?)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::test_support::*;
+
+    #[test]
+    fn passwords_roundtrip_through_the_host_key() {
+        let (cache, _dir) = new_cache();
+        let encrypted = encrypt(&cache, "hunter2").unwrap();
+        assert!(!encrypted.contains("hunter2"));
+        assert_eq!(decrypt_with(&cache, &encrypted).unwrap(), "hunter2");
+        // The host key is reused rather than generated again
+        assert_eq!(decrypt_with(&cache, &encrypt(&cache, "hunter3").unwrap()).unwrap(),
+                   "hunter3");
+
+        let (other, _dir) = new_cache();
+        assert!(decrypt_with(&other, &encrypted).is_err());
+        assert!(decrypt_with(&cache, "hunter2").is_err());
+    }
+}
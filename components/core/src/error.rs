@@ -98,6 +98,8 @@ pub enum Error {
     IO(io::Error),
     /// Errors when joining paths :)
     JoinPathsError(env::JoinPathsError),
+    /// Occurs when a call to LoadUserProfileW fails
+    LoadUserProfileFailed(io::Error),
     // When LogonUserW does not have the correct logon type
     LogonTypeNotGranted,
     /// Occurs when a call to LogonUserW fails
@@ -127,6 +129,9 @@ pub enum Error {
     ParseSignalError(String),
     /// Occurs upon errors related to file or directory permissions.
     PermissionFailed(String),
+    /// Occurs when a downloaded public origin key does not match the fingerprint recorded when
+    /// that key was first downloaded.
+    PinnedKeyMismatch(String, String, String),
     /// Error parsing the contents of a plan file were incomplete or malformed.
     PlanMalformed,
     // When CreateProcessAsUserW does not have the correct privileges
@@ -135,6 +140,8 @@ pub enum Error {
     RegexParse(regex::Error),
     /// When an error occurs serializing rendering context
     RenderContextSerialization(serde_json::Error),
+    /// Occurs when something is signed with an origin key revision that has been revoked.
+    RevokedKey(String, Option<String>),
     /// When an error occurs converting a `String` from a UTF-8 byte vector.
     StringFromUtf8Error(string::FromUtf8Error),
     /// When the system target (platform and architecture) do not match the package target.
@@ -295,6 +302,9 @@ impl fmt::Display for Error {
             Error::InvalidUrl(ref url) => format!("Invalid url: {}", url),
             Error::IO(ref err) => format!("{}", err),
             Error::JoinPathsError(ref err) => format!("{}", err),
+            Error::LoadUserProfileFailed(ref e) => {
+                format!("Failure calling LoadUserProfileW: {:?}", e)
+            }
             Error::LogonTypeNotGranted => {
                 "hab_svc_user user must possess the 'SE_SERVICE_LOGON_NAME' account right to be \
                  spawned as a service by the Supervisor"
@@ -327,6 +337,13 @@ impl fmt::Display for Error {
             Error::ParseSignalError(ref s) => format!("Failed to parse '{}' as a signal", s),
             Error::PlanMalformed => "Failed to read or parse contents of Plan file".to_string(),
             Error::PermissionFailed(ref e) => e.to_string(),
+            Error::PinnedKeyMismatch(ref key, ref pinned, ref received) => {
+                format!("The public origin key {} does not match the key first downloaded for \
+                         that revision (expected fingerprint {}, received {}). If the key was \
+                         legitimately replaced, download it again with `hab origin key download \
+                         --accept-new-key`.",
+                        key, pinned, received)
+            }
             Error::PrivilegeNotHeld => "Current user must possess the 'SE_INCREASE_QUOTA_NAME' \
                                         and 'SE_ASSIGNPRIMARYTOKEN_NAME' privilege to spawn a new \
                                         process as a different user"
@@ -334,6 +351,14 @@ impl fmt::Display for Error {
             Error::RenderContextSerialization(ref e) => {
                 format!("Unable to serialize rendering context, {}", e)
             }
+            Error::RevokedKey(ref key, ref reason) => {
+                match reason {
+                    Some(reason) => {
+                        format!("The origin key {} has been revoked: {}", key, reason)
+                    }
+                    None => format!("The origin key {} has been revoked", key),
+                }
+            }
             Error::RegexParse(ref e) => format!("{}", e),
             Error::StringFromUtf8Error(ref e) => format!("{}", e),
             Error::TargetMatchError(ref e) => e.to_string(),
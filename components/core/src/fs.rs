@@ -49,11 +49,15 @@ pub const LAUNCHER_ROOT_PATH: &str = "hab/launcher";
 pub const PKG_PATH: &str = "hab/pkgs";
 #[cfg(target_os = "windows")]
 pub const PKG_PATH: &str = "hab\\pkgs";
+/// The root path of the store that installed package files are deduplicated into
+pub const PKG_STORE_PATH: &str = "hab/store";
 /// The environment variable pointing to the filesystem root. This exists for internal Habitat team
 /// usage and is not intended to be used by Habitat consumers. Using this variable could lead to
 /// broken Supervisor services and should be used with extreme caution. The services may break due
 /// to absolute paths in package binaries and libraries. Valid use cases include limited  testing or
-/// creating new self-contained root filesystems for tarballs or containers.
+/// creating new self-contained root filesystems for tarballs or containers. `hab sup run --fs-root`
+/// sets it for the Launcher, the Supervisor and its services so that several Supervisors can keep
+/// their state apart on one host.
 pub const FS_ROOT_ENVVAR: &str = "FS_ROOT";
 pub const SYSTEMDRIVE_ENVVAR: &str = "SYSTEMDRIVE";
 /// The file where user-defined configuration for each service is found.
@@ -276,6 +280,14 @@ pub fn pkg_root_path<T>(fs_root: Option<T>) -> PathBuf
     buf
 }
 
+pub fn pkg_store_path<T>(fs_root: Option<T>) -> PathBuf
+    where T: AsRef<Path>
+{
+    let mut buf = fs_root.map_or(PathBuf::from("/"), |p| p.as_ref().into());
+    buf.push(PKG_STORE_PATH);
+    buf
+}
+
 pub fn pkg_install_path<T>(ident: &PackageIdent, fs_root: Option<T>) -> PathBuf
     where T: AsRef<Path>
 {
@@ -1,7 +1,14 @@
+pub mod cgroup;
 pub mod ffi;
 pub mod filesystem;
+pub mod isolation;
 pub mod net;
 pub mod process;
+#[cfg(target_os = "linux")]
+pub mod sandbox;
+pub mod seccomp;
 pub mod signals;
+#[cfg(unix)]
+pub mod socket_activation;
 pub mod system;
 pub mod users;
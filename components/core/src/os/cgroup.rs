@@ -0,0 +1,504 @@
+//! Placement of supervised processes into cgroup v2 (unified hierarchy) groups.
+//!
+//! Every service gets its own group below `hab.slice`, in the group the Launcher was started in,
+//! e.g. `/sys/fs/cgroup/system.slice/hab-sup.service/hab.slice/redis.default`. Under systemd,
+//! that group belongs to the Launcher when its unit sets `Delegate=yes`. The `cpu`, `memory` and
+//! `io` controllers are only enabled for that group when a corresponding limit has been
+//! requested, but the usage of every group can be read back regardless.
+//!
+//! A group can only enable controllers for its children when it has no processes of its own, so
+//! the Launcher and its descendants, such as the Supervisor, are then moved into `hab-launcher`,
+//! next to `hab.slice`.
+//!
+//! On hosts without a cgroup v2 hierarchy (including any non-Linux platform),
+//! `is_available` returns `false` and nothing else in this module should be called.
+use serde_derive::{Deserialize,
+                   Serialize};
+use std::{fs,
+          io,
+          path::{Path,
+                 PathBuf}};
+
+const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+const SLICE: &str = "hab.slice";
+/// The group the Launcher and its descendants are moved into, next to `SLICE`.
+const LAUNCHER_GROUP: &str = "hab-launcher";
+/// The period the kernel uses when `cpu.max` only specifies a quota.
+const DEFAULT_CPU_PERIOD_USEC: u64 = 100_000;
+
+/// Limits applied to the group of a service. The values use the syntax of the cgroup v2
+/// interface files they are written to. On Windows, the Launcher applies the CPU and memory
+/// limits to the service's job object instead.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Limits {
+    /// Contents of `cpu.max`, e.g. `"50000 100000"` for half a CPU.
+    pub cpu_max:    Option<String>,
+    /// Contents of `memory.max`, e.g. `"512M"`.
+    pub memory_max: Option<String>,
+    /// Contents of `io.weight`, between 1 and 10000.
+    pub io_weight:  Option<u32>,
+}
+
+impl Limits {
+    pub fn is_empty(&self) -> bool {
+        self.cpu_max.is_none() && self.memory_max.is_none() && self.io_weight.is_none()
+    }
+
+    /// The memory limit in bytes, `None` when unlimited.
+    pub fn memory_max_bytes(&self) -> Option<u64> {
+        let value = self.memory_max.as_ref()?;
+        let (digits, multiplier) = match value.chars().last()?.to_ascii_uppercase() {
+            'K' => (&value[..value.len() - 1], 1 << 10),
+            'M' => (&value[..value.len() - 1], 1 << 20),
+            'G' => (&value[..value.len() - 1], 1 << 30),
+            'T' => (&value[..value.len() - 1], 1 << 40),
+            _ => (value.as_str(), 1),
+        };
+        digits.parse::<u64>()
+              .ok()
+              .map(|n| n.saturating_mul(multiplier))
+    }
+
+    /// The CPU limit as a number of CPUs (e.g. `0.5`), `None` when unlimited.
+    pub fn cpu_fraction(&self) -> Option<f64> {
+        let mut parts = self.cpu_max.as_ref()?.split_whitespace();
+        let quota = parts.next()?.parse::<u64>().ok()?;
+        let period = parts.next()
+                          .map_or(Some(DEFAULT_CPU_PERIOD_USEC), |p| p.parse().ok())?;
+        Some(quota as f64 / period as f64)
+    }
+
+    fn controllers(&self) -> Vec<&'static str> {
+        let mut controllers = vec![];
+        if self.cpu_max.is_some() {
+            controllers.push("cpu");
+        }
+        if self.memory_max.is_some() {
+            controllers.push("memory");
+        }
+        if self.io_weight.is_some() {
+            controllers.push("io");
+        }
+        controllers
+    }
+}
+
+/// Resource usage of a service's group. Memory and IO usage are only accounted when the
+/// corresponding controller is enabled for the group.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Usage {
+    pub cpu_usage_usec:       u64,
+    pub memory_current_bytes: Option<u64>,
+    pub io_read_bytes:        Option<u64>,
+    pub io_write_bytes:       Option<u64>,
+}
+
+/// Whether the host has a cgroup v2 hierarchy mounted at the usual location.
+pub fn is_available() -> bool { Path::new(CGROUP_ROOT).join("cgroup.controllers").is_file() }
+
+/// The path of the group of the service `id` (e.g. "redis.default").
+pub fn service_path(id: &str) -> PathBuf { delegated_root().join(SLICE).join(id) }
+
+/// Create the group of the service `id` with the given limits, returning its path. A process
+/// joins the group by writing its PID, or `0` for itself, to the group's `cgroup.procs`.
+pub fn create(id: &str, limits: &Limits) -> io::Result<PathBuf> {
+    create_in(&delegated_root(), id, limits)
+}
+
+/// The group the Launcher was started in, which the groups of services are created below. It is
+/// found from the group of the calling process, the Launcher or the Supervisor, which may
+/// already have been moved into `LAUNCHER_GROUP`.
+fn delegated_root() -> PathBuf {
+    let content = fs::read_to_string("/proc/self/cgroup").unwrap_or_default();
+    Path::new(CGROUP_ROOT).join(delegated_root_in(&content))
+}
+
+/// Find the group the Launcher was started in, relative to the root of the hierarchy, in the
+/// contents of a `/proc/<pid>/cgroup` file.
+fn delegated_root_in(content: &str) -> PathBuf {
+    let group = content.lines()
+                       .find(|line| line.starts_with("0::"))
+                       .map_or("", |line| line[3..].trim_start_matches('/'));
+    let group = Path::new(group);
+    if group.file_name()
+            .map_or(false, |name| name == LAUNCHER_GROUP)
+    {
+        group.parent().unwrap_or(group).to_path_buf()
+    } else {
+        group.to_path_buf()
+    }
+}
+
+/// Remove the group of the service `id`. This is a no-op when the group does not exist and
+/// fails if processes are still running in it.
+pub fn remove(id: &str) -> io::Result<()> {
+    match fs::remove_dir(service_path(id)) {
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+/// Read the resource usage of the group of the service `id`.
+pub fn usage(id: &str) -> io::Result<Usage> { usage_in(&service_path(id)) }
+
+/// The service whose group the process `pid` is in, if any. Processes inherit the group of
+/// their parent, so this holds for every descendant of a service, even once it has been
+/// re-parented.
+pub fn service_of(pid: u32) -> Option<String> {
+    let content = fs::read_to_string(format!("/proc/{}/cgroup", pid)).ok()?;
+    service_in(&content)
+}
+
+/// Find the service group in the contents of a `/proc/<pid>/cgroup` file, whose cgroup v2
+/// entry looks like `0::/system.slice/hab-sup.service/hab.slice/redis.default`.
+fn service_in(content: &str) -> Option<String> {
+    content.lines()
+           .filter(|line| line.starts_with("0::"))
+           .find_map(|line| {
+               let parts = line[3..].split('/').collect::<Vec<_>>();
+               parts.windows(2)
+                    .find(|pair| pair[0] == SLICE && !pair[1].is_empty())
+                    .map(|pair| pair[1].to_string())
+           })
+}
+
+fn create_in(root: &Path, id: &str, limits: &Limits) -> io::Result<PathBuf> {
+    let slice = root.join(SLICE);
+    fs::create_dir_all(&slice)?;
+    let controllers = limits.controllers();
+    if !controllers.is_empty() {
+        // Controllers have to be enabled on every level above the group which uses them, which
+        // the root of the hierarchy is exempt from.
+        if root != Path::new(CGROUP_ROOT) {
+            vacate(root)?;
+        }
+        enable_controllers(root, &controllers)?;
+        enable_controllers(&slice, &controllers)?;
+    }
+    let group = slice.join(id);
+    fs::create_dir_all(&group)?;
+    if let Some(ref cpu_max) = limits.cpu_max {
+        fs::write(group.join("cpu.max"), cpu_max)?;
+    }
+    if let Some(ref memory_max) = limits.memory_max {
+        fs::write(group.join("memory.max"), memory_max)?;
+    }
+    if let Some(io_weight) = limits.io_weight {
+        fs::write(group.join("io.weight"), format!("default {}", io_weight))?;
+    }
+    Ok(group)
+}
+
+/// Move the Launcher, which is the calling process, and its descendants out of `group` into its
+/// `LAUNCHER_GROUP`, so that it can enable controllers for its children. Other processes are left
+/// alone: when the Launcher was started in a group it shares with unrelated processes, such as
+/// a login session, enabling the controllers then fails.
+fn vacate(group: &Path) -> io::Result<()> { vacate_tree(group, std::process::id(), parent_of) }
+
+fn vacate_tree(group: &Path,
+               launcher: u32,
+               parent_of: impl Fn(u32) -> Option<u32>)
+               -> io::Result<()> {
+    let procs = fs::read_to_string(group.join("cgroup.procs")).unwrap_or_default();
+    let pids = procs.split_whitespace()
+                    .filter_map(|pid| pid.parse::<u32>().ok())
+                    .filter(|pid| descends_from(*pid, launcher, &parent_of))
+                    .collect::<Vec<_>>();
+    if pids.is_empty() {
+        return Ok(());
+    }
+    let launcher_group = group.join(LAUNCHER_GROUP);
+    fs::create_dir_all(&launcher_group)?;
+    for pid in pids {
+        if let Err(err) = fs::write(launcher_group.join("cgroup.procs"), pid.to_string()) {
+            // A process which exited in the meantime doesn't need to be moved
+            if Path::new("/proc").join(pid.to_string()).exists() {
+                return Err(err);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Whether `pid` is `ancestor` or one of its descendants.
+fn descends_from(mut pid: u32, ancestor: u32, parent_of: impl Fn(u32) -> Option<u32>) -> bool {
+    loop {
+        if pid == ancestor {
+            return true;
+        }
+        match parent_of(pid) {
+            Some(parent) if parent != 0 && parent != pid => pid = parent,
+            _ => return false,
+        }
+    }
+}
+
+/// The parent of the process `pid`, from the field following its command in `/proc/<pid>/stat`.
+fn parent_of(pid: u32) -> Option<u32> {
+    let stat = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    stat[stat.rfind(')')? + 1..].split_whitespace()
+                                .nth(1)?
+                                .parse()
+                                .ok()
+}
+
+fn enable_controllers(group: &Path, controllers: &[&str]) -> io::Result<()> {
+    let enabled = fs::read_to_string(group.join("cgroup.subtree_control")).unwrap_or_default();
+    let missing = controllers.iter()
+                             .filter(|c| !enabled.split_whitespace().any(|e| e == **c))
+                             .map(|c| format!("+{}", c))
+                             .collect::<Vec<_>>();
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        fs::write(group.join("cgroup.subtree_control"), missing.join(" "))
+    }
+}
+
+fn usage_in(group: &Path) -> io::Result<Usage> {
+    let cpu_stat = fs::read_to_string(group.join("cpu.stat"))?;
+    let (io_read_bytes, io_write_bytes) = match fs::read_to_string(group.join("io.stat")) {
+        Ok(io_stat) => {
+            let (read, write) = parse_io_stat(&io_stat);
+            (Some(read), Some(write))
+        }
+        Err(_) => (None, None),
+    };
+    let memory_current_bytes =
+        fs::read_to_string(group.join("memory.current")).ok()
+                                                        .and_then(|m| m.trim().parse().ok());
+    Ok(Usage { cpu_usage_usec: flat_keyed_value(&cpu_stat, "usage_usec").unwrap_or_default(),
+               memory_current_bytes,
+               io_read_bytes,
+               io_write_bytes })
+}
+
+/// Look up `key` in the contents of a flat keyed file such as `cpu.stat`.
+fn flat_keyed_value(content: &str, key: &str) -> Option<u64> {
+    content.lines().find_map(|line| {
+                       let mut parts = line.split_whitespace();
+                       if parts.next() == Some(key) {
+                           parts.next().and_then(|v| v.parse().ok())
+                       } else {
+                           None
+                       }
+                   })
+}
+
+/// Sum the bytes read and written over all devices of an `io.stat` file, whose lines look
+/// like `8:0 rbytes=1459200 wbytes=314773504 rios=192 wios=353 dbytes=0 dios=0`.
+fn parse_io_stat(content: &str) -> (u64, u64) {
+    content.lines()
+           .flat_map(|line| line.split_whitespace().skip(1))
+           .fold((0, 0), |(read, write), field| {
+               let mut kv = field.splitn(2, '=');
+               match (kv.next(), kv.next().and_then(|v| v.parse::<u64>().ok())) {
+                   (Some("rbytes"), Some(v)) => (read + v, write),
+                   (Some("wbytes"), Some(v)) => (read, write + v),
+                   _ => (read, write),
+               }
+           })
+}
+
+/// Validate a value for `cpu.max`: `max` or a quota, optionally followed by a period, both in
+/// microseconds.
+pub fn validate_cpu_max(value: &str) -> Result<(), String> {
+    let mut parts = value.split_whitespace();
+    let quota = parts.next();
+    let period = parts.next();
+    let valid = match (quota, period, parts.next()) {
+        (Some(quota), period, None) => {
+            (quota == "max" || quota.parse::<u64>().map(|q| q > 0).unwrap_or(false))
+            && period.map_or(true, |p| p.parse::<u64>().map(|p| p > 0).unwrap_or(false))
+        }
+        _ => false,
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("'{}' is not a valid CPU limit; expected 'max' or \
+                     '<quota> [<period>]' in microseconds, e.g. \
+                     '50000 100000'",
+                    value))
+    }
+}
+
+/// Validate a value for `memory.max`: `max` or a number of bytes with an optional `K`, `M`,
+/// `G` or `T` suffix.
+pub fn validate_memory_max(value: &str) -> Result<(), String> {
+    let digits = value.trim_end_matches(|c| "KMGTkmgt".contains(c));
+    let valid = value == "max"
+                || (value.len() - digits.len() <= 1
+                    && !digits.is_empty()
+                    && digits.chars().all(|c| c.is_ascii_digit()));
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("'{}' is not a valid memory limit; expected 'max' \
+                     or a number of bytes with an optional K, M, G or \
+                     T suffix, e.g. '512M'",
+                    value))
+    }
+}
+
+/// Validate a value for `io.weight`.
+pub fn validate_io_weight(value: &str) -> Result<(), String> {
+    match value.parse::<u32>() {
+        Ok(weight) if (1..=10_000).contains(&weight) => Ok(()),
+        _ => {
+            Err(format!("'{}' is not a valid IO weight; expected a number \
+                         between 1 and 10000",
+                        value))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn limits_are_written_to_the_service_group() {
+        let root = TempDir::new().unwrap();
+        let limits = Limits { cpu_max:    Some("50000 100000".to_string()),
+                              memory_max: Some("512M".to_string()),
+                              io_weight:  None, };
+        let group = create_in(root.path(), "redis.default", &limits).unwrap();
+
+        assert_eq!(group, root.path().join(SLICE).join("redis.default"));
+        assert_eq!(fs::read_to_string(root.path().join("cgroup.subtree_control")).unwrap(),
+                   "+cpu +memory");
+        assert_eq!(fs::read_to_string(group.join("cpu.max")).unwrap(),
+                   "50000 100000");
+        assert_eq!(fs::read_to_string(group.join("memory.max")).unwrap(),
+                   "512M");
+        assert!(!group.join("io.weight").exists());
+    }
+
+    #[test]
+    fn the_launcher_is_moved_out_of_the_way() {
+        let root = TempDir::new().unwrap();
+        fs::write(root.path().join("cgroup.procs"),
+                  format!("{}\n", std::process::id())).unwrap();
+        let limits = Limits { memory_max: Some("512M".to_string()),
+                              ..Default::default() };
+        create_in(root.path(), "redis.default", &limits).unwrap();
+
+        let launcher_group = root.path().join(LAUNCHER_GROUP);
+        assert_eq!(fs::read_to_string(launcher_group.join("cgroup.procs")).unwrap(),
+                   std::process::id().to_string());
+        assert_eq!(fs::read_to_string(root.path().join("cgroup.subtree_control")).unwrap(),
+                   "+memory");
+    }
+
+    #[test]
+    fn unrelated_processes_are_left_in_the_launcher_group() {
+        let root = TempDir::new().unwrap();
+        fs::write(root.path().join("cgroup.procs"), "1\n7\n8\n").unwrap();
+        let parents = |pid| {
+            match pid {
+                7 => Some(1),
+                8 => Some(7),
+                _ => Some(0),
+            }
+        };
+
+        vacate_tree(root.path(), 42, parents).unwrap();
+        assert!(!root.path().join(LAUNCHER_GROUP).exists());
+
+        vacate_tree(root.path(), 7, parents).unwrap();
+        let launcher_group = root.path().join(LAUNCHER_GROUP);
+        assert_eq!(fs::read_to_string(launcher_group.join("cgroup.procs")).unwrap(),
+                   "8");
+    }
+
+    #[test]
+    fn descendants_are_found() {
+        let parents = |pid| {
+            match pid {
+                10 => Some(5),
+                5 => Some(1),
+                1 => Some(0),
+                _ => None,
+            }
+        };
+        assert!(descends_from(10, 5, parents));
+        assert!(descends_from(5, 5, parents));
+        assert!(!descends_from(5, 10, parents));
+        assert!(!descends_from(3, 5, parents));
+    }
+
+    #[test]
+    fn delegated_root_is_found_in_proc_cgroup() {
+        assert_eq!(delegated_root_in("0::/system.slice/hab-sup.service\n"),
+                   PathBuf::from("system.slice/hab-sup.service"));
+        assert_eq!(delegated_root_in("0::/system.slice/hab-sup.service/hab-launcher\n"),
+                   PathBuf::from("system.slice/hab-sup.service"));
+        assert_eq!(delegated_root_in("0::/\n"), PathBuf::from(""));
+    }
+
+    #[test]
+    fn usage_is_read_from_the_service_group() {
+        let group = TempDir::new().unwrap();
+        fs::write(group.path().join("cpu.stat"),
+                  "usage_usec 1234\nuser_usec 1000\nsystem_usec 234\n").unwrap();
+        fs::write(group.path().join("memory.current"), "4096\n").unwrap();
+        fs::write(group.path().join("io.stat"),
+                  "8:0 rbytes=100 wbytes=200 rios=1 wios=2\n8:16 rbytes=10 wbytes=20 rios=1 \
+                   wios=2\n").unwrap();
+
+        assert_eq!(usage_in(group.path()).unwrap(),
+                   Usage { cpu_usage_usec:       1234,
+                           memory_current_bytes: Some(4096),
+                           io_read_bytes:        Some(110),
+                           io_write_bytes:       Some(220), });
+    }
+
+    #[test]
+    fn service_is_found_in_proc_cgroup() {
+        assert_eq!(service_in("0::/hab.slice/redis.default\n"),
+                   Some("redis.default".to_string()));
+        assert_eq!(service_in("12:pids:/user.slice\n0::/hab.slice/redis.default/worker\n"),
+                   Some("redis.default".to_string()));
+        assert_eq!(service_in("0::/user.slice/user-1000.slice\n"), None);
+        assert_eq!(service_in("0::/hab.slice\n"), None);
+        assert_eq!(service_in("0::/system.slice/hab-sup.service/hab.slice/redis.default\n"),
+                   Some("redis.default".to_string()));
+        assert_eq!(service_in("0::/system.slice/hab-sup.service/hab-launcher\n"),
+                   None);
+    }
+
+    #[test]
+    fn limits_are_converted() {
+        let limits = Limits { cpu_max:    Some("50000 100000".to_string()),
+                              memory_max: Some("512M".to_string()),
+                              io_weight:  None, };
+        assert_eq!(limits.cpu_fraction(), Some(0.5));
+        assert_eq!(limits.memory_max_bytes(), Some(512 * 1024 * 1024));
+
+        let limits = Limits { cpu_max:    Some("max".to_string()),
+                              memory_max: Some("max".to_string()),
+                              io_weight:  None, };
+        assert_eq!(limits.cpu_fraction(), None);
+        assert_eq!(limits.memory_max_bytes(), None);
+
+        let limits = Limits { cpu_max: Some("200000".to_string()),
+                              ..Default::default() };
+        assert_eq!(limits.cpu_fraction(), Some(2.0));
+    }
+
+    #[test]
+    fn limit_values_are_validated() {
+        assert!(validate_cpu_max("max").is_ok());
+        assert!(validate_cpu_max("50000 100000").is_ok());
+        assert!(validate_cpu_max("0.5").is_err());
+        assert!(validate_cpu_max("50000 100000 1").is_err());
+        assert!(validate_memory_max("512M").is_ok());
+        assert!(validate_memory_max("1073741824").is_ok());
+        assert!(validate_memory_max("512MB").is_err());
+        assert!(validate_io_weight("100").is_ok());
+        assert!(validate_io_weight("0").is_err());
+        assert!(validate_io_weight("10001").is_err());
+    }
+}
@@ -0,0 +1,163 @@
+//! Isolation of a service's processes from the rest of the host, beyond the user and the cgroup
+//! they run as. The Launcher applies it when it spawns a service.
+//!
+//! Namespaces and seccomp only exist on Linux; elsewhere the Launcher warns that the isolation a
+//! service asks for is unavailable and runs it without.
+use super::seccomp::SeccompProfile;
+use std::{fmt,
+          path::PathBuf,
+          str::FromStr};
+
+/// How a service's processes are isolated.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Isolation {
+    /// Run the service under a minimal init process in a PID namespace of its own, where it
+    /// can't see or signal the processes of other services, and whose processes are all killed
+    /// when it exits.
+    pub pid_namespace:      bool,
+    /// Run the service in a network namespace of its own, which has nothing but a loopback
+    /// interface. The service is only reachable through `ports`.
+    pub network_namespace:  bool,
+    /// Host ports forwarded to the ports the service listens on in its network namespace.
+    pub ports:              Vec<PortMapping>,
+    /// Restrict the system calls the service can make to those its seccomp profile allows.
+    pub seccomp_profile:    Option<SeccompProfile>,
+    /// Restrict the filesystem the service sees to the system directories, `allowed_paths`, a
+    /// `/proc` of its own and an empty `/tmp`.
+    pub filesystem_sandbox: bool,
+    /// The paths of the host visible to the service when `filesystem_sandbox` is set.
+    pub allowed_paths:      Vec<AllowedPath>,
+}
+
+impl Isolation {
+    pub fn is_empty(&self) -> bool { *self == Isolation::default() }
+
+    /// Whether the isolation can be applied on this platform.
+    pub fn is_available(&self) -> bool { cfg!(target_os = "linux") || self.is_empty() }
+}
+
+/// A TCP port on the host forwarded to a port of a service in its network namespace, given as
+/// `HOST_PORT:SERVICE_PORT`, e.g. `8080:80`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct PortMapping {
+    pub host:    u16,
+    pub service: u16,
+}
+
+impl FromStr for PortMapping {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut parts = value.splitn(2, ':');
+        let host = parts.next().and_then(|p| p.trim().parse().ok());
+        let service = parts.next().and_then(|p| p.trim().parse().ok());
+        match (host, service) {
+            (Some(host), Some(service)) if host != 0 && service != 0 => {
+                Ok(PortMapping { host, service })
+            }
+            _ => {
+                Err(format!("'{}' is not a valid port mapping, expected \
+                             HOST_PORT:SERVICE_PORT (ex: 8080:80)",
+                            value))
+            }
+        }
+    }
+}
+
+impl fmt::Display for PortMapping {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.host, self.service)
+    }
+}
+
+pub fn validate_port_mapping(value: &str) -> Result<(), String> {
+    value.parse::<PortMapping>().map(|_| ())
+}
+
+/// A path of the host visible to a service whose filesystem is sandboxed, given as its absolute
+/// path, followed by `:ro` to make it read-only, e.g. `/srv/media:ro`. Paths are writable unless
+/// made read-only, as far as the permissions of the service's user allow.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct AllowedPath {
+    pub path:      PathBuf,
+    pub read_only: bool,
+}
+
+impl AllowedPath {
+    pub fn read_only(path: impl Into<PathBuf>) -> Self {
+        AllowedPath { path:      path.into(),
+                      read_only: true, }
+    }
+
+    pub fn writable(path: impl Into<PathBuf>) -> Self {
+        AllowedPath { path:      path.into(),
+                      read_only: false, }
+    }
+}
+
+impl FromStr for AllowedPath {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (path, read_only) = if value.ends_with(":ro") {
+            (&value[..value.len() - 3], true)
+        } else if value.ends_with(":rw") {
+            (&value[..value.len() - 3], false)
+        } else {
+            (value, false)
+        };
+        let path = PathBuf::from(path);
+        if path.is_absolute() {
+            Ok(AllowedPath { path, read_only })
+        } else {
+            Err(format!("'{}' is not a valid path to allow, expected an \
+                         absolute path, optionally followed by :ro (ex: \
+                         /srv/media:ro)",
+                        value))
+        }
+    }
+}
+
+impl fmt::Display for AllowedPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.read_only {
+            write!(f, "{}:ro", self.path.display())
+        } else {
+            write!(f, "{}", self.path.display())
+        }
+    }
+}
+
+pub fn validate_allowed_path(value: &str) -> Result<(), String> {
+    value.parse::<AllowedPath>().map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn port_mapping_from_str() {
+        assert_eq!("8080:80".parse(),
+                   Ok(PortMapping { host:    8080,
+                                    service: 80, }));
+        assert!("8080".parse::<PortMapping>().is_err());
+        assert!("0:80".parse::<PortMapping>().is_err());
+        assert!("8080:70000".parse::<PortMapping>().is_err());
+        assert!("http:80".parse::<PortMapping>().is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn allowed_path_from_str() {
+        assert_eq!("/srv/media:ro".parse(),
+                   Ok(AllowedPath::read_only("/srv/media")));
+        assert_eq!("/srv/uploads".parse(),
+                   Ok(AllowedPath::writable("/srv/uploads")));
+        assert_eq!("/srv/uploads:rw".parse(),
+                   Ok(AllowedPath::writable("/srv/uploads")));
+        assert!("srv/media".parse::<AllowedPath>().is_err());
+        assert_eq!(AllowedPath::read_only("/srv/media").to_string(),
+                   "/srv/media:ro");
+    }
+}
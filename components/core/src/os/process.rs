@@ -34,7 +34,9 @@ use crate::{error::Error,
             util};
 use serde_derive::{Deserialize,
                    Serialize};
-use std::{fmt,
+use std::{convert::TryFrom,
+          fmt,
+          path::PathBuf,
           result,
           str::FromStr,
           time::Duration};
@@ -73,6 +75,276 @@ impl From<ShutdownTimeout> for Duration {
     fn from(timeout: ShutdownTimeout) -> Self { Duration::from_secs(timeout.0.into()) }
 }
 
+/// How a service's process is set up by the Launcher, beyond its environment, the user it runs
+/// as and its isolation.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ProcessSettings {
+    /// The file mode creation mask of the service, rather than the Launcher's.
+    pub umask:       Option<Umask>,
+    /// The working directory of the service, rather than the Launcher's.
+    pub working_dir: Option<PathBuf>,
+    /// Resource limits of the service, rather than the Launcher's.
+    pub ulimits:     Vec<Ulimit>,
+    /// The niceness of the service, from -20 (the most favorable scheduling) to 19.
+    pub nice:        Option<i32>,
+    /// The IO scheduling class and priority of the service.
+    pub io_priority: Option<IoPriority>,
+    /// The names of the sockets passed to the Launcher by systemd socket activation that the
+    /// service takes over.
+    pub listen_fds:  Vec<String>,
+}
+
+/// A file mode creation mask, given in octal, e.g. `0027`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Umask(u32);
+
+impl FromStr for Umask {
+    type Err = String;
+
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        match u32::from_str_radix(s, 8) {
+            Ok(mask) if mask <= 0o777 => Ok(Umask(mask)),
+            _ => {
+                Err(format!("'{}' is not a valid umask, expected an octal \
+                             mode of at most 0777 (ex: 0027)",
+                            s))
+            }
+        }
+    }
+}
+
+impl TryFrom<u32> for Umask {
+    type Error = String;
+
+    fn try_from(mask: u32) -> result::Result<Self, Self::Error> {
+        if mask <= 0o777 {
+            Ok(Umask(mask))
+        } else {
+            Err(format!("{:o} is not a valid umask, expected an octal \
+                         mode of at most 0777",
+                        mask))
+        }
+    }
+}
+
+impl fmt::Display for Umask {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { write!(f, "{:04o}", self.0) }
+}
+
+impl From<Umask> for u32 {
+    fn from(umask: Umask) -> Self { umask.0 }
+}
+
+pub fn validate_umask(value: &str) -> result::Result<(), String> {
+    value.parse::<Umask>().map(|_| ())
+}
+
+/// A resource that can be limited per service.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum UlimitResource {
+    /// The size of core files, in bytes.
+    Core,
+    /// The memory that can be locked into RAM, in bytes.
+    Memlock,
+    /// The number of open file descriptors.
+    Nofile,
+    /// The number of processes of the service's user.
+    Nproc,
+}
+
+impl FromStr for UlimitResource {
+    type Err = String;
+
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        match s {
+            "core" => Ok(UlimitResource::Core),
+            "memlock" => Ok(UlimitResource::Memlock),
+            "nofile" => Ok(UlimitResource::Nofile),
+            "nproc" => Ok(UlimitResource::Nproc),
+            _ => {
+                Err(format!("'{}' is not a resource that can be limited, \
+                             expected one of core, memlock, nofile or nproc",
+                            s))
+            }
+        }
+    }
+}
+
+impl fmt::Display for UlimitResource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            UlimitResource::Core => "core",
+            UlimitResource::Memlock => "memlock",
+            UlimitResource::Nofile => "nofile",
+            UlimitResource::Nproc => "nproc",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// A resource limit of a service, given as `RESOURCE=SOFT[:HARD]`, where each limit is a number
+/// or `unlimited`, e.g. `nofile=65536` or `core=0:unlimited`. The hard limit defaults to the soft
+/// one.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Ulimit {
+    pub resource: UlimitResource,
+    /// The soft limit, or `None` if unlimited.
+    pub soft:     Option<u64>,
+    /// The hard limit, or `None` if unlimited.
+    pub hard:     Option<u64>,
+}
+
+impl FromStr for Ulimit {
+    type Err = String;
+
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        let invalid = || {
+            format!("'{}' is not a valid ulimit, expected RESOURCE=SOFT[:HARD] (ex: nofile=65536)",
+                    s)
+        };
+        let parse_limit = |limit: &str| {
+            match limit.trim() {
+                "unlimited" => Ok(None),
+                limit => limit.parse::<u64>().map(Some).map_err(|_| invalid()),
+            }
+        };
+        let mut parts = s.splitn(2, '=');
+        let resource = parts.next().unwrap_or_default().trim().parse()?;
+        let mut limits = parts.next().ok_or_else(invalid)?.splitn(2, ':');
+        let soft = parse_limit(limits.next().unwrap_or_default())?;
+        let hard = match limits.next() {
+            Some(hard) => parse_limit(hard)?,
+            None => soft,
+        };
+        let exceeds_hard = match (soft, hard) {
+            (None, Some(_)) => true,
+            (Some(soft), Some(hard)) => soft > hard,
+            (_, None) => false,
+        };
+        if exceeds_hard {
+            return Err(format!("'{}' is not a valid ulimit, its soft limit \
+                                exceeds its hard limit",
+                               s));
+        }
+        Ok(Ulimit { resource,
+                    soft,
+                    hard })
+    }
+}
+
+impl fmt::Display for Ulimit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let limit = |limit: Option<u64>| limit.map_or("unlimited".to_string(), |l| l.to_string());
+        if self.soft == self.hard {
+            write!(f, "{}={}", self.resource, limit(self.soft))
+        } else {
+            write!(f,
+                   "{}={}:{}",
+                   self.resource,
+                   limit(self.soft),
+                   limit(self.hard))
+        }
+    }
+}
+
+pub fn validate_ulimit(value: &str) -> result::Result<(), String> {
+    value.parse::<Ulimit>().map(|_| ())
+}
+
+pub fn validate_nice(value: &str) -> result::Result<(), String> {
+    match value.parse::<i32>() {
+        Ok(nice) if (-20..=19).contains(&nice) => Ok(()),
+        _ => {
+            Err(format!("'{}' is not a valid niceness; expected a number \
+                         between -20 and 19",
+                        value))
+        }
+    }
+}
+
+/// The IO scheduling class and priority of a service, given as `CLASS[:LEVEL]`, e.g.
+/// `best-effort:7` or `idle`. The levels of the `realtime` and `best-effort` classes go from 0,
+/// the highest priority, to 7, and default to 4; the `idle` class has no levels.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum IoPriority {
+    /// Served first, whatever else is going on.
+    Realtime(u8),
+    BestEffort(u8),
+    /// Only served when no other process needs the disk.
+    Idle,
+}
+
+impl IoPriority {
+    const DEFAULT_LEVEL: u8 = 4;
+
+    /// The priority in the form of the `ioprio_set` system call.
+    pub fn ioprio(self) -> i32 {
+        const CLASS_SHIFT: i32 = 13;
+        match self {
+            IoPriority::Realtime(level) => 1 << CLASS_SHIFT | i32::from(level),
+            IoPriority::BestEffort(level) => 2 << CLASS_SHIFT | i32::from(level),
+            IoPriority::Idle => 3 << CLASS_SHIFT,
+        }
+    }
+}
+
+impl FromStr for IoPriority {
+    type Err = String;
+
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        let invalid = || {
+            format!("'{}' is not a valid IO priority, expected realtime[:LEVEL], \
+                     best-effort[:LEVEL] or idle, where LEVEL is between 0 and 7",
+                    s)
+        };
+        let mut parts = s.splitn(2, ':');
+        let class = parts.next().unwrap_or_default();
+        let level = match parts.next() {
+            Some(level) => {
+                match level.parse() {
+                    Ok(level) if level <= 7 => Some(level),
+                    _ => return Err(invalid()),
+                }
+            }
+            None => None,
+        };
+        match (class, level) {
+            ("realtime", level) => Ok(IoPriority::Realtime(level.unwrap_or(Self::DEFAULT_LEVEL))),
+            ("best-effort", level) => {
+                Ok(IoPriority::BestEffort(level.unwrap_or(Self::DEFAULT_LEVEL)))
+            }
+            ("idle", None) => Ok(IoPriority::Idle),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl fmt::Display for IoPriority {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IoPriority::Realtime(level) => write!(f, "realtime:{}", level),
+            IoPriority::BestEffort(level) => write!(f, "best-effort:{}", level),
+            IoPriority::Idle => write!(f, "idle"),
+        }
+    }
+}
+
+pub fn validate_io_priority(value: &str) -> result::Result<(), String> {
+    value.parse::<IoPriority>().map(|_| ())
+}
+
+/// Working directories are given as absolute paths, as the directory the Launcher runs in is not
+/// meaningful to the service.
+pub fn validate_working_dir(value: &str) -> result::Result<(), String> {
+    if PathBuf::from(value).is_absolute() {
+        Ok(())
+    } else {
+        Err(format!("'{}' is not a valid working directory, expected \
+                     an absolute path",
+                    value))
+    }
+}
+
 // This defines a handful of Unix signals that we want to deal with,
 // but we are making it available on Windows as well for situations
 // where a Windows CLI is communicating with a Linux Supervisor.
@@ -173,6 +445,53 @@ impl From<ShutdownSignal> for Signal {
 mod test {
     use super::*;
 
+    #[test]
+    fn umasks_are_parsed_as_octal() {
+        assert_eq!("0027".parse::<Umask>().map(u32::from), Ok(0o027));
+        assert_eq!("77".parse::<Umask>().map(u32::from), Ok(0o077));
+        assert_eq!("0027".parse::<Umask>().unwrap().to_string(), "0027");
+        assert!("0800".parse::<Umask>().is_err());
+        assert!("1777".parse::<Umask>().is_err());
+        assert!("-1".parse::<Umask>().is_err());
+    }
+
+    #[test]
+    fn ulimits_from_str() {
+        assert_eq!("nofile=65536".parse(),
+                   Ok(Ulimit { resource: UlimitResource::Nofile,
+                               soft:     Some(65536),
+                               hard:     Some(65536), }));
+        assert_eq!("core=0:unlimited".parse(),
+                   Ok(Ulimit { resource: UlimitResource::Core,
+                               soft:     Some(0),
+                               hard:     None, }));
+        assert_eq!("memlock=unlimited".parse(),
+                   Ok(Ulimit { resource: UlimitResource::Memlock,
+                               soft:     None,
+                               hard:     None, }));
+        assert_eq!("nproc=1024:4096".parse::<Ulimit>().unwrap().to_string(),
+                   "nproc=1024:4096");
+        assert_eq!("core=0:unlimited".parse::<Ulimit>().unwrap().to_string(),
+                   "core=0:unlimited");
+        assert!("nofile".parse::<Ulimit>().is_err());
+        assert!("stack=8192".parse::<Ulimit>().is_err());
+        assert!("nofile=lots".parse::<Ulimit>().is_err());
+        assert!("nofile=4096:1024".parse::<Ulimit>().is_err());
+        assert!("nofile=unlimited:1024".parse::<Ulimit>().is_err());
+    }
+
+    #[test]
+    fn io_priorities_from_str() {
+        assert_eq!("realtime:0".parse(), Ok(IoPriority::Realtime(0)));
+        assert_eq!("best-effort".parse(), Ok(IoPriority::BestEffort(4)));
+        assert_eq!("idle".parse(), Ok(IoPriority::Idle));
+        assert_eq!(IoPriority::BestEffort(7).to_string(), "best-effort:7");
+        assert_eq!(IoPriority::BestEffort(7).ioprio(), 0x4007);
+        assert!("idle:3".parse::<IoPriority>().is_err());
+        assert!("best-effort:8".parse::<IoPriority>().is_err());
+        assert!("low".parse::<IoPriority>().is_err());
+    }
+
     #[test]
     fn signal_names_are_only_accepted_as_uppercase() {
         assert_eq!(Signal::HUP, "HUP".parse().unwrap());
@@ -1,11 +1,29 @@
-use crate::os::process::can_run_services_as_svc_user;
+use crate::os::{isolation::{AllowedPath,
+                            Isolation},
+                process::{can_run_services_as_svc_user,
+                          IoPriority,
+                          ProcessSettings,
+                          Ulimit,
+                          UlimitResource,
+                          Umask},
+                seccomp::SeccompProfile,
+                socket_activation::{Handoff,
+                                    ListenFd}};
+#[cfg(target_os = "linux")]
+use crate::{fs,
+            os::sandbox::Sandbox};
 use nix::unistd::{setgid,
                   setuid,
                   Gid,
                   Uid};
-use std::{ffi::OsStr,
+use std::{collections::BTreeMap,
+          ffi::{CString,
+                OsStr,
+                OsString},
           io,
-          os::unix::process::CommandExt,
+          os::unix::{ffi::OsStrExt,
+                     process::CommandExt},
+          path::Path,
           process::{Command,
                     Stdio},
           result};
@@ -21,7 +39,125 @@ pub fn hook_command<X, I, K, V>(executable: X, env: I, ids: Option<(Uid, Gid)>)
           V: AsRef<OsStr>
 {
     let mut cmd = Command::new(executable);
+    configure_hook_command(&mut cmd, env, ids);
+    cmd
+}
+
+/// Prepare a `Command` to execute a lifecycle hook in a restricted environment. The hook sees only
+/// `env`, starts in `work_dir` and, unless `network` is true, runs in a network namespace of its
+/// own, which has nothing but a loopback interface that is down.
+///
+/// Network namespaces only exist on Linux; elsewhere the hook keeps its network access.
+pub fn sandboxed_hook_command<X, I, K, V, P>(executable: X,
+                                             env: I,
+                                             ids: Option<(Uid, Gid)>,
+                                             work_dir: P,
+                                             network: bool)
+                                             -> Command
+    where X: AsRef<OsStr>,
+          I: IntoIterator<Item = (K, V)>,
+          K: AsRef<OsStr>,
+          V: AsRef<OsStr>,
+          P: AsRef<Path>
+{
+    let mut cmd = Command::new(executable);
+    cmd.env_clear().current_dir(work_dir);
+    // The namespace must be entered before `with_user_and_group_information` drops privileges
+    if !network {
+        without_network(&mut cmd);
+    }
+    configure_hook_command(&mut cmd, env, ids);
+    cmd
+}
 
+/// Prepare a `Command` to execute the `run` hook of a service. Like any hook, it runs in a
+/// process group of its own. Before it drops privileges, it joins the cgroup at `cgroup`, if any,
+/// so that it is accounted and limited from the start, and enters the namespaces and the
+/// filesystem sandbox `isolation` asks for. Once it has, it applies the seccomp profile of
+/// `isolation`, which fails if the profile can't be read. It runs with the umask, in the working
+/// directory, and with the resource limits and scheduling priorities of `settings`, if any; these
+/// are set before dropping privileges, so that they can be raised above those of the Launcher.
+/// Last of all, it takes over `listen_fds`, if any, with the `LISTEN_FDS` protocol.
+pub fn service_command<X, I, K, V>(executable: X,
+                                   env: I,
+                                   ids: Option<(Uid, Gid)>,
+                                   cgroup: Option<&Path>,
+                                   isolation: &Isolation,
+                                   settings: &ProcessSettings,
+                                   listen_fds: &[ListenFd])
+                                   -> io::Result<Command>
+    where X: AsRef<OsStr>,
+          I: IntoIterator<Item = (K, V)>,
+          K: AsRef<OsStr>,
+          V: AsRef<OsStr>
+{
+    let env = env.into_iter()
+                 .map(|(key, value)| (key.as_ref().to_os_string(), value.as_ref().to_os_string()))
+                 .collect::<BTreeMap<OsString, OsString>>();
+    let handoff = if listen_fds.is_empty() {
+        None
+    } else {
+        Some(Handoff::new(executable.as_ref(), env.clone(), listen_fds)?)
+    };
+    let mut cmd = Command::new(executable);
+    cmd.stdin(Stdio::null())
+       .stdout(Stdio::piped())
+       .stderr(Stdio::piped())
+       .envs(env);
+    if let Some(ref working_dir) = settings.working_dir {
+        cmd.current_dir(working_dir);
+    }
+    if let Some(umask) = settings.umask {
+        with_umask(&mut cmd, umask);
+    }
+    if !settings.ulimits.is_empty() {
+        with_ulimits(&mut cmd, settings.ulimits.clone());
+    }
+    if let Some(nice) = settings.nice {
+        with_nice(&mut cmd, nice);
+    }
+    if let Some(io_priority) = settings.io_priority {
+        with_io_priority(&mut cmd, io_priority);
+    }
+
+    // The PID namespace must be entered after the process group is set, so that the process the
+    // Launcher signals leads it.
+    with_own_process_group(&mut cmd);
+    if let Some(cgroup) = cgroup {
+        in_cgroup(&mut cmd, cgroup);
+    }
+    // The network namespace is entered first, so that the process the Launcher knows about is
+    // in it as well.
+    if isolation.network_namespace {
+        with_own_network_namespace(&mut cmd);
+    }
+    if isolation.pid_namespace {
+        with_own_pid_namespace(&mut cmd);
+    }
+    // The sandbox is entered after the PID namespace, so that its `/proc` belongs to the
+    // namespace.
+    if isolation.filesystem_sandbox {
+        with_filesystem_sandbox(&mut cmd,
+                                &isolation.allowed_paths,
+                                settings.working_dir.as_deref())?;
+    }
+    if let Some((uid, gid)) = ids {
+        with_user_and_group_information(&mut cmd, uid, gid);
+    }
+    if let Some(ref profile) = isolation.seccomp_profile {
+        with_seccomp_profile(&mut cmd, profile)?;
+    }
+    if let Some(handoff) = handoff {
+        with_listen_fds(&mut cmd, handoff);
+    }
+    Ok(cmd)
+}
+
+fn configure_hook_command<I, K, V>(cmd: &mut Command, env: I, ids: Option<(Uid, Gid)>)
+    where I: IntoIterator<Item = (K, V)>,
+          K: AsRef<OsStr>,
+          V: AsRef<OsStr>
+{
     // NOTE: CommandExt::uid and CommandExt::guid should *not* be
     // called here! They are set in `with_user_and_group_information`;
     // see there for further details.
@@ -30,14 +166,382 @@ pub fn hook_command<X, I, K, V>(executable: X, env: I, ids: Option<(Uid, Gid)>)
        .stderr(Stdio::piped())
        .envs(env);
 
-    with_own_process_group(&mut cmd);
+    with_own_process_group(cmd);
     if let Some((uid, gid)) = ids {
-        with_user_and_group_information(&mut cmd, uid, gid);
+        with_user_and_group_information(cmd, uid, gid);
+    }
+}
+
+/// Ensures that the `Command` is executed in a network namespace of
+/// its own.
+#[cfg(target_os = "linux")]
+fn without_network(cmd: &mut Command) -> &mut Command {
+    unsafe {
+        cmd.pre_exec(unshare_network);
+    }
+    cmd
+}
+
+#[cfg(not(target_os = "linux"))]
+fn without_network(cmd: &mut Command) -> &mut Command { cmd }
+
+/// Move the calling process into a new network namespace.
+///
+/// Intended for use in a
+/// `std::os::unix::process::CommandExt::pre_exec` callback.
+#[cfg(target_os = "linux")]
+fn unshare_network() -> result::Result<(), io::Error> {
+    unsafe {
+        if libc::unshare(libc::CLONE_NEWNET) == 0 {
+            Ok(())
+        } else {
+            Err(io::Error::last_os_error())
+        }
+    }
+}
+
+/// Ensures that the `Command` is executed with `umask` as its file mode creation mask.
+fn with_umask(cmd: &mut Command, umask: Umask) -> &mut Command {
+    let mask = u32::from(umask) as libc::mode_t;
+    unsafe {
+        cmd.pre_exec(move || {
+               libc::umask(mask);
+               Ok(())
+           });
     }
+    cmd
+}
 
+/// Ensures that the `Command` is executed with the resource limits `ulimits`.
+fn with_ulimits(cmd: &mut Command, ulimits: Vec<Ulimit>) -> &mut Command {
+    unsafe {
+        cmd.pre_exec(move || {
+               for ulimit in &ulimits {
+                   set_ulimit(ulimit)?;
+               }
+               Ok(())
+           });
+    }
+    cmd
+}
+
+/// Set a resource limit of the calling process.
+///
+/// Intended for use in a
+/// `std::os::unix::process::CommandExt::pre_exec` callback.
+fn set_ulimit(ulimit: &Ulimit) -> io::Result<()> {
+    let limit = |limit: Option<u64>| limit.map_or(libc::RLIM_INFINITY, |l| l as libc::rlim_t);
+    let rlimit = libc::rlimit { rlim_cur: limit(ulimit.soft),
+                                rlim_max: limit(ulimit.hard), };
+    // The type of the resources differs between C libraries, so each is passed as is.
+    let result = unsafe {
+        match ulimit.resource {
+            UlimitResource::Core => libc::setrlimit(libc::RLIMIT_CORE, &rlimit),
+            UlimitResource::Memlock => libc::setrlimit(libc::RLIMIT_MEMLOCK, &rlimit),
+            UlimitResource::Nofile => libc::setrlimit(libc::RLIMIT_NOFILE, &rlimit),
+            UlimitResource::Nproc => libc::setrlimit(libc::RLIMIT_NPROC, &rlimit),
+        }
+    };
+    if result == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+/// Ensures that the `Command` is executed with the niceness `nice`.
+fn with_nice(cmd: &mut Command, nice: i32) -> &mut Command {
+    unsafe {
+        cmd.pre_exec(move || {
+               if libc::setpriority(libc::PRIO_PROCESS, 0, nice) == 0 {
+                   Ok(())
+               } else {
+                   Err(io::Error::last_os_error())
+               }
+           });
+    }
     cmd
 }
 
+/// Ensures that the `Command` is executed with the IO priority `io_priority`.
+#[cfg(target_os = "linux")]
+fn with_io_priority(cmd: &mut Command, io_priority: IoPriority) -> &mut Command {
+    const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+    let ioprio = io_priority.ioprio();
+    unsafe {
+        cmd.pre_exec(move || {
+               if libc::syscall(libc::SYS_ioprio_set, IOPRIO_WHO_PROCESS, 0, ioprio) == 0 {
+                   Ok(())
+               } else {
+                   Err(io::Error::last_os_error())
+               }
+           });
+    }
+    cmd
+}
+
+#[cfg(not(target_os = "linux"))]
+fn with_io_priority(cmd: &mut Command, _io_priority: IoPriority) -> &mut Command { cmd }
+
+/// Ensures that the `Command` takes over the sockets of `handoff`. The process execs itself, so
+/// this must be the last `pre_exec` callback.
+fn with_listen_fds(cmd: &mut Command, mut handoff: Handoff) -> &mut Command {
+    unsafe {
+        cmd.pre_exec(move || Err(handoff.exec()));
+    }
+    cmd
+}
+
+/// Ensures that the `Command` is executed in the cgroup at `cgroup`. Failing to join it is not
+/// fatal; the process keeps running in the cgroup of its parent.
+fn in_cgroup<'a>(cmd: &'a mut Command, cgroup: &Path) -> &'a mut Command {
+    let procs = CString::new(cgroup.join("cgroup.procs").as_os_str().as_bytes()).ok();
+    unsafe {
+        cmd.pre_exec(move || {
+               if let Some(ref procs) = procs {
+                   // Writing 0 moves the writing process
+                   let fd = libc::open(procs.as_ptr(), libc::O_WRONLY);
+                   if fd >= 0 {
+                       libc::write(fd, b"0".as_ptr() as *const libc::c_void, 1);
+                       libc::close(fd);
+                   }
+               }
+               Ok(())
+           });
+    }
+    cmd
+}
+
+/// Ensures that the `Command` is executed in a network namespace of
+/// its own, with its loopback interface up.
+#[cfg(target_os = "linux")]
+fn with_own_network_namespace(cmd: &mut Command) -> &mut Command {
+    unsafe {
+        cmd.pre_exec(enter_network_namespace);
+    }
+    cmd
+}
+
+#[cfg(not(target_os = "linux"))]
+fn with_own_network_namespace(cmd: &mut Command) -> &mut Command { cmd }
+
+/// The part of `struct ifreq` needed to get and set the flags of an
+/// interface.
+#[cfg(target_os = "linux")]
+#[repr(C)]
+struct InterfaceFlagsRequest {
+    name:     [libc::c_char; libc::IFNAMSIZ],
+    flags:    libc::c_short,
+    _padding: [u8; 22],
+}
+
+/// Move the calling process into a new network namespace, and bring
+/// up its loopback interface, so that the processes in it can talk to
+/// each other.
+///
+/// Intended for use in a
+/// `std::os::unix::process::CommandExt::pre_exec` callback.
+#[cfg(target_os = "linux")]
+fn enter_network_namespace() -> result::Result<(), io::Error> {
+    unshare_network()?;
+    unsafe {
+        let socket = libc::socket(libc::AF_INET, libc::SOCK_DGRAM, 0);
+        if socket < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let mut request: InterfaceFlagsRequest = std::mem::zeroed();
+        for (to, from) in request.name.iter_mut().zip(b"lo") {
+            *to = *from as libc::c_char;
+        }
+        let result = if libc::ioctl(socket, libc::SIOCGIFFLAGS as _, &mut request) == 0 {
+            request.flags |= libc::IFF_UP as libc::c_short;
+            libc::ioctl(socket, libc::SIOCSIFFLAGS as _, &mut request)
+        } else {
+            -1
+        };
+        let result = if result == 0 {
+            Ok(())
+        } else {
+            Err(io::Error::last_os_error())
+        };
+        libc::close(socket);
+        result
+    }
+}
+
+/// Ensures that the `Command` is executed in a filesystem sandbox that
+/// only has the system directories and `allowed_paths` of the host.
+#[cfg(target_os = "linux")]
+fn with_filesystem_sandbox<'a>(cmd: &'a mut Command,
+                               allowed_paths: &[AllowedPath],
+                               working_dir: Option<&Path>)
+                               -> io::Result<&'a mut Command> {
+    let root = fs::launcher_root_path(None::<&Path>).join("sandbox");
+    let sandbox = Sandbox::new(&root, allowed_paths, working_dir)?;
+    unsafe {
+        cmd.pre_exec(move || sandbox.enter());
+    }
+    Ok(cmd)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn with_filesystem_sandbox<'a>(cmd: &'a mut Command,
+                               _allowed_paths: &[AllowedPath],
+                               _working_dir: Option<&Path>)
+                               -> io::Result<&'a mut Command> {
+    Ok(cmd)
+}
+
+/// Ensures that the `Command` is executed restricted to the system
+/// calls `profile` allows. The filter is compiled beforehand, as
+/// nothing may be allocated between `fork` and `exec`.
+#[cfg(target_os = "linux")]
+fn with_seccomp_profile<'a>(cmd: &'a mut Command,
+                            profile: &SeccompProfile)
+                            -> io::Result<&'a mut Command> {
+    let filter = crate::os::seccomp::Filter::new(&profile.load()?)?;
+    unsafe {
+        cmd.pre_exec(move || filter.apply());
+    }
+    Ok(cmd)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn with_seccomp_profile<'a>(cmd: &'a mut Command,
+                            _profile: &SeccompProfile)
+                            -> io::Result<&'a mut Command> {
+    Ok(cmd)
+}
+
+/// Ensures that the `Command` is executed in a PID namespace of its
+/// own, under a minimal init process.
+#[cfg(target_os = "linux")]
+fn with_own_pid_namespace(cmd: &mut Command) -> &mut Command {
+    unsafe {
+        cmd.pre_exec(enter_pid_namespace);
+    }
+    cmd
+}
+
+#[cfg(not(target_os = "linux"))]
+fn with_own_pid_namespace(cmd: &mut Command) -> &mut Command { cmd }
+
+/// The signals which stop or reload a service, passed on to it by the
+/// processes `enter_pid_namespace` puts between it and the Launcher.
+#[cfg(target_os = "linux")]
+const FORWARDED_SIGNALS: &[libc::c_int] = &[libc::SIGTERM,
+                                            libc::SIGINT,
+                                            libc::SIGHUP,
+                                            libc::SIGQUIT,
+                                            libc::SIGUSR1,
+                                            libc::SIGUSR2];
+
+/// Fork the calling process into a new PID namespace, returning in a
+/// child of the namespace's init process.
+///
+/// `unshare` only places the *children* of the calling process in the
+/// new namespace. The parent stays behind as the process its own
+/// parent knows about, and its child is the init process of the
+/// namespace. The kernel only delivers the signals an init process
+/// handles or blocks, so rather than being the service, it blocks all
+/// signals and forks once more, returning in that child. Both the
+/// parent and the init process pass the `FORWARDED_SIGNALS` on, the
+/// init process to every process of the namespace, and exit once
+/// their child does, the way it did. The init process also reaps the
+/// orphans of the namespace, leaves the process group of the parent
+/// so that a signal to the group reaches the service only once, and
+/// is killed along with the parent. The kernel kills whatever is left
+/// in the namespace when its init process exits.
+///
+/// Neither process execs, so they close every file descriptor but the
+/// standard ones, so that `Command::spawn` does not wait for them to
+/// exit.
+///
+/// Intended for use in a
+/// `std::os::unix::process::CommandExt::pre_exec` callback.
+#[cfg(target_os = "linux")]
+fn enter_pid_namespace() -> result::Result<(), io::Error> {
+    unsafe {
+        if libc::unshare(libc::CLONE_NEWPID) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let mut all_signals: libc::sigset_t = std::mem::zeroed();
+        let mut previous_mask: libc::sigset_t = std::mem::zeroed();
+        libc::sigfillset(&mut all_signals);
+        libc::sigprocmask(libc::SIG_SETMASK, &all_signals, &mut previous_mask);
+        match libc::fork() {
+            -1 => return Err(io::Error::last_os_error()),
+            0 => {}
+            init => {
+                close_non_standard_fds();
+                wait_forwarding_signals(init, init)
+            }
+        }
+        if libc::setpgid(0, 0) != 0 || libc::prctl(libc::PR_SET_PDEATHSIG, libc::SIGKILL) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        match libc::fork() {
+            -1 => Err(io::Error::last_os_error()),
+            0 => {
+                libc::sigprocmask(libc::SIG_SETMASK, &previous_mask, std::ptr::null_mut());
+                Ok(())
+            }
+            service => {
+                close_non_standard_fds();
+                // -1 stands for every process of the namespace but its init process
+                wait_forwarding_signals(service, -1)
+            }
+        }
+    }
+}
+
+/// Wait for `child` to exit, with every signal blocked, then exit the
+/// way it did. Meanwhile, the `FORWARDED_SIGNALS` are sent on to
+/// `forward_to`, and any other child that exits is reaped.
+#[cfg(target_os = "linux")]
+unsafe fn wait_forwarding_signals(child: libc::pid_t, forward_to: libc::pid_t) -> ! {
+    let mut signals: libc::sigset_t = std::mem::zeroed();
+    libc::sigemptyset(&mut signals);
+    libc::sigaddset(&mut signals, libc::SIGCHLD);
+    for signal in FORWARDED_SIGNALS {
+        libc::sigaddset(&mut signals, *signal);
+    }
+    loop {
+        match libc::sigwaitinfo(&signals, std::ptr::null_mut()) {
+            -1 => {}
+            libc::SIGCHLD => {
+                let mut status = 0;
+                loop {
+                    match libc::waitpid(-1, &mut status, libc::WNOHANG) {
+                        -1 | 0 => break,
+                        pid if pid == child => {
+                            if libc::WIFSIGNALED(status) {
+                                libc::_exit(128 + libc::WTERMSIG(status));
+                            }
+                            libc::_exit(libc::WEXITSTATUS(status))
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            signal => {
+                libc::kill(forward_to, signal);
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+unsafe fn close_non_standard_fds() {
+    let max_fd = match libc::sysconf(libc::_SC_OPEN_MAX) {
+        -1 => 1024,
+        max => max.min(65536),
+    };
+    for fd in 3..max_fd as libc::c_int {
+        libc::close(fd);
+    }
+}
+
 /// Ensures that the `Command` is executed within its own process
 /// group, and not that of its parent process.
 ///
@@ -60,6 +60,8 @@ use winapi::{shared::{minwindef::{BOOL,
                                       LPSTARTUPINFOW,
                                       PROCESS_INFORMATION,
                                       STARTUPINFOW},
+                  profinfo::{PI_NOUI,
+                             PROFILEINFOW},
                   synchapi,
                   userenv,
                   winbase::{CREATE_NEW_PROCESS_GROUP,
@@ -136,6 +138,11 @@ const HANDLE_FLAG_INHERIT: DWORD = 0x0000_0001;
 
 const LOGON32_LOGON_SERVICE: DWORD = 5;
 
+/// Group Managed Service Accounts have no password that we could know. Logging one on as a
+/// service with this well known value makes LSA retrieve the account's managed password from
+/// Active Directory instead.
+const MANAGED_SERVICE_ACCOUNT_PASSWORD: &str = "_SA_{262E99C9-6160-4871-ACEC-4E61736B6F21}";
+
 enum ParsePart {
     Key,
     Value,
@@ -164,6 +171,9 @@ impl ServiceCredential {
         };
         let pass = match svc_encrypted_password {
             Some(password) => decrypt(&password.to_string())?,
+            None if is_managed_service_account(&user) => {
+                MANAGED_SERVICE_ACCOUNT_PASSWORD.to_string()
+            }
             None => String::new(),
         };
         Ok(Self { user,
@@ -171,6 +181,8 @@ impl ServiceCredential {
                   password: pass })
     }
 
+    pub fn is_managed_service_account(&self) -> bool { is_managed_service_account(&self.user) }
+
     pub fn is_current_user(&self) -> bool {
         self.user == get_current_username().ok().flatten().unwrap_or_default()
     }
@@ -186,10 +198,15 @@ impl ServiceCredential {
     }
 }
 
+/// Managed service account names end in a `$`, like the computer accounts they are modeled on.
+fn is_managed_service_account(user: &str) -> bool { user.ends_with('$') }
+
 pub struct Child {
-    pub handle: Handle,
-    pub stdout: Option<ChildStdout>,
-    pub stderr: Option<ChildStderr>,
+    pub handle:  Handle,
+    pub stdout:  Option<ChildStdout>,
+    pub stderr:  Option<ChildStderr>,
+    /// The profile loaded for the child, which has to stay loaded for as long as it runs.
+    pub profile: Option<UserProfile>,
 }
 
 impl Child {
@@ -271,11 +288,12 @@ impl Child {
         let flags = CREATE_UNICODE_ENVIRONMENT | CREATE_NEW_PROCESS_GROUP;
 
         let cred = ServiceCredential::new(svc_user, svc_encrypted_password)?;
-        if cred.is_current_user() {
+        let profile = if cred.is_current_user() {
             create_process(cmd_str.as_mut_ptr(), flags, &os_env, &mut si, &mut pi)?;
+            None
         } else {
-            create_process_as_user(&cred, cmd_str.as_mut_ptr(), flags, env, &mut si, &mut pi)?;
-        }
+            create_process_as_user(&cred, cmd_str.as_mut_ptr(), flags, env, &mut si, &mut pi)?
+        };
 
         // We close the thread handle because we don't care about keeping
         // the thread id valid, and we aren't keeping the thread handle
@@ -283,7 +301,8 @@ impl Child {
         unsafe { handleapi::CloseHandle(pi.hThread) };
         Ok(Child { handle: Handle::new(pi.hProcess),
                    stdout: pipes.stdout.map(ChildStdout::from_inner),
-                   stderr: pipes.stderr.map(ChildStderr::from_inner), })
+                   stderr: pipes.stderr.map(ChildStderr::from_inner),
+                   profile })
     }
 
     pub fn id(&self) -> u32 { unsafe { processthreadsapi::GetProcessId(self.handle.raw()) as u32 } }
@@ -304,11 +323,34 @@ impl Child {
             }
             let mut status = 0;
             cvt(processthreadsapi::GetExitCodeProcess(self.handle.raw(), &mut status))?;
+            self.profile.take();
             Ok(ExitStatus(status))
         }
     }
 }
 
+/// The profile of a managed service account, loaded for a process running as it. It is unloaded
+/// when dropped, which must not happen before the process has exited.
+pub struct UserProfile {
+    token:   Handle,
+    profile: RawHandle,
+}
+
+impl Drop for UserProfile {
+    fn drop(&mut self) {
+        unsafe {
+            if userenv::UnloadUserProfile(self.token.raw(), self.profile.raw()) == 0 {
+                debug!("Failed to call UnloadUserProfile: {}",
+                       io::Error::last_os_error());
+            }
+        }
+    }
+}
+
+impl fmt::Debug for UserProfile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { f.pad("UserProfile { .. }") }
+}
+
 pub trait AsInner<Inner: ?Sized> {
     fn as_inner(&self) -> &Inner;
 }
@@ -891,13 +933,16 @@ fn create_process(command: LPWSTR,
     }
 }
 
+/// Spawns the process under the credential's account. Managed service accounts never log on
+/// interactively, so their profile is loaded first to give the process a usable `USERPROFILE`
+/// and registry hive. The profile is returned, to be unloaded once the process exits.
 fn create_process_as_user(credential: &ServiceCredential,
                           command: LPWSTR,
                           flags: DWORD,
                           env: &HashMap<String, String>,
                           si: LPSTARTUPINFOW,
                           pi: LPPROCESS_INFORMATION)
-                          -> Result<i32> {
+                          -> Result<Option<UserProfile>> {
     unsafe {
         let mut token = ptr::null_mut();
 
@@ -963,6 +1008,21 @@ fn create_process_as_user(credential: &ServiceCredential,
                                | sid::WRITE_DAC
                                | sid::WRITE_OWNER)?;
 
+        let profile = if credential.is_managed_service_account() {
+            let mut user = credential.user_wide().into_vec_with_nul();
+            let mut info: PROFILEINFOW = mem::zeroed();
+            info.dwSize = mem::size_of::<PROFILEINFOW>() as DWORD;
+            info.dwFlags = PI_NOUI;
+            info.lpUserName = user.as_mut_ptr();
+            if let Err(err) = cvt(userenv::LoadUserProfileW(token, &mut info)) {
+                return Err(Error::LoadUserProfileFailed(err));
+            }
+            Some(UserProfile { token:   Handle::new(token),
+                               profile: RawHandle::new(info.hProfile), })
+        } else {
+            None
+        };
+
         let mut os_env = create_user_environment(token, &mut env.clone())?;
         match cvt(CreateProcessAsUserW(token,
                                        ptr::null(),
@@ -976,7 +1036,7 @@ fn create_process_as_user(credential: &ServiceCredential,
                                        si,
                                        pi))
         {
-            Ok(process) => Ok(process),
+            Ok(_) => Ok(profile),
             Err(ref err) if err.raw_os_error() == Some(ERROR_PRIVILEGE_NOT_HELD as i32) => {
                 Err(Error::PrivilegeNotHeld)
             }
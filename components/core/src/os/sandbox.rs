@@ -0,0 +1,358 @@
+//! Filesystem sandboxes, which restrict what a service sees of the filesystem of the host to what
+//! it needs: the system directories, its packages and service directory, and any other path it is
+//! allowed. The Launcher sets up the sandbox of a service right before it execs it.
+//!
+//! A sandbox is a mount namespace whose root is an empty tmpfs, into which the allowed paths are
+//! bind mounted at the same place they have on the host, along with the mounts inside them. The
+//! service also gets a `/proc` of its own, which only shows the processes of its PID namespace
+//! when it has one, an empty `/tmp`, and a `/dev` with nothing but a few harmless devices and an
+//! empty `/dev/shm`.
+//! Nothing else of the host, such as the service directories of other services, and the secrets
+//! rendered into them, can be reached from the sandbox.
+
+use super::isolation::AllowedPath;
+use std::{env,
+          ffi::{CString,
+                OsStr},
+          fs,
+          io,
+          os::unix::ffi::OsStrExt,
+          path::{Path,
+                 PathBuf},
+          ptr};
+
+/// The system directories every sandbox sees, read-only, where they exist on the host.
+const SYSTEM_PATHS: &[&str] = &["/bin", "/etc", "/lib", "/lib64", "/sbin", "/sys", "/usr"];
+
+/// The devices of the host every sandbox sees, where they exist on the host.
+const DEVICES: &[&str] = &["/dev/full",
+                           "/dev/null",
+                           "/dev/random",
+                           "/dev/tty",
+                           "/dev/urandom",
+                           "/dev/zero"];
+
+/// The links of `/dev` to the file descriptors of a process, and where they point.
+const DEV_LINKS: &[(&str, &str)] = &[("fd", "/proc/self/fd"),
+                                     ("stdin", "/proc/self/fd/0"),
+                                     ("stdout", "/proc/self/fd/1"),
+                                     ("stderr", "/proc/self/fd/2")];
+
+/// Where the root of the host is moved to inside the sandbox, before it is detached.
+const OLD_ROOT: &str = ".old-root";
+
+/// A path of the host, and where it is mounted in the sandbox.
+struct BindMount {
+    source:    CString,
+    target:    CString,
+    /// The directories leading to `target` in the sandbox, from the outermost.
+    parents:   Vec<CString>,
+    is_dir:    bool,
+    read_only: bool,
+    /// Where the mounts of the host inside `source` are in the sandbox, if it is read-only.
+    submounts: Vec<CString>,
+}
+
+/// A filesystem sandbox, prepared ahead of the `fork` after which it is entered, as nothing may be
+/// allocated between `fork` and `exec`.
+pub struct Sandbox {
+    root:            CString,
+    proc:            CString,
+    tmp:             CString,
+    dev:             CString,
+    dev_shm:         CString,
+    dev_links:       Vec<(CString, CString)>,
+    old_root:        CString,
+    old_root_inside: CString,
+    work_dir:        CString,
+    mounts:          Vec<BindMount>,
+}
+
+impl Sandbox {
+    /// Prepare a sandbox built at `root`, an empty directory of the host, which is created if
+    /// need be. As each sandbox lives in a mount namespace of its own, several of them can be
+    /// built at the same `root`.
+    ///
+    /// Paths inside another allowed path are visible through it, and paths that don't exist are
+    /// skipped. The sandboxed process stays in `work_dir`, or the working directory of the
+    /// calling process if there is none, provided the sandbox has it.
+    pub fn new(root: &Path,
+               allowed_paths: &[AllowedPath],
+               work_dir: Option<&Path>)
+               -> io::Result<Self> {
+        fs::create_dir_all(root)?;
+        let mut paths = SYSTEM_PATHS.iter()
+                                    .map(|path| AllowedPath::read_only(*path))
+                                    .chain(DEVICES.iter().map(|path| AllowedPath::writable(*path)))
+                                    .chain(allowed_paths.iter().cloned())
+                                    .collect::<Vec<_>>();
+        // Sorting puts every path right after the paths it is inside of
+        paths.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let mount_points = mount_points()?;
+        let mut mounted: Vec<PathBuf> = Vec::new();
+        let mut mounts = Vec::new();
+        for allowed in paths {
+            if allowed.path.starts_with("/proc")
+               || mounted.iter().any(|path| allowed.path.starts_with(path))
+            {
+                continue;
+            }
+            let metadata = match fs::metadata(&allowed.path) {
+                Ok(metadata) => metadata,
+                Err(err) => {
+                    debug!("Not allowing {} in sandbox: {}",
+                           allowed.path.display(),
+                           err);
+                    continue;
+                }
+            };
+            let target = sandboxed(root, &allowed.path);
+            let mut parents = target.ancestors()
+                                    .skip(1)
+                                    .take_while(|parent| *parent != root)
+                                    .map(c_path)
+                                    .collect::<io::Result<Vec<_>>>()?;
+            parents.reverse();
+            let submounts = if allowed.read_only {
+                mount_points.iter()
+                            .filter(|point| {
+                                *point != &allowed.path && point.starts_with(&allowed.path)
+                            })
+                            .map(|point| c_path(&sandboxed(root, point)))
+                            .collect::<io::Result<Vec<_>>>()?
+            } else {
+                Vec::new()
+            };
+            mounts.push(BindMount { source: c_path(&allowed.path)?,
+                                    target: c_path(&target)?,
+                                    parents,
+                                    is_dir: metadata.is_dir(),
+                                    read_only: allowed.read_only,
+                                    submounts });
+            mounted.push(allowed.path);
+        }
+
+        Ok(Sandbox { root: c_path(root)?,
+                     proc: c_path(&root.join("proc"))?,
+                     tmp: c_path(&root.join("tmp"))?,
+                     dev: c_path(&root.join("dev"))?,
+                     dev_shm: c_path(&root.join("dev/shm"))?,
+                     dev_links: DEV_LINKS.iter()
+                                         .map(|(link, target)| {
+                                             Ok((c_path(Path::new(target))?,
+                                                 c_path(&root.join("dev").join(link))?))
+                                         })
+                                         .collect::<io::Result<Vec<_>>>()?,
+                     old_root: c_path(&root.join(OLD_ROOT))?,
+                     old_root_inside: c_path(&Path::new("/").join(OLD_ROOT))?,
+                     work_dir: match work_dir {
+                         Some(work_dir) => c_path(work_dir)?,
+                         None => c_path(&env::current_dir()?)?,
+                     },
+                     mounts })
+    }
+
+    /// Move the calling process into a new mount namespace, whose root is the sandbox. It stays
+    /// in its working directory if the sandbox has it, and moves to `/` otherwise.
+    ///
+    /// Intended for use in a
+    /// `std::os::unix::process::CommandExt::pre_exec` callback.
+    pub fn enter(&self) -> io::Result<()> {
+        unsafe {
+            check(libc::unshare(libc::CLONE_NEWNS))?;
+            // Keep the mounts of the sandbox from propagating to the host
+            check(libc::mount(ptr::null(),
+                              c_str(b"/\0"),
+                              ptr::null(),
+                              libc::MS_REC | libc::MS_PRIVATE,
+                              ptr::null()))?;
+            check(libc::mount(c_str(b"tmpfs\0"),
+                              self.root.as_ptr(),
+                              c_str(b"tmpfs\0"),
+                              libc::MS_NOSUID | libc::MS_NODEV,
+                              c_str(b"mode=0755\0") as *const libc::c_void))?;
+            libc::mkdir(self.proc.as_ptr(), 0o555);
+            check(libc::mount(c_str(b"proc\0"),
+                              self.proc.as_ptr(),
+                              c_str(b"proc\0"),
+                              libc::MS_NOSUID | libc::MS_NODEV | libc::MS_NOEXEC,
+                              ptr::null()))?;
+            libc::mkdir(self.tmp.as_ptr(), 0o755);
+            check(libc::mount(c_str(b"tmpfs\0"),
+                              self.tmp.as_ptr(),
+                              c_str(b"tmpfs\0"),
+                              libc::MS_NOSUID | libc::MS_NODEV,
+                              c_str(b"mode=1777\0") as *const libc::c_void))?;
+
+            for mount in &self.mounts {
+                for parent in &mount.parents {
+                    libc::mkdir(parent.as_ptr(), 0o755);
+                }
+                // Bind mounts need something to mount onto of the same kind as their source
+                if mount.is_dir {
+                    libc::mkdir(mount.target.as_ptr(), 0o755);
+                } else {
+                    let fd = libc::open(mount.target.as_ptr(),
+                                        libc::O_WRONLY | libc::O_CREAT | libc::O_CLOEXEC,
+                                        0o644);
+                    if fd >= 0 {
+                        libc::close(fd);
+                    }
+                }
+                check(libc::mount(mount.source.as_ptr(),
+                                  mount.target.as_ptr(),
+                                  ptr::null(),
+                                  libc::MS_BIND | libc::MS_REC,
+                                  ptr::null()))?;
+                if mount.read_only {
+                    // Remounting doesn't reach the mounts inside the target, which are remounted
+                    // one by one
+                    for target in Some(&mount.target).into_iter().chain(&mount.submounts) {
+                        check(libc::mount(ptr::null(),
+                                          target.as_ptr(),
+                                          ptr::null(),
+                                          libc::MS_BIND
+                                          | libc::MS_REMOUNT
+                                          | libc::MS_RDONLY
+                                          | libc::MS_REC,
+                                          ptr::null()))?;
+                    }
+                }
+            }
+
+            libc::mkdir(self.dev.as_ptr(), 0o755);
+            for (target, link) in &self.dev_links {
+                libc::symlink(target.as_ptr(), link.as_ptr());
+            }
+            libc::mkdir(self.dev_shm.as_ptr(), 0o1777);
+            check(libc::mount(c_str(b"tmpfs\0"),
+                              self.dev_shm.as_ptr(),
+                              c_str(b"tmpfs\0"),
+                              libc::MS_NOSUID | libc::MS_NODEV | libc::MS_NOEXEC,
+                              c_str(b"mode=1777\0") as *const libc::c_void))?;
+
+            libc::mkdir(self.old_root.as_ptr(), 0o700);
+            if libc::syscall(libc::SYS_pivot_root,
+                             self.root.as_ptr(),
+                             self.old_root.as_ptr())
+               != 0
+            {
+                return Err(io::Error::last_os_error());
+            }
+            check(libc::chdir(c_str(b"/\0")))?;
+            check(libc::umount2(self.old_root_inside.as_ptr(), libc::MNT_DETACH))?;
+            libc::rmdir(self.old_root_inside.as_ptr());
+            if libc::chdir(self.work_dir.as_ptr()) != 0 {
+                check(libc::chdir(c_str(b"/\0")))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Where `path` of the host is in a sandbox built at `root`.
+fn sandboxed(root: &Path, path: &Path) -> PathBuf {
+    root.join(path.strip_prefix("/").unwrap_or(path))
+}
+
+/// The mount points of the host, from the mount table of the calling process.
+fn mount_points() -> io::Result<Vec<PathBuf>> {
+    let mountinfo = fs::read("/proc/self/mountinfo")?;
+    let mut points = mountinfo.split(|byte| *byte == b'\n')
+                              .filter_map(|line| line.split(|byte| *byte == b' ').nth(4))
+                              .map(unescape)
+                              .collect::<Vec<_>>();
+    points.sort();
+    points.dedup();
+    Ok(points)
+}
+
+/// Undo the octal escapes of a field of the mount table, such as `\040` for a space.
+fn unescape(field: &[u8]) -> PathBuf {
+    let mut unescaped = Vec::with_capacity(field.len());
+    let mut rest = field;
+    while let Some((&byte, tail)) = rest.split_first() {
+        match tail.get(..3) {
+            Some(digits) if byte == b'\\' && digits.iter().all(|d| (b'0'..=b'7').contains(d)) => {
+                unescaped.push(digits.iter()
+                                     .fold(0u8, |n, d| n.wrapping_mul(8).wrapping_add(d - b'0')));
+                rest = &tail[3..];
+            }
+            _ => {
+                unescaped.push(byte);
+                rest = tail;
+            }
+        }
+    }
+    PathBuf::from(OsStr::from_bytes(&unescaped))
+}
+
+fn c_path(path: &Path) -> io::Result<CString> {
+    CString::new(path.as_os_str().as_bytes()).map_err(|err| {
+                                                 io::Error::new(io::ErrorKind::InvalidInput, err)
+                                             })
+}
+
+/// A C string from a nul-terminated byte string.
+fn c_str(bytes: &'static [u8]) -> *const libc::c_char { bytes.as_ptr() as *const libc::c_char }
+
+fn check(result: libc::c_int) -> io::Result<()> {
+    if result == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sandbox_mounts_each_allowed_path_once() {
+        let root = tempfile::tempdir().unwrap();
+        let svc = tempfile::tempdir().unwrap();
+        let config = svc.path().join("config");
+        fs::create_dir(&config).unwrap();
+        let sandbox = Sandbox::new(root.path(),
+                                   &[AllowedPath::writable(svc.path()),
+                                     AllowedPath::read_only(&config),
+                                     AllowedPath::read_only("/proc/self"),
+                                     AllowedPath::read_only("/does/not/exist")],
+                                   None).unwrap();
+
+        let sources = sandbox.mounts
+                             .iter()
+                             .map(|mount| mount.source.to_str().unwrap().to_string())
+                             .collect::<Vec<_>>();
+        assert!(sources.contains(&"/dev/null".to_string()));
+        assert!(!sources.contains(&"/dev".to_string()));
+        assert!(sources.contains(&svc.path().to_str().unwrap().to_string()));
+        assert!(!sources.contains(&config.to_str().unwrap().to_string()));
+        assert!(!sources.iter().any(|source| source.starts_with("/proc")));
+        assert!(!sources.contains(&"/does/not/exist".to_string()));
+
+        let svc_mount =
+            sandbox.mounts
+                   .iter()
+                   .find(|mount| mount.source.as_bytes() == svc.path().as_os_str().as_bytes())
+                   .unwrap();
+        assert_eq!(svc_mount.target.as_bytes(),
+                   sandboxed(root.path(), svc.path()).as_os_str().as_bytes());
+        assert_eq!(svc_mount.parents.last().map(|parent| parent.as_bytes()),
+                   sandboxed(root.path(), svc.path()).parent()
+                                                     .map(|parent| parent.as_os_str().as_bytes()));
+        assert!(svc_mount.is_dir);
+        assert!(!svc_mount.read_only);
+        assert!(svc_mount.submounts.is_empty());
+    }
+
+    #[test]
+    fn mount_table_fields_are_unescaped() {
+        assert_eq!(unescape(b"/mnt/a\\040b\\011c"),
+                   PathBuf::from("/mnt/a b\tc"));
+        assert_eq!(unescape(b"/mnt/a\\b"), PathBuf::from("/mnt/a\\b"));
+        assert!(mount_points().unwrap().contains(&PathBuf::from("/")));
+    }
+}
@@ -0,0 +1,763 @@
+//! Seccomp profiles, which restrict the system calls the processes of a service can make. The
+//! Launcher applies the profile of a service right before it execs it, once it has dropped
+//! privileges.
+//!
+//! A profile is a TOML file that gives the action taken for the system calls it names, and for
+//! those it doesn't:
+//!
+//! ```toml
+//! default_action = "allow"
+//!
+//! [[syscalls]]
+//! names = ["ptrace", "process_vm_readv", "process_vm_writev"]
+//! action = "errno"
+//! ```
+//!
+//! The actions are `allow`, `errno`, which fails the call with `EPERM`, `enosys`, which fails it
+//! as if the kernel didn't have it so that callers fall back to older system calls, `log`, which
+//! allows the call and records it in the audit log, and `kill`, which kills the process. A rule
+//! can be limited to the calls whose argument `index` (counting from 0) has any of `any_bits` set:
+//!
+//! ```toml
+//! [[syscalls]]
+//! names = ["clone"]
+//! action = "errno"
+//! arg = { index = 0, any_bits = 0x10000000 }
+//! ```
+//!
+//! When several rules match a system call, the first one wins. Names of system calls that don't
+//! exist on the architecture of the host are ignored, so that a profile can be shared between
+//! architectures.
+//!
+//! Seccomp only exists on Linux.
+
+use serde_derive::Deserialize;
+use std::{borrow::Cow,
+          convert::TryFrom,
+          fmt,
+          fs,
+          io,
+          path::PathBuf,
+          str::FromStr};
+
+/// The profile used by `--seccomp-profile default`. It allows everything but the system calls a
+/// service has no business making: those that administer the host, load kernel code, or inspect
+/// and change other processes or namespaces. `clone` may not create namespaces, and `clone3`,
+/// whose flags seccomp can't read, and the newer mount API fail as if they didn't exist.
+pub const DEFAULT_PROFILE: &str = r#"default_action = "allow"
+
+[[syscalls]]
+action = "errno"
+names = [
+    "_sysctl", "acct", "add_key", "bpf", "clock_adjtime", "clock_settime", "create_module",
+    "delete_module", "finit_module", "get_kernel_syms", "get_mempolicy", "init_module", "ioperm",
+    "iopl", "kcmp", "kexec_file_load", "kexec_load", "keyctl", "lookup_dcookie", "mbind", "mount",
+    "move_pages", "name_to_handle_at", "nfsservctl", "open_by_handle_at", "perf_event_open",
+    "pivot_root", "process_vm_readv", "process_vm_writev", "ptrace", "query_module", "quotactl",
+    "reboot", "request_key", "set_mempolicy", "setdomainname", "sethostname", "setns",
+    "settimeofday", "swapoff", "swapon", "sysfs", "umount2", "unshare", "uselib", "userfaultfd",
+    "ustat",
+]
+
+# CLONE_NEWNS, CLONE_NEWCGROUP, CLONE_NEWUTS, CLONE_NEWIPC, CLONE_NEWUSER, CLONE_NEWPID and
+# CLONE_NEWNET
+[[syscalls]]
+action = "errno"
+names = ["clone"]
+arg = { index = 0, any_bits = 0x7e020000 }
+
+[[syscalls]]
+action = "enosys"
+names = ["clone3", "fsconfig", "fsmount", "fsopen", "fspick", "move_mount", "open_tree"]
+"#;
+
+/// The seccomp profile of a service: `default` for `DEFAULT_PROFILE`, or the path of a profile
+/// file.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[serde(try_from = "String")]
+pub enum SeccompProfile {
+    Default,
+    File(PathBuf),
+}
+
+impl SeccompProfile {
+    /// Read the profile.
+    pub fn load(&self) -> io::Result<Profile> {
+        let content = match self {
+            SeccompProfile::Default => Cow::Borrowed(DEFAULT_PROFILE),
+            SeccompProfile::File(path) => Cow::Owned(fs::read_to_string(path)?),
+        };
+        toml::from_str(&content).map_err(|err| {
+                                    io::Error::new(io::ErrorKind::InvalidData,
+                                                   format!("Invalid seccomp profile {}: {}",
+                                                           self, err))
+                                })
+    }
+}
+
+impl FromStr for SeccompProfile {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim() {
+            "" => Err("A seccomp profile can't be empty".to_string()),
+            "default" => Ok(SeccompProfile::Default),
+            path => Ok(SeccompProfile::File(PathBuf::from(path))),
+        }
+    }
+}
+
+impl TryFrom<String> for SeccompProfile {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> { value.parse() }
+}
+
+impl fmt::Display for SeccompProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SeccompProfile::Default => write!(f, "default"),
+            SeccompProfile::File(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+/// Validate a seccomp profile given to the Supervisor, which can't resolve a relative path the
+/// way the one giving it meant.
+pub fn validate_seccomp_profile(value: &str) -> Result<(), String> {
+    match value.parse()? {
+        SeccompProfile::File(ref path) if !path.is_absolute() => {
+            Err(format!("'{}' is not a valid seccomp profile, expected \
+                         'default' or the absolute path of a profile file",
+                        value))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// What happens when a process makes a system call.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    Allow,
+    Errno,
+    Enosys,
+    Log,
+    Kill,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    pub default_action: Action,
+    #[serde(default)]
+    pub syscalls:       Vec<Rule>,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    pub names:  Vec<String>,
+    pub action: Action,
+    /// Only apply the rule to the calls this argument filter matches.
+    #[serde(default)]
+    pub arg:    Option<ArgFilter>,
+}
+
+/// Matches the system calls whose argument `index` has any of `any_bits` set.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ArgFilter {
+    pub index:    u8,
+    pub any_bits: u64,
+}
+
+#[cfg(target_os = "linux")]
+pub use self::filter::Filter;
+
+#[cfg(target_os = "linux")]
+mod filter {
+    use super::{Action,
+                ArgFilter,
+                Profile};
+    use std::io;
+
+    /// An instruction of a classic BPF program, `struct sock_filter`.
+    #[repr(C)]
+    #[derive(Clone, Copy, Debug)]
+    struct Instruction {
+        code: u16,
+        jt:   u8,
+        jf:   u8,
+        k:    u32,
+    }
+
+    /// A classic BPF program, `struct sock_fprog`.
+    #[repr(C)]
+    struct Program {
+        len:    libc::c_ushort,
+        filter: *const Instruction,
+    }
+
+    const BPF_LD_W_ABS: u16 = 0x20;
+    const BPF_JMP_JEQ_K: u16 = 0x15;
+    const BPF_JMP_JGE_K: u16 = 0x35;
+    const BPF_JMP_JSET_K: u16 = 0x45;
+    const BPF_RET_K: u16 = 0x06;
+    /// The kernel refuses programs any longer than this.
+    const BPF_MAXINSNS: usize = 4096;
+
+    /// Offsets of the fields of `struct seccomp_data`.
+    const SYSCALL_NR_OFFSET: u32 = 0;
+    const ARCH_OFFSET: u32 = 4;
+    const ARGS_OFFSET: u32 = 16;
+    /// System calls have up to this many arguments.
+    const ARGS_COUNT: u8 = 6;
+
+    const SECCOMP_MODE_FILTER: libc::c_ulong = 2;
+    const SECCOMP_RET_KILL_PROCESS: u32 = 0x8000_0000;
+    const SECCOMP_RET_ERRNO: u32 = 0x0005_0000;
+    const SECCOMP_RET_LOG: u32 = 0x7ffc_0000;
+    const SECCOMP_RET_ALLOW: u32 = 0x7fff_0000;
+
+    #[cfg(target_arch = "x86_64")]
+    const AUDIT_ARCH: u32 = 0xc000_003e;
+    #[cfg(target_arch = "aarch64")]
+    const AUDIT_ARCH: u32 = 0xc000_00b7;
+    /// The system calls of the x32 ABI, which an x86_64 kernel also accepts, have this bit set.
+    #[cfg(target_arch = "x86_64")]
+    const X32_SYSCALL_BIT: u32 = 0x4000_0000;
+
+    /// A profile compiled to the seccomp filter that enforces it.
+    #[derive(Clone, Debug)]
+    pub struct Filter(Vec<Instruction>);
+
+    impl Filter {
+        /// Compile `profile`. System calls of other architectures, or of the x32 ABI, kill the
+        /// process, so that they can't be used to get around the profile.
+        pub fn new(profile: &Profile) -> io::Result<Self> {
+            let mut program = vec![statement(BPF_LD_W_ABS, ARCH_OFFSET),
+                                   jump(BPF_JMP_JEQ_K, AUDIT_ARCH, 1, 0),
+                                   statement(BPF_RET_K, SECCOMP_RET_KILL_PROCESS),
+                                   statement(BPF_LD_W_ABS, SYSCALL_NR_OFFSET),];
+            #[cfg(target_arch = "x86_64")]
+            program.extend(&[jump(BPF_JMP_JGE_K, X32_SYSCALL_BIT, 0, 1),
+                             statement(BPF_RET_K, SECCOMP_RET_KILL_PROCESS)]);
+            for rule in &profile.syscalls {
+                let checks = match rule.arg {
+                    Some(arg) => arg_checks(arg)?,
+                    None => Vec::new(),
+                };
+                for name in &rule.names {
+                    match super::syscall_number(name) {
+                        Some(number) => {
+                            program.extend(rule_block(number as u32, &checks, rule.action))
+                        }
+                        None => debug!("Ignoring unknown system call {} of seccomp profile", name),
+                    }
+                }
+            }
+            program.push(statement(BPF_RET_K, return_value(profile.default_action)));
+            if program.len() > BPF_MAXINSNS {
+                return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                          "Seccomp profile names too many system \
+                                           calls"));
+            }
+            Ok(Filter(program))
+        }
+
+        /// Restrict the calling process, and every process it execs or forks, to the system
+        /// calls the filter allows. The process can't gain privileges afterwards, e.g. by
+        /// executing a setuid binary, which seccomp requires of processes without
+        /// `CAP_SYS_ADMIN`.
+        ///
+        /// Intended for use in a
+        /// `std::os::unix::process::CommandExt::pre_exec` callback.
+        pub fn apply(&self) -> io::Result<()> {
+            let program = Program { len:    self.0.len() as libc::c_ushort,
+                                    filter: self.0.as_ptr(), };
+            // The arguments of `prctl` are read as unsigned longs
+            let (on, unused): (libc::c_ulong, libc::c_ulong) = (1, 0);
+            unsafe {
+                if libc::prctl(libc::PR_SET_NO_NEW_PRIVS, on, unused, unused, unused) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                if libc::prctl(libc::PR_SET_SECCOMP,
+                               SECCOMP_MODE_FILTER,
+                               &program as *const Program)
+                   != 0
+                {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+            Ok(())
+        }
+    }
+
+    /// The words of the argument `arg` filters, with the bits any of which must be set in them.
+    /// Arguments are 64 bits wide, and laid out little-endian on the architectures supported.
+    fn arg_checks(arg: ArgFilter) -> io::Result<Vec<(u32, u32)>> {
+        if arg.index >= ARGS_COUNT {
+            return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                      format!("Seccomp profile filters argument \
+                                               {} of a system call, which has \
+                                               at most {}",
+                                              arg.index, ARGS_COUNT)));
+        }
+        let offset = ARGS_OFFSET + u32::from(arg.index) * 8;
+        Ok(vec![(offset, arg.any_bits as u32),
+                (offset + 4, (arg.any_bits >> 32) as u32)].into_iter()
+                                                          .filter(|&(_, bits)| bits != 0)
+                                                          .collect())
+    }
+
+    /// The instructions returning `action` for the system call `number`, when its arguments
+    /// pass any of `checks` or there are none. The system call number is loaded again
+    /// afterwards, for the rules that follow.
+    fn rule_block(number: u32, checks: &[(u32, u32)], action: Action) -> Vec<Instruction> {
+        // The checks, the return and the reload follow the first jump
+        let len = checks.len() * 2 + 2;
+        let mut block = vec![jump(BPF_JMP_JEQ_K,
+                                  number,
+                                  0,
+                                  if checks.is_empty() { 1 } else { len as u8 })];
+        for (i, &(offset, bits)) in checks.iter().enumerate() {
+            let to_return = ((checks.len() - i - 1) * 2) as u8;
+            let to_reload = if i + 1 == checks.len() { 1 } else { 0 };
+            block.push(statement(BPF_LD_W_ABS, offset));
+            block.push(jump(BPF_JMP_JSET_K, bits, to_return, to_reload));
+        }
+        block.push(statement(BPF_RET_K, return_value(action)));
+        if !checks.is_empty() {
+            block.push(statement(BPF_LD_W_ABS, SYSCALL_NR_OFFSET));
+        }
+        block
+    }
+
+    fn statement(code: u16, k: u32) -> Instruction {
+        Instruction { code,
+                      jt: 0,
+                      jf: 0,
+                      k }
+    }
+
+    fn jump(code: u16, k: u32, jt: u8, jf: u8) -> Instruction { Instruction { code, jt, jf, k } }
+
+    fn return_value(action: Action) -> u32 {
+        match action {
+            Action::Allow => SECCOMP_RET_ALLOW,
+            Action::Errno => SECCOMP_RET_ERRNO | libc::EPERM as u32,
+            Action::Enosys => SECCOMP_RET_ERRNO | libc::ENOSYS as u32,
+            Action::Log => SECCOMP_RET_LOG,
+            Action::Kill => SECCOMP_RET_KILL_PROCESS,
+        }
+    }
+}
+
+/// Lists the system calls a profile can name, as the `libc::SYS_*` constants of their numbers.
+#[cfg(target_os = "linux")]
+macro_rules! syscalls {
+    ($($syscall:ident),* $(,)?) => {
+        &[$((stringify!($syscall), libc::$syscall)),*]
+    };
+}
+
+/// The system calls of every architecture.
+#[cfg(target_os = "linux")]
+const SYSCALLS: &[(&str, libc::c_long)] = syscalls![SYS_accept,
+                                                    SYS_accept4,
+                                                    SYS_acct,
+                                                    SYS_add_key,
+                                                    SYS_adjtimex,
+                                                    SYS_bind,
+                                                    SYS_bpf,
+                                                    SYS_brk,
+                                                    SYS_capget,
+                                                    SYS_capset,
+                                                    SYS_chdir,
+                                                    SYS_chroot,
+                                                    SYS_clock_adjtime,
+                                                    SYS_clock_getres,
+                                                    SYS_clock_gettime,
+                                                    SYS_clock_nanosleep,
+                                                    SYS_clock_settime,
+                                                    SYS_clone,
+                                                    SYS_close,
+                                                    SYS_connect,
+                                                    SYS_delete_module,
+                                                    SYS_dup,
+                                                    SYS_dup3,
+                                                    SYS_epoll_create1,
+                                                    SYS_epoll_ctl,
+                                                    SYS_epoll_pwait,
+                                                    SYS_eventfd2,
+                                                    SYS_execve,
+                                                    SYS_execveat,
+                                                    SYS_exit,
+                                                    SYS_exit_group,
+                                                    SYS_faccessat,
+                                                    SYS_fadvise64,
+                                                    SYS_fallocate,
+                                                    SYS_fanotify_init,
+                                                    SYS_fanotify_mark,
+                                                    SYS_fchdir,
+                                                    SYS_fchmod,
+                                                    SYS_fchmodat,
+                                                    SYS_fchown,
+                                                    SYS_fchownat,
+                                                    SYS_fcntl,
+                                                    SYS_fdatasync,
+                                                    SYS_fgetxattr,
+                                                    SYS_finit_module,
+                                                    SYS_flistxattr,
+                                                    SYS_flock,
+                                                    SYS_fremovexattr,
+                                                    SYS_fsetxattr,
+                                                    SYS_fstat,
+                                                    SYS_fstatfs,
+                                                    SYS_fsync,
+                                                    SYS_ftruncate,
+                                                    SYS_futex,
+                                                    SYS_get_mempolicy,
+                                                    SYS_get_robust_list,
+                                                    SYS_getcpu,
+                                                    SYS_getcwd,
+                                                    SYS_getdents64,
+                                                    SYS_getegid,
+                                                    SYS_geteuid,
+                                                    SYS_getgid,
+                                                    SYS_getgroups,
+                                                    SYS_getitimer,
+                                                    SYS_getpeername,
+                                                    SYS_getpgid,
+                                                    SYS_getpid,
+                                                    SYS_getppid,
+                                                    SYS_getpriority,
+                                                    SYS_getrandom,
+                                                    SYS_getresgid,
+                                                    SYS_getresuid,
+                                                    SYS_getrlimit,
+                                                    SYS_getrusage,
+                                                    SYS_getsid,
+                                                    SYS_getsockname,
+                                                    SYS_getsockopt,
+                                                    SYS_gettid,
+                                                    SYS_gettimeofday,
+                                                    SYS_getuid,
+                                                    SYS_getxattr,
+                                                    SYS_init_module,
+                                                    SYS_inotify_add_watch,
+                                                    SYS_inotify_init1,
+                                                    SYS_inotify_rm_watch,
+                                                    SYS_io_cancel,
+                                                    SYS_io_destroy,
+                                                    SYS_io_getevents,
+                                                    SYS_io_setup,
+                                                    SYS_io_submit,
+                                                    SYS_ioctl,
+                                                    SYS_ioprio_get,
+                                                    SYS_ioprio_set,
+                                                    SYS_kcmp,
+                                                    SYS_kexec_load,
+                                                    SYS_keyctl,
+                                                    SYS_kill,
+                                                    SYS_lgetxattr,
+                                                    SYS_linkat,
+                                                    SYS_listen,
+                                                    SYS_listxattr,
+                                                    SYS_llistxattr,
+                                                    SYS_lookup_dcookie,
+                                                    SYS_lremovexattr,
+                                                    SYS_lseek,
+                                                    SYS_lsetxattr,
+                                                    SYS_madvise,
+                                                    SYS_mbind,
+                                                    SYS_membarrier,
+                                                    SYS_memfd_create,
+                                                    SYS_migrate_pages,
+                                                    SYS_mincore,
+                                                    SYS_mkdirat,
+                                                    SYS_mknodat,
+                                                    SYS_mlock,
+                                                    SYS_mlockall,
+                                                    SYS_mmap,
+                                                    SYS_mount,
+                                                    SYS_move_pages,
+                                                    SYS_mprotect,
+                                                    SYS_mq_getsetattr,
+                                                    SYS_mq_notify,
+                                                    SYS_mq_open,
+                                                    SYS_mq_timedreceive,
+                                                    SYS_mq_timedsend,
+                                                    SYS_mq_unlink,
+                                                    SYS_mremap,
+                                                    SYS_msgctl,
+                                                    SYS_msgget,
+                                                    SYS_msgrcv,
+                                                    SYS_msgsnd,
+                                                    SYS_msync,
+                                                    SYS_munlock,
+                                                    SYS_munlockall,
+                                                    SYS_munmap,
+                                                    SYS_name_to_handle_at,
+                                                    SYS_nanosleep,
+                                                    SYS_newfstatat,
+                                                    SYS_nfsservctl,
+                                                    SYS_open_by_handle_at,
+                                                    SYS_openat,
+                                                    SYS_perf_event_open,
+                                                    SYS_personality,
+                                                    SYS_pipe2,
+                                                    SYS_pivot_root,
+                                                    SYS_ppoll,
+                                                    SYS_prctl,
+                                                    SYS_pread64,
+                                                    SYS_preadv,
+                                                    SYS_prlimit64,
+                                                    SYS_process_vm_readv,
+                                                    SYS_process_vm_writev,
+                                                    SYS_pselect6,
+                                                    SYS_ptrace,
+                                                    SYS_pwrite64,
+                                                    SYS_pwritev,
+                                                    SYS_quotactl,
+                                                    SYS_read,
+                                                    SYS_readahead,
+                                                    SYS_readlinkat,
+                                                    SYS_readv,
+                                                    SYS_reboot,
+                                                    SYS_recvfrom,
+                                                    SYS_recvmmsg,
+                                                    SYS_recvmsg,
+                                                    SYS_remap_file_pages,
+                                                    SYS_removexattr,
+                                                    SYS_renameat,
+                                                    SYS_renameat2,
+                                                    SYS_request_key,
+                                                    SYS_restart_syscall,
+                                                    SYS_rt_sigaction,
+                                                    SYS_rt_sigpending,
+                                                    SYS_rt_sigprocmask,
+                                                    SYS_rt_sigqueueinfo,
+                                                    SYS_rt_sigreturn,
+                                                    SYS_rt_sigsuspend,
+                                                    SYS_rt_sigtimedwait,
+                                                    SYS_rt_tgsigqueueinfo,
+                                                    SYS_sched_get_priority_max,
+                                                    SYS_sched_get_priority_min,
+                                                    SYS_sched_getaffinity,
+                                                    SYS_sched_getattr,
+                                                    SYS_sched_getparam,
+                                                    SYS_sched_getscheduler,
+                                                    SYS_sched_rr_get_interval,
+                                                    SYS_sched_setaffinity,
+                                                    SYS_sched_setattr,
+                                                    SYS_sched_setparam,
+                                                    SYS_sched_setscheduler,
+                                                    SYS_sched_yield,
+                                                    SYS_seccomp,
+                                                    SYS_semctl,
+                                                    SYS_semget,
+                                                    SYS_semop,
+                                                    SYS_semtimedop,
+                                                    SYS_sendfile,
+                                                    SYS_sendmmsg,
+                                                    SYS_sendmsg,
+                                                    SYS_sendto,
+                                                    SYS_set_mempolicy,
+                                                    SYS_set_robust_list,
+                                                    SYS_set_tid_address,
+                                                    SYS_setdomainname,
+                                                    SYS_setfsgid,
+                                                    SYS_setfsuid,
+                                                    SYS_setgid,
+                                                    SYS_setgroups,
+                                                    SYS_sethostname,
+                                                    SYS_setitimer,
+                                                    SYS_setns,
+                                                    SYS_setpgid,
+                                                    SYS_setpriority,
+                                                    SYS_setregid,
+                                                    SYS_setresgid,
+                                                    SYS_setresuid,
+                                                    SYS_setreuid,
+                                                    SYS_setrlimit,
+                                                    SYS_setsid,
+                                                    SYS_setsockopt,
+                                                    SYS_settimeofday,
+                                                    SYS_setuid,
+                                                    SYS_setxattr,
+                                                    SYS_shmat,
+                                                    SYS_shmctl,
+                                                    SYS_shmdt,
+                                                    SYS_shmget,
+                                                    SYS_shutdown,
+                                                    SYS_sigaltstack,
+                                                    SYS_signalfd4,
+                                                    SYS_socket,
+                                                    SYS_socketpair,
+                                                    SYS_splice,
+                                                    SYS_statfs,
+                                                    SYS_swapoff,
+                                                    SYS_swapon,
+                                                    SYS_symlinkat,
+                                                    SYS_sync,
+                                                    SYS_sync_file_range,
+                                                    SYS_syncfs,
+                                                    SYS_sysinfo,
+                                                    SYS_syslog,
+                                                    SYS_tee,
+                                                    SYS_tgkill,
+                                                    SYS_timer_create,
+                                                    SYS_timer_delete,
+                                                    SYS_timer_getoverrun,
+                                                    SYS_timer_gettime,
+                                                    SYS_timer_settime,
+                                                    SYS_timerfd_create,
+                                                    SYS_timerfd_gettime,
+                                                    SYS_timerfd_settime,
+                                                    SYS_times,
+                                                    SYS_tkill,
+                                                    SYS_truncate,
+                                                    SYS_umask,
+                                                    SYS_umount2,
+                                                    SYS_uname,
+                                                    SYS_unlinkat,
+                                                    SYS_unshare,
+                                                    SYS_userfaultfd,
+                                                    SYS_utimensat,
+                                                    SYS_vhangup,
+                                                    SYS_vmsplice,
+                                                    SYS_wait4,
+                                                    SYS_waitid,
+                                                    SYS_write,
+                                                    SYS_writev];
+
+/// The system calls x86_64 kept from before the generic system call table.
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+const ARCH_SYSCALLS: &[(&str, libc::c_long)] = syscalls![SYS__sysctl,
+                                                         SYS_access,
+                                                         SYS_alarm,
+                                                         SYS_arch_prctl,
+                                                         SYS_chmod,
+                                                         SYS_chown,
+                                                         SYS_creat,
+                                                         SYS_create_module,
+                                                         SYS_dup2,
+                                                         SYS_epoll_create,
+                                                         SYS_epoll_wait,
+                                                         SYS_eventfd,
+                                                         SYS_fork,
+                                                         SYS_futimesat,
+                                                         SYS_get_kernel_syms,
+                                                         SYS_getdents,
+                                                         SYS_getpgrp,
+                                                         SYS_inotify_init,
+                                                         SYS_ioperm,
+                                                         SYS_iopl,
+                                                         SYS_kexec_file_load,
+                                                         SYS_lchown,
+                                                         SYS_link,
+                                                         SYS_lstat,
+                                                         SYS_mkdir,
+                                                         SYS_mknod,
+                                                         SYS_modify_ldt,
+                                                         SYS_open,
+                                                         SYS_pause,
+                                                         SYS_pipe,
+                                                         SYS_poll,
+                                                         SYS_query_module,
+                                                         SYS_readlink,
+                                                         SYS_rename,
+                                                         SYS_rmdir,
+                                                         SYS_select,
+                                                         SYS_signalfd,
+                                                         SYS_stat,
+                                                         SYS_symlink,
+                                                         SYS_sysfs,
+                                                         SYS_time,
+                                                         SYS_unlink,
+                                                         SYS_uselib,
+                                                         SYS_ustat,
+                                                         SYS_utime,
+                                                         SYS_utimes,
+                                                         SYS_vfork];
+
+#[cfg(all(target_os = "linux", not(target_arch = "x86_64")))]
+const ARCH_SYSCALLS: &[(&str, libc::c_long)] = &[];
+
+/// The system calls added since every architecture shares their numbers, which the `libc` crate
+/// doesn't have yet.
+#[cfg(target_os = "linux")]
+const NEW_SYSCALLS: &[(&str, libc::c_long)] = &[("SYS_open_tree", 428),
+                                                ("SYS_move_mount", 429),
+                                                ("SYS_fsopen", 430),
+                                                ("SYS_fsconfig", 431),
+                                                ("SYS_fsmount", 432),
+                                                ("SYS_fspick", 433),
+                                                ("SYS_clone3", 435)];
+
+/// The number of the system call `name` on this architecture.
+#[cfg(target_os = "linux")]
+fn syscall_number(name: &str) -> Option<libc::c_long> {
+    SYSCALLS.iter()
+            .chain(ARCH_SYSCALLS)
+            .chain(NEW_SYSCALLS)
+            .find(|(syscall, _)| &syscall["SYS_".len()..] == name)
+            .map(|&(_, number)| number)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_profile_is_valid() {
+        let profile = SeccompProfile::Default.load().unwrap();
+        assert_eq!(profile.default_action, Action::Allow);
+        assert!(profile.syscalls[0].names.contains(&"ptrace".to_string()));
+        #[cfg(target_os = "linux")]
+        assert!(Filter::new(&profile).is_ok());
+    }
+
+    #[test]
+    fn seccomp_profile_from_str() {
+        assert_eq!("default".parse(), Ok(SeccompProfile::Default));
+        assert_eq!("/hab/seccomp.toml".parse(),
+                   Ok(SeccompProfile::File(PathBuf::from("/hab/seccomp.toml"))));
+        assert!("".parse::<SeccompProfile>().is_err());
+        assert!(validate_seccomp_profile("default").is_ok());
+        assert!(validate_seccomp_profile("seccomp.toml").is_err());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn syscall_numbers() {
+        assert_eq!(syscall_number("read"), Some(libc::SYS_read));
+        assert_eq!(syscall_number("clone3"), Some(435));
+        assert_eq!(syscall_number("not_a_syscall"), None);
+    }
+
+    #[test]
+    fn argument_filters() {
+        let content = r#"default_action = "allow"
+
+[[syscalls]]
+names = ["clone"]
+action = "errno"
+arg = { index = 0, any_bits = 0x100000000 }
+"#;
+        let profile: Profile = toml::from_str(content).unwrap();
+        assert_eq!(profile.syscalls[0].arg,
+                   Some(ArgFilter { index:    0,
+                                    any_bits: 0x1_0000_0000, }));
+        #[cfg(target_os = "linux")]
+        assert!(Filter::new(&profile).is_ok());
+
+        let content = content.replace("index = 0", "index = 6");
+        let profile: Profile = toml::from_str(&content).unwrap();
+        assert_eq!(profile.syscalls[0].arg.map(|arg| arg.index), Some(6));
+        #[cfg(target_os = "linux")]
+        assert!(Filter::new(&profile).is_err());
+    }
+}
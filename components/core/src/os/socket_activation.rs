@@ -0,0 +1,218 @@
+//! Socket activation, with systemd's `LISTEN_FDS` protocol.
+//!
+//! When systemd starts the Launcher from a socket unit, it passes the sockets of the unit as the
+//! file descriptors following the standard ones, and describes them in the `LISTEN_PID`,
+//! `LISTEN_FDS` and `LISTEN_FDNAMES` environment variables. The Launcher holds on to them for as
+//! long as it runs, and hands the ones a service asks for, by the `FileDescriptorName=` of the
+//! socket unit, to the service in turn. Since the sockets stay open across restarts of the
+//! service, connections made while it restarts wait in their backlog rather than being refused.
+
+use std::{collections::BTreeMap,
+          env,
+          ffi::{CString,
+                OsStr,
+                OsString},
+          io,
+          os::unix::{ffi::OsStrExt,
+                     io::RawFd},
+          process,
+          ptr};
+
+/// The first file descriptor passed with the protocol; the ones following it are passed as well.
+pub const LISTEN_FDS_START: RawFd = 3;
+
+const LISTEN_PID_ENV: &str = "LISTEN_PID";
+const LISTEN_FDS_ENV: &str = "LISTEN_FDS";
+const LISTEN_FDNAMES_ENV: &str = "LISTEN_FDNAMES";
+/// The name systemd gives sockets whose unit doesn't name them.
+const DEFAULT_NAME: &str = "unknown";
+/// Enough room for the decimal digits of any PID.
+const PID_DIGITS: usize = 20;
+
+/// A socket passed to the current process.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ListenFd {
+    pub fd:   RawFd,
+    /// The `FileDescriptorName=` of the socket unit it comes from.
+    pub name: String,
+}
+
+/// Take the sockets passed to the current process, if any. The variables describing them are
+/// removed from the environment, so that no process started later mistakes them for its own,
+/// and the sockets are marked close-on-exec, so that only the processes they are handed to
+/// inherit them.
+pub fn take_listen_fds() -> io::Result<Vec<ListenFd>> {
+    let pid = env::var(LISTEN_PID_ENV).ok();
+    let count = env::var(LISTEN_FDS_ENV).ok();
+    let names = env::var(LISTEN_FDNAMES_ENV).ok();
+    for var in &[LISTEN_PID_ENV, LISTEN_FDS_ENV, LISTEN_FDNAMES_ENV] {
+        env::remove_var(var);
+    }
+    match pid {
+        Some(pid) if pid.parse::<u32>().ok() == Some(process::id()) => {}
+        _ => return Ok(Vec::new()),
+    }
+    let count = count.and_then(|count| count.parse::<RawFd>().ok())
+                     .ok_or_else(|| {
+                         io::Error::new(io::ErrorKind::InvalidData,
+                                        format!("{} is not a number of sockets", LISTEN_FDS_ENV))
+                     })?;
+    let names = names.unwrap_or_default();
+    let mut names = names.split(':');
+    let mut fds = Vec::new();
+    for fd in LISTEN_FDS_START..LISTEN_FDS_START + count {
+        set_cloexec(fd)?;
+        let name = match names.next() {
+            Some(name) if !name.is_empty() => name,
+            _ => DEFAULT_NAME,
+        };
+        fds.push(ListenFd { fd,
+                            name: name.to_string() });
+    }
+    Ok(fds)
+}
+
+fn set_cloexec(fd: RawFd) -> io::Result<()> {
+    unsafe {
+        let flags = libc::fcntl(fd, libc::F_GETFD);
+        if flags == -1 || libc::fcntl(fd, libc::F_SETFD, flags | libc::FD_CLOEXEC) == -1 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+/// Everything a child process needs to exec a program that takes over sockets with the
+/// protocol.
+///
+/// `LISTEN_PID` must name the process that execs the program, which is only known once it has
+/// been forked, while the environment a `Command` was given is fixed by then. The child therefore
+/// execs the program itself, with an environment prepared beforehand, into which it only writes
+/// its PID: nothing may be allocated between `fork` and `exec`.
+pub(crate) struct Handoff {
+    program:  CString,
+    // Point into `program`, `env` and `pid`, whose heap buffers don't move
+    argv:     Vec<*const libc::c_char>,
+    envp:     Vec<*const libc::c_char>,
+    #[allow(dead_code)] // Only read through `envp`
+    env: Vec<CString>,
+    /// `LISTEN_PID=`, followed by room for the PID and its terminating NUL.
+    pid:      Vec<u8>,
+    /// The sockets to hand over, in order.
+    fds:      Vec<RawFd>,
+    /// The lowest descriptor the sockets are moved out of the way to.
+    spare_fd: RawFd,
+}
+
+// The raw pointers only point into the buffers the `Handoff` owns.
+unsafe impl Send for Handoff {}
+unsafe impl Sync for Handoff {}
+
+impl Handoff {
+    /// Prepare the exec of `program`, with the environment of the current process updated with
+    /// `env`, handing it `fds`.
+    pub fn new(program: &OsStr,
+               env: BTreeMap<OsString, OsString>,
+               fds: &[ListenFd])
+               -> io::Result<Self> {
+        let names = fds.iter()
+                       .map(|fd| fd.name.as_str())
+                       .collect::<Vec<_>>()
+                       .join(":");
+        let mut vars = env::vars_os().collect::<BTreeMap<_, _>>();
+        vars.extend(env);
+        vars.remove(OsStr::new(LISTEN_PID_ENV));
+        vars.insert(LISTEN_FDS_ENV.into(), fds.len().to_string().into());
+        vars.insert(LISTEN_FDNAMES_ENV.into(), names.into());
+        let env = vars.into_iter()
+                      .map(|(key, value)| {
+                          let mut var = key;
+                          var.push("=");
+                          var.push(value);
+                          cstring(&var)
+                      })
+                      .collect::<io::Result<Vec<_>>>()?;
+
+        let mut pid = format!("{}=", LISTEN_PID_ENV).into_bytes();
+        pid.resize(pid.len() + PID_DIGITS + 1, 0);
+        let program = cstring(program)?;
+        let argv = vec![program.as_ptr(), ptr::null()];
+        let envp = env.iter()
+                      .map(|var| var.as_ptr())
+                      .chain(vec![pid.as_ptr() as *const libc::c_char, ptr::null()])
+                      .collect();
+        let fds = fds.iter().map(|fd| fd.fd).collect::<Vec<_>>();
+        let spare_fd = LISTEN_FDS_START + fds.len() as RawFd;
+        Ok(Handoff { program,
+                     argv,
+                     envp,
+                     env,
+                     pid,
+                     fds,
+                     spare_fd })
+    }
+
+    /// Move the sockets into place and exec the program, which only returns if that fails.
+    ///
+    /// Intended for use as the last `std::os::unix::process::CommandExt::pre_exec` callback.
+    pub fn exec(&mut self) -> io::Error {
+        unsafe {
+            // The sockets are first moved out of the range they are moved to, so that moving one
+            // into place never closes another. The copies are closed on exec.
+            for fd in self.fds.iter_mut() {
+                let spare = libc::fcntl(*fd, libc::F_DUPFD_CLOEXEC, self.spare_fd);
+                if spare == -1 {
+                    return io::Error::last_os_error();
+                }
+                *fd = spare;
+            }
+            for (target, fd) in (LISTEN_FDS_START..).zip(self.fds.iter()) {
+                if libc::dup2(*fd, target) == -1 {
+                    return io::Error::last_os_error();
+                }
+            }
+            write_pid(&mut self.pid, process::id());
+            libc::execve(self.program.as_ptr(),
+                         self.argv.as_ptr(),
+                         self.envp.as_ptr());
+        }
+        io::Error::last_os_error()
+    }
+}
+
+fn cstring(value: &OsStr) -> io::Result<CString> {
+    CString::new(value.as_bytes()).map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))
+}
+
+/// Write `pid` and a terminating NUL after the `LISTEN_PID=` at the start of `var`, without
+/// allocating.
+fn write_pid(var: &mut [u8], pid: u32) {
+    let mut digits = [0u8; PID_DIGITS];
+    let mut len = 0;
+    let mut rest = pid;
+    loop {
+        digits[len] = b'0' + (rest % 10) as u8;
+        len += 1;
+        rest /= 10;
+        if rest == 0 {
+            break;
+        }
+    }
+    let start = LISTEN_PID_ENV.len() + 1;
+    for (index, digit) in digits[..len].iter().rev().enumerate() {
+        var[start + index] = *digit;
+    }
+    var[start + len] = 0;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_pid_terminates_the_variable() {
+        let mut var = b"LISTEN_PID=99999999999999999999x".to_vec();
+        write_pid(&mut var, 4021);
+        assert_eq!(&var[..16], b"LISTEN_PID=4021\0");
+    }
+}
@@ -20,7 +20,9 @@ pub use implementation::{assert_pkg_user_and_group,
 
 // Unix-specific functions
 #[cfg(unix)]
-pub use unix::{get_effective_gid,
+pub use unix::{ensure_system_group,
+               ensure_system_user,
+               get_effective_gid,
                get_effective_groupname,
                get_effective_username,
                get_members_by_groupname};
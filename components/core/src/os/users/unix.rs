@@ -2,7 +2,8 @@ use crate::error::{Error,
                    Result};
 use nix::unistd::{Group,
                   User};
-use std::path::PathBuf;
+use std::{path::PathBuf,
+          process::Command};
 
 pub fn get_uid_by_name(owner: &str) -> Result<Option<u32>> {
     Ok(User::from_name(owner)?.map(|u| u.uid.as_raw()))
@@ -45,6 +46,69 @@ pub fn get_home_for_user(username: &str) -> Result<Option<PathBuf>> {
     Ok(User::from_name(username)?.map(|u| u.dir))
 }
 
+/// Create a system group named `group` unless it already exists,
+/// returning its GID. `groupadd` is used where available, falling back
+/// to BusyBox's `addgroup`.
+pub fn ensure_system_group(group: &str) -> Result<u32> {
+    if let Some(gid) = get_gid_by_name(group)? {
+        return Ok(gid);
+    }
+    run_first_available(&[("groupadd", &["--system", group]),
+                          ("addgroup", &["-S", group])],
+                        &format!("group {}", group))?;
+    get_gid_by_name(group)?.ok_or_else(|| {
+                               Error::PermissionFailed(format!("Group {} was not created", group))
+                           })
+}
+
+/// Create a system user named `user` with `group` as its primary
+/// group unless it already exists, returning its UID. The user has no
+/// home directory and cannot log in.
+pub fn ensure_system_user(user: &str, group: &str) -> Result<u32> {
+    if let Some(uid) = get_uid_by_name(user)? {
+        return Ok(uid);
+    }
+    run_first_available(&[("useradd",
+                           &["--system",
+                             "--gid",
+                             group,
+                             "--no-create-home",
+                             "--shell",
+                             "/bin/false",
+                             user]),
+                          ("adduser", &["-S", "-G", group, "-H", "-s", "/bin/false", user])],
+                        &format!("user {}", user))?;
+    get_uid_by_name(user)?.ok_or_else(|| {
+                              Error::PermissionFailed(format!("User {} was not created", user))
+                          })
+}
+
+/// Run the first of `commands` which is installed, failing if it does
+/// not succeed.
+fn run_first_available(commands: &[(&str, &[&str])], what: &str) -> Result<()> {
+    for (program, args) in commands {
+        match Command::new(program).args(*args).status() {
+            Ok(status) if status.success() => return Ok(()),
+            Ok(status) => {
+                return Err(Error::PermissionFailed(format!("Unable to create {}: {} \
+                                                            exited with {}",
+                                                           what, program, status)));
+            }
+            Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => {
+                return Err(Error::PermissionFailed(format!("Unable to create {}: {}", what, e)));
+            }
+        }
+    }
+    Err(Error::PermissionFailed(format!("Unable to create {}: \
+                                         none of {} are installed",
+                                        what,
+                                        commands.iter()
+                                                .map(|(program, _)| *program)
+                                                .collect::<Vec<_>>()
+                                                .join(", "))))
+}
+
 /// This function checks to see if a user and group and if:
 ///     a) we are root
 ///     b) we are the specified user:group
@@ -4,6 +4,8 @@ pub mod install;
 pub mod list;
 pub mod metadata;
 pub mod plan;
+#[cfg(unix)]
+pub mod store;
 pub mod target;
 
 pub use self::{archive::{FromArchive,
@@ -11,7 +13,8 @@ pub use self::{archive::{FromArchive,
                          PackageArchiveInfo},
                ident::{FullyQualifiedPackageIdent,
                        Identifiable,
-                       PackageIdent},
+                       PackageIdent,
+                       VersionReq},
                install::PackageInstall,
                list::all_packages,
                plan::Plan,
@@ -7,7 +7,8 @@ use crate::{crypto::{artifact,
                      Blake2bHash},
             error::{Error,
                     Result},
-            package::ident::FullyQualifiedPackageIdent};
+            package::ident::FullyQualifiedPackageIdent,
+            util::read_ahead::ReadAhead};
 use regex::Regex;
 use serde::Serialize;
 use std::{collections::HashMap,
@@ -22,7 +23,6 @@ use std::{collections::HashMap,
                 FromStr},
           string::ToString};
 use tar::Archive;
-use xz2::read::XzDecoder;
 
 lazy_static::lazy_static! {
     static ref METAFILE_REGXS: HashMap<MetaFile, Regex> = {
@@ -329,8 +329,9 @@ impl PackageArchive {
     /// * If the package cannot be unpacked
     pub fn unpack(&self, fs_root_path: Option<&Path>) -> Result<()> {
         let root = fs_root_path.unwrap_or_else(|| Path::new("/"));
-        let hart_payload_tar_xz = artifact::get_archive_reader(&self.path)?;
-        let decoder = XzDecoder::new(hart_payload_tar_xz);
+        // Decompress on a thread of its own, so the payload is decompressed while the files
+        // already decompressed are written to disk
+        let decoder = ReadAhead::new(artifact::get_archive_decoder(&self.path)?)?;
         let mut tar = Archive::new(decoder);
         tar.set_preserve_permissions(true);
         tar.set_preserve_mtime(true);
@@ -364,8 +365,7 @@ impl PackageArchive {
 
     fn get_all_metadata(path: impl AsRef<Path>) -> Result<Metadata> {
         let mut metadata = Metadata::new();
-        let hart_payload_tar_xz = artifact::get_archive_reader(path)?;
-        let decoder = XzDecoder::new(hart_payload_tar_xz);
+        let decoder = artifact::get_archive_decoder(path)?;
         let mut tar = Archive::new(decoder);
 
         // Check all entries in the tar archive for metafiles and add them to the `Metadata` store
@@ -413,6 +413,7 @@ pub trait FromArchive: Sized {
 #[derive(Serialize)]
 pub struct PackageArchiveInfo {
     pub format_version: String,
+    pub compression:    String,
     pub key_name:       String,
     pub hash_type:      String,
     // This should probably be called `encoded_signature`, or perhaps
@@ -447,6 +448,7 @@ impl TryFrom<PackageArchive> for PackageArchiveInfo {
         let header = artifact::get_artifact_header(&archive.path)?;
         let ident: FullyQualifiedPackageIdent = archive.ident()?.try_into()?;
         Ok(PackageArchiveInfo { format_version: header.format().clone(),
+                                compression:    header.compression().to_string(),
 
                                 // TODO (CM): NamedRevision!
                                 key_name:      header.signer().to_string(),
@@ -536,6 +538,7 @@ mod test {
         let info = PackageArchiveInfo::from_path(hart.path).unwrap();
         let expected = serde_json::json!({
         "format_version": "HART-1",
+        "compression": "xz",
         "key_name": "happyhumans-20160424223347",
         "hash_type": "BLAKE2b",
         "signature_raw": "U0cp/+npru0ZxhK76zm+PDVSV/707siyrO1r7T6CZZ4ShSLrIxyx8jLSMr5wnLuGrVIV358smQPWOSTOmyfFCjBmMmM1ZjRkZTE0NWM3Zjc4NjAxY2FhZTljN2I4NzY3MDk4NDEzZDA1NzM5ZGU5MTNjMDEyOTIyYjdlZWQ3NjA=",
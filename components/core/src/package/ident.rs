@@ -56,7 +56,7 @@ pub trait Identifiable: fmt::Display {
             if other.version().is_none() {
                 return true;
             }
-            if *self.version().unwrap() != *other.version().unwrap() {
+            if !version_matches(self.version().unwrap(), other.version().unwrap()) {
                 return false;
             }
         }
@@ -219,6 +219,12 @@ impl FromStr for PackageIdent {
             4 => (items[0], items[1], Some(items[2]), Some(items[3])),
             _ => return Err(Error::InvalidPackageIdent(value.to_string())),
         };
+        if let Some(ver) = ver.filter(|v| VersionReq::is_version_req(v)) {
+            // A range picks among versions, so naming a release with it is meaningless
+            if rel.is_some() || ver.parse::<VersionReq>().is_err() {
+                return Err(Error::InvalidPackageIdent(value.to_string()));
+            }
+        }
         Ok(PackageIdent::new(origin, name, ver, rel))
     }
 }
@@ -431,6 +437,117 @@ impl<'a> Iterator for Iter<'a> {
     }
 }
 
+/// A range of versions given in place of a version in a package identifier, such as
+/// `core/redis/^6.2`.
+///
+/// A range is one or more comparisons separated by commas, all of which a version must satisfy:
+///
+/// * `^6.2` allows any version that does not change the first non-zero number: at least `6.2`, but
+///   less than `7`. `^0.4` allows at least `0.4`, but less than `0.5`.
+/// * `~6.2` allows changes to the numbers after the second: at least `6.2`, but less than `6.3`.
+///   `~6` allows at least `6`, but less than `7`.
+/// * `>1.0`, `>=1.0`, `<2.0`, `<=2.0` and `=1.2.3` compare against a single version.
+///
+/// Versions are compared with `version_sort`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VersionReq(Vec<(Comparison, String)>);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Comparison {
+    Greater,
+    GreaterEq,
+    Less,
+    LessEq,
+    Eq,
+}
+
+impl VersionReq {
+    /// Is this version component of a package identifier a range rather than a version?
+    pub fn is_version_req(version: &str) -> bool { version.starts_with(|c| "^~<>=".contains(c)) }
+
+    /// Does the version satisfy every comparison in the range? Versions that cannot be compared
+    /// never do.
+    pub fn matches(&self, version: &str) -> bool {
+        self.0.iter().all(|(comparison, bound)| {
+                         match version_sort(version, bound) {
+                             Ok(ordering) => {
+                                 match comparison {
+                                     Comparison::Greater => ordering == Ordering::Greater,
+                                     Comparison::GreaterEq => ordering != Ordering::Less,
+                                     Comparison::Less => ordering == Ordering::Less,
+                                     Comparison::LessEq => ordering != Ordering::Greater,
+                                     Comparison::Eq => ordering == Ordering::Equal,
+                                 }
+                             }
+                             Err(_) => false,
+                         }
+                     })
+    }
+}
+
+impl FromStr for VersionReq {
+    type Err = Error;
+
+    fn from_str(value: &str) -> result::Result<Self, Self::Err> {
+        let mut comparisons = Vec::new();
+        for part in value.split(',').map(str::trim) {
+            let invalid = || Error::InvalidPackageIdent(value.to_string());
+            let (comparison, version) = if part.starts_with(">=") {
+                (Some(Comparison::GreaterEq), &part[2..])
+            } else if part.starts_with("<=") {
+                (Some(Comparison::LessEq), &part[2..])
+            } else if part.starts_with('>') {
+                (Some(Comparison::Greater), &part[1..])
+            } else if part.starts_with('<') {
+                (Some(Comparison::Less), &part[1..])
+            } else if part.starts_with('=') {
+                (Some(Comparison::Eq), &part[1..])
+            } else if part.starts_with('^') || part.starts_with('~') {
+                (None, &part[1..])
+            } else {
+                return Err(invalid());
+            };
+            let numbers = version.split('.')
+                                 .map(str::parse::<u64>)
+                                 .collect::<result::Result<Vec<_>, _>>()
+                                 .map_err(|_| invalid())?;
+            match comparison {
+                Some(comparison) => comparisons.push((comparison, version.to_string())),
+                None => {
+                    // Increment the last number that may not change, dropping the rest
+                    let fixed = if part.starts_with('^') {
+                        numbers.iter()
+                               .position(|n| *n != 0)
+                               .unwrap_or(numbers.len() - 1)
+                    } else {
+                        numbers.len().min(2) - 1
+                    };
+                    let mut upper = numbers[..=fixed].to_vec();
+                    upper[fixed] += 1;
+                    let upper = upper.iter()
+                                     .map(u64::to_string)
+                                     .collect::<Vec<_>>()
+                                     .join(".");
+                    comparisons.push((Comparison::GreaterEq, version.to_string()));
+                    comparisons.push((Comparison::Less, upper));
+                }
+            }
+        }
+        Ok(VersionReq(comparisons))
+    }
+}
+
+/// Does `version` match `wanted`, which may be a version or a range of versions?
+fn version_matches(version: &str, wanted: &str) -> bool {
+    if VersionReq::is_version_req(wanted) {
+        wanted.parse::<VersionReq>()
+              .map(|req| req.matches(version))
+              .unwrap_or(false)
+    } else {
+        version == wanted
+    }
+}
+
 /// Sorts two packages according to their version.
 ///
 /// We are a bit more strict than your average package management solution on versioning.
@@ -840,6 +957,65 @@ mod tests {
         assert_eq!(None, iter.next());
     }
 
+    #[test]
+    fn version_req_caret() {
+        let req = VersionReq::from_str("^6.2").unwrap();
+        assert!(req.matches("6.2"));
+        assert!(req.matches("6.2.1"));
+        assert!(req.matches("6.10.0"));
+        assert!(!req.matches("6.1.9"));
+        assert!(!req.matches("7.0.0"));
+
+        let req = VersionReq::from_str("^0.4.1").unwrap();
+        assert!(req.matches("0.4.7"));
+        assert!(!req.matches("0.5.0"));
+    }
+
+    #[test]
+    fn version_req_tilde() {
+        let req = VersionReq::from_str("~6.2").unwrap();
+        assert!(req.matches("6.2.9"));
+        assert!(!req.matches("6.3.0"));
+
+        let req = VersionReq::from_str("~6").unwrap();
+        assert!(req.matches("6.9"));
+        assert!(!req.matches("7"));
+    }
+
+    #[test]
+    fn version_req_comparisons() {
+        let req = VersionReq::from_str(">=1.0,<2.0").unwrap();
+        assert!(req.matches("1.0.0"));
+        assert!(req.matches("1.9.9"));
+        assert!(!req.matches("2.0.0"));
+        assert!(!req.matches("0.9"));
+        assert!(!req.matches("undefined"));
+
+        assert!(VersionReq::from_str("=1.2.3").unwrap().matches("1.2.3"));
+        assert!(VersionReq::from_str(">1.2").unwrap().matches("1.2.1"));
+        assert!(VersionReq::from_str("<=1.2").unwrap().matches("1.2"));
+    }
+
+    #[test]
+    fn version_req_invalid() {
+        assert!(VersionReq::from_str("6.2").is_err());
+        assert!(VersionReq::from_str("^six").is_err());
+        assert!(VersionReq::from_str(">=1.0,").is_err());
+    }
+
+    #[test]
+    fn package_ident_with_version_req() {
+        let ident = PackageIdent::from_str("core/redis/^6.2").unwrap();
+        assert_eq!(ident.version(), Some("^6.2"));
+        assert!(PackageIdent::from_str("core/redis/^6.2/20200101000000").is_err());
+        assert!(PackageIdent::from_str("core/redis/^six").is_err());
+
+        let installed = PackageIdent::from_str("core/redis/6.2.4/20210101000000").unwrap();
+        assert!(installed.satisfies(&ident));
+        let installed = PackageIdent::from_str("core/redis/7.0.0/20220101000000").unwrap();
+        assert!(!installed.satisfies(&ident));
+    }
+
     #[test]
     fn iter_without_version() {
         let ident = PackageIdent::from_str("cypress-hill/rise-up").unwrap();
@@ -12,8 +12,9 @@ use super::{list::package_list_for_ident,
 use crate::{error::{Error,
                     Result},
             fs,
-            os::process::{ShutdownSignal,
-                          ShutdownTimeout}};
+            os::{process::{ShutdownSignal,
+                           ShutdownTimeout},
+                 seccomp::SeccompProfile}};
 use serde_derive::{Deserialize,
                    Serialize};
 use std::{cmp::{Ordering,
@@ -620,6 +621,25 @@ impl PackageInstall {
         }
     }
 
+    /// Returns the seccomp profile that the package is specified to run with, with a profile
+    /// file resolved relative to the package, or None if the package doesn't contain a
+    /// SECCOMP_PROFILE Metafile
+    pub fn seccomp_profile(&self) -> Result<Option<SeccompProfile>> {
+        match self.read_metafile(MetaFile::SeccompProfile) {
+            Ok(body) => {
+                match body.parse() {
+                    Ok(SeccompProfile::File(path)) => {
+                        Ok(Some(SeccompProfile::File(self.installed_path.join(path))))
+                    }
+                    Ok(profile) => Ok(Some(profile)),
+                    Err(_) => Err(Error::MetaFileMalformed(MetaFile::SeccompProfile)),
+                }
+            }
+            Err(Error::MetaFileNotFound(MetaFile::SeccompProfile)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
     /// Read the contents of a given metafile.
     ///
     /// # Failures
@@ -807,6 +827,24 @@ core/bar=pub:core/publish sub:core/subscribe
         assert_eq!(expected, bind_map);
     }
 
+    #[test]
+    fn seccomp_profile_file_is_relative_to_the_package() {
+        let fs_root = Builder::new().prefix("fs-root").tempdir().unwrap();
+        let package_install = testing_package_install("core/sandboxed", fs_root.path());
+        assert_eq!(package_install.seccomp_profile().unwrap(), None);
+
+        write_metafile(&package_install, MetaFile::SeccompProfile, "default\n");
+        assert_eq!(package_install.seccomp_profile().unwrap(),
+                   Some(SeccompProfile::Default));
+
+        write_metafile(&package_install,
+                       MetaFile::SeccompProfile,
+                       "config/seccomp.toml\n");
+        assert_eq!(package_install.seccomp_profile().unwrap(),
+                   Some(SeccompProfile::File(package_install.installed_path
+                                                            .join("config/seccomp.toml"))));
+    }
+
     #[test]
     fn reading_a_bad_bind_map_file_results_in_an_error() {
         let fs_root = Builder::new().prefix("fs-root").tempdir().unwrap();
@@ -157,6 +157,7 @@ pub enum MetaFile {
     RuntimeEnvironment,
     RuntimeEnvironmentPaths,
     RuntimePath,
+    SeccompProfile,
     ShutdownSignal,
     ShutdownTimeout,
     Services, // Composite-only
@@ -191,6 +192,7 @@ impl fmt::Display for MetaFile {
             MetaFile::RuntimeEnvironment => "RUNTIME_ENVIRONMENT",
             MetaFile::RuntimeEnvironmentPaths => "RUNTIME_ENVIRONMENT_PATHS",
             MetaFile::RuntimePath => "RUNTIME_PATH",
+            MetaFile::SeccompProfile => "SECCOMP_PROFILE",
             MetaFile::Services => "SERVICES",
             MetaFile::ShutdownSignal => "SHUTDOWN_SIGNAL",
             MetaFile::ShutdownTimeout => "SHUTDOWN_TIMEOUT",
@@ -0,0 +1,208 @@
+//! A content-addressed store that identical files of installed packages are deduplicated into.
+//!
+//! Every regular file of a deduplicated package is a hard link to an entry in the store, named
+//! for the BLAKE2b hash of the file's contents along with its mode and owner (which hard links
+//! share). A file that appears unchanged in many releases of a package is then only kept on disk
+//! once.
+//!
+//! Deduplication is enabled for a filesystem root by creating its store, after which packages are
+//! deduplicated as they are installed. Since the files of a package are shared, they must never be
+//! modified in place.
+
+use crate::{crypto::hash::Blake2bHash,
+            error::Result,
+            fs};
+use std::{fs as stdfs,
+          io,
+          os::unix::fs::MetadataExt,
+          path::{Path,
+                 PathBuf}};
+
+/// The outcome of deduplicating or pruning the store.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct StoreStats {
+    /// The number of files that were linked to or removed from the store
+    pub files: u64,
+    /// The number of bytes this freed
+    pub bytes: u64,
+}
+
+pub struct Store {
+    path: PathBuf,
+}
+
+impl Store {
+    /// Returns the store beneath `fs_root_path` if deduplication is enabled there.
+    pub fn open(fs_root_path: &Path) -> Option<Self> {
+        let path = fs::pkg_store_path(Some(fs_root_path));
+        if path.is_dir() {
+            Some(Store { path })
+        } else {
+            None
+        }
+    }
+
+    /// Creates the store beneath `fs_root_path`, enabling deduplication there.
+    pub fn create(fs_root_path: &Path) -> Result<Self> {
+        let path = fs::pkg_store_path(Some(fs_root_path));
+        stdfs::create_dir_all(&path)?;
+        Ok(Store { path })
+    }
+
+    /// Replaces every regular file beneath `path` with a hard link to its entry in the store,
+    /// adding entries for files the store does not have yet.
+    pub fn dedup(&self, path: &Path) -> Result<StoreStats> {
+        let mut stats = StoreStats::default();
+        for file in files(path)? {
+            let metadata = stdfs::symlink_metadata(&file)?;
+            // Empty files have nothing to share
+            if metadata.len() == 0 {
+                continue;
+            }
+            let entry = self.entry_path(&Blake2bHash::from_file(&file)?, &metadata);
+            loop {
+                match stdfs::symlink_metadata(&entry) {
+                    Ok(existing) => {
+                        if existing.dev() != metadata.dev() || existing.ino() != metadata.ino() {
+                            replace_with_link(&entry, &file)?;
+                            stats.files += 1;
+                            stats.bytes += metadata.len();
+                        }
+                        break;
+                    }
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                        if let Some(parent) = entry.parent() {
+                            stdfs::create_dir_all(parent)?;
+                        }
+                        match stdfs::hard_link(&file, &entry) {
+                            Ok(()) => break,
+                            // Another install added the same entry in the meantime, so link to
+                            // theirs instead
+                            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                            Err(e) => return Err(e.into()),
+                        }
+                    }
+                    Err(e) => return Err(e.into()),
+                }
+            }
+        }
+        Ok(stats)
+    }
+
+    /// Removes the entries that are no longer linked from any installed package.
+    pub fn prune(&self) -> Result<StoreStats> {
+        let mut stats = StoreStats::default();
+        for entry in files(&self.path)? {
+            let metadata = stdfs::symlink_metadata(&entry)?;
+            if metadata.nlink() == 1 {
+                stdfs::remove_file(&entry)?;
+                stats.files += 1;
+                stats.bytes += metadata.len();
+            }
+        }
+        Ok(stats)
+    }
+
+    fn entry_path(&self, hash: &Blake2bHash, metadata: &stdfs::Metadata) -> PathBuf {
+        let hash = hash.to_string();
+        self.path.join(&hash[..2]).join(format!("{}-{:o}-{}-{}",
+                                                hash,
+                                                metadata.mode(),
+                                                metadata.uid(),
+                                                metadata.gid()))
+    }
+}
+
+/// Atomically replaces `file` with a hard link to `entry`.
+fn replace_with_link(entry: &Path, file: &Path) -> Result<()> {
+    let mut temp = file.as_os_str().to_owned();
+    temp.push(".hab-store");
+    let temp = PathBuf::from(temp);
+    stdfs::hard_link(entry, &temp)?;
+    if let Err(e) = stdfs::rename(&temp, file) {
+        stdfs::remove_file(&temp)?;
+        return Err(e.into());
+    }
+    Ok(())
+}
+
+/// Every regular file beneath `path`.
+fn files(path: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut dirs = vec![path.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in stdfs::read_dir(dir)? {
+            let entry = entry?;
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                dirs.push(entry.path());
+            } else if file_type.is_file() {
+                files.push(entry.path());
+            }
+        }
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write(path: &Path, contents: &str) {
+        stdfs::create_dir_all(path.parent().unwrap()).unwrap();
+        stdfs::write(path, contents).unwrap();
+    }
+
+    #[test]
+    fn store_is_only_opened_once_created() {
+        let fs_root = TempDir::new().unwrap();
+        assert!(Store::open(fs_root.path()).is_none());
+        Store::create(fs_root.path()).unwrap();
+        assert!(Store::open(fs_root.path()).is_some());
+    }
+
+    #[test]
+    fn identical_files_are_linked() {
+        let fs_root = TempDir::new().unwrap();
+        let store = Store::create(fs_root.path()).unwrap();
+        let pkgs = fs_root.path().join("hab/pkgs/core/foo/1.0.0");
+        let first = pkgs.join("20200101000000/bin/foo");
+        let second = pkgs.join("20200202000000/bin/foo");
+        let changed = pkgs.join("20200202000000/bin/bar");
+        write(&first, "foo");
+        write(&second, "foo");
+        write(&changed, "bar");
+
+        let stats = store.dedup(&pkgs.join("20200101000000")).unwrap();
+        assert_eq!(stats, StoreStats::default());
+        let stats = store.dedup(&pkgs.join("20200202000000")).unwrap();
+        assert_eq!(stats, StoreStats { files: 1, bytes: 3 });
+
+        let first = stdfs::metadata(&first).unwrap();
+        let second = stdfs::metadata(&second).unwrap();
+        assert_eq!(first.ino(), second.ino());
+        assert_eq!(first.nlink(), 3);
+        assert_eq!(stdfs::metadata(&changed).unwrap().nlink(), 2);
+
+        // Deduplicating again changes nothing
+        let stats = store.dedup(&pkgs).unwrap();
+        assert_eq!(stats, StoreStats::default());
+    }
+
+    #[test]
+    fn unlinked_entries_are_pruned() {
+        let fs_root = TempDir::new().unwrap();
+        let store = Store::create(fs_root.path()).unwrap();
+        let release = fs_root.path()
+                             .join("hab/pkgs/core/foo/1.0.0/20200101000000");
+        write(&release.join("bin/foo"), "foo");
+        write(&release.join("bin/bar"), "bar");
+        store.dedup(&release).unwrap();
+
+        assert_eq!(store.prune().unwrap(), StoreStats::default());
+        stdfs::remove_file(release.join("bin/foo")).unwrap();
+        assert_eq!(store.prune().unwrap(), StoreStats { files: 1, bytes: 3 });
+        assert_eq!(files(&store.path).unwrap().len(), 1);
+    }
+}
@@ -304,7 +304,7 @@ package_targets! {
     /// [x86_64]: https://en.wikipedia.org/wiki/X86-64
     ("x86_64-windows", X86_64_Windows, X86_64_WINDOWS, "x86_64", "windows");
 
-    /// **UNSUPPORTED TARGET** Represents a [Linux kernel]-based system running on an
+    /// Represents a [Linux kernel]-based system running on an
     /// [ARM Architecture processor][arm-arch].
     ///
     /// [Linux kernel]: https://en.wikipedia.org/wiki/Linux_kernel
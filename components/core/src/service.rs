@@ -39,6 +39,11 @@ pub enum BindingMode {
     /// applications that cannot cope with the absence of a service
     /// dependency at startup should bind with this mode.
     Strict,
+    /// Binds the package declares as required *must* be satisfied
+    /// before a service can start, while its optional binds may be
+    /// absent; templates then fall back to the plan's defaults in
+    /// their place.
+    Optional,
 }
 
 impl Default for BindingMode {
@@ -58,6 +63,7 @@ impl fmt::Display for BindingMode {
         let value = match *self {
             BindingMode::Relaxed => "relaxed",
             BindingMode::Strict => "strict",
+            BindingMode::Optional => "optional",
         };
         write!(f, "{}", value)
     }
@@ -70,6 +76,7 @@ impl FromStr for BindingMode {
         match value.to_lowercase().as_ref() {
             "relaxed" => Ok(BindingMode::Relaxed),
             "strict" => Ok(BindingMode::Strict),
+            "optional" => Ok(BindingMode::Optional),
             _ => Err(Error::BadBindingMode(value.to_string())),
         }
     }
@@ -1,6 +1,7 @@
 pub mod docker;
 #[cfg(not(windows))]
 pub mod posix_perm;
+pub mod read_ahead;
 pub mod serde;
 pub mod sys;
 pub mod text_render;
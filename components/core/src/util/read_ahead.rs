@@ -0,0 +1,131 @@
+//! A reader that reads ahead of its consumer on a thread of its own, so that producing a stream
+//! of bytes (reading it from disk, decompressing it, ...) overlaps with consuming it.
+
+use std::{cmp,
+          io::{self,
+               Read},
+          sync::mpsc::{self,
+                       Receiver,
+                       SyncSender},
+          thread};
+
+/// How much the reading thread reads at a time.
+const CHUNK_SIZE: usize = 1024 * 1024;
+/// How many chunks the reading thread may get ahead of the consumer.
+const CHUNKS_AHEAD: usize = 8;
+
+pub struct ReadAhead {
+    chunks: Receiver<io::Result<Vec<u8>>>,
+    chunk:  Vec<u8>,
+    pos:    usize,
+    done:   bool,
+}
+
+impl ReadAhead {
+    /// Start reading `inner` on a new thread. The thread stops once `inner` is exhausted, fails,
+    /// or the `ReadAhead` is dropped.
+    pub fn new<R>(mut inner: R) -> io::Result<Self>
+        where R: Read + Send + 'static
+    {
+        let (tx, rx) = mpsc::sync_channel(CHUNKS_AHEAD);
+        thread::Builder::new().name("read-ahead".to_string())
+                              .spawn(move || produce(&mut inner, &tx))?;
+        Ok(ReadAhead { chunks: rx,
+                       chunk:  Vec::new(),
+                       pos:    0,
+                       done:   false, })
+    }
+}
+
+impl Read for ReadAhead {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.chunk.len() {
+            if self.done {
+                return Ok(0);
+            }
+            match self.chunks.recv() {
+                Ok(Ok(chunk)) => {
+                    // An empty chunk marks the end of the stream
+                    self.done = chunk.is_empty();
+                    self.chunk = chunk;
+                    self.pos = 0;
+                }
+                Ok(Err(e)) => {
+                    self.done = true;
+                    return Err(e);
+                }
+                Err(_) => {
+                    self.done = true;
+                    return Err(io::Error::new(io::ErrorKind::Other,
+                                              "read-ahead thread stopped unexpectedly"));
+                }
+            }
+        }
+        let len = cmp::min(buf.len(), self.chunk.len() - self.pos);
+        buf[..len].copy_from_slice(&self.chunk[self.pos..self.pos + len]);
+        self.pos += len;
+        Ok(len)
+    }
+}
+
+fn produce<R: Read>(inner: &mut R, chunks: &SyncSender<io::Result<Vec<u8>>>) {
+    loop {
+        let mut chunk = vec![0; CHUNK_SIZE];
+        let result = fill(inner, &mut chunk).map(|len| {
+                                                chunk.truncate(len);
+                                                chunk
+                                            });
+        let last = result.as_ref().map_or(true, Vec::is_empty);
+        // Sending only fails when the consumer has gone away
+        if chunks.send(result).is_err() || last {
+            return;
+        }
+    }
+}
+
+/// Read from `inner` until `buf` is full or `inner` is exhausted.
+fn fill<R: Read>(inner: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match inner.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(len) => filled += len,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_everything_in_order() {
+        let data = (0..3 * CHUNK_SIZE + 42).map(|i| (i % 251) as u8)
+                                           .collect::<Vec<_>>();
+        let mut read = Vec::new();
+        ReadAhead::new(io::Cursor::new(data.clone())).unwrap()
+                                                     .read_to_end(&mut read)
+                                                     .unwrap();
+        assert_eq!(read, data);
+    }
+
+    struct Failing;
+
+    impl Read for Failing {
+        fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+            Err(io::Error::new(io::ErrorKind::InvalidData, "corrupt"))
+        }
+    }
+
+    #[test]
+    fn errors_reach_the_consumer() {
+        let mut read = Vec::new();
+        let err = ReadAhead::new(Failing).unwrap()
+                                         .read_to_end(&mut read)
+                                         .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
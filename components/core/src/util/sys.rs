@@ -5,12 +5,12 @@ pub use crate::os::system::{uname,
 use std::{io,
           net::{IpAddr,
                 Ipv4Addr,
+                Ipv6Addr,
                 SocketAddr,
+                SocketAddrV6,
                 ToSocketAddrs,
                 UdpSocket}};
 
-const UNSPECIFIED_SOCKET_ADDR: (Ipv4Addr, u16) = (Ipv4Addr::UNSPECIFIED, 0);
-
 crate::env_config_socketaddr!(OutboundIpAddrLookupSocketAddr,
                               HAB_OUTBOUND_IP_ADDR_LOOKUP_SOCKET_ADDR,
                               // Use Google DNS as the default
@@ -20,6 +20,19 @@ crate::env_config_socketaddr!(OutboundIpAddrLookupSocketAddr,
                               8,
                               53);
 
+crate::env_config!(OutboundIpv6AddrLookupSocketAddr,
+                   SocketAddr,
+                   HAB_OUTBOUND_IPV6_ADDR_LOOKUP_SOCKET_ADDR,
+                   // Use Google DNS as the default
+                   SocketAddr::V6(SocketAddrV6::new(Ipv6Addr::new(0x2001, 0x4860, 0x4860, 0, 0,
+                                                                  0, 0, 0x8888),
+                                                    53,
+                                                    0,
+                                                    0)),
+                   std::net::AddrParseError,
+                   val,
+                   Ok(Self(val.parse()?)));
+
 /// The technique used to determine the outgoing IP address is documented [here][1].
 ///
 /// "A connected UDP socket can also be used to determine the outgoing interface
@@ -31,13 +44,29 @@ crate::env_config_socketaddr!(OutboundIpAddrLookupSocketAddr,
 /// section 14.
 ///
 /// [1]: http://www.masterraghu.com/subjects/np/introduction/unix_network_programming_v1.3/ch08lev1sec14.html
+///
+/// Hosts without an IPv4 route fall back to looking up the outgoing IPv6 address.
 pub fn ip() -> Result<IpAddr> {
     let connect_addr = SocketAddr::from(OutboundIpAddrLookupSocketAddr::configured_value());
-    ip_impl(connect_addr).map_err(Error::NoOutboundIpAddr)
+    ip_impl(connect_addr).or_else(|_| {
+        let connect_addr = SocketAddr::from(OutboundIpv6AddrLookupSocketAddr::configured_value());
+        ip_impl(connect_addr)
+    })
+    .map_err(Error::NoOutboundIpAddr)
 }
 
 fn ip_impl(connect_addr: impl ToSocketAddrs) -> io::Result<IpAddr> {
-    let socket = UdpSocket::bind(UNSPECIFIED_SOCKET_ADDR)?;
+    let connect_addr =
+        connect_addr.to_socket_addrs()?
+                    .next()
+                    .ok_or_else(|| {
+                        io::Error::new(io::ErrorKind::InvalidInput, "no address to connect to")
+                    })?;
+    let unspecified = match connect_addr {
+        SocketAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        SocketAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+    };
+    let socket = UdpSocket::bind((unspecified, 0))?;
     socket.connect(connect_addr)?;
     let addr = socket.local_addr()?;
     Ok(addr.ip())
@@ -59,4 +88,14 @@ mod test {
         // localhost should route back to localhost
         assert_eq!(ip().unwrap(), Ipv4Addr::LOCALHOST);
     }
+
+    #[test]
+    fn ipv6_ip_lookup() {
+        // Sandboxed hosts may not have IPv6 loopback configured
+        if UdpSocket::bind((Ipv6Addr::LOCALHOST, 0)).is_err() {
+            return;
+        }
+        assert_eq!(ip_impl((Ipv6Addr::LOCALHOST, 80)).unwrap(),
+                   Ipv6Addr::LOCALHOST);
+    }
 }
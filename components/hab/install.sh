@@ -71,7 +71,7 @@ FLAGS:
     -h    Prints help information
     -v    Specifies a version (ex: 0.15.0, 0.15.0/20161222215311)
     -t    Specifies the ActiveTarget of the 'hab' program to download.
-            [values: x86_64-linux, x86_64-linux-kernel2] [default: x86_64-linux]
+            [values: x86_64-linux, x86_64-linux-kernel2, aarch64-linux] [default: x86_64-linux]
             This option is only valid on Linux platforms
 
 ENVIRONMENT VARIABLES:
@@ -149,8 +149,8 @@ get_platform() {
 # Target is present in the array.
 validate_target() {
   local valid_targets=("${arch}-${sys}")
-  case "${sys}" in
-   linux)
+  case "${arch}-${sys}" in
+   x86_64-linux)
     valid_targets+=("x86_64-linux-kernel2")
     ;;
   esac
@@ -4,6 +4,7 @@ pub mod hab;
 use crate::{cli::hab::{origin::Rbac,
                        pkg::{ExportCommand,
                              PkgExec},
+                       remote::Remote,
                        studio::Studio,
                        sup::{HabSup,
                              SupRun},
@@ -22,11 +23,17 @@ use habitat_common::{cli::{file_into_idents,
                            BINLINK_DIR_ENVVAR,
                            DEFAULT_BINLINK_DIR,
                            PACKAGE_TARGET_ENVVAR},
+                     output::OutputTargets,
+                     util::log_forward::LogForward,
                      FeatureFlag};
 use habitat_core::{crypto::CACHE_KEY_PATH_ENV_VAR,
                    env::Config,
                    origin::Origin,
-                   os::process::ShutdownTimeout,
+                   os::{cgroup,
+                        isolation,
+                        process::{self,
+                                  ShutdownTimeout},
+                        seccomp},
                    package::{Identifiable,
                              PackageIdent,
                              PackageTarget},
@@ -46,7 +53,8 @@ pub const AFTER_HELP: &str =
     "\nALIASES:\n    apply      Alias for: 'config apply'\n    install    Alias for: 'pkg \
      install'\n    run        Alias for: 'sup run'\n    setup      Alias for: 'cli setup'\n    \
      start      Alias for: 'svc start'\n    stop       Alias for: 'svc stop'\n    term       \
-     Alias for: 'sup term'\n";
+     Alias for: 'sup term'\n\nGLOBAL OPTIONS:\n    --output <FORMAT>    Report the results of a \
+     command in the given format, before the subcommand [possible values: text, json]\n";
 
 pub fn get(feature_flags: FeatureFlag) -> App<'static, 'static> {
     if feature_flags.contains(FeatureFlag::STRUCTOPT_CLI) {
@@ -93,6 +101,7 @@ pub fn get(feature_flags: FeatureFlag) -> App<'static, 'static> {
             (@setting SubcommandRequiredElseHelp)
             (subcommand: sub_cli_setup().aliases(&["s", "se", "set", "setu"]))
             (subcommand: sub_cli_completers().aliases(&["c", "co", "com", "comp"]))
+            (subcommand: sub_cli_complete())
         )
         (@subcommand config =>
             (about: "Commands relating to a Service's runtime config")
@@ -100,6 +109,16 @@ pub fn get(feature_flags: FeatureFlag) -> App<'static, 'static> {
             (@setting ArgRequiredElseHelp)
             (@setting SubcommandRequiredElseHelp)
             (subcommand: sub_config_apply().aliases(&["ap", "app", "appl"]))
+            (@subcommand audit =>
+                (about: "Displays who applied which configuration and files through a Supervisor")
+                (aliases: &["au", "aud", "audi"])
+                (@arg SERVICE_GROUP: +takes_value {valid_service_group}
+                    "Only show changes to this service group service.group[@organization] (ex: redis.default or foo.default@bazcorp)")
+                (@arg LIMIT: -l --limit +takes_value {valid_numeric::<u64>}
+                    "Only show this many of the most recent changes")
+                (@arg REMOTE_SUP: --("remote-sup") -r +takes_value default_value("127.0.0.1:9632")
+                    "Address to a remote Supervisor's Control Gateway")
+            )
             (@subcommand show =>
                 (about: "Displays the default configuration options for a service")
                 (aliases: &["sh", "sho"])
@@ -109,6 +128,7 @@ pub fn get(feature_flags: FeatureFlag) -> App<'static, 'static> {
                     "Address to a remote Supervisor's Control Gateway")
             )
         )
+        (subcommand: sub_doctor())
         (@subcommand file =>
             (about: "Commands relating to Habitat files")
             (aliases: &["f", "fi", "fil"])
@@ -153,11 +173,21 @@ pub fn get(feature_flags: FeatureFlag) -> App<'static, 'static> {
                         dependencies")
                 )
                 (@subcommand cancel =>
-                    (about: "Cancel a build job group and any in-progress builds")
+                    (about: "Cancel one or more build job groups and any in-progress builds")
                     (aliases: &["c", "ca", "can", "cance", "cancel"])
-                    (@arg GROUP_ID: +required +takes_value
-                        "The job group id that was returned from \"hab bldr job start\" \
-                        (ex: 771100000000000000)")
+                    (@group cancel =>
+                        (@attributes +required)
+                        (@arg GROUP_ID: +takes_value +multiple
+                            "The job group ids that were returned from \"hab bldr job start\" \
+                            (ex: 771100000000000000)")
+                        (@arg ORIGIN: -o --origin +takes_value {valid_origin}
+                            "Cancel the in-progress job groups created in this origin")
+                    )
+                    (@arg LIMIT: -l --limit +takes_value {valid_numeric::<usize>}
+                        "Limit how many of the origin's job groups to consider, ordered by most \
+                        recent (default: 50)")
+                    (@arg FAILING: --failing
+                        "Only cancel job groups in which a build job has already failed")
                     (@arg BLDR_URL: -u --url +takes_value {valid_url}
                         "Specify an alternate Builder endpoint. If not specified, the value will \
                          be taken from the HAB_BLDR_URL environment variable if defined. (default: \
@@ -222,6 +252,20 @@ pub fn get(feature_flags: FeatureFlag) -> App<'static, 'static> {
                          be taken from the HAB_BLDR_URL environment variable if defined. (default: \
                          https://bldr.habitat.sh)")
                 )
+                (@subcommand log =>
+                    (about: "Display the build log of a job")
+                    (aliases: &["l", "lo"])
+                    (@arg JOB_ID: +required +takes_value {valid_numeric::<u64>}
+                        "The job id as shown by \"hab bldr job status --showjobs\" \
+                        (ex: 771100000000000000)")
+                    (@arg FOLLOW: -f --follow
+                        "Stream new log output as it is produced until the job completes")
+                    (@arg BLDR_URL: -u --url +takes_value {valid_url}
+                        "Specify an alternate Builder endpoint. If not specified, the value will \
+                         be taken from the HAB_BLDR_URL environment variable if defined. (default: \
+                         https://bldr.habitat.sh)")
+                    (@arg AUTH_TOKEN: -z --auth +takes_value "Authentication token for Builder")
+                )
             )
             (@subcommand channel =>
                 (about: "Commands relating to Habitat Builder channels")
@@ -295,6 +339,7 @@ pub fn get(feature_flags: FeatureFlag) -> App<'static, 'static> {
                         "The origin for which channels will be listed. Default is from 'HAB_ORIGIN' \
                         or cli.toml")
                 )
+                (subcommand: sub_bldr_channel_promote_on_green())
             )
         )
         (@subcommand origin =>
@@ -334,6 +379,7 @@ pub fn get(feature_flags: FeatureFlag) -> App<'static, 'static> {
             )
             (@subcommand depart =>
                 (about: "Departs membership from selected origin")
+                (aliases: &["departure"])
                 (@arg ORIGIN: +required +takes_value {valid_origin} "The origin name")
                 (@arg BLDR_URL: -u --url +takes_value {valid_url}
                      "Specify an alternate Builder endpoint. If not specified, the value will \
@@ -434,6 +480,9 @@ pub fn get(feature_flags: FeatureFlag) -> App<'static, 'static> {
                         "Download public encryption key instead of origin public key")
                     (@arg AUTH_TOKEN: -z --auth +takes_value "Authentication token for Builder \
                         (required for downloading origin private keys)")
+                    (@arg ACCEPT_NEW_KEY: --("accept-new-key")
+                        "Trust a public origin key even if it does not match the key previously \
+                         downloaded for the same revision")
                 )
                 (@subcommand export =>
                     (about: "Outputs the latest origin key contents to stdout")
@@ -456,6 +505,50 @@ pub fn get(feature_flags: FeatureFlag) -> App<'static, 'static> {
                     (aliases: &["i", "im", "imp", "impo", "impor"])
                     (arg: arg_cache_key_path())
                 )
+                (@subcommand revocations =>
+                    (about: "Imports the revocation list of an origin's signing keys from Builder \
+                        or from a file")
+                    (@arg ORIGIN: +required +takes_value {valid_origin} "The origin name")
+                    (@arg FILE: --file +takes_value {file_exists}
+                        "Import the revocation list from a file instead of Builder")
+                    (@arg BLDR_URL: -u --url +takes_value {valid_url}
+                        "Specify an alternate Builder endpoint. If not specified, the value will \
+                         be taken from the HAB_BLDR_URL environment variable if defined. (default: \
+                         https://bldr.habitat.sh)")
+                    (arg: arg_cache_key_path())
+                )
+                (@subcommand revoke =>
+                    (about: "Revokes an origin signing key revision, signing the revocation list \
+                        of the origin with its latest key that is not revoked")
+                    (@arg ORIGIN: +required +takes_value {valid_origin} "The origin name")
+                    (@arg REVISION: +required +takes_value "The origin key revision to revoke")
+                    (@arg REASON: --reason +takes_value "Why the key revision is revoked")
+                    (@arg OUTPUT: -o --output +takes_value
+                        "Write the signed revocation list to this file")
+                    (@arg BLDR_URL: -u --url +takes_value {valid_url}
+                        "Specify an alternate Builder endpoint. If not specified, the value will \
+                         be taken from the HAB_BLDR_URL environment variable if defined. (default: \
+                         https://bldr.habitat.sh)")
+                    (@arg AUTH_TOKEN: -z --auth +takes_value "Authentication token for Builder")
+                    (@arg NO_UPLOAD: --("no-upload")
+                        "Don't upload the revocation list to Builder")
+                    (arg: arg_cache_key_path())
+                )
+                (@subcommand sync =>
+                    (about: "Downloads the public origin key revisions on Builder, uploads the \
+                        ones only on disk, and reports the origin private keys on disk")
+                    (@arg ORIGIN: +required +takes_value {valid_origin} "The origin name")
+                    (@arg BLDR_URL: -u --url +takes_value {valid_url}
+                        "Specify an alternate Builder endpoint. If not specified, the value will \
+                         be taken from the HAB_BLDR_URL environment variable if defined. (default: \
+                         https://bldr.habitat.sh)")
+                    (@arg AUTH_TOKEN: -z --auth +takes_value "Authentication token for Builder \
+                        (required for uploading public origin keys)")
+                    (@arg ACCEPT_NEW_KEY: --("accept-new-key")
+                        "Trust a public origin key even if it does not match the key previously \
+                         downloaded for the same revision")
+                    (arg: arg_cache_key_path())
+                )
                 (@subcommand upload =>
                     (@group upload =>
                         (@attributes +required)
@@ -487,8 +580,10 @@ pub fn get(feature_flags: FeatureFlag) -> App<'static, 'static> {
                     (@arg KEY_NAME: +required +takes_value
                         "The name of the variable key to be injected into the studio. \
                         Ex: KEY=\"some_value\"")
-                    (@arg SECRET: +required +takes_value
+                    (@arg SECRET: +takes_value required_unless[FROM_STDIN]
                         "The contents of the variable to be injected into the studio")
+                    (@arg FROM_STDIN: --("from-stdin") conflicts_with[SECRET]
+                        "Read the contents of the secret from stdin")
                     (@arg BLDR_URL: -u --url +takes_value {valid_url}
                         "Specify an alternate Builder endpoint. If not specified, the value will \
                          be taken from the HAB_BLDR_URL environment variable if defined. (default: \
@@ -499,6 +594,41 @@ pub fn get(feature_flags: FeatureFlag) -> App<'static, 'static> {
                         'HAB_ORIGIN' or cli.toml")
                     (arg: arg_cache_key_path())
                 )
+                (@subcommand update =>
+                    (about: "Replace the value of a secret for your origin, creating it if it \
+                        does not exist")
+                    (@arg KEY_NAME: +required +takes_value
+                        "The name of the variable key to be injected into the studio")
+                    (@arg SECRET: +takes_value required_unless[FROM_STDIN]
+                        "The new contents of the variable to be injected into the studio")
+                    (@arg FROM_STDIN: --("from-stdin") conflicts_with[SECRET]
+                        "Read the contents of the secret from stdin")
+                    (@arg BLDR_URL: -u --url +takes_value {valid_url}
+                        "Specify an alternate Builder endpoint. If not specified, the value will \
+                         be taken from the HAB_BLDR_URL environment variable if defined. (default: \
+                         https://bldr.habitat.sh)")
+                    (@arg AUTH_TOKEN: -z --auth +takes_value "Authentication token for Builder")
+                    (@arg ORIGIN: -o --origin +takes_value {valid_origin}
+                        "The origin for which the secret will be updated. Default is from \
+                        'HAB_ORIGIN' or cli.toml")
+                    (arg: arg_cache_key_path())
+                )
+                (@subcommand import =>
+                    (about: "Upload every secret in an env file to your origin, replacing \
+                        existing secrets with the same name")
+                    (@arg ENV_FILE: +required +takes_value {file_exists}
+                        "Path to a file of KEY=VALUE lines. Blank lines and lines starting with \
+                        '#' are ignored")
+                    (@arg BLDR_URL: -u --url +takes_value {valid_url}
+                        "Specify an alternate Builder endpoint. If not specified, the value will \
+                         be taken from the HAB_BLDR_URL environment variable if defined. (default: \
+                         https://bldr.habitat.sh)")
+                    (@arg AUTH_TOKEN: -z --auth +takes_value "Authentication token for Builder")
+                    (@arg ORIGIN: -o --origin +takes_value {valid_origin}
+                        "The origin for which the secrets will be uploaded. Default is from \
+                        'HAB_ORIGIN' or cli.toml")
+                    (arg: arg_cache_key_path())
+                )
                 (@subcommand delete =>
                     (about: "Delete a secret for your origin")
                     (@arg KEY_NAME: +required +takes_value
@@ -522,6 +652,8 @@ pub fn get(feature_flags: FeatureFlag) -> App<'static, 'static> {
                     (@arg ORIGIN: -o --origin +takes_value {valid_origin}
                         "The origin for which secrets will be listed. Default is from 'HAB_ORIGIN' \
                         or cli.toml")
+                    (@arg METADATA: --metadata
+                        "Display who created each secret and when")
                 )
             )
         )
@@ -530,6 +662,21 @@ pub fn get(feature_flags: FeatureFlag) -> App<'static, 'static> {
             (aliases: &["p", "pk", "package"])
             (@setting ArgRequiredElseHelp)
             (@setting SubcommandRequiredElseHelp)
+            (@subcommand attest =>
+                (about: "Attests to the provenance of a Habitat Artifact, writing an attestation \
+                    signed with its origin key next to it")
+                (@arg SOURCE: +required +takes_value {file_exists} "A path to a Habitat Artifact \
+                    (ex: /home/acme-redis-3.0.7-21120102031201-x86_64-linux.hart)")
+                (@arg BUILDER_ID: --("builder-id") +required +takes_value
+                    "The identity of the builder that built the artifact \
+                    (ex: https://ci.example.com/jobs/42)")
+                (@arg SOURCE_URI: --("source-uri") +takes_value
+                    "The URI of the source the artifact's plan was built from \
+                    (ex: git+https://github.com/acme/plans)")
+                (@arg SOURCE_REVISION: --("source-revision") +takes_value
+                    "The git commit of the source the artifact's plan was built from")
+                (arg: arg_cache_key_path())
+            )
             (@subcommand binds =>
                 (about: "Displays the binds for a service")
                 (@arg PKG_IDENT: +required +takes_value {valid_ident}
@@ -538,13 +685,18 @@ pub fn get(feature_flags: FeatureFlag) -> App<'static, 'static> {
             (@subcommand binlink =>
                 (about: "Creates a binlink for a package binary in a common 'PATH' location")
                 (aliases: &["bi", "bin", "binl", "binli", "binlin"])
-                (@arg PKG_IDENT: +required +takes_value {valid_ident}
+                (@arg PKG_IDENT: +takes_value {valid_ident}
+                    required_unless_one(&["ALL_INSTALLED", "PRUNE"])
                     "A package identifier (ex: core/redis, core/busybox-static/1.42.2)")
                 (@arg BINARY: +takes_value
                     "The command to binlink (ex: bash)")
                 (@arg DEST_DIR: -d --dest +takes_value {non_empty} env(BINLINK_DIR_ENVVAR) default_value(DEFAULT_BINLINK_DIR)
                     "Sets the destination directory")
                 (@arg FORCE: -f --force "Overwrite existing binlinks")
+                (@arg ALL_INSTALLED: --("all-installed") conflicts_with[PKG_IDENT BINARY]
+                    "Binlink all binaries of the latest installed release of every installed package")
+                (@arg PRUNE: --prune
+                    "Remove the binlinks pointing into package releases that are no longer installed")
              )
             (subcommand: sub_pkg_build())
             (@subcommand config =>
@@ -553,14 +705,29 @@ pub fn get(feature_flags: FeatureFlag) -> App<'static, 'static> {
                 (@arg PKG_IDENT: +required +takes_value {valid_ident}
                     "A package identifier (ex: core/redis, core/busybox-static/1.42.2)")
              )
+            (@subcommand dedup =>
+                (about: "Stores the identical files of installed packages once, hard linking them \
+                    from a store keyed on their blake2b hash")
+            )
             (subcommand: sub_pkg_download())
             (@subcommand env =>
                 (about: "Prints the runtime environment of a specific installed package")
                 (@arg PKG_IDENT: +required +takes_value {valid_ident}
                     "A package identifier (ex: core/redis, core/busybox-static/1.42.2)")
+                (@arg SHELL: --shell +takes_value possible_value[sh fish powershell json dotenv]
+                    "The shell or format to print the environment for (default: sh, or powershell on Windows)")
             )
             (subcommand: PkgExec::clap())
             (subcommand: ExportCommand::clap())
+            (@subcommand gc =>
+                (about: "Uninstalls the package releases that are not loaded by the Supervisor, \
+                    binlinked or depended on by another package that is kept")
+                (@arg DRYRUN: -d --("dry-run") "Just show what would be uninstalled, don't actually do it")
+                (@arg KEEP_LATEST: --("keep-latest") +takes_value default_value("1") {valid_numeric::<usize>}
+                    "Keep this number of latest releases of each package, whether or not they are used")
+                (@arg BINLINK_DIR: --("binlink-dir") +takes_value {non_empty} env(BINLINK_DIR_ENVVAR) default_value(DEFAULT_BINLINK_DIR)
+                    "The directory whose binlinks keep the packages they point to")
+            )
             (@subcommand hash =>
                 (about: "Generates a blake2b hashsum from a target at any given filepath")
                 (aliases: &["ha", "has"])
@@ -588,6 +755,22 @@ pub fn get(feature_flags: FeatureFlag) -> App<'static, 'static> {
                 )
 
             )
+            (@subcommand lock =>
+                (about: "Writes a lockfile pinning the releases at the head of a channel of \
+                    packages and their runtime dependencies")
+                (@arg PKG_IDENT: +required +multiple +takes_value {valid_ident}
+                    "One or more Habitat package identifiers (ex: acme/redis)")
+                (@arg BLDR_URL: -u --url +takes_value {valid_url} "Specify an alternate Builder \
+                    endpoint. If not specified, the value will be taken from the HAB_BLDR_URL \
+                    environment variable if defined. (default: https://bldr.habitat.sh)")
+                (@arg CHANNEL: --channel -c +takes_value default_value[stable] env(ChannelIdent::ENVVAR)
+                    "Pin the releases in the specified release channel")
+                (@arg PKG_TARGET: --target -t +takes_value {valid_target}
+                    "A package target (ex: x86_64-windows) (default: system appropriate target)")
+                (@arg OUTPUT: -o --output +takes_value
+                    "The path to write the lockfile to (default: stdout)")
+                (@arg AUTH_TOKEN: -z --auth +takes_value "Authentication token for Builder")
+            )
             (@subcommand provides =>
                 (about: "Search installed Habitat packages for a given file")
                 (@arg FILE: +required +takes_value
@@ -726,6 +909,8 @@ pub fn get(feature_flags: FeatureFlag) -> App<'static, 'static> {
                 (aliases: &["v", "ve", "ver", "veri", "verif"])
                 (@arg SOURCE: +required +takes_value {file_exists} "A path to a Habitat Artifact \
                     (ex: /home/acme-redis-3.0.7-21120102031201-x86_64-linux.hart)")
+                (@arg PROVENANCE: --provenance "Also verify the provenance attestation next to \
+                    the artifact, which must be signed with a key of the artifact's origin")
                 (arg: arg_cache_key_path())
             )
             (@subcommand header =>
@@ -769,6 +954,10 @@ pub fn get(feature_flags: FeatureFlag) -> App<'static, 'static> {
                 (@arg MIN: --min -m "Create a minimal plan file")
                 (@arg SCAFFOLDING: --scaffolding -s +takes_value
                     "Specify explicit Scaffolding for your app (ex: node, ruby)")
+                (@arg TEMPLATE: --template -t +takes_value conflicts_with[SCAFFOLDING MIN]
+                    possible_value[rust node python go java]
+                    "Generate a working plan, default hooks and config templates for an app \
+                    written in the given language")
             )
             (@subcommand render =>
                 (about: "Renders plan config files")
@@ -777,13 +966,28 @@ pub fn get(feature_flags: FeatureFlag) -> App<'static, 'static> {
                 (@arg DEFAULT_TOML: -d --("default-toml") +takes_value default_value("./default.toml") "Path to default.toml")
                 (@arg USER_TOML: -u --("user-toml") +takes_value "Path to user.toml, defaults to none")
                 (@arg MOCK_DATA: -m --("mock-data") +takes_value "Path to json file with mock data for template, defaults to none")
+                (@arg MOCK_CENSUS: -c --("mock-census") +takes_value {file_exists}
+                    "Path to a TOML or JSON file describing the service group members, binds and \
+                    system info to render the template with, defaults to none")
                 (@arg PRINT: -p --("print") "Prints config to STDOUT")
                 (@arg RENDER_DIR: -r --("render-dir") +takes_value default_value("./results") "Path to render templates")
                 (@arg NO_RENDER: -n --("no-render") "Don't write anything to disk, ignores --render-dir")
                 (@arg QUIET: -q --("no-verbose") --quiet
                     "Don't print any helper messages.  When used with `--print` will only print config file")
             )
+            (@subcommand test =>
+                (about: "Runs the hooks of a plan against the test cases of a test manifest, with \
+                    the config and mock census of each test case, and checks their exit codes and \
+                    output")
+                (@arg PLAN_DIR: +takes_value default_value(".")
+                    "Path to the plan directory, with its default.toml, config and hooks")
+                (@arg MANIFEST: -m --manifest +takes_value {file_exists}
+                    "Path to the test manifest, defaults to tests/hooks.toml in the plan directory")
+                (@arg FILTER: -f --filter +takes_value
+                    "Only run the test cases whose name contains this string")
+            )
         )
+        (subcommand: Remote::clap())
         (@subcommand ring =>
             (about: "Commands relating to Habitat rings")
             (aliases: &["r", "ri", "rin"])
@@ -794,6 +998,19 @@ pub fn get(feature_flags: FeatureFlag) -> App<'static, 'static> {
                 (aliases: &["k", "ke"])
                 (@setting ArgRequiredElseHelp)
                 (@setting SubcommandRequiredElseHelp)
+                (@subcommand distribute =>
+                    (about: "Copies the latest ring key to the key caches of the peers of a \
+                    ring over SSH")
+                    (@arg RING: +required +takes_value "Ring key name")
+                    (@arg PEERS: --peers +required +takes_value {file_exists}
+                        "A file listing the peers, one SSH destination ([user@]host) per line")
+                    (@arg SSH: --ssh +takes_value default_value("ssh")
+                        "The SSH client to connect to the peers with")
+                    (@arg SUDO: --sudo "Import the key on the peers with sudo")
+                    (@arg REMOTE_CACHE_KEY_PATH: --("remote-cache-key-path") +takes_value
+                        "The key cache of the peers (default: their default key cache)")
+                    (arg: arg_cache_key_path())
+                )
                 (@subcommand export =>
                     (about: "Outputs the latest ring key contents to stdout")
                     (aliases: &["e", "ex", "exp", "expo", "expor"])
@@ -847,6 +1064,7 @@ pub fn get(feature_flags: FeatureFlag) -> App<'static, 'static> {
             (about: "Create a tarball of Habitat Supervisor data to send to support")
             (aliases: &["supp", "suppo", "suppor", "support-bundle"])
         )
+        (subcommand: sub_top())
         (@subcommand user =>
             (about: "Commands relating to Habitat users")
             (aliases: &["u", "us", "use"])
@@ -857,12 +1075,48 @@ pub fn get(feature_flags: FeatureFlag) -> App<'static, 'static> {
                 (aliases: &["k", "ke"])
                 (@setting ArgRequiredElseHelp)
                 (@setting SubcommandRequiredElseHelp)
+                (@subcommand export =>
+                    (about: "Outputs the latest user key contents to stdout")
+                    (aliases: &["e", "ex", "exp", "expo", "expor"])
+                    (@arg USER: +required +takes_value "Name of the user key")
+                    (@arg KEY_TYPE: -t --type +takes_value {valid_key_type}
+                        "Export either the 'public' or 'secret' key")
+                    (arg: arg_cache_key_path())
+                )
                 (@subcommand generate =>
                     (about: "Generates a Habitat user key")
                     (aliases: &["g", "ge", "gen", "gene", "gener", "genera", "generat"])
                     (@arg USER: +required +takes_value "Name of the user key")
                     (arg: arg_cache_key_path())
                 )
+                (@subcommand import =>
+                    (about: "Reads a stdin stream containing a public or secret user key \
+                        contents and writes the key to disk")
+                    (aliases: &["i", "im", "imp", "impo", "impor"])
+                    (arg: arg_cache_key_path())
+                )
+                (@subcommand list =>
+                    (about: "Lists the user keys on disk")
+                    (aliases: &["l", "li", "lis"])
+                    (@arg USER: +takes_value "Only list the keys of this user")
+                    (arg: arg_cache_key_path())
+                )
+            )
+            (@subcommand encrypt =>
+                (about: "Encrypts a file for another user, who can decrypt it with \
+                    'hab user decrypt'")
+                (@arg FOR: --for +required +takes_value "Name of the user key to encrypt for")
+                (@arg FILE: +takes_value {file_exists_or_stdin}
+                    "Path to local file on disk (ex: /tmp/secret.txt, default: <stdin>)")
+                (@arg USER: -u --user +takes_value
+                    "Name of the user key to encrypt with (default: $HAB_USER)")
+                (arg: arg_cache_key_path())
+            )
+            (@subcommand decrypt =>
+                (about: "Decrypts a file encrypted for a user with 'hab user encrypt'")
+                (@arg FILE: +takes_value {file_exists_or_stdin}
+                    "Path to local file on disk (ex: /tmp/secret.txt.box, default: <stdin>)")
+                (arg: arg_cache_key_path())
             )
         )
         (subcommand: alias_apply)
@@ -932,6 +1186,24 @@ fn sub_cli_completers() -> App<'static, 'static> {
                                    .possible_values(&supported_shells))
 }
 
+fn sub_cli_complete() -> App<'static, 'static> {
+    let sub = clap_app!(@subcommand complete =>
+        (about: "Lists candidates for the command-line completers to complete arguments with")
+        (@setting Hidden));
+
+    sub.arg(Arg::with_name("SOURCE").help("What to list: the services loaded in a Supervisor, \
+                                           the installed packages or the named remote Supervisors")
+                                    .required(true)
+                                    .takes_value(true)
+                                    .case_insensitive(true)
+                                    .possible_values(&["Services", "Idents", "Remotes"]))
+       .arg(Arg::with_name("REMOTE_SUP").help("The Supervisor to list the services of instead of \
+                                               the local one, by address or by name")
+                                        .long("remote-sup")
+                                        .short("r")
+                                        .takes_value(true))
+}
+
 fn arg_cache_key_path() -> Arg<'static, 'static> {
     Arg::with_name("CACHE_KEY_PATH").long("cache-key-path")
                                     .validator(non_empty)
@@ -940,6 +1212,23 @@ fn arg_cache_key_path() -> Arg<'static, 'static> {
                                     .help("Cache for creating and searching for encryption keys")
 }
 
+fn arg_studio_mount() -> Arg<'static, 'static> {
+    Arg::with_name("STUDIO_MOUNT").long("mount")
+                                  .takes_value(true)
+                                  .multiple(true)
+                                  .number_of_values(1)
+                                  .help("Mounts a host path into the Studio, optionally read-only \
+                                         (ex: /home/me/.m2:/root/.m2:ro)")
+}
+
+fn arg_studio_env_pass() -> Arg<'static, 'static> {
+    Arg::with_name("STUDIO_ENV_PASS").long("env-pass")
+                                     .takes_value(true)
+                                     .multiple(true)
+                                     .number_of_values(1)
+                                     .help("Passes an environment variable into the Studio")
+}
+
 fn arg_target() -> Arg<'static, 'static> {
     Arg::with_name("PKG_TARGET").takes_value(true)
                                 .validator(valid_target)
@@ -948,6 +1237,52 @@ fn arg_target() -> Arg<'static, 'static> {
                                        appropriate target)")
 }
 
+fn sub_bldr_channel_promote_on_green() -> App<'static, 'static> {
+    App::new("promote-on-green")
+        .about("Watches the job groups of an origin and promotes the packages they build to a \
+                channel as the groups finish")
+        .arg(Arg::with_name("ORIGIN").required(true)
+                                     .takes_value(true)
+                                     .validator(valid_origin)
+                                     .help("The origin whose job groups are watched"))
+        .arg(Arg::with_name("CHANNEL").required(true)
+                                      .takes_value(true)
+                                      .help("The channel to promote packages to"))
+        .arg(Arg::with_name("POLICY").short("p")
+                                     .long("policy")
+                                     .takes_value(true)
+                                     .validator(file_exists)
+                                     .help("A policy file deciding which packages are promoted \
+                                            (default: every package of a job group in which \
+                                            every build succeeded)"))
+        .arg(Arg::with_name("LIMIT").short("l")
+                                    .long("limit")
+                                    .takes_value(true)
+                                    .validator(valid_numeric::<usize>)
+                                    .help("Limit how many of the origin's job groups to watch, \
+                                           ordered by most recent (default: 10)"))
+        .arg(Arg::with_name("INTERVAL").short("i")
+                                       .long("interval")
+                                       .takes_value(true)
+                                       .validator(valid_numeric::<u64>)
+                                       .help("The interval in seconds on which to check the job \
+                                              groups (default: 60)"))
+        .arg(Arg::with_name("ONCE").long("once")
+                                   .help("Check the job groups once instead of watching them"))
+        .arg(Arg::with_name("BLDR_URL").short("u")
+                                       .long("url")
+                                       .takes_value(true)
+                                       .validator(valid_url)
+                                       .help("Specify an alternate Builder endpoint. If not \
+                                              specified, the value will be taken from the \
+                                              HAB_BLDR_URL environment variable if defined. \
+                                              (default: https://bldr.habitat.sh)"))
+        .arg(Arg::with_name("AUTH_TOKEN").short("z")
+                                         .long("auth")
+                                         .takes_value(true)
+                                         .help("Authentication token for Builder"))
+}
+
 fn sub_pkg_build() -> App<'static, 'static> {
     let mut sub = clap_app!(@subcommand build =>
     (about: "Builds a Plan using a Studio")
@@ -961,6 +1296,8 @@ fn sub_pkg_build() -> App<'static, 'static> {
         "A directory containing a plan file \
         or a `habitat/` directory which contains the plan file")
     (arg: arg_cache_key_path())
+    (arg: arg_studio_mount())
+    (arg: arg_studio_env_pass())
     );
     // Only a truly native/local Studio can be reused--the Docker implementation will always be
     // ephemeral
@@ -1020,6 +1357,9 @@ fn sub_pkg_install(feature_flags: FeatureFlag) -> App<'static, 'static> {
         (@arg FORCE: -f --force "Overwrite existing binlinks")
         (@arg AUTH_TOKEN: -z --auth +takes_value "Authentication token for Builder")
         (@arg IGNORE_INSTALL_HOOK: --("ignore-install-hook") "Do not run any install hooks")
+        (@arg RELOCATE: --relocate "Rewrite the interpreter paths and RUNPATHs of installed \
+            packages to run them from beneath FS_ROOT. Binaries are rewritten with patchelf, which \
+            must be on the PATH")
     );
     sub = sub.arg(Arg::with_name("OFFLINE").help("Install packages in offline mode")
                                                .hidden(!feature_flags.contains(FeatureFlag::OFFLINE_INSTALL))
@@ -1064,9 +1404,17 @@ fn sub_svc_start() -> App<'static, 'static> {
 fn sub_svc_status() -> App<'static, 'static> {
     clap_app!(@subcommand status =>
         (about: "Query the status of Habitat services")
-        (@arg PKG_IDENT: +takes_value {valid_ident} "A package identifier (ex: core/redis, core/busybox-static/1.42.2)")
+        (@arg PKG_IDENT: +takes_value {valid_ident} "A package identifier (ex: core/redis, \
+            core/busybox-static/1.42.2). The resources used by the service's processes and the \
+            output of its last failed lifecycle hook, if any, are shown along with its status")
+        (@arg history: --history
+            "Show the most recent health check results instead of the service status")
         (@arg REMOTE_SUP: --("remote-sup") -r +takes_value default_value("127.0.0.1:9632")
             "Address to a remote Supervisor's Control Gateway")
+        (@arg REMOTE_RING: --("remote-ring") +takes_value conflicts_with[history]
+            "Address to the Control Gateway of a member of a gossip ring. The status of the \
+            services of every Supervisor in the ring it knows of is shown, instead of the \
+            services of the remote Supervisor")
     )
 }
 
@@ -1099,6 +1447,30 @@ fn sub_svc_unload() -> App<'static, 'static> {
     add_shutdown_timeout_option(sub)
 }
 
+fn sub_doctor() -> App<'static, 'static> {
+    clap_app!(@subcommand doctor =>
+        (about: "Check the local Habitat installation for common problems")
+        (@arg REMOTE_SUP: --("remote-sup") -r +takes_value default_value("127.0.0.1:9632")
+            "Address to a remote Supervisor's Control Gateway")
+        (@arg BLDR_URL: -u --url +takes_value {valid_url}
+            "Specify an alternate Builder endpoint. If not specified, the value will \
+             be taken from the HAB_BLDR_URL environment variable if defined. (default: \
+             https://bldr.habitat.sh)")
+        (arg: arg_cache_key_path())
+    )
+}
+
+fn sub_top() -> App<'static, 'static> {
+    clap_app!(@subcommand top =>
+        (about: "Live view of the services of one or more Supervisors")
+        (@arg REMOTE_SUP: --("remote-sup") -r +takes_value +multiple number_of_values(1)
+            default_value("127.0.0.1:9632")
+            "Address to the Control Gateway of a Supervisor to watch. May be given more than once.")
+        (@arg INTERVAL: --interval -i +takes_value default_value("2") {valid_numeric::<u64>}
+            "How often to refresh the view, in seconds")
+    )
+}
+
 // CLAP Validation Functions
 ////////////////////////////////////////////////////////////////////////
 
@@ -1231,6 +1603,72 @@ fn valid_shutdown_timeout(val: String) -> result::Result<(), String> {
     }
 }
 
+#[allow(clippy::needless_pass_by_value)] // Signature required by CLAP
+fn valid_cpu_max(val: String) -> result::Result<(), String> { cgroup::validate_cpu_max(&val) }
+
+#[allow(clippy::needless_pass_by_value)] // Signature required by CLAP
+fn valid_memory_max(val: String) -> result::Result<(), String> { cgroup::validate_memory_max(&val) }
+
+#[allow(clippy::needless_pass_by_value)] // Signature required by CLAP
+fn valid_io_weight(val: String) -> result::Result<(), String> { cgroup::validate_io_weight(&val) }
+
+#[allow(clippy::needless_pass_by_value)] // Signature required by CLAP
+fn valid_port_mapping(val: String) -> result::Result<(), String> {
+    isolation::validate_port_mapping(&val)
+}
+
+#[allow(clippy::needless_pass_by_value)] // Signature required by CLAP
+fn valid_allowed_path(val: String) -> result::Result<(), String> {
+    isolation::validate_allowed_path(&val)
+}
+
+#[allow(clippy::needless_pass_by_value)] // Signature required by CLAP
+fn valid_seccomp_profile(val: String) -> result::Result<(), String> {
+    seccomp::validate_seccomp_profile(&val)
+}
+
+#[allow(clippy::needless_pass_by_value)] // Signature required by CLAP
+fn valid_umask(val: String) -> result::Result<(), String> { process::validate_umask(&val) }
+
+#[allow(clippy::needless_pass_by_value)] // Signature required by CLAP
+fn valid_ulimit(val: String) -> result::Result<(), String> { process::validate_ulimit(&val) }
+
+#[allow(clippy::needless_pass_by_value)] // Signature required by CLAP
+fn valid_nice(val: String) -> result::Result<(), String> { process::validate_nice(&val) }
+
+#[allow(clippy::needless_pass_by_value)] // Signature required by CLAP
+fn valid_io_priority(val: String) -> result::Result<(), String> {
+    process::validate_io_priority(&val)
+}
+
+#[allow(clippy::needless_pass_by_value)] // Signature required by CLAP
+fn valid_working_dir(val: String) -> result::Result<(), String> {
+    process::validate_working_dir(&val)
+}
+
+/// Environment files are read by the Supervisor, which may run on another host, so their paths
+/// must not depend on the working directory of the CLI.
+#[allow(clippy::needless_pass_by_value)] // Signature required by CLAP
+fn valid_env_file(val: String) -> result::Result<(), String> {
+    if Path::new(&val).is_absolute() {
+        Ok(())
+    } else {
+        Err(format!("'{}' is not a valid environment file, expected \
+                     an absolute path",
+                    val))
+    }
+}
+
+#[allow(clippy::needless_pass_by_value)] // Signature required by CLAP
+fn valid_log_forward(val: String) -> result::Result<(), String> {
+    val.parse::<LogForward>().map(|_| ())
+}
+
+#[allow(clippy::needless_pass_by_value)] // Signature required by CLAP
+fn valid_output_targets(val: String) -> result::Result<(), String> {
+    val.parse::<OutputTargets>().map(|_| ())
+}
+
 #[allow(clippy::needless_pass_by_value)] // Signature required by CLAP
 fn non_empty(val: String) -> result::Result<(), String> {
     if val.is_empty() {
@@ -5,10 +5,13 @@ use crate::error::Result;
 use futures::stream::StreamExt;
 use habitat_common as common;
 use habitat_common::{types::ListenCtlAddr,
-                     ui::{UIWriter,
+                     ui::{self,
+                          OutputFormat,
+                          UIWriter,
                           UI}};
 use habitat_sup_client::{SrvClient,
-                         SrvClientError};
+                         SrvClientError,
+                         SrvSession};
 use habitat_sup_protocol as sup_proto;
 use habitat_sup_protocol::codec::SrvMessage;
 use std::{fmt,
@@ -35,6 +38,18 @@ pub async fn send(remote_sup_addr: &ListenCtlAddr,
     Ok(())
 }
 
+/// Send a message over a session with the Supervisor's control gateway, and process the reply.
+pub async fn send_in_session(session: &mut SrvSession,
+                             msg: impl Into<SrvMessage> + fmt::Debug)
+                             -> Result<()> {
+    let mut response = session.request(msg).await?;
+    while let Some(message_result) = response.next().await {
+        let reply = message_result?;
+        handle_ctl_reply(&reply)?;
+    }
+    Ok(())
+}
+
 ////////////////////////////////////////////////////////////////////////
 
 fn handle_ctl_reply(reply: &SrvMessage) -> result::Result<(), SrvClientError> {
@@ -56,6 +71,9 @@ fn handle_ctl_reply(reply: &SrvMessage) -> result::Result<(), SrvClientError> {
             };
             common::ui::print(UI::default_with_env().out(), m.line.as_bytes(), msg_spec)?;
         }
+        // The progress bar always draws on standard output, which is kept for the result alone
+        // when reporting results as JSON.
+        "NetProgress" if ui::output_format() == OutputFormat::Json => {}
         "NetProgress" => {
             let m = reply.parse::<sup_proto::ctl::NetProgress>()
                          .map_err(SrvClientError::Decode)?;
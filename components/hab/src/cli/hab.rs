@@ -1,17 +1,20 @@
 mod bldr;
-mod cli;
+pub mod cli;
 mod config;
+pub mod doctor;
 mod file;
 pub mod license;
 pub mod origin;
 pub mod pkg;
 mod plan;
+pub mod remote;
 mod ring;
 pub mod studio;
 pub mod sup;
 pub mod svc;
 #[cfg(test)]
 mod tests;
+pub mod top;
 mod user;
 pub mod util;
 
@@ -23,6 +26,8 @@ use self::{bldr::{Bldr,
                     ConfigOptServiceConfigApply,
                     ServiceConfig,
                     ServiceConfigApply},
+           doctor::{ConfigOptDoctor,
+                    Doctor},
            file::{ConfigOptFile,
                   File},
            license::{ConfigOptLicense,
@@ -35,6 +40,8 @@ use self::{bldr::{Bldr,
                  PkgInstall},
            plan::{ConfigOptPlan,
                   Plan},
+           remote::{ConfigOptRemote,
+                    Remote},
            ring::{ConfigOptRing,
                   Ring},
            studio::{ConfigOptStudio,
@@ -49,6 +56,8 @@ use self::{bldr::{Bldr,
                  Svc,
                  SvcStart,
                  SvcStop},
+           top::{ConfigOptTop,
+                 Top},
            user::{ConfigOptUser,
                   User},
            util::{CacheKeyPath,
@@ -76,6 +85,8 @@ pub enum Hab {
     #[structopt(no_version)]
     Config(ServiceConfig),
     #[structopt(no_version)]
+    Doctor(Doctor),
+    #[structopt(no_version)]
     File(File),
     #[structopt(no_version)]
     License(License),
@@ -86,6 +97,8 @@ pub enum Hab {
     #[structopt(no_version)]
     Plan(Plan),
     #[structopt(no_version)]
+    Remote(Remote),
+    #[structopt(no_version)]
     Ring(Ring),
     #[structopt(no_version, aliases = &["stu", "stud", "studi"])]
     Studio(Studio),
@@ -97,6 +110,8 @@ pub enum Hab {
     #[structopt(no_version)]
     Svc(Svc),
     #[structopt(no_version)]
+    Top(Top),
+    #[structopt(no_version)]
     User(User),
 
     /// Alias for 'config apply'
@@ -4,10 +4,12 @@ use super::util::{AuthToken,
                   ConfigOptBldrUrl,
                   ConfigOptPkgIdent,
                   PkgIdent};
-use crate::cli::valid_origin;
+use crate::cli::{file_exists,
+                 valid_origin};
 use configopt::ConfigOpt;
 use habitat_common::cli::PACKAGE_TARGET_ENVVAR;
 use habitat_core::package::PackageTarget;
+use std::path::PathBuf;
 use structopt::{clap::ArgGroup,
                 StructOpt};
 
@@ -100,6 +102,49 @@ pub enum Channel {
         #[structopt(flatten)]
         auth_token:     AuthToken,
     },
+    /// Watches the job groups of an origin and promotes the packages they build to a channel as
+    /// the groups finish
+    PromoteOnGreen {
+        /// The origin whose job groups are watched
+        #[structopt(name = "ORIGIN", validator = valid_origin)]
+        origin:     String,
+        /// The channel to promote packages to
+        #[structopt(name = "CHANNEL")]
+        channel:    String,
+        /// A policy file deciding which packages are promoted (default: every package of a job
+        /// group in which every build succeeded)
+        #[structopt(name = "POLICY", short = "p", long = "policy", validator = file_exists)]
+        policy:     Option<PathBuf>,
+        /// Limit how many of the origin's job groups to watch, ordered by most recent (default:
+        /// 10)
+        #[structopt(name = "LIMIT", short = "l", long = "limit")]
+        limit:      Option<usize>,
+        /// The interval in seconds on which to check the job groups (default: 60)
+        #[structopt(name = "INTERVAL", short = "i", long = "interval")]
+        interval:   Option<u64>,
+        /// Check the job groups once instead of watching them
+        #[structopt(name = "ONCE", long = "once")]
+        once:       bool,
+        #[structopt(flatten)]
+        bldr_url:   BldrUrl,
+        #[structopt(flatten)]
+        auth_token: AuthToken,
+    },
+}
+
+#[derive(ConfigOpt, StructOpt, Debug)]
+#[structopt(group = ArgGroup::with_name("cancel").required(true), no_version)]
+pub struct BldrJobCancelSourceGroup {
+    /// The job group ids that were returned from "hab bldr job start" (ex: 771100000000000000)
+    #[structopt(name = "GROUP_ID", group = "cancel")]
+    group_id: Vec<String>,
+    /// Cancel the in-progress job groups created in this origin
+    #[structopt(name = "ORIGIN",
+            short = "o",
+            long = "origin",
+            validator = valid_origin,
+            group = "cancel")]
+    origin:   Option<String>,
 }
 
 #[derive(ConfigOpt, StructOpt, Debug)]
@@ -121,11 +166,17 @@ pub struct BldrJobStatusSourceGroup {
 #[structopt(no_version)]
 /// Commands relating to Habitat Builder jobs
 pub enum Job {
-    /// Cancel a build job group and any in-progress builds
+    /// Cancel one or more build job groups and any in-progress builds
     Cancel {
-        /// The job group id that was returned from "hab bldr job start" (ex: 771100000000000000)
-        #[structopt(name = "GROUP_ID")]
-        group_id:   String,
+        #[structopt(flatten)]
+        source:     BldrJobCancelSourceGroup,
+        /// Limit how many of the origin's job groups to consider, ordered by most recent
+        /// (default: 50)
+        #[structopt(name = "LIMIT", short = "l", long = "limit")]
+        limit:      Option<usize>,
+        /// Only cancel job groups in which a build job has already failed
+        #[structopt(name = "FAILING", long = "failing")]
+        failing:    bool,
         #[structopt(flatten)]
         bldr_url:   BldrUrl,
         /// Don't prompt for confirmation
@@ -156,6 +207,19 @@ pub enum Job {
         #[structopt(flatten)]
         auth_token:  AuthToken,
     },
+    /// Display the build log of a job
+    Log {
+        /// The job id as shown by "hab bldr job status --showjobs" (ex: 771100000000000000)
+        #[structopt(name = "JOB_ID")]
+        job_id:     u64,
+        /// Stream new log output as it is produced until the job completes
+        #[structopt(name = "FOLLOW", short = "f", long = "follow")]
+        follow:     bool,
+        #[structopt(flatten)]
+        bldr_url:   BldrUrl,
+        #[structopt(flatten)]
+        auth_token: AuthToken,
+    },
     /// Promote packages from a completed build job to a specified channel
     Promote {
         /// The job group id that was returned from "hab bldr job start" (ex: 771100000000000000)
@@ -1,7 +1,8 @@
 use super::util::{CacheKeyPath,
                   ConfigOptCacheKeyPath};
 use configopt::ConfigOpt;
-use structopt::StructOpt;
+use structopt::{clap::AppSettings,
+                StructOpt};
 
 arg_enum! {
     #[derive(Deserialize)]
@@ -13,6 +14,15 @@ arg_enum! {
     }
 }
 
+arg_enum! {
+    #[derive(Clone, Copy, Deserialize)]
+    pub enum CompletionSource {
+        Services,
+        Idents,
+        Remotes,
+    }
+}
+
 #[derive(ConfigOpt, StructOpt)]
 #[structopt(no_version)]
 /// Commands relating to Habitat runtime config
@@ -31,4 +41,17 @@ pub enum Cli {
                     case_insensitive = true)]
         shell: Shell,
     },
+    /// Lists candidates for the command-line completers to complete arguments with
+    #[structopt(no_version, settings = &[AppSettings::Hidden])]
+    Complete {
+        /// What to list: the services loaded in a Supervisor, the installed packages or the named
+        /// remote Supervisors
+        #[structopt(name = "SOURCE",
+                    possible_values = &CompletionSource::variants(),
+                    case_insensitive = true)]
+        source:     CompletionSource,
+        /// The Supervisor to list the services of instead of the local one, by address or by name
+        #[structopt(name = "REMOTE_SUP", long = "remote-sup", short = "r")]
+        remote_sup: Option<String>,
+    },
 }
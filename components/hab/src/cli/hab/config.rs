@@ -14,6 +14,19 @@ use structopt::StructOpt;
 /// Commands relating to a Service's runtime config
 pub enum ServiceConfig {
     Apply(ServiceConfigApply),
+    /// Displays who applied which configuration and files through a Supervisor
+    #[structopt(aliases = &["au", "aud", "audi"])]
+    Audit {
+        /// Only show changes to this service group service.group[@organization] (ex:
+        /// redis.default or foo.default@bazcorp)
+        #[structopt(name = "SERVICE_GROUP")]
+        service_group: Option<ServiceGroup>,
+        /// Only show this many of the most recent changes
+        #[structopt(name = "LIMIT", short = "l", long = "limit")]
+        limit:         Option<u64>,
+        #[structopt(flatten)]
+        remote_sup:    RemoteSup,
+    },
     /// Displays the default configuration options for a service
     Show {
         #[structopt(flatten)]
@@ -0,0 +1,20 @@
+use super::util::{BldrUrl,
+                  CacheKeyPath,
+                  ConfigOptBldrUrl,
+                  ConfigOptCacheKeyPath,
+                  ConfigOptRemoteSup,
+                  RemoteSup};
+use configopt::ConfigOpt;
+use structopt::StructOpt;
+
+/// Check the local Habitat installation for common problems
+#[derive(ConfigOpt, StructOpt)]
+#[structopt(no_version)]
+pub struct Doctor {
+    #[structopt(flatten)]
+    pub remote_sup:     RemoteSup,
+    #[structopt(flatten)]
+    pub bldr_url:       BldrUrl,
+    #[structopt(flatten)]
+    pub cache_key_path: CacheKeyPath,
+}
@@ -6,7 +6,8 @@ use super::util::{AuthToken,
                   ConfigOptBldrOrigin,
                   ConfigOptBldrUrl,
                   ConfigOptCacheKeyPath};
-use crate::cli::{valid_origin,
+use crate::cli::{file_exists,
+                 valid_origin,
                  KeyType};
 use configopt::ConfigOpt;
 use habitat_core::origin::OriginMemberRole;
@@ -39,6 +40,7 @@ pub enum Origin {
         auth_token: AuthToken,
     },
     /// Departs membership from selected origin
+    #[structopt(aliases = &["departure"])]
     Depart {
         #[structopt(flatten)]
         bldr_url:   BldrUrl,
@@ -181,6 +183,10 @@ pub enum Key {
         /// Authentication token for Builder (required for downloading origin private keys)
         #[structopt(name = "AUTH_TOKEN", short = "z", long = "auth")]
         auth_token:      Option<String>,
+        /// Trust a public origin key even if it does not match the key previously downloaded for
+        /// the same revision
+        #[structopt(name = "ACCEPT_NEW_KEY", long = "accept-new-key")]
+        accept_new_key:  bool,
     },
     /// Outputs the latest origin key contents to stdout
     Export {
@@ -207,6 +213,62 @@ pub enum Key {
         #[structopt(flatten)]
         cache_key_path: CacheKeyPath,
     },
+    /// Imports the revocation list of an origin's signing keys from Builder or from a file
+    Revocations {
+        /// The origin name
+        #[structopt(name = "ORIGIN", validator = valid_origin)]
+        origin:         String,
+        /// Import the revocation list from a file instead of Builder
+        #[structopt(name = "FILE", long = "file", validator = file_exists)]
+        file:           Option<PathBuf>,
+        #[structopt(flatten)]
+        bldr_url:       BldrUrl,
+        #[structopt(flatten)]
+        cache_key_path: CacheKeyPath,
+    },
+    /// Revokes an origin signing key revision, signing the revocation list of the origin with its
+    /// latest key that is not revoked
+    Revoke {
+        /// The origin name
+        #[structopt(name = "ORIGIN", validator = valid_origin)]
+        origin:         String,
+        /// The origin key revision to revoke
+        #[structopt(name = "REVISION")]
+        revision:       String,
+        /// Why the key revision is revoked
+        #[structopt(name = "REASON", long = "reason")]
+        reason:         Option<String>,
+        /// Write the signed revocation list to this file
+        #[structopt(name = "OUTPUT", short = "o", long = "output")]
+        output:         Option<PathBuf>,
+        #[structopt(flatten)]
+        bldr_url:       BldrUrl,
+        #[structopt(flatten)]
+        auth_token:     AuthToken,
+        /// Don't upload the revocation list to Builder
+        #[structopt(name = "NO_UPLOAD", long = "no-upload")]
+        no_upload:      bool,
+        #[structopt(flatten)]
+        cache_key_path: CacheKeyPath,
+    },
+    /// Downloads the public origin key revisions on Builder, uploads the ones only on disk, and
+    /// reports the origin private keys on disk
+    Sync {
+        /// The origin name
+        #[structopt(name = "ORIGIN", validator = valid_origin)]
+        origin:         String,
+        #[structopt(flatten)]
+        bldr_url:       BldrUrl,
+        /// Authentication token for Builder (required for uploading public origin keys)
+        #[structopt(name = "AUTH_TOKEN", short = "z", long = "auth")]
+        auth_token:     Option<String>,
+        /// Trust a public origin key even if it does not match the key previously downloaded for
+        /// the same revision
+        #[structopt(name = "ACCEPT_NEW_KEY", long = "accept-new-key")]
+        accept_new_key: bool,
+        #[structopt(flatten)]
+        cache_key_path: CacheKeyPath,
+    },
     /// Upload origin keys to Builder
     Upload {
         #[structopt(flatten)]
@@ -303,6 +365,23 @@ pub enum Secret {
         #[structopt(name = "ORIGIN", short = "o", long = "origin", validator = valid_origin)]
         origin:     Option<String>,
     },
+    /// Upload every secret in an env file to your origin, replacing existing secrets with the
+    /// same name
+    Import {
+        /// Path to a file of KEY=VALUE lines. Blank lines and lines starting with '#' are ignored
+        #[structopt(name = "ENV_FILE", validator = file_exists)]
+        env_file:       PathBuf,
+        #[structopt(flatten)]
+        bldr_url:       BldrUrl,
+        #[structopt(flatten)]
+        auth_token:     AuthToken,
+        /// The origin for which the secrets will be uploaded. Default is from 'HAB_ORIGIN' or
+        /// cli.toml
+        #[structopt(name = "ORIGIN", short = "o", long = "origin", validator = valid_origin)]
+        origin:         Option<String>,
+        #[structopt(flatten)]
+        cache_key_path: CacheKeyPath,
+    },
     /// List all secrets for your origin
     List {
         #[structopt(flatten)]
@@ -312,6 +391,31 @@ pub enum Secret {
         /// The origin for which secrets will be listed. Default is from 'HAB_ORIGIN' or cli.toml
         #[structopt(name = "ORIGIN", short = "o", long = "origin", validator = valid_origin)]
         origin:     Option<String>,
+        /// Display who created each secret and when
+        #[structopt(name = "METADATA", long = "metadata")]
+        metadata:   bool,
+    },
+    /// Replace the value of a secret for your origin, creating it if it does not exist
+    Update {
+        /// The name of the variable key to be injected into the studio
+        #[structopt(name = "KEY_NAME")]
+        key_name:       String,
+        /// The new contents of the variable to be injected into the studio
+        #[structopt(name = "SECRET", required_unless = "FROM_STDIN")]
+        secret:         Option<String>,
+        /// Read the contents of the secret from stdin
+        #[structopt(name = "FROM_STDIN", long = "from-stdin", conflicts_with = "SECRET")]
+        from_stdin:     bool,
+        #[structopt(flatten)]
+        bldr_url:       BldrUrl,
+        #[structopt(flatten)]
+        auth_token:     AuthToken,
+        /// The origin for which the secret will be updated. Default is from 'HAB_ORIGIN' or
+        /// cli.toml
+        #[structopt(name = "ORIGIN", short = "o", long = "origin", validator = valid_origin)]
+        origin:         Option<String>,
+        #[structopt(flatten)]
+        cache_key_path: CacheKeyPath,
     },
     /// Create and upload a secret for your origin
     Upload {
@@ -319,8 +423,11 @@ pub enum Secret {
         #[structopt(name = "KEY_NAME")]
         key_name:       String,
         /// The contents of the variable to be injected into the studio
-        #[structopt(name = "SECRET")]
-        secret:         String,
+        #[structopt(name = "SECRET", required_unless = "FROM_STDIN")]
+        secret:         Option<String>,
+        /// Read the contents of the secret from stdin
+        #[structopt(name = "FROM_STDIN", long = "from-stdin", conflicts_with = "SECRET")]
+        from_stdin:     bool,
         #[structopt(flatten)]
         bldr_url:       BldrUrl,
         #[structopt(flatten)]
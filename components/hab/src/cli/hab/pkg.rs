@@ -53,6 +53,27 @@ pub struct List {
 #[allow(clippy::large_enum_variant)]
 /// Commands relating to Habitat packages
 pub enum Pkg {
+    /// Attests to the provenance of a Habitat Artifact, writing an attestation signed with its
+    /// origin key next to it
+    Attest {
+        /// A path to a Habitat Artifact (ex:
+        /// /home/acme-redis-3.0.7-21120102031201-x86_64-linux.hart)
+        #[structopt(name = "SOURCE", validator = file_exists)]
+        source:          PathBuf,
+        /// The identity of the builder that built the artifact (ex:
+        /// https://ci.example.com/jobs/42)
+        #[structopt(name = "BUILDER_ID", long = "builder-id")]
+        builder_id:      String,
+        /// The URI of the source the artifact's plan was built from (ex:
+        /// git+https://github.com/acme/plans)
+        #[structopt(name = "SOURCE_URI", long = "source-uri")]
+        source_uri:      Option<String>,
+        /// The git commit of the source the artifact's plan was built from
+        #[structopt(name = "SOURCE_REVISION", long = "source-revision")]
+        source_revision: Option<String>,
+        #[structopt(flatten)]
+        cache_key_path:  CacheKeyPath,
+    },
     /// Displays the binds for a service
     Binds {
         #[structopt(flatten)]
@@ -60,21 +81,30 @@ pub enum Pkg {
     },
     /// Creates a binlink for a package binary in a common 'PATH' location
     Binlink {
-        #[structopt(flatten)]
-        pkg_ident: PkgIdent,
+        /// A package identifier (ex: core/redis, core/busybox-static/1.42.2)
+        #[structopt(name = "PKG_IDENT", required_unless_one = &["ALL_INSTALLED", "PRUNE"])]
+        pkg_ident:     Option<PackageIdent>,
         /// The command to binlink (ex: bash)
         #[structopt(name = "BINARY")]
-        binary:    Option<String>,
+        binary:        Option<String>,
         /// Sets the destination directory
         #[structopt(name = "DEST_DIR",
                     short = "d",
                     long = "dest",
                     env = BINLINK_DIR_ENVVAR,
                     default_value = DEFAULT_BINLINK_DIR)]
-        dest_dir:  PathBuf,
+        dest_dir:      PathBuf,
         /// Overwrite existing binlinks
         #[structopt(name = "FORCE", short = "f", long = "force")]
-        force:     bool,
+        force:         bool,
+        /// Binlink all binaries of the latest installed release of every installed package
+        #[structopt(name = "ALL_INSTALLED",
+                    long = "all-installed",
+                    conflicts_with_all = &["PKG_IDENT", "BINARY"])]
+        all_installed: bool,
+        /// Remove the binlinks pointing into package releases that are no longer installed
+        #[structopt(name = "PRUNE", long = "prune")]
+        prune:         bool,
     },
     /// Builds a Plan using a Studio
     Build {
@@ -93,6 +123,13 @@ pub enum Pkg {
         plan_context:    PathBuf,
         #[structopt(flatten)]
         cache_key_path:  CacheKeyPath,
+        /// Mounts a host path into the Studio, optionally read-only (ex:
+        /// /home/me/.m2:/root/.m2:ro)
+        #[structopt(name = "STUDIO_MOUNT", long = "mount", number_of_values = 1)]
+        mount:           Vec<String>,
+        /// Passes an environment variable into the Studio
+        #[structopt(name = "STUDIO_ENV_PASS", long = "env-pass", number_of_values = 1)]
+        env_pass:        Vec<String>,
         #[cfg(any(target_os = "linux", target_os = "windows"))]
         /// Reuses a previous Studio for the build (default: clean up before building)
         // Only a truly native/local Studio can be reused--the Docker implementation will always be
@@ -146,6 +183,9 @@ pub enum Pkg {
         #[structopt(flatten)]
         pkg_ident: PkgIdent,
     },
+    /// Stores the identical files of installed packages once, hard linking them from a store
+    /// keyed on their blake2b hash
+    Dedup,
     /// Removes a package from Builder
     Delete {
         #[structopt(flatten)]
@@ -224,9 +264,31 @@ pub enum Pkg {
     Env {
         #[structopt(flatten)]
         pkg_ident: PkgIdent,
+        /// The shell or format to print the environment for (default: sh, or powershell on
+        /// Windows)
+        #[structopt(name = "SHELL",
+                    long = "shell",
+                    possible_values = &["sh", "fish", "powershell", "json", "dotenv"])]
+        shell:     Option<String>,
     },
     Exec(PkgExec),
     Export(ExportCommand),
+    /// Uninstalls the package releases that are not loaded by the Supervisor, binlinked or
+    /// depended on by another package that is kept
+    Gc {
+        /// Just show what would be uninstalled, don't actually do it
+        #[structopt(name = "DRYRUN", short = "d", long = "dry-run")]
+        dryrun:      bool,
+        /// Keep this number of latest releases of each package, whether or not they are used
+        #[structopt(name = "KEEP_LATEST", long = "keep-latest", default_value = "1")]
+        keep_latest: usize,
+        /// The directory whose binlinks keep the packages they point to
+        #[structopt(name = "BINLINK_DIR",
+                    long = "binlink-dir",
+                    env = BINLINK_DIR_ENVVAR,
+                    default_value = DEFAULT_BINLINK_DIR)]
+        binlink_dir: PathBuf,
+    },
     /// Generates a blake2b hashsum from a target at any given filepath
     Hash {
         /// A filepath of the target
@@ -254,6 +316,30 @@ pub enum Pkg {
     Install(PkgInstall),
     /// List all versions of installed packages
     List(List),
+    /// Writes a lockfile pinning the releases at the head of a channel of packages and their
+    /// runtime dependencies
+    Lock {
+        /// One or more Habitat package identifiers (ex: acme/redis)
+        #[structopt(name = "PKG_IDENT", required = true)]
+        pkg_ident:  Vec<PackageIdent>,
+        #[structopt(flatten)]
+        bldr_url:   BldrUrl,
+        /// Pin the releases in the specified release channel
+        #[structopt(name = "CHANNEL",
+                    short = "c",
+                    long = "channel",
+                    default_value = "stable",
+                    env = ChannelIdent::ENVVAR)]
+        channel:    String,
+        /// A package target (ex: x86_64-windows) (default: system appropriate target)
+        #[structopt(name = "PKG_TARGET", short = "t", long = "target")]
+        pkg_target: Option<PackageTarget>,
+        /// The path to write the lockfile to (default: stdout)
+        #[structopt(name = "OUTPUT", short = "o", long = "output")]
+        output:     Option<PathBuf>,
+        #[structopt(flatten)]
+        auth_token: AuthToken,
+    },
     /// Prints the path to a specific installed release of a package
     Path {
         #[structopt(flatten)]
@@ -368,6 +454,10 @@ pub enum Pkg {
         /// /home/acme-redis-3.0.7-21120102031201-x86_64-linux.hart)
         #[structopt(name = "SOURCE", validator = file_exists)]
         source:         PathBuf,
+        /// Also verify the provenance attestation next to the artifact, which must be signed with
+        /// a key of the artifact's origin
+        #[structopt(name = "PROVENANCE", long = "provenance")]
+        provenance:     bool,
         #[structopt(flatten)]
         cache_key_path: CacheKeyPath,
     },
@@ -382,6 +472,13 @@ pub struct PkgExec {
     /// The command to execute (ex: ls)
     #[structopt()]
     pub cmd:       PathBuf,
+    /// Run the command with only the runtime environment of the package, and the variables
+    /// describing the user and terminal, rather than on top of the current environment
+    #[structopt(long = "clean-env")]
+    pub clean_env: bool,
+    /// Keep a variable of the current environment in the clean environment (ex: SSL_CERT_FILE)
+    #[structopt(long = "keep-env", number_of_values = 1, requires = "CLEAN_ENV")]
+    pub keep_env:  Vec<String>,
     #[structopt(flatten)]
     pub args:      ExternalCommandArgsWithHelpAndVersion,
 }
@@ -418,6 +515,10 @@ pub struct PkgInstall {
     /// Do not run any install hooks
     #[structopt(long = "ignore-install-hook")]
     ignore_install_hook:   bool,
+    /// Rewrite the interpreter paths and RUNPATHs of installed packages to run them from beneath
+    /// FS_ROOT. Binaries are rewritten with patchelf, which must be on the PATH
+    #[structopt(long = "relocate")]
+    relocate:              bool,
     /// Install packages in offline mode
     #[structopt(long = "offline",
                 hidden = !FEATURE_FLAGS.contains(FeatureFlag::OFFLINE_INSTALL))]
@@ -436,12 +537,18 @@ pub enum ExportCommand {
     #[cfg(target_os = "linux")]
     /// Cloud Foundry exporter
     Cf(ExternalCommandArgs),
+    /// Docker Compose exporter
+    #[cfg(target_os = "linux")]
+    Compose(ExternalCommandArgs),
     #[cfg(any(target_os = "linux", target_os = "windows"))]
     /// Container exporter
     Container(ExternalCommandArgs),
     #[cfg(any(target_os = "linux", target_os = "windows"))]
     #[structopt(settings = &[AppSettings::Hidden])]
     Docker(ExternalCommandArgs),
+    /// Amazon ECS task definition exporter
+    #[cfg(target_os = "linux")]
+    Ecs(ExternalCommandArgs),
     /// Mesos exporter
     #[cfg(target_os = "linux")]
     Mesos(ExternalCommandArgs),
@@ -28,6 +28,14 @@ pub enum Plan {
         /// Specify explicit Scaffolding for your app (ex: node, ruby)
         #[structopt(name = "SCAFFOLDING", short = "s", long = "scaffolding")]
         scaffolding: Option<String>,
+        /// Generate a working plan, default hooks and config templates for an app written in
+        /// the given language
+        #[structopt(name = "TEMPLATE",
+                    short = "t",
+                    long = "template",
+                    conflicts_with_all = &["SCAFFOLDING", "MIN"],
+                    possible_values = &["rust", "node", "python", "go", "java"])]
+        template:    Option<String>,
     },
     /// Renders plan config files      
     Render {
@@ -46,6 +54,13 @@ pub enum Plan {
         /// Path to json file with mock data for template, defaults to none        
         #[structopt(name = "MOCK_DATA", short = "m", long = "mock-data")]
         mock_data:     Option<PathBuf>,
+        /// Path to a TOML or JSON file describing the service group members, binds and system
+        /// info to render the template with, defaults to none
+        #[structopt(name = "MOCK_CENSUS",
+                    short = "c",
+                    long = "mock-census",
+                    validator = file_exists)]
+        mock_census:   Option<PathBuf>,
         /// Prints config to STDOUT
         #[structopt(name = "PRINT", short = "p", long = "print")]
         print:         bool,
@@ -62,4 +77,17 @@ pub enum Plan {
         #[structopt(name = "QUIET", short = "q", long = "no-verbose", long = "quiet")]
         quiet:         bool,
     },
+    /// Runs the hooks of a plan against the test cases of a test manifest, with the config and
+    /// mock census of each test case, and checks their exit codes and output
+    Test {
+        /// Path to the plan directory, with its default.toml, config and hooks
+        #[structopt(name = "PLAN_DIR", default_value = ".")]
+        plan_dir: PathBuf,
+        /// Path to the test manifest, defaults to tests/hooks.toml in the plan directory
+        #[structopt(name = "MANIFEST", short = "m", long = "manifest", validator = file_exists)]
+        manifest: Option<PathBuf>,
+        /// Only run the test cases whose name contains this string
+        #[structopt(name = "FILTER", short = "f", long = "filter")]
+        filter:   Option<String>,
+    },
 }
@@ -0,0 +1,32 @@
+use crate::cli::file_exists;
+use configopt::ConfigOpt;
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+#[derive(ConfigOpt, StructOpt)]
+#[structopt(no_version)]
+/// Commands relating to named remote Supervisors
+pub enum Remote {
+    /// Names a remote Supervisor, so that `--remote-sup NAME` connects to it
+    Add {
+        /// The name to give the remote Supervisor
+        #[structopt(name = "NAME")]
+        name:        String,
+        /// Address to the remote Supervisor's Control Gateway (ex: tcp://10.0.0.5:9632)
+        #[structopt(name = "ADDRESS")]
+        address:     String,
+        /// Path to a file with the secret of the remote Supervisor's Control Gateway
+        #[structopt(name = "SECRET_FILE",
+                    long = "secret-file",
+                    validator = file_exists)]
+        secret_file: Option<PathBuf>,
+    },
+    /// Lists the named remote Supervisors
+    List,
+    /// Removes the name of a remote Supervisor
+    Remove {
+        /// The name of the remote Supervisor
+        #[structopt(name = "NAME")]
+        name: String,
+    },
+}
@@ -1,6 +1,8 @@
 use super::util::{CacheKeyPath,
                   ConfigOptCacheKeyPath};
+use crate::cli::file_exists;
 use configopt::ConfigOpt;
+use std::path::PathBuf;
 use structopt::StructOpt;
 
 #[derive(ConfigOpt, StructOpt)]
@@ -14,6 +16,26 @@ pub enum Ring {
 #[structopt(no_version)]
 /// Commands relating to Habitat ring keys
 pub enum Key {
+    /// Copies the latest ring key to the key caches of the peers of a ring over SSH
+    Distribute {
+        /// Ring key name
+        #[structopt(name = "RING")]
+        ring:                  String,
+        /// A file listing the peers, one SSH destination ([user@]host) per line
+        #[structopt(name = "PEERS", long = "peers", validator = file_exists)]
+        peers:                 PathBuf,
+        /// The SSH client to connect to the peers with
+        #[structopt(name = "SSH", long = "ssh", default_value = "ssh")]
+        ssh:                   String,
+        /// Import the key on the peers with sudo
+        #[structopt(name = "SUDO", long = "sudo")]
+        sudo:                  bool,
+        /// The key cache of the peers (default: their default key cache)
+        #[structopt(name = "REMOTE_CACHE_KEY_PATH", long = "remote-cache-key-path")]
+        remote_cache_key_path: Option<PathBuf>,
+        #[structopt(flatten)]
+        cache_key_path:        CacheKeyPath,
+    },
     /// Outputs the latest ring key contents to stdout
     Export {
         /// Ring key name
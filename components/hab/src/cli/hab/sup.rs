@@ -14,18 +14,26 @@ use configopt::{self,
 use habitat_common::{cli::{RING_ENVVAR,
                            RING_KEY_ENVVAR},
                      command::package::install::InstallSource,
-                     types::{EventStreamConnectMethod,
+                     output::{OutputTargets,
+                              ServiceOutputFormat},
+                     types::{CloudProvider,
+                             CtlListener,
+                             EventStreamConnectMethod,
                              EventStreamMetaPair,
                              EventStreamServerCertificate,
                              EventStreamToken,
                              GossipListenAddr,
                              HttpListenAddr,
-                             ListenCtlAddr},
+                             ListenCtlAddr,
+                             UpdateWindow},
+                     util::log_forward::LogForward,
                      FeatureFlag,
                      FEATURE_FLAGS};
 use habitat_core::{env::Config,
+                   fs::FS_ROOT_ENVVAR,
                    package::PackageIdent,
-                   util as core_util};
+                   util as core_util,
+                   ChannelIdent};
 use rants::{error::Error as RantsError,
             Address as NatsAddress};
 use std::{fmt,
@@ -36,6 +44,10 @@ use std::{fmt,
           str::FromStr};
 use structopt::{clap::AppSettings,
                 StructOpt};
+use url::Url;
+
+pub const DEFAULT_ACME_DIRECTORY: &str = "https://acme-v02.api.letsencrypt.org/directory";
+pub const DEFAULT_ACME_CHALLENGE_LISTEN: &str = "0.0.0.0:80";
 
 // All commands relating to the Supervisor (ie commands handled by both the `hab` and `hab-sup`
 // binary)
@@ -53,12 +65,22 @@ pub enum HabSup {
         #[structopt(flatten)]
         remote_sup: RemoteSup,
     },
+    /// Purge the members that departed the gossip ring from the census and the persisted rumors
+    #[structopt(no_version)]
+    PurgeDeparted {
+        /// Only purge members that have been departed for at least this many seconds
+        #[structopt(long = "older-than")]
+        older_than: Option<u64>,
+        #[structopt(flatten)]
+        remote_sup: RemoteSup,
+    },
     #[structopt(no_version, aliases = &["sec", "secr"])]
     Secret(Secret),
-    /// Query the status of Habitat services
+    /// Query the status of the Supervisor itself
     #[structopt(no_version, aliases = &["stat", "statu"])]
     Status {
-        /// A package identifier (ex: core/redis, core/busybox-static/1.42.2)
+        /// Query the status of this service instead. Deprecated, use `hab svc status` (ex:
+        /// core/redis)
         #[structopt(name = "PKG_IDENT")]
         pkg_ident:  Option<PackageIdent>,
         #[structopt(flatten)]
@@ -69,6 +91,10 @@ pub enum HabSup {
     Restart {
         #[structopt(flatten)]
         remote_sup: RemoteSup,
+        /// Arguments to `hab sup run` for the restarted Supervisor, replacing the current ones
+        /// (ex: `hab sup restart -- --peer 10.0.0.1 --listen-gossip 0.0.0.0:9638`)
+        #[structopt(name = "SUP_RUN_ARGS", last = true)]
+        args:       Vec<String>,
     },
     #[cfg(not(target_os = "macos"))]
     #[structopt(flatten)]
@@ -159,10 +185,18 @@ pub struct SupRun {
                 env = ListenCtlAddr::ENVVAR,
                 default_value = ListenCtlAddr::default_as_str())]
     pub listen_ctl: ListenCtlAddr,
+    /// An additional listen address for the Control Gateway (IP[:PORT][,tls][,secret-file=PATH])
+    ///
+    /// Give `tls` to encrypt the listener with the certificate of the HTTP Gateway and
+    /// `secret-file` to have clients authenticate with the secret in that file instead of the
+    /// Supervisor's CTL_SECRET. Can be given several times.
+    #[structopt(long = "ctl-listener", number_of_values = 1)]
+    pub ctl_listener: Vec<CtlListener>,
     /// The organization the Supervisor and its services are part of
     #[structopt(long = "org")]
     pub organization: Option<String>,
-    /// The listen address of one or more initial peers (IP[:PORT])
+    /// The listen address of one or more initial peers (IP[:PORT]). IPv6 addresses may be given
+    /// in brackets (ex: [fe80::1]:9638)
     // TODO (DM): Currently there is not a good way to use `parse_peer` when deserializing. Due to
     // https://github.com/serde-rs/serde/issues/723. There are workarounds but they are all ugly.
     // This means that you have to specify the port when setting this with a config file.
@@ -172,8 +206,31 @@ pub struct SupRun {
     #[structopt(long = "permanent-peer", short = "I")]
     pub permanent_peer: bool,
     /// Watch this file for connecting to the ring
+    ///
+    /// Peers added to the file are joined as it changes, and members at the addresses of peers
+    /// removed from it are checked for departure.
     #[structopt(long = "peer-watch-file", conflicts_with = "PEER")]
     pub peer_watch_file: Option<PathBuf>,
+    /// The period of time in seconds a member confirmed dead lingers before it is considered
+    /// departed and no longer counts towards quorum
+    #[structopt(long = "departure-timeout", default_value = "259200")]
+    pub departure_timeout: DurationProxy,
+    /// The period of time in seconds departed members are kept before they are purged from the
+    /// census and the persisted rumors
+    ///
+    /// If this argument is not specified, departed members are kept indefinitely. Every Supervisor
+    /// in the ring should use the same value, as a member that a peer still knows about is
+    /// gossiped again.
+    #[structopt(long = "departed-retention")]
+    pub departed_retention: Option<DurationProxy>,
+    /// The filesystem root under which the Supervisor keeps its state, packages, caches and
+    /// service directories
+    ///
+    /// Give each Supervisor on a host its own root, along with its own listen addresses, to run
+    /// several independent Supervisors side by side. Packages installed beneath the root are
+    /// relocated to run from it, which needs `patchelf` on the PATH for their binaries.
+    #[structopt(long = "fs-root", env = FS_ROOT_ENVVAR)]
+    pub fs_root: Option<PathBuf>,
     #[structopt(flatten)]
     #[serde(flatten)]
     pub cache_key_path: CacheKeyPath,
@@ -192,12 +249,50 @@ pub struct SupRun {
                 env = RING_KEY_ENVVAR,
                 hidden = true)]
     pub ring_key: Option<String>,
+    /// The private key for mutual TLS between Supervisors on the Gossip Gateway
+    ///
+    /// Read the private key from GOSSIP_KEY_FILE. This should be an RSA private key or
+    /// PKCS8-encoded private key in PEM format. Gossip is sent over TLS instead of ZeroMQ, so
+    /// every Supervisor in the ring must be configured for it.
+    #[structopt(long = "gossip-key",
+                requires_all = &["GOSSIP_CERT_FILE", "GOSSIP_CA_CERT_FILE"])]
+    pub gossip_key_file: Option<PathBuf>,
+    /// The certificates for mutual TLS between Supervisors on the Gossip Gateway
+    ///
+    /// Read certificates from GOSSIP_CERT_FILE. This should contain PEM-format certificates in the
+    /// right order. The first certificate should certify GOSSIP_KEY_FILE and be issued for this
+    /// Supervisor alone.
+    #[structopt(long = "gossip-certs", requires = "GOSSIP_KEY_FILE")]
+    pub gossip_cert_file: Option<PathBuf>,
+    /// The CA certificate for mutual TLS between Supervisors on the Gossip Gateway
+    ///
+    /// Read the CA certificate from GOSSIP_CA_CERT_FILE. Peers are only trusted if their
+    /// certificate was issued by this CA.
+    #[structopt(long = "gossip-ca-certs", requires = "GOSSIP_KEY_FILE")]
+    pub gossip_ca_cert_file: Option<PathBuf>,
+    /// The revoked certificates for mutual TLS between Supervisors on the Gossip Gateway
+    ///
+    /// Read PEM-format certificates of Supervisors that are no longer trusted from
+    /// GOSSIP_REVOKED_CERTS_FILE. The file is read again whenever it changes.
+    #[structopt(long = "gossip-revoked-certs", requires = "GOSSIP_KEY_FILE")]
+    pub gossip_revoked_certs_file: Option<PathBuf>,
     /// Enable automatic updates for the Supervisor itself
     #[structopt(long = "auto-update", short = "A")]
     pub auto_update: bool,
     /// The period of time in seconds between Supervisor update checks
     #[structopt(long = "auto-update-period", default_value = "60")]
     pub auto_update_period: DurationProxy,
+    /// Enable automatic updates for the Supervisor itself, following this channel rather than
+    /// the `--channel` services are loaded from
+    #[structopt(long = "auto-update-sup-channel")]
+    pub auto_update_sup_channel: Option<ChannelIdent>,
+    /// Only restart into an updated Supervisor during this daily window, given in UTC as
+    /// HH:MM-HH:MM
+    ///
+    /// Updates are still downloaded as soon as they are found. A window whose end is earlier than
+    /// its start spans midnight.
+    #[structopt(long = "auto-update-window")]
+    pub auto_update_window: Option<UpdateWindow>,
     /// The period of time in seconds between service update checks
     #[structopt(long = "service-update-period", default_value = "60")]
     pub service_update_period: DurationProxy,
@@ -221,6 +316,45 @@ pub struct SupRun {
     #[structopt(long = "ca-certs",
                 requires_all = &["CERT_FILE", "KEY_FILE"])]
     pub ca_cert_file: Option<PathBuf>,
+    /// Use a self-signed certificate for HTTP Gateway TLS encryption
+    ///
+    /// The certificate is generated the first time the Supervisor needs it and kept in its data
+    /// directory. Its SHA-256 fingerprint is printed at startup so that clients can trust it.
+    #[structopt(long = "self-signed-certs",
+                conflicts_with_all = &["KEY_FILE", "ACME_DOMAIN"])]
+    pub self_signed_certs: bool,
+    /// A domain to obtain the certificate for HTTP Gateway TLS encryption for from an ACME CA
+    ///
+    /// The first domain is the subject of the certificate. The Supervisor answers the CA's HTTP-01
+    /// challenges itself and renews the certificate when it has fewer than 30 days left.
+    #[structopt(long = "acme-domain",
+                number_of_values = 1,
+                requires = "ACME_EMAIL",
+                conflicts_with = "KEY_FILE")]
+    pub acme_domain: Vec<String>,
+    /// The contact email address of the Supervisor's account with the ACME CA
+    #[structopt(long = "acme-email", requires = "ACME_DOMAIN")]
+    pub acme_email: Option<String>,
+    /// The URL of the directory of the ACME CA
+    #[structopt(long = "acme-directory", default_value = DEFAULT_ACME_DIRECTORY)]
+    pub acme_directory: String,
+    /// The listen address for answering the HTTP-01 challenges of the ACME CA
+    ///
+    /// The CA connects to port 80 of each domain, so this should be reachable there.
+    #[structopt(long = "acme-challenge-listen",
+                default_value = DEFAULT_ACME_CHALLENGE_LISTEN)]
+    pub acme_challenge_listen: SocketAddr,
+    /// Encrypt the ctl gateway with the certificate of the HTTP Gateway
+    ///
+    /// Clients then need HAB_CTL_TLS_FINGERPRINT or HAB_CTL_TLS_SERVER_NAME to connect.
+    #[structopt(long = "ctl-tls")]
+    pub ctl_tls: bool,
+    /// The period of time in seconds a ctl gateway request may take before it is cancelled
+    ///
+    /// If this argument is not specified, requests may take as long as they need. Requests are
+    /// also cancelled when the client disconnects.
+    #[structopt(long = "ctl-request-timeout")]
+    pub ctl_request_timeout: Option<DurationProxy>,
     /// Load a Habitat package as part of the Supervisor startup
     ///
     /// The package can be specified by a package identifier (ex: core/redis) or filepath to a
@@ -238,12 +372,24 @@ pub struct SupRun {
     /// This option also sets NO_COLOR.
     #[structopt(long = "json-logging")]
     pub json_logging: bool,
-    /// The IPv4 address to use as the `sys.ip` template variable
+    /// A tag describing this Supervisor, matched against the `--prefer-leader` and
+    /// `--exclude-leader` options of services (ex: backup-dc)
+    #[structopt(long = "tag")]
+    pub tag: Vec<String>,
+    /// The IP address to use as the `sys.ip` template variable
     ///
     /// If this argument is not set, the supervisor tries to dynamically determine an IP address.
     /// If that fails, the supervisor defaults to using `127.0.0.1`.
     #[structopt(long = "sys-ip-address")]
     pub sys_ip_address: Option<IpAddr>,
+    /// The cloud provider whose instance metadata to query for the `sys.cloud` template variable
+    ///
+    /// The instance's ID, type, region and availability zone are read from the provider's
+    /// instance metadata service when the Supervisor starts. Without this option, `sys.cloud` is
+    /// not set.
+    #[structopt(long = "sys-cloud-metadata",
+                possible_values = CloudProvider::VARIANTS)]
+    pub sys_cloud_metadata: Option<CloudProvider>,
     /// The name of the application for event stream purposes
     ///
     /// This will be attached to all events generated by this Supervisor.
@@ -286,6 +432,38 @@ pub struct SupRun {
     /// The certificate should be in PEM format.
     #[structopt(long = "event-stream-server-certificate")]
     pub event_stream_server_certificate: Option<EventStreamServerCertificate>,
+    /// The host of a StatsD server to push Supervisor and service metrics to
+    ///
+    /// Metrics such as service restarts, health check transitions and update durations are sent
+    /// over UDP as they happen, with DogStatsD tags.
+    #[structopt(long = "statsd-host", empty_values = false)]
+    pub statsd_host: Option<String>,
+    /// The port of the StatsD server
+    #[structopt(long = "statsd-port", default_value = "8125")]
+    pub statsd_port: u16,
+    /// The prefix of the name of every metric sent to the StatsD server
+    #[structopt(long = "statsd-prefix", default_value = "habitat")]
+    pub statsd_prefix: String,
+    /// A tag to send with every metric sent to the StatsD server (ex: env:prod)
+    #[structopt(long = "statsd-tag")]
+    pub statsd_tag: Vec<String>,
+    /// The url of a Vault server to read the secrets referenced in service configuration from
+    ///
+    /// Any string value of the form `vault:<path>#<field>` in a service's configuration is
+    /// replaced by that field of the secret at that path when the service's templates are
+    /// rendered. Secrets are never gossiped. This requires VAULT_TOKEN also be set.
+    #[structopt(long = "vault-url", requires = "VAULT_TOKEN")]
+    pub vault_url: Option<Url>,
+    /// The token used to authenticate to the Vault server
+    #[structopt(long = "vault-token", env = "HAB_VAULT_TOKEN", hide_env_values = true)]
+    pub vault_token: Option<String>,
+    /// Encrypt service spec files at rest with the Supervisor's host key
+    ///
+    /// The host key is generated in the key cache if it does not exist yet. Spec files that were
+    /// written before are encrypted when the Supervisor starts. Configuration and files applied
+    /// to service groups are also encrypted in the rumors the Supervisor persists.
+    #[structopt(long = "encrypt-specs")]
+    pub encrypt_specs: bool,
     /// Automatically cleanup old packages
     ///
     /// The Supervisor will automatically cleanup old packages only keeping the
@@ -293,6 +471,50 @@ pub struct SupRun {
     /// automatic package cleanup is performed.
     #[structopt(long = "keep-latest-packages", env = "HAB_KEEP_LATEST_PACKAGES")]
     pub keep_latest_packages: Option<usize>,
+    /// The size in bytes at which a service's log file in `/hab/svc/<name>/logs` is rotated
+    #[structopt(long = "service-log-max-bytes", default_value = "10485760")]
+    pub service_log_max_bytes: u64,
+    /// The age in seconds at which a service's log file is rotated
+    ///
+    /// If this argument is not specified, log files are only rotated by size.
+    #[structopt(long = "service-log-max-age")]
+    pub service_log_max_age: Option<DurationProxy>,
+    /// The number of rotated log files to keep for each service
+    ///
+    /// Set to 0 to not write service log files at all. Individual services can override any of
+    /// the `--service-log-*` settings when they are loaded.
+    #[structopt(long = "service-log-max-files", default_value = "5")]
+    pub service_log_max_files: u32,
+    /// How the output of services is rendered
+    ///
+    /// With `json`, each line a service writes to stdout or stderr is wrapped in a JSON object
+    /// carrying the service's package identifier, service group, stream, PID and a timestamp.
+    #[structopt(long = "service-log-format",
+                default_value = "text",
+                possible_values = ServiceOutputFormat::VARIANTS)]
+    pub service_log_format: ServiceOutputFormat,
+    /// Also forward the output of services to `journald` or to a syslog receiver given as
+    /// `syslog+<udp|tcp|tls>://<host>[:<port>]`
+    ///
+    /// Syslog messages follow RFC 5424 and identify the service by its service group and PID.
+    /// Individual services can override the destination with `hab svc load --log-forward`.
+    #[structopt(long = "service-log-forward")]
+    pub service_log_forward: Option<LogForward>,
+    /// Where to send the stdout of services: `none` or a comma-separated list of `console`,
+    /// `file`, `forward` and `events`
+    ///
+    /// `file` writes to the service's `logs/service.log` and `forward` uses the
+    /// `--service-log-forward` destination. With `events`, each line is published to the event
+    /// stream. Individual services can override this with `hab svc load --stdout-to`.
+    #[structopt(long = "service-stdout-to", default_value = "console,file,forward")]
+    pub service_stdout_to: OutputTargets,
+    /// Where to send the stderr of services, which is written to the service's
+    /// `logs/service.err.log`
+    ///
+    /// Accepts the same targets as `--service-stdout-to`. Individual services can override this
+    /// with `hab svc load --stderr-to`.
+    #[structopt(long = "service-stderr-to", default_value = "console,file,forward")]
+    pub service_stderr_to: OutputTargets,
     /// Paths to files or directories of service config files to load on startup
     ///
     /// See `hab svc bulkload --help` for details
@@ -300,6 +522,27 @@ pub struct SupRun {
                 default_value = DEFAULT_SVC_CONFIG_DIR,
                 hidden = !FEATURE_FLAGS.contains(FeatureFlag::SERVICE_CONFIG_FILES))]
     pub svc_config_paths: Vec<PathBuf>,
+    /// Glob patterns of service config files or directories not to load on startup
+    ///
+    /// See `hab svc bulkload --help` for details
+    #[structopt(long = "svc-config-exclude",
+                hidden = !FEATURE_FLAGS.contains(FeatureFlag::SERVICE_CONFIG_FILES))]
+    pub svc_config_exclude: Vec<String>,
+    /// A file listing glob patterns of service config files in the order to load them on startup
+    ///
+    /// See `hab svc bulkload --help` for details
+    #[structopt(long = "svc-config-order-file",
+                hidden = !FEATURE_FLAGS.contains(FeatureFlag::SERVICE_CONFIG_FILES))]
+    pub svc_config_order_file: Option<PathBuf>,
+    /// Print what the Supervisor would do on startup, and exit without starting any service or
+    /// joining the ring
+    ///
+    /// The services to load are resolved against the installed packages, and their config
+    /// templates and hooks are rendered with a census of this Supervisor's services alone. Binds
+    /// that no local service satisfies, and leader topologies that need other Supervisors, are
+    /// reported. The exit code is non-zero if any service would fail to load or render.
+    #[structopt(long = "dry-run")]
+    pub dry_run: bool,
     #[structopt(flatten)]
     #[serde(flatten)]
     pub shared_load: SharedLoad,
@@ -1,18 +1,37 @@
-use super::util::{CacheKeyPath,
+use super::util::{resolve_remote_sup,
+                  CacheKeyPath,
                   ConfigOptCacheKeyPath,
                   ConfigOptPkgIdent,
                   ConfigOptRemoteSup,
+                  DurationProxy,
                   PkgIdent,
                   RemoteSup};
-use crate::error::{Error,
-                   Result};
+use crate::{cli::{valid_allowed_path,
+                  valid_cpu_max,
+                  valid_env_file,
+                  valid_io_priority,
+                  valid_io_weight,
+                  valid_log_forward,
+                  valid_memory_max,
+                  valid_nice,
+                  valid_output_targets,
+                  valid_port_mapping,
+                  valid_seccomp_profile,
+                  valid_ulimit,
+                  valid_umask,
+                  valid_working_dir},
+            command::pkg::lock,
+            error::{Error,
+                    Result}};
 use clap::AppSettings;
 use configopt::{configopt_fields,
                 ConfigOpt};
-use habitat_common::{FeatureFlag,
+use habitat_common::{types::ListenCtlAddr,
+                     FeatureFlag,
                      FEATURE_FLAGS};
 use habitat_core::{os::process::ShutdownTimeout,
-                   package::PackageIdent,
+                   package::{PackageIdent,
+                             PackageTarget},
                    service::{BindingMode,
                              HealthCheckInterval,
                              ServiceBind,
@@ -20,6 +39,7 @@ use habitat_core::{os::process::ShutdownTimeout,
                    ChannelIdent};
 use habitat_sup_protocol::{ctl,
                            types::UpdateCondition};
+use serde::de::DeserializeOwned;
 use std::{convert::TryFrom,
           iter::FromIterator,
           path::{Path,
@@ -47,11 +67,24 @@ pub enum Svc {
     /// Query the status of Habitat services
     #[structopt(aliases = &["stat", "statu"])]
     Status {
-        /// A package identifier (ex: core/redis, core/busybox-static/1.42.2)
+        /// A package identifier (ex: core/redis, core/busybox-static/1.42.2). The resources used
+        /// by the service's processes and the output of its last failed lifecycle hook, if
+        /// any, are shown along with its status.
         #[structopt(name = "PKG_IDENT")]
-        pkg_ident:  Option<PackageIdent>,
+        pkg_ident:   Option<PackageIdent>,
+        /// Show the most recent health check results instead of the service status
+        #[structopt(long = "history")]
+        history:     bool,
         #[structopt(flatten)]
-        remote_sup: RemoteSup,
+        remote_sup:  RemoteSup,
+        /// Address to the Control Gateway of a member of a gossip ring. The status of the
+        /// services of every Supervisor in the ring it knows of is shown, instead of the
+        /// services of the remote Supervisor.
+        #[structopt(name = "REMOTE_RING",
+                    long = "remote-ring",
+                    conflicts_with = "history",
+                    parse(try_from_str = resolve_remote_sup))]
+        remote_ring: Option<ListenCtlAddr>,
     },
     Stop(SvcStop),
     /// Unload a service loaded by the Habitat Supervisor. If the service is running it will
@@ -82,10 +115,23 @@ pub enum Svc {
 /// Service config files will be patched with the default values from `/hab/sup/default/
 /// config/svc.toml`.
 pub struct BulkLoad {
-    /// Paths to files or directories of service config files
+    /// Paths to files or directories of service config files, or glob patterns matching them
+    /// (ex: '/hab/sup/default/config/svc/*-prod')
     #[structopt(long = "svc-config-paths",
                 default_value = "/hab/sup/default/config/svc")]
     pub svc_config_paths: Vec<PathBuf>,
+    /// Glob patterns of service config files or directories not to load, matched against their
+    /// name or their whole path (ex: '*-staging.toml')
+    #[structopt(long = "exclude")]
+    pub exclude:          Vec<String>,
+    /// A file listing glob patterns of service config files, one per line, in the order to load
+    /// them
+    ///
+    /// Files are loaded in the order of the first line matching them. Files no line matches are
+    /// loaded last, in the order they are found. Blank lines and lines starting with `#` are
+    /// ignored.
+    #[structopt(long = "order-file")]
+    pub order_file:       Option<PathBuf>,
 }
 
 /// Start a loaded, but stopped, Habitat service.
@@ -149,31 +195,37 @@ fn health_check_interval_default() -> u64 { 30 }
 #[structopt(no_version, rename_all = "screamingsnake")]
 pub struct SharedLoad {
     /// Receive updates from the specified release channel
-    #[structopt(long = "channel", default_value = &*CHANNEL_IDENT_DEFAULT)]
+    #[structopt(long = "channel",
+                default_value = &*CHANNEL_IDENT_DEFAULT,
+                env = "HAB_SVC_LOAD_CHANNEL")]
     #[serde(default)]
-    pub channel:               ChannelIdent,
+    pub channel:                  ChannelIdent,
     /// Specify an alternate Builder endpoint. If not specified, the value will be taken from
     /// the HAB_BLDR_URL environment variable if defined. (default: https://bldr.habitat.sh)
     // TODO (DM): This should probably use `env` and `default_value`
     // TODO (DM): serde nested flattens do no work https://github.com/serde-rs/serde/issues/1547
-    #[structopt(short = "u", long = "url")]
-    pub bldr_url:              Option<Url>,
+    #[structopt(short = "u", long = "url", env = "HAB_SVC_LOAD_BLDR_URL")]
+    pub bldr_url:                 Option<Url>,
     /// The service group with shared config and topology
-    #[structopt(long = "group", default_value = &*GROUP_DEFAULT)]
+    #[structopt(long = "group",
+                default_value = &*GROUP_DEFAULT,
+                env = "HAB_SVC_LOAD_GROUP")]
     #[serde(default = "GROUP_DEFAULT::get")]
-    pub group:                 String,
+    pub group:                    String,
     /// Service topology
     #[structopt(long = "topology",
             short = "t",
-            possible_values = &["standalone", "leader"])]
-    pub topology:              Option<habitat_sup_protocol::types::Topology>,
+            possible_values = &["standalone", "leader"],
+            env = "HAB_SVC_LOAD_TOPOLOGY")]
+    pub topology:                 Option<habitat_sup_protocol::types::Topology>,
     /// The update strategy
     #[structopt(long = "strategy",
                 short = "s",
                 default_value = "none",
-                possible_values = &["none", "at-once", "rolling"])]
+                possible_values = &["none", "at-once", "rolling"],
+                env = "HAB_SVC_LOAD_STRATEGY")]
     #[serde(default)]
-    pub strategy:              habitat_sup_protocol::types::UpdateStrategy,
+    pub strategy:                 habitat_sup_protocol::types::UpdateStrategy,
     /// The condition dictating when this service should update
     ///
     /// latest: Runs the latest package that can be found in the configured channel and local
@@ -186,54 +238,248 @@ pub struct SharedLoad {
     /// during a service rollback.
     #[structopt(long = "update-condition",
                 default_value = UpdateCondition::Latest.as_str(),
-                possible_values = UpdateCondition::VARIANTS)]
+                possible_values = UpdateCondition::VARIANTS,
+                env = "HAB_SVC_LOAD_UPDATE_CONDITION")]
     #[serde(default)]
-    pub update_condition:      UpdateCondition,
+    pub update_condition:         UpdateCondition,
     /// One or more service groups to bind to a configuration
-    #[structopt(long = "bind")]
+    ///
+    /// Binds may also be given in the environment, separated by commas (ex:
+    /// HAB_BIND=database:mongodb.default,cache:redis.default).
+    #[structopt(long = "bind", env = "HAB_BIND", use_delimiter = true)]
     #[serde(default)]
-    pub bind:                  Vec<ServiceBind>,
+    pub bind:                     Vec<ServiceBind>,
     /// Governs how the presence or absence of binds affects service startup
     ///
-    /// strict: blocks startup until all binds are present.
+    /// strict: blocks startup until all binds are present. optional: blocks startup until the
+    /// required binds are present, while optional binds may be absent.
     #[structopt(long = "binding-mode",
                 default_value = "strict",
-                possible_values = &["strict", "relaxed"])]
+                possible_values = &["strict", "relaxed", "optional"],
+                env = "HAB_SVC_LOAD_BINDING_MODE")]
     #[serde(default)]
-    pub binding_mode:          habitat_sup_protocol::types::BindingMode,
+    pub binding_mode:             habitat_sup_protocol::types::BindingMode,
     /// The interval in seconds on which to run health checks
     // We would prefer to use `HealthCheckInterval`. However, `HealthCheckInterval` uses a map based
     // serialization format. We want to allow the user to simply specify a `u64` to be consistent
     // with the CLI, but we cannot change the serialization because the spec file depends on the map
     // based format.
-    #[structopt(long = "health-check-interval", short = "i", default_value = "30")]
+    #[structopt(long = "health-check-interval",
+                short = "i",
+                default_value = "30",
+                env = "HAB_SVC_LOAD_HEALTH_CHECK_INTERVAL")]
     #[serde(default = "health_check_interval_default")]
-    pub health_check_interval: u64,
+    pub health_check_interval:    u64,
     /// The delay in seconds after sending the shutdown signal to wait before killing the service
     /// process
     ///
     /// The default value can be set in the packages plan file.
-    #[structopt(long = "shutdown-timeout")]
-    pub shutdown_timeout:      Option<ShutdownTimeout>,
+    #[structopt(long = "shutdown-timeout", env = "HAB_SVC_LOAD_SHUTDOWN_TIMEOUT")]
+    pub shutdown_timeout:         Option<ShutdownTimeout>,
+    /// Limit the CPU time of the service (cgroup v2 'cpu.max' format: '<quota> [<period>]' in
+    /// microseconds, e.g. '50000 100000' for half a CPU). On Windows, the limit is applied to
+    /// the service's job object
+    #[structopt(long = "cpu-max", validator = valid_cpu_max, env = "HAB_SVC_LOAD_CPU_MAX")]
+    pub cpu_max:                  Option<String>,
+    /// Limit the memory of the service (cgroup v2 'memory.max' format, e.g. '512M'). On
+    /// Windows, the limit is applied to the service's job object
+    #[structopt(long = "memory-max", validator = valid_memory_max, env = "HAB_SVC_LOAD_MEMORY_MAX")]
+    pub memory_max:               Option<String>,
+    /// The IO weight of the service relative to other services, between 1 and 10000 (cgroup v2
+    /// 'io.weight')
+    #[structopt(long = "io-weight", validator = valid_io_weight, env = "HAB_SVC_LOAD_IO_WEIGHT")]
+    pub io_weight:                Option<u32>,
+    /// Run the service as this user instead of the package's `pkg_svc_user`. When the
+    /// Supervisor runs as root, the user is created if it doesn't exist
+    #[structopt(long = "svc-user", env = "HAB_SVC_LOAD_SVC_USER")]
+    pub svc_user:                 Option<String>,
+    /// Run the service as this group instead of the package's `pkg_svc_group`. When the
+    /// Supervisor runs as root, the group is created if it doesn't exist
+    #[structopt(long = "svc-group", env = "HAB_SVC_LOAD_SVC_GROUP")]
+    pub svc_group:                Option<String>,
+    /// The size in bytes at which the service's log file in `/hab/svc/<name>/logs` is rotated.
+    /// Defaults to the Supervisor's `--service-log-max-bytes`
+    #[structopt(long = "log-max-bytes", env = "HAB_SVC_LOAD_LOG_MAX_BYTES")]
+    pub log_max_bytes:            Option<u64>,
+    /// The age in seconds at which the service's log file is rotated. Defaults to the
+    /// Supervisor's `--service-log-max-age`
+    #[structopt(long = "log-max-age", env = "HAB_SVC_LOAD_LOG_MAX_AGE")]
+    pub log_max_age:              Option<DurationProxy>,
+    /// The number of rotated log files to keep for the service, 0 to not write a log file.
+    /// Defaults to the Supervisor's `--service-log-max-files`
+    #[structopt(long = "log-max-files", env = "HAB_SVC_LOAD_LOG_MAX_FILES")]
+    pub log_max_files:            Option<u32>,
+    /// Also forward the service's output to `journald` or to a syslog receiver given as
+    /// `syslog+<udp|tcp|tls>://<host>[:<port>]`. Defaults to the Supervisor's
+    /// `--service-log-forward`
+    #[structopt(long = "log-forward",
+                validator = valid_log_forward,
+                env = "HAB_SVC_LOAD_LOG_FORWARD")]
+    pub log_forward:              Option<String>,
+    /// Where to send the service's stdout: `none` or a comma-separated list of `console`,
+    /// `file`, `forward` and `events`. Defaults to the Supervisor's `--service-stdout-to`
+    #[structopt(long = "stdout-to",
+                validator = valid_output_targets,
+                env = "HAB_SVC_LOAD_STDOUT_TO")]
+    pub stdout_to:                Option<String>,
+    /// Where to send the service's stderr: `none` or a comma-separated list of `console`,
+    /// `file`, `forward` and `events`. Defaults to the Supervisor's `--service-stderr-to`
+    #[structopt(long = "stderr-to",
+                validator = valid_output_targets,
+                env = "HAB_SVC_LOAD_STDERR_TO")]
+    pub stderr_to:                Option<String>,
+    /// The time in seconds after which a health-check hook that is still running is killed and
+    /// counted as a failure
+    #[structopt(long = "health-check-timeout",
+                env = "HAB_SVC_LOAD_HEALTH_CHECK_TIMEOUT")]
+    pub health_check_timeout:     Option<DurationProxy>,
+    /// The longest interval in seconds that health checks back off to, doubling after each
+    /// critical result, while the service stays critical
+    #[structopt(long = "health-check-max-backoff",
+                env = "HAB_SVC_LOAD_HEALTH_CHECK_MAX_BACKOFF")]
+    pub health_check_max_backoff: Option<DurationProxy>,
+    /// One or more member IDs or Supervisor tags (see `hab sup run --tag`) preferred as the
+    /// leader of the service group in a leader topology
+    #[structopt(long = "prefer-leader",
+                env = "HAB_SVC_LOAD_PREFER_LEADER",
+                use_delimiter = true)]
+    #[serde(default)]
+    pub prefer_leader:            Vec<String>,
+    /// One or more member IDs or Supervisor tags never elected leader of the service group in a
+    /// leader topology while another member can be
+    #[structopt(long = "exclude-leader",
+                env = "HAB_SVC_LOAD_EXCLUDE_LEADER",
+                use_delimiter = true)]
+    #[serde(default)]
+    pub exclude_leader:           Vec<String>,
+    /// Run the service in a PID namespace of its own, where it can't see or signal the
+    /// processes of other services, and whose processes all die with the service (Linux only).
+    /// A minimal init process of the namespace passes the signals the service is sent on to it
+    #[structopt(long = "pid-namespace")]
+    #[serde(default)]
+    pub pid_namespace:            bool,
+    /// Run the service in a network namespace of its own, which has nothing but a loopback
+    /// interface, so that it can bind the ports it wants without clashing with other services
+    /// (Linux only). The service can't make connections out of the namespace, and is only
+    /// reachable through its `--port` mappings
+    #[structopt(long = "network-namespace")]
+    #[serde(default)]
+    pub network_namespace:        bool,
+    /// One or more TCP ports of the host forwarded to the service in its network namespace, as
+    /// HOST_PORT:SERVICE_PORT (ex: 8080:80). Exported ports (keys named `port` or ending in
+    /// `_port` or `-port`) equal to a service port are advertised to the census as its host port
+    #[structopt(long = "port",
+                validator = valid_port_mapping,
+                env = "HAB_SVC_LOAD_PORT",
+                use_delimiter = true)]
+    #[serde(default)]
+    pub port:                     Vec<String>,
+    /// Restrict the system calls the service can make with a seccomp profile (Linux only):
+    /// `default`, which denies those that administer the host, load kernel code, or inspect and
+    /// change other processes, or the absolute path of a profile file. A profile is a TOML file
+    /// with a `default_action` and `[[syscalls]]` rules, each giving the `action` for its
+    /// `names`, optionally limited to calls whose `arg` has any of some bits set; actions are
+    /// `allow`, `errno`, `enosys`, `log` and `kill`. Defaults to the package's
+    /// `pkg_seccomp_profile`
+    #[structopt(long = "seccomp-profile",
+                validator = valid_seccomp_profile,
+                env = "HAB_SVC_LOAD_SECCOMP_PROFILE")]
+    pub seccomp_profile:          Option<String>,
+    /// Run the service in a filesystem sandbox, where it only sees the system directories, its
+    /// packages and service directory, the paths given with `--fs-allow`, a `/proc` of its own
+    /// and an empty `/tmp` (Linux only). It can't read the service directories of other
+    /// services, nor the configuration and secrets rendered into them
+    #[structopt(long = "filesystem-sandbox")]
+    #[serde(default)]
+    pub filesystem_sandbox:       bool,
+    /// One or more further paths of the host visible in the service's filesystem sandbox, as an
+    /// absolute path, followed by `:ro` to make it read-only (ex: /srv/media:ro)
+    #[structopt(long = "fs-allow",
+                validator = valid_allowed_path,
+                env = "HAB_SVC_LOAD_FS_ALLOW",
+                use_delimiter = true)]
+    #[serde(default)]
+    pub fs_allow:                 Vec<String>,
+    /// The file mode creation mask of the service, in octal, rather than the Supervisor's (ex:
+    /// 0027)
+    #[structopt(long = "umask", validator = valid_umask, env = "HAB_SVC_LOAD_UMASK")]
+    pub umask:                    Option<String>,
+    /// The absolute path of the directory the service runs in, rather than the Supervisor's
+    #[structopt(long = "working-dir",
+                validator = valid_working_dir,
+                env = "HAB_SVC_LOAD_WORKING_DIR")]
+    pub working_dir:              Option<String>,
+    /// One or more environment files on the Supervisor's host, in the dotenv format, whose
+    /// variables are set in the service's environment. The files are read when the service is
+    /// loaded, and read again on every `hab svc update`
+    #[structopt(long = "env-file",
+                validator = valid_env_file,
+                env = "HAB_SVC_LOAD_ENV_FILE",
+                use_delimiter = true)]
+    #[serde(default)]
+    pub env_file:                 Vec<String>,
+    /// One or more resource limits of the service, rather than the Supervisor's, as
+    /// RESOURCE=SOFT[:HARD], where RESOURCE is one of core, memlock, nofile and nproc, and each
+    /// limit is a number or `unlimited` (ex: nofile=65536)
+    #[structopt(long = "ulimit",
+                validator = valid_ulimit,
+                env = "HAB_SVC_LOAD_ULIMIT",
+                use_delimiter = true)]
+    #[serde(default)]
+    pub ulimit:                   Vec<String>,
+    /// The niceness of the service, rather than the Supervisor's, from -20 (the most favorable
+    /// scheduling) to 19
+    #[structopt(long = "nice",
+                validator = valid_nice,
+                env = "HAB_SVC_LOAD_NICE",
+                allow_hyphen_values = true)]
+    pub nice:                     Option<i32>,
+    /// The IO scheduling class and priority of the service, as realtime[:LEVEL],
+    /// best-effort[:LEVEL] or idle, where LEVEL goes from 0, the highest priority, to 7 and
+    /// defaults to 4 (ex: best-effort:7). Only honored on Linux
+    #[structopt(long = "io-priority",
+                validator = valid_io_priority,
+                env = "HAB_SVC_LOAD_IO_PRIORITY")]
+    pub io_priority:              Option<String>,
+    /// One or more names of sockets passed to the Launcher by systemd socket activation (the
+    /// `FileDescriptorName=` of the socket unit), which the service takes over with the
+    /// `LISTEN_FDS` protocol. The Launcher holds on to the sockets across restarts of the
+    /// service. Not honored on Windows
+    #[structopt(long = "listen-fd",
+                env = "HAB_SVC_LOAD_LISTEN_FD",
+                use_delimiter = true)]
+    #[serde(default)]
+    pub listen_fd:                Vec<String>,
     #[cfg(target_os = "windows")]
-    /// Password of the service user
-    #[structopt(long = "password")]
-    pub password:              Option<String>,
+    /// Password of the service user. Not needed when the service user is a group Managed
+    /// Service Account (ex: DOMAIN\\webapp$)
+    #[structopt(long = "password",
+                env = "HAB_SVC_LOAD_PASSWORD",
+                hide_env_values = true)]
+    pub password:                 Option<String>,
+    #[cfg(not(target_os = "windows"))]
+    /// A secret the service needs to start, encrypted with the host key of the Supervisor and
+    /// given to its hooks and run as HAB_SVC_PASSWORD. Must be given as root on the Supervisor's
+    /// host, and not with --remote-sup
+    #[structopt(long = "password",
+                env = "HAB_SVC_LOAD_PASSWORD",
+                hide_env_values = true)]
+    pub password:                 Option<String>,
     // TODO (DM): This flag can eventually be removed.
     // See https://github.com/habitat-sh/habitat/issues/7339
     /// DEPRECATED
     #[structopt(long = "application", short = "a", takes_value = false, hidden = true)]
     #[serde(skip)]
-    pub application:           Vec<String>,
+    pub application:              Vec<String>,
     // TODO (DM): This flag can eventually be removed.
     // See https://github.com/habitat-sh/habitat/issues/7339
     /// DEPRECATED
     #[structopt(long = "environment", short = "e", takes_value = false, hidden = true)]
     #[serde(skip)]
-    pub environment:           Vec<String>,
+    pub environment:              Vec<String>,
     /// Use the package config from this path rather than the package itself
-    #[structopt(long = "config-from")]
-    pub config_from:           Option<PathBuf>,
+    #[structopt(long = "config-from", env = "HAB_SVC_LOAD_CONFIG_FROM")]
+    pub config_from:              Option<PathBuf>,
 }
 
 fn load_default_config_files() -> Vec<PathBuf> {
@@ -256,21 +502,70 @@ fn load_default_config_files() -> Vec<PathBuf> {
 /// installed from Builder.
 pub struct Load {
     #[structopt(flatten)]
-    pub pkg_ident:   PkgIdent,
+    pub pkg_ident:             PkgIdent,
+    /// More package identifiers to load with the same options, in a single session with the
+    /// Supervisor
+    #[structopt(name = "ADDITIONAL_PKG_IDENT")]
+    #[serde(skip)]
+    pub additional_pkg_idents: Vec<PackageIdent>,
     /// Load or reload an already loaded service. If the service was previously loaded and
     /// running this operation will also restart the service
     #[structopt(short = "f", long = "force")]
     #[serde(default)]
-    pub force:       bool,
+    pub force:                 bool,
+    /// Load the release of the package pinned in this lockfile (see `hab pkg lock`) instead of
+    /// the latest one satisfying the package identifier
+    #[structopt(long = "lockfile", env = "HAB_SVC_LOAD_LOCKFILE")]
+    pub lockfile:              Option<PathBuf>,
     #[structopt(flatten)]
     #[serde(flatten)]
-    pub remote_sup:  RemoteSup,
+    pub remote_sup:            RemoteSup,
     #[structopt(flatten)]
     #[serde(flatten)]
-    pub shared_load: SharedLoad,
+    pub shared_load:           SharedLoad,
+}
+
+impl Load {
+    /// The package identifier to load, pinned to a release if a lockfile was given.
+    pub fn ident(&self) -> Result<PackageIdent> { self.pinned(self.pkg_ident.clone().pkg_ident()) }
+
+    /// All the package identifiers to load, pinned to releases if a lockfile was given.
+    pub fn idents(&self) -> Result<Vec<PackageIdent>> {
+        let mut idents = vec![self.ident()?];
+        for ident in &self.additional_pkg_idents {
+            idents.push(self.pinned(ident.clone())?);
+        }
+        Ok(idents)
+    }
+
+    /// The load messages for every package identifier, which only differ by their ident.
+    pub fn into_ctl_msgs(self) -> Result<Vec<habitat_sup_protocol::ctl::SvcLoad>> {
+        #[cfg(not(target_os = "windows"))]
+        ensure_local_sup_for_svc_password(self.shared_load.password.as_ref(), &self.remote_sup)?;
+        let idents = self.idents()?;
+        let msg = shared_load_cli_to_ctl(idents[0].clone(), self.shared_load, self.force)?;
+        Ok(idents.into_iter()
+                 .map(|ident| {
+                     habitat_sup_protocol::ctl::SvcLoad { ident: Some(ident.into()),
+                                                          ..msg.clone() }
+                 })
+                 .collect())
+    }
+
+    fn pinned(&self, ident: PackageIdent) -> Result<PackageIdent> {
+        match &self.lockfile {
+            Some(lockfile) => lock::pinned_ident(lockfile, &ident, PackageTarget::active_target()),
+            None => Ok(ident),
+        }
+    }
 }
 
-pub fn svc_loads_from_paths<T: AsRef<Path>>(paths: &[T]) -> Result<Vec<Load>> {
+/// The service config files in the given paths, in the order the ordering file gives, if any,
+/// and otherwise in the order they are found. See `BulkLoad` for the meaning of the arguments.
+pub fn svc_loads_from_paths<T: AsRef<Path>>(paths: &[T],
+                                            exclude: &[String],
+                                            order_file: Option<&Path>)
+                                            -> Result<Vec<Load>> {
     // If the only path is the default location and the directory does not exist do not report an
     // error. This allows users to run the Supervisor without creating the directory.
     if paths.len() == 1 {
@@ -279,30 +574,201 @@ pub fn svc_loads_from_paths<T: AsRef<Path>>(paths: &[T]) -> Result<Vec<Load>> {
             return Ok(Vec::new());
         }
     }
-    let mut svc_loads = Vec::new();
-    let default_svc_load = ConfigOptLoad::from_default_config_files()?;
+    let exclude = exclude.iter()
+                         .map(|p| svc_config_pattern(p))
+                         .collect::<Result<Vec<_>>>()?;
+    let mut svc_config_files = Vec::new();
     for path in paths {
-        for entry in WalkDir::new(path) {
-            let entry = entry?;
-            let path = entry.path();
-            if entry.file_type().is_file() {
-                if let Some(extension) = path.extension() {
-                    if extension == "toml" {
-                        // Patch the service config with values from the default config file. We
-                        // must use two `take` calls instead of a single patch call to ensure
-                        // deserialization default values are correctly overwritten.
-                        let mut configopt_svc_load = configopt::from_toml_file(path)?;
-                        let mut default_svc_load = default_svc_load.clone();
-                        default_svc_load.take(&mut configopt_svc_load);
-                        let mut svc_load = configopt::from_toml_file(path)?;
-                        default_svc_load.clone().take_for(&mut svc_load);
-                        svc_loads.push(svc_load);
-                    }
+        for path in expand_svc_config_path(path.as_ref())? {
+            let entries = WalkDir::new(path).sort_by(|a, b| a.file_name().cmp(b.file_name()))
+                                            .into_iter()
+                                            .filter_entry(|e| {
+                                                !exclude.iter()
+                                                        .any(|p| svc_config_matches(p, e.path()))
+                                            });
+            for entry in entries {
+                let entry = entry?;
+                let path = entry.path();
+                if entry.file_type().is_file() && path.extension() == Some("toml".as_ref()) {
+                    svc_config_files.push(path.to_path_buf());
                 }
             }
         }
     }
-    Ok(svc_loads)
+    if let Some(order_file) = order_file {
+        order_svc_config_files(&mut svc_config_files, order_file)?;
+    }
+
+    // Every file is parsed, so that all the invalid ones are reported together
+    let mut svc_loads = Vec::new();
+    let mut invalid = Vec::new();
+    let default_svc_load = ConfigOptLoad::from_default_config_files()?;
+    for path in svc_config_files {
+        match svc_load_from_file(&path, &default_svc_load) {
+            Ok(svc_load) => svc_loads.push(svc_load),
+            Err(e) => invalid.push((path, e)),
+        }
+    }
+    if invalid.is_empty() {
+        Ok(svc_loads)
+    } else {
+        Err(Error::InvalidSvcConfigFiles(invalid))
+    }
+}
+
+/// Read a service config file, patched with values from the default config file.
+fn svc_load_from_file(path: &Path,
+                      default_svc_load: &ConfigOptLoad)
+                      -> std::result::Result<Load, String> {
+    let content = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    // We must use two `take` calls instead of a single patch call to ensure deserialization
+    // default values are correctly overwritten.
+    let mut configopt_svc_load = parse_svc_config(&content)?;
+    let mut default_svc_load = default_svc_load.clone();
+    default_svc_load.take(&mut configopt_svc_load);
+    let mut svc_load = parse_svc_config(&content)?;
+    default_svc_load.take_for(&mut svc_load);
+    Ok(svc_load)
+}
+
+/// Parse a service config file, describing where it is invalid when it is: the line and column
+/// of a syntax error, or the key whose value is invalid and the line it is on.
+fn parse_svc_config<T: DeserializeOwned>(content: &str) -> std::result::Result<T, String> {
+    let value = content.parse::<toml::Value>().map_err(|e| e.to_string())?;
+    value.clone()
+         .try_into()
+         .map_err(|e| locate_svc_config_error(content, &value, &e))
+}
+
+/// Find the key of a service config file that makes it invalid. Values of flattened fields are
+/// deserialized without the context of their key, so each key is checked on its own.
+fn locate_svc_config_error(content: &str, value: &toml::Value, err: &toml::de::Error) -> String {
+    let table = match value.as_table() {
+        Some(table) => table,
+        None => return err.to_string(),
+    };
+    for (key, value) in table {
+        let mut single = toml::value::Table::new();
+        single.insert(key.clone(), value.clone());
+        if let Err(e) = toml::Value::Table(single).try_into::<ConfigOptLoad>() {
+            let mut message = e.to_string();
+            if !message.contains("for key") {
+                message = format!("{} for key `{}`", message, key);
+            }
+            return match content.lines().position(|line| defines_key(line, key)) {
+                Some(line) => format!("{} at line {}", message, line + 1),
+                None => message,
+            };
+        }
+    }
+    err.to_string()
+}
+
+/// Whether a line of a TOML document sets the given top level key or starts its table.
+fn defines_key(line: &str, key: &str) -> bool {
+    let line = line.trim();
+    if line.starts_with('[') {
+        return line.trim_start_matches('[').trim_end_matches(']').trim() == key;
+    }
+    line.starts_with(key) && line[key.len()..].trim_start().starts_with('=')
+}
+
+const SVC_CONFIG_MATCH_OPTIONS: glob::MatchOptions =
+    glob::MatchOptions { case_sensitive:              true,
+                         require_literal_separator:   true,
+                         require_literal_leading_dot: false, };
+
+fn svc_config_pattern(pattern: &str) -> Result<glob::Pattern> {
+    glob::Pattern::new(pattern).map_err(|e| {
+                                   Error::ArgumentError(format!("Invalid glob pattern '{}', {}",
+                                                                pattern, e))
+                               })
+}
+
+/// Whether the pattern matches the name or the whole path of a service config file or directory.
+fn svc_config_matches(pattern: &glob::Pattern, path: &Path) -> bool {
+    let name_matches = path.file_name()
+                           .map_or(false, |name| {
+                               pattern.matches_path_with(name.as_ref(), SVC_CONFIG_MATCH_OPTIONS)
+                           });
+    name_matches || pattern.matches_path_with(path, SVC_CONFIG_MATCH_OPTIONS)
+}
+
+/// The paths a glob pattern matches, in the order of their paths, or the path itself when it
+/// isn't a pattern.
+fn expand_svc_config_path(path: &Path) -> Result<Vec<PathBuf>> {
+    let path_str = path.to_string_lossy();
+    if !path_str.contains(|c| c == '*' || c == '?' || c == '[') {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let paths = glob::glob_with(&path_str, SVC_CONFIG_MATCH_OPTIONS).map_err(|e| {
+                    Error::ArgumentError(format!("Invalid glob pattern '{}', {}", path_str, e))
+                })?;
+    Ok(paths.filter_map(std::result::Result::ok).collect())
+}
+
+/// Sort service config files by the first line of the ordering file that matches them, keeping
+/// the files no line matches last.
+fn order_svc_config_files(files: &mut Vec<PathBuf>, order_file: &Path) -> Result<()> {
+    let content = std::fs::read_to_string(order_file).map_err(|e| {
+                      Error::ArgumentError(format!("Unable to read the ordering file {}, {}",
+                                                   order_file.display(),
+                                                   e))
+                  })?;
+    let patterns = content.lines()
+                          .map(str::trim)
+                          .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                          .map(svc_config_pattern)
+                          .collect::<Result<Vec<_>>>()?;
+    files.sort_by_key(|file| {
+             patterns.iter()
+                     .position(|p| svc_config_matches(p, file))
+                     .unwrap_or_else(|| patterns.len())
+         });
+    Ok(())
+}
+
+/// Fail if a service password is given for a Supervisor other than the local one, whose host key
+/// isn't available to encrypt it with.
+#[cfg(not(target_os = "windows"))]
+fn ensure_local_sup_for_svc_password(password: Option<&String>,
+                                     remote_sup: &RemoteSup)
+                                     -> Result<()> {
+    let remote_sup = remote_sup.to_listen_ctl_addr();
+    if password.is_some() && !remote_sup.ip().is_loopback() {
+        return Err(Error::ArgumentError(format!("--password can't be used \
+                                                 with the remote Supervisor \
+                                                 {}, as it must be \
+                                                 encrypted with the \
+                                                 Supervisor's host key",
+                                                remote_sup)));
+    }
+    Ok(())
+}
+
+/// Encrypt a service password with the host key in the key cache the Supervisor uses. That is
+/// root's key cache unless one is given explicitly, so other users can't encrypt passwords.
+#[cfg(not(target_os = "windows"))]
+fn encrypt_svc_password(password: &str) -> Result<String> {
+    use habitat_core::{crypto::{keys::KeyCache,
+                                svc_password,
+                                CACHE_KEY_PATH_ENV_VAR},
+                       env as henv,
+                       fs::{am_i_root,
+                            CACHE_KEY_PATH}};
+
+    let key_cache = match henv::var(CACHE_KEY_PATH_ENV_VAR) {
+        Ok(path) => KeyCache::new(path),
+        Err(_) if am_i_root() => KeyCache::new(&*CACHE_KEY_PATH),
+        Err(_) => {
+            return Err(Error::ArgumentError(format!("--password must be given \
+                                                     as root, or with {} set to \
+                                                     the key cache of the \
+                                                     Supervisor",
+                                                    CACHE_KEY_PATH_ENV_VAR)));
+        }
+    };
+    Ok(svc_password::encrypt(&key_cache, password)?)
 }
 
 pub fn shared_load_cli_to_ctl(ident: PackageIdent,
@@ -313,8 +779,14 @@ pub fn shared_load_cli_to_ctl(ident: PackageIdent,
                          ui::UIWriter};
     #[cfg(target_os = "windows")]
     use habitat_core::crypto::dpapi;
-    use habitat_sup_protocol::{ctl::{ServiceBindList,
-                                     SvcLoad},
+    use habitat_sup_protocol::{ctl::{AllowedPathList,
+                                     EnvFileList,
+                                     ListenFdList,
+                                     MemberList,
+                                     PortMappingList,
+                                     ServiceBindList,
+                                     SvcLoad,
+                                     UlimitList},
                                types::{HealthCheckInterval,
                                        ServiceBind}};
 
@@ -344,6 +816,41 @@ pub fn shared_load_cli_to_ctl(ident: PackageIdent,
         None
     };
 
+    let member_list = |members: Vec<String>| {
+        if members.is_empty() {
+            None
+        } else {
+            Some(MemberList { members })
+        }
+    };
+    let preferred_leaders = member_list(shared_load.prefer_leader);
+    let excluded_leaders = member_list(shared_load.exclude_leader);
+    let ports = if shared_load.port.is_empty() {
+        None
+    } else {
+        Some(PortMappingList { mappings: shared_load.port, })
+    };
+    let fs_allow = if shared_load.fs_allow.is_empty() {
+        None
+    } else {
+        Some(AllowedPathList { paths: shared_load.fs_allow, })
+    };
+    let env_files = if shared_load.env_file.is_empty() {
+        None
+    } else {
+        Some(EnvFileList { paths: shared_load.env_file, })
+    };
+    let ulimits = if shared_load.ulimit.is_empty() {
+        None
+    } else {
+        Some(UlimitList { ulimits: shared_load.ulimit, })
+    };
+    let listen_fds = if shared_load.listen_fd.is_empty() {
+        None
+    } else {
+        Some(ListenFdList { names: shared_load.listen_fd, })
+    };
+
     #[cfg(target_os = "windows")]
     let svc_encrypted_password = if let Some(password) = shared_load.password {
         Some(dpapi::encrypt(password)?)
@@ -351,7 +858,10 @@ pub fn shared_load_cli_to_ctl(ident: PackageIdent,
         None
     };
     #[cfg(not(target_os = "windows"))]
-    let svc_encrypted_password = None;
+    let svc_encrypted_password = shared_load.password
+                                            .as_deref()
+                                            .map(encrypt_svc_password)
+                                            .transpose()?;
 
     Ok(SvcLoad { ident: Some(ident.into()),
                  application_environment: None,
@@ -368,16 +878,45 @@ pub fn shared_load_cli_to_ctl(ident: PackageIdent,
                  health_check_interval:
                      Some(HealthCheckInterval { seconds: shared_load.health_check_interval, }),
                  shutdown_timeout: shared_load.shutdown_timeout.map(u32::from),
-                 update_condition: Some(shared_load.update_condition as i32) })
+                 update_condition: Some(shared_load.update_condition as i32),
+                 cpu_max: shared_load.cpu_max,
+                 memory_max: shared_load.memory_max,
+                 io_weight: shared_load.io_weight,
+                 svc_user: shared_load.svc_user,
+                 svc_group: shared_load.svc_group,
+                 log_max_bytes: shared_load.log_max_bytes,
+                 log_max_age: shared_load.log_max_age.map(u64::from),
+                 log_max_files: shared_load.log_max_files,
+                 log_forward: shared_load.log_forward,
+                 stdout_to: shared_load.stdout_to,
+                 stderr_to: shared_load.stderr_to,
+                 health_check_timeout: shared_load.health_check_timeout.map(u64::from),
+                 health_check_max_backoff: shared_load.health_check_max_backoff.map(u64::from),
+                 preferred_leaders,
+                 excluded_leaders,
+                 pid_namespace: Some(shared_load.pid_namespace),
+                 network_namespace: Some(shared_load.network_namespace),
+                 ports,
+                 seccomp_profile: shared_load.seccomp_profile,
+                 filesystem_sandbox: Some(shared_load.filesystem_sandbox),
+                 fs_allow,
+                 umask: shared_load.umask,
+                 working_dir: shared_load.working_dir,
+                 env_files,
+                 ulimits,
+                 nice: shared_load.nice,
+                 io_priority: shared_load.io_priority,
+                 listen_fds })
 }
 
 impl TryFrom<Load> for habitat_sup_protocol::ctl::SvcLoad {
     type Error = crate::error::Error;
 
     fn try_from(svc_load: Load) -> Result<Self> {
-        shared_load_cli_to_ctl(svc_load.pkg_ident.pkg_ident(),
-                               svc_load.shared_load,
-                               svc_load.force)
+        #[cfg(not(target_os = "windows"))]
+        ensure_local_sup_for_svc_password(svc_load.shared_load.password.as_ref(),
+                                          &svc_load.remote_sup)?;
+        shared_load_cli_to_ctl(svc_load.ident()?, svc_load.shared_load, svc_load.force)
     }
 }
 
@@ -440,16 +979,18 @@ pub struct Update {
                 possible_values = UpdateCondition::VARIANTS)]
     pub update_condition: Option<UpdateCondition>,
 
-    /// One or more service groups to bind to a configuration
+    /// One or more service groups to bind to a configuration. Pointing the service's existing
+    /// binds at other service groups does not restart it
     #[structopt(long = "bind")]
     #[serde(default)]
     pub bind: Option<Vec<ServiceBind>>,
 
     /// Governs how the presence or absence of binds affects service startup
     ///
-    /// strict: blocks startup until all binds are present.
+    /// strict: blocks startup until all binds are present. optional: blocks startup until the
+    /// required binds are present, while optional binds may be absent.
     #[structopt(long = "binding-mode",
-                possible_values = &["strict", "relaxed"])]
+                possible_values = &["strict", "relaxed", "optional"])]
     pub binding_mode: Option<BindingMode>,
 
     /// The interval in seconds on which to run health checks
@@ -465,16 +1006,168 @@ pub struct Update {
     #[structopt(long = "shutdown-timeout")]
     pub shutdown_timeout: Option<ShutdownTimeout>,
 
-    /// Password of the service user
+    /// Limit the CPU time of the service (cgroup v2 'cpu.max' format: '<quota> [<period>]' in
+    /// microseconds, e.g. '50000 100000' for half a CPU). On Windows, the limit is applied to
+    /// the service's job object
+    #[structopt(long = "cpu-max", validator = valid_cpu_max)]
+    pub cpu_max: Option<String>,
+
+    /// Limit the memory of the service (cgroup v2 'memory.max' format, e.g. '512M'). On
+    /// Windows, the limit is applied to the service's job object
+    #[structopt(long = "memory-max", validator = valid_memory_max)]
+    pub memory_max: Option<String>,
+
+    /// The IO weight of the service relative to other services, between 1 and 10000 (cgroup v2
+    /// 'io.weight')
+    #[structopt(long = "io-weight", validator = valid_io_weight)]
+    pub io_weight: Option<u32>,
+
+    /// Run the service as this user instead of the package's `pkg_svc_user`. When the
+    /// Supervisor runs as root, the user is created if it doesn't exist
+    #[structopt(long = "svc-user")]
+    pub svc_user: Option<String>,
+
+    /// Run the service as this group instead of the package's `pkg_svc_group`. When the
+    /// Supervisor runs as root, the group is created if it doesn't exist
+    #[structopt(long = "svc-group")]
+    pub svc_group: Option<String>,
+
+    /// The size in bytes at which the service's log file in `/hab/svc/<name>/logs` is rotated
+    #[structopt(long = "log-max-bytes")]
+    pub log_max_bytes: Option<u64>,
+
+    /// The age in seconds at which the service's log file is rotated
+    #[structopt(long = "log-max-age")]
+    pub log_max_age: Option<DurationProxy>,
+
+    /// The number of rotated log files to keep for the service, 0 to not write a log file
+    #[structopt(long = "log-max-files")]
+    pub log_max_files: Option<u32>,
+
+    /// Also forward the service's output to `journald` or to a syslog receiver given as
+    /// `syslog+<udp|tcp|tls>://<host>[:<port>]`
+    #[structopt(long = "log-forward", validator = valid_log_forward)]
+    pub log_forward: Option<String>,
+
+    /// Where to send the service's stdout: `none` or a comma-separated list of `console`,
+    /// `file`, `forward` and `events`
+    #[structopt(long = "stdout-to", validator = valid_output_targets)]
+    pub stdout_to: Option<String>,
+
+    /// Where to send the service's stderr: `none` or a comma-separated list of `console`,
+    /// `file`, `forward` and `events`
+    #[structopt(long = "stderr-to", validator = valid_output_targets)]
+    pub stderr_to: Option<String>,
+
+    /// The time in seconds after which a health-check hook that is still running is killed and
+    /// counted as a failure
+    #[structopt(long = "health-check-timeout")]
+    pub health_check_timeout: Option<DurationProxy>,
+
+    /// The longest interval in seconds that health checks back off to, doubling after each
+    /// critical result, while the service stays critical
+    #[structopt(long = "health-check-max-backoff")]
+    pub health_check_max_backoff: Option<DurationProxy>,
+
+    /// One or more member IDs or Supervisor tags (see `hab sup run --tag`) preferred as the
+    /// leader of the service group in a leader topology
+    #[structopt(long = "prefer-leader")]
+    #[serde(default)]
+    pub prefer_leader: Option<Vec<String>>,
+
+    /// One or more member IDs or Supervisor tags never elected leader of the service group in a
+    /// leader topology while another member can be
+    #[structopt(long = "exclude-leader")]
+    #[serde(default)]
+    pub exclude_leader: Option<Vec<String>>,
+
+    /// Whether to run the service in a PID namespace of its own (Linux only)
+    #[structopt(long = "pid-namespace", possible_values = &["true", "false"])]
+    pub pid_namespace: Option<bool>,
+
+    /// Whether to run the service in a network namespace of its own (Linux only)
+    #[structopt(long = "network-namespace", possible_values = &["true", "false"])]
+    pub network_namespace: Option<bool>,
+
+    /// One or more TCP ports of the host forwarded to the service in its network namespace, as
+    /// HOST_PORT:SERVICE_PORT (ex: 8080:80)
+    #[structopt(long = "port", validator = valid_port_mapping)]
+    #[serde(default)]
+    pub port: Option<Vec<String>>,
+
+    /// The seccomp profile restricting the system calls the service can make (Linux only):
+    /// `default`, or the absolute path of a profile file
+    #[structopt(long = "seccomp-profile", validator = valid_seccomp_profile)]
+    pub seccomp_profile: Option<String>,
+
+    /// Whether to run the service in a filesystem sandbox (Linux only)
+    #[structopt(long = "filesystem-sandbox", possible_values = &["true", "false"])]
+    pub filesystem_sandbox: Option<bool>,
+
+    /// One or more further paths of the host visible in the service's filesystem sandbox, as an
+    /// absolute path, followed by `:ro` to make it read-only (ex: /srv/media:ro)
+    #[structopt(long = "fs-allow", validator = valid_allowed_path)]
+    #[serde(default)]
+    pub fs_allow: Option<Vec<String>>,
+
+    /// The file mode creation mask of the service, in octal (ex: 0027)
+    #[structopt(long = "umask", validator = valid_umask)]
+    pub umask: Option<String>,
+
+    /// The absolute path of the directory the service runs in
+    #[structopt(long = "working-dir", validator = valid_working_dir)]
+    pub working_dir: Option<String>,
+
+    /// One or more environment files on the Supervisor's host, in the dotenv format, whose
+    /// variables are set in the service's environment. The environment files of the service
+    /// are read again on every update
+    #[structopt(long = "env-file", validator = valid_env_file)]
+    #[serde(default)]
+    pub env_file: Option<Vec<String>>,
+
+    /// One or more resource limits of the service, as RESOURCE=SOFT[:HARD], where RESOURCE is
+    /// one of core, memlock, nofile and nproc, and each limit is a number or `unlimited` (ex:
+    /// nofile=65536)
+    #[structopt(long = "ulimit", validator = valid_ulimit)]
+    #[serde(default)]
+    pub ulimit: Option<Vec<String>>,
+
+    /// The niceness of the service, from -20 (the most favorable scheduling) to 19
+    #[structopt(long = "nice", validator = valid_nice, allow_hyphen_values = true)]
+    pub nice: Option<i32>,
+
+    /// The IO scheduling class and priority of the service, as realtime[:LEVEL],
+    /// best-effort[:LEVEL] or idle, where LEVEL goes from 0, the highest priority, to 7 (ex:
+    /// best-effort:7). Only honored on Linux
+    #[structopt(long = "io-priority", validator = valid_io_priority)]
+    pub io_priority: Option<String>,
+
+    /// One or more names of sockets passed to the Launcher by systemd socket activation, which
+    /// the service takes over with the `LISTEN_FDS` protocol. Not honored on Windows
+    #[structopt(long = "listen-fd")]
+    #[serde(default)]
+    pub listen_fd: Option<Vec<String>>,
+
+    /// Password of the service user. Not needed when the service user is a group Managed
+    /// Service Account (ex: DOMAIN\\webapp$)
     #[cfg(target_os = "windows")]
     #[structopt(long = "password")]
     pub password: Option<String>,
+
+    /// A secret the service needs to start, encrypted with the host key of the Supervisor and
+    /// given to its hooks and run as HAB_SVC_PASSWORD. Must be given as root on the Supervisor's
+    /// host, and not with --remote-sup
+    #[cfg(not(target_os = "windows"))]
+    #[structopt(long = "password")]
+    pub password: Option<String>,
 }
 
 impl TryFrom<Update> for ctl::SvcUpdate {
     type Error = Error;
 
     fn try_from(u: Update) -> Result<Self> {
+        #[cfg(not(target_os = "windows"))]
+        ensure_local_sup_for_svc_password(u.password.as_ref(), &u.remote_sup)?;
         let msg = ctl::SvcUpdate { ident: Some(From::from(u.pkg_ident.pkg_ident())),
                                    // We are explicitly *not* using the environment variable as a
                                    // fallback.
@@ -488,10 +1181,44 @@ impl TryFrom<Update> for ctl::SvcUpdate {
                                    update_strategy: u.strategy.map(|v| v as i32),
                                    update_condition: u.update_condition.map(|v| v as i32),
                                    shutdown_timeout: u.shutdown_timeout.map(Into::into),
+                                   cpu_max: u.cpu_max,
+                                   memory_max: u.memory_max,
+                                   io_weight: u.io_weight,
+                                   svc_user: u.svc_user,
+                                   svc_group: u.svc_group,
+                                   log_max_bytes: u.log_max_bytes,
+                                   log_max_age: u.log_max_age.map(u64::from),
+                                   log_max_files: u.log_max_files,
+                                   log_forward: u.log_forward,
+                                   stdout_to: u.stdout_to,
+                                   stderr_to: u.stderr_to,
+                                   health_check_timeout: u.health_check_timeout.map(u64::from),
+                                   health_check_max_backoff: u.health_check_max_backoff
+                                                              .map(u64::from),
+                                   preferred_leaders: u.prefer_leader
+                                                       .map(|members| ctl::MemberList { members }),
+                                   excluded_leaders: u.exclude_leader
+                                                      .map(|members| ctl::MemberList { members }),
+                                   pid_namespace: u.pid_namespace,
+                                   network_namespace: u.network_namespace,
+                                   ports: u.port.map(|mappings| ctl::PortMappingList { mappings }),
+                                   seccomp_profile: u.seccomp_profile,
+                                   filesystem_sandbox: u.filesystem_sandbox,
+                                   fs_allow: u.fs_allow.map(|paths| ctl::AllowedPathList { paths }),
+                                   umask: u.umask,
+                                   working_dir: u.working_dir,
+                                   env_files: u.env_file.map(|paths| ctl::EnvFileList { paths }),
+                                   ulimits: u.ulimit.map(|ulimits| ctl::UlimitList { ulimits }),
+                                   nice: u.nice,
+                                   io_priority: u.io_priority,
+                                   listen_fds: u.listen_fd.map(|names| ctl::ListenFdList { names }),
                                    #[cfg(windows)]
                                    svc_encrypted_password: u.password,
                                    #[cfg(not(windows))]
-                                   svc_encrypted_password: None, };
+                                   svc_encrypted_password: u.password
+                                                            .as_deref()
+                                                            .map(encrypt_svc_password)
+                                                            .transpose()?, };
 
         // Compiler-assisted validation that the user has indeed
         // specified *something* to change. If they didn't, all the
@@ -507,7 +1234,35 @@ impl TryFrom<Update> for ctl::SvcUpdate {
                                 update_strategy: None,
                                 health_check_interval: None,
                                 shutdown_timeout: None,
-                                update_condition: None, } = &msg
+                                update_condition: None,
+                                cpu_max: None,
+                                memory_max: None,
+                                io_weight: None,
+                                svc_user: None,
+                                svc_group: None,
+                                log_max_bytes: None,
+                                log_max_age: None,
+                                log_max_files: None,
+                                log_forward: None,
+                                stdout_to: None,
+                                stderr_to: None,
+                                health_check_timeout: None,
+                                health_check_max_backoff: None,
+                                preferred_leaders: None,
+                                excluded_leaders: None,
+                                pid_namespace: None,
+                                network_namespace: None,
+                                ports: None,
+                                seccomp_profile: None,
+                                filesystem_sandbox: None,
+                                fs_allow: None,
+                                umask: None,
+                                working_dir: None,
+                                env_files: None,
+                                ulimits: None,
+                                nice: None,
+                                io_priority: None,
+                                listen_fds: None, } = &msg
         {
             Err(Error::ArgumentError("No fields specified for update".to_string()))
         } else {
@@ -1,4 +1,5 @@
-use super::{svc::{Load,
+use super::{svc::{self,
+                  Load,
                   Svc,
                   Update},
             Hab};
@@ -450,6 +451,33 @@ fn test_hab_svc_load_flag_ordering() {
     assert_eq!(load.pkg_ident.pkg_ident(), pkg_ident);
 }
 
+#[test]
+fn test_hab_svc_load_multiple_idents() {
+    let hab = Hab::try_from_iter_with_configopt(&["hab",
+                                                  "svc",
+                                                  "load",
+                                                  "core/redis",
+                                                  "core/nginx",
+                                                  "core/postgresql/11.2",
+                                                  "--group",
+                                                  "prod"]).unwrap();
+    let load = extract_hab_svc_load(hab);
+    let idents: Vec<String> = load.idents()
+                                  .unwrap()
+                                  .iter()
+                                  .map(ToString::to_string)
+                                  .collect();
+    assert_eq!(idents,
+               vec!["core/redis", "core/nginx", "core/postgresql/11.2"]);
+
+    let msgs = load.into_ctl_msgs().unwrap();
+    assert_eq!(msgs.len(), 3);
+    for msg in &msgs {
+        assert_eq!(msg.group.as_deref(), Some("prod"));
+    }
+    assert_eq!(msgs[1].ident.as_ref().unwrap().to_string(), "core/nginx");
+}
+
 #[test]
 fn test_hab_svc_update_empty_binds() {
     let hab = Hab::try_from_iter_with_configopt(&["hab", "svc", "update", "core/redis", "--bind"]).unwrap();
@@ -465,3 +493,92 @@ fn test_hab_svc_update_empty_binds() {
     let update = extract_hab_svc_update(hab);
     assert_eq!(update.bind.unwrap().len(), 1);
 }
+
+#[test]
+fn test_svc_loads_from_paths_globs_excludes_and_order() {
+    use std::fs;
+
+    let dir = tempfile::TempDir::new().unwrap();
+    for (subdir, name) in &[("prod", "redis"),
+                            ("prod", "nginx"),
+                            ("staging", "redis"),
+                            ("prod", "postgresql")]
+    {
+        let subdir = dir.path().join(subdir);
+        fs::create_dir_all(&subdir).unwrap();
+        fs::write(subdir.join(format!("{}.toml", name)),
+                  format!("pkg_ident = \"core/{}\"", name)).unwrap();
+    }
+    fs::write(dir.path().join("prod").join("README.md"), "not a service").unwrap();
+    let order_file = dir.path().join("order");
+    fs::write(&order_file,
+              "# databases first\npostgresql.toml\n\n*/prod/redis.toml\n").unwrap();
+
+    let idents = |loads: Vec<Load>| -> Vec<String> {
+        loads.into_iter()
+             .map(|l| l.pkg_ident.pkg_ident().to_string())
+             .collect()
+    };
+
+    let pattern = dir.path().join("*");
+    let loads = svc::svc_loads_from_paths(&[&pattern], &[], None).unwrap();
+    assert_eq!(idents(loads),
+               vec!["core/nginx", "core/postgresql", "core/redis", "core/redis"]);
+
+    let loads = svc::svc_loads_from_paths(&[&pattern], &["staging".to_string()], None).unwrap();
+    assert_eq!(idents(loads),
+               vec!["core/nginx", "core/postgresql", "core/redis"]);
+
+    let loads = svc::svc_loads_from_paths(&[dir.path()],
+                                          &["staging".to_string(), "nginx.toml".to_string()],
+                                          Some(&order_file)).unwrap();
+    assert_eq!(idents(loads), vec!["core/postgresql", "core/redis"]);
+
+    assert!(svc::svc_loads_from_paths(&[dir.path()], &["[".to_string()], None).is_err());
+}
+
+#[test]
+fn test_svc_loads_from_paths_reports_every_invalid_file() {
+    use std::fs;
+
+    let dir = tempfile::TempDir::new().unwrap();
+    fs::write(dir.path().join("good.toml"), "pkg_ident = \"core/redis\"\n").unwrap();
+    fs::write(dir.path().join("syntax.toml"),
+              "pkg_ident = \"core/nginx\"\ngroup = \n").unwrap();
+    fs::write(dir.path().join("type.toml"),
+              "pkg_ident = \"core/postgresql\"\n\nhealth_check_interval = \"often\"\n").unwrap();
+
+    let err = svc::svc_loads_from_paths(&[dir.path()], &[], None).unwrap_err()
+                                                                 .to_string();
+    assert!(!err.contains("good.toml"));
+    assert!(err.contains(&format!("{}: ", dir.path().join("syntax.toml").display())));
+    assert!(err.contains("line 2"));
+    let type_err = err.lines().find(|l| l.contains("type.toml")).unwrap();
+    assert!(type_err.contains("`health_check_interval`"));
+    assert!(type_err.contains("at line 3"));
+}
+
+#[test]
+fn test_hab_svc_load_env_overrides() {
+    habitat_core::locked_env_var!(HAB_SVC_LOAD_SVC_USER, lock_svc_user);
+    habitat_core::locked_env_var!(HAB_SVC_LOAD_PREFER_LEADER, lock_prefer_leader);
+    let svc_user = lock_svc_user();
+    svc_user.set("hab");
+    let prefer_leader = lock_prefer_leader();
+    prefer_leader.set("region:east,abc123");
+
+    let hab = Hab::try_from_iter_with_configopt(&["hab", "svc", "load", "core/redis"]).unwrap();
+    let load = extract_hab_svc_load(hab);
+    assert_eq!(load.shared_load.svc_user, Some(String::from("hab")));
+    assert_eq!(load.shared_load.prefer_leader,
+               vec![String::from("region:east"), String::from("abc123")]);
+
+    let hab = Hab::try_from_iter_with_configopt(&["hab",
+                                                  "svc",
+                                                  "load",
+                                                  "core/redis",
+                                                  "--svc-user",
+                                                  "redis"]).unwrap();
+    let load = extract_hab_svc_load(hab);
+    assert_eq!(load.shared_load.svc_user, Some(String::from("redis")));
+}
@@ -0,0 +1,21 @@
+use super::util::resolve_remote_sup;
+use configopt::ConfigOpt;
+use habitat_common::types::ListenCtlAddr;
+use structopt::StructOpt;
+
+/// Live view of the services of one or more Supervisors
+#[derive(ConfigOpt, StructOpt)]
+#[structopt(no_version)]
+pub struct Top {
+    /// Address to the Control Gateway of a Supervisor to watch. May be given more than once.
+    #[structopt(name = "REMOTE_SUP",
+                long = "remote-sup",
+                short = "r",
+                number_of_values = 1,
+                default_value = ListenCtlAddr::default_as_str(),
+                parse(try_from_str = resolve_remote_sup))]
+    pub remote_sup: Vec<ListenCtlAddr>,
+    /// How often to refresh the view, in seconds
+    #[structopt(name = "INTERVAL", long = "interval", short = "i", default_value = "2")]
+    pub interval:   u64,
+}
@@ -1,6 +1,9 @@
 use super::util::{CacheKeyPath,
                   ConfigOptCacheKeyPath};
+use crate::cli::{file_exists_or_stdin,
+                 KeyType};
 use configopt::ConfigOpt;
+use std::path::PathBuf;
 use structopt::StructOpt;
 
 #[derive(ConfigOpt, StructOpt)]
@@ -9,12 +12,45 @@ use structopt::StructOpt;
 pub enum User {
     /// Commands relating to Habitat user keys
     Key(Key),
+    /// Encrypts a file for another user, who can decrypt it with 'hab user decrypt'
+    Encrypt {
+        /// Name of the user key to encrypt for
+        #[structopt(name = "FOR", long = "for")]
+        for_user:       String,
+        /// Path to local file on disk (ex: /tmp/secret.txt, default: <stdin>)
+        #[structopt(name = "FILE", validator = file_exists_or_stdin)]
+        file:           Option<PathBuf>,
+        /// Name of the user key to encrypt with (default: $HAB_USER)
+        #[structopt(name = "USER", short = "u", long = "user")]
+        user:           Option<String>,
+        #[structopt(flatten)]
+        cache_key_path: CacheKeyPath,
+    },
+    /// Decrypts a file encrypted for a user with 'hab user encrypt'
+    Decrypt {
+        /// Path to local file on disk (ex: /tmp/secret.txt.box, default: <stdin>)
+        #[structopt(name = "FILE", validator = file_exists_or_stdin)]
+        file:           Option<PathBuf>,
+        #[structopt(flatten)]
+        cache_key_path: CacheKeyPath,
+    },
 }
 
 #[derive(ConfigOpt, StructOpt)]
 #[structopt(no_version)]
 /// Commands relating to Habitat users
 pub enum Key {
+    /// Outputs the latest user key contents to stdout
+    Export {
+        /// Name of the user key
+        #[structopt(name = "USER")]
+        user:           String,
+        /// Export either the 'public' or 'secret' key
+        #[structopt(name = "KEY_TYPE", short = "t", long = "type")]
+        key_type:       Option<KeyType>,
+        #[structopt(flatten)]
+        cache_key_path: CacheKeyPath,
+    },
     /// Generates a Habitat user key
     Generate {
         /// Name of the user key
@@ -23,4 +59,18 @@ pub enum Key {
         #[structopt(flatten)]
         cache_key_path: CacheKeyPath,
     },
+    /// Reads a stdin stream containing a public or secret user key contents and writes the key to
+    /// disk
+    Import {
+        #[structopt(flatten)]
+        cache_key_path: CacheKeyPath,
+    },
+    /// Lists the user keys on disk
+    List {
+        /// Only list the keys of this user
+        #[structopt(name = "USER")]
+        user:           Option<String>,
+        #[structopt(flatten)]
+        cache_key_path: CacheKeyPath,
+    },
 }
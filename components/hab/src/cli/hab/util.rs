@@ -3,7 +3,8 @@ use crate::{cli::valid_fully_qualified_ident,
 use configopt::{self,
                 ConfigOpt};
 use habitat_common::{cli_config::CliConfig,
-                     types::ListenCtlAddr};
+                     types::{self,
+                             ListenCtlAddr}};
 use habitat_core::{crypto::CACHE_KEY_PATH_ENV_VAR,
                    env as henv,
                    fs as hab_core_fs,
@@ -179,7 +180,7 @@ pub struct RemoteSup {
                 long = "remote-sup",
                 short = "r",
                 default_value = ListenCtlAddr::default_as_str(),
-                parse(try_from_str = ListenCtlAddr::resolve_listen_ctl_addr))]
+                parse(try_from_str = resolve_remote_sup))]
     #[serde(default)]
     remote_sup: ListenCtlAddr,
 }
@@ -188,15 +189,19 @@ impl RemoteSup {
     pub fn to_listen_ctl_addr(&self) -> ListenCtlAddr { self.remote_sup }
 }
 
+/// Resolve the Supervisor given to `--remote-sup`, by the name it was added with by `hab remote
+/// add` or by its address.
+pub fn resolve_remote_sup(input: &str) -> habitat_common::error::Result<ListenCtlAddr> {
+    let address = CliConfig::cache().remotes
+                                    .get(input)
+                                    .map_or(input, |remote| remote.address.as_str());
+    ListenCtlAddr::resolve_listen_ctl_addr(address)
+}
+
 pub fn socket_addr_with_default_port<S: AsRef<str>>(addr: S,
                                                     default_port: u16)
                                                     -> io::Result<SocketAddr> {
-    let addr = addr.as_ref();
-    let mut iter = if addr.find(':').is_some() {
-        addr.to_socket_addrs()
-    } else {
-        (addr, default_port).to_socket_addrs()
-    }?;
+    let mut iter = types::with_default_port(addr.as_ref(), default_port).to_socket_addrs()?;
     // We expect exactly one address
     iter.next().ok_or_else(|| {
                    io::Error::new(io::ErrorKind::InvalidInput,
@@ -288,6 +293,12 @@ mod test {
                    "127.0.0.1:89".parse().expect(""));
         assert_eq!(socket_addr_with_default_port("1.2.3.4:1500", 89).unwrap(),
                    "1.2.3.4:1500".parse().expect(""));
+        assert_eq!(socket_addr_with_default_port("::1", 89).unwrap(),
+                   "[::1]:89".parse().expect(""));
+        assert_eq!(socket_addr_with_default_port("[::1]", 89).unwrap(),
+                   "[::1]:89".parse().expect(""));
+        assert_eq!(socket_addr_with_default_port("[fe80::1]:1500", 89).unwrap(),
+                   "[fe80::1]:1500".parse().expect(""));
         assert!(socket_addr_with_default_port("an_invalid_address", 89).is_err());
     }
 }
@@ -1,12 +1,15 @@
 pub mod bldr;
 pub mod cli;
+pub mod doctor;
 pub mod launcher;
 pub mod origin;
 pub mod pkg;
 pub mod plan;
+pub mod remote;
 pub mod ring;
 pub mod service;
 pub mod studio;
 pub mod sup;
 pub mod supportbundle;
+pub mod top;
 pub mod user;
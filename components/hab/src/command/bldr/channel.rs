@@ -3,3 +3,4 @@ pub mod demote;
 pub mod destroy;
 pub mod list;
 pub mod promote;
+pub mod promote_on_green;
@@ -0,0 +1,230 @@
+//! Promote the packages built by job groups to a channel as the groups finish.
+//!
+//! # Examples
+//!
+//! ```bash
+//! $ hab bldr channel promote-on-green core staging --policy promote.toml
+//! ```
+//!
+//! The recent job groups of the origin are checked on an interval, and the successful builds of
+//! each group that has finished are promoted once. Which builds are promoted is decided by a local
+//! policy file:
+//!
+//! ```toml
+//! # Also promote the successful builds of job groups in which other builds failed
+//! partial = false
+//! # Only promote these packages; every package is promoted when left out
+//! packages = ["core/redis", "core/nginx"]
+//! # Never promote these packages
+//! exclude = ["core/nginx/1.19.0"]
+//! # Only promote builds for these targets; every target is promoted when left out
+//! targets = ["x86_64-linux"]
+//! ```
+
+use crate::{api_client::{self,
+                         SchedulerResponse},
+            common::ui::{Status,
+                         UIWriter,
+                         UI},
+            error::{Error,
+                    Result},
+            hcore::{package::{Identifiable,
+                              PackageIdent,
+                              PackageTarget},
+                    ChannelIdent},
+            PRODUCT,
+            VERSION};
+use reqwest::StatusCode;
+use serde::{de,
+            Deserialize,
+            Deserializer};
+use std::{collections::HashSet,
+          fs,
+          path::Path,
+          str::FromStr,
+          time::Duration};
+use tokio::time;
+
+/// Which builds of a finished job group are promoted
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PromotionPolicy {
+    /// Whether the successful builds of a job group in which other builds failed are promoted
+    pub partial:  bool,
+    #[serde(deserialize_with = "idents")]
+    pub packages: Vec<PackageIdent>,
+    #[serde(deserialize_with = "idents")]
+    pub exclude:  Vec<PackageIdent>,
+    pub targets:  Vec<PackageTarget>,
+}
+
+fn idents<'de, D>(d: D) -> std::result::Result<Vec<PackageIdent>, D::Error>
+    where D: Deserializer<'de>
+{
+    Vec::<String>::deserialize(d)?.iter()
+                                  .map(|s| PackageIdent::from_str(s).map_err(de::Error::custom))
+                                  .collect()
+}
+
+impl PromotionPolicy {
+    pub fn read(path: &Path) -> Result<Self> { Ok(toml::from_str(&fs::read_to_string(path)?)?) }
+
+    /// The idents of the builds of `group` to promote, which is none until the group finishes.
+    pub fn promotable(&self, group: &SchedulerResponse) -> Vec<String> {
+        match group.state.as_str() {
+            "Complete" => {}
+            "Failed" if self.partial => {}
+            _ => return Vec::new(),
+        }
+        group.projects
+             .iter()
+             .filter(|p| p.state == "Success")
+             .filter(|p| self.allows(&p.ident, &p.target))
+             .map(|p| p.ident.clone())
+             .collect()
+    }
+
+    fn allows(&self, ident: &str, target: &str) -> bool {
+        let ident = match PackageIdent::from_str(ident) {
+            Ok(ident) => ident,
+            Err(_) => return false,
+        };
+        let target_allowed =
+            self.targets.is_empty()
+            || PackageTarget::from_str(target).map_or(false, |t| self.targets.contains(&t));
+        target_allowed
+        && (self.packages.is_empty() || self.packages.iter().any(|p| ident.satisfies(p)))
+        && !self.exclude.iter().any(|p| ident.satisfies(p))
+    }
+}
+
+/// The state of a job group that will not change anymore
+fn is_finished(group: &SchedulerResponse) -> bool {
+    matches!(group.state.as_str(), "Complete" | "Failed" | "Canceled")
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn start(ui: &mut UI,
+                   bldr_url: &str,
+                   token: &str,
+                   origin: &str,
+                   channel: &ChannelIdent,
+                   policy: &PromotionPolicy,
+                   limit: usize,
+                   interval: Duration,
+                   once: bool)
+                   -> Result<()> {
+    let api_client =
+        api_client::Client::new(bldr_url, PRODUCT, VERSION, None).map_err(Error::APIClient)?;
+    let mut handled = HashSet::new();
+
+    loop {
+        ui.status(Status::Determining,
+                  format!("finished job groups in {} origin", origin))?;
+        let groups = api_client.get_origin_schedule(origin, limit)
+                               .await
+                               .map_err(Error::ScheduleStatus)?;
+        // Groups are listed most recent first, so promote the oldest first to leave the most
+        // recent builds at the head of the channel
+        for group in groups.iter().rev() {
+            if handled.contains(&group.id) || !is_finished(group) {
+                continue;
+            }
+            let gid = group.id.parse::<u64>().map_err(Error::ParseIntError)?;
+            let group = api_client.get_schedule(gid as i64, true)
+                                  .await
+                                  .map_err(Error::ScheduleStatus)?;
+            let idents = policy.promotable(&group);
+            if idents.is_empty() {
+                ui.status(Status::Skipping,
+                          format!("job group {} ({}): no packages to promote",
+                                  group.id, group.state))?;
+            } else {
+                ui.status(Status::Promoting,
+                          format!("{} package(s) from job group {} to channel '{}'",
+                                  idents.len(),
+                                  group.id,
+                                  channel))?;
+                match api_client.job_group_promote_or_demote(gid, &idents, channel, token, true)
+                                .await
+                {
+                    Ok(_) => {
+                        ui.status(Status::Promoted,
+                                  format!("job group {} to channel '{}'", group.id, channel))?;
+                    }
+                    Err(api_client::Error::APIError(StatusCode::UNPROCESSABLE_ENTITY, _)) => {
+                        return Err(Error::JobGroupPromoteOrDemoteUnprocessable(true));
+                    }
+                    Err(e) => return Err(Error::JobGroupPromoteOrDemote(e, true)),
+                }
+            }
+            handled.insert(group.id);
+        }
+
+        if once {
+            return Ok(());
+        }
+        time::delay_for(interval).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api_client::Project;
+
+    fn project(ident: &str, state: &str, target: &str) -> Project {
+        Project { name:   ident.split('/').nth(1).unwrap().to_string(),
+                  ident:  ident.to_string(),
+                  state:  state.to_string(),
+                  job_id: "12345678".to_string(),
+                  target: target.to_string(), }
+    }
+
+    fn group(state: &str) -> SchedulerResponse {
+        SchedulerResponse { id:           "12345678".to_string(),
+                            state:        state.to_string(),
+                            projects:     vec![project("core/redis/6.2.5/20210915000000",
+                                                       "Success",
+                                                       "x86_64-linux"),
+                                               project("core/nginx/1.19.0/20210915000000",
+                                                       "Success",
+                                                       "x86_64-linux"),
+                                               project("core/nginx/1.19.0/20210915000001",
+                                                       "Success",
+                                                       "x86_64-windows"),
+                                               project("core/haproxy", "Failure", "x86_64-linux"),],
+                            created_at:   "2021-09-15T00:00:00Z".to_string(),
+                            project_name: "core/redis".to_string(),
+                            target:       "x86_64-linux".to_string(), }
+    }
+
+    #[test]
+    fn unfinished_and_failed_groups_are_not_promoted_by_default() {
+        let policy = PromotionPolicy::default();
+        assert!(policy.promotable(&group("Dispatching")).is_empty());
+        assert!(policy.promotable(&group("Failed")).is_empty());
+        assert_eq!(policy.promotable(&group("Complete")).len(), 3);
+    }
+
+    const POLICY: &str = r#"
+partial = true
+packages = ["core/redis", "core/nginx"]
+exclude = ["core/redis/6.2.5"]
+targets = ["x86_64-linux"]
+"#;
+
+    #[test]
+    fn policy_filters_packages_and_targets() {
+        let policy: PromotionPolicy = toml::from_str(POLICY).unwrap();
+        assert_eq!(policy.promotable(&group("Failed")),
+                   vec!["core/nginx/1.19.0/20210915000000".to_string()]);
+    }
+
+    #[test]
+    fn invalid_policies_are_rejected() {
+        assert!(toml::from_str::<PromotionPolicy>("partial = \"yes\"").is_err());
+        assert!(toml::from_str::<PromotionPolicy>("pakages = [\"core/redis\"]").is_err());
+        assert!(toml::from_str::<PromotionPolicy>("packages = [\"core\"]").is_err());
+    }
+}
@@ -1,4 +1,5 @@
 pub mod cancel;
+pub mod log;
 pub mod promote;
 pub mod start;
 pub mod status;
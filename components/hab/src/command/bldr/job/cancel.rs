@@ -9,44 +9,105 @@ use crate::{error::{Error,
             PRODUCT,
             VERSION};
 
+/// The states of a job group that still has builds to cancel
+const IN_PROGRESS_STATES: &[&str] = &["Queued", "Pending", "Dispatching"];
+
+/// Cancel the job groups with `group_ids`, or the in-progress job groups among the `limit` most
+/// recent ones created in `origin`. With `failing`, only the job groups in which a build has
+/// already failed are canceled.
+#[allow(clippy::too_many_arguments)]
 pub async fn start(ui: &mut UI,
                    bldr_url: &str,
-                   group_id: &str,
+                   group_ids: &[&str],
+                   origin: Option<&str>,
+                   limit: usize,
+                   failing: bool,
                    token: &str,
                    force: bool)
                    -> Result<()> {
-    if !force {
-        // TODO (SA): Show all the in-progress builds that will get canceled
-        let question = "If you choose to cancel a group build, all of the builds that are in \
-                        progress will be canceled. Is this what you want?";
+    let api_client =
+        api_client::Client::new(bldr_url, PRODUCT, VERSION, None).map_err(Error::APIClient)?;
 
-        if !ui.prompt_yes_no(question, Some(true))? {
-            ui.fatal("Aborted")?;
-            return Ok(());
+    let mut gids = Vec::new();
+    for group_id in group_ids {
+        match group_id.parse::<u64>() {
+            Ok(g) => gids.push(g),
+            Err(e) => {
+                ui.fatal(format!("Failed to parse group id: {}", e))?;
+                return Err(Error::ParseIntError(e));
+            }
         }
     }
-
-    let api_client =
-        api_client::Client::new(bldr_url, PRODUCT, VERSION, None).map_err(Error::APIClient)?;
-    let gid = match group_id.parse::<u64>() {
-        Ok(g) => g,
-        Err(e) => {
-            ui.fatal(format!("Failed to parse group id: {}", e))?;
-            return Err(Error::ParseIntError(e));
+    if let Some(origin) = origin {
+        ui.status(Status::Determining,
+                  format!("in-progress job groups in {} origin", origin))?;
+        for sr in api_client.get_origin_schedule(origin, limit)
+                            .await
+                            .map_err(Error::ScheduleStatus)?
+        {
+            if IN_PROGRESS_STATES.contains(&sr.state.as_str()) {
+                gids.push(sr.id.parse().map_err(Error::ParseIntError)?);
+            }
+        }
+    }
+    if failing {
+        let mut failing_gids = Vec::new();
+        for gid in gids {
+            let sr = api_client.get_schedule(gid as i64, true)
+                               .await
+                               .map_err(Error::ScheduleStatus)?;
+            if sr.projects.iter().any(|p| p.state == "Failure") {
+                failing_gids.push(gid);
+            }
         }
-    };
+        gids = failing_gids;
+    }
+
+    if gids.is_empty() {
+        ui.warn("No job groups to cancel")?;
+        return Ok(());
+    }
 
-    ui.status(Status::Canceling, format!("job group {}", group_id))?;
+    if !force {
+        // TODO (SA): Show all the in-progress builds that will get canceled
+        let question = if gids.len() == 1 {
+            String::from("If you choose to cancel a group build, all of the builds that are in \
+                          progress will be canceled. Is this what you want?")
+        } else {
+            format!("If you choose to cancel these {} group builds ({}), all of their builds that \
+                     are in progress will be canceled. Is this what you want?",
+                    gids.len(),
+                    gids.iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>()
+                        .join(", "))
+        };
 
-    match api_client.job_group_cancel(gid, token).await {
-        Ok(_) => {
-            ui.status(Status::Canceled, format!("job group {}", group_id))?;
+        if !ui.prompt_yes_no(&question, Some(true))? {
+            ui.fatal("Aborted")?;
+            return Ok(());
         }
+    }
 
-        Err(e) => {
-            return Err(Error::JobGroupCancel(e));
+    // Keep canceling the remaining job groups when one fails, so a single bad group does not
+    // leave the rest of a batch running
+    let mut first_err = None;
+    for gid in &gids {
+        ui.status(Status::Canceling, format!("job group {}", gid))?;
+        match api_client.job_group_cancel(*gid, token).await {
+            Ok(_) => {
+                ui.status(Status::Canceled, format!("job group {}", gid))?;
+            }
+            Err(e) if gids.len() == 1 => return Err(Error::JobGroupCancel(e)),
+            Err(e) => {
+                ui.warn(format!("Failed to cancel job group {}: {}", gid, e))?;
+                first_err.get_or_insert(e);
+            }
         }
-    };
+    }
 
-    Ok(())
+    match first_err {
+        Some(e) => Err(Error::JobGroupCancel(e)),
+        None => Ok(()),
+    }
 }
@@ -0,0 +1,46 @@
+use crate::{api_client,
+            common::ui::{Glyph,
+                         Status,
+                         UIWriter,
+                         UI},
+            error::{Error,
+                    Result},
+            PRODUCT,
+            VERSION};
+use std::time::Duration;
+use tokio::time;
+
+/// How long to wait between requests for new log output when following a job.
+const FOLLOW_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+pub async fn start(ui: &mut UI,
+                   bldr_url: &str,
+                   job_id: u64,
+                   token: Option<&str>,
+                   follow: bool)
+                   -> Result<()> {
+    let api_client =
+        api_client::Client::new(bldr_url, PRODUCT, VERSION, None).map_err(Error::APIClient)?;
+
+    ui.status(Status::Determining, format!("build log of job {}", job_id))?;
+
+    let mut start = 0;
+    loop {
+        let log = api_client.get_job_log(job_id, start, token)
+                            .await
+                            .map_err(Error::JobLog)?;
+        for line in &log.content {
+            println!("{}", line);
+        }
+        start = log.stop;
+
+        if !follow || log.is_complete {
+            if follow {
+                ui.status(Status::Custom(Glyph::CheckMark, String::from("Completed")),
+                          format!("job {}", job_id))?;
+            }
+            return Ok(());
+        }
+        time::delay_for(FOLLOW_POLL_INTERVAL).await;
+    }
+}
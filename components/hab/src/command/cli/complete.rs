@@ -0,0 +1,178 @@
+//! Candidates for the command-line completers generated by `hab cli completers`.
+//!
+//! The completers call back into `hab cli complete` to complete arguments whose values can't be
+//! known when the completer is generated: the services loaded in a Supervisor, the packages
+//! installed locally and the names of remote Supervisors. Completion must never get in the user's
+//! way, so failing to find candidates simply results in there being none.
+
+use crate::{cli::hab::util::resolve_remote_sup,
+            command::pkg::{list::{self,
+                                  ListingType},
+                           uninstall}};
+use clap::Shell;
+use habitat_common::cli_config::CliConfig;
+use std::collections::BTreeSet;
+
+/// Print the idents of the services loaded in the Supervisor given to `--remote-sup`, or in the
+/// local Supervisor, one per line.
+pub async fn services(remote_sup: Option<&str>) {
+    let services = match remote_sup {
+        Some(remote_sup) => {
+            match resolve_remote_sup(remote_sup) {
+                Ok(listen_ctl_addr) => uninstall::services_of(&listen_ctl_addr).await,
+                Err(_) => Ok(vec![]),
+            }
+        }
+        None => uninstall::supervisor_services().await,
+    };
+    for ident in services.unwrap_or_default()
+                         .iter()
+                         .map(ToString::to_string)
+                         .collect::<BTreeSet<_>>()
+    {
+        println!("{}", ident);
+    }
+}
+
+/// Print the names of the remote Supervisors added with `hab remote add`, one per line.
+pub fn remotes() {
+    for name in CliConfig::load().unwrap_or_default().remotes.keys() {
+        println!("{}", name);
+    }
+}
+
+/// Print the idents of the locally installed packages, one per line. Each package is listed as
+/// `origin/name` as well as by its fully qualified ident.
+pub fn idents() {
+    let packages = list::package_list(&ListingType::AllPackages).unwrap_or_default();
+    let short_idents = packages.iter()
+                               .map(|p| format!("{}/{}", p.origin, p.name))
+                               .collect::<BTreeSet<_>>();
+    for ident in short_idents {
+        println!("{}", ident);
+    }
+    for ident in &packages {
+        println!("{}", ident);
+    }
+}
+
+/// The part of the completer for `shell` which completes services, package idents and remote
+/// Supervisors, to be appended to the completer clap generates. There is none for PowerShell.
+pub fn dynamic_completer(shell: Shell) -> Option<&'static str> {
+    match shell {
+        Shell::Bash => Some(BASH_COMPLETER),
+        Shell::Zsh => Some(ZSH_COMPLETER),
+        Shell::Fish => Some(FISH_COMPLETER),
+        Shell::PowerShell | Shell::Elvish => None,
+    }
+}
+
+const BASH_COMPLETER: &str = r#"
+_hab_dynamic() {
+    local cur="${COMP_WORDS[COMP_CWORD]}"
+    local prev="${COMP_WORDS[COMP_CWORD-1]}"
+    case "${COMP_WORDS[1]}" in
+        config|file|sup|svc|top)
+            if [[ "${prev}" == "-r" || "${prev}" == "--remote-sup" ]]; then
+                COMPREPLY=( $(compgen -W "$(hab cli complete remotes 2>/dev/null)" -- "${cur}") )
+                return 0
+            fi
+            ;;
+    esac
+    local remote=() positionals=0 i
+    for (( i = 3; i < COMP_CWORD; i++ )); do
+        case "${COMP_WORDS[i]}" in
+            -r|--remote-sup)
+                remote=(--remote-sup "${COMP_WORDS[i+1]}")
+                (( i++ ))
+                ;;
+            -*)
+                ;;
+            *)
+                (( positionals++ ))
+                ;;
+        esac
+    done
+    if [[ ${COMP_CWORD} -ge 3 && ${positionals} -eq 0 && "${cur}" != -* ]]; then
+        local source=""
+        case "${COMP_WORDS[1]} ${COMP_WORDS[2]}" in
+            "svc start"|"svc stop"|"svc status"|"svc unload"|"svc update")
+                source="services"
+                ;;
+            "pkg binds"|"pkg binlink"|"pkg config"|"pkg dependencies"|"pkg env"|"pkg exec"|"pkg path"|"pkg uninstall")
+                source="idents"
+                remote=()
+                ;;
+        esac
+        if [[ -n "${source}" ]]; then
+            COMPREPLY=( $(compgen -W "$(hab cli complete ${source} "${remote[@]}" 2>/dev/null)" -- "${cur}") )
+            return 0
+        fi
+    fi
+    _hab "$@"
+}
+
+complete -F _hab_dynamic -o bashdefault -o default hab
+"#;
+
+const ZSH_COMPLETER: &str = r#"
+_hab_dynamic() {
+    if [[ ${words[CURRENT-1]} == (-r|--remote-sup) && ${words[2]} == (config|file|sup|svc|top) ]]; then
+        local -a remotes
+        remotes=(${(f)"$(hab cli complete remotes 2>/dev/null)"})
+        compadd -a remotes
+        return
+    fi
+    local -a remote
+    local positionals=0 i
+    for (( i = 4; i < CURRENT; i++ )); do
+        case ${words[i]} in
+            (-r|--remote-sup)
+                remote=(--remote-sup ${words[i+1]})
+                (( i++ ))
+                ;;
+            (-*)
+                ;;
+            (*)
+                (( positionals++ ))
+                ;;
+        esac
+    done
+    if (( CURRENT >= 4 && positionals == 0 )) && [[ ${words[CURRENT]} != -* ]]; then
+        local source
+        case "${words[2]} ${words[3]}" in
+            (svc\ (start|stop|status|unload|update))
+                source=services
+                ;;
+            (pkg\ (binds|binlink|config|dependencies|env|exec|path|uninstall))
+                source=idents
+                remote=()
+                ;;
+        esac
+        if [[ -n ${source} ]]; then
+            local -a candidates
+            candidates=(${(f)"$(hab cli complete ${source} ${remote} 2>/dev/null)"})
+            compadd -a candidates
+            return
+        fi
+    fi
+    _hab "$@"
+}
+
+compdef _hab_dynamic hab
+"#;
+
+const FISH_COMPLETER: &str = r#"
+function __hab_remote_sup
+    set -l tokens (commandline -opc)
+    set -l i (contains -i -- -r $tokens; or contains -i -- --remote-sup $tokens)
+    if test -n "$i"; and test $i -lt (count $tokens)
+        echo --remote-sup
+        echo $tokens[(math $i + 1)]
+    end
+end
+
+complete -c hab -n "__fish_seen_subcommand_from svc; and __fish_seen_subcommand_from start stop status unload update" -f -a "(hab cli complete services (__hab_remote_sup) 2>/dev/null)"
+complete -c hab -n "__fish_seen_subcommand_from pkg; and __fish_seen_subcommand_from binds binlink config dependencies env exec path uninstall" -f -a "(hab cli complete idents 2>/dev/null)"
+complete -c hab -n "__fish_seen_subcommand_from config file sup svc top" -s r -l remote-sup -x -a "(hab cli complete remotes 2>/dev/null)"
+"#;
@@ -0,0 +1,543 @@
+//! Diagnose common problems with the local Habitat installation.
+//!
+//! Every check produces a single finding, and a finding that isn't `ok` says what to do about
+//! it. The findings are also reported as a single JSON document when run with `--output json`, so
+//! the same checks can be used by monitoring and provisioning tools.
+
+use crate::{api_client::{self,
+                         Client},
+            common::{cli::CTL_SECRET_ENVVAR,
+                     cli_config::CliConfig,
+                     types::ListenCtlAddr,
+                     ui::{Status,
+                          UIWriter,
+                          UI},
+                     FeatureFlag},
+            error::{Error,
+                    Result},
+            hcore::{crypto::keys::{BuilderSecretEncryptionKey,
+                                   HostKey,
+                                   Key,
+                                   KeyCache,
+                                   OriginPublicEncryptionKey,
+                                   OriginSecretEncryptionKey,
+                                   PublicOriginSigningKey,
+                                   RingKey,
+                                   SecretOriginSigningKey,
+                                   ServicePublicEncryptionKey,
+                                   ServiceSecretEncryptionKey,
+                                   UserPublicEncryptionKey,
+                                   UserSecretEncryptionKey},
+                    env as henv,
+                    fs::FS_ROOT_PATH,
+                    os::process::{self,
+                                  Pid}},
+            protocol::{self,
+                       net::ErrCode},
+            sup_client::{SrvClient,
+                         SrvClientError},
+            PRODUCT,
+            VERSION};
+use chrono::Utc;
+use futures::stream::StreamExt;
+use serde_json::json;
+use std::{fs,
+          io,
+          path::Path,
+          str::FromStr};
+
+/// The file in the Supervisor's state directory holding the PID of the running Supervisor.
+const PROC_LOCK_FILE: &str = "LOCK";
+
+/// Name of the Launcher's process.
+#[cfg(target_os = "linux")]
+const LAUNCHER_PROCESS_NAME: &str = "hab-launch";
+
+/// Length in bytes of a ctl secret generated by the Supervisor.
+const CTL_SECRET_LEN: usize = 64;
+
+/// Free space under `/hab` below which packages will soon fail to install.
+const MIN_FREE_BYTES: u64 = 512 * 1024 * 1024;
+
+/// Percentage of free space under `/hab` below which a warning is given.
+const LOW_FREE_PERCENT: u64 = 10;
+
+/// How far the local clock may drift from Builder's, in seconds, before a warning is given.
+const MAX_CLOCK_SKEW_SECS: i64 = 60;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Outcome {
+    Ok,
+    Info,
+    Warning,
+    Failure,
+    Skipped,
+}
+
+#[derive(Debug, Serialize)]
+struct Finding {
+    check:   &'static str,
+    outcome: Outcome,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    remedy:  Option<String>,
+}
+
+impl Finding {
+    fn new(check: &'static str, outcome: Outcome, message: impl Into<String>) -> Self {
+        Finding { check,
+                  outcome,
+                  message: message.into(),
+                  remedy: None }
+    }
+
+    fn remedy(mut self, remedy: impl Into<String>) -> Self {
+        self.remedy = Some(remedy.into());
+        self
+    }
+}
+
+/// The outcome of the Control Gateway handshake with the Supervisor.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Handshake {
+    Accepted,
+    Rejected,
+    NotAttempted,
+}
+
+pub async fn start(ui: &mut UI,
+                   remote_sup: &ListenCtlAddr,
+                   bldr_url: &str,
+                   key_cache: &KeyCache,
+                   feature_flags: FeatureFlag)
+                   -> Result<()> {
+    let sup_root = protocol::sup_root(None);
+    let (supervisor, handshake) = check_supervisor(remote_sup).await;
+    let mut findings = vec![check_launcher(&sup_root),
+                            supervisor,
+                            check_ctl_secret(&sup_root, handshake),
+                            check_key_cache(key_cache),
+                            check_disk_space(&Path::new(&*FS_ROOT_PATH).join("hab")),];
+    findings.append(&mut check_builder(bldr_url).await);
+    findings.push(check_feature_flags(feature_flags));
+
+    for finding in &findings {
+        report(ui, finding)?;
+    }
+    ui.result(&json!({ "findings": findings }))?;
+
+    let failures = findings.iter()
+                           .filter(|f| f.outcome == Outcome::Failure)
+                           .count();
+    if failures > 0 {
+        Err(Error::DoctorFailures(failures))
+    } else {
+        Ok(())
+    }
+}
+
+fn report(ui: &mut UI, finding: &Finding) -> Result<()> {
+    let message = format!("{}: {}", finding.check, finding.message);
+    match finding.outcome {
+        Outcome::Ok => ui.status(Status::Verified, message)?,
+        Outcome::Info => ui.status(Status::Found, message)?,
+        Outcome::Skipped => ui.status(Status::Skipping, message)?,
+        Outcome::Warning | Outcome::Failure => ui.warn(message)?,
+    }
+    if let Some(remedy) = &finding.remedy {
+        ui.para(remedy)?;
+    }
+    Ok(())
+}
+
+/// Check that the Supervisor at `remote_sup` answers requests over its Control Gateway.
+async fn check_supervisor(remote_sup: &ListenCtlAddr) -> (Finding, Handshake) {
+    const CHECK: &str = "supervisor";
+    match SrvClient::request(remote_sup, protocol::ctl::SvcStatus::default()).await {
+        Ok(mut replies) => {
+            while replies.next().await.is_some() {}
+            (Finding::new(CHECK,
+                          Outcome::Ok,
+                          format!("The Supervisor at {} is answering requests", remote_sup)),
+             Handshake::Accepted)
+        }
+        Err(SrvClientError::NetErr(ref err))
+            if ErrCode::from_i32(err.code) == Some(ErrCode::Unauthorized) =>
+        {
+            (Finding::new(CHECK,
+                          Outcome::Failure,
+                          format!("The Supervisor at {} refused our ctl secret", remote_sup)),
+             Handshake::Rejected)
+        }
+        Err(SrvClientError::ConnectionRefused) => {
+            (Finding::new(CHECK,
+                          Outcome::Failure,
+                          format!("Nothing is listening at {}", remote_sup))
+             .remedy("Start a Supervisor with `hab sup run`, or give the address of a running \
+                      Supervisor's Control Gateway with --remote-sup."),
+             Handshake::NotAttempted)
+        }
+        Err(err) => {
+            (Finding::new(CHECK,
+                          Outcome::Failure,
+                          format!("Unable to contact the Supervisor at {}: {}", remote_sup, err)),
+             Handshake::NotAttempted)
+        }
+    }
+}
+
+/// Check that the Supervisor running on this host, if any, was started by the Launcher, which
+/// restarts it if it exits.
+fn check_launcher(sup_root: &Path) -> Finding {
+    match supervisor_pid(sup_root) {
+        Some(pid) if process::is_alive(pid) => launcher_of(pid),
+        _ => {
+            Finding::new("launcher",
+                         Outcome::Skipped,
+                         "No Supervisor is running on this host")
+        }
+    }
+}
+
+/// The PID recorded in the Supervisor's process lock file.
+fn supervisor_pid(sup_root: &Path) -> Option<Pid> {
+    let lock = fs::read_to_string(sup_root.join(PROC_LOCK_FILE)).ok()?;
+    match lock.lines().next()?.trim().parse::<Pid>() {
+        Ok(pid) if pid != 0 => Some(pid),
+        _ => None,
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn launcher_of(pid: Pid) -> Finding {
+    const CHECK: &str = "launcher";
+    match parent_process(pid) {
+        Some((parent, name)) if name == LAUNCHER_PROCESS_NAME => {
+            Finding::new(CHECK,
+                         Outcome::Ok,
+                         format!("The Supervisor (pid {}) is running under the Launcher (pid {})",
+                                 pid, parent))
+        }
+        Some((parent, name)) => {
+            Finding::new(CHECK,
+                         Outcome::Warning,
+                         format!("The Supervisor (pid {}) was started by {} (pid {}) rather than \
+                                  the Launcher",
+                                 pid, name, parent)).remedy("Start the Supervisor with `hab sup \
+                                                             run`, so the Launcher can restart it \
+                                                             if it exits.")
+        }
+        None => {
+            Finding::new(CHECK,
+                         Outcome::Skipped,
+                         format!("Unable to determine the parent of the Supervisor (pid {})",
+                                 pid))
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn launcher_of(pid: Pid) -> Finding {
+    Finding::new("launcher",
+                 Outcome::Skipped,
+                 format!("A Supervisor (pid {}) is running; its Launcher can only be checked on \
+                          Linux",
+                         pid))
+}
+
+/// The PID and name of the parent of process `pid`.
+#[cfg(target_os = "linux")]
+fn parent_process(pid: Pid) -> Option<(Pid, String)> {
+    let status = fs::read_to_string(format!("/proc/{}/status", pid)).ok()?;
+    let parent = status.lines()
+                       .find(|line| line.starts_with("PPid:"))?
+                       .trim_start_matches("PPid:")
+                       .trim()
+                       .parse::<Pid>()
+                       .ok()?;
+    let name = fs::read_to_string(format!("/proc/{}/comm", parent)).ok()?;
+    Some((parent, name.trim().to_string()))
+}
+
+/// Check the ctl secret used to authenticate with the Supervisor, in the order `SrvClient` looks
+/// for it.
+fn check_ctl_secret(sup_root: &Path, handshake: Handshake) -> Finding {
+    const CHECK: &str = "ctl-secret";
+    let secret_path = protocol::secret_key_path(sup_root);
+    let (secret, source) = if let Ok(secret) = henv::var(CTL_SECRET_ENVVAR) {
+        (secret, format!("the {} environment variable", CTL_SECRET_ENVVAR))
+    } else if let Some(secret) = CliConfig::load().ok().and_then(|config| config.ctl_secret) {
+        (secret, "cli.toml".to_string())
+    } else {
+        let secret = SrvClient::ctl_secret_key_from_file().unwrap_or_default();
+        (secret, secret_path.display().to_string())
+    };
+
+    if secret.is_empty() {
+        return Finding::new(CHECK,
+                            Outcome::Failure,
+                            format!("No ctl secret is set and {} doesn't exist",
+                                    secret_path.display())).remedy("Run `hab cli setup`, or run \
+                                                                    the Supervisor on this host \
+                                                                    once to generate a ctl \
+                                                                    secret.");
+    }
+    match handshake {
+        Handshake::Accepted => {
+            Finding::new(CHECK,
+                         Outcome::Ok,
+                         format!("The ctl secret from {} was accepted by the Supervisor",
+                                 source))
+        }
+        Handshake::Rejected => {
+            Finding::new(CHECK,
+                         Outcome::Failure,
+                         format!("The ctl secret from {} doesn't match the Supervisor's",
+                                 source)).remedy(format!("Set {} or `ctl_secret` in cli.toml to \
+                                                          the contents of {} on the Supervisor's \
+                                                          host.",
+                                                         CTL_SECRET_ENVVAR,
+                                                         secret_path.display()))
+        }
+        Handshake::NotAttempted => {
+            match base64::decode(&secret) {
+                Ok(bytes) if bytes.len() == CTL_SECRET_LEN => {
+                    Finding::new(CHECK,
+                                 Outcome::Ok,
+                                 format!("The ctl secret from {} is well formed", source))
+                }
+                _ => {
+                    Finding::new(CHECK,
+                                 Outcome::Warning,
+                                 format!("The ctl secret from {} doesn't look like one generated \
+                                          by a Supervisor",
+                                         source))
+                }
+            }
+        }
+    }
+}
+
+/// Check that every key in the key cache can be read, and that every secret origin signing key
+/// has its public key alongside it.
+fn check_key_cache(key_cache: &KeyCache) -> Finding {
+    const CHECK: &str = "key-cache";
+    let dir = key_cache.as_ref();
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => {
+            return Finding::new(CHECK,
+                                Outcome::Skipped,
+                                format!("The key cache {} doesn't exist yet", dir.display()));
+        }
+        Err(err) => {
+            return Finding::new(CHECK,
+                                Outcome::Failure,
+                                format!("Unable to read the key cache {}: {}",
+                                        dir.display(),
+                                        err));
+        }
+    };
+
+    let mut keys = 0;
+    let mut unreadable = Vec::new();
+    let mut unpaired = Vec::new();
+    for path in entries.filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                       .filter(|path| path.is_file())
+    {
+        let name = path.file_name()
+                       .map(|name| name.to_string_lossy().into_owned())
+                       .unwrap_or_default();
+        let content = fs::read_to_string(&path).unwrap_or_default();
+        let valid = if name.ends_with(".sig.key") {
+            match content.parse::<SecretOriginSigningKey>() {
+                Ok(key) => {
+                    if key_cache.public_signing_key(key.named_revision()).is_err() {
+                        unpaired.push(key.named_revision().to_string());
+                    }
+                    true
+                }
+                Err(_) => false,
+            }
+        } else if name.ends_with(".box.key") {
+            parses::<OriginSecretEncryptionKey>(&content)
+            || parses::<ServiceSecretEncryptionKey>(&content)
+            || parses::<UserSecretEncryptionKey>(&content)
+            || parses::<BuilderSecretEncryptionKey>(&content)
+        } else if name.ends_with(".sym.key") {
+            parses::<RingKey>(&content)
+        } else if name.ends_with(".host.key") {
+            parses::<HostKey>(&content)
+        } else if name.ends_with(".pub") {
+            parses::<PublicOriginSigningKey>(&content)
+            || parses::<OriginPublicEncryptionKey>(&content)
+            || parses::<ServicePublicEncryptionKey>(&content)
+            || parses::<UserPublicEncryptionKey>(&content)
+        } else {
+            continue;
+        };
+        keys += 1;
+        if !valid {
+            unreadable.push(name);
+        }
+    }
+
+    if !unreadable.is_empty() {
+        Finding::new(CHECK,
+                     Outcome::Failure,
+                     format!("Unable to parse {} of the {} keys in {}: {}",
+                             unreadable.len(),
+                             keys,
+                             dir.display(),
+                             unreadable.join(", "))).remedy("Remove the damaged keys and download \
+                                                             or import them again.")
+    } else if !unpaired.is_empty() {
+        Finding::new(CHECK,
+                     Outcome::Warning,
+                     format!("No public key was found for the secret origin signing keys {}",
+                             unpaired.join(", "))).remedy("Download the public keys with `hab \
+                                                           origin key download <ORIGIN> \
+                                                           <REVISION>`.")
+    } else {
+        Finding::new(CHECK,
+                     Outcome::Ok,
+                     format!("All {} keys in {} are readable", keys, dir.display()))
+    }
+}
+
+fn parses<K: FromStr>(content: &str) -> bool { content.parse::<K>().is_ok() }
+
+/// Check that there is room under `/hab` to install packages.
+fn check_disk_space(path: &Path) -> Finding {
+    const CHECK: &str = "disk-space";
+    let path = if path.exists() {
+        path
+    } else {
+        Path::new(&*FS_ROOT_PATH)
+    };
+    match free_space(path) {
+        Ok(Some((available, total))) => {
+            let message = format!("{} MiB of {} MiB is available under {}",
+                                  available / 1024 / 1024,
+                                  total / 1024 / 1024,
+                                  path.display());
+            if available < MIN_FREE_BYTES {
+                Finding::new(CHECK, Outcome::Failure, message).remedy("Free some space, for \
+                                                                       example by uninstalling \
+                                                                       old releases of packages \
+                                                                       with `hab pkg uninstall`.")
+            } else if total > 0 && available * 100 / total < LOW_FREE_PERCENT {
+                Finding::new(CHECK, Outcome::Warning, message)
+            } else {
+                Finding::new(CHECK, Outcome::Ok, message)
+            }
+        }
+        Ok(None) => {
+            Finding::new(CHECK,
+                         Outcome::Skipped,
+                         "Free space can't be checked on this platform")
+        }
+        Err(err) => {
+            Finding::new(CHECK,
+                         Outcome::Failure,
+                         format!("Unable to determine the free space under {}: {}",
+                                 path.display(),
+                                 err))
+        }
+    }
+}
+
+/// The bytes available to unprivileged users and the total size of the filesystem holding
+/// `path`.
+#[cfg(unix)]
+#[allow(clippy::unnecessary_cast)]
+fn free_space(path: &Path) -> io::Result<Option<(u64, u64)>> {
+    use std::{ffi::CString,
+              mem,
+              os::unix::ffi::OsStrExt};
+
+    let path = CString::new(path.as_os_str().as_bytes())?;
+    let mut stat: libc::statvfs = unsafe { mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let fragment_size = stat.f_frsize as u64;
+    Ok(Some((stat.f_bavail as u64 * fragment_size, stat.f_blocks as u64 * fragment_size)))
+}
+
+#[cfg(windows)]
+fn free_space(_path: &Path) -> io::Result<Option<(u64, u64)>> { Ok(None) }
+
+/// Check that Builder can be reached, and that the local clock agrees with Builder's.
+async fn check_builder(bldr_url: &str) -> Vec<Finding> {
+    const CHECK: &str = "builder";
+    const CLOCK_CHECK: &str = "clock";
+    let status = match Client::new(bldr_url, PRODUCT, VERSION, None) {
+        Ok(client) => client.status().await,
+        Err(err) => Err(err),
+    };
+    match status {
+        Ok(builder_time) => {
+            let builder = Finding::new(CHECK,
+                                       Outcome::Ok,
+                                       format!("Builder at {} is answering requests", bldr_url));
+            let clock = match builder_time {
+                Some(builder_time) => {
+                    let skew = Utc::now().signed_duration_since(builder_time).num_seconds();
+                    if skew.abs() > MAX_CLOCK_SKEW_SECS {
+                        Finding::new(CLOCK_CHECK,
+                                     Outcome::Warning,
+                                     format!("The local clock is {} seconds {} Builder's",
+                                             skew.abs(),
+                                             if skew > 0 { "ahead of" } else { "behind" }))
+                        .remedy("Synchronize the clock, for example with NTP. Key revisions and \
+                                 TLS certificate validation depend on an accurate clock.")
+                    } else {
+                        Finding::new(CLOCK_CHECK,
+                                     Outcome::Ok,
+                                     format!("The local clock is within {} seconds of Builder's",
+                                             MAX_CLOCK_SKEW_SECS))
+                    }
+                }
+                None => {
+                    Finding::new(CLOCK_CHECK,
+                                 Outcome::Skipped,
+                                 "Builder didn't report its time")
+                }
+            };
+            vec![builder, clock]
+        }
+        Err(api_client::Error::MirrorUnsupported) => {
+            vec![Finding::new(CHECK,
+                              Outcome::Skipped,
+                              format!("{} is a mirror of Builder", bldr_url)),
+                 Finding::new(CLOCK_CHECK,
+                              Outcome::Skipped,
+                              "Mirrors don't report their time"),]
+        }
+        Err(err) => {
+            vec![Finding::new(CHECK,
+                              Outcome::Failure,
+                              format!("Unable to reach Builder at {}: {}", bldr_url, err))
+                 .remedy("Check network access and proxy settings, or give another Builder with \
+                          --url or HAB_BLDR_URL."),
+                 Finding::new(CLOCK_CHECK,
+                              Outcome::Skipped,
+                              "Builder couldn't be reached to compare clocks"),]
+        }
+    }
+}
+
+/// Report the feature flags enabled in the environment, which change the behavior of Habitat.
+fn check_feature_flags(feature_flags: FeatureFlag) -> Finding {
+    const CHECK: &str = "feature-flags";
+    let env_vars = feature_flags.env_vars();
+    if env_vars.is_empty() {
+        Finding::new(CHECK, Outcome::Ok, "No feature flags are enabled")
+    } else {
+        Finding::new(CHECK,
+                     Outcome::Info,
+                     format!("Enabled by {}", env_vars.join(", ")))
+    }
+}
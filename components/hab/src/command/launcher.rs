@@ -8,11 +8,13 @@ use crate::{cli::hab::sup::SupRun,
             exec,
             hcore::{crypto::init,
                     env as henv,
-                    fs::find_command,
+                    fs::{find_command,
+                         FS_ROOT_ENVVAR},
                     os::process,
                     package::PackageIdent},
             VERSION};
-use std::{ffi::OsString,
+use std::{env,
+          ffi::OsString,
           path::PathBuf,
           str::FromStr};
 
@@ -25,12 +27,23 @@ pub async fn start(ui: &mut UI, sup_run: SupRun, args: &[OsString]) -> Result<()
     let channel = sup_run.shared_load.channel;
     if henv::var(SUP_CMD_ENVVAR).is_err() {
         let version: Vec<&str> = VERSION.split('/').collect();
-        exec::command_from_min_pkg_with_channel(ui,
-                                                SUP_CMD,
-                                                &PackageIdent::from_str(&format!("{}/{}",
-                                                                                 SUP_PKG_IDENT,
-                                                                                 version[0]))?,
-                                                channel.clone()).await?;
+        let sup_cmd =
+            exec::command_from_min_pkg_with_channel(ui,
+                                                    SUP_CMD,
+                                                    &PackageIdent::from_str(&format!("{}/{}",
+                                                                                     SUP_PKG_IDENT,
+                                                                                     version[0]))?,
+                                                    channel.clone()).await?;
+        if sup_run.fs_root.is_some() {
+            // The Launcher only looks for the Supervisor package beneath its own filesystem
+            // root, which does not have it yet, so point it at the one we just found
+            env::set_var(SUP_CMD_ENVVAR, sup_cmd);
+        }
+    }
+    if let Some(fs_root) = &sup_run.fs_root {
+        // The Launcher, the Supervisor and its services all derive their paths from the
+        // filesystem root in the environment they inherit
+        env::set_var(FS_ROOT_ENVVAR, fs_root);
     }
     let command = match henv::var(LAUNCH_CMD_ENVVAR) {
         Ok(command) => PathBuf::from(command),
@@ -2,5 +2,8 @@ pub mod download;
 pub mod export;
 pub mod generate;
 pub mod import;
+pub mod revocations;
+pub mod revoke;
+pub mod sync;
 pub mod upload;
 pub mod upload_latest;
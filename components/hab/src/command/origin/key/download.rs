@@ -17,6 +17,7 @@ use habitat_core::{crypto::keys::{KeyCache,
                                   NamedRevision},
                    origin::Origin};
 use reqwest::StatusCode;
+use serde_json::json;
 
 #[allow(clippy::too_many_arguments)]
 pub async fn start(ui: &mut UI,
@@ -25,37 +26,55 @@ pub async fn start(ui: &mut UI,
                    revision: Option<&str>,
                    secret: bool,
                    encryption: bool,
+                   accept_new_key: bool,
                    token: Option<&str>,
                    key_cache: &KeyCache)
                    -> Result<()> {
     let api_client = Client::new(bldr_url, PRODUCT, VERSION, None)?;
 
-    if secret {
-        handle_secret(ui, &api_client, origin, token, key_cache).await
+    let keys = if secret {
+        handle_secret(ui, &api_client, origin, token, key_cache).await?
     } else if encryption {
-        handle_encryption(ui, &api_client, origin, token, key_cache).await
+        handle_encryption(ui, &api_client, origin, token, key_cache).await?
     } else {
-        handle_public(ui, &api_client, origin, revision, token, key_cache).await
-    }
+        handle_public(ui,
+                      &api_client,
+                      origin,
+                      revision,
+                      accept_new_key,
+                      token,
+                      key_cache).await?
+    };
+    ui.result(&json!({ "origin": origin.to_string(),
+                       "keys": keys,
+                       "cache_path": key_cache.as_ref() }))?;
+    Ok(())
 }
 
 async fn handle_public(ui: &mut UI,
                        api_client: &BuilderAPIClient,
                        origin: &Origin,
                        revision: Option<&str>,
+                       accept_new_key: bool,
                        token: Option<&str>,
                        key_cache: &KeyCache)
-                       -> Result<()> {
+                       -> Result<Vec<String>> {
     match revision {
         Some(revision) => {
             let named_revision = format!("{}-{}", origin, revision).parse()?;
             ui.begin(format!("Downloading public origin key {}", named_revision))?;
-            match download_key(ui, api_client, &named_revision, token, key_cache).await {
+            match download_key(ui,
+                               api_client,
+                               &named_revision,
+                               accept_new_key,
+                               token,
+                               key_cache).await
+            {
                 Ok(()) => {
                     let msg = format!("Download of {} public origin key completed.",
                                       named_revision);
                     ui.end(msg)?;
-                    Ok(())
+                    Ok(vec![named_revision.to_string()])
                 }
                 Err(e) => Err(e),
             }
@@ -65,15 +84,22 @@ async fn handle_public(ui: &mut UI,
             match api_client.show_origin_keys(origin).await {
                 Ok(ref keys) if keys.is_empty() => {
                     ui.end(format!("No public keys for {}.", origin))?;
-                    Ok(())
+                    Ok(Vec::new())
                 }
                 Ok(keys) => {
+                    let mut downloaded = Vec::new();
                     for key in keys {
                         let named_revision = format!("{}-{}", key.origin, key.revision).parse()?;
-                        download_key(ui, api_client, &named_revision, token, key_cache).await?;
+                        download_key(ui,
+                                     api_client,
+                                     &named_revision,
+                                     accept_new_key,
+                                     token,
+                                     key_cache).await?;
+                        downloaded.push(named_revision.to_string());
                     }
                     ui.end(format!("Download of {} public origin keys completed.", &origin))?;
-                    Ok(())
+                    Ok(downloaded)
                 }
                 Err(e) => Err(Error::from(e)),
             }
@@ -86,16 +112,16 @@ async fn handle_secret(ui: &mut UI,
                        origin: &Origin,
                        token: Option<&str>,
                        key_cache: &KeyCache)
-                       -> Result<()> {
+                       -> Result<Vec<String>> {
     if token.is_none() {
         ui.end("No auth token found. You must pass a token to download secret keys.")?;
-        return Ok(());
+        return Ok(Vec::new());
     }
 
     ui.begin(format!("Downloading secret origin keys for {}", origin))?;
-    download_secret_key(ui, &api_client, origin, token.unwrap(), key_cache).await?; // unwrap is safe because we already checked it above
+    let key = download_secret_key(ui, &api_client, origin, token.unwrap(), key_cache).await?; // unwrap is safe because we already checked it above
     ui.end(format!("Download of {} secret origin keys completed.", origin))?;
-    Ok(())
+    Ok(vec![key])
 }
 
 async fn handle_encryption(ui: &mut UI,
@@ -103,16 +129,17 @@ async fn handle_encryption(ui: &mut UI,
                            origin: &Origin,
                            token: Option<&str>,
                            key_cache: &KeyCache)
-                           -> Result<()> {
+                           -> Result<Vec<String>> {
     if token.is_none() {
         ui.end("No auth token found. You must pass a token to download secret keys.")?;
-        return Ok(());
+        return Ok(Vec::new());
     }
 
     ui.begin(format!("Downloading public encryption origin key for {}", origin))?;
-    download_public_encryption_key(ui, &api_client, origin, token.unwrap(), key_cache).await?; // unwrap is safe because we already checked it above
+    let key =
+        download_public_encryption_key(ui, &api_client, origin, token.unwrap(), key_cache).await?; // unwrap is safe because we already checked it above
     ui.end(format!("Download of {} public encryption keys completed.", &origin))?;
-    Ok(())
+    Ok(vec![key])
 }
 
 pub async fn download_public_encryption_key(ui: &mut UI,
@@ -120,7 +147,8 @@ pub async fn download_public_encryption_key(ui: &mut UI,
                                             origin: &Origin,
                                             token: &str,
                                             key_cache: &KeyCache)
-                                            -> Result<()> {
+                                            -> Result<String> {
+    let mut key_name = String::new();
     retry_builder_api!(async {
         ui.status(Status::Downloading, "latest public encryption key")?;
         let key_path = api_client.fetch_origin_public_encryption_key(origin,
@@ -130,6 +158,7 @@ pub async fn download_public_encryption_key(ui: &mut UI,
                                  .await?;
         ui.status(Status::Cached,
                   key_path.file_name().unwrap().to_str().unwrap() /* lol */)?;
+        key_name = key_path.file_stem().unwrap().to_string_lossy().into_owned();
         Ok::<_, habitat_api_client::error::Error>(())
     }).await
       .map_err(|e| {
@@ -137,7 +166,7 @@ pub async fn download_public_encryption_key(ui: &mut UI,
                                                              origin.to_string(),
                                                              Box::new(e)))
       })?;
-    Ok(())
+    Ok(key_name)
 }
 
 async fn download_secret_key(ui: &mut UI,
@@ -145,7 +174,8 @@ async fn download_secret_key(ui: &mut UI,
                              origin: &Origin,
                              token: &str,
                              key_cache: &KeyCache)
-                             -> Result<()> {
+                             -> Result<String> {
+    let mut key_name = String::new();
     retry_builder_api!(async {
         ui.status(Status::Downloading, "latest secret key")?;
         let key_path =
@@ -153,6 +183,7 @@ async fn download_secret_key(ui: &mut UI,
                       .await?;
         ui.status(Status::Cached,
                   key_path.file_name().unwrap().to_str().unwrap() /* lol */)?;
+        key_name = key_path.file_stem().unwrap().to_string_lossy().into_owned();
         Ok::<_, habitat_api_client::error::Error>(())
     }).await
       .map_err(|e| {
@@ -160,15 +191,16 @@ async fn download_secret_key(ui: &mut UI,
                                                              origin.to_string(),
                                                              Box::new(e)))
       })?;
-    Ok(())
+    Ok(key_name)
 }
 
-async fn download_key(ui: &mut UI,
-                      api_client: &BuilderAPIClient,
-                      named_revision: &NamedRevision,
-                      token: Option<&str>,
-                      key_cache: &KeyCache)
-                      -> Result<()> {
+pub async fn download_key(ui: &mut UI,
+                          api_client: &BuilderAPIClient,
+                          named_revision: &NamedRevision,
+                          accept_new_key: bool,
+                          token: Option<&str>,
+                          key_cache: &KeyCache)
+                          -> Result<()> {
     if key_cache.public_signing_key(named_revision).is_ok() {
         ui.status(Status::Using,
                   &format!("{} in {}", named_revision, key_cache.as_ref().display()))?;
@@ -191,6 +223,7 @@ async fn download_key(ui: &mut UI,
                                                            named_revision.to_string(),
                                                            Box::new(e)))
           })?;
+        key_cache.pin_public_signing_key(named_revision, accept_new_key)?;
         Ok(())
     }
 }
@@ -0,0 +1,47 @@
+use crate::{api_client::Client,
+            common::ui::{Status,
+                         UIWriter,
+                         UI},
+            error::{Error,
+                    Result},
+            PRODUCT,
+            VERSION};
+use habitat_core::{crypto::{keys::KeyCache,
+                            revocation::{self,
+                                         SignedRevocationList}},
+                   origin::Origin};
+use std::{fs,
+          path::Path};
+
+pub async fn start(ui: &mut UI,
+                   bldr_url: &str,
+                   origin: &Origin,
+                   file: Option<&Path>,
+                   key_cache: &KeyCache)
+                   -> Result<()> {
+    ui.begin(format!("Importing the revocation list of {}", origin))?;
+    let json = match file {
+        Some(path) => fs::read_to_string(path)?,
+        None => {
+            let api_client = Client::new(bldr_url, PRODUCT, VERSION, None)?;
+            ui.status(Status::Downloading,
+                      format!("revocation list of {}", origin))?;
+            api_client.get_origin_revocations(origin).await?
+        }
+    };
+    let signed = SignedRevocationList::from_json(&json)?;
+    if signed.list.origin != origin.as_ref() {
+        return Err(Error::ArgumentError(format!("Expected the revocation \
+                                                 list of {}, but got the \
+                                                 list of {}",
+                                                origin, signed.list.origin)));
+    }
+    let revoked = revocation::import(&signed, key_cache)?;
+    for key in &revoked {
+        ui.status(Status::Revoked, key)?;
+    }
+    ui.end(format!("Imported the revocation list of {}, revoking {} new key(s).",
+                   origin,
+                   revoked.len()))?;
+    Ok(())
+}
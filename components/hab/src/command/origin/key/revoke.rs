@@ -0,0 +1,66 @@
+use crate::{api_client::Client,
+            common::ui::{Status,
+                         UIWriter,
+                         UI},
+            error::{Error,
+                    Result},
+            PRODUCT,
+            VERSION};
+use habitat_core::{crypto::{keys::{Key,
+                                   KeyCache,
+                                   NamedRevision},
+                            revocation::{self,
+                                         RevocationList}},
+                   origin::Origin};
+use std::{fs,
+          path::Path};
+
+#[allow(clippy::too_many_arguments)]
+pub async fn start(ui: &mut UI,
+                   bldr_url: &str,
+                   origin: &Origin,
+                   revision: &str,
+                   reason: Option<&str>,
+                   output: Option<&Path>,
+                   token: Option<&str>,
+                   key_cache: &KeyCache)
+                   -> Result<()> {
+    let named_revision = format!("{}-{}", origin, revision).parse::<NamedRevision>()?;
+    ui.begin(format!("Revoking origin key {}", named_revision))?;
+
+    let mut list = RevocationList::from_cache(key_cache, origin)?;
+    list.revoke(&named_revision, reason)?;
+    let key = key_cache.latest_secret_origin_signing_key(origin)?;
+    if list.revoked.contains_key(&key.named_revision().to_string()) {
+        return Err(Error::ArgumentError(format!("The latest {} origin key \
+                                                 {} is revoked. Generate a \
+                                                 new one with `hab origin \
+                                                 key generate {}` to sign \
+                                                 the revocation list.",
+                                                origin,
+                                                key.named_revision(),
+                                                origin)));
+    }
+    ui.status(Status::Signing,
+              format!("revocation list of {} with {}",
+                      origin,
+                      key.named_revision()))?;
+    let signed = list.sign(&key)?;
+    revocation::import(&signed, key_cache)?;
+    ui.status(Status::Revoked, &named_revision)?;
+
+    let json = signed.to_json()?;
+    if let Some(output) = output {
+        fs::write(output, &json)?;
+        ui.status(Status::Created, output.display())?;
+    }
+    if let Some(token) = token {
+        let api_client = Client::new(bldr_url, PRODUCT, VERSION, None)?;
+        ui.status(Status::Uploading, format!("revocation list of {}", origin))?;
+        api_client.put_origin_revocations(origin, token, json)
+                  .await?;
+        ui.status(Status::Uploaded, format!("revocation list of {}", origin))?;
+    }
+    ui.end(format!("Revoked origin key {}.", named_revision))?;
+    Ok(())
+}
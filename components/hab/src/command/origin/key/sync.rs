@@ -0,0 +1,132 @@
+use super::download::download_key;
+use crate::{api_client::{self,
+                         retry_builder_api,
+                         APIFailure,
+                         BuilderAPIClient,
+                         Client,
+                         Error::APIClientError,
+                         API_RETRY_COUNT,
+                         API_RETRY_DELAY},
+            common::ui::{Status,
+                         UIWriter,
+                         UI},
+            error::Result,
+            PRODUCT,
+            VERSION};
+use habitat_core::{crypto::keys::{Key,
+                                  KeyCache,
+                                  PublicOriginSigningKey},
+                   origin::Origin};
+use reqwest::StatusCode;
+use serde_json::json;
+
+/// Bring the public signing keys of `origin` in the key cache and on Builder in line: every
+/// revision on Builder is downloaded, and every revision only in the cache is uploaded, given a
+/// token. The secret signing key revisions in the cache are reported, as they are what packages
+/// of the origin can be signed with here.
+pub async fn start(ui: &mut UI,
+                   bldr_url: &str,
+                   origin: &Origin,
+                   accept_new_key: bool,
+                   token: Option<&str>,
+                   key_cache: &KeyCache)
+                   -> Result<()> {
+    let api_client = Client::new(bldr_url, PRODUCT, VERSION, None)?;
+
+    ui.begin(format!("Synchronizing origin keys for {}", origin))?;
+    let remote_revisions = api_client.show_origin_keys(origin)
+                                     .await?
+                                     .into_iter()
+                                     .map(|key| format!("{}-{}", key.origin, key.revision))
+                                     .collect::<Vec<_>>();
+    let mut downloaded = Vec::new();
+    for revision in &remote_revisions {
+        download_key(ui,
+                     &api_client,
+                     &revision.parse()?,
+                     accept_new_key,
+                     token,
+                     key_cache).await?;
+        downloaded.push(revision.clone());
+    }
+
+    let mut uploaded = Vec::new();
+    for public_key in key_cache.public_origin_signing_key_revisions(origin)? {
+        let revision = public_key.named_revision().to_string();
+        if remote_revisions.contains(&revision) {
+            continue;
+        }
+        match token {
+            Some(token) => {
+                upload_key(ui, &api_client, &public_key, token, key_cache).await?;
+                uploaded.push(revision);
+            }
+            None => {
+                ui.warn(format!("Not uploading public origin key {}, which Builder doesn't \
+                                 have, without an auth token",
+                                revision))?;
+            }
+        }
+    }
+
+    let secret_keys = key_cache.secret_origin_signing_key_revisions(origin)?
+                               .iter()
+                               .map(|key| key.named_revision().to_string())
+                               .collect::<Vec<_>>();
+    if secret_keys.is_empty() {
+        ui.warn(format!("No origin private keys for {} in {}; packages of the origin can't be \
+                         signed here",
+                        origin,
+                        key_cache.as_ref().display()))?;
+    }
+    for secret_key in &secret_keys {
+        ui.status(Status::Found,
+                  format!("origin private key {} in {}",
+                          secret_key,
+                          key_cache.as_ref().display()))?;
+    }
+    ui.end(format!("Synchronized origin keys for {}: {} downloaded, {} uploaded.",
+                   origin,
+                   downloaded.len(),
+                   uploaded.len()))?;
+    ui.result(&json!({ "origin": origin.to_string(),
+                       "downloaded": downloaded,
+                       "uploaded": uploaded,
+                       "secret_keys": secret_keys,
+                       "cache_path": key_cache.as_ref() }))?;
+    Ok(())
+}
+
+async fn upload_key(ui: &mut UI,
+                    api_client: &BuilderAPIClient,
+                    public_key: &PublicOriginSigningKey,
+                    token: &str,
+                    key_cache: &KeyCache)
+                    -> Result<()> {
+    let public_keyfile = key_cache.path_in_cache(public_key);
+    retry_builder_api!(async {
+        ui.status(Status::Uploading, public_keyfile.display())?;
+        match api_client.put_origin_key(public_key.named_revision().name(),
+                                        public_key.named_revision().revision(),
+                                        &public_keyfile,
+                                        token,
+                                        ui.progress())
+                        .await
+        {
+            Ok(()) => ui.status(Status::Uploaded, public_key.named_revision())?,
+            Err(api_client::Error::APIError(StatusCode::CONFLICT, _)) => {
+                ui.status(Status::Using,
+                          format!("public key revision {} which already exists in the depot",
+                                  public_key.named_revision()))?;
+            }
+            Err(err) => return Err(err),
+        }
+        Ok::<_, habitat_api_client::error::Error>(())
+    }).await
+      .map_err(|e| {
+          APIClientError(APIFailure::UploadKeyFailed(API_RETRY_COUNT,
+                                                     public_key.named_revision().to_string(),
+                                                     Box::new(e)))
+      })?;
+    Ok(())
+}
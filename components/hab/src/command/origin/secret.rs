@@ -1,3 +1,5 @@
 pub mod delete;
+pub mod import;
 pub mod list;
+pub mod update;
 pub mod upload;
@@ -0,0 +1,51 @@
+use super::upload::{encryption_key,
+                    upload};
+use crate::{api_client::Client,
+            common::{ui::{Status,
+                          UIWriter,
+                          UI},
+                     util::env_file},
+            error::{Error,
+                    Result},
+            PRODUCT,
+            VERSION};
+use habitat_core::{crypto::keys::KeyCache,
+                   origin::Origin};
+use std::{fs,
+          path::Path};
+
+pub async fn start(ui: &mut UI,
+                   bldr_url: &str,
+                   token: &str,
+                   origin: &Origin,
+                   env_file: &Path,
+                   key_cache: &KeyCache)
+                   -> Result<()> {
+    let content = fs::read_to_string(env_file)?;
+    let secrets = env_file::parse(&content).map_err(|err| {
+                                               Error::ArgumentError(format!("Invalid env file \
+                                                                             {}: {}",
+                                                                            env_file.display(),
+                                                                            err))
+                                           })?;
+
+    let api_client = Client::new(bldr_url, PRODUCT, VERSION, None).map_err(Error::APIClient)?;
+    let encryption_key = encryption_key(ui, &api_client, token, origin, key_cache).await?;
+
+    ui.begin(format!("Importing {} secrets from {}",
+                     secrets.len(),
+                     env_file.display()))?;
+    for (key, secret) in &secrets {
+        upload(ui,
+               &api_client,
+               token,
+               origin,
+               key,
+               secret,
+               &encryption_key,
+               true).await?;
+    }
+    ui.status(Status::Uploaded,
+              format!("{} secrets for {}.", secrets.len(), origin))?;
+    Ok(())
+}
@@ -6,13 +6,27 @@ use crate::{api_client::Client,
                     Result},
             PRODUCT,
             VERSION};
-use habitat_core::origin::Origin;
+use habitat_core::{origin::Origin,
+                   util::text_render::TabularText};
 
-pub async fn start(ui: &mut UI, bldr_url: &str, token: &str, origin: &Origin) -> Result<()> {
+pub async fn start(ui: &mut UI,
+                   bldr_url: &str,
+                   token: &str,
+                   origin: &Origin,
+                   metadata: bool)
+                   -> Result<()> {
     let api_client = Client::new(bldr_url, PRODUCT, VERSION, None).map_err(Error::APIClient)?;
 
     ui.status(Status::Determining, format!("secrets for {}.", origin))?;
 
+    if metadata {
+        let secrets = api_client.list_origin_secrets_metadata(origin, token)
+                                .await
+                                .map_err(Error::APIClient)?;
+        println!("{}", secrets.as_tabbed()?);
+        return Ok(());
+    }
+
     match api_client.list_origin_secrets(origin, token).await {
         Ok(secrets) => {
             println!("{}", secrets.join("\n"));
@@ -0,0 +1,30 @@
+use super::upload::{encryption_key,
+                    upload};
+use crate::{api_client::Client,
+            common::ui::UI,
+            error::{Error,
+                    Result},
+            PRODUCT,
+            VERSION};
+use habitat_core::{crypto::keys::KeyCache,
+                   origin::Origin};
+
+pub async fn start(ui: &mut UI,
+                   bldr_url: &str,
+                   token: &str,
+                   origin: &Origin,
+                   key: &str,
+                   secret: &str,
+                   key_cache: &KeyCache)
+                   -> Result<()> {
+    let api_client = Client::new(bldr_url, PRODUCT, VERSION, None).map_err(Error::APIClient)?;
+    let encryption_key = encryption_key(ui, &api_client, token, origin, key_cache).await?;
+    upload(ui,
+           &api_client,
+           token,
+           origin,
+           key,
+           secret,
+           &encryption_key,
+           true).await
+}
@@ -1,4 +1,5 @@
-use crate::{api_client::Client,
+use crate::{api_client::{BuilderAPIClient,
+                         Client},
             command::origin::key::download::download_public_encryption_key,
             common::ui::{Status,
                          UIWriter,
@@ -7,7 +8,8 @@ use crate::{api_client::Client,
                     Result},
             PRODUCT,
             VERSION};
-use habitat_core::{crypto::keys::KeyCache,
+use habitat_core::{crypto::keys::{KeyCache,
+                                  OriginPublicEncryptionKey},
                    origin::Origin};
 
 pub async fn start(ui: &mut UI,
@@ -19,25 +21,60 @@ pub async fn start(ui: &mut UI,
                    key_cache: &KeyCache)
                    -> Result<()> {
     let api_client = Client::new(bldr_url, PRODUCT, VERSION, None).map_err(Error::APIClient)?;
+    let encryption_key = encryption_key(ui, &api_client, token, origin, key_cache).await?;
+    upload(ui,
+           &api_client,
+           token,
+           origin,
+           key,
+           secret,
+           &encryption_key,
+           false).await
+}
 
-    let encryption_key = match key_cache.latest_origin_public_encryption_key(origin) {
-        Ok(key) => key,
+/// Find the public encryption key of the origin, downloading it if it is not in the cache.
+pub(super) async fn encryption_key(ui: &mut UI,
+                                   api_client: &BuilderAPIClient,
+                                   token: &str,
+                                   origin: &Origin,
+                                   key_cache: &KeyCache)
+                                   -> Result<OriginPublicEncryptionKey> {
+    match key_cache.latest_origin_public_encryption_key(origin) {
+        Ok(key) => Ok(key),
         Err(_) => {
             debug!("Didn't find public encryption key in cache path");
-            download_public_encryption_key(ui, &api_client, origin, token, key_cache).await?;
-            key_cache.latest_origin_public_encryption_key(origin)?
+            download_public_encryption_key(ui, api_client, origin, token, key_cache).await?;
+            Ok(key_cache.latest_origin_public_encryption_key(origin)?)
         }
-    };
+    }
+}
 
+/// Encrypt and upload a single secret. If `replace` is set any existing secret with the same name
+/// is replaced.
+#[allow(clippy::too_many_arguments)]
+pub(super) async fn upload(ui: &mut UI,
+                           api_client: &BuilderAPIClient,
+                           token: &str,
+                           origin: &Origin,
+                           key: &str,
+                           secret: &str,
+                           encryption_key: &OriginPublicEncryptionKey,
+                           replace: bool)
+                           -> Result<()> {
     ui.status(Status::Encrypting, format!("value for key {}.", key))?;
     let anonymous_box = encryption_key.encrypt(secret.as_bytes());
     ui.status(Status::Encrypted, format!("{}=[REDACTED].", key))?;
 
     ui.status(Status::Uploading, format!("secret for key {}.", key))?;
 
-    api_client.create_origin_secret(origin, token, key, &anonymous_box)
-              .await
-              .map_err(Error::APIClient)?;
+    let result = if replace {
+        api_client.update_origin_secret(origin, token, key, &anonymous_box)
+                  .await
+    } else {
+        api_client.create_origin_secret(origin, token, key, &anonymous_box)
+                  .await
+    };
+    result.map_err(Error::APIClient)?;
 
     ui.status(Status::Uploaded, format!("secret for {}.", key))?;
 
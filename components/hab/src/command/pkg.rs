@@ -1,7 +1,10 @@
+pub mod attest;
 pub mod binlink;
 pub mod build;
 pub mod bulkupload;
 pub mod channels;
+#[cfg(unix)]
+pub mod dedup;
 pub mod delete;
 pub mod demote;
 pub mod dependencies;
@@ -9,10 +12,12 @@ pub mod download;
 pub mod env;
 pub mod exec;
 pub mod export;
+pub mod gc;
 pub mod hash;
 pub mod header;
 pub mod info;
 pub mod list;
+pub mod lock;
 pub mod path;
 pub mod promote;
 pub mod provides;
@@ -0,0 +1,41 @@
+use crate::{common::ui::{Status,
+                         UIWriter,
+                         UI},
+            error::Result};
+use habitat_core::crypto::{artifact,
+                           keys::KeyCache,
+                           provenance::{self,
+                                        ConfigSource,
+                                        DigestSet}};
+use std::path::Path;
+
+pub fn start(ui: &mut UI,
+             src: &Path,
+             builder_id: &str,
+             source_uri: Option<&str>,
+             source_revision: Option<&str>,
+             key_cache: &KeyCache)
+             -> Result<()> {
+    ui.begin(format!("Attesting to the provenance of {}", src.display()))?;
+    let header = artifact::get_artifact_header(src)?;
+    let key = key_cache.secret_signing_key(header.signer())?;
+    let config_source = if source_uri.is_some() || source_revision.is_some() {
+        let mut digest = DigestSet::new();
+        if let Some(revision) = source_revision {
+            digest.insert("sha1".to_string(), revision.to_string());
+        }
+        Some(ConfigSource { uri: source_uri.map(str::to_string),
+                            digest,
+                            entry_point: None })
+    } else {
+        None
+    };
+    ui.status(Status::Signing,
+              format!("provenance of {} built by {} with {}",
+                      src.display(),
+                      builder_id,
+                      header.signer()))?;
+    let path = provenance::attest(src, builder_id, config_source, &key)?;
+    ui.end(format!("Wrote provenance attestation {}.", path.display()))?;
+    Ok(())
+}
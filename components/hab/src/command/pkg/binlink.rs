@@ -4,9 +4,11 @@ use crate::{common::ui::{Status,
             error::{Error,
                     Result},
             hcore::{fs as hfs,
-                    package::{PackageIdent,
+                    package::{all_packages,
+                              PackageIdent,
                               PackageInstall}}};
-use std::{collections::BTreeMap,
+use std::{collections::{BTreeMap,
+                        BTreeSet},
           env,
           fs,
           path::{Path,
@@ -107,13 +109,41 @@ impl Binlink {
             if key == "PATH" {
                 value.push_str(";%PATH%");
             }
-            exports.push_str(&format!("SET {}={}\n", key, value));
+            exports.push_str(&format!("SET \"{}={}\"\n", key, value));
         }
 
         Ok(format!(include_str!("../../../static/template_binstub.\
                                  bat"),
                    target = self.target.display(),
-                   env = exports))
+                   env = exports,
+                   command = self.command()))
+    }
+
+    /// How the stub runs its target, with the arguments it was given. Batch files are `call`ed,
+    /// so that the stub gets their exit code, and PowerShell scripts are run by PowerShell.
+    #[cfg(windows)]
+    fn command(&self) -> String {
+        let extension = self.target
+                            .extension()
+                            .map(|ext| ext.to_string_lossy().to_lowercase());
+        match extension.as_deref() {
+            Some("bat") | Some("cmd") => format!("call \"{}\" %*", self.target.display()),
+            Some("ps1") => {
+                format!("powershell.exe -NoProfile -ExecutionPolicy Bypass -File \"{}\" %*",
+                        self.target.display())
+            }
+            _ => format!("\"{}\" %*", self.target.display()),
+        }
+    }
+
+    /// Whether the link points into another release of the package `ident`.
+    fn targets_other_release_of(&self, ident: &PackageIdent, fs_root_path: &Path) -> bool {
+        match package_of(&self.target, fs_root_path) {
+            Some(linked) => {
+                linked.origin == ident.origin && linked.name == ident.name && &linked != ident
+            }
+            None => false,
+        }
     }
 }
 
@@ -154,7 +184,10 @@ pub fn start(ui: &mut UI,
                                binlink.link.display(),);
     match Binlink::from_file(&binlink.link) {
         Ok(link) => {
-            if force && link.target != src {
+            // Links into other releases of the package are updated as it is upgraded
+            if (force || link.targets_other_release_of(pkg_install.ident(), fs_root_path))
+               && link.target != src
+            {
                 fs::remove_file(link.link)?;
                 binlink.link(pkg_install.environment_for_command()?)?;
                 ui.end(ui_binlinked)?;
@@ -232,6 +265,110 @@ pub fn binlink_all_in_pkg(ui: &mut UI,
     Ok(())
 }
 
+/// Binlink all binaries of the latest installed release of every installed package. Packages
+/// whose binaries can't be binlinked are skipped with a warning.
+pub fn binlink_all_installed(ui: &mut UI,
+                             dest_path: &Path,
+                             fs_root_path: &Path,
+                             force: bool)
+                             -> Result<()> {
+    let idents =
+        all_packages(&hfs::pkg_root_path(Some(fs_root_path)))?.into_iter()
+                                                              .map(|ident| {
+                                                                  PackageIdent::new(ident.origin,
+                                                                                    ident.name,
+                                                                                    None,
+                                                                                    None)
+                                                              })
+                                                              .collect::<BTreeSet<_>>();
+    for ident in idents {
+        if let Err(err) = binlink_all_in_pkg(ui, &ident, dest_path, fs_root_path, force) {
+            ui.warn(format!("Skipping the binaries of {}: {}", ident, err))?;
+        }
+    }
+    Ok(())
+}
+
+/// Remove the binlinks in `dest_path` that point into package releases which are no longer
+/// installed, returning how many were removed.
+pub fn prune(ui: &mut UI, dest_path: &Path, fs_root_path: &Path) -> Result<usize> {
+    let dst_path = fs_root_path.join(dest_path.strip_prefix("/")?);
+    ui.begin(format!("Pruning binlinks in {}", dst_path.display()))?;
+    let mut count = 0;
+    if dst_path.is_dir() {
+        for entry in fs::read_dir(&dst_path)? {
+            let binlink = match Binlink::from_file(&entry?.path()) {
+                Ok(binlink) => binlink,
+                Err(_) => continue,
+            };
+            if package_of(&binlink.target, fs_root_path).is_none()
+               || rooted_target(&binlink.target, fs_root_path)?.exists()
+            {
+                continue;
+            }
+            fs::remove_file(&binlink.link)?;
+            ui.status(Status::Deleted,
+                      format!("{} pointing at {}",
+                              binlink.link.display(),
+                              binlink.target.display()))?;
+            count += 1;
+        }
+    }
+    ui.end(format!("Pruned {} binlinks", count))?;
+    Ok(count)
+}
+
+/// The target of a binlink beneath `fs_root_path`. Binlinks point at their target as seen from
+/// `fs_root_path` on Unix, and at its full path on Windows.
+fn rooted_target(target: &Path, fs_root_path: &Path) -> Result<PathBuf> {
+    if cfg!(target_os = "windows") {
+        Ok(target.to_path_buf())
+    } else {
+        Ok(fs_root_path.join(target.strip_prefix("/")?))
+    }
+}
+
+/// The package release that the binlink target `target` belongs to, if any.
+fn package_of(target: &Path, fs_root_path: &Path) -> Option<PackageIdent> {
+    let target = rooted_target(target, fs_root_path).ok()?;
+    let parts = target.strip_prefix(&hfs::pkg_root_path(Some(fs_root_path)))
+                      .ok()?
+                      .iter()
+                      .take(4)
+                      .map(|part| part.to_string_lossy().into_owned())
+                      .collect::<Vec<_>>();
+    match parts.as_slice() {
+        [origin, name, version, release] => {
+            Some(PackageIdent::new(origin.clone(),
+                                   name.clone(),
+                                   Some(version.clone()),
+                                   Some(release.clone())))
+        }
+        _ => None,
+    }
+}
+
+/// The installed packages that the binlinks in `dest_path` point into.
+pub fn binlinked_packages(dest_path: &Path, fs_root_path: &Path) -> Result<Vec<PackageIdent>> {
+    let dst_path = fs_root_path.join(dest_path.strip_prefix("/")?);
+    let mut idents = Vec::new();
+    if !dst_path.is_dir() {
+        return Ok(idents);
+    }
+    for entry in fs::read_dir(&dst_path)? {
+        let binlink = match Binlink::from_file(&entry?.path()) {
+            Ok(binlink) => binlink,
+            Err(_) => continue,
+        };
+        if let Some(ident) = package_of(&binlink.target, fs_root_path) {
+            if !idents.contains(&ident) {
+                idents.push(ident);
+            }
+        }
+    }
+    Ok(idents)
+}
+
 fn is_dest_on_path(dest_dir: &Path) -> bool {
     if let Some(val) = env::var_os("PATH") {
         env::split_paths(&val).any(|p| p == dest_dir)
@@ -244,6 +381,8 @@ fn is_dest_on_path(dest_dir: &Path) -> bool {
 #[cfg(not(target_os = "macos"))]
 mod test {
     use super::{binlink_all_in_pkg,
+                binlink_all_installed,
+                prune,
                 start,
                 Binlink};
     use crate::{common::ui::UI,
@@ -443,6 +582,69 @@ mod test {
                                                                              .target);
     }
 
+    #[test]
+    fn binlink_all_installed_follows_upgrades() {
+        let rootfs = TempDir::new().unwrap();
+        let mut tools = HashMap::new();
+        tools.insert("bin", vec!["magicate.exe"]);
+        let old = fake_bin_pkg_install("acme/cooltools/1.2.3/20170101000000",
+                                       tools.clone(),
+                                       rootfs.path());
+        let new = fake_bin_pkg_install("acme/cooltools/1.2.4/20180101000000", tools, rootfs.path());
+        let dst_path = Path::new("/opt/bin");
+        let rootfs_bin_dir = rootfs.path().join("opt/bin");
+        #[cfg(target_os = "linux")]
+        let magicate_link = "magicate.exe";
+        #[cfg(target_os = "windows")]
+        let magicate_link = "magicate.bat";
+
+        let mut ui = UI::with_sinks();
+        start(&mut ui,
+              &old,
+              "magicate.exe",
+              &dst_path,
+              rootfs.path(),
+              false).unwrap();
+        binlink_all_installed(&mut ui, &dst_path, rootfs.path(), false).unwrap();
+
+        let target = Binlink::from_file(&rootfs_bin_dir.join(magicate_link)).unwrap()
+                                                                            .target;
+        assert!(target.ends_with("acme/cooltools/1.2.4/20180101000000/bin/magicate.exe"),
+                "{} should point into {}",
+                target.display(),
+                new);
+    }
+
+    #[test]
+    fn prune_removes_binlinks_into_uninstalled_packages() {
+        let rootfs = TempDir::new().unwrap();
+        let mut tools = HashMap::new();
+        tools.insert("bin", vec!["magicate.exe"]);
+        let uninstalled = fake_bin_pkg_install("acme/cooltools", tools, rootfs.path());
+        let mut tools = HashMap::new();
+        tools.insert("bin", vec!["hypnoanalyze.exe"]);
+        fake_bin_pkg_install("acme/securetools", tools, rootfs.path());
+        let dst_path = Path::new("/opt/bin");
+        let rootfs_bin_dir = rootfs.path().join("opt/bin");
+        #[cfg(target_os = "linux")]
+        let magicate_link = "magicate.exe";
+        #[cfg(target_os = "windows")]
+        let magicate_link = "magicate.bat";
+        #[cfg(target_os = "linux")]
+        let hypnoanalyze_link = "hypnoanalyze.exe";
+        #[cfg(target_os = "windows")]
+        let hypnoanalyze_link = "hypnoanalyze.bat";
+
+        let mut ui = UI::with_sinks();
+        binlink_all_installed(&mut ui, &dst_path, rootfs.path(), false).unwrap();
+        let uninstalled_path = hcore::fs::pkg_install_path(&uninstalled, Some(rootfs.path()));
+        fs::remove_dir_all(uninstalled_path).unwrap();
+
+        assert_eq!(prune(&mut ui, &dst_path, rootfs.path()).unwrap(), 1);
+        assert!(fs::symlink_metadata(rootfs_bin_dir.join(magicate_link)).is_err());
+        assert!(Binlink::from_file(&rootfs_bin_dir.join(hypnoanalyze_link)).is_ok());
+    }
+
     fn fake_bin_pkg_install<P>(ident: &str,
                                binaries: HashMap<&str, Vec<&str>>,
                                rootfs: P)
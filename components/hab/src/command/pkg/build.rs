@@ -6,12 +6,16 @@ use std::ffi::OsString;
 
 /// origins - origins whose secret signing keys should be made
 ///           available in the build
+/// mounts - host paths to mount into the Studio as `SRC:DST[:ro]`
+/// env_pass - names of environment variables to pass into the Studio
 #[allow(clippy::too_many_arguments)]
 pub async fn start(ui: &mut UI,
                    plan_context: &str,
                    root: Option<&str>,
                    src: Option<&str>,
                    origins: &[Origin],
+                   mounts: &[&str],
+                   env_pass: &[&str],
                    reuse: bool,
                    docker: bool)
                    -> Result<()> {
@@ -34,6 +38,15 @@ pub async fn start(ui: &mut UI,
         args.push(signing_key_names.into());
     }
 
+    for mount in mounts {
+        args.push("--mount".into());
+        args.push(mount.into());
+    }
+    for var in env_pass {
+        args.push("--env-pass".into());
+        args.push(var.into());
+    }
+
     args.push("build".into());
     if studio::native_studio_support() && reuse {
         args.push("-R".into());
@@ -0,0 +1,31 @@
+use crate::{common::ui::{Glyph,
+                         Status,
+                         UIWriter},
+            error::Result,
+            hcore::{fs::pkg_root_path,
+                    package::{all_packages,
+                              store::{Store,
+                                      StoreStats},
+                              PackageInstall}}};
+use std::path::Path;
+
+/// Enables deduplication beneath `fs_root_path` and deduplicates every installed package.
+pub fn start<U>(ui: &mut U, fs_root_path: &Path) -> Result<()>
+    where U: UIWriter
+{
+    ui.begin("Deduplicating installed packages")?;
+    let store = Store::create(fs_root_path)?;
+    let mut total = StoreStats::default();
+    for ident in all_packages(&pkg_root_path(Some(fs_root_path)))? {
+        let install = PackageInstall::load(&ident, Some(fs_root_path))?;
+        let stats = store.dedup(install.installed_path())?;
+        ui.status(Status::Custom(Glyph::CheckMark, String::from("Deduplicated")),
+                  format!("{} ({} files)", ident, stats.files))?;
+        total.files += stats.files;
+        total.bytes += stats.bytes;
+    }
+    ui.end(format!("Deduplicated {} files, freeing {} bytes. Packages installed from now on \
+                    are deduplicated as they are installed.",
+                   total.files, total.bytes))?;
+    Ok(())
+}
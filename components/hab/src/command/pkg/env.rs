@@ -2,25 +2,125 @@ use crate::{error::Result,
             hcore::package::{PackageIdent,
                              PackageInstall}};
 use std::{collections::BTreeMap,
-          path::Path};
+          path::Path,
+          result,
+          str::FromStr};
 
-pub fn start(ident: &PackageIdent, fs_root_path: &Path) -> Result<()> {
+/// How the runtime environment of a package is printed.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EnvFormat {
+    /// `export` statements for POSIX shells.
+    Sh,
+    /// `set -gx` statements for fish.
+    Fish,
+    /// `$env:` assignments for PowerShell.
+    Powershell,
+    /// A JSON object of the variables.
+    Json,
+    /// `KEY="VALUE"` lines, as read by `hab svc load --env-file`.
+    Dotenv,
+}
+
+impl EnvFormat {
+    pub const VARIANTS: &'static [&'static str] = &["sh", "fish", "powershell", "json", "dotenv"];
+}
+
+impl Default for EnvFormat {
+    #[cfg(unix)]
+    fn default() -> Self { EnvFormat::Sh }
+
+    #[cfg(windows)]
+    fn default() -> Self { EnvFormat::Powershell }
+}
+
+impl FromStr for EnvFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> result::Result<Self, Self::Err> {
+        match value {
+            "sh" => Ok(EnvFormat::Sh),
+            "fish" => Ok(EnvFormat::Fish),
+            "powershell" => Ok(EnvFormat::Powershell),
+            "json" => Ok(EnvFormat::Json),
+            "dotenv" => Ok(EnvFormat::Dotenv),
+            _ => Err(format!("Invalid environment format '{}'", value)),
+        }
+    }
+}
+
+pub fn start(ident: &PackageIdent, fs_root_path: &Path, format: EnvFormat) -> Result<()> {
     let pkg_install = PackageInstall::load(ident, Some(fs_root_path))?;
     let env = pkg_install.environment_for_command()?;
-    render_environment(env);
+    print!("{}", render_environment(&env, format)?);
     Ok(())
 }
 
-#[cfg(unix)]
-fn render_environment(env: BTreeMap<String, String>) {
-    for (key, value) in env.into_iter() {
-        println!("export {}=\"{}\"", key, value);
+fn render_environment(env: &BTreeMap<String, String>, format: EnvFormat) -> Result<String> {
+    if format == EnvFormat::Json {
+        return Ok(format!("{}\n", serde_json::to_string_pretty(env)?));
+    }
+    let mut rendered = String::new();
+    for (key, value) in env {
+        let line = match format {
+            EnvFormat::Sh => format!("export {}=\"{}\"", key, escape(value, "\\\"$`")),
+            EnvFormat::Fish => format!("set -gx {} '{}'", key, escape(value, "\\'")),
+            EnvFormat::Powershell => format!("$env:{}='{}'", key, value.replace('\'', "''")),
+            EnvFormat::Dotenv => {
+                format!("{}=\"{}\"",
+                        key,
+                        escape(value, "\\\"").replace('\n', "\\n")
+                                             .replace('\t', "\\t"))
+            }
+            EnvFormat::Json => unreachable!(),
+        };
+        rendered.push_str(&line);
+        rendered.push('\n');
+    }
+    Ok(rendered)
+}
+
+/// Escape each of `special` in `value` with a backslash.
+fn escape(value: &str, special: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if special.contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
     }
+    escaped
 }
 
-#[cfg(windows)]
-fn render_environment(env: BTreeMap<String, String>) {
-    for (key, value) in env.into_iter() {
-        println!("$env:{}=\"{}\"", key, value);
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::common::util::env_file;
+
+    fn env() -> BTreeMap<String, String> {
+        let mut env = BTreeMap::new();
+        env.insert("GREETING".to_string(), "it's \"$HOME\"\n\\o/".to_string());
+        env.insert("PATH".to_string(), "/hab/pkgs/core/redis/bin".to_string());
+        env
+    }
+
+    #[test]
+    fn renders_shell_statements() {
+        assert_eq!(render_environment(&env(), EnvFormat::Sh).unwrap(),
+                   "export GREETING=\"it's \\\"\\$HOME\\\"\n\\\\o/\"\nexport \
+                    PATH=\"/hab/pkgs/core/redis/bin\"\n");
+        assert_eq!(render_environment(&env(), EnvFormat::Fish).unwrap(),
+                   "set -gx GREETING 'it\\'s \"$HOME\"\n\\\\o/'\nset -gx PATH \
+                    '/hab/pkgs/core/redis/bin'\n");
+        assert_eq!(render_environment(&env(), EnvFormat::Powershell).unwrap(),
+                   "$env:GREETING='it''s \"$HOME\"\n\\o/'\n$env:PATH='/hab/pkgs/core/redis/bin'\n");
+    }
+
+    #[test]
+    fn renders_json_and_dotenv_that_read_back() {
+        let json = render_environment(&env(), EnvFormat::Json).unwrap();
+        assert_eq!(serde_json::from_str::<BTreeMap<String, String>>(&json).unwrap(),
+                   env());
+        let dotenv = render_environment(&env(), EnvFormat::Dotenv).unwrap();
+        assert_eq!(env_file::parse(&dotenv).unwrap(), env());
     }
 }
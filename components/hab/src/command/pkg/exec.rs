@@ -12,14 +12,52 @@ use std::{env,
 
 const PATH_KEY: &str = "PATH";
 
-pub fn start<T>(ident: &PackageIdent, command: T, args: &[OsString]) -> Result<()>
+/// The variables of the current environment kept in a clean one, which describe the user and
+/// terminal rather than how programs are found and behave.
+#[cfg(unix)]
+const CLEAN_ENV_KEYS: &[&str] =
+    &["HOME", "LANG", "LOGNAME", "SHELL", "TERM", "TMPDIR", "TZ", "USER"];
+#[cfg(windows)]
+const CLEAN_ENV_KEYS: &[&str] = &["APPDATA",
+                                  "COMPUTERNAME",
+                                  "ComSpec",
+                                  "HOMEDRIVE",
+                                  "HOMEPATH",
+                                  "LOCALAPPDATA",
+                                  "PATHEXT",
+                                  "SystemDrive",
+                                  "SystemRoot",
+                                  "TEMP",
+                                  "TMP",
+                                  "USERNAME",
+                                  "USERPROFILE",
+                                  "windir"];
+
+/// The environment a command runs in, besides the runtime environment of its package.
+pub enum Env {
+    /// The current environment, with the `PATH` of the package prepended to its own.
+    Current,
+    /// Only the variables in `CLEAN_ENV_KEYS`, the locale variables and the given ones are kept
+    /// from the current environment.
+    Clean(Vec<String>),
+}
+
+pub fn start<T>(ident: &PackageIdent, command: T, args: &[OsString], exec_env: &Env) -> Result<()>
     where T: Into<PathBuf>
 {
     let command = command.into();
     let pkg_install = PackageInstall::load(&ident, Some(&*FS_ROOT_PATH))?;
     let mut cmd_env = pkg_install.environment_for_command()?;
 
-    if let Some(path) = cmd_env.get(PATH_KEY) {
+    if let Env::Clean(keep) = exec_env {
+        for (key, _) in env::vars_os() {
+            let key = key.to_string_lossy();
+            if !is_kept_in_clean_env(&key, keep) {
+                debug!("Removing: {}", key);
+                env::remove_var(key.as_ref());
+            }
+        }
+    } else if let Some(path) = cmd_env.get(PATH_KEY) {
         if let Some(val) = env::var_os(PATH_KEY) {
             let mut paths: Vec<PathBuf> = env::split_paths(&path).collect();
             let mut os_paths = env::split_paths(&val).collect();
@@ -51,3 +89,35 @@ pub fn start<T>(ident: &PackageIdent, command: T, args: &[OsString]) -> Result<(
     process::become_command(command, args)?;
     Ok(())
 }
+
+fn is_kept_in_clean_env(key: &str, keep: &[String]) -> bool {
+    // Variable names are case-insensitive on Windows
+    let matches = |name: &str| {
+        if cfg!(windows) {
+            name.eq_ignore_ascii_case(key)
+        } else {
+            name == key
+        }
+    };
+    key.starts_with("LC_")
+    || CLEAN_ENV_KEYS.iter().any(|name| matches(name))
+    || keep.iter().any(|name| matches(name))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn clean_env_keeps_user_locale_and_given_variables() {
+        let keep = vec!["SSL_CERT_FILE".to_string()];
+        #[cfg(unix)]
+        assert!(is_kept_in_clean_env("HOME", &keep));
+        #[cfg(windows)]
+        assert!(is_kept_in_clean_env("SYSTEMROOT", &keep));
+        assert!(is_kept_in_clean_env("LC_ALL", &keep));
+        assert!(is_kept_in_clean_env("SSL_CERT_FILE", &keep));
+        assert!(!is_kept_in_clean_env("PATH", &keep));
+        assert!(!is_kept_in_clean_env("LD_LIBRARY_PATH", &keep));
+    }
+}
@@ -1,5 +1,7 @@
 pub mod cf;
+pub mod compose;
 pub mod container;
+pub mod ecs;
 mod export_common;
 pub mod mesos;
 pub mod tar;
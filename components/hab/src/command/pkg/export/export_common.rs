@@ -49,7 +49,7 @@ pub async fn start(ui: &mut UI,
             };
             debug!("Using export package `{}` with args `{:?}`", ident, args);
             let command = exec::command_from_min_pkg(ui, export_cmd, &ident).await?;
-            command::pkg::exec::start(&ident, command, args)?;
+            command::pkg::exec::start(&ident, command, args, &command::pkg::exec::Env::Current)?;
         }
     };
     Ok(())
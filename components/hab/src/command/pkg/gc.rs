@@ -0,0 +1,55 @@
+use super::{binlink,
+            uninstall::{self,
+                        UninstallHookMode},
+            ExecutionStrategy};
+use crate::{common::ui::{UIWriter,
+                         UI},
+            error::Result,
+            hcore::{fs::pkg_root_path,
+                    package::{all_packages,
+                              PackageIdent}}};
+use std::{collections::HashMap,
+          path::Path};
+
+/// Uninstalls every installed package release that is not one of the `keep_latest` latest
+/// releases of its package, loaded by the Supervisor, binlinked into `binlink_dir` or a
+/// dependency of one of those.
+pub async fn start(ui: &mut UI,
+                   binlink_dir: &Path,
+                   fs_root_path: &Path,
+                   keep_latest: usize,
+                   execution_strategy: ExecutionStrategy)
+                   -> Result<()> {
+    ui.begin("Removing unreferenced packages")?;
+    let mut keep = binlink::binlinked_packages(binlink_dir, fs_root_path)?;
+    keep.extend(latest_releases(fs_root_path, keep_latest)?);
+    let count = uninstall::uninstall_unreferenced(ui,
+                                                  &keep,
+                                                  fs_root_path,
+                                                  execution_strategy,
+                                                  UninstallHookMode::default()).await?;
+    match execution_strategy {
+        ExecutionStrategy::DryRun => {
+            ui.end(format!("Would uninstall {} packages (Dry run)", count))?
+        }
+        ExecutionStrategy::Run => ui.end(format!("Uninstalled {} packages", count))?,
+    }
+    Ok(())
+}
+
+/// The `count` latest installed releases of each package.
+fn latest_releases(fs_root_path: &Path, count: usize) -> Result<Vec<PackageIdent>> {
+    let mut by_name: HashMap<(String, String), Vec<PackageIdent>> = HashMap::new();
+    for ident in all_packages(&pkg_root_path(Some(fs_root_path)))? {
+        by_name.entry((ident.origin.clone(), ident.name.clone()))
+               .or_default()
+               .push(ident);
+    }
+    let mut latest = Vec::new();
+    for (_, mut idents) in by_name {
+        // Reverse sort the idents so the latest occur first in the list
+        idents.sort_unstable_by(|a, b| b.by_parts_cmp(a));
+        latest.extend(idents.into_iter().take(count));
+    }
+    Ok(latest)
+}
@@ -12,6 +12,7 @@ pub fn start(ui: &mut UI, src: &Path) -> Result<()> {
         println!("Format Version : {}", header.format());
         println!("Key Name       : {}", header.signer());
         println!("Hash Type      : {}", header.hash_type());
+        println!("Compression    : {}", header.compression());
         // This should just be "Signature", but this is a public
         // interface right now.
         println!("Raw Signature  : {}", header.encoded_signature());
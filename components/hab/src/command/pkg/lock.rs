@@ -0,0 +1,156 @@
+//! Pin the releases of packages and their runtime dependencies in a lockfile.
+//!
+//! # Examples
+//!
+//! ```bash
+//! $ hab pkg lock core/redis --channel stable --output app.lock
+//! $ hab svc load core/redis --lockfile app.lock
+//! ```
+//!
+//! The lockfile records the release at the head of the channel for each package, along with the
+//! releases of its runtime dependencies. Loading a service with the lockfile loads exactly that
+//! release, so every Supervisor runs the same bits however the channel moves afterwards.
+
+use crate::{api_client::Client,
+            common::ui::{Status,
+                         UIWriter,
+                         UI},
+            error::{Error,
+                    Result},
+            hcore::{package::{Identifiable,
+                              PackageIdent,
+                              PackageTarget},
+                    util,
+                    ChannelIdent},
+            PRODUCT,
+            VERSION};
+use std::{fs,
+          path::Path};
+
+/// The releases pinned by a lockfile
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Lockfile {
+    #[serde(default, rename = "package")]
+    pub packages: Vec<LockedPackage>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct LockedPackage {
+    /// The fully qualified release of the package
+    #[serde(with = "util::serde::string")]
+    pub ident:    PackageIdent,
+    pub target:   PackageTarget,
+    /// The blake2b checksum of the package's artifact
+    pub checksum: String,
+    /// The fully qualified releases of the package's runtime dependencies
+    #[serde(default)]
+    pub deps:     Vec<String>,
+}
+
+impl Lockfile {
+    pub fn read(path: &Path) -> Result<Self> { Ok(toml::from_str(&fs::read_to_string(path)?)?) }
+
+    /// The pinned release that satisfies `ident` for `target`, if there is one.
+    pub fn pinned(&self, ident: &PackageIdent, target: PackageTarget) -> Option<&PackageIdent> {
+        self.packages
+            .iter()
+            .find(|p| p.target == target && p.ident.satisfies(ident))
+            .map(|p| &p.ident)
+    }
+}
+
+/// Returns the release of `ident` pinned in the lockfile at `path`.
+pub fn pinned_ident(path: &Path,
+                    ident: &PackageIdent,
+                    target: PackageTarget)
+                    -> Result<PackageIdent> {
+    Lockfile::read(path)?.pinned(ident, target)
+                         .cloned()
+                         .ok_or_else(|| {
+                             Error::LockfilePackageNotFound(path.to_path_buf(), ident.clone())
+                         })
+}
+
+/// Resolve `idents` in `channel` and write a lockfile pinning them to `output`, or to stdout.
+pub async fn start(ui: &mut UI,
+                   bldr_url: &str,
+                   channel: &ChannelIdent,
+                   target: PackageTarget,
+                   idents: &[PackageIdent],
+                   token: Option<&str>,
+                   output: Option<&Path>)
+                   -> Result<()> {
+    let api_client = Client::new(bldr_url, PRODUCT, VERSION, None)?;
+    let mut lockfile = Lockfile::default();
+
+    ui.begin(format!("Pinning {} packages from channel {}", idents.len(), channel))?;
+    for ident in idents {
+        let package = api_client.show_package_metadata((ident, target), channel, token)
+                                .await?;
+        ui.status(Status::Found, &package.ident)?;
+        lockfile.packages.push(LockedPackage { ident: package.ident,
+                                               target,
+                                               checksum: package.checksum,
+                                               deps: package.tdeps
+                                                            .iter()
+                                                            .map(ToString::to_string)
+                                                            .collect() });
+    }
+
+    let contents = toml::to_string(&lockfile)?;
+    match output {
+        Some(path) => {
+            fs::write(path, contents)?;
+            ui.end(format!("Wrote lockfile {}", path.display()))?;
+        }
+        None => print!("{}", contents),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    const LOCKFILE: &str = r#"
+[[package]]
+ident = "core/redis/6.2.5/20210915000000"
+target = "x86_64-linux"
+checksum = "abc123"
+deps = ["core/glibc/2.29/20200305172459"]
+
+[[package]]
+ident = "core/redis/6.2.5/20210916000000"
+target = "x86_64-windows"
+checksum = "def456"
+"#;
+
+    #[test]
+    fn pinned_release_matches_ident_and_target() {
+        let lockfile: Lockfile = toml::from_str(LOCKFILE).unwrap();
+        let linux = PackageTarget::from_str("x86_64-linux").unwrap();
+        let windows = PackageTarget::from_str("x86_64-windows").unwrap();
+        let redis = PackageIdent::from_str("core/redis").unwrap();
+
+        assert_eq!(lockfile.pinned(&redis, linux),
+                   Some(&PackageIdent::from_str("core/redis/6.2.5/20210915000000").unwrap()));
+        assert_eq!(lockfile.pinned(&redis, windows),
+                   Some(&PackageIdent::from_str("core/redis/6.2.5/20210916000000").unwrap()));
+        assert_eq!(lockfile.pinned(&PackageIdent::from_str("core/redis/7.0").unwrap(), linux),
+                   None);
+        assert_eq!(lockfile.pinned(&PackageIdent::from_str("core/nginx").unwrap(), linux),
+                   None);
+    }
+
+    #[test]
+    fn lockfile_round_trips() {
+        let lockfile: Lockfile = toml::from_str(LOCKFILE).unwrap();
+        let written = toml::to_string(&lockfile).unwrap();
+        let read: Lockfile = toml::from_str(&written).unwrap();
+        assert_eq!(read.packages.len(), 2);
+        assert_eq!(read.packages[0].deps,
+                   vec!["core/glibc/2.29/20200305172459".to_string()]);
+        assert!(read.packages[1].deps.is_empty());
+    }
+}
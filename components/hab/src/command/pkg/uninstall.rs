@@ -8,8 +8,11 @@ use habitat_common::ui::UI;
 use habitat_core::package::PackageIdent;
 use std::path::Path;
 
-pub use uninstall_impl::{uninstall,
+pub use uninstall_impl::{services_of,
+                         supervisor_services,
+                         uninstall,
                          uninstall_all_but_latest,
+                         uninstall_unreferenced,
                          UninstallHookMode,
                          UninstallSafety};
 
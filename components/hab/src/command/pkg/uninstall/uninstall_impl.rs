@@ -10,16 +10,20 @@ use habitat_common::{package_graph::PackageGraph,
                      types::ListenCtlAddr,
                      ui::{Status,
                           UIWriter}};
+#[cfg(unix)]
+use habitat_core::package::store::Store;
 use habitat_core::{error as herror,
                    fs::{self as hfs,
                         FS_ROOT_PATH},
-                   package::{list::temp_package_directory,
+                   package::{all_packages,
+                             list::temp_package_directory,
                              Identifiable,
                              PackageIdent,
                              PackageInstall}};
 use habitat_sup_client::{SrvClient,
                          SrvClientError};
-use std::{fs,
+use std::{collections::HashSet,
+          fs,
           path::Path,
           str::FromStr};
 
@@ -117,12 +121,10 @@ pub async fn uninstall_all_but_latest<U>(ui: &mut U,
 /// 1. We find all packages on the filesystem and convert them into a graph
 /// 2. We find the fully qualified package ident and all its dependencies
 /// 3. We do a BFS on the graph to get the dependencies in order
-/// 4. We check if the specified package has any reverse deps
-///     4a. If there are, we throw an error
-///     4b. If not, we delete the package
-/// 5. For each dependency we check if there are any packages which depend on it
-///     5a. If there are, we skip it
-///     5b. If there are not, we delete it from disk and the graph
+/// 4. We check if the specified package has any reverse deps 4a. If there are, we throw an error
+///    4b. If not, we delete the package
+/// 5. For each dependency we check if there are any packages which depend on it 5a. If there are,
+///    we skip it 5b. If there are not, we delete it from disk and the graph
 ///
 /// `excludes` is a list of user-supplied `PackageIdent`s.
 #[allow(clippy::too_many_arguments)]
@@ -252,6 +254,88 @@ pub async fn uninstall_many<U>(ui: &mut U,
             }
         };
     }
+    prune_store(fs_root_path, execution_strategy)
+}
+
+/// Uninstall every installed package that is not in `keep`, loaded by the supervisor or a
+/// dependency of one of those. Packages are uninstalled once nothing else depends on them, so
+/// dependents go before their dependencies.
+///
+/// Returns the number of packages that were uninstalled
+pub async fn uninstall_unreferenced<U>(ui: &mut U,
+                                       keep: &[PackageIdent],
+                                       fs_root_path: &Path,
+                                       execution_strategy: ExecutionStrategy,
+                                       uninstall_hook_mode: UninstallHookMode)
+                                       -> Result<usize>
+    where U: UIWriter
+{
+    let mut graph = PackageGraph::from_root_path(fs_root_path)?;
+    let loaded_services = supervisor_services().await?;
+    for s in loaded_services.iter() {
+        ui.status(Status::Found, format!("loaded service {}", s))?;
+    }
+    let hab = PackageIdent::from_str("core/hab")?;
+
+    let installed = all_packages(&hfs::pkg_root_path(Some(fs_root_path)))?;
+    let mut referenced = HashSet::new();
+    for ident in installed.iter().filter(|i| {
+                                     i.satisfies(&hab)
+                                     || keep.iter().any(|k| i.satisfies(k))
+                                     || loaded_services.iter().any(|s| i.satisfies(s))
+                                 })
+    {
+        referenced.extend(graph.owned_ordered_deps(ident));
+        referenced.insert(ident.clone());
+    }
+
+    // Sort the idents so the latest occur last, for the same reason as in `uninstall_many`
+    let mut unreferenced = installed.into_iter()
+                                    .filter(|i| !referenced.contains(i))
+                                    .collect::<Vec<_>>();
+    unreferenced.sort_unstable_by(|a, b| a.by_parts_cmp(b));
+
+    let safety = UninstallSafetyImpl::SkipIfLoaded(&loaded_services);
+    let mut count = 0;
+    while !unreferenced.is_empty() {
+        let (ready, waiting): (Vec<_>, Vec<_>) =
+            unreferenced.into_iter()
+                        .partition(|i| graph.count_rdeps(i).unwrap_or(0) == 0);
+        // Unreachable as long as the dependency graph has no cycles
+        if ready.is_empty() {
+            break;
+        }
+        for ident in ready {
+            let install = PackageInstall::load(&ident, Some(fs_root_path))?;
+            maybe_delete(ui,
+                         &fs_root_path,
+                         &install,
+                         execution_strategy,
+                         &[],
+                         uninstall_hook_mode,
+                         safety).await?;
+            graph.remove(&ident);
+            count += 1;
+        }
+        unreferenced = waiting;
+    }
+    prune_store(fs_root_path, execution_strategy)?;
+    Ok(count)
+}
+
+/// Drop the deduplicated files that only uninstalled packages were using
+#[cfg(unix)]
+fn prune_store(fs_root_path: &Path, execution_strategy: ExecutionStrategy) -> Result<()> {
+    if let ExecutionStrategy::Run = execution_strategy {
+        if let Some(store) = Store::open(fs_root_path) {
+            store.prune()?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+fn prune_store(_fs_root_path: &Path, _execution_strategy: ExecutionStrategy) -> Result<()> {
     Ok(())
 }
 
@@ -264,16 +348,20 @@ fn launcher_is_running(fs_root_path: &Path) -> bool {
     pid_file_path.is_file()
 }
 
-async fn supervisor_services() -> Result<Vec<PackageIdent>> {
+/// The idents of the services loaded in the local Supervisor, if it is running.
+pub async fn supervisor_services() -> Result<Vec<PackageIdent>> {
     if !launcher_is_running(&*FS_ROOT_PATH) {
         return Ok(vec![]);
     }
+    services_of(&ListenCtlAddr::default()).await
+}
 
-    let listen_ctl_addr = ListenCtlAddr::default();
+/// The idents of the services loaded in the Supervisor at `listen_ctl_addr`.
+pub async fn services_of(listen_ctl_addr: &ListenCtlAddr) -> Result<Vec<PackageIdent>> {
     let msg = habitat_sup_protocol::ctl::SvcStatus::default();
 
     let mut out: Vec<PackageIdent> = vec![];
-    let mut response = SrvClient::request(&listen_ctl_addr, msg).await?;
+    let mut response = SrvClient::request(listen_ctl_addr, msg).await?;
     while let Some(message_result) = response.next().await {
         let reply = message_result?;
         match reply.message_id() {
@@ -396,7 +484,7 @@ async fn maybe_run_uninstall_hook<T>(ui: &mut T, package: &PackageInstall) -> Re
                           unqualified_ident))?;
         return Ok(());
     }
-    Ok(UninstallHook::find_run_and_error_for_status(ui, package).await?)
+    Ok(UninstallHook::find_run_and_error_for_status(ui, package, None).await?)
 }
 
 /// Delete empty parent directories from a given path. don't traverse above
@@ -28,7 +28,8 @@ use crate::{api_client::{self,
             VERSION};
 use habitat_core::{crypto::{artifact::get_artifact_header,
                             keys::{KeyCache,
-                                   KeyFile}},
+                                   KeyFile},
+                            provenance},
                    package::{PackageArchive,
                              PackageIdent,
                              PackageTarget},
@@ -180,8 +181,10 @@ async fn upload_into_depot(ui: &mut UI,
         };
     ui.status(Status::Uploaded, ident)?;
 
-    // Promote to additional_release_channel if specified
     if package_exists_in_target {
+        upload_provenance(ui, api_client, token, (ident, target), &archive.path).await?;
+
+        // Promote to additional_release_channel if specified
         if let Some(channel) = additional_release_channel.clone() {
             promote_to_channel(ui, api_client, (ident, target), channel, token).await?
         }
@@ -190,6 +193,37 @@ async fn upload_into_depot(ui: &mut UI,
     Ok(())
 }
 
+/// Uploads the provenance attestation next to the package's artifact, if there is one.
+async fn upload_provenance(ui: &mut UI,
+                           api_client: &BuilderAPIClient,
+                           token: &str,
+                           (ident, target): (&PackageIdent, PackageTarget),
+                           archive_path: &Path)
+                           -> Result<()> {
+    let path = provenance::provenance_path(archive_path);
+    if !path.is_file() {
+        return Ok(());
+    }
+    ui.status(Status::Uploading, path.display())?;
+    match api_client.put_package_provenance((ident, target), &path, token, ui.progress())
+                    .await
+    {
+        Ok(_) => ui.status(Status::Uploaded, format!("provenance of {}", ident))?,
+        Err(api_client::Error::APIError(StatusCode::CONFLICT, _)) => {
+            ui.status(Status::Using,
+                      format!("existing provenance of {} already on target", ident))?
+        }
+        Err(api_client::Error::APIError(StatusCode::NOT_FOUND, _))
+        | Err(api_client::Error::APIError(StatusCode::NOT_IMPLEMENTED, _)) => {
+            ui.warn(format!("The targeted depot doesn't store provenance attestations; {} was \
+                             not uploaded.",
+                            path.display()))?
+        }
+        Err(e) => return Err(Error::from(e)),
+    }
+    Ok(())
+}
+
 async fn promote_to_channel(ui: &mut UI,
                             api_client: &BuilderAPIClient,
                             (ident, target): (&PackageIdent, PackageTarget),
@@ -3,14 +3,29 @@ use crate::{common::ui::{Status,
                          UI},
             error::Result};
 use habitat_core::crypto::{artifact,
-                           keys::KeyCache};
+                           keys::KeyCache,
+                           provenance};
 use std::path::Path;
 
-pub fn start(ui: &mut UI, src: &Path, key_cache: &KeyCache) -> Result<()> {
+pub fn start(ui: &mut UI, src: &Path, key_cache: &KeyCache, check_provenance: bool) -> Result<()> {
     ui.begin(format!("Verifying artifact {}", &src.display()))?;
     let (name_with_rev, hash) = artifact::verify(src, &key_cache)?;
     ui.status(Status::Verified,
               format!("checksum {} signed with {}", &hash, &name_with_rev))?;
+    if check_provenance {
+        let (name_with_rev, statement) = provenance::verify(src, &key_cache)?;
+        ui.status(Status::Verified,
+                  format!("provenance signed with {}: built by {}",
+                          &name_with_rev, statement.predicate.builder.id))?;
+        if let Some(source) = statement.predicate.invocation.config_source {
+            ui.status(Status::Found,
+                      format!("source {} at revision {}",
+                              source.uri.as_deref().unwrap_or("<unknown>"),
+                              source.digest
+                                    .get("sha1")
+                                    .map_or("<unknown>", String::as_str)))?;
+        }
+    }
     ui.end(format!("Verified artifact {}.", &src.display()))?;
     Ok(())
 }
@@ -1,12 +1,14 @@
 use crate::{common::ui::{Status,
                          UIWriter,
                          UI},
-            error::Result};
+            error::{Error,
+                    Result}};
 use habitat_core::{origin::Origin,
                    package::PackageIdent};
 use handlebars::Handlebars;
 use std::{collections::HashMap,
           env,
+          fmt,
           fs::{canonicalize,
                create_dir_all,
                File,
@@ -14,7 +16,8 @@ use std::{collections::HashMap,
           io::{BufRead,
                BufReader,
                Write},
-          path::Path};
+          path::Path,
+          str::FromStr};
 
 const PLAN_TEMPLATE_SH: &str =
     include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/static/template_plan.sh"));
@@ -26,14 +29,129 @@ const GITIGNORE_TEMPLATE: &str =
     include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/static/template_gitignore"));
 const README_TEMPLATE: &str =
     include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/static/template_README.md"));
+const APP_DEFAULT_TOML_TEMPLATE: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"),
+                                                             "/static/template_app_default.toml"));
+const APP_ENV_TEMPLATE: &str =
+    include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/static/template_app_env"));
 
 const DEFAULT_PKG_VERSION: &str = "0.1.0";
 
+/// A language specific template generating a working plan, its default hooks and configuration
+/// instead of the generic plan skeleton.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PlanTemplate {
+    Go,
+    Java,
+    Node,
+    Python,
+    Rust,
+}
+
+impl PlanTemplate {
+    pub const VARIANTS: &'static [&'static str] = &["rust", "node", "python", "go", "java"];
+
+    fn as_str(self) -> &'static str {
+        match self {
+            PlanTemplate::Go => "go",
+            PlanTemplate::Java => "java",
+            PlanTemplate::Node => "node",
+            PlanTemplate::Python => "python",
+            PlanTemplate::Rust => "rust",
+        }
+    }
+
+    fn language(self) -> &'static str {
+        match self {
+            PlanTemplate::Go => "Go",
+            PlanTemplate::Java => "Java",
+            PlanTemplate::Node => "Node.js",
+            PlanTemplate::Python => "Python",
+            PlanTemplate::Rust => "Rust",
+        }
+    }
+
+    fn plan(self) -> &'static str {
+        match self {
+            PlanTemplate::Go => {
+                include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/static/template_plan_go.sh"))
+            }
+            PlanTemplate::Java => {
+                include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/static/template_plan_java.sh"))
+            }
+            PlanTemplate::Node => {
+                include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/static/template_plan_node.sh"))
+            }
+            PlanTemplate::Python => {
+                include_str!(concat!(env!("CARGO_MANIFEST_DIR"),
+                                     "/static/template_plan_python.sh"))
+            }
+            PlanTemplate::Rust => {
+                include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/static/template_plan_rust.sh"))
+            }
+        }
+    }
+
+    fn run_hook(self) -> &'static str {
+        match self {
+            PlanTemplate::Go => {
+                include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/static/template_hook_run_go"))
+            }
+            PlanTemplate::Java => {
+                include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/static/template_hook_run_java"))
+            }
+            PlanTemplate::Node => {
+                include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/static/template_hook_run_node"))
+            }
+            PlanTemplate::Python => {
+                include_str!(concat!(env!("CARGO_MANIFEST_DIR"),
+                                     "/static/template_hook_run_python"))
+            }
+            PlanTemplate::Rust => {
+                include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/static/template_hook_run_rust"))
+            }
+        }
+    }
+
+    /// The default port the application listens on.
+    fn port(self) -> u16 {
+        match self {
+            PlanTemplate::Go | PlanTemplate::Java | PlanTemplate::Rust => 8080,
+            PlanTemplate::Node => 8000,
+            PlanTemplate::Python => 5000,
+        }
+    }
+}
+
+impl FromStr for PlanTemplate {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value.to_lowercase().as_ref() {
+            "go" => Ok(PlanTemplate::Go),
+            "java" => Ok(PlanTemplate::Java),
+            "node" => Ok(PlanTemplate::Node),
+            "python" => Ok(PlanTemplate::Python),
+            "rust" => Ok(PlanTemplate::Rust),
+            _ => {
+                Err(Error::ArgumentError(format!("Unknown plan template \
+                                                  '{}', expected one of: {}",
+                                                 value,
+                                                 Self::VARIANTS.join(", "))))
+            }
+        }
+    }
+}
+
+impl fmt::Display for PlanTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { write!(f, "{}", self.as_str()) }
+}
+
 #[allow(clippy::too_many_arguments)]
 pub fn start(ui: &mut UI,
              origin: &Origin,
              minimal: bool,
              scaffolding_ident: Option<PackageIdent>,
+             template: Option<PlanTemplate>,
              maybe_name: Option<String>)
              -> Result<()> {
     ui.begin("Constructing a cozy habitat for your app...")?;
@@ -81,8 +199,21 @@ pub fn start(ui: &mut UI,
         data.insert("minimal".to_string(), "true".to_string());
     }
 
-    // We want to render the configured variables.
-    if cfg!(windows) {
+    if let Some(template) = template {
+        ui.status(Status::Using,
+                  format!("{} plan template '{}'", template.language(), template))?;
+        ui.para("")?;
+        data.insert("app_port".to_string(), template.port().to_string());
+        if template == PlanTemplate::Python {
+            data.insert("app_module".to_string(), data["pkg_name"].replace('-', "_"));
+        }
+    }
+
+    // We want to render the configured variables. Language templates are always Bash plans.
+    if let Some(template) = template {
+        let rendered_plan = handlebars.template_render(template.plan(), &data)?;
+        create_with_template(ui, &format!("{}/plan.sh", root), &rendered_plan)?;
+    } else if cfg!(windows) {
         let rendered_plan = handlebars.template_render(PLAN_TEMPLATE_PS1, &data)?;
         create_with_template(ui, &format!("{}/plan.ps1", root), &rendered_plan)?;
     } else {
@@ -91,7 +222,12 @@ pub fn start(ui: &mut UI,
     }
     ui.para("`plan.sh` is the foundation of your new habitat. It contains metadata, \
              dependencies, and tasks.")?;
-    let rendered_default_toml = handlebars.template_render(DEFAULT_TOML_TEMPLATE, &data)?;
+    let default_toml_template = if template.is_some() {
+        APP_DEFAULT_TOML_TEMPLATE
+    } else {
+        DEFAULT_TOML_TEMPLATE
+    };
+    let rendered_default_toml = handlebars.template_render(default_toml_template, &data)?;
     create_with_template(ui,
                          &format!("{}/default.toml", root),
                          &rendered_default_toml)?;
@@ -109,6 +245,10 @@ pub fn start(ui: &mut UI,
         ui.status(Status::Creating, format!("directory: {}", config_path))?;
         create_dir_all(&config_path)?;
     }
+    // The config and hook templates are rendered by the Supervisor, not here.
+    if template.is_some() {
+        create_with_template(ui, &format!("{}app.env", config_path), APP_ENV_TEMPLATE)?;
+    }
     ui.para("`/config/` contains configuration files for your app.")?;
 
     let hooks_path = format!("{}/hooks/", root);
@@ -118,6 +258,9 @@ pub fn start(ui: &mut UI,
         ui.status(Status::Creating, format!("directory: {}", hooks_path))?;
         create_dir_all(&hooks_path)?;
     }
+    if let Some(template) = template {
+        create_with_template(ui, &format!("{}run", hooks_path), template.run_hook())?;
+    }
     ui.para("`/hooks/` contains automation hooks into your habitat.")?;
 
     ui.para(
@@ -192,3 +335,33 @@ fn create_with_template(ui: &mut UI, location: &str, template: &str) -> Result<(
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plan_templates_render() {
+        let handlebars = Handlebars::new();
+        for variant in PlanTemplate::VARIANTS {
+            let template = PlanTemplate::from_str(variant).unwrap();
+            assert_eq!(template.to_string(), *variant);
+
+            let mut data = HashMap::new();
+            data.insert("pkg_name".to_string(), "my-app".to_string());
+            data.insert("pkg_origin".to_string(), "acme".to_string());
+            data.insert("pkg_version".to_string(), DEFAULT_PKG_VERSION.to_string());
+            data.insert("app_port".to_string(), template.port().to_string());
+            let plan = handlebars.template_render(template.plan(), &data).unwrap();
+            assert!(plan.contains("pkg_name=my-app\n"));
+            assert!(plan.contains("pkg_origin=acme\n"));
+            assert!(plan.contains("pkg_version=\"0.1.0\"\n"));
+            assert!(plan.contains("pkg_maintainer=\"The Habitat Maintainers"));
+
+            let default_toml = handlebars.template_render(APP_DEFAULT_TOML_TEMPLATE, &data)
+                                         .unwrap();
+            assert!(default_toml.contains(&format!("port = {}\n", template.port())));
+        }
+        assert!(PlanTemplate::from_str("cobol").is_err());
+    }
+}
@@ -12,7 +12,8 @@ use crate::{common::{templating::TemplateRenderer,
                      ui::{Status,
                           UIWriter,
                           UI}},
-            error::Result};
+            error::Result,
+            hcore::package::PackageIdent};
 
 #[allow(clippy::too_many_arguments)]
 pub fn start(ui: &mut UI,
@@ -20,6 +21,7 @@ pub fn start(ui: &mut UI,
              default_toml_path: &Path,
              user_toml_path: Option<&Path>,
              mock_data_path: Option<&Path>,
+             mock_census_path: Option<&Path>,
              print: bool,
              render: bool,
              render_dir: &Path,
@@ -68,6 +70,20 @@ pub fn start(ui: &mut UI,
     // merge default into data struct
     merge(&mut data, toml_to_json(&user_toml)?);
 
+    // expand the mock census into the `sys`, `svc` and `bind` data a Supervisor would provide
+    if let Some(path) = mock_census_path {
+        if !quiet {
+            ui.begin(format!("Importing mock census: {}", path.display()))?;
+        }
+        let mock_census = read_to_string(path)?;
+        let mock_census = if path.extension().map_or(false, |ext| ext == "json") {
+            serde_json::from_str(&mock_census)?
+        } else {
+            serde_json::to_value(mock_census.parse::<Value>()?)?
+        };
+        merge(&mut data, census_to_json(mock_census));
+    }
+
     // read mock data if provided
     let mock_data = match mock_data_path {
         Some(path) => {
@@ -118,15 +134,167 @@ pub fn start(ui: &mut UI,
     Ok(())
 }
 
-fn toml_to_json(cfg: &str) -> Result<Json> {
+pub fn toml_to_json(cfg: &str) -> Result<Json> {
     let toml_value = cfg.parse::<Value>()?;
     let toml_string = serde_json::to_string(&toml_value)?;
     let json = serde_json::from_str(&format!(r#"{{ "cfg": {} }}"#, &toml_string))?;
     Ok(json)
 }
 
+/// Expand a mock census into the shape of the `sys`, `svc` and `bind` template data. Members
+/// only need the fields the template uses; the others get the values of a lone local member.
+/// A member is the Supervisor's own when it has `me = true`, or else when it is the first one.
+pub fn census_to_json(mut census: Json) -> Json {
+    let svc = census.get_mut("svc")
+                    .map(Json::take)
+                    .unwrap_or_else(|| json!({}));
+    let service = svc.get("service")
+                     .and_then(Json::as_str)
+                     .unwrap_or("mock")
+                     .to_string();
+    let group = svc.get("group")
+                   .and_then(Json::as_str)
+                   .unwrap_or("default")
+                   .to_string();
+    let org = svc.get("org").cloned().unwrap_or(Json::Null);
+
+    let mut members = mock_members(&svc, &service, &group, &org);
+    if members.is_empty() {
+        members.push(mock_member(0, json!({}), &service, &group, &org));
+    }
+    let me_index = svc["members"].as_array()
+                                 .and_then(|m| m.iter().position(|m| m["me"] == json!(true)))
+                                 .unwrap_or(0);
+    let me = members[me_index].clone();
+    let leader = members.iter().find(|m| m["leader"] == json!(true)).cloned();
+    let update_leader = members.iter()
+                               .find(|m| m["update_leader"] == json!(true))
+                               .cloned();
+    let mut svc_json = json!({
+        "service": service,
+        "group": group,
+        "org": org,
+        "election_is_running": false,
+        "election_is_no_quorum": false,
+        "election_is_finished": leader.is_some(),
+        "update_election_is_running": false,
+        "update_election_is_no_quorum": false,
+        "update_election_is_finished": update_leader.is_some(),
+        "me": me,
+        "first": leader.clone().unwrap_or_else(|| members[0].clone()),
+        "leader": leader,
+        "update_leader": update_leader,
+    });
+    svc_json["members"] = Json::Array(active_members(members));
+
+    let mut sys = me["sys"].clone();
+    sys["version"] = json!(crate::VERSION);
+    sys["member_id"] = me["member_id"].clone();
+    sys["permanent"] = json!(false);
+    if let Some(overrides) = census.get_mut("sys").map(Json::take) {
+        merge(&mut sys, overrides);
+    }
+
+    let mut binds = serde_json::Map::new();
+    if let Some(Json::Object(bind)) = census.get_mut("bind").map(Json::take) {
+        for (name, bind_group) in bind {
+            let service = bind_group.get("service")
+                                    .and_then(Json::as_str)
+                                    .unwrap_or(&name)
+                                    .to_string();
+            let group = bind_group.get("group")
+                                  .and_then(Json::as_str)
+                                  .unwrap_or("default")
+                                  .to_string();
+            let org = bind_group.get("org").cloned().unwrap_or(Json::Null);
+            let members = mock_members(&bind_group, &service, &group, &org);
+            let leader = members.iter().find(|m| m["leader"] == json!(true)).cloned();
+            let first = leader.clone().or_else(|| members.first().cloned());
+            binds.insert(name,
+                         json!({
+                             "first": first,
+                             "leader": leader,
+                             "members": active_members(members),
+                         }));
+        }
+    }
+
+    json!({ "sys": sys, "svc": svc_json, "bind": binds })
+}
+
+/// The members of a mock service group, with the fields they don't give filled in.
+fn mock_members(group_data: &Json, service: &str, group: &str, org: &Json) -> Vec<Json> {
+    group_data.get("members")
+              .and_then(Json::as_array)
+              .map(|members| {
+                  members.iter()
+                         .enumerate()
+                         .map(|(i, m)| mock_member(i, m.clone(), service, group, org))
+                         .collect()
+              })
+              .unwrap_or_default()
+}
+
+fn mock_member(index: usize, mut member: Json, service: &str, group: &str, org: &Json) -> Json {
+    if let Json::Object(ref mut m) = member {
+        m.remove("me");
+    }
+    // The package is given as an identifier, but exposed both as one and broken into its parts
+    let pkg = member.get("pkg")
+                    .and_then(Json::as_str)
+                    .unwrap_or("")
+                    .parse::<PackageIdent>()
+                    .unwrap_or_default();
+    let mut full = json!({
+        "member_id": format!("{}-{}", service, index),
+        "pkg": pkg,
+        "package": pkg.to_string(),
+        "service": service,
+        "group": group,
+        "org": org,
+        "persistent": true,
+        "leader": false,
+        "follower": false,
+        "update_leader": false,
+        "update_follower": false,
+        "election_is_running": false,
+        "election_is_no_quorum": false,
+        "election_is_finished": false,
+        "update_election_is_running": false,
+        "update_election_is_no_quorum": false,
+        "update_election_is_finished": false,
+        "sys": {
+            "ip": "127.0.0.1",
+            "hostname": "localhost",
+            "gossip_ip": "127.0.0.1",
+            "gossip_port": 9638,
+            "http_gateway_ip": "127.0.0.1",
+            "http_gateway_port": 9631,
+            "ctl_gateway_ip": "127.0.0.1",
+            "ctl_gateway_port": 9632,
+        },
+        "alive": true,
+        "suspect": false,
+        "confirmed": false,
+        "departed": false,
+        "cfg": {},
+    });
+    if let Json::Object(ref mut m) = member {
+        m.remove("pkg");
+    }
+    merge(&mut full, member);
+    full
+}
+
+/// Like the Supervisor, only alive and suspect members are listed as members.
+fn active_members(members: Vec<Json>) -> Vec<Json> {
+    members.into_iter()
+           .filter(|m| m["alive"] == json!(true) || m["suspect"] == json!(true))
+           .collect()
+}
+
 // merge two Json structs
-fn merge(a: &mut Json, b: Json) {
+pub fn merge(a: &mut Json, b: Json) {
     if let Json::Object(a_map) = a {
         if let Json::Object(b_map) = b {
             for (k, v) in b_map {
@@ -155,3 +323,44 @@ fn create_with_template(ui: &mut UI,
     File::create(path).and_then(|mut file| file.write(template.as_bytes()))?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_alive_renders_mock_census() {
+        let census = r#"
+[sys]
+hostname = "web-1"
+
+[[svc.members]]
+me = true
+leader = true
+sys = { ip = "10.0.0.1" }
+
+[[svc.members]]
+alive = false
+sys = { ip = "10.0.0.2" }
+
+[[svc.members]]
+sys = { ip = "10.0.0.3" }
+
+[[bind.database.members]]
+pkg = "core/postgresql"
+cfg = { port = 5432 }
+sys = { ip = "10.0.1.1" }
+"#;
+        let census = serde_json::to_value(census.parse::<Value>().unwrap()).unwrap();
+        let data = census_to_json(census);
+
+        let template = "{{sys.hostname}} {{svc.me.sys.ip}} {{svc.leader.sys.ip}}{{#eachAlive \
+                        svc.members as |m|}} {{m.sys.ip}}{{/eachAlive}}{{#each \
+                        bind.database.members as |m|}} \
+                        {{m.pkg.name}}@{{m.sys.ip}}:{{m.cfg.port}}{{/each}}";
+        let mut renderer = TemplateRenderer::new();
+        renderer.register_template_string("t", template).unwrap();
+        assert_eq!(renderer.render("t", &data).unwrap(),
+                   "web-1 10.0.0.1 10.0.0.1 10.0.0.1 10.0.0.3 postgresql@10.0.1.1:5432");
+    }
+}
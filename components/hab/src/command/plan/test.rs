@@ -0,0 +1,331 @@
+use super::render::{census_to_json,
+                    merge,
+                    toml_to_json};
+use crate::{common::{templating::TemplateRenderer,
+                     ui::{Glyph,
+                          Status,
+                          UIWriter,
+                          UI}},
+            error::{Error,
+                    Result},
+            hcore::package::PackageIdent};
+use serde_json::{json,
+                 Value as Json};
+use std::{collections::BTreeMap,
+          env,
+          fs,
+          path::Path,
+          process::Stdio,
+          time::Duration};
+use tokio::{process::Command,
+            time};
+
+/// The test manifest used when none is given, relative to the plan directory.
+pub const DEFAULT_MANIFEST: &str = "tests/hooks.toml";
+
+const DEFAULT_TIMEOUT_SECS: u64 = 60;
+
+/// The environment variables hooks get from `hab`. Test cases give the others.
+#[cfg(unix)]
+const INHERITED_ENV_VARS: &[&str] = &["PATH"];
+#[cfg(windows)]
+const INHERITED_ENV_VARS: &[&str] = &["PATH", "SystemRoot", "TEMP", "TMP"];
+
+/// A test manifest, describing the test cases of the hooks of a plan.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Manifest {
+    /// The identifier of the package the hooks are tested as, for the `pkg` template data
+    pkg_ident: Option<String>,
+    #[serde(rename = "test", default)]
+    tests:     Vec<TestCase>,
+}
+
+/// A test case: a hook to run, the data to render it with, and what it must do.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TestCase {
+    name:            String,
+    hook:            String,
+    /// Config merged over `default.toml`, like a `user.toml`
+    #[serde(default)]
+    cfg:             toml::value::Table,
+    /// A mock census, as given to `hab plan render --mock-census`, or the path to one relative
+    /// to the manifest
+    census:          Option<toml::Value>,
+    /// Environment variables of the hook, on top of `PATH`, which is inherited
+    #[serde(default)]
+    env:             BTreeMap<String, String>,
+    #[serde(default)]
+    exit_code:       i32,
+    #[serde(default)]
+    stdout_contains: Vec<String>,
+    #[serde(default)]
+    stderr_contains: Vec<String>,
+    timeout:         Option<u64>,
+}
+
+pub async fn start(ui: &mut UI,
+                   plan_dir: &Path,
+                   manifest_path: Option<&Path>,
+                   filter: Option<&str>)
+                   -> Result<()> {
+    let manifest_path = manifest_path.map(Path::to_path_buf)
+                                     .unwrap_or_else(|| plan_dir.join(DEFAULT_MANIFEST));
+    ui.begin(format!("Testing the hooks of {} with {}",
+                     plan_dir.display(),
+                     manifest_path.display()))?;
+    let manifest: Manifest = toml::from_str(&fs::read_to_string(&manifest_path)?)?;
+    let manifest_dir = manifest_path.parent().unwrap_or_else(|| Path::new("."));
+    let pkg_ident = match &manifest.pkg_ident {
+        Some(pkg_ident) => pkg_ident.parse()?,
+        None => PackageIdent::new("local", "plan", None, None),
+    };
+
+    let mut total = 0;
+    let mut failed = 0;
+    for test in &manifest.tests {
+        if filter.map_or(false, |f| !test.name.contains(f)) {
+            continue;
+        }
+        total += 1;
+        let failures = run(plan_dir, manifest_dir, &pkg_ident, test).await?;
+        if failures.is_empty() {
+            ui.status(Status::Custom(Glyph::CheckMark, String::from("Passed")),
+                      &test.name)?;
+        } else {
+            failed += 1;
+            ui.status(Status::Custom(Glyph::ErrorX, String::from("Failed")),
+                      &test.name)?;
+            for failure in failures {
+                ui.warn(failure)?;
+            }
+        }
+    }
+
+    if failed > 0 {
+        return Err(Error::HookTestsFailed(failed, total));
+    }
+    ui.end(format!("{} hook tests passed.", total))?;
+    Ok(())
+}
+
+/// Run the hook of a test case in a service directory of its own, returning how it failed the
+/// test case, if it did.
+async fn run(plan_dir: &Path,
+             manifest_dir: &Path,
+             pkg_ident: &PackageIdent,
+             test: &TestCase)
+             -> Result<Vec<String>> {
+    let svc_dir = tempfile::Builder::new().prefix("hab-plan-test-")
+                                          .tempdir()?;
+    let svc_path = svc_dir.path();
+    for dir in &["config", "data", "files", "hooks", "static", "var"] {
+        fs::create_dir_all(svc_path.join(dir))?;
+    }
+
+    let data = render_data(plan_dir, manifest_dir, pkg_ident, svc_path, test)?;
+    let mut renderer = TemplateRenderer::new();
+    let config_dir = plan_dir.join("config");
+    if config_dir.is_dir() {
+        for entry in walkdir::WalkDir::new(&config_dir).into_iter()
+                                                       .filter_map(|e| e.ok())
+                                                       .filter(|e| e.file_type().is_file())
+        {
+            let relative = entry.path()
+                                .strip_prefix(&config_dir)
+                                .expect("entry in config dir");
+            let dest = svc_path.join("config").join(relative);
+            if let Some(parent) = dest.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            render_file(&mut renderer, entry.path(), &dest, &data)?;
+        }
+    }
+
+    let hook = plan_dir.join("hooks").join(&test.hook);
+    if !hook.is_file() {
+        return Err(Error::FileNotFound(hook.display().to_string()));
+    }
+    let mut hook_path = svc_path.join("hooks").join(&test.hook);
+    if cfg!(windows) {
+        hook_path.set_extension("ps1");
+    }
+    render_file(&mut renderer, &hook, &hook_path, &data)?;
+
+    let mut command = hook_command(&hook_path)?;
+    command.current_dir(svc_path)
+           .env_clear()
+           .envs(INHERITED_ENV_VARS.iter()
+                                   .filter_map(|var| env::var_os(var).map(|value| (var, value))))
+           .envs(&test.env)
+           .stdin(Stdio::null())
+           .kill_on_drop(true);
+    let timeout = Duration::from_secs(test.timeout.unwrap_or(DEFAULT_TIMEOUT_SECS));
+    let output = match time::timeout(timeout, command.output()).await {
+        Ok(output) => output?,
+        Err(_) => {
+            return Ok(vec![format!("{} did not exit within {} seconds",
+                                   test.hook,
+                                   timeout.as_secs())]);
+        }
+    };
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let mut failures = Vec::new();
+    match output.status.code() {
+        Some(code) if code == test.exit_code => {}
+        Some(code) => {
+            failures.push(format!("{} exited with {}, expected {}",
+                                  test.hook, code, test.exit_code))
+        }
+        None => failures.push(format!("{} was killed by a signal", test.hook)),
+    }
+    for expected in &test.stdout_contains {
+        if !stdout.contains(expected.as_str()) {
+            failures.push(format!("stdout does not contain {:?}", expected));
+        }
+    }
+    for expected in &test.stderr_contains {
+        if !stderr.contains(expected.as_str()) {
+            failures.push(format!("stderr does not contain {:?}", expected));
+        }
+    }
+    if !failures.is_empty() {
+        failures.push(format!("stdout:\n{}", stdout));
+        failures.push(format!("stderr:\n{}", stderr));
+    }
+    Ok(failures)
+}
+
+/// The template data of a test case: the package, its config and the mock census.
+fn render_data(plan_dir: &Path,
+               manifest_dir: &Path,
+               pkg_ident: &PackageIdent,
+               svc_path: &Path,
+               test: &TestCase)
+               -> Result<Json> {
+    let mut data = json!({});
+    let default_toml = plan_dir.join("default.toml");
+    if default_toml.is_file() {
+        merge(&mut data, toml_to_json(&fs::read_to_string(default_toml)?)?);
+    }
+    merge(&mut data, json!({ "cfg": test.cfg }));
+
+    let census = match &test.census {
+        Some(toml::Value::String(path)) => {
+            let path = manifest_dir.join(path);
+            let census = fs::read_to_string(&path)?;
+            if path.extension().map_or(false, |ext| ext == "json") {
+                serde_json::from_str(&census)?
+            } else {
+                serde_json::to_value(census.parse::<toml::Value>()?)?
+            }
+        }
+        Some(census) => serde_json::to_value(census)?,
+        None => json!({}),
+    };
+    merge(&mut data, census_to_json(census));
+
+    let svc_dir = |dir: &str| svc_path.join(dir);
+    merge(&mut data,
+          json!({
+              "pkg": {
+                  "ident": pkg_ident.to_string(),
+                  "origin": pkg_ident.origin,
+                  "name": pkg_ident.name,
+                  "version": pkg_ident.version,
+                  "release": pkg_ident.release,
+                  "deps": [],
+                  "env": {},
+                  "exposes": [],
+                  "exports": {},
+                  "path": plan_dir,
+                  "svc_path": svc_path,
+                  "svc_config_path": svc_dir("config"),
+                  "svc_config_install_path": svc_dir("config_install"),
+                  "svc_data_path": svc_dir("data"),
+                  "svc_files_path": svc_dir("files"),
+                  "svc_static_path": svc_dir("static"),
+                  "svc_var_path": svc_dir("var"),
+                  "svc_pid_file": svc_dir("PID"),
+                  "svc_run": svc_dir("run"),
+                  "svc_user": "hab",
+                  "svc_group": "hab",
+              }
+          }));
+    Ok(data)
+}
+
+fn render_file(renderer: &mut TemplateRenderer,
+               template: &Path,
+               dest: &Path,
+               data: &Json)
+               -> Result<()> {
+    let name = template.display().to_string();
+    renderer.register_template_string(&name, &fs::read_to_string(template)?)
+            .map_err(handlebars::TemplateRenderError::from)?;
+    fs::write(dest, renderer.render(&name, data)?)?;
+    Ok(())
+}
+
+#[cfg(unix)]
+fn hook_command(hook_path: &Path) -> Result<Command> {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::set_permissions(hook_path, fs::Permissions::from_mode(0o755))?;
+    Ok(Command::new(hook_path))
+}
+
+#[cfg(windows)]
+fn hook_command(hook_path: &Path) -> Result<Command> {
+    let mut command = Command::new("pwsh.exe");
+    command.arg("-NonInteractive").arg("-File").arg(hook_path);
+    Ok(command)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    const HEALTH_CHECK: &str = "#!/bin/sh
+echo \"{{cfg.port}} {{svc.leader.sys.ip}} $GREETING\"
+exit 2
+";
+
+    const MANIFEST: &str = r#"
+[[test]]
+name = "critical"
+hook = "health-check"
+cfg = { port = 8080 }
+census = { svc = { members = [{ leader = true, sys = { ip = "10.0.0.1" } }] } }
+env = { GREETING = "hello" }
+exit_code = 2
+stdout_contains = ["8080 10.0.0.1 hello"]
+
+[[test]]
+name = "ok"
+hook = "health-check"
+stdout_contains = ["80"]
+"#;
+
+    #[tokio::test]
+    async fn hooks_run_against_test_cases() {
+        let plan_dir = tempfile::TempDir::new().unwrap();
+        let plan_dir = plan_dir.path();
+        fs::create_dir_all(plan_dir.join("hooks")).unwrap();
+        fs::create_dir_all(plan_dir.join("tests")).unwrap();
+        fs::write(plan_dir.join("default.toml"), "port = 80\n").unwrap();
+        fs::write(plan_dir.join("hooks").join("health-check"), HEALTH_CHECK).unwrap();
+        fs::write(plan_dir.join(DEFAULT_MANIFEST), MANIFEST).unwrap();
+
+        let mut ui = UI::with_sinks();
+        let result = start(&mut ui, plan_dir, None, Some("critical")).await;
+        assert!(result.is_ok());
+        match start(&mut ui, plan_dir, None, None).await {
+            Err(Error::HookTestsFailed(1, 2)) => {}
+            other => panic!("expected one failed test case, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use crate::{common::{cli_config::{CliConfig,
+                                  Remote},
+                     types::ListenCtlAddr,
+                     ui::{Status,
+                          UIWriter,
+                          UI}},
+            error::{Error,
+                    Result}};
+use std::{fs,
+          io::{self,
+               Write},
+          path::Path};
+use tabwriter::TabWriter;
+
+pub fn add(ui: &mut UI, name: &str, address: &str, secret_file: Option<&Path>) -> Result<()> {
+    // Fail early on an address `--remote-sup` could not connect to. The address itself is saved,
+    // so that host names are resolved again each time the remote Supervisor is used.
+    ListenCtlAddr::resolve_listen_ctl_addr(address)?;
+    let secret_file = secret_file.map(fs::canonicalize).transpose()?;
+
+    let mut config = CliConfig::load()?;
+    let status = if config.remotes.contains_key(name) {
+        Status::Updated
+    } else {
+        Status::Added
+    };
+    config.remotes.insert(name.to_string(),
+                          Remote { address: address.to_string(),
+                                   secret_file });
+    config.save()?;
+    ui.status(status, format!("remote Supervisor {} at {}", name, address))?;
+    Ok(())
+}
+
+pub fn list() -> Result<()> {
+    let config = CliConfig::load()?;
+    let mut out = TabWriter::new(io::stdout());
+    writeln!(out, "NAME\tADDRESS\tSECRET FILE")?;
+    for (name, remote) in &config.remotes {
+        let secret_file = remote.secret_file
+                                .as_ref()
+                                .map_or_else(|| String::from("-"), |f| f.display().to_string());
+        writeln!(out, "{}\t{}\t{}", name, remote.address, secret_file)?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+pub fn remove(ui: &mut UI, name: &str) -> Result<()> {
+    let mut config = CliConfig::load()?;
+    if config.remotes.remove(name).is_none() {
+        return Err(Error::RemoteNotFound(name.to_string()));
+    }
+    config.save()?;
+    ui.status(Status::Deleted, format!("remote Supervisor {}", name))?;
+    Ok(())
+}
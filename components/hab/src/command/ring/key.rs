@@ -1,3 +1,4 @@
+pub mod distribute;
 pub mod export;
 pub mod generate;
 pub mod import;
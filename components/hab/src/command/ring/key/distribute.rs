@@ -0,0 +1,133 @@
+//! Distributes a ring key to the key caches of the peers of a ring, over SSH.
+//!
+//! The key is written to the standard input of `hab ring key import` run on each peer, so it is
+//! never put on a command line or in a temporary file, and ends up in the peer's key cache with
+//! the permissions `hab` gives keys there.
+
+use crate::{common::ui::{Status,
+                         UIWriter,
+                         UI},
+            error::{Error,
+                    Result}};
+use habitat_core::crypto::keys::{Key,
+                                 KeyCache,
+                                 KeyFile};
+use std::{io::Write,
+          path::Path,
+          process::{Command,
+                    Stdio}};
+
+/// Where and how the ring key is imported on the peers.
+pub struct RemoteImport<'a> {
+    /// The SSH client to connect with.
+    pub ssh:            &'a str,
+    /// Run the import with `sudo`, for peers whose key cache belongs to root.
+    pub sudo:           bool,
+    /// The key cache of the peers, if not their default one.
+    pub cache_key_path: Option<&'a Path>,
+}
+
+pub fn start(ui: &mut UI,
+             ring: &str,
+             peers: &[String],
+             remote: &RemoteImport<'_>,
+             key_cache: &KeyCache)
+             -> Result<()> {
+    let key = key_cache.latest_ring_key_revision(ring)?;
+    let content = key.to_key_string();
+    let command = import_command(remote);
+    ui.begin(format!("Distributing ring key {} to {} peers",
+                     key.named_revision(),
+                     peers.len()))?;
+    let mut failed = 0;
+    for peer in peers {
+        ui.status(Status::Uploading, peer)?;
+        match import_on(peer, remote.ssh, &command, &content) {
+            Ok(()) => {
+                ui.status(Status::Uploaded,
+                          format!("{} to {}", key.named_revision(), peer))?
+            }
+            Err(err) => {
+                ui.warn(format!("Unable to import the ring key on {}: {}", peer, err))?;
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(Error::RingKeyDistributionFailed(failed, peers.len()));
+    }
+    ui.end(format!("Distributed ring key {} to {} peers.",
+                   key.named_revision(),
+                   peers.len()))?;
+    Ok(())
+}
+
+/// The peers listed in a peers file: one SSH destination (`[user@]host`) per line, ignoring
+/// blank lines and `#` comments.
+pub fn parse_peers(contents: &str) -> Vec<String> {
+    contents.lines()
+            .map(|line| line.split('#').next().unwrap_or_default().trim())
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect()
+}
+
+/// The shell command that imports the ring key on a peer.
+fn import_command(remote: &RemoteImport<'_>) -> String {
+    let mut command = String::new();
+    if remote.sudo {
+        command.push_str("sudo -n ");
+    }
+    command.push_str("hab ring key import");
+    if let Some(path) = remote.cache_key_path {
+        command.push_str(" --cache-key-path ");
+        command.push_str(&shell_quote(&path.to_string_lossy()));
+    }
+    command
+}
+
+fn shell_quote(value: &str) -> String { format!("'{}'", value.replace('\'', "'\\''")) }
+
+fn import_on(peer: &str, ssh: &str, command: &str, content: &str) -> Result<()> {
+    let mut child = Command::new(ssh).args(&["-o", "BatchMode=yes", "--", peer, command])
+                                     .stdin(Stdio::piped())
+                                     .stdout(Stdio::null())
+                                     .stderr(Stdio::piped())
+                                     .spawn()?;
+    child.stdin
+         .take()
+         .expect("stdin of ssh is piped")
+         .write_all(content.as_bytes())?;
+    let output = child.wait_with_output()?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(Error::CryptoCLI(format!("{} exited with {}: {}",
+                                     ssh,
+                                     output.status,
+                                     String::from_utf8_lossy(&output.stderr).trim())))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn peers_file() {
+        let peers = parse_peers("# Ring members\nhab@10.0.0.1\n\n  10.0.0.2  # backup\n");
+        assert_eq!(peers, vec!["hab@10.0.0.1", "10.0.0.2"]);
+    }
+
+    #[test]
+    fn remote_import_command() {
+        let mut remote = RemoteImport { ssh:            "ssh",
+                                        sudo:           false,
+                                        cache_key_path: None, };
+        assert_eq!(import_command(&remote), "hab ring key import");
+        remote.sudo = true;
+        remote.cache_key_path = Some(Path::new("/srv/it's keys"));
+        assert_eq!(import_command(&remote),
+                   "sudo -n hab ring key import --cache-key-path '/srv/it'\\''s keys'");
+    }
+}
@@ -1 +1,2 @@
 pub mod key;
+pub mod status;
@@ -0,0 +1,191 @@
+//! The status of the services of every Supervisor in a gossip ring, found through one of its
+//! members rather than a list of hosts.
+//!
+//! The member asked, the bastion, reports the Supervisors of the ring running services along
+//! with the address of their Control Gateway, and each of them is then asked about its services.
+
+use crate::error::Result;
+use futures::{future,
+              stream::StreamExt};
+use habitat_common::{types::ListenCtlAddr,
+                     ui::{UIWriter,
+                          UI}};
+use habitat_core::package::PackageIdent;
+use habitat_sup_client::{SrvClient,
+                         SrvClientError};
+use habitat_sup_protocol::{self as sup_proto,
+                           codec::SrvMessage,
+                           net::ErrCode,
+                           types::{DesiredState,
+                                   ProcessState,
+                                   ServiceStatus,
+                                   SupervisorMember}};
+use std::{io::{self,
+               Write},
+          time::Duration};
+use tabwriter::TabWriter;
+use tokio::time;
+
+const HEADER: &[&str] = &["supervisor",
+                          "package",
+                          "desired",
+                          "state",
+                          "elapsed (s)",
+                          "pid",
+                          "group"];
+
+/// How long to wait for a member to report the status of its services, so that one which stopped
+/// responding doesn't hold up the others.
+const MEMBER_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Show the status of the services of every Supervisor in the ring `bastion` is a member of, or
+/// only of those running `pkg_ident`.
+pub async fn start(ui: &mut UI,
+                   bastion: &ListenCtlAddr,
+                   pkg_ident: Option<&PackageIdent>)
+                   -> Result<()> {
+    let (alive, gone): (Vec<_>, Vec<_>) = members(bastion).await?
+                                                          .into_iter()
+                                                          .partition(|member| member.alive);
+    let mut reachable = Vec::new();
+    let mut unreachable = Vec::new();
+    for member in alive {
+        match member_address(&member, bastion) {
+            Some(address) => reachable.push((member, address)),
+            None => unreachable.push(member),
+        }
+    }
+    let requests =
+        reachable.into_iter().map(|(member, address)| {
+                                 async move { (member, member_statuses(address, pkg_ident).await) }
+                             });
+    let results = future::join_all(requests).await;
+
+    let mut out = TabWriter::new(io::stdout());
+    writeln!(out, "{}", HEADER.join("\t"))?;
+    let mut failures = Vec::new();
+    for (member, result) in results {
+        match result {
+            Ok(statuses) => {
+                for status in statuses {
+                    print_row(&mut out, &member, &status)?;
+                }
+            }
+            Err(err) => failures.push((member, err)),
+        }
+    }
+    out.flush()?;
+
+    for member in gone {
+        ui.warn(format!("Skipped {} ({}), which isn't alive",
+                        member.hostname, member.member_id))?;
+    }
+    for member in unreachable {
+        ui.warn(format!("Skipped {} ({}), which advertises no address to reach it at",
+                        member.hostname, member.member_id))?;
+    }
+    for (member, err) in failures {
+        ui.warn(format!("Couldn't get the status of the services of {} ({}): {}",
+                        member.hostname, member.member_id, err))?;
+    }
+    Ok(())
+}
+
+/// The address to reach `member` at, if it has one. The bastion itself is reached at the address
+/// it was asked at, as it may only listen on a loopback address.
+fn member_address(member: &SupervisorMember,
+                  bastion: &ListenCtlAddr)
+                  -> Option<habitat_common::error::Result<ListenCtlAddr>> {
+    if member.local {
+        Some(Ok(*bastion))
+    } else {
+        member.ctl_gateway
+              .as_deref()
+              .map(ListenCtlAddr::resolve_listen_ctl_addr)
+    }
+}
+
+/// The status of the services of the member at `address`, giving up after `MEMBER_TIMEOUT`.
+async fn member_statuses(address: habitat_common::error::Result<ListenCtlAddr>,
+                         pkg_ident: Option<&PackageIdent>)
+                         -> Result<Vec<ServiceStatus>> {
+    match time::timeout(MEMBER_TIMEOUT, statuses(&address?, pkg_ident)).await {
+        Ok(result) => result,
+        Err(_) => {
+            let msg = format!("No reply within {}s", MEMBER_TIMEOUT.as_secs());
+            Err(io::Error::new(io::ErrorKind::TimedOut, msg).into())
+        }
+    }
+}
+
+/// The Supervisors of the ring running services, as known to `bastion`.
+async fn members(bastion: &ListenCtlAddr) -> Result<Vec<SupervisorMember>> {
+    let mut members = Vec::new();
+    let mut response = SrvClient::request(bastion, sup_proto::ctl::SupMembers::default()).await?;
+    while let Some(message_result) = response.next().await {
+        let reply = message_result?;
+        check_reply(&reply)?;
+        if reply.message_id() == "SupervisorMember" {
+            members.push(reply.parse::<SupervisorMember>()
+                              .map_err(SrvClientError::Decode)?);
+        }
+    }
+    Ok(members)
+}
+
+/// The status of the services of the Supervisor at `remote_sup`, or only of `pkg_ident`.
+async fn statuses(remote_sup: &ListenCtlAddr,
+                  pkg_ident: Option<&PackageIdent>)
+                  -> Result<Vec<ServiceStatus>> {
+    let msg = sup_proto::ctl::SvcStatus { ident: pkg_ident.cloned().map(Into::into), };
+    let mut statuses = Vec::new();
+    let mut response = SrvClient::request(remote_sup, msg).await?;
+    while let Some(message_result) = response.next().await {
+        let reply = message_result?;
+        if reply.message_id() == "NetErr" {
+            let err = reply.parse::<sup_proto::net::NetErr>()
+                           .map_err(SrvClientError::Decode)?;
+            // The service isn't loaded on every Supervisor
+            if ErrCode::from_i32(err.code) == Some(ErrCode::NotFound) {
+                continue;
+            }
+            return Err(SrvClientError::from(err).into());
+        }
+        if reply.message_id() == "ServiceStatus" {
+            statuses.push(reply.parse::<ServiceStatus>()
+                               .map_err(SrvClientError::Decode)?);
+        }
+    }
+    Ok(statuses)
+}
+
+fn print_row(out: &mut impl Write,
+             member: &SupervisorMember,
+             status: &ServiceStatus)
+             -> io::Result<()> {
+    let desired = status.desired_state
+                        .and_then(DesiredState::from_i32)
+                        .unwrap_or(DesiredState::DesiredNone);
+    let process = status.process.clone().unwrap_or_default();
+    writeln!(out,
+             "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+             member.hostname,
+             status.ident,
+             desired,
+             ProcessState::from_i32(process.state).unwrap_or_default(),
+             process.elapsed
+                    .map_or_else(|| "<none>".to_string(), |elapsed| elapsed.to_string()),
+             process.pid
+                    .map_or_else(|| "<none>".to_string(), |pid| pid.to_string()),
+             status.service_group)
+}
+
+/// Turn a `NetErr` reply into an error.
+fn check_reply(reply: &SrvMessage) -> Result<()> {
+    if reply.message_id() == "NetErr" {
+        let err = reply.parse::<sup_proto::net::NetErr>()
+                       .map_err(SrvClientError::Decode)?;
+        return Err(SrvClientError::from(err).into());
+    }
+    Ok(())
+}
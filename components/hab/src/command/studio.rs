@@ -1,4 +1,6 @@
 pub mod docker;
+pub mod engine;
 pub mod enter;
+pub mod options;
 
 pub fn native_studio_support() -> bool { cfg!(target_os = "linux") || cfg!(target_os = "windows") }
@@ -1,6 +1,8 @@
-use crate::{command::studio::enter::{ARTIFACT_PATH_ENVVAR,
-                                     CERT_PATH_ENVVAR,
-                                     SSL_CERT_FILE_ENVVAR},
+use crate::{command::studio::{engine::ContainerEngine,
+                              enter::{ARTIFACT_PATH_ENVVAR,
+                                      CERT_PATH_ENVVAR,
+                                      SSL_CERT_FILE_ENVVAR},
+                              options::StudioOptions},
             common::ui::UI,
             error::{Error,
                     Result},
@@ -18,8 +20,7 @@ use crate::{command::studio::enter::{ARTIFACT_PATH_ENVVAR,
 use std::{env,
           ffi::{OsStr,
                 OsString},
-          path::{Path,
-                 PathBuf},
+          path::Path,
           process::{Command,
                     Stdio}};
 
@@ -30,21 +31,22 @@ const DOCKER_OPTS_ENVVAR: &str = "HAB_DOCKER_OPTS";
 const DOCKER_SOCKET: &str = "/var/run/docker.sock";
 const HAB_STUDIO_SECRET: &str = "HAB_STUDIO_SECRET_";
 
-pub fn start_docker_studio(_ui: &mut UI, args: &[OsString]) -> Result<()> {
+pub fn start_docker_studio(_ui: &mut UI, args: &[OsString], options: &StudioOptions) -> Result<()> {
     let mut args = args.to_vec();
     if args.get(0) == Some(&OsString::from("rm")) {
         return Err(Error::CannotRemoveDockerStudio);
     }
 
-    let docker_cmd = docker::command_path()?;
-    let using_windows_containers = is_serving_windows_containers(&docker_cmd);
-    let image = image_identifier_for_active_target(using_windows_containers)?;
+    let engine = ContainerEngine::from_env()?;
+    let using_windows_containers = engine.serves_windows_containers();
+    let image =
+        engine.qualified_image(&image_identifier_for_active_target(using_windows_containers)?);
 
-    if is_image_present(&docker_cmd, &image) {
+    if is_image_present(engine.command_path(), &image) {
         debug!("Found Studio Docker image locally.");
     } else {
         debug!("Failed to find Studio Docker image locally.");
-        pull_image(&docker_cmd, &image)?;
+        pull_image(engine.command_path(), &image)?;
     }
 
     let mnt_prefix = if using_windows_containers { "c:" } else { "" };
@@ -78,10 +80,11 @@ pub fn start_docker_studio(_ui: &mut UI, args: &[OsString]) -> Result<()> {
         // Don't use Path::join here as "\" can cause problems in Docker mounts
         volumes.push(format!("{}:{}/{}", cache_ssl_path, mnt_prefix, CACHE_SSL_PATH));
     }
-    if !using_windows_containers
-       && (Path::new(DOCKER_SOCKET).exists() || cfg!(target_os = "windows"))
-    {
-        volumes.push(format!("{}:{}", DOCKER_SOCKET, DOCKER_SOCKET));
+    volumes.extend(options.mounts.iter().map(|mount| mount.to_volume()));
+    if !using_windows_containers {
+        if let Some(socket) = engine.api_socket() {
+            volumes.push(format!("{}:{}", socket.display(), DOCKER_SOCKET));
+        }
     }
 
     let mut env_vars = vec![String::from("DEBUG"),
@@ -100,6 +103,7 @@ pub fn start_docker_studio(_ui: &mut UI, args: &[OsString]) -> Result<()> {
                             String::from("http_proxy"),
                             String::from("https_proxy"),
                             String::from("RUST_LOG"),
+                            String::from("SOURCE_DATE_EPOCH"),
                             String::from("SSL_CERT_FILE"),];
 
     for (key, _) in env::vars() {
@@ -107,6 +111,7 @@ pub fn start_docker_studio(_ui: &mut UI, args: &[OsString]) -> Result<()> {
             env_vars.push(key);
         }
     }
+    env_vars.extend(options.env_pass.iter().cloned());
 
     // We need to strip out the -D if it exists to avoid
     // it getting passed to the sup on entering the studio
@@ -122,9 +127,9 @@ pub fn start_docker_studio(_ui: &mut UI, args: &[OsString]) -> Result<()> {
     // Windows containers do not use filesystem sharing for
     // local mounts
     if !using_windows_containers {
-        check_mounts(&docker_cmd, volumes.iter(), &image)?;
+        check_mounts(&engine, volumes.iter(), &image)?;
     }
-    run_container(docker_cmd,
+    run_container(&engine,
                   &args,
                   volumes.iter(),
                   env_vars.iter(),
@@ -158,14 +163,6 @@ fn is_image_present(docker_cmd: &Path, image: &str) -> bool {
     !String::from_utf8_lossy(&result.stdout).as_ref().is_empty()
 }
 
-fn is_serving_windows_containers(docker_cmd: &Path) -> bool {
-    let mut cmd = Command::new(docker_cmd);
-    cmd.arg("version").arg("--format='{{.Server.Os}}'");
-    debug!("Running command: {:?}", cmd);
-    let result = cmd.output().expect("Docker command failed to spawn");
-    String::from_utf8_lossy(&result.stdout).contains("windows")
-}
-
 fn pull_image(docker_cmd: &Path, image: &str) -> Result<()> {
     let mut cmd = Command::new(docker_cmd);
     cmd.arg("pull")
@@ -204,11 +201,12 @@ fn pull_image(docker_cmd: &Path, image: &str) -> Result<()> {
 /// greeted with a horrible error message that's difficult to make sense of. To mitigate this,
 /// we check the studio version. This will cause Docker to go through the mounting steps, so we
 /// can watch stderr for failure, but has the advantage of not requiring a TTY.
-fn check_mounts<I, S>(docker_cmd: &Path, volumes: I, image: &str) -> Result<()>
+fn check_mounts<I, S>(engine: &ContainerEngine, volumes: I, image: &str) -> Result<()>
     where I: IntoIterator<Item = S>,
           S: AsRef<OsStr>
 {
     let mut cmd_args: Vec<OsString> = vec!["run".into(), "--rm".into()];
+    cmd_args.extend(engine.run_args());
 
     for vol in volumes {
         cmd_args.push("--volume".into());
@@ -216,9 +214,9 @@ fn check_mounts<I, S>(docker_cmd: &Path, volumes: I, image: &str) -> Result<()>
     }
     cmd_args.push(image.into());
     cmd_args.push("-V".into());
-    let version_output = Command::new(docker_cmd).args(&cmd_args)
-                                                 .output()
-                                                 .expect("docker failed to start");
+    let version_output = Command::new(engine.command_path()).args(&cmd_args)
+                                                            .output()
+                                                            .expect("docker failed to start");
 
     let stderr = String::from_utf8(version_output.stderr).unwrap();
     if !stderr.is_empty()
@@ -230,7 +228,7 @@ fn check_mounts<I, S>(docker_cmd: &Path, volumes: I, image: &str) -> Result<()>
     Ok(())
 }
 
-fn run_container<I, J, S, T>(docker_cmd: PathBuf,
+fn run_container<I, J, S, T>(engine: &ContainerEngine,
                              args: &[OsString],
                              volumes: I,
                              env_vars: J,
@@ -247,6 +245,7 @@ fn run_container<I, J, S, T>(docker_cmd: PathBuf,
     if !using_windows_containers {
         cmd_args.push("--privileged".into());
     }
+    cmd_args.extend(engine.run_args());
 
     if atty::is(atty::Stream::Stderr) || atty::is(atty::Stream::Stdout) {
         cmd_args.push("--tty".into());
@@ -296,7 +295,7 @@ fn run_container<I, J, S, T>(docker_cmd: PathBuf,
     }
 
     unset_proxy_env_vars();
-    process::become_command(docker_cmd, &cmd_args)?;
+    process::become_command(engine.command_path().to_path_buf(), &cmd_args)?;
     Ok(())
 }
 
@@ -352,7 +351,7 @@ fn studio_target(windows: bool, target: target::PackageTarget) -> target::Packag
         #[cfg(feature = "supported_targets")]
         target::X86_64_WINDOWS => target::X86_64_LINUX,
         #[cfg(feature = "aarch64-linux")]
-        target::AARCH64_LINUX => panic!("{} studios are not supported", target::AARCH64_LINUX),
+        target::AARCH64_LINUX => target::AARCH64_LINUX,
         // This is only needed for the case that we have no target enabled. In that case, we get a
         // non-exhaustive patterns error because the match statement is empty.
         #[cfg(not(any(feature = "supported_targets", feature = "aarch64-linux")))]
@@ -393,9 +392,12 @@ mod tests {
                            DOCKER_WINDOWS_IMAGE, "x86_64-windows", "ltsc2016", VERSION));
     }
 
-    #[should_panic]
+    #[test]
     #[cfg(feature = "aarch64-linux")]
-    fn retrieve_aarch64_image_identifier() { image_identifier(None, target::AARCH64_LINUX); }
+    fn retrieve_aarch64_image_identifier() {
+        assert_eq!(image_identifier(None, target::AARCH64_LINUX),
+                   format!("{}-{}:{}", DOCKER_IMAGE, "aarch64-linux", VERSION));
+    }
 
     #[test]
     fn update_ssl_cert_file_envvar_not_set() {
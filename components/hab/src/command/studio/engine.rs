@@ -0,0 +1,178 @@
+//! The container engines which can run a containerized Studio.
+//!
+//! Docker is used by default. Podman, including rootless Podman, can be selected by setting
+//! `HAB_STUDIO_BACKEND=podman`.
+use crate::{error::{Error,
+                    Result},
+            hcore::{env as henv,
+                    fs::{am_i_root,
+                         find_command},
+                    util::docker}};
+use std::{ffi::OsString,
+          path::{Path,
+                 PathBuf},
+          process::Command,
+          str::FromStr};
+
+/// The environment variable used to select the container engine of containerized Studios
+pub const STUDIO_BACKEND_ENVVAR: &str = "HAB_STUDIO_BACKEND";
+
+const PODMAN_CMD: &str = "podman";
+const DOCKER_SOCKET: &str = "/var/run/docker.sock";
+const PODMAN_SOCKET: &str = "/run/podman/podman.sock";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Backend {
+    Docker,
+    Podman,
+}
+
+impl Default for Backend {
+    fn default() -> Self { Backend::Docker }
+}
+
+impl FromStr for Backend {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "docker" => Ok(Backend::Docker),
+            "podman" => Ok(Backend::Podman),
+            _ => Err(Error::UnsupportedStudioBackend(value.to_string())),
+        }
+    }
+}
+
+impl Backend {
+    /// The backend selected by `HAB_STUDIO_BACKEND`, defaulting to Docker.
+    pub fn from_env() -> Result<Self> {
+        match henv::var(STUDIO_BACKEND_ENVVAR) {
+            Ok(value) if !value.is_empty() => value.parse(),
+            _ => Ok(Backend::default()),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ContainerEngine {
+    backend:  Backend,
+    cmd:      PathBuf,
+    rootless: bool,
+}
+
+impl ContainerEngine {
+    pub fn from_env() -> Result<Self> {
+        let backend = Backend::from_env()?;
+        let cmd =
+            match backend {
+                Backend::Docker => docker::command_path()?,
+                Backend::Podman => find_command(PODMAN_CMD).ok_or_else(|| {
+                                       Error::ExecCommandNotFound(PathBuf::from(PODMAN_CMD))
+                                   })?,
+            };
+        // Podman run by an unprivileged user is always rootless
+        let rootless = backend == Backend::Podman && !am_i_root();
+        debug!("Using {:?} container engine at {}, rootless: {}",
+               backend,
+               cmd.display(),
+               rootless);
+        Ok(ContainerEngine { backend,
+                             cmd,
+                             rootless })
+    }
+
+    pub fn backend(&self) -> Backend { self.backend }
+
+    pub fn command_path(&self) -> &Path { &self.cmd }
+
+    pub fn is_rootless(&self) -> bool { self.rootless }
+
+    pub fn serves_windows_containers(&self) -> bool {
+        if self.backend == Backend::Podman {
+            return false;
+        }
+        let mut cmd = Command::new(&self.cmd);
+        cmd.arg("version").arg("--format='{{.Server.Os}}'");
+        debug!("Running command: {:?}", cmd);
+        let result = cmd.output().expect("Docker command failed to spawn");
+        String::from_utf8_lossy(&result.stdout).contains("windows")
+    }
+
+    /// Podman may prompt for a registry when given an image name without one, so images are
+    /// always fully qualified when using Podman.
+    pub fn qualified_image(&self, image: &str) -> String {
+        match self.backend {
+            Backend::Podman if !has_registry(image) => format!("docker.io/{}", image),
+            _ => image.to_string(),
+        }
+    }
+
+    /// The socket of the engine's API, if it is available. It is mounted into the Studio at
+    /// `/var/run/docker.sock` so that tools in the Studio, such as the Docker exporter, can use
+    /// the engine. Podman serves a Docker compatible API when its socket service is enabled.
+    pub fn api_socket(&self) -> Option<PathBuf> {
+        let socket = match self.backend {
+            Backend::Docker if cfg!(target_os = "windows") => return Some(DOCKER_SOCKET.into()),
+            Backend::Docker => PathBuf::from(DOCKER_SOCKET),
+            Backend::Podman if self.rootless => {
+                PathBuf::from(henv::var("XDG_RUNTIME_DIR").ok()?).join("podman/podman.sock")
+            }
+            Backend::Podman => PathBuf::from(PODMAN_SOCKET),
+        };
+        if socket.exists() {
+            Some(socket)
+        } else {
+            None
+        }
+    }
+
+    /// Additional arguments for `run` needed by this engine.
+    pub fn run_args(&self) -> Vec<OsString> {
+        match self.backend {
+            Backend::Docker => Vec::new(),
+            // SELinux labels would otherwise prevent the Studio from reading mounted volumes
+            Backend::Podman => vec!["--security-opt".into(), "label=disable".into()],
+        }
+    }
+}
+
+/// Determine if an image name starts with a registry host, e.g. `quay.io/habitat/studio`.
+fn has_registry(image: &str) -> bool {
+    match image.find('/') {
+        Some(index) => {
+            let host = &image[..index];
+            host.contains('.') || host.contains(':') || host == "localhost"
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backend_from_str() {
+        assert_eq!("docker".parse::<Backend>().unwrap(), Backend::Docker);
+        assert_eq!("Podman".parse::<Backend>().unwrap(), Backend::Podman);
+        assert!("lxc".parse::<Backend>().is_err());
+    }
+
+    #[test]
+    fn podman_images_are_fully_qualified() {
+        let engine = ContainerEngine { backend:  Backend::Podman,
+                                       cmd:      PathBuf::from(PODMAN_CMD),
+                                       rootless: true, };
+        assert_eq!(engine.qualified_image("habitat/default-studio-x86_64-linux:1.6.0"),
+                   "docker.io/habitat/default-studio-x86_64-linux:1.6.0");
+        assert_eq!(engine.qualified_image("quay.io/me/studio:1.6.0"),
+                   "quay.io/me/studio:1.6.0");
+        assert_eq!(engine.qualified_image("localhost:5000/studio"),
+                   "localhost:5000/studio");
+
+        let engine = ContainerEngine { backend: Backend::Docker,
+                                       ..engine };
+        assert_eq!(engine.qualified_image("habitat/default-studio-x86_64-linux:1.6.0"),
+                   "habitat/default-studio-x86_64-linux:1.6.0");
+    }
+}
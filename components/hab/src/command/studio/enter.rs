@@ -1,4 +1,5 @@
-use crate::{common::ui::{UIWriter,
+use crate::{command::studio::options::StudioOptions,
+            common::ui::{UIWriter,
                          UI},
             error::{Error,
                     Result},
@@ -121,12 +122,15 @@ pub async fn start(ui: &mut UI, args: &[OsString]) -> Result<()> {
         }
     }
 
-    inner::start(ui, args).await
+    let (options, args) = StudioOptions::extract(args)?;
+    inner::start(ui, &args, &options).await
 }
 
 #[cfg(target_os = "linux")]
 mod inner {
-    use crate::{command::studio::docker,
+    use crate::{command::studio::{docker,
+                                  engine::Backend,
+                                  options::StudioOptions},
                 common::ui::{UIWriter,
                              UI},
                 error::{Error,
@@ -150,10 +154,10 @@ mod inner {
     const STUDIO_CMD: &str = "hab-studio";
     const STUDIO_CMD_ENVVAR: &str = "HAB_STUDIO_BINARY";
 
-    pub async fn start(ui: &mut UI, args: &[OsString]) -> Result<()> {
+    pub async fn start(ui: &mut UI, args: &[OsString], options: &StudioOptions) -> Result<()> {
         rerun_with_sudo_if_needed(ui, &args)?;
         if is_docker_studio(&args) {
-            docker::start_docker_studio(ui, args)
+            docker::start_docker_studio(ui, args, options)
         } else {
             let command = match henv::var(STUDIO_CMD_ENVVAR) {
                 Ok(command) => PathBuf::from(command),
@@ -187,7 +191,9 @@ mod inner {
             };
 
             if let Some(cmd) = find_command(command.to_string_lossy().as_ref()) {
-                process::become_command(cmd, args)?;
+                let mut studio_args = options.studio_args();
+                studio_args.extend_from_slice(args);
+                process::become_command(cmd, &studio_args)?;
                 Ok(())
             } else {
                 Err(Error::ExecCommandNotFound(command))
@@ -214,8 +220,12 @@ mod inner {
 
     fn rerun_with_sudo_if_needed(ui: &mut UI, args: &[OsString]) -> Result<()> {
         // If I have root permissions or if I am executing a docker studio
-        // and have the appropriate group - early return, we are done.
-        if am_i_root() || (is_docker_studio(args) && has_docker_group()?) {
+        // and have the appropriate group - early return, we are done. Podman
+        // runs rootless so never requires `sudo`.
+        if am_i_root()
+           || (is_docker_studio(args)
+               && (Backend::from_env()? == Backend::Podman || has_docker_group()?))
+        {
             return Ok(());
         }
 
@@ -244,8 +254,10 @@ mod inner {
 
 #[cfg(not(target_os = "linux"))]
 mod inner {
-    use crate::{command::studio::docker,
-                common::ui::UI,
+    use crate::{command::studio::{docker,
+                                  options::StudioOptions},
+                common::ui::{UIWriter,
+                             UI},
                 error::{Error,
                         Result},
                 exec,
@@ -257,11 +269,15 @@ mod inner {
     use std::{ffi::OsString,
               str::FromStr};
 
-    pub async fn start(_ui: &mut UI, args: &[OsString]) -> Result<()> {
+    pub async fn start(ui: &mut UI, args: &[OsString], options: &StudioOptions) -> Result<()> {
         if is_windows_studio(&args) {
-            start_windows_studio(_ui, args).await
+            if !options.is_empty() {
+                ui.warn("The --mount and --env-pass options are only supported by Docker \
+                         Studios and will be ignored.")?;
+            }
+            start_windows_studio(ui, args).await
         } else {
-            docker::start_docker_studio(_ui, args)
+            docker::start_docker_studio(ui, args, options)
         }
     }
 
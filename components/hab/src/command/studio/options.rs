@@ -0,0 +1,197 @@
+//! The `--mount` (`-m`) and `--env-pass` (`-e`) options of `hab studio`.
+//!
+//! The Studio itself is a shell script which only understands short options, so these are
+//! removed from the arguments before they are handed off. A chroot Studio receives them back as
+//! `-m` and `-e` options while a Docker Studio turns them into container volumes and environment
+//! variables.
+use crate::error::{Error,
+                   Result};
+use std::{env,
+          ffi::OsString,
+          path::PathBuf,
+          str::FromStr};
+
+const MOUNT_OPTS: &[&str] = &["-m", "--mount"];
+const ENV_PASS_OPTS: &[&str] = &["-e", "--env-pass"];
+
+/// The Studio options which take a value. Their values must not be mistaken for a subcommand.
+const STUDIO_VALUE_OPTS: &[&str] = &["-a", "-c", "-k", "-r", "-s", "-t"];
+const STUDIO_SUBCOMMANDS: &[&str] = &["build", "enter", "help", "new", "rm", "run", "version"];
+
+/// A host path to be mounted into the Studio, given as `SRC:DST[:ro]`.
+#[derive(Debug, PartialEq)]
+pub struct StudioMount {
+    pub src:       PathBuf,
+    pub dst:       String,
+    pub read_only: bool,
+}
+
+impl FromStr for StudioMount {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let invalid = || Error::InvalidStudioMount(value.to_string());
+        let mut parts = value.split(':');
+        let src = parts.next().filter(|s| !s.is_empty()).ok_or_else(invalid)?;
+        let dst = parts.next()
+                       .filter(|d| d.starts_with('/'))
+                       .ok_or_else(invalid)?;
+        let read_only = match parts.next() {
+            None | Some("rw") => false,
+            Some("ro") => true,
+            Some(_) => return Err(invalid()),
+        };
+        if parts.next().is_some() {
+            return Err(invalid());
+        }
+        // Relative sources are resolved against the current directory as both Docker and the
+        // chroot Studio require absolute paths.
+        let src = env::current_dir()?.join(src);
+        Ok(StudioMount { src,
+                         dst: dst.to_string(),
+                         read_only })
+    }
+}
+
+impl StudioMount {
+    /// The mount in the `SRC:DST[:ro]` form understood by Docker and the Studio.
+    pub fn to_volume(&self) -> String {
+        let mut volume = format!("{}:{}", self.src.display(), self.dst);
+        if self.read_only {
+            volume.push_str(":ro");
+        }
+        volume
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct StudioOptions {
+    pub mounts:   Vec<StudioMount>,
+    pub env_pass: Vec<String>,
+}
+
+impl StudioOptions {
+    /// Removes the `--mount` and `--env-pass` options which precede the Studio subcommand from
+    /// `args`, returning them along with the remaining arguments.
+    pub fn extract(args: &[OsString]) -> Result<(Self, Vec<OsString>)> {
+        let mut options = StudioOptions::default();
+        let mut remaining = Vec::with_capacity(args.len());
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let arg_str = arg.to_string_lossy();
+            let (opt, inline_value) = match arg_str.find('=') {
+                Some(i) if arg_str.starts_with("--") => (&arg_str[..i], Some(&arg_str[i + 1..])),
+                _ => (arg_str.as_ref(), None),
+            };
+            let is_mount = MOUNT_OPTS.contains(&opt);
+            if is_mount || ENV_PASS_OPTS.contains(&opt) {
+                let value = match inline_value {
+                    Some(value) => value.to_string(),
+                    None => {
+                        args.next()
+                            .map(|v| v.to_string_lossy().into_owned())
+                            .ok_or_else(|| {
+                                Error::ArgumentError(format!("{} requires a value", opt))
+                            })?
+                    }
+                };
+                if is_mount {
+                    options.mounts.push(value.parse()?);
+                } else {
+                    options.env_pass.push(env_var_name(value)?);
+                }
+                continue;
+            }
+
+            remaining.push(arg.clone());
+            if STUDIO_SUBCOMMANDS.contains(&opt) {
+                break;
+            }
+            if STUDIO_VALUE_OPTS.contains(&opt) {
+                remaining.extend(args.next().cloned());
+            }
+        }
+        remaining.extend(args.cloned());
+        Ok((options, remaining))
+    }
+
+    pub fn is_empty(&self) -> bool { self.mounts.is_empty() && self.env_pass.is_empty() }
+
+    /// The options in the form understood by the Studio program.
+    pub fn studio_args(&self) -> Vec<OsString> {
+        let mut args: Vec<OsString> = Vec::new();
+        for mount in &self.mounts {
+            args.push("-m".into());
+            args.push(mount.to_volume().into());
+        }
+        for var in &self.env_pass {
+            args.push("-e".into());
+            args.push(var.into());
+        }
+        args
+    }
+}
+
+fn env_var_name(name: String) -> Result<String> {
+    let valid = name.chars()
+                    .next()
+                    .map_or(false, |c| c.is_ascii_alphabetic() || c == '_')
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if valid {
+        Ok(name)
+    } else {
+        Err(Error::ArgumentError(format!("Invalid environment \
+                                          variable name: {}",
+                                         name)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn os_args(args: &[&str]) -> Vec<OsString> { args.iter().map(OsString::from).collect() }
+
+    #[test]
+    fn parse_mounts() {
+        let mount: StudioMount = "/home/me/.m2:/root/.m2:ro".parse().unwrap();
+        assert_eq!(mount.src, PathBuf::from("/home/me/.m2"));
+        assert_eq!(mount.dst, "/root/.m2");
+        assert!(mount.read_only);
+        assert_eq!(mount.to_volume(), "/home/me/.m2:/root/.m2:ro");
+
+        let mount: StudioMount = "/opt/shared:/shared".parse().unwrap();
+        assert!(!mount.read_only);
+        assert_eq!(mount.to_volume(), "/opt/shared:/shared");
+
+        assert!("/opt/shared".parse::<StudioMount>().is_err());
+        assert!("/opt/shared:relative".parse::<StudioMount>().is_err());
+        assert!("/opt/shared:/shared:rx".parse::<StudioMount>().is_err());
+    }
+
+    #[test]
+    fn extract_options_before_subcommand() {
+        let args = os_args(&["-k",
+                             "core",
+                             "--mount=/a:/b",
+                             "-e",
+                             "AWS_PROFILE",
+                             "-D",
+                             "run",
+                             "--mount",
+                             "x"]);
+        let (options, remaining) = StudioOptions::extract(&args).unwrap();
+        assert_eq!(options.mounts.len(), 1);
+        assert_eq!(options.env_pass, vec!["AWS_PROFILE".to_string()]);
+        assert_eq!(remaining,
+                   os_args(&["-k", "core", "-D", "run", "--mount", "x"]));
+        assert_eq!(options.studio_args(),
+                   os_args(&["-m", "/a:/b", "-e", "AWS_PROFILE"]));
+    }
+
+    #[test]
+    fn invalid_env_pass_names_are_rejected() {
+        assert!(StudioOptions::extract(&os_args(&["--env-pass", "$(reboot)", "enter"])).is_err());
+        assert!(StudioOptions::extract(&os_args(&["--env-pass"])).is_err());
+    }
+}
@@ -0,0 +1,391 @@
+//! A live view of the services of one or more Supervisors, `top`-style.
+//!
+//! The view is redrawn periodically from the status and health check history each Supervisor
+//! reports through its Control Gateway. Changes between two refreshes (a service starting,
+//! stopping, updating to a new release, changing health or failing a hook) are listed as recent
+//! events. Services are controlled by typing a command and pressing enter, e.g. `restart 2`.
+
+use crate::error::Result;
+use chrono::Local;
+use futures::stream::StreamExt;
+use habitat_common::{types::ListenCtlAddr,
+                     ui::{UIWriter,
+                          UI}};
+use habitat_sup_client::{SrvClient,
+                         SrvClientError};
+use habitat_sup_protocol::{self as sup_proto,
+                           codec::SrvMessage,
+                           types::{DesiredState,
+                                   HealthCheckResult,
+                                   ProcessState}};
+use std::{collections::{HashMap,
+                        HashSet,
+                        VecDeque},
+          fmt,
+          io::{self,
+               BufRead,
+               Write},
+          thread,
+          time::Duration};
+use tabwriter::TabWriter;
+use tokio::{sync::mpsc,
+            time};
+
+/// How many recent events are shown.
+const MAX_EVENTS: usize = 10;
+
+const HELP: &str = "Commands: stop <#>, start <#>, restart <#>, quit";
+
+/// Show a live view of the services of `remote_sups`, refreshed every `interval`, until the user
+/// quits.
+pub async fn start(ui: &mut UI, remote_sups: &[ListenCtlAddr], interval: Duration) -> Result<()> {
+    let mut commands = read_commands();
+    let mut top = Top::default();
+    loop {
+        top.refresh(remote_sups).await;
+        top.draw(ui)?;
+        let command = tokio::select! {
+            command = commands.recv() => Some(command),
+            _ = time::delay_for(interval) => None,
+        };
+        match command {
+            // Time to refresh
+            None => {}
+            // Standard input was closed
+            Some(None) => return Ok(()),
+            Some(Some(command)) => {
+                match command.trim() {
+                    "q" | "quit" => return Ok(()),
+                    "" => {}
+                    command => top.run(command).await,
+                }
+            }
+        }
+    }
+}
+
+/// Read lines from standard input on a thread of their own, so waiting for the user doesn't hold
+/// up refreshing the view.
+fn read_commands() -> mpsc::UnboundedReceiver<String> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            match line {
+                Ok(line) if tx.send(line).is_ok() => {}
+                _ => break,
+            }
+        }
+    });
+    rx
+}
+
+/// The state of one service, as of the last refresh.
+#[derive(Clone)]
+struct Row {
+    remote_sup:    ListenCtlAddr,
+    ident:         sup_proto::types::PackageIdent,
+    service_group: String,
+    desired:       DesiredState,
+    state:         ProcessState,
+    pid:           Option<u32>,
+    elapsed:       Option<u64>,
+    health:        Option<HealthCheckResult>,
+    hook_failure:  Option<(String, u64)>,
+}
+
+struct Event {
+    time:    String,
+    message: String,
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}  {}", self.time, self.message)
+    }
+}
+
+#[derive(Default)]
+struct Top {
+    rows:        Vec<Row>,
+    /// The Supervisors that couldn't be reached on the last refresh, and why.
+    unreachable: Vec<(ListenCtlAddr, String)>,
+    events:      VecDeque<Event>,
+    /// Services that were stopped to be restarted and are to be started again once down.
+    restarting:  HashSet<(ListenCtlAddr, String)>,
+    /// The outcome of the last command.
+    message:     Option<String>,
+}
+
+impl Top {
+    async fn refresh(&mut self, remote_sups: &[ListenCtlAddr]) {
+        let mut rows = Vec::new();
+        self.unreachable.clear();
+        for remote_sup in remote_sups {
+            match status(remote_sup).await {
+                Ok(mut sup_rows) => rows.append(&mut sup_rows),
+                Err(e) => self.unreachable.push((*remote_sup, e.to_string())),
+            }
+        }
+        // Events are only worked out once there is something to compare with
+        if !self.rows.is_empty() {
+            let previous = self.rows
+                               .iter()
+                               .map(|row| ((row.remote_sup, row.service_group.clone()), row))
+                               .collect::<HashMap<_, _>>();
+            let current = rows.iter()
+                              .map(|row| (row.remote_sup, row.service_group.clone()))
+                              .collect::<HashSet<_>>();
+            let mut messages = Vec::new();
+            for row in &rows {
+                match previous.get(&(row.remote_sup, row.service_group.clone())) {
+                    Some(before) => messages.extend(changes(before, row)),
+                    None => messages.push(format!("{} loaded on {}", row.ident, row.remote_sup)),
+                }
+            }
+            // Services of a Supervisor that couldn't be reached may well still be loaded
+            for (key, before) in &previous {
+                if !current.contains(key)
+                   && !self.unreachable
+                           .iter()
+                           .any(|(remote_sup, _)| *remote_sup == before.remote_sup)
+                {
+                    messages.push(format!("{} unloaded from {}", before.ident, before.remote_sup));
+                }
+            }
+            let time = Local::now().format("%H:%M:%S").to_string();
+            for message in messages {
+                self.events.push_front(Event { time: time.clone(),
+                                               message });
+            }
+            self.events.truncate(MAX_EVENTS);
+        }
+        self.rows = rows;
+
+        // Complete restarts of services that have come down
+        let restarting = self.restarting
+                             .iter()
+                             .filter(|(remote_sup, service_group)| {
+                                 self.rows.iter().any(|row| {
+                                                     row.remote_sup == *remote_sup
+                                                     && row.service_group == *service_group
+                                                     && row.desired == DesiredState::DesiredDown
+                                                 })
+                             })
+                             .cloned()
+                             .collect::<Vec<_>>();
+        for key in restarting {
+            self.restarting.remove(&key);
+            if let Some(row) = self.row_for(&key) {
+                let msg = sup_proto::ctl::SvcStart { ident: Some(row.ident.clone()), };
+                if let Err(e) = send(&key.0, msg).await {
+                    self.message = Some(format!("Unable to start {}: {}", key.1, e));
+                }
+            }
+        }
+    }
+
+    fn row_for(&self, (remote_sup, service_group): &(ListenCtlAddr, String)) -> Option<&Row> {
+        self.rows
+            .iter()
+            .find(|row| row.remote_sup == *remote_sup && row.service_group == *service_group)
+    }
+
+    /// Run a command typed by the user.
+    async fn run(&mut self, command: &str) {
+        let mut words = command.split_whitespace();
+        let action = words.next().unwrap_or_default();
+        let row = match words.next()
+                             .and_then(|n| n.parse::<usize>().ok())
+                             .and_then(|n| n.checked_sub(1))
+                             .and_then(|n| self.rows.get(n))
+        {
+            Some(row) => row.clone(),
+            None => {
+                self.message = Some(format!("Unknown command '{}'. {}", command, HELP));
+                return;
+            }
+        };
+        let ident = Some(row.ident.clone());
+        let result = match action {
+            "stop" => {
+                send(&row.remote_sup,
+                     sup_proto::ctl::SvcStop { ident,
+                                               timeout_in_seconds: None }).await
+            }
+            "start" => send(&row.remote_sup, sup_proto::ctl::SvcStart { ident }).await,
+            "restart" => {
+                let result = send(&row.remote_sup,
+                                  sup_proto::ctl::SvcStop { ident,
+                                                            timeout_in_seconds: None }).await;
+                if result.is_ok() {
+                    self.restarting
+                        .insert((row.remote_sup, row.service_group.clone()));
+                }
+                result
+            }
+            _ => {
+                self.message = Some(format!("Unknown command '{}'. {}", command, HELP));
+                return;
+            }
+        };
+        self.message = Some(match result {
+                                Ok(()) => {
+                                    format!("Requested {} of {} on {}",
+                                            action, row.service_group, row.remote_sup)
+                                }
+                                Err(e) => {
+                                    format!("Unable to {} {}: {}", action, row.service_group, e)
+                                }
+                            });
+    }
+
+    fn draw(&self, ui: &mut UI) -> io::Result<()> {
+        let out = ui.out();
+        // Clear the screen and move to its top left corner
+        write!(out, "\x1b[2J\x1b[H")?;
+        writeln!(out,
+                 "hab top - {} - {} services on {} Supervisors\n",
+                 Local::now().format("%H:%M:%S"),
+                 self.rows.len(),
+                 self.rows
+                     .iter()
+                     .map(|row| row.remote_sup)
+                     .collect::<HashSet<_>>()
+                     .len())?;
+        let mut table = TabWriter::new(&mut *out);
+        writeln!(table,
+                 "#\tsupervisor\tservice group\tpackage\tdesired\tstate\tpid\telapsed \
+                  (s)\thealth")?;
+        for (i, row) in self.rows.iter().enumerate() {
+            writeln!(table,
+                     "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                     i + 1,
+                     row.remote_sup,
+                     row.service_group,
+                     row.ident,
+                     row.desired,
+                     row.state,
+                     row.pid
+                        .map_or_else(|| "<none>".to_string(), |pid| pid.to_string()),
+                     row.elapsed
+                        .map_or_else(|| "<none>".to_string(), |e| e.to_string()),
+                     row.health
+                        .map_or_else(|| "<none>".to_string(), |h| h.to_string()))?;
+        }
+        table.flush()?;
+        drop(table);
+        for (remote_sup, error) in &self.unreachable {
+            writeln!(out,
+                     "Unable to reach the Supervisor at {}: {}",
+                     remote_sup, error)?;
+        }
+        writeln!(out, "\nRecent events")?;
+        for event in &self.events {
+            writeln!(out, "{}", event)?;
+        }
+        writeln!(out)?;
+        if let Some(ref message) = self.message {
+            writeln!(out, "{}", message)?;
+        }
+        write!(out, "{}\n> ", HELP)?;
+        out.flush()
+    }
+}
+
+/// What changed about a service between two refreshes.
+fn changes(before: &Row, after: &Row) -> Vec<String> {
+    let mut changes = Vec::new();
+    let service = format!("{} on {}", after.service_group, after.remote_sup);
+    if before.ident != after.ident {
+        changes.push(format!("{} updated from {} to {}",
+                             service, before.ident, after.ident));
+    }
+    if before.state != after.state || (after.state == ProcessState::Up && before.pid != after.pid) {
+        let change = match after.state {
+            ProcessState::Up => "started",
+            ProcessState::Down => "stopped",
+        };
+        changes.push(format!("{} {}", service, change));
+    }
+    if before.health != after.health {
+        if let Some(health) = after.health {
+            changes.push(format!("{} health is {}", service, health));
+        }
+    }
+    if before.hook_failure != after.hook_failure {
+        if let Some((ref hook, _)) = after.hook_failure {
+            changes.push(format!("{} {} hook failed", service, hook));
+        }
+    }
+    changes
+}
+
+/// The state of the services loaded in the Supervisor at `remote_sup`.
+async fn status(remote_sup: &ListenCtlAddr) -> Result<Vec<Row>> {
+    let mut rows = Vec::new();
+    let mut response = SrvClient::request(remote_sup, sup_proto::ctl::SvcStatus::default()).await?;
+    while let Some(message_result) = response.next().await {
+        let reply = message_result?;
+        check_reply(&reply)?;
+        if reply.message_id() == "ServiceStatus" {
+            let status = reply.parse::<sup_proto::types::ServiceStatus>()
+                              .map_err(SrvClientError::Decode)?;
+            let process = status.process.unwrap_or_default();
+            let hook_failure = status.last_hook_failure
+                                     .map(|failure| (failure.hook, failure.timestamp));
+            rows.push(Row { remote_sup: *remote_sup,
+                            ident: status.ident,
+                            service_group: status.service_group.to_string(),
+                            desired: status.desired_state
+                                           .and_then(DesiredState::from_i32)
+                                           .unwrap_or(DesiredState::DesiredNone),
+                            state: ProcessState::from_i32(process.state).unwrap_or_default(),
+                            pid: process.pid,
+                            elapsed: process.elapsed,
+                            health: None,
+                            hook_failure });
+        }
+    }
+
+    let msg = sup_proto::ctl::SvcHealthHistory { ident: None };
+    let mut response = SrvClient::request(remote_sup, msg).await?;
+    while let Some(message_result) = response.next().await {
+        let reply = message_result?;
+        check_reply(&reply)?;
+        if reply.message_id() == "HealthCheckHistory" {
+            let history = reply.parse::<sup_proto::types::HealthCheckHistory>()
+                               .map_err(SrvClientError::Decode)?;
+            let service_group = history.service_group.to_string();
+            let health = history.records
+                                .last()
+                                .and_then(|record| HealthCheckResult::from_i32(record.result));
+            if let Some(row) = rows.iter_mut()
+                                   .find(|row| row.service_group == service_group)
+            {
+                row.health = health;
+            }
+        }
+    }
+    rows.sort_by(|a, b| a.service_group.cmp(&b.service_group));
+    Ok(rows)
+}
+
+/// Send `msg` to the Supervisor at `remote_sup`. Unlike `gateway_util::send`, the messages the
+/// Supervisor replies with aren't printed, as they would mess up the view.
+async fn send(remote_sup: &ListenCtlAddr, msg: impl Into<SrvMessage> + fmt::Debug) -> Result<()> {
+    let mut response = SrvClient::request(remote_sup, msg).await?;
+    while let Some(message_result) = response.next().await {
+        check_reply(&message_result?)?;
+    }
+    Ok(())
+}
+
+/// Turn a `NetErr` reply into an error.
+fn check_reply(reply: &SrvMessage) -> Result<()> {
+    if reply.message_id() == "NetErr" {
+        let err = reply.parse::<sup_proto::net::NetErr>()
+                       .map_err(SrvClientError::Decode)?;
+        return Err(SrvClientError::from(err).into());
+    }
+    Ok(())
+}
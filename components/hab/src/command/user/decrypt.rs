@@ -0,0 +1,19 @@
+use crate::error::Result;
+use habitat_core::crypto::keys::{KeyCache,
+                                 SignedBox};
+use std::io::{self,
+              Write};
+
+/// Decrypt a message encrypted with `hab user encrypt`, with the secret key of the user it was
+/// encrypted for and the public key of the user who sent it, writing it to standard output.
+pub fn start(message: &str, key_cache: &KeyCache) -> Result<()> {
+    let signed: SignedBox = message.parse()?;
+    let receiver = key_cache.user_secret_encryption_key(signed.decryptor())?;
+    let sender = key_cache.user_public_encryption_key(signed.encryptor())?;
+    let data = receiver.decrypt_user_message(&signed, &sender)?;
+    debug!("Decrypted message from {} to {}",
+           signed.encryptor(),
+           signed.decryptor());
+    io::stdout().write_all(&data)?;
+    Ok(())
+}
@@ -0,0 +1,15 @@
+use crate::error::Result;
+use habitat_core::crypto::keys::KeyCache;
+use std::io::{self,
+              Write};
+
+/// Encrypt `data` from the latest key of `sender` to the latest key of `receiver`, writing the
+/// encrypted message to standard output. Only `receiver` can decrypt it, with `hab user decrypt`,
+/// and knows it was sent by `sender`.
+pub fn start(data: &[u8], sender: &str, receiver: &str, key_cache: &KeyCache) -> Result<()> {
+    let sender = key_cache.latest_user_secret_key(sender)?;
+    let receiver = key_cache.latest_user_public_key(receiver)?;
+    let signed = sender.encrypt_for_user(data, &receiver);
+    writeln!(io::stdout(), "{}", signed)?;
+    Ok(())
+}
@@ -0,0 +1,15 @@
+use crate::{cli::KeyType,
+            error::Result};
+use habitat_core::crypto::keys::{KeyCache,
+                                 KeyFile};
+use std::{io,
+          io::Write};
+
+pub fn start(user: &str, key_type: KeyType, key_cache: &KeyCache) -> Result<()> {
+    let contents = match key_type {
+        KeyType::Public => key_cache.latest_user_public_key(user)?.to_key_string(),
+        KeyType::Secret => key_cache.latest_user_secret_key(user)?.to_key_string(),
+    };
+    io::stdout().write_all(contents.as_bytes())?;
+    Ok(())
+}
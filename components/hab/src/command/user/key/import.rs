@@ -0,0 +1,29 @@
+use crate::{common::ui::{UIWriter,
+                         UI},
+            error::Result};
+use habitat_core::{crypto::keys::{Key,
+                                  KeyCache,
+                                  UserPublicEncryptionKey,
+                                  UserSecretEncryptionKey},
+                   error::Error as CoreError};
+
+pub fn start(ui: &mut UI, content: &str, key_cache: &KeyCache) -> Result<()> {
+    ui.begin("Importing user key from standard input")?;
+
+    // Service keys share the format of user keys, but are named `service.group@org`
+    if let Ok(key) = content.parse::<UserPublicEncryptionKey>() {
+        if !key.named_revision().name().contains('@') {
+            key_cache.write_key(&key)?;
+            ui.end(format!("Imported public user key {}", &key.named_revision()))?;
+            return Ok(());
+        }
+    } else if let Ok(key) = content.parse::<UserSecretEncryptionKey>() {
+        if !key.named_revision().name().contains('@') {
+            key_cache.write_key(&key)?;
+            ui.end(format!("Imported secret user key {}", &key.named_revision()))?;
+            return Ok(());
+        }
+    }
+    let msg = "Could not parse content as a public or secret user key!";
+    Err(CoreError::CryptoError(msg.to_string()).into())
+}
@@ -0,0 +1,30 @@
+use crate::{common::ui::{Status,
+                         UIWriter,
+                         UI},
+            error::Result};
+use habitat_core::crypto::keys::{Key,
+                                 KeyCache};
+use serde_json::json;
+
+/// List the user public keys in the key cache, and whether their secret keys are there too.
+pub fn start(ui: &mut UI, user: Option<&str>, key_cache: &KeyCache) -> Result<()> {
+    ui.begin(format!("Listing user keys in {}", key_cache.as_ref().display()))?;
+    let mut keys = Vec::new();
+    for public in key_cache.user_public_keys(user)? {
+        let named_revision = public.named_revision();
+        let has_secret = key_cache.user_secret_encryption_key(named_revision).is_ok();
+        if has_secret {
+            ui.status(Status::Found,
+                      format!("{} (public and secret key)", named_revision))?;
+        } else {
+            ui.status(Status::Found, format!("{} (public key)", named_revision))?;
+        }
+        keys.push(json!({ "name": named_revision.name(),
+                          "revision": named_revision.to_string(),
+                          "secret": has_secret }));
+    }
+    ui.end(format!("Found {} user keys.", keys.len()))?;
+    ui.result(&json!({ "keys": keys,
+                       "cache_path": key_cache.as_ref() }))?;
+    Ok(())
+}
@@ -41,6 +41,7 @@ pub enum Error {
     DockerFileSharingNotEnabled,
     DockerImageNotFound(String),
     DockerNetworkDown(String),
+    DoctorFailures(usize),
     EnvJoinPathsError(env::JoinPathsError),
     ErrorPerIdent(HashMap<PackageIdent, Error>),
     ExecCommandNotFound(PathBuf),
@@ -50,13 +51,18 @@ pub enum Error {
     HabitatCore(hcore::Error),
     // Boxed due to clippy::large_enum_variant
     HandlebarsRenderError(Box<handlebars::TemplateRenderError>),
+    HookTestsFailed(usize /* failed */, usize /* total */),
+    InvalidStudioMount(String),
+    InvalidSvcConfigFiles(Vec<(PathBuf, String)>),
     IO(io::Error),
     JobGroupPromoteOrDemote(api_client::Error, bool /* promote */),
     JobGroupCancel(api_client::Error),
     JobGroupPromoteOrDemoteUnprocessable(bool /* promote */),
+    JobLog(api_client::Error),
     JsonErr(serde_json::Error),
     KeyTypeParseError(String),
     LicenseNotAccepted,
+    LockfilePackageNotFound(PathBuf, PackageIdent),
     NameLookup,
     NetErr(net::NetErr),
     PackageArchiveMalformed(String),
@@ -65,10 +71,13 @@ pub enum Error {
     ParseUrlError(url::ParseError),
     PathPrefixError(path::StripPrefixError),
     ProvidesError(String),
+    RemoteNotFound(String),
+    RingKeyDistributionFailed(usize /* failed */, usize /* total */),
     RootRequired,
     ScheduleStatus(api_client::Error),
     SubcommandNotSupported(String),
     UnsupportedExportFormat(String),
+    UnsupportedStudioBackend(String),
     TomlDeserializeError(toml::de::Error),
     TomlSerializeError(toml::ser::Error),
     Utf8Error(String),
@@ -136,6 +145,11 @@ impl fmt::Display for Error {
                          HAB_DOCKER_STUDIO_IMAGE environment variable.",
                         e)
             }
+            Error::DoctorFailures(n) => {
+                format!("{} check{} failed; see the findings above",
+                        n,
+                        if n == 1 { "" } else { "s" })
+            }
             Error::EnvJoinPathsError(ref err) => format!("{}", err),
             Error::ErrorPerIdent(ref e) => {
                 e.iter()
@@ -152,6 +166,21 @@ impl fmt::Display for Error {
             Error::HabitatCommon(ref e) => e.to_string(),
             Error::HabitatCore(ref e) => e.to_string(),
             Error::HandlebarsRenderError(ref e) => e.to_string(),
+            Error::HookTestsFailed(failed, total) => {
+                format!("{} of {} hook tests failed", failed, total)
+            }
+            Error::InvalidStudioMount(ref m) => {
+                format!("Invalid Studio mount '{}', expected SRC:DST[:ro] where DST is an \
+                         absolute path",
+                        m)
+            }
+            Error::InvalidSvcConfigFiles(ref files) => {
+                let files = files.iter()
+                                 .map(|(path, e)| format!("{}: {}", path.display(), e))
+                                 .collect::<Vec<_>>()
+                                 .join("\n");
+                format!("Invalid service config files:\n{}", files)
+            }
             Error::IO(ref err) => format!("{}", err),
             Error::JobGroupPromoteOrDemoteUnprocessable(true) => {
                 "Failed to promote job group, the build job is still in progress".to_string()
@@ -166,8 +195,14 @@ impl fmt::Display for Error {
             }
             Error::JsonErr(ref e) => e.to_string(),
             Error::JobGroupCancel(ref e) => format!("Failed to cancel job group: {:?}", e),
+            Error::JobLog(ref e) => format!("Failed to retrieve job log: {:?}", e),
             Error::KeyTypeParseError(ref s) => format!("Failed to parse key type: {}", s),
             Error::LicenseNotAccepted => "License agreement not accepted".to_string(),
+            Error::LockfilePackageNotFound(ref path, ref ident) => {
+                format!("Lockfile {} does not pin a release of {} for this target",
+                        path.display(),
+                        ident)
+            }
             Error::NameLookup => "Error resolving a name or IP address".to_string(),
             Error::NetErr(ref e) => e.to_string(),
             Error::PackageArchiveMalformed(ref e) => {
@@ -181,6 +216,14 @@ impl fmt::Display for Error {
             Error::ParseUrlError(ref err) => format!("{}", err),
             Error::PathPrefixError(ref err) => format!("{}", err),
             Error::ProvidesError(ref err) => format!("Can't find {}", err),
+            Error::RemoteNotFound(ref name) => {
+                format!("There is no remote Supervisor named '{}'. See `hab remote list`.",
+                        name)
+            }
+            Error::RingKeyDistributionFailed(failed, total) => {
+                format!("The ring key could not be distributed to {} of {} peers",
+                        failed, total)
+            }
             Error::RootRequired => {
                 "Root or administrator permissions required to complete operation".to_string()
             }
@@ -189,6 +232,10 @@ impl fmt::Display for Error {
                 format!("Subcommand `{}' not supported on this operating system", e)
             }
             Error::UnsupportedExportFormat(ref e) => format!("Unsupported export format: {}", e),
+            Error::UnsupportedStudioBackend(ref b) => {
+                format!("Unsupported studio backend: {}. Supported backends are docker and podman",
+                        b)
+            }
             Error::TomlDeserializeError(ref e) => format!("Can't deserialize TOML: {}", e),
             Error::TomlSerializeError(ref e) => format!("Can't serialize TOML: {}", e),
             Error::Utf8Error(ref e) => format!("Error processing a string as UTF-8: {}", e),
@@ -1,7 +1,8 @@
 use crate::{common::{self,
-                     command::package::install::{InstallHookMode,
-                                                 InstallMode,
-                                                 LocalPackageUsage},
+                     command::package::{install::{InstallHookMode,
+                                                  InstallMode,
+                                                  LocalPackageUsage},
+                                        relocate::RelocateMode},
                      ui::{Status,
                           UIWriter,
                           UI}},
@@ -108,7 +109,8 @@ async fn command_from_min_pkg_with_optional_channel(ui: &mut UI,
                                                          // TODO (CM): pass through and enable
                                                          // no-local-package mode
                                                          &LocalPackageUsage::default(),
-                                                         InstallHookMode::default()).await
+                                                         InstallHookMode::default(),
+                                                         RelocateMode::default()).await
             }).await
               .map_err(|e| CommonError::PackageFailedToInstall(ident.clone(), Box::new(e.error)))?
         }
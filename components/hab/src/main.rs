@@ -15,7 +15,10 @@ use configopt::{ConfigOpt,
 use futures::stream::StreamExt;
 use hab::{cli::{self,
                 gateway_util,
-                hab::{license::License,
+                hab::{cli::{Cli,
+                            CompletionSource},
+                      doctor::Doctor,
+                      license::License,
                       origin::{Origin,
                                Rbac,
                                RbacSet,
@@ -23,6 +26,7 @@ use hab::{cli::{self,
                       pkg::{ExportCommand as PkgExportCommand,
                             Pkg,
                             PkgExec},
+                      remote::Remote,
                       sup::{HabSup,
                             Secret,
                             Sup},
@@ -31,15 +35,18 @@ use hab::{cli::{self,
                             Load as SvcLoad,
                             Svc},
                       util::{bldr_auth_token_from_args_env_or_load,
-                             bldr_url_from_args_env_load_or_default},
+                             bldr_url_from_args_env_load_or_default,
+                             resolve_remote_sup},
                       Hab},
                 parse_optional_arg,
                 KeyType},
           command::{self,
+                    bldr::channel::promote_on_green::PromotionPolicy,
                     pkg::{download::{PackageSet,
                                      PackageSetFile},
                           list::ListingType,
-                          uninstall::UninstallHookMode}},
+                          uninstall::UninstallHookMode},
+                    plan::init::PlanTemplate},
           error::{Error,
                   Result},
           license,
@@ -51,12 +58,14 @@ use hab::{cli::{self,
           VERSION};
 use habitat_api_client::BuildOnUpload;
 use habitat_common::{self as common,
-                     cli::key_cache_from_matches,
+                     cli::{key_cache_from_matches,
+                           CTL_TIMEOUT_ENVVAR},
                      cli_config::CliConfig,
-                     command::package::install::{InstallHookMode,
-                                                 InstallMode,
-                                                 InstallSource,
-                                                 LocalPackageUsage},
+                     command::package::{install::{InstallHookMode,
+                                                  InstallMode,
+                                                  InstallSource,
+                                                  LocalPackageUsage},
+                                        relocate::RelocateMode},
                      types::ListenCtlAddr,
                      ui::{self,
                           Status,
@@ -97,9 +106,15 @@ use std::{collections::HashMap,
           result,
           str::FromStr,
           string::ToString,
-          thread};
+          thread,
+          time::Duration};
 use tabwriter::TabWriter;
 
+/// The global option selecting the format command results are reported in
+const OUTPUT_OPTION: &str = "--output";
+/// The global option giving the seconds to wait for the Supervisor to complete a request
+const CTL_TIMEOUT_OPTION: &str = "--ctl-timeout";
+
 /// Makes the --org CLI param optional when this env var is set
 const HABITAT_ORG_ENVVAR: &str = "HAB_ORG";
 /// Makes the --user CLI param optional when this env var is set
@@ -115,6 +130,14 @@ lazy_static! {
              "pid",
              "group",]
     };
+    static ref HEALTH_HISTORY_HEADER: Vec<&'static str> =
+        { vec!["group", "time", "result", "duration (ms)", "output",] };
+    static ref CONFIG_AUDIT_HEADER: Vec<&'static str> = {
+        vec!["time", "group", "file", "incarnation", "encrypted", "hash", "client",]
+    };
+    /// The values of the global options and the arguments `hab` was called with, less those
+    /// options.
+    static ref ARGS: (GlobalOptions, Vec<OsString>) = split_global_options(env::args_os());
 }
 
 #[tokio::main]
@@ -122,8 +145,15 @@ async fn main() {
     env_logger::init();
     let mut ui = UI::default_with_env();
     let flags = FeatureFlag::from_env(&mut ui);
+    habitat_api_client::set_rate_limit_observer(|delay: std::time::Duration| {
+        UI::default_with_env().warn(format!("Builder is rate limiting requests, retrying in {}s",
+                                            delay.as_secs()))
+                              .ok();
+    });
     if let Err(e) = start(&mut ui, flags).await {
         let exit_code = e.exit_code();
+        ui.result(&serde_json::json!({ "error": e.to_string() }))
+          .unwrap();
         ui.fatal(e).unwrap();
         std::process::exit(exit_code)
     }
@@ -131,13 +161,40 @@ async fn main() {
 
 #[allow(clippy::cognitive_complexity)]
 async fn start(ui: &mut UI, feature_flags: FeatureFlag) -> Result<()> {
-    let hab = Hab::try_from_args_with_configopt();
+    if let Some(output_format) = &ARGS.0.output {
+        ui::set_output_format(output_format.parse()?);
+    }
+    let ctl_timeout = ARGS.0
+                          .ctl_timeout
+                          .clone()
+                          .or_else(|| henv::var(CTL_TIMEOUT_ENVVAR).ok());
+    if let Some(ctl_timeout) = ctl_timeout {
+        let invalid = format!("'{}' is not a number of seconds to wait for the Supervisor",
+                              ctl_timeout);
+        let secs = ctl_timeout.parse()
+                              .map_err(|_| Error::ArgumentError(invalid))?;
+        habitat_sup_client::set_request_timeout(Some(Duration::from_secs(secs)));
+    }
+    let hab = Hab::try_from_iter_with_configopt(ARGS.1.iter());
 
     if let Ok(Hab::License(License::Accept)) = hab {
         license::accept_license(ui)?;
         return Ok(());
     }
 
+    // The command-line completers call back into `hab` to list candidates while the user types, so
+    // this must never prompt for the license or print anything but the candidates.
+    if let Ok(Hab::Cli(Cli::Complete { source, remote_sup })) = hab {
+        match source {
+            CompletionSource::Services => {
+                command::cli::complete::services(remote_sup.as_deref()).await
+            }
+            CompletionSource::Idents => command::cli::complete::idents(),
+            CompletionSource::Remotes => command::cli::complete::remotes(),
+        }
+        return Ok(());
+    }
+
     // Allow checking version information and displaying command help without accepting the license.
     // TODO (DM): To prevent errors in discrepancy between the structopt and cli versions only do
     // this when the license has not yet been accepted. When we switch fully to structopt this can
@@ -159,10 +216,10 @@ async fn start(ui: &mut UI, feature_flags: FeatureFlag) -> Result<()> {
     // `hab` binary and the `hab-sup` binary. Potential fixes:
     // 1. Handle all `hab sup` subcommands with the `hab-sup` binary
     // 2. Have a dedicated subcommand for commands handled by the `hab-sup` binary
-    let mut args = env::args();
-    if matches!((args.next().unwrap_or_default().as_str(),
-                 args.next().unwrap_or_default().as_str(),
-                 args.next().unwrap_or_default().as_str()),
+    let mut args = ARGS.1.iter().map(|arg| arg.to_string_lossy());
+    if matches!((&*args.next().unwrap_or_default(),
+                 &*args.next().unwrap_or_default(),
+                 &*args.next().unwrap_or_default()),
                  (_, "sup", "--version") | (_, "sup", "-V"))
     {
         return command::sup::start(ui, &args_after_first(2)).await;
@@ -176,6 +233,7 @@ async fn start(ui: &mut UI, feature_flags: FeatureFlag) -> Result<()> {
         Ok(hab) => {
             #[allow(clippy::single_match)]
             match hab {
+                Hab::Doctor(doctor) => return sub_doctor(ui, doctor, feature_flags).await,
                 Hab::Origin(origin) => {
                     match origin {
                         // hab origin rbac set|show
@@ -200,6 +258,20 @@ async fn start(ui: &mut UI, feature_flags: FeatureFlag) -> Result<()> {
                              update your automation and processes accordingly.")?;
                     return command::launcher::start(ui, sup_run, &args_after_first(1)).await;
                 }
+                Hab::Remote(remote) => {
+                    match remote {
+                        Remote::Add { name,
+                                      address,
+                                      secret_file, } => {
+                            return command::remote::add(ui,
+                                                        &name,
+                                                        &address,
+                                                        secret_file.as_deref());
+                        }
+                        Remote::List => return command::remote::list(),
+                        Remote::Remove { name } => return command::remote::remove(ui, &name),
+                    }
+                }
                 Hab::Studio(studio) => {
                     return command::studio::enter::start(ui, studio.args()).await;
                 }
@@ -225,18 +297,28 @@ async fn start(ui: &mut UI, feature_flags: FeatureFlag) -> Result<()> {
                                          remote_sup, } => {
                             return sub_sup_depart(member_id, &remote_sup.to_listen_ctl_addr()).await;
                         }
+                        HabSup::PurgeDeparted { older_than,
+                                                remote_sup, } => {
+                            return sub_sup_purge_departed(older_than,
+                                                          &remote_sup.to_listen_ctl_addr()).await;
+                        }
                         HabSup::Secret(Secret::Generate) => {
                             return sub_sup_secret_generate();
                         }
-                        HabSup::Status { pkg_ident,
+                        HabSup::Status { pkg_ident: Some(pkg_ident),
                                          remote_sup, } => {
                             ui.warn("'hab sup status' as an alias for 'hab svc status' is \
                                      deprecated. Please update your automation and processes \
                                      accordingly.")?;
-                            return sub_svc_status(pkg_ident, &remote_sup.to_listen_ctl_addr()).await;
+                            return sub_svc_status(Some(pkg_ident),
+                                                  &remote_sup.to_listen_ctl_addr()).await;
+                        }
+                        HabSup::Status { pkg_ident: None,
+                                         remote_sup, } => {
+                            return sub_sup_status(ui, &remote_sup.to_listen_ctl_addr()).await;
                         }
-                        HabSup::Restart { remote_sup } => {
-                            return sub_sup_restart(&remote_sup.to_listen_ctl_addr()).await;
+                        HabSup::Restart { remote_sup, args } => {
+                            return sub_sup_restart(&remote_sup.to_listen_ctl_addr(), args).await;
                         }
                     }
                 }
@@ -250,18 +332,43 @@ async fn start(ui: &mut UI, feature_flags: FeatureFlag) -> Result<()> {
                             }
                         }
                         Svc::Load(svc_load) => {
-                            return sub_svc_load(svc_load).await;
+                            let idents = svc_load.idents()?;
+                            let remote_sup = svc_load.remote_sup.to_listen_ctl_addr();
+                            sub_svc_load_many(&remote_sup, svc_load.into_ctl_msgs()?).await?;
+                            let idents: Vec<String> =
+                                idents.iter().map(ToString::to_string).collect();
+                            // `ident` is kept for scripts written before several could be loaded
+                            ui.result(&serde_json::json!({ "ident": idents[0],
+                                                           "idents": idents,
+                                                           "remote_sup": remote_sup.to_string() }))?;
+                            return Ok(());
                         }
                         Svc::Update(svc_update) => return sub_svc_update(svc_update).await,
                         Svc::Status { pkg_ident,
-                                      remote_sup, } => {
-                            return sub_svc_status(pkg_ident, &remote_sup.to_listen_ctl_addr()).await;
+                                      history,
+                                      remote_sup,
+                                      remote_ring, } => {
+                            if let Some(bastion) = remote_ring {
+                                return command::service::status::start(ui,
+                                                                       &bastion,
+                                                                       pkg_ident.as_ref()).await;
+                            }
+                            let remote_sup = remote_sup.to_listen_ctl_addr();
+                            if history {
+                                return sub_svc_health_history(pkg_ident, &remote_sup).await;
+                            }
+                            return sub_svc_status(pkg_ident, &remote_sup).await;
                         }
                         _ => {
                             // All other commands will be caught by the CLI parsing logic below.
                         }
                     }
                 }
+                Hab::Top(top) => {
+                    return command::top::start(ui,
+                                               &top.remote_sup,
+                                               Duration::from_secs(top.interval)).await;
+                }
                 #[cfg(not(target_os = "macos"))]
                 Hab::Term => {
                     ui.warn("'hab term' as an alias for 'hab sup term' is deprecated. Please \
@@ -276,6 +383,10 @@ async fn start(ui: &mut UI, feature_flags: FeatureFlag) -> Result<()> {
                                 PkgExportCommand::Cf(args) => {
                                     return command::pkg::export::cf::start(ui, &args.args).await;
                                 }
+                                #[cfg(target_os = "linux")]
+                                PkgExportCommand::Compose(args) => {
+                                    return command::pkg::export::compose::start(ui, &args.args).await;
+                                }
                                 #[cfg(any(target_os = "linux", target_os = "windows"))]
                                 PkgExportCommand::Container(args) => {
                                     return command::pkg::export::container::start(ui, &args.args).await;
@@ -288,6 +399,10 @@ async fn start(ui: &mut UI, feature_flags: FeatureFlag) -> Result<()> {
                                     return command::pkg::export::container::start(ui, &args.args).await;
                                 }
                                 #[cfg(target_os = "linux")]
+                                PkgExportCommand::Ecs(args) => {
+                                    return command::pkg::export::ecs::start(ui, &args.args).await;
+                                }
+                                #[cfg(target_os = "linux")]
                                 PkgExportCommand::Mesos(args) => {
                                     return command::pkg::export::mesos::start(ui, &args.args).await;
                                 }
@@ -299,10 +414,18 @@ async fn start(ui: &mut UI, feature_flags: FeatureFlag) -> Result<()> {
                         }
                         Pkg::Exec(PkgExec { pkg_ident,
                                             cmd,
+                                            clean_env,
+                                            keep_env,
                                             args, }) => {
+                            let env = if clean_env {
+                                command::pkg::exec::Env::Clean(keep_env)
+                            } else {
+                                command::pkg::exec::Env::Current
+                            };
                             return command::pkg::exec::start(&pkg_ident.pkg_ident(),
                                                              cmd,
-                                                             &args.args);
+                                                             &args.args,
+                                                             &env);
                         }
                         _ => {
                             // All other commands will be caught by the CLI parsing logic below.
@@ -327,7 +450,8 @@ async fn start(ui: &mut UI, feature_flags: FeatureFlag) -> Result<()> {
     // https://github.com/kbknapp/clap-rs/issues/86
     let child = thread::Builder::new().stack_size(8 * 1024 * 1024)
                                       .spawn(move || {
-                                          cli::get(feature_flags).get_matches_safe()
+                                          cli::get(feature_flags).get_matches_from_safe(ARGS.1
+                                                                                            .iter())
                                                                  .unwrap_or_else(|e| {
                                                                      e.exit();
                                                                  })
@@ -351,6 +475,7 @@ async fn start(ui: &mut UI, feature_flags: FeatureFlag) -> Result<()> {
         ("config", Some(m)) => {
             match m.subcommand() {
                 ("apply", Some(m)) => sub_svc_set(m).await?,
+                ("audit", Some(m)) => sub_config_audit(m).await?,
                 ("show", Some(m)) => sub_svc_config(m).await?,
                 _ => unreachable!(),
             }
@@ -385,6 +510,9 @@ async fn start(ui: &mut UI, feature_flags: FeatureFlag) -> Result<()> {
                         ("export", Some(sc)) => sub_origin_key_export(sc)?,
                         ("generate", Some(sc)) => sub_origin_key_generate(ui, sc)?,
                         ("import", Some(sc)) => sub_origin_key_import(ui, sc)?,
+                        ("revocations", Some(sc)) => sub_origin_key_revocations(ui, sc).await?,
+                        ("revoke", Some(sc)) => sub_origin_key_revoke(ui, sc).await?,
+                        ("sync", Some(sc)) => sub_origin_key_sync(ui, sc).await?,
                         ("upload", Some(sc)) => sub_origin_key_upload(ui, sc).await?,
                         _ => unreachable!(),
                     }
@@ -392,6 +520,8 @@ async fn start(ui: &mut UI, feature_flags: FeatureFlag) -> Result<()> {
                 ("secret", Some(m)) => {
                     match m.subcommand() {
                         ("upload", Some(sc)) => sub_origin_secret_upload(ui, sc).await?,
+                        ("update", Some(sc)) => sub_origin_secret_update(ui, sc).await?,
+                        ("import", Some(sc)) => sub_origin_secret_import(ui, sc).await?,
                         ("delete", Some(sc)) => sub_origin_secret_delete(ui, sc).await?,
                         ("list", Some(sc)) => sub_origin_secret_list(ui, sc).await?,
                         _ => unreachable!(),
@@ -414,6 +544,7 @@ async fn start(ui: &mut UI, feature_flags: FeatureFlag) -> Result<()> {
                         ("promote", Some(m)) => sub_bldr_job_promote_or_demote(ui, m, true).await?,
                         ("demote", Some(m)) => sub_bldr_job_promote_or_demote(ui, m, false).await?,
                         ("status", Some(m)) => sub_bldr_job_status(ui, m).await?,
+                        ("log", Some(m)) => sub_bldr_job_log(ui, m).await?,
                         _ => unreachable!(),
                     }
                 }
@@ -424,6 +555,9 @@ async fn start(ui: &mut UI, feature_flags: FeatureFlag) -> Result<()> {
                         ("list", Some(m)) => sub_bldr_channel_list(ui, m).await?,
                         ("promote", Some(m)) => sub_bldr_channel_promote(ui, m).await?,
                         ("demote", Some(m)) => sub_bldr_channel_demote(ui, m).await?,
+                        ("promote-on-green", Some(m)) => {
+                            sub_bldr_channel_promote_on_green(ui, m).await?
+                        }
                         _ => unreachable!(),
                     }
                 }
@@ -432,17 +566,21 @@ async fn start(ui: &mut UI, feature_flags: FeatureFlag) -> Result<()> {
         }
         ("pkg", Some(matches)) => {
             match matches.subcommand() {
+                ("attest", Some(m)) => sub_pkg_attest(ui, m)?,
                 ("binds", Some(m)) => sub_pkg_binds(m)?,
                 ("binlink", Some(m)) => sub_pkg_binlink(ui, m)?,
                 ("build", Some(m)) => sub_pkg_build(ui, m).await?,
                 ("channels", Some(m)) => sub_pkg_channels(ui, m).await?,
                 ("config", Some(m)) => sub_pkg_config(m)?,
+                ("dedup", Some(_)) => sub_pkg_dedup(ui)?,
                 ("dependencies", Some(m)) => sub_pkg_dependencies(m)?,
                 ("download", Some(m)) => sub_pkg_download(ui, m, feature_flags).await?,
                 ("env", Some(m)) => sub_pkg_env(m)?,
+                ("gc", Some(m)) => sub_pkg_gc(ui, m).await?,
                 ("hash", Some(m)) => sub_pkg_hash(m)?,
                 ("install", Some(m)) => sub_pkg_install(ui, m, feature_flags).await?,
                 ("list", Some(m)) => sub_pkg_list(m)?,
+                ("lock", Some(m)) => sub_pkg_lock(ui, m).await?,
                 ("path", Some(m)) => sub_pkg_path(m)?,
                 ("provides", Some(m)) => sub_pkg_provides(m)?,
                 ("search", Some(m)) => sub_pkg_search(m).await?,
@@ -463,6 +601,7 @@ async fn start(ui: &mut UI, feature_flags: FeatureFlag) -> Result<()> {
             match matches.subcommand() {
                 ("init", Some(m)) => sub_plan_init(ui, m)?,
                 ("render", Some(m)) => sub_plan_render(ui, m)?,
+                ("test", Some(m)) => sub_plan_test(ui, m).await?,
                 _ => unreachable!(),
             }
         }
@@ -470,6 +609,7 @@ async fn start(ui: &mut UI, feature_flags: FeatureFlag) -> Result<()> {
             match matches.subcommand() {
                 ("key", Some(m)) => {
                     match m.subcommand() {
+                        ("distribute", Some(sc)) => sub_ring_key_distribute(ui, sc)?,
                         ("export", Some(sc)) => sub_ring_key_export(sc)?,
                         ("import", Some(sc)) => sub_ring_key_import(ui, sc)?,
                         ("generate", Some(sc)) => sub_ring_key_generate(ui, sc)?,
@@ -513,10 +653,15 @@ async fn start(ui: &mut UI, feature_flags: FeatureFlag) -> Result<()> {
             match matches.subcommand() {
                 ("key", Some(m)) => {
                     match m.subcommand() {
+                        ("export", Some(sc)) => sub_user_key_export(sc)?,
                         ("generate", Some(sc)) => sub_user_key_generate(ui, sc)?,
+                        ("import", Some(sc)) => sub_user_key_import(ui, sc)?,
+                        ("list", Some(sc)) => sub_user_key_list(ui, sc)?,
                         _ => unreachable!(),
                     }
                 }
+                ("encrypt", Some(m)) => sub_user_encrypt(m)?,
+                ("decrypt", Some(m)) => sub_user_decrypt(m)?,
                 _ => unreachable!(),
             }
         }
@@ -539,9 +684,12 @@ fn sub_cli_completers(m: &ArgMatches<'_>, feature_flags: FeatureFlag) -> Result<
     // TODO (CM): Interesting... the completions generated can depend
     // on what feature flags happen to be enabled at the time you
     // generated the completions
-    cli::get(feature_flags).gen_completions_to("hab",
-                                               shell.parse::<Shell>().unwrap(),
-                                               &mut io::stdout());
+    let shell = shell.parse::<Shell>().unwrap();
+    cli::get(feature_flags).gen_completions_to("hab", shell, &mut io::stdout());
+    // Complete loaded services and installed packages by calling back into `hab`
+    if let Some(completer) = command::cli::complete::dynamic_completer(shell) {
+        print!("{}", completer);
+    }
     Ok(())
 }
 
@@ -550,6 +698,7 @@ async fn sub_origin_key_download(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()>
     let revision = m.value_of("REVISION");
     let with_secret = m.is_present("WITH_SECRET");
     let with_encryption = m.is_present("WITH_ENCRYPTION");
+    let accept_new_key = m.is_present("ACCEPT_NEW_KEY");
     let token = maybe_auth_token(&m);
     let url = bldr_url_from_matches(&m)?;
     let key_cache = key_cache_from_matches(&m)?;
@@ -560,6 +709,7 @@ async fn sub_origin_key_download(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()>
                                           revision,
                                           with_secret,
                                           with_encryption,
+                                          accept_new_key,
                                           token.as_deref(),
                                           &key_cache).await
 }
@@ -591,6 +741,55 @@ fn sub_origin_key_import(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
     command::origin::key::import::start(ui, content.trim(), &key_cache)
 }
 
+async fn sub_origin_key_revocations(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
+    let origin = m.value_of("ORIGIN").unwrap().parse()?; // Required via clap
+    let file = m.value_of("FILE").map(Path::new);
+    let url = bldr_url_from_matches(&m)?;
+    let key_cache = key_cache_from_matches(&m)?;
+    init()?;
+
+    command::origin::key::revocations::start(ui, &url, &origin, file, &key_cache).await
+}
+
+async fn sub_origin_key_revoke(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
+    let origin = m.value_of("ORIGIN").unwrap().parse()?; // Required via clap
+    let revision = m.value_of("REVISION").unwrap(); // Required via clap
+    let output = m.value_of("OUTPUT").map(Path::new);
+    let token = if m.is_present("NO_UPLOAD") {
+        None
+    } else {
+        Some(auth_token_param_or_env(&m)?)
+    };
+    let url = bldr_url_from_matches(&m)?;
+    let key_cache = key_cache_from_matches(&m)?;
+    init()?;
+
+    command::origin::key::revoke::start(ui,
+                                        &url,
+                                        &origin,
+                                        revision,
+                                        m.value_of("REASON"),
+                                        output,
+                                        token.as_deref(),
+                                        &key_cache).await
+}
+
+async fn sub_origin_key_sync(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
+    let origin = m.value_of("ORIGIN").unwrap().parse()?; // Required via clap
+    let accept_new_key = m.is_present("ACCEPT_NEW_KEY");
+    let token = maybe_auth_token(&m);
+    let url = bldr_url_from_matches(&m)?;
+    let key_cache = key_cache_from_matches(&m)?;
+    init()?;
+
+    command::origin::key::sync::start(ui,
+                                      &url,
+                                      &origin,
+                                      accept_new_key,
+                                      token.as_deref(),
+                                      &key_cache).await
+}
+
 async fn sub_origin_key_upload(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
     let url = bldr_url_from_matches(&m)?;
     let token = auth_token_param_or_env(&m)?;
@@ -620,7 +819,7 @@ async fn sub_origin_secret_upload(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()>
     let token = auth_token_param_or_env(&m)?;
     let origin = origin_param_or_env(&m)?;
     let key = m.value_of("KEY_NAME").unwrap();
-    let secret = m.value_of("SECRET").unwrap();
+    let secret = secret_param_or_stdin(&m)?;
     let key_cache = key_cache_from_matches(&m)?;
     command::origin::secret::upload::start(ui,
                                            &url,
@@ -631,6 +830,31 @@ async fn sub_origin_secret_upload(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()>
                                            &key_cache).await
 }
 
+async fn sub_origin_secret_update(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
+    let url = bldr_url_from_matches(&m)?;
+    let token = auth_token_param_or_env(&m)?;
+    let origin = origin_param_or_env(&m)?;
+    let key = m.value_of("KEY_NAME").unwrap();
+    let secret = secret_param_or_stdin(&m)?;
+    let key_cache = key_cache_from_matches(&m)?;
+    command::origin::secret::update::start(ui,
+                                           &url,
+                                           &token,
+                                           &origin,
+                                           &key,
+                                           &secret,
+                                           &key_cache).await
+}
+
+async fn sub_origin_secret_import(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
+    let url = bldr_url_from_matches(&m)?;
+    let token = auth_token_param_or_env(&m)?;
+    let origin = origin_param_or_env(&m)?;
+    let env_file = Path::new(m.value_of("ENV_FILE").unwrap()); // Required via clap
+    let key_cache = key_cache_from_matches(&m)?;
+    command::origin::secret::import::start(ui, &url, &token, &origin, env_file, &key_cache).await
+}
+
 async fn sub_origin_secret_delete(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
     let url = bldr_url_from_matches(&m)?;
     let token = auth_token_param_or_env(&m)?;
@@ -643,7 +867,8 @@ async fn sub_origin_secret_list(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
     let url = bldr_url_from_matches(&m)?;
     let token = auth_token_param_or_env(&m)?;
     let origin = origin_param_or_env(&m)?;
-    command::origin::secret::list::start(ui, &url, &token, &origin).await
+    let metadata = m.is_present("METADATA");
+    command::origin::secret::list::start(ui, &url, &token, &origin, metadata).await
 }
 
 async fn sub_origin_create(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
@@ -763,17 +988,29 @@ async fn sub_origin_member_role_set(ui: &mut UI, r: RbacSet) -> Result<()> {
 }
 
 fn sub_pkg_binlink(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
-    let ident = required_pkg_ident_from_input(m)?;
     let dest_dir = Path::new(m.value_of("DEST_DIR").unwrap()); // required by clap
     let force = m.is_present("FORCE");
-    match m.value_of("BINARY") {
-        Some(binary) => {
-            command::pkg::binlink::start(ui, &ident, &binary, dest_dir, &FS_ROOT_PATH, force)
-        }
-        None => {
-            command::pkg::binlink::binlink_all_in_pkg(ui, &ident, dest_dir, &FS_ROOT_PATH, force)
+    if m.is_present("ALL_INSTALLED") {
+        command::pkg::binlink::binlink_all_installed(ui, dest_dir, &FS_ROOT_PATH, force)?;
+    } else if m.is_present("PKG_IDENT") {
+        let ident = required_pkg_ident_from_input(m)?;
+        match m.value_of("BINARY") {
+            Some(binary) => {
+                command::pkg::binlink::start(ui, &ident, &binary, dest_dir, &FS_ROOT_PATH, force)?
+            }
+            None => {
+                command::pkg::binlink::binlink_all_in_pkg(ui,
+                                                          &ident,
+                                                          dest_dir,
+                                                          &FS_ROOT_PATH,
+                                                          force)?
+            }
         }
     }
+    if m.is_present("PRUNE") {
+        command::pkg::binlink::prune(ui, dest_dir, &FS_ROOT_PATH)?;
+    }
+    Ok(())
 }
 
 /// Generate a (possibly empty) list of `Origin`s from the value of
@@ -801,10 +1038,24 @@ async fn sub_pkg_build(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
         }
     }
 
+    let mounts = m.values_of("STUDIO_MOUNT")
+                  .unwrap_or_default()
+                  .collect::<Vec<_>>();
+    let env_pass = m.values_of("STUDIO_ENV_PASS")
+                    .unwrap_or_default()
+                    .collect::<Vec<_>>();
     let docker = m.is_present("DOCKER");
     let reuse = m.is_present("REUSE");
 
-    command::pkg::build::start(ui, plan_context, root, src, &origins, reuse, docker).await
+    command::pkg::build::start(ui,
+                               plan_context,
+                               root,
+                               src,
+                               &origins,
+                               &mounts,
+                               &env_pass,
+                               reuse,
+                               docker).await
 }
 
 fn sub_pkg_config(m: &ArgMatches<'_>) -> Result<()> {
@@ -876,7 +1127,11 @@ async fn sub_pkg_download(ui: &mut UI,
 
 fn sub_pkg_env(m: &ArgMatches<'_>) -> Result<()> {
     let ident = required_pkg_ident_from_input(m)?;
-    command::pkg::env::start(&ident, &*FS_ROOT_PATH)
+    let format = match m.value_of("SHELL") {
+        Some(shell) => shell.parse().map_err(Error::ArgumentError)?,
+        None => command::pkg::env::EnvFormat::default(),
+    };
+    command::pkg::env::start(&ident, &*FS_ROOT_PATH, format)
 }
 
 fn sub_pkg_hash(m: &ArgMatches<'_>) -> Result<()> {
@@ -928,6 +1183,25 @@ async fn sub_pkg_uninstall(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
                                    uninstall_hook_mode).await
 }
 
+async fn sub_pkg_gc(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
+    let execute_strategy = if m.is_present("DRYRUN") {
+        command::pkg::ExecutionStrategy::DryRun
+    } else {
+        command::pkg::ExecutionStrategy::Run
+    };
+    let keep_latest = m.value_of("KEEP_LATEST")
+                       .expect("required opt KEEP_LATEST")
+                       .parse()
+                       .expect("valid KEEP_LATEST");
+    let binlink_dir = Path::new(m.value_of("BINLINK_DIR").unwrap()); // Required via clap
+
+    command::pkg::gc::start(ui,
+                            binlink_dir,
+                            &*FS_ROOT_PATH,
+                            keep_latest,
+                            execute_strategy).await
+}
+
 async fn sub_bldr_channel_create(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
     let url = bldr_url_from_matches(&m)?;
     let origin = origin_param_or_env(&m)?;
@@ -964,6 +1238,35 @@ async fn sub_bldr_channel_promote(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()>
                                            &target_channel).await
 }
 
+async fn sub_bldr_channel_promote_on_green(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
+    let url = bldr_url_from_matches(&m)?;
+    let origin = m.value_of("ORIGIN").unwrap(); // Required via clap
+    let channel = required_channel_from_matches(&m);
+    let policy = match m.value_of("POLICY") {
+        Some(path) => PromotionPolicy::read(Path::new(path))?,
+        None => PromotionPolicy::default(),
+    };
+    let limit = m.value_of("LIMIT")
+                 .unwrap_or("10")
+                 .parse::<usize>()
+                 .unwrap();
+    let interval = m.value_of("INTERVAL")
+                    .unwrap_or("60")
+                    .parse::<u64>()
+                    .unwrap();
+    let once = m.is_present("ONCE");
+    let token = auth_token_param_or_env(&m)?;
+    command::bldr::channel::promote_on_green::start(ui,
+                                                    &url,
+                                                    &token,
+                                                    origin,
+                                                    &channel,
+                                                    &policy,
+                                                    limit,
+                                                    Duration::from_secs(interval),
+                                                    once).await
+}
+
 async fn sub_bldr_channel_demote(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
     let url = bldr_url_from_matches(&m)?;
     let origin = origin_param_or_env(&m)?;
@@ -989,10 +1292,25 @@ async fn sub_bldr_job_start(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
 
 async fn sub_bldr_job_cancel(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
     let url = bldr_url_from_matches(&m)?;
-    let group_id = m.value_of("GROUP_ID").unwrap(); // Required via clap
+    let group_ids: Vec<&str> = m.values_of("GROUP_ID")
+                                .map(Iterator::collect)
+                                .unwrap_or_else(Vec::new);
+    let origin = m.value_of("ORIGIN");
+    let limit = m.value_of("LIMIT")
+                 .unwrap_or("50")
+                 .parse::<usize>()
+                 .unwrap();
+    let failing = m.is_present("FAILING");
     let token = auth_token_param_or_env(&m)?;
     let force = m.is_present("FORCE");
-    command::bldr::job::cancel::start(ui, &url, &group_id, &token, force).await
+    command::bldr::job::cancel::start(ui,
+                                      &url,
+                                      &group_ids,
+                                      origin,
+                                      limit,
+                                      failing,
+                                      &token,
+                                      force).await
 }
 
 async fn sub_bldr_job_promote_or_demote(ui: &mut UI,
@@ -1030,11 +1348,26 @@ async fn sub_bldr_job_status(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
     command::bldr::job::status::start(ui, &url, group_id, origin, limit, show_jobs).await
 }
 
+async fn sub_bldr_job_log(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
+    let url = bldr_url_from_matches(&m)?;
+    let job_id = m.value_of("JOB_ID").unwrap().parse::<u64>().unwrap(); // Required via clap
+    let token = maybe_auth_token(&m);
+    let follow = m.is_present("FOLLOW");
+
+    command::bldr::job::log::start(ui, &url, job_id, token.as_deref(), follow).await
+}
+
 fn sub_plan_init(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
     let name = m.value_of("PKG_NAME").map(String::from);
     let origin = origin_param_or_env(&m)?;
     let minimal = m.is_present("MIN");
-    let scaffolding_ident = if cfg!(windows) {
+    let template = match m.value_of("TEMPLATE") {
+        Some(template) => Some(PlanTemplate::from_str(template)?),
+        None => None,
+    };
+    let scaffolding_ident = if template.is_some() {
+        None
+    } else if cfg!(windows) {
         match m.value_of("SCAFFOLDING") {
             Some(scaffold) => Some(PackageIdent::from_str(scaffold)?),
             None => None,
@@ -1043,7 +1376,7 @@ fn sub_plan_init(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
         scaffolding::scaffold_check(ui, m.value_of("SCAFFOLDING"))?
     };
 
-    command::plan::init::start(ui, &origin, minimal, scaffolding_ident, name)
+    command::plan::init::start(ui, &origin, minimal, scaffolding_ident, template, name)
 }
 
 fn sub_plan_render(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
@@ -1055,6 +1388,8 @@ fn sub_plan_render(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
 
     let mock_data_path = m.value_of("MOCK_DATA").map(Path::new);
 
+    let mock_census_path = m.value_of("MOCK_CENSUS").map(Path::new);
+
     let print = m.is_present("PRINT");
     let render = !m.is_present("NO_RENDER");
     let quiet = m.is_present("QUIET");
@@ -1066,12 +1401,20 @@ fn sub_plan_render(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
                                  default_toml_path,
                                  user_toml_path,
                                  mock_data_path,
+                                 mock_census_path,
                                  print,
                                  render,
                                  render_dir,
                                  quiet)
 }
 
+async fn sub_plan_test(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
+    let plan_dir = Path::new(m.value_of("PLAN_DIR").unwrap());
+    let manifest_path = m.value_of("MANIFEST").map(Path::new);
+    let filter = m.value_of("FILTER");
+    command::plan::test::start(ui, plan_dir, manifest_path, filter).await
+}
+
 async fn sub_pkg_install(ui: &mut UI,
                          m: &ArgMatches<'_>,
                          feature_flags: FeatureFlag)
@@ -1100,8 +1443,15 @@ async fn sub_pkg_install(ui: &mut UI,
         InstallHookMode::default()
     };
 
+    let relocate_mode = if m.is_present("RELOCATE") {
+        RelocateMode::Relocate
+    } else {
+        RelocateMode::default()
+    };
+
     init()?;
 
+    let mut installed = Vec::new();
     for install_source in install_sources.iter() {
         let pkg_install =
             common::command::package::install::start(ui,
@@ -1115,7 +1465,8 @@ async fn sub_pkg_install(ui: &mut UI,
                                                      token.as_deref(),
                                                      &install_mode,
                                                      &local_package_usage,
-                                                     install_hook_mode).await?;
+                                                     install_hook_mode,
+                                                     relocate_mode).await?;
 
         if let Some(dest_dir) = binlink_dest_dir_from_matches(m) {
             let force = m.is_present("FORCE");
@@ -1125,10 +1476,21 @@ async fn sub_pkg_install(ui: &mut UI,
                                                       &FS_ROOT_PATH,
                                                       force)?;
         }
+        installed.push(serde_json::json!({ "ident": pkg_install.ident().to_string(),
+                                           "path": pkg_install.installed_path() }));
     }
+    ui.result(&serde_json::json!({ "installed": installed }))?;
     Ok(())
 }
 
+#[cfg(unix)]
+fn sub_pkg_dedup(ui: &mut UI) -> Result<()> { command::pkg::dedup::start(ui, &*FS_ROOT_PATH) }
+
+#[cfg(windows)]
+fn sub_pkg_dedup(_ui: &mut UI) -> Result<()> {
+    Err(Error::SubcommandNotSupported(String::from("pkg dedup")))
+}
+
 fn sub_pkg_path(m: &ArgMatches<'_>) -> Result<()> {
     let ident = required_pkg_ident_from_input(m)?;
     command::pkg::path::start(&ident, &*FS_ROOT_PATH)
@@ -1252,7 +1614,21 @@ fn sub_pkg_verify(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
     let key_cache = key_cache_from_matches(&m)?;
     init()?;
 
-    command::pkg::verify::start(ui, &src, &key_cache)
+    command::pkg::verify::start(ui, &src, &key_cache, m.is_present("PROVENANCE"))
+}
+
+fn sub_pkg_attest(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
+    let src = Path::new(m.value_of("SOURCE").unwrap()); // Required via clap
+    let builder_id = m.value_of("BUILDER_ID").unwrap(); // Required via clap
+    let key_cache = key_cache_from_matches(&m)?;
+    init()?;
+
+    command::pkg::attest::start(ui,
+                                &src,
+                                builder_id,
+                                m.value_of("SOURCE_URI"),
+                                m.value_of("SOURCE_REVISION"),
+                                &key_cache)
 }
 
 fn sub_pkg_header(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
@@ -1297,6 +1673,23 @@ async fn sub_pkg_channels(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
     command::pkg::channels::start(ui, &url, (&ident, target), token.as_deref()).await
 }
 
+async fn sub_pkg_lock(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
+    let url = bldr_url_from_matches(&m)?;
+    let channel = channel_from_matches_or_default(m);
+    let target = target_from_matches(m)?;
+    let idents = idents_from_matches(m)?;
+    let token = maybe_auth_token(&m);
+    let output = m.value_of("OUTPUT").map(Path::new);
+
+    command::pkg::lock::start(ui,
+                              &url,
+                              &channel,
+                              target,
+                              &idents,
+                              token.as_deref(),
+                              output).await
+}
+
 async fn sub_svc_set(m: &ArgMatches<'_>) -> Result<()> {
     let remote_sup_addr = remote_sup_from_input(m)?;
     let service_group = ServiceGroup::from_str(m.value_of("SERVICE_GROUP").unwrap())?;
@@ -1383,6 +1776,38 @@ async fn sub_svc_set(m: &ArgMatches<'_>) -> Result<()> {
     Ok(())
 }
 
+async fn sub_config_audit(m: &ArgMatches<'_>) -> Result<()> {
+    let service_group = m.value_of("SERVICE_GROUP")
+                         .map(ServiceGroup::from_str)
+                         .transpose()?;
+    let limit = m.value_of("LIMIT")
+                 .map(|limit| limit.parse::<u64>().expect("LIMIT is validated"));
+    let remote_sup_addr = remote_sup_from_input(m)?;
+    let msg = sup_proto::ctl::SvcConfigAudit { service_group: service_group.map(Into::into),
+                                               limit };
+
+    let mut out = TabWriter::new(io::stdout());
+    let mut response = SrvClient::request(&remote_sup_addr, msg).await?;
+    while let Some(message_result) = response.next().await {
+        let reply = message_result?;
+        match reply.message_id() {
+            "ConfigAudit" => {
+                let audit = reply.parse::<sup_proto::types::ConfigAudit>()
+                                 .map_err(SrvClientError::Decode)?;
+                print_config_audit(&mut out, audit)?;
+            }
+            "NetErr" => {
+                let m = reply.parse::<sup_proto::net::NetErr>()
+                             .map_err(SrvClientError::Decode)?;
+                return Err(SrvClientError::from(m).into());
+            }
+            _ => return Err(SrvClientError::from(io::Error::from(io::ErrorKind::UnexpectedEof)).into()),
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
+
 async fn sub_svc_config(m: &ArgMatches<'_>) -> Result<()> {
     let ident = required_pkg_ident_from_input(m)?;
     let remote_sup_addr = remote_sup_from_input(m)?;
@@ -1413,9 +1838,35 @@ async fn sub_svc_load(svc_load: SvcLoad) -> Result<()> {
     gateway_util::send(&remote_sup_addr, msg).await
 }
 
+/// Load several services in a single session with the Supervisor, carrying on past failures. A
+/// lone service's error is returned as is.
+async fn sub_svc_load_many(remote_sup_addr: &ListenCtlAddr,
+                           msgs: Vec<sup_proto::ctl::SvcLoad>)
+                           -> Result<()> {
+    let mut session = SrvClient::session(remote_sup_addr).await?;
+    if msgs.len() == 1 {
+        return gateway_util::send_in_session(&mut session, msgs[0].clone()).await;
+    }
+    let mut errors = HashMap::new();
+    for msg in msgs {
+        let ident: PackageIdent = msg.ident.clone().map(Into::into).unwrap_or_default();
+        if let Err(e) = gateway_util::send_in_session(&mut session, msg).await {
+            errors.insert(ident, e);
+        }
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors.into())
+    }
+}
+
 async fn sub_svc_bulk_load(svc_bulk_load: SvcBulkLoad) -> Result<()> {
     let mut errors = HashMap::new();
-    for svc_load in svc::svc_loads_from_paths(&svc_bulk_load.svc_config_paths)? {
+    for svc_load in svc::svc_loads_from_paths(&svc_bulk_load.svc_config_paths,
+                                              &svc_bulk_load.exclude,
+                                              svc_bulk_load.order_file.as_deref())?
+    {
         let ident = svc_load.pkg_ident.clone().pkg_ident();
         if let Err(e) = sub_svc_load(svc_load).await {
             errors.insert(ident, e);
@@ -1453,22 +1904,55 @@ async fn sub_svc_start(m: &ArgMatches<'_>) -> Result<()> {
 
 async fn sub_svc_status(pkg_ident: Option<PackageIdent>, remote_sup: &ListenCtlAddr) -> Result<()> {
     let mut msg = sup_proto::ctl::SvcStatus::default();
+    let detailed = pkg_ident.is_some();
     msg.ident = pkg_ident.map(Into::into);
 
     let mut out = TabWriter::new(io::stdout());
     let mut response = SrvClient::request(remote_sup, msg).await?;
     // Ensure there is at least one result from the server otherwise produce an error
-    if let Some(message_result) = response.next().await {
+    let status = if let Some(message_result) = response.next().await {
         let reply = message_result?;
-        print_svc_status(&mut out, &reply, true)?;
+        print_svc_status(&mut out, &reply, true)?
     } else {
         return Err(SrvClientError::from(io::Error::from(io::ErrorKind::UnexpectedEof)).into());
-    }
+    };
     while let Some(message_result) = response.next().await {
         let reply = message_result?;
         print_svc_status(&mut out, &reply, false)?;
     }
     out.flush()?;
+    // The status of all services is kept to the table, so the resource usage and the details of
+    // a hook failure are only given when asking after a single service.
+    if detailed {
+        if let Some(status) = status {
+            if let Some(usage) = status.process.and_then(|p| p.usage) {
+                print_process_usage(&usage)?;
+            }
+            if let Some(failure) = status.last_hook_failure {
+                print_hook_failure(&mut io::stdout(), &failure)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn sub_svc_health_history(pkg_ident: Option<PackageIdent>,
+                                remote_sup: &ListenCtlAddr)
+                                -> Result<()> {
+    let msg = sup_proto::ctl::SvcHealthHistory { ident: pkg_ident.map(Into::into), };
+
+    let mut out = TabWriter::new(io::stdout());
+    let mut response = SrvClient::request(remote_sup, msg).await?;
+    let mut print_header = true;
+    while let Some(message_result) = response.next().await {
+        let reply = message_result?;
+        print_health_history(&mut out, &reply, print_header)?;
+        print_header = false;
+    }
+    if print_header {
+        return Err(SrvClientError::from(io::Error::from(io::ErrorKind::UnexpectedEof)).into());
+    }
+    out.flush()?;
     Ok(())
 }
 
@@ -1579,9 +2063,14 @@ async fn sub_sup_depart(member_id: String, remote_sup: &ListenCtlAddr) -> Result
     Ok(())
 }
 
-async fn sub_sup_restart(remote_sup: &ListenCtlAddr) -> Result<()> {
+async fn sub_sup_purge_departed(older_than: Option<u64>, remote_sup: &ListenCtlAddr) -> Result<()> {
+    let msg = sup_proto::ctl::SupPurgeDeparted { older_than };
+    gateway_util::send(remote_sup, msg).await
+}
+
+async fn sub_sup_restart(remote_sup: &ListenCtlAddr, args: Vec<String>) -> Result<()> {
     let mut ui = ui::ui();
-    let msg = sup_proto::ctl::SupRestart::default();
+    let msg = sup_proto::ctl::SupRestart { args };
 
     ui.begin(format!("Restarting supervisor {}", remote_sup))?;
     let mut response = SrvClient::request(&remote_sup, msg).await?;
@@ -1601,6 +2090,55 @@ async fn sub_sup_restart(remote_sup: &ListenCtlAddr) -> Result<()> {
     Ok(())
 }
 
+async fn sub_sup_status(ui: &mut UI, remote_sup: &ListenCtlAddr) -> Result<()> {
+    let msg = sup_proto::ctl::SupStatus::default();
+    let mut response = SrvClient::request(remote_sup, msg).await?;
+    let reply = match response.next().await {
+        Some(message_result) => message_result?,
+        None => {
+            return Err(SrvClientError::from(io::Error::from(io::ErrorKind::UnexpectedEof)).into())
+        }
+    };
+    let status = match reply.message_id() {
+        "SupervisorStatus" => {
+            reply.parse::<sup_proto::types::SupervisorStatus>()
+                 .map_err(SrvClientError::Decode)?
+        }
+        "NetErr" => {
+            let err = reply.parse::<sup_proto::net::NetErr>()
+                           .map_err(SrvClientError::Decode)?;
+            return Err(SrvClientError::from(err).into());
+        }
+        _ => {
+            warn!("Unexpected status message, {:?}", reply);
+            return Ok(());
+        }
+    };
+
+    let mut out = TabWriter::new(io::stdout());
+    writeln!(out, "uptime (s)\t{}", status.uptime_seconds)?;
+    writeln!(out,
+             "memory (MiB)\t{}",
+             status.memory_bytes
+                   .map_or_else(|| "unknown".to_string(),
+                                |bytes| format!("{:.1}", bytes as f64 / 1024.0 / 1024.0)))?;
+    writeln!(out,
+             "cpu time (s)\t{}",
+             status.cpu_time_ms.map_or_else(|| "unknown".to_string(),
+                                            |ms| format!("{:.1}", ms as f64 / 1000.0)))?;
+    writeln!(out, "gossip peers\t{}", status.gossip_peers)?;
+    writeln!(out,
+             "spec reconciliation lag (s)\t{}",
+             status.spec_reconciliation_lag_seconds)?;
+    writeln!(out,
+             "event stream backlog\t{}",
+             status.event_stream_backlog
+                   .map_or_else(|| "disabled".to_string(), |backlog| backlog.to_string()))?;
+    out.flush()?;
+    ui.result(&status)?;
+    Ok(())
+}
+
 fn sub_sup_secret_generate() -> Result<()> {
     let mut ui = ui::ui();
     let mut buf = String::new();
@@ -1609,12 +2147,37 @@ fn sub_sup_secret_generate() -> Result<()> {
     Ok(())
 }
 
+async fn sub_doctor(ui: &mut UI, doctor: Doctor, feature_flags: FeatureFlag) -> Result<()> {
+    let bldr_url = bldr_url_from_args_env_load_or_default(doctor.bldr_url.value)?;
+    let key_cache = KeyCache::new(doctor.cache_key_path.cache_key_path);
+    command::doctor::start(ui,
+                           &doctor.remote_sup.to_listen_ctl_addr(),
+                           bldr_url.as_str(),
+                           &key_cache,
+                           feature_flags).await
+}
+
 fn sub_supportbundle(ui: &mut UI) -> Result<()> {
     init()?;
 
     command::supportbundle::start(ui)
 }
 
+fn sub_ring_key_distribute(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
+    let ring = m.value_of("RING").unwrap(); // Required via clap
+    let peers = std::fs::read_to_string(m.value_of("PEERS").unwrap())?; // Required via clap
+    let peers = command::ring::key::distribute::parse_peers(&peers);
+    let ssh = m.value_of("SSH").unwrap(); // Has a default
+    let cache_key_path = m.value_of("REMOTE_CACHE_KEY_PATH").map(Path::new);
+    let remote = command::ring::key::distribute::RemoteImport { ssh,
+                                                                sudo: m.is_present("SUDO"),
+                                                                cache_key_path };
+    let key_cache = key_cache_from_matches(&m)?;
+    init()?;
+
+    command::ring::key::distribute::start(ui, ring, &peers, &remote, &key_cache)
+}
+
 fn sub_ring_key_export(m: &ArgMatches<'_>) -> Result<()> {
     let ring = m.value_of("RING").unwrap(); // Required via clap
     let key_cache = key_cache_from_matches(&m)?;
@@ -1650,6 +2213,15 @@ fn sub_service_key_generate(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
     command::service::key::generate::start(ui, &org, &service_group, &key_cache)
 }
 
+fn sub_user_key_export(m: &ArgMatches<'_>) -> Result<()> {
+    let user = m.value_of("USER").unwrap(); // Required via clap
+    let key_type = KeyType::from_str(m.value_of("KEY_TYPE").unwrap_or("public"))?;
+    let key_cache = key_cache_from_matches(&m)?;
+    init()?;
+
+    command::user::key::export::start(user, key_type, &key_cache)
+}
+
 fn sub_user_key_generate(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
     let user = m.value_of("USER").unwrap(); // Required via clap
     let key_cache = key_cache_from_matches(&m)?;
@@ -1658,8 +2230,99 @@ fn sub_user_key_generate(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
     command::user::key::generate::start(ui, user, &key_cache)
 }
 
+fn sub_user_key_import(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
+    let mut content = String::new();
+    let key_cache = key_cache_from_matches(&m)?;
+    init()?;
+    io::stdin().read_to_string(&mut content)?;
+
+    // Trim the content to lose line feeds added by Powershell pipeline
+    command::user::key::import::start(ui, content.trim(), &key_cache)
+}
+
+fn sub_user_key_list(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
+    let key_cache = key_cache_from_matches(&m)?;
+    init()?;
+
+    command::user::key::list::start(ui, m.value_of("USER"), &key_cache)
+}
+
+fn sub_user_encrypt(m: &ArgMatches<'_>) -> Result<()> {
+    let receiver = m.value_of("FOR").unwrap(); // Required via clap
+    let sender = user_param_or_env(&m).ok_or_else(|| {
+                     Error::ArgumentError("No user key to encrypt with; pass one with --user or \
+                                           set HAB_USER"
+                                                        .to_string())
+                 })?;
+    let data = file_or_stdin(m.value_of("FILE"))?;
+    let key_cache = key_cache_from_matches(&m)?;
+    init()?;
+
+    command::user::encrypt::start(&data, &sender, receiver, &key_cache)
+}
+
+fn sub_user_decrypt(m: &ArgMatches<'_>) -> Result<()> {
+    let message = String::from_utf8_lossy(&file_or_stdin(m.value_of("FILE"))?).into_owned();
+    let key_cache = key_cache_from_matches(&m)?;
+    init()?;
+
+    // Trim the content to lose line feeds added by Powershell pipeline
+    command::user::decrypt::start(message.trim(), &key_cache)
+}
+
+/// The contents of the file at `path`, or of standard input if there is none or it is `-`.
+fn file_or_stdin(path: Option<&str>) -> Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    match path {
+        Some("-") | None => io::stdin().read_to_end(&mut buf)?,
+        Some(path) => File::open(path)?.read_to_end(&mut buf)?,
+    };
+    Ok(buf)
+}
+
 fn args_after_first(args_to_skip: usize) -> Vec<OsString> {
-    env::args_os().skip(args_to_skip).collect()
+    ARGS.1.iter().skip(args_to_skip).cloned().collect()
+}
+
+/// The global options, given before the subcommand.
+#[derive(Debug, Default, PartialEq)]
+struct GlobalOptions {
+    /// `--output <FORMAT>`
+    output:      Option<String>,
+    /// `--ctl-timeout <SECONDS>`
+    ctl_timeout: Option<String>,
+}
+
+/// Take the global options out of `args`, so they aren't mistaken for arguments of a subcommand.
+/// The options may only come before the subcommand (e.g. `hab --output json pkg install
+/// core/redis`).
+fn split_global_options(args: impl IntoIterator<Item = OsString>)
+                        -> (GlobalOptions, Vec<OsString>) {
+    let mut args = args.into_iter();
+    let mut rest: Vec<OsString> = args.next().into_iter().collect();
+    let mut options = GlobalOptions::default();
+    while let Some(arg) = args.next() {
+        let arg_str = arg.to_string_lossy().into_owned();
+        let (name, value) = match arg_str.find('=') {
+            Some(i) => (&arg_str[..i], Some(arg_str[i + 1..].to_string())),
+            None => (&arg_str[..], None),
+        };
+        let option = match name {
+            OUTPUT_OPTION => &mut options.output,
+            CTL_TIMEOUT_OPTION => &mut options.ctl_timeout,
+            _ => {
+                rest.push(arg);
+                rest.extend(args);
+                break;
+            }
+        };
+        *option = Some(value.unwrap_or_else(|| {
+                                args.next()
+                                    .map(|value| value.to_string_lossy().into_owned())
+                                    .unwrap_or_default()
+                            }));
+    }
+    (options, rest)
 }
 
 /// Check to see if the user has passed in an AUTH_TOKEN param. If not, check the
@@ -1714,6 +2377,26 @@ fn origin_param_or_env(m: &ArgMatches<'_>) -> Result<habitat_core::origin::Origi
     }
 }
 
+/// Use the SECRET param, or read the secret from stdin when `--from-stdin` was given. A single
+/// trailing newline is dropped so that `echo $VALUE | hab origin secret upload ...` works as
+/// expected.
+fn secret_param_or_stdin(m: &ArgMatches<'_>) -> Result<String> {
+    match m.value_of("SECRET") {
+        Some(secret) => Ok(secret.to_string()),
+        None => {
+            let mut secret = String::new();
+            io::stdin().read_to_string(&mut secret)?;
+            if secret.ends_with('\n') {
+                secret.pop();
+                if secret.ends_with('\r') {
+                    secret.pop();
+                }
+            }
+            Ok(secret)
+        }
+    }
+}
+
 /// Check to see if the user has passed in an ORG param.
 /// If not, check the HABITAT_ORG env var. If that's
 /// empty too, then error.
@@ -1914,10 +2597,11 @@ fn excludes_from_matches(matches: &ArgMatches<'_>) -> Vec<PackageIdent> {
         .collect()
 }
 
+/// Print a row of the status table, returning the service's status, if any.
 fn print_svc_status<T>(out: &mut T,
                        reply: &SrvMessage,
                        print_header: bool)
-                       -> result::Result<(), SrvClientError>
+                       -> result::Result<Option<sup_proto::types::ServiceStatus>, SrvClientError>
     where T: io::Write
 {
     let status = match reply.message_id() {
@@ -1927,7 +2611,7 @@ fn print_svc_status<T>(out: &mut T,
         }
         "NetOk" => {
             println!("No services loaded.");
-            return Ok(());
+            return Ok(None);
         }
         "NetErr" => {
             let err = reply.parse::<sup_proto::net::NetErr>()
@@ -1936,14 +2620,14 @@ fn print_svc_status<T>(out: &mut T,
         }
         _ => {
             warn!("Unexpected status message, {:?}", reply);
-            return Ok(());
+            return Ok(None);
         }
     };
     let svc_desired_state = status.desired_state
                                   .map_or("<none>".to_string(), |s| s.to_string());
     let (svc_state, svc_pid, svc_elapsed) = {
         match status.process {
-            Some(process) => {
+            Some(ref process) => {
                 (process.state.to_string(),
                  process.pid
                         .map_or_else(|| "<none>".to_string(), |p| p.to_string()),
@@ -1973,6 +2657,127 @@ fn print_svc_status<T>(out: &mut T,
              svc_elapsed,
              svc_pid,
              status.service_group,)?;
+    Ok(Some(status))
+}
+
+fn print_process_usage(usage: &sup_proto::types::ProcessUsage) -> Result<()> {
+    let mut out = TabWriter::new(io::stdout());
+    writeln!(out,
+             "\nmemory (MiB)\t{}",
+             usage.memory_bytes
+                  .map_or_else(|| "unknown".to_string(),
+                               |bytes| format!("{:.1}", bytes as f64 / 1024.0 / 1024.0)))?;
+    writeln!(out,
+             "cpu time (s)\t{}",
+             usage.cpu_time_ms.map_or_else(|| "unknown".to_string(),
+                                           |ms| format!("{:.1}", ms as f64 / 1000.0)))?;
+    writeln!(out,
+             "open files\t{}",
+             usage.open_files
+                  .map_or_else(|| "unknown".to_string(), |files| files.to_string()))?;
+    writeln!(out, "child processes\t{}", usage.child_processes)?;
+    out.flush()?;
+    Ok(())
+}
+
+fn print_hook_failure<T>(out: &mut T,
+                         failure: &sup_proto::types::HookFailure)
+                         -> result::Result<(), SrvClientError>
+    where T: io::Write
+{
+    let time = chrono::NaiveDateTime::from_timestamp(failure.timestamp as i64, 0);
+    writeln!(out,
+             "\nThe {} hook last failed at {}Z",
+             failure.hook,
+             time.format("%Y-%m-%dT%H:%M:%S"))?;
+    if let Some(exit_status) = failure.exit_status {
+        writeln!(out, "exit status: {}", exit_status)?;
+    }
+    if let Some(ref error) = failure.error {
+        writeln!(out, "error: {}", error)?;
+    }
+    for &(stream, output) in &[("stdout", failure.stdout.as_deref()),
+                               ("stderr", failure.stderr.as_deref())]
+    {
+        if let Some(output) = output.filter(|o| !o.trim().is_empty()) {
+            writeln!(out, "{}:", stream)?;
+            for line in output.lines() {
+                writeln!(out, "    {}", line)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn print_config_audit<T>(out: &mut T, audit: sup_proto::types::ConfigAudit) -> io::Result<()>
+    where T: io::Write
+{
+    if audit.entries.is_empty() {
+        println!("No configuration or files applied.");
+        return Ok(());
+    }
+    writeln!(out, "{}", CONFIG_AUDIT_HEADER.join("\t"))?;
+    for entry in audit.entries {
+        let time = chrono::NaiveDateTime::from_timestamp(entry.timestamp as i64, 0);
+        writeln!(out,
+                 "{}Z\t{}\t{}\t{}\t{}\t{}\t{}",
+                 time.format("%Y-%m-%dT%H:%M:%S"),
+                 entry.service_group,
+                 entry.filename.as_deref().unwrap_or("<config>"),
+                 entry.incarnation,
+                 entry.encrypted,
+                 entry.hash,
+                 entry.client)?;
+    }
+    Ok(())
+}
+
+fn print_health_history<T>(out: &mut T,
+                           reply: &SrvMessage,
+                           print_header: bool)
+                           -> result::Result<(), SrvClientError>
+    where T: io::Write
+{
+    let history = match reply.message_id() {
+        "HealthCheckHistory" => {
+            reply.parse::<sup_proto::types::HealthCheckHistory>()
+                 .map_err(SrvClientError::Decode)?
+        }
+        "NetOk" => {
+            println!("No services loaded.");
+            return Ok(());
+        }
+        "NetErr" => {
+            let err = reply.parse::<sup_proto::net::NetErr>()
+                           .map_err(SrvClientError::Decode)?;
+            return Err(SrvClientError::from(err));
+        }
+        _ => {
+            warn!("Unexpected health history message, {:?}", reply);
+            return Ok(());
+        }
+    };
+    if print_header {
+        writeln!(out, "{}", HEALTH_HISTORY_HEADER.join("\t")).unwrap();
+    }
+    for record in history.records {
+        let time = chrono::NaiveDateTime::from_timestamp(record.timestamp as i64, 0);
+        let result = sup_proto::types::HealthCheckResult::from_i32(record.result)
+            .unwrap_or(sup_proto::types::HealthCheckResult::Unknown);
+        // Only the first line of the output fits in the table
+        let output = record.output
+                           .as_ref()
+                           .and_then(|output| output.lines().next())
+                           .unwrap_or_default();
+        writeln!(out,
+                 "{}\t{}Z\t{}\t{}\t{}",
+                 history.service_group,
+                 time.format("%Y-%m-%dT%H:%M:%S"),
+                 result,
+                 record.duration_ms
+                       .map_or_else(|| "<none>".to_string(), |d| d.to_string()),
+                 output)?;
+    }
     Ok(())
 }
 
@@ -1984,8 +2789,7 @@ fn bulkupload_dir_from_matches(matches: &ArgMatches<'_>) -> PathBuf {
 
 fn remote_sup_from_input(m: &ArgMatches<'_>) -> Result<ListenCtlAddr> {
     Ok(m.value_of("REMOTE_SUP")
-        .map_or(Ok(ListenCtlAddr::default()),
-                ListenCtlAddr::resolve_listen_ctl_addr)?)
+        .map_or(Ok(ListenCtlAddr::default()), resolve_remote_sup)?)
 }
 
 fn required_pkg_ident_from_input(m: &ArgMatches<'_>) -> Result<PackageIdent> {
@@ -2145,4 +2949,60 @@ mod test {
             binlink_dest_dir_from_matches(pkg_install_matches)
         }
     }
+
+    mod split_global_options {
+        use super::*;
+
+        fn split(args: &[&str]) -> (Option<String>, Vec<OsString>) {
+            let (options, rest) = super::split_global_options(args.iter().map(OsString::from));
+            (options.output, rest)
+        }
+
+        #[test]
+        fn no_output_option() {
+            assert_eq!(split(&["hab", "pkg", "list", "--all"]),
+                       (None, vec!["hab".into(), "pkg".into(), "list".into(), "--all".into()]));
+        }
+
+        #[test]
+        fn output_option_before_subcommand() {
+            assert_eq!(split(&["hab", "--output", "json", "pkg", "list"]),
+                       (Some("json".to_string()), vec!["hab".into(), "pkg".into(), "list".into()]));
+            assert_eq!(split(&["hab", "--output=json", "pkg", "list"]),
+                       (Some("json".to_string()), vec!["hab".into(), "pkg".into(), "list".into()]));
+        }
+
+        #[test]
+        fn output_option_after_subcommand_is_left_alone() {
+            assert_eq!(split(&["hab", "svc", "load", "--output", "json"]),
+                       (None,
+                        vec!["hab".into(),
+                             "svc".into(),
+                             "load".into(),
+                             "--output".into(),
+                             "json".into()]));
+        }
+
+        #[test]
+        fn output_option_without_value() {
+            assert_eq!(split(&["hab", "--output"]),
+                       (Some(String::new()), vec!["hab".into()]));
+        }
+
+        #[test]
+        fn ctl_timeout_option_with_output_option() {
+            let args = ["hab",
+                        "--ctl-timeout=30",
+                        "--output",
+                        "json",
+                        "svc",
+                        "status"];
+            let (options, rest) = super::split_global_options(args.iter().map(OsString::from));
+            assert_eq!(options,
+                       GlobalOptions { output:      Some("json".to_string()),
+                                       ctl_timeout: Some("30".to_string()), });
+            let expected: Vec<OsString> = vec!["hab".into(), "svc".into(), "status".into()];
+            assert_eq!(rest, expected);
+        }
+    }
 }
@@ -1,4 +1,6 @@
 @echo off
 REM target='{target}'
+setlocal
 {env}
-"{target}" %*
+{command}
+exit /b %ERRORLEVEL%
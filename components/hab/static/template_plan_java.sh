@@ -0,0 +1,26 @@
+# This file is the heart of your application's habitat.
+# See full docs at https://www.habitat.sh/docs/reference/plan-syntax/
+#
+# This plan builds the Gradle project in the parent directory of this plan and
+# packages the resulting jar.
+# The build callbacks are provided by the `core/scaffolding-gradle` Scaffolding
+# and can be overridden in this file if needed.
+
+pkg_name={{ pkg_name }}
+pkg_origin={{ pkg_origin }}
+pkg_version="{{ pkg_version }}"
+{{#if pkg_maintainer ~}}
+pkg_maintainer="{{ pkg_maintainer }}"
+{{~ else ~}}
+pkg_maintainer="The Habitat Maintainers <humans@habitat.sh>"
+{{~ /if}}
+{{#if pkg_license ~}}
+pkg_license={{ pkg_license }}
+{{~ else ~}}
+pkg_license=("Apache-2.0")
+{{~ /if}}
+pkg_scaffolding="core/scaffolding-gradle"
+pkg_exports=(
+  [port]=app.port
+)
+pkg_exposes=(port)
@@ -0,0 +1,26 @@
+# This file is the heart of your application's habitat.
+# See full docs at https://www.habitat.sh/docs/reference/plan-syntax/
+#
+# This plan builds the Python application in the parent directory of this plan,
+# installing it and its dependencies from its setup.py or requirements.txt.
+# The build callbacks are provided by the `core/scaffolding-python` Scaffolding
+# and can be overridden in this file if needed.
+
+pkg_name={{ pkg_name }}
+pkg_origin={{ pkg_origin }}
+pkg_version="{{ pkg_version }}"
+{{#if pkg_maintainer ~}}
+pkg_maintainer="{{ pkg_maintainer }}"
+{{~ else ~}}
+pkg_maintainer="The Habitat Maintainers <humans@habitat.sh>"
+{{~ /if}}
+{{#if pkg_license ~}}
+pkg_license={{ pkg_license }}
+{{~ else ~}}
+pkg_license=("Apache-2.0")
+{{~ /if}}
+pkg_scaffolding="core/scaffolding-python"
+pkg_exports=(
+  [port]=app.port
+)
+pkg_exposes=(port)
@@ -0,0 +1,62 @@
+# This file is the heart of your application's habitat.
+# See full docs at https://www.habitat.sh/docs/reference/plan-syntax/
+#
+# This plan builds the Cargo project in the parent directory of this plan and
+# packages the binary named after the package. There is no Rust scaffolding, so
+# the build callbacks are defined below.
+
+pkg_name={{ pkg_name }}
+pkg_origin={{ pkg_origin }}
+pkg_version="{{ pkg_version }}"
+{{#if pkg_maintainer ~}}
+pkg_maintainer="{{ pkg_maintainer }}"
+{{~ else ~}}
+pkg_maintainer="The Habitat Maintainers <humans@habitat.sh>"
+{{~ /if}}
+{{#if pkg_license ~}}
+pkg_license={{ pkg_license }}
+{{~ else ~}}
+pkg_license=("Apache-2.0")
+{{~ /if}}
+pkg_deps=(core/glibc core/gcc-libs)
+pkg_build_deps=(core/rust core/gcc core/pkg-config)
+pkg_bin_dirs=(bin)
+pkg_exports=(
+  [port]=app.port
+)
+pkg_exposes=(port)
+
+# The source is the Cargo project this plan lives in, so there is nothing to
+# download, verify or unpack.
+do_download() {
+  return 0
+}
+
+do_verify() {
+  return 0
+}
+
+do_unpack() {
+  return 0
+}
+
+do_prepare() {
+  export CARGO_HOME="$CACHE_PATH/cargo"
+  export CARGO_TARGET_DIR="$CACHE_PATH/target"
+}
+
+do_build() {
+  pushd "$PLAN_CONTEXT/.." > /dev/null
+  cargo build --release --locked
+  popd > /dev/null
+}
+
+do_check() {
+  pushd "$PLAN_CONTEXT/.." > /dev/null
+  cargo test --release --locked
+  popd > /dev/null
+}
+
+do_install() {
+  install -v -D "$CARGO_TARGET_DIR/release/$pkg_name" "$pkg_prefix/bin/$pkg_name"
+}
@@ -213,21 +213,24 @@ impl ApiClient {
 fn proxy_for(url: &Url) -> reqwest::Result<Proxy> {
     trace!("Checking proxy for url: {:?}", url);
 
-    if let Some(proxy_url) = env_proxy::for_url(url).to_string() {
-        match url.scheme() {
-            "http" => {
-                debug!("Setting http_proxy to {}", proxy_url);
-                Proxy::http(&proxy_url)
-            }
-            "https" => {
-                debug!("Setting https proxy to {}", proxy_url);
-                Proxy::https(&proxy_url)
-            }
-            _ => unimplemented!(),
+    // Only HTTP(S) requests go through a proxy, the endpoint may be a `file://` mirror.
+    let proxy_url = match url.scheme() {
+        "http" | "https" => env_proxy::for_url(url).to_string(),
+        _ => None,
+    };
+    match (url.scheme(), proxy_url) {
+        ("http", Some(proxy_url)) => {
+            debug!("Setting http_proxy to {}", proxy_url);
+            Proxy::http(&proxy_url)
+        }
+        ("https", Some(proxy_url)) => {
+            debug!("Setting https proxy to {}", proxy_url);
+            Proxy::https(&proxy_url)
+        }
+        _ => {
+            debug!("No proxy configured for url: {:?}", url);
+            Ok(Proxy::custom(|_| None::<Url>))
         }
-    } else {
-        debug!("No proxy configured for url: {:?}", url);
-        Ok(Proxy::custom(|_| None::<Url>))
     }
 }
 
@@ -384,11 +387,19 @@ fn certs_from_file(file_path: &Path) -> Result<Vec<Certificate>> {
 
 #[cfg(test)]
 mod tests {
-    use super::certs_from_file;
+    use super::{certs_from_file,
+                proxy_for};
     use native_tls::Certificate;
+    use reqwest::Url;
     use std::io::Write;
     use tempfile::NamedTempFile;
 
+    #[test]
+    fn file_urls_are_not_proxied() {
+        let url = Url::parse("file:///srv/mirror").unwrap();
+        assert!(proxy_for(&url).is_ok());
+    }
+
     #[test]
     fn test_certs_from_file() {
         const PEM_CERT: &str = "-----BEGIN CERTIFICATE-----
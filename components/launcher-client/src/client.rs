@@ -1,18 +1,29 @@
 use crate::error::{Error,
                    Result};
-use habitat_common::types::UserInfo;
-use habitat_core::os::process::Pid;
+use habitat_common::{output::{OutputTargets,
+                              ServiceOutputFormat},
+                     types::UserInfo,
+                     util::rotating_log::LogRotation};
+use habitat_core::os::{cgroup,
+                       isolation::Isolation,
+                       process::{Pid,
+                                 ProcessSettings}};
 use habitat_launcher_protocol::{self as protocol,
-                                Error as ProtocolError};
+                                Error as ProtocolError,
+                                LauncherMessage};
 use ipc_channel::ipc::{IpcOneShotServer,
                        IpcReceiver,
                        IpcSender};
 use std::{collections::BTreeMap,
           io,
           path::Path,
+          sync::atomic::{AtomicBool,
+                         Ordering},
           thread,
           time::{Duration,
-                 Instant}};
+                 Instant,
+                 SystemTime,
+                 UNIX_EPOCH}};
 
 type Env = BTreeMap<String, String>;
 type IpcServer = IpcOneShotServer<Vec<u8>>;
@@ -27,6 +38,31 @@ type IpcServer = IpcOneShotServer<Vec<u8>>;
 habitat_core::env_config_duration!(LauncherInteractionTimeout,
                                    HAB_LAUNCHER_INTERACTION_TIMEOUT_MS => from_millis,
                                    Duration::from_millis(1000));
+/// How the Launcher should capture a service's output.
+#[derive(Clone, Copy, Debug)]
+pub struct ServiceOutput<'a> {
+    /// Included in JSON-formatted output.
+    pub pkg_ident:      &'a str,
+    pub format:         ServiceOutputFormat,
+    /// The file the service's stdout is written to.
+    pub stdout_log:     Option<&'a Path>,
+    /// The file the service's stderr is written to; it shares `stdout_log` when unset.
+    pub stderr_log:     Option<&'a Path>,
+    pub rotation:       LogRotation,
+    /// A syslog receiver, or journald, to forward the output to.
+    pub forward:        Option<&'a str>,
+    pub stdout_targets: OutputTargets,
+    pub stderr_targets: OutputTargets,
+}
+
+/// A service process run by the Launcher.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ServiceProcess {
+    pub pid:        Pid,
+    /// When the process was started; older Launchers don't report this.
+    pub started_at: Option<SystemTime>,
+}
+
 pub struct LauncherCli {
     tx:   IpcSender<Vec<u8>>,
     rx:   IpcReceiver<Vec<u8>>,
@@ -39,6 +75,10 @@ pub struct LauncherCli {
 
     /// Maximum wait time for interactions that can timeout.
     timeout: Duration,
+
+    /// Set when a shutdown request from the Launcher arrived while waiting for the reply to
+    /// another message.
+    stopping: AtomicBool,
 }
 
 #[cfg(not(windows))]
@@ -72,7 +112,8 @@ impl LauncherCli {
                          rx,
                          #[cfg(not(windows))]
                          pipe: pipe_to_sup,
-                         timeout })
+                         timeout,
+                         stopping: AtomicBool::new(false) })
     }
 
     /// Read a launcher protocol message from a byte array
@@ -88,13 +129,39 @@ impl LauncherCli {
         Ok(msg)
     }
 
-    /// Receive and read protocol message from an IpcReceiver
-    fn recv<T>(rx: &IpcReceiver<Vec<u8>>) -> Result<T>
+    /// Read the reply to a message of type `T` from a byte array. Other messages are not
+    /// replies to it: a shutdown request is remembered for `is_stopping`, while anything else is
+    /// a late reply to an earlier message which timed out, and is dropped.
+    fn read_reply<T>(&self, bytes: &[u8]) -> Result<Option<T>>
         where T: protocol::LauncherMessage
     {
-        match rx.recv() {
-            Ok(bytes) => Self::read(&bytes),
-            Err(err) => Err(Error::from(*err)),
+        let txn = protocol::NetTxn::from_bytes(bytes)?;
+        match txn.message_id() {
+            id if id == T::MESSAGE_ID || id == "NetErr" => Self::read(bytes).map(Some),
+            id if id == protocol::Shutdown::MESSAGE_ID => {
+                self.stopping.store(true, Ordering::SeqCst);
+                Ok(None)
+            }
+            id => {
+                debug!("Dropping a stale {} reply from the Launcher", id);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Receive and read the reply to a message of type `T`
+    fn recv<T>(&self) -> Result<T>
+        where T: protocol::LauncherMessage
+    {
+        loop {
+            match self.rx.recv() {
+                Ok(bytes) => {
+                    if let Some(reply) = self.read_reply(&bytes)? {
+                        return Ok(reply);
+                    }
+                }
+                Err(err) => return Err(Error::from(*err)),
+            }
         }
     }
 
@@ -115,15 +182,22 @@ impl LauncherCli {
     /// *should* be fine, but I can't guarantee that right now).
     ///
     /// As such, use this with caution and intention.
-    fn recv_timeout<T>(rx: &IpcReceiver<Vec<u8>>, timeout: Duration) -> Result<T>
+    ///
+    /// A reply which arrives after the timeout is dropped once it is
+    /// received while waiting for the reply to another message.
+    fn recv_timeout<T>(&self) -> Result<T>
         where T: protocol::LauncherMessage
     {
         // If ipc_channel implemented this directly, we wouldn't have
         // to do this :(
         let start_time = Instant::now();
         loop {
-            match rx.try_recv().map_err(|e| Error::from(*e)) {
-                Ok(bytes) => return Self::read(&bytes),
+            match self.rx.try_recv().map_err(|e| Error::from(*e)) {
+                Ok(bytes) => {
+                    if let Some(reply) = self.read_reply(&bytes)? {
+                        return Ok(reply);
+                    }
+                }
                 Err(Error::IPCIO(io::ErrorKind::WouldBlock)) => {
                     trace!("try_recv would block; waiting 5ms");
                     thread::sleep(Duration::from_millis(5));
@@ -132,7 +206,7 @@ impl LauncherCli {
                     return Err(err);
                 }
             }
-            if start_time.elapsed() > timeout {
+            if start_time.elapsed() > self.timeout {
                 return Err(Error::Timeout);
             }
         }
@@ -148,22 +222,28 @@ impl LauncherCli {
         Ok(())
     }
 
-    /// Receive and read protocol message from an IpcReceiver
-    fn try_recv<T>(rx: &IpcReceiver<Vec<u8>>) -> Result<Option<T>>
+    /// Receive and read a message of type `T`, if one is waiting
+    fn try_recv<T>(&self) -> Result<Option<T>>
         where T: protocol::LauncherMessage
     {
-        match rx.try_recv().map_err(|err| Error::from(*err)) {
-            Ok(bytes) => {
-                let msg = Self::read::<T>(&bytes)?;
-                Ok(Some(msg))
+        loop {
+            match self.rx.try_recv().map_err(|err| Error::from(*err)) {
+                Ok(bytes) => {
+                    if let Some(msg) = self.read_reply(&bytes)? {
+                        return Ok(Some(msg));
+                    }
+                }
+                Err(Error::IPCIO(io::ErrorKind::WouldBlock)) => return Ok(None),
+                Err(err) => return Err(err),
             }
-            Err(Error::IPCIO(io::ErrorKind::WouldBlock)) => Ok(None),
-            Err(err) => Err(err),
         }
     }
 
     pub fn is_stopping(&self) -> bool {
-        match Self::try_recv::<protocol::Shutdown>(&self.rx) {
+        if self.stopping.load(Ordering::SeqCst) {
+            return true;
+        }
+        match self.try_recv::<protocol::Shutdown>() {
             Ok(Some(_)) | Err(Error::IPCIO(_)) => true,
             Ok(None) => false,
             Err(err) => panic!("Unexpected error checking for shutdown request, {}", err),
@@ -174,7 +254,7 @@ impl LauncherCli {
     pub fn restart(&self, pid: Pid) -> Result<Pid> {
         let msg = protocol::Restart { pid: pid.into() };
         Self::send(&self.tx, &msg)?;
-        let reply = Self::recv::<protocol::SpawnOk>(&self.rx)?;
+        let reply = self.recv::<protocol::SpawnOk>()?;
         Ok(reply.pid as Pid)
     }
 
@@ -183,6 +263,16 @@ impl LauncherCli {
     /// `username` and `groupname` are string names, while `uid` and
     /// `gid` are numeric IDs. Newer versions of the Launcher can
     /// accept either, but prefer numeric IDs.
+    ///
+    /// `limits` are applied to the service's cgroup where the Launcher
+    /// supports cgroup v2; older Launchers ignore them, as they do
+    /// `isolation` and `settings`.
+    ///
+    /// `output` determines where each of the service's stdout and
+    /// stderr is sent: the Launcher's console, rotated log files, a
+    /// syslog receiver or journald, and (by way of the log files) the
+    /// Supervisor's event stream.
+    #[allow(clippy::too_many_arguments)]
     pub fn spawn(&self,
                  id: &str,
                  bin: &Path,
@@ -191,7 +281,11 @@ impl LauncherCli {
                             groupname,
                             gid, }: UserInfo,
                  password: Option<&str>,
-                 env: Env)
+                 env: Env,
+                 limits: &cgroup::Limits,
+                 isolation: &Isolation,
+                 settings: &ProcessSettings,
+                 output: &ServiceOutput)
                  -> Result<Pid> {
         // On Windows, we only expect user to be Some.
         //
@@ -199,17 +293,51 @@ impl LauncherCli {
         // user and groupname may be either Some or None. Only the IDs are
         // used; names are only for backward compatibility with older
         // Launchers.
-        let msg = protocol::Spawn { binary: bin.to_string_lossy().into_owned(),
-                                    svc_user: username,
-                                    svc_group: groupname,
-                                    svc_user_id: uid,
-                                    svc_group_id: gid,
-                                    svc_password: password.map(str::to_string),
-                                    env,
-                                    id: id.to_string() };
+        let msg =
+            protocol::Spawn { binary: bin.to_string_lossy().into_owned(),
+                              svc_user: username,
+                              svc_group: groupname,
+                              svc_user_id: uid,
+                              svc_group_id: gid,
+                              svc_password: password.map(str::to_string),
+                              env,
+                              id: id.to_string(),
+                              cpu_max: limits.cpu_max.clone(),
+                              memory_max: limits.memory_max.clone(),
+                              io_weight: limits.io_weight,
+                              log_path: output.stdout_log.map(path_string),
+                              log_max_bytes: Some(output.rotation.max_bytes),
+                              log_max_age_secs: output.rotation.max_age.map(|age| age.as_secs()),
+                              log_max_files: Some(output.rotation.max_files),
+                              output_format: Some(output.format.to_string()),
+                              pkg_ident: Some(output.pkg_ident.to_string()),
+                              log_forward: output.forward.map(str::to_string),
+                              stdout_targets: Some(output.stdout_targets.bits()),
+                              stderr_targets: Some(output.stderr_targets.bits()),
+                              stderr_log_path: output.stderr_log.map(path_string),
+                              pid_namespace: Some(isolation.pid_namespace),
+                              network_namespace: Some(isolation.network_namespace),
+                              ports: isolation.ports.iter().map(ToString::to_string).collect(),
+                              seccomp_profile: isolation.seccomp_profile
+                                                        .as_ref()
+                                                        .map(ToString::to_string),
+                              filesystem_sandbox: Some(isolation.filesystem_sandbox),
+                              allowed_paths: isolation.allowed_paths
+                                                      .iter()
+                                                      .map(ToString::to_string)
+                                                      .collect(),
+                              umask: settings.umask.map(u32::from),
+                              working_dir: settings.working_dir.as_deref().map(path_string),
+                              ulimits: settings.ulimits
+                                               .iter()
+                                               .map(ToString::to_string)
+                                               .collect(),
+                              nice: settings.nice,
+                              io_priority: settings.io_priority.map(|p| p.to_string()),
+                              listen_fds: settings.listen_fds.clone() };
 
         Self::send(&self.tx, &msg)?;
-        let reply = Self::recv::<protocol::SpawnOk>(&self.rx)?;
+        let reply = self.recv::<protocol::SpawnOk>()?;
         if reply.pid == 0 {
             warn!(target: "pidfile_tracing", "Spawn operation for {} resulted in a spawned PID of 0, which \
                    should be impossible! (proceeding anyway)",
@@ -221,22 +349,41 @@ impl LauncherCli {
     /// Query the launcher for the PID of the named service. If the
     /// Launcher is aware of it, you'll get `Ok(Some(Pid))`
     pub fn pid_of(&self, service_name: &str) -> Result<Option<Pid>> {
+        Ok(self.process_of(service_name)?.map(|process| process.pid))
+    }
+
+    /// Query the launcher for the process of the named service. Unlike
+    /// `pid_of`, this includes when the process was started, which
+    /// older Launchers don't report.
+    pub fn process_of(&self, service_name: &str) -> Result<Option<ServiceProcess>> {
         let msg = protocol::PidOf { service_name: service_name.to_string(), };
         Self::send(&self.tx, &msg)?;
         // This should be a recv_timeout until pidfile-less
         // supervisors are the norm. We only expect to not receive a
         // response when dealing with older Launchers that didn't know
         // how to return PIDs.
-        let reply = Self::recv_timeout::<protocol::PidIs>(&self.rx, self.timeout)?;
+        let reply = self.recv_timeout::<protocol::PidIs>()?;
         // TODO (CM): really, we need to have all our protocol types
         // that use pids actually use a Pid type that's nonzero, with
         // lots of descriptive errors for failures.
         match reply.pid {
-            Some(pid) => Ok(Some(pid as Pid)),
+            Some(pid) => {
+                Ok(Some(ServiceProcess { pid:        pid as Pid,
+                                         started_at: reply.started_at.map(from_epoch_secs), }))
+            }
             None => Ok(None),
         }
     }
 
+    /// Make sure the Launcher is still responsive, and get the status
+    /// of the processes it runs. An `Error::Timeout` indicates the
+    /// Launcher is wedged; older Launchers reply with an unknown
+    /// message error instead.
+    pub fn heartbeat(&self) -> Result<protocol::HeartbeatOk> {
+        Self::send(&self.tx, &protocol::Heartbeat {})?;
+        self.recv_timeout::<protocol::HeartbeatOk>()
+    }
+
     /// Query the launcher for its version. If the
     /// Launcher is aware of it, you'll get `Ok(u32)`
     pub fn version(&self) -> Result<u32> {
@@ -245,14 +392,30 @@ impl LauncherCli {
 
         // We only expect to not receive a response when dealing with
         // older Launchers that didn't know how to return its version.
-        let reply = Self::recv_timeout::<protocol::VersionNumber>(&self.rx, self.timeout)?;
+        let reply = self.recv_timeout::<protocol::VersionNumber>()?;
         Ok(reply.version)
     }
 
+    /// Replace the arguments the Launcher uses to start the Supervisor the next time it is
+    /// restarted.
+    pub fn set_supervisor_args(&self, args: Vec<String>) -> Result<()> {
+        let msg = protocol::SupervisorArgs { args };
+        Self::send(&self.tx, &msg)?;
+        // Launchers which predate this message reply with an unknown message error, and the
+        // oldest ones do not reply at all, so only wait for a reply until the interaction times
+        // out.
+        self.recv_timeout::<protocol::NetOk>()?;
+        Ok(())
+    }
+
     pub fn terminate(&self, pid: Pid) -> Result<i32> {
         let msg = protocol::Terminate { pid: pid.into() };
         Self::send(&self.tx, &msg)?;
-        let reply = Self::recv::<protocol::TerminateOk>(&self.rx)?;
+        let reply = self.recv::<protocol::TerminateOk>()?;
         Ok(reply.exit_code)
     }
 }
+
+fn path_string(path: &Path) -> String { path.to_string_lossy().into_owned() }
+
+fn from_epoch_secs(secs: u64) -> SystemTime { UNIX_EPOCH + Duration::from_secs(secs) }
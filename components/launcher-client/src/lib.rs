@@ -9,7 +9,9 @@ pub use habitat_launcher_protocol::{ERR_NO_RETRY_EXCODE,
                                     LAUNCHER_PID_ENV,
                                     OK_NO_RETRY_EXCODE};
 
-pub use crate::{client::LauncherCli,
+pub use crate::{client::{LauncherCli,
+                         ServiceOutput,
+                         ServiceProcess},
                 error::Error};
 
 pub fn env_pipe() -> Option<String> {
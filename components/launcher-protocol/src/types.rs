@@ -126,14 +126,39 @@ impl From<Restart> for generated::Restart {
 
 #[derive(Clone, Debug, Default, PartialEq)]
 pub struct Spawn {
-    pub id:           String,
-    pub binary:       String,
-    pub svc_user:     Option<String>,
-    pub svc_group:    Option<String>,
-    pub svc_password: Option<String>,
-    pub env:          BTreeMap<String, String>,
-    pub svc_user_id:  Option<u32>,
-    pub svc_group_id: Option<u32>,
+    pub id:                 String,
+    pub binary:             String,
+    pub svc_user:           Option<String>,
+    pub svc_group:          Option<String>,
+    pub svc_password:       Option<String>,
+    pub env:                BTreeMap<String, String>,
+    pub svc_user_id:        Option<u32>,
+    pub svc_group_id:       Option<u32>,
+    pub cpu_max:            Option<String>,
+    pub memory_max:         Option<String>,
+    pub io_weight:          Option<u32>,
+    pub log_path:           Option<String>,
+    pub log_max_bytes:      Option<u64>,
+    pub log_max_age_secs:   Option<u64>,
+    pub log_max_files:      Option<u32>,
+    pub output_format:      Option<String>,
+    pub pkg_ident:          Option<String>,
+    pub log_forward:        Option<String>,
+    pub stdout_targets:     Option<u32>,
+    pub stderr_targets:     Option<u32>,
+    pub stderr_log_path:    Option<String>,
+    pub pid_namespace:      Option<bool>,
+    pub network_namespace:  Option<bool>,
+    pub ports:              Vec<String>,
+    pub seccomp_profile:    Option<String>,
+    pub filesystem_sandbox: Option<bool>,
+    pub allowed_paths:      Vec<String>,
+    pub umask:              Option<u32>,
+    pub working_dir:        Option<String>,
+    pub ulimits:            Vec<String>,
+    pub nice:               Option<i32>,
+    pub io_priority:        Option<String>,
+    pub listen_fds:         Vec<String>,
 }
 
 impl LauncherMessage for Spawn {
@@ -142,33 +167,85 @@ impl LauncherMessage for Spawn {
     const MESSAGE_ID: &'static str = "Spawn";
 
     fn from_proto(proto: generated::Spawn) -> Result<Self> {
-        Ok(Spawn { id:           proto.id.ok_or(Error::ProtocolMismatch("id"))?,
-                   binary:       proto.binary.ok_or(Error::ProtocolMismatch("binary"))?,
-                   svc_user:     proto.svc_user,
-                   svc_group:    proto.svc_group,
-                   svc_password: proto.svc_password,
-                   env:          BTreeMap::from_iter(proto.env.into_iter()),
-                   svc_user_id:  proto.svc_user_id,
-                   svc_group_id: proto.svc_group_id, })
+        Ok(Spawn { id:                 proto.id.ok_or(Error::ProtocolMismatch("id"))?,
+                   binary:             proto.binary.ok_or(Error::ProtocolMismatch("binary"))?,
+                   svc_user:           proto.svc_user,
+                   svc_group:          proto.svc_group,
+                   svc_password:       proto.svc_password,
+                   env:                BTreeMap::from_iter(proto.env.into_iter()),
+                   svc_user_id:        proto.svc_user_id,
+                   svc_group_id:       proto.svc_group_id,
+                   cpu_max:            proto.cpu_max,
+                   memory_max:         proto.memory_max,
+                   io_weight:          proto.io_weight,
+                   log_path:           proto.log_path,
+                   log_max_bytes:      proto.log_max_bytes,
+                   log_max_age_secs:   proto.log_max_age_secs,
+                   log_max_files:      proto.log_max_files,
+                   output_format:      proto.output_format,
+                   pkg_ident:          proto.pkg_ident,
+                   log_forward:        proto.log_forward,
+                   stdout_targets:     proto.stdout_targets,
+                   stderr_targets:     proto.stderr_targets,
+                   stderr_log_path:    proto.stderr_log_path,
+                   pid_namespace:      proto.pid_namespace,
+                   network_namespace:  proto.network_namespace,
+                   ports:              proto.ports,
+                   seccomp_profile:    proto.seccomp_profile,
+                   filesystem_sandbox: proto.filesystem_sandbox,
+                   allowed_paths:      proto.allowed_paths,
+                   umask:              proto.umask,
+                   working_dir:        proto.working_dir,
+                   ulimits:            proto.ulimits,
+                   nice:               proto.nice,
+                   io_priority:        proto.io_priority,
+                   listen_fds:         proto.listen_fds, })
     }
 }
 
 impl From<Spawn> for generated::Spawn {
     fn from(value: Spawn) -> Self {
-        generated::Spawn { id:           Some(value.id),
-                           binary:       Some(value.binary),
-                           svc_user:     value.svc_user,
-                           svc_group:    value.svc_group,
-                           svc_password: value.svc_password,
-                           env:          HashMap::from_iter(value.env.into_iter()),
-                           svc_user_id:  value.svc_user_id,
-                           svc_group_id: value.svc_group_id, }
+        generated::Spawn { id:                 Some(value.id),
+                           binary:             Some(value.binary),
+                           svc_user:           value.svc_user,
+                           svc_group:          value.svc_group,
+                           svc_password:       value.svc_password,
+                           env:                HashMap::from_iter(value.env.into_iter()),
+                           svc_user_id:        value.svc_user_id,
+                           svc_group_id:       value.svc_group_id,
+                           cpu_max:            value.cpu_max,
+                           memory_max:         value.memory_max,
+                           io_weight:          value.io_weight,
+                           log_path:           value.log_path,
+                           log_max_bytes:      value.log_max_bytes,
+                           log_max_age_secs:   value.log_max_age_secs,
+                           log_max_files:      value.log_max_files,
+                           output_format:      value.output_format,
+                           pkg_ident:          value.pkg_ident,
+                           log_forward:        value.log_forward,
+                           stdout_targets:     value.stdout_targets,
+                           stderr_targets:     value.stderr_targets,
+                           stderr_log_path:    value.stderr_log_path,
+                           pid_namespace:      value.pid_namespace,
+                           network_namespace:  value.network_namespace,
+                           ports:              value.ports,
+                           seccomp_profile:    value.seccomp_profile,
+                           filesystem_sandbox: value.filesystem_sandbox,
+                           allowed_paths:      value.allowed_paths,
+                           umask:              value.umask,
+                           working_dir:        value.working_dir,
+                           ulimits:            value.ulimits,
+                           nice:               value.nice,
+                           io_priority:        value.io_priority,
+                           listen_fds:         value.listen_fds, }
     }
 }
 
 #[derive(Clone, Debug, Default, PartialEq)]
 pub struct SpawnOk {
-    pub pid: i64,
+    pub pid:        i64,
+    /// Seconds since the UNIX epoch; older Launchers don't send it.
+    pub started_at: Option<u64>,
 }
 
 impl LauncherMessage for SpawnOk {
@@ -177,12 +254,16 @@ impl LauncherMessage for SpawnOk {
     const MESSAGE_ID: &'static str = "SpawnOk";
 
     fn from_proto(proto: generated::SpawnOk) -> Result<Self> {
-        Ok(SpawnOk { pid: proto.pid.ok_or(Error::ProtocolMismatch("pid"))?, })
+        Ok(SpawnOk { pid:        proto.pid.ok_or(Error::ProtocolMismatch("pid"))?,
+                     started_at: proto.started_at, })
     }
 }
 
 impl From<SpawnOk> for generated::SpawnOk {
-    fn from(value: SpawnOk) -> Self { generated::SpawnOk { pid: Some(value.pid), } }
+    fn from(value: SpawnOk) -> Self {
+        generated::SpawnOk { pid:        Some(value.pid),
+                             started_at: value.started_at, }
+    }
 }
 
 #[derive(Clone, Debug, Default, PartialEq)]
@@ -299,7 +380,9 @@ impl From<PidOf> for generated::PidOf {
 
 #[derive(Clone, Debug)]
 pub struct PidIs {
-    pub pid: Option<u32>,
+    pub pid:        Option<u32>,
+    /// Seconds since the UNIX epoch; older Launchers don't send it.
+    pub started_at: Option<u64>,
 }
 
 impl LauncherMessage for PidIs {
@@ -309,7 +392,8 @@ impl LauncherMessage for PidIs {
 
     fn from_proto(proto: generated::PidIs) -> Result<Self> {
         // TODO (CM): ensure that the Pid is never Some(0)
-        Ok(PidIs { pid: proto.pid })
+        Ok(PidIs { pid:        proto.pid,
+                   started_at: proto.started_at, })
     }
 }
 
@@ -318,7 +402,10 @@ impl From<PidIs> for generated::PidIs {
     // a non-zero u32
     //
     // Perhaps we truly do need a NonZero Pid type here
-    fn from(value: PidIs) -> Self { generated::PidIs { pid: value.pid } }
+    fn from(value: PidIs) -> Self {
+        generated::PidIs { pid:        value.pid,
+                           started_at: value.started_at, }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -356,3 +443,105 @@ impl LauncherMessage for Version {
 impl From<Version> for generated::Version {
     fn from(_value: Version) -> Self { generated::Version {} }
 }
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SupervisorArgs {
+    pub args: Vec<String>,
+}
+
+impl LauncherMessage for SupervisorArgs {
+    type Generated = generated::SupervisorArgs;
+
+    const MESSAGE_ID: &'static str = "SupervisorArgs";
+
+    fn from_proto(proto: generated::SupervisorArgs) -> Result<Self> {
+        Ok(SupervisorArgs { args: proto.args })
+    }
+}
+
+impl From<SupervisorArgs> for generated::SupervisorArgs {
+    fn from(value: SupervisorArgs) -> Self { generated::SupervisorArgs { args: value.args } }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Heartbeat {}
+
+impl LauncherMessage for Heartbeat {
+    type Generated = generated::Heartbeat;
+
+    const MESSAGE_ID: &'static str = "Heartbeat";
+
+    fn from_proto(_proto: generated::Heartbeat) -> Result<Self> { Ok(Heartbeat {}) }
+}
+
+impl From<Heartbeat> for generated::Heartbeat {
+    fn from(_value: Heartbeat) -> Self { generated::Heartbeat {} }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HeartbeatOk {
+    pub launcher_pid: u32,
+    pub processes:    Vec<ProcessStatus>,
+}
+
+impl LauncherMessage for HeartbeatOk {
+    type Generated = generated::HeartbeatOk;
+
+    const MESSAGE_ID: &'static str = "HeartbeatOk";
+
+    fn from_proto(proto: generated::HeartbeatOk) -> Result<Self> {
+        let processes = proto.processes
+                             .into_iter()
+                             .map(ProcessStatus::from_proto)
+                             .collect::<Result<_>>()?;
+        Ok(HeartbeatOk { launcher_pid: proto.launcher_pid
+                                            .ok_or(Error::ProtocolMismatch("launcher_pid"))?,
+                         processes })
+    }
+}
+
+impl From<HeartbeatOk> for generated::HeartbeatOk {
+    fn from(value: HeartbeatOk) -> Self {
+        generated::HeartbeatOk { launcher_pid: Some(value.launcher_pid),
+                                 processes:    value.processes
+                                                    .into_iter()
+                                                    .map(Into::into)
+                                                    .collect(), }
+    }
+}
+
+/// A process run by the Launcher, as reported in `HeartbeatOk`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProcessStatus {
+    pub id:             String,
+    pub pid:            u32,
+    /// Seconds since the UNIX epoch.
+    pub started_at:     u64,
+    pub cpu_usage_usec: Option<u64>,
+    pub memory_bytes:   Option<u64>,
+    /// Orphaned descendants of the process which the Launcher adopted.
+    pub adopted_pids:   Vec<u32>,
+}
+
+impl ProcessStatus {
+    fn from_proto(proto: generated::ProcessStatus) -> Result<Self> {
+        Ok(ProcessStatus { id:             proto.id.ok_or(Error::ProtocolMismatch("id"))?,
+                           pid:            proto.pid.ok_or(Error::ProtocolMismatch("pid"))?,
+                           started_at:     proto.started_at
+                                                .ok_or(Error::ProtocolMismatch("started_at"))?,
+                           cpu_usage_usec: proto.cpu_usage_usec,
+                           memory_bytes:   proto.memory_bytes,
+                           adopted_pids:   proto.adopted_pids, })
+    }
+}
+
+impl From<ProcessStatus> for generated::ProcessStatus {
+    fn from(value: ProcessStatus) -> Self {
+        generated::ProcessStatus { id:             Some(value.id),
+                                   pid:            Some(value.pid),
+                                   started_at:     Some(value.started_at),
+                                   cpu_usage_usec: value.cpu_usage_usec,
+                                   memory_bytes:   value.memory_bytes,
+                                   adopted_pids:   value.adopted_pids, }
+    }
+}
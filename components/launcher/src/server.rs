@@ -1,9 +1,13 @@
 mod handlers;
 
+#[cfg(target_os = "linux")]
+use crate::sys::orphans::{self,
+                          Orphans};
 use crate::{core::{self,
                    fs::{launcher_root_path,
                         FS_ROOT_PATH},
-                   os::{process,
+                   os::{cgroup,
+                        process,
                         signals},
                    package::{PackageIdent,
                              PackageInstall}},
@@ -20,8 +24,10 @@ use habitat_common::{liveliness_checker::{self,
                                           ThreadUnregistered},
                      outputln};
 #[cfg(unix)]
-use habitat_core::os::process::{Pid,
-                                Signal};
+use habitat_core::os::{process::{Pid,
+                                 Signal},
+                       socket_activation::{self,
+                                           ListenFd}};
 use ipc_channel::ipc::{IpcOneShotServer,
                        IpcReceiver,
                        IpcSender};
@@ -84,15 +90,33 @@ impl Drop for Server {
 
 impl Server {
     pub fn new(args: Vec<String>) -> Result<Self> {
+        // The sockets must be taken before the Supervisor is spawned, so that it doesn't
+        // inherit them.
+        #[allow(unused_mut)]
+        let mut services = ServiceTable::default();
+        #[cfg(unix)]
+        {
+            services.listen_fds = take_listen_fds();
+        }
+
         let launcher_root = launcher_root_path(Some(&*core::fs::FS_ROOT_PATH));
         fs::create_dir_all(&launcher_root)?;
         let pid_file_path = launcher_root.join("PID");
         let mut pid_file = fs::File::create(&pid_file_path)?;
         write!(&mut pid_file, "{}", process::current_pid())?;
 
+        #[cfg(target_os = "linux")]
+        {
+            if let Err(err) = orphans::become_subreaper() {
+                warn!("Unable to become a child subreaper; orphaned service processes will be \
+                       re-parented to init: {}",
+                      err);
+            }
+        }
+
         let ((rx, tx), supervisor, pipe) = Self::init(&args, false)?;
         Ok(Server { pid_file_path,
-                    services: ServiceTable::default(),
+                    services,
                     tx,
                     rx,
                     pipe,
@@ -150,7 +174,7 @@ impl Server {
     fn handle_message(&mut self) -> Result<TickState> {
         match self.rx.try_recv() {
             Ok(bytes) => {
-                dispatch(&self.tx, &bytes, &mut self.services);
+                dispatch(&self.tx, &bytes, &mut self.services, &mut self.args);
                 Ok(TickState::Continue)
             }
             Err(_) => {
@@ -256,6 +280,8 @@ impl Server {
         // be done in a way that the basic functionality of process
         // tracking still works on Windows.
         self.reap_services();
+        #[cfg(target_os = "linux")]
+        self.services.scan_orphans();
 
         if signals::pending_shutdown() {
             self.shutdown();
@@ -288,10 +314,10 @@ impl Server {
     /// need to call `waitpid` on these children to prevent a zombie
     /// horde from ultimately bringing down the system.
     ///
-    /// Note that we are not (yet?) doing anything with
-    /// `prctl(PR_SET_CHILD_SUBREAPER, ...)` to make the Launcher a
-    /// subreaper; this behavior currently handles the case when the
-    /// Launcher is running as PID 1.
+    /// On Linux, the Launcher also makes itself a child subreaper with
+    /// `prctl(PR_SET_CHILD_SUBREAPER, ...)`, so orphaned descendants of
+    /// services are re-parented to it even when it isn't PID 1. Those
+    /// are attributed to their service by `scan_orphans`.
     ///
     /// (See http://man7.org/linux/man-pages/man2/prctl.2.html for
     /// further information.)
@@ -318,6 +344,13 @@ impl Server {
                         // Note: from_raw is a Unix-only call
                         reaped_sup_status = Some(ExitStatus::from_raw(waitpid_status));
                     } else {
+                        #[cfg(target_os = "linux")]
+                        {
+                            if let Some(service) = self.services.orphan_reaped(res as u32) {
+                                outputln!(preamble service, "Reaped orphaned process {}", res);
+                                continue;
+                            }
+                        }
                         debug!("Reaped a non-supervisor child process, PID {}", res);
                     }
                 }
@@ -343,22 +376,36 @@ impl Server {
             // with an exit code, or it was terminated by a signal
             // that it couldn't otherwise handle.
             //
-            // In the latter case, we treat it as though the
-            // Supervisor shut down, but we will not restart.
+            // In the latter case, TERM and INT are treated as though
+            // the Supervisor shut down, and we will not restart it;
+            // any other signal means it crashed, and it is restarted.
             if let Some(exit_code) = status.code() {
                 debug!("Supervisor exit status: {}", exit_code);
                 Some(self.handle_supervisor_exit(Some(exit_code)))
             } else if let Some(signal) = status.signal() {
-                // If you TERM or INT the Supervisor (currently), the
-                // Supervisor does not otherwise catch the signal. The
-                // previous Launcher implementation ultimately shut
-                // down in this scenario, but by accident, and through
-                // at least one restart/stop cycle of the
-                // Supervisor. This just makes the behavior explicit;
-                // it can be revisited later.
-                outputln!("Supervisor process killed by signal {}; shutting everything down now",
-                          signal);
-                Some(self.handle_supervisor_exit(Some(ERR_NO_RETRY_EXCODE)))
+                if signal == libc::SIGTERM || signal == libc::SIGINT {
+                    // If you TERM or INT the Supervisor (currently), the
+                    // Supervisor does not otherwise catch the signal. The
+                    // previous Launcher implementation ultimately shut
+                    // down in this scenario, but by accident, and through
+                    // at least one restart/stop cycle of the
+                    // Supervisor. This just makes the behavior explicit;
+                    // it can be revisited later.
+                    outputln!("Supervisor process killed by signal {}; shutting everything down \
+                               now",
+                              signal);
+                    Some(self.handle_supervisor_exit(Some(ERR_NO_RETRY_EXCODE)))
+                } else {
+                    // Any other signal (KILL from the OOM killer, SEGV,
+                    // ABRT, ...) means the Supervisor crashed. Its state
+                    // is persisted as it changes, so we restart it and
+                    // let it re-adopt the services which are still
+                    // running rather than bouncing all of them.
+                    outputln!("Supervisor process crashed with signal {}; restarting it and \
+                               leaving services running",
+                              signal);
+                    Some(self.handle_supervisor_exit(None))
+                }
             } else {
                 // We should never get here; a Linux process either
                 // exits with a status code, or it was killed with a
@@ -375,16 +422,27 @@ impl Server {
 }
 
 #[derive(Debug, Default)]
-pub struct ServiceTable(HashMap<u32, Service>);
+pub struct ServiceTable {
+    services:   HashMap<u32, Service>,
+    #[cfg(target_os = "linux")]
+    orphans:    Orphans,
+    /// The sockets systemd passed to the Launcher, which it holds on to for the services that
+    /// take them over.
+    #[cfg(unix)]
+    listen_fds: Vec<ListenFd>,
+}
 
 impl ServiceTable {
-    pub fn get(&self, pid: u32) -> Option<&Service> { self.0.get(&pid) }
+    pub fn get(&self, pid: u32) -> Option<&Service> { self.services.get(&pid) }
 
-    pub fn get_mut(&mut self, pid: u32) -> Option<&mut Service> { self.0.get_mut(&pid) }
+    #[cfg(unix)]
+    pub fn listen_fds(&self) -> &[ListenFd] { &self.listen_fds }
 
-    pub fn insert(&mut self, service: Service) { self.0.insert(service.id(), service); }
+    pub fn get_mut(&mut self, pid: u32) -> Option<&mut Service> { self.services.get_mut(&pid) }
 
-    pub fn remove(&mut self, pid: u32) -> Option<Service> { self.0.remove(&pid) }
+    pub fn insert(&mut self, service: Service) { self.services.insert(service.id(), service); }
+
+    pub fn remove(&mut self, pid: u32) -> Option<Service> { self.services.remove(&pid) }
 
     // Obviously this is not the most elegant implementation. However,
     // in practice we don't have a whole lot of processes per
@@ -401,33 +459,68 @@ impl ServiceTable {
     // TODO (CM): Enforce that service_name is actually a full
     // ServiceGroup name (and elsewhere)
     /// Given the name of a service group (e.g. "redis.default"),
-    /// return the PID of the process we're currently running for that
-    /// service group, if it exists.
+    /// return the process we're currently running for that service
+    /// group, if it exists.
     ///
     /// This allows a restarting Supervisor to query the Launcher to
     /// figure out if there are currently-running services to which it
     /// needs to re-attach itself.
-    pub fn pid_of(&self, service_name: &str) -> Option<u32> {
-        self.0.iter().find_map(|(pid, service)| {
-                         if service_name == service.args().id {
-                             Some(*pid)
-                         } else {
-                             None
-                         }
-                     })
+    pub fn find(&self, service_name: &str) -> Option<&Service> {
+        self.services
+            .values()
+            .find(|service| service_name == service.args().id)
+    }
+
+    /// The status of every process we're currently running.
+    pub fn statuses(&self) -> Vec<protocol::ProcessStatus> {
+        self.services
+            .values()
+            .map(|service| {
+                let mut status = service.status();
+                #[cfg(target_os = "linux")]
+                {
+                    status.adopted_pids = self.orphans.adopted_by(service.name());
+                }
+                status
+            })
+            .collect()
     }
 
+    /// Terminate the orphaned descendants of the named service once
+    /// the service itself has been stopped. Orphans are only tracked
+    /// on Linux.
+    #[allow(unused_variables)]
+    pub fn terminate_orphans_of(&mut self, service_name: &str) {
+        #[cfg(target_os = "linux")]
+        self.orphans.terminate(service_name);
+    }
+
+    /// Keep track of the descendants of our services, so those that
+    /// get re-parented to us can be attributed to their service.
+    #[cfg(target_os = "linux")]
+    fn scan_orphans(&mut self) { self.orphans.scan(&self.services) }
+
+    /// Forget a process reaped by `reap_zombie_orphans`, returning
+    /// the service it belonged to, if any.
+    #[cfg(target_os = "linux")]
+    fn orphan_reaped(&mut self, pid: u32) -> Option<String> { self.orphans.reaped(pid) }
+
     fn kill_all(&mut self) {
-        for service in self.0.values_mut() {
+        let mut names = Vec::new();
+        for service in self.services.values_mut() {
             outputln!(preamble service.name(), "Stopping...");
             let shutdown_method = service.kill();
             outputln!(preamble service.name(), "Shutdown OK: {}", shutdown_method);
+            names.push(service.name().to_string());
+        }
+        for name in names {
+            self.terminate_orphans_of(&name);
         }
     }
 
     fn reap_services(&mut self) {
         let mut dead: Vec<u32> = vec![];
-        for service in self.0.values_mut() {
+        for service in self.services.values_mut() {
             match service.try_wait() {
                 Ok(None) => (),
                 Ok(Some(code)) => {
@@ -444,7 +537,11 @@ impl ServiceTable {
             }
         }
         for pid in dead {
-            self.0.remove(&pid);
+            if let Some(service) = self.services.remove(&pid) {
+                if let Err(err) = cgroup::remove(service.name()) {
+                    debug!("Unable to remove cgroup of {}: {}", service.name(), err);
+                }
+            }
         }
     }
 }
@@ -487,7 +584,30 @@ pub fn send<T>(tx: &Sender, msg: &T) -> Result<()>
 // Private Func
 //
 
-fn dispatch(tx: &Sender, bytes: &[u8], services: &mut ServiceTable) {
+/// Take the sockets systemd passed to the Launcher, if it was started by socket activation.
+#[cfg(unix)]
+fn take_listen_fds() -> Vec<ListenFd> {
+    match socket_activation::take_listen_fds() {
+        Ok(fds) => {
+            if !fds.is_empty() {
+                let names = fds.iter()
+                               .map(|fd| fd.name.as_str())
+                               .collect::<Vec<_>>()
+                               .join(", ");
+                outputln!("Holding {} socket(s) passed by systemd: {}",
+                          fds.len(),
+                          names);
+            }
+            fds
+        }
+        Err(err) => {
+            warn!("Ignoring the sockets passed by systemd: {}", err);
+            Vec::new()
+        }
+    }
+}
+
+fn dispatch(tx: &Sender, bytes: &[u8], services: &mut ServiceTable, sup_args: &mut Vec<String>) {
     let msg = match protocol::NetTxn::from_bytes(bytes) {
         Ok(msg) => msg,
         Err(err) => {
@@ -501,6 +621,11 @@ fn dispatch(tx: &Sender, bytes: &[u8], services: &mut ServiceTable) {
         "Terminate" => handlers::TerminateHandler::run,
         "PidOf" => handlers::PidHandler::run,
         "Version" => handlers::VersionHandler::run,
+        "Heartbeat" => handlers::HeartbeatHandler::run,
+        "SupervisorArgs" => {
+            set_supervisor_args(tx, &msg, sup_args);
+            return;
+        }
         unknown => {
             // This sucks a bit because it replicates some code from the
             // Handler trait, but manipulating an unknown message
@@ -518,6 +643,22 @@ fn dispatch(tx: &Sender, bytes: &[u8], services: &mut ServiceTable) {
     func(tx, msg, services);
 }
 
+/// Replace the arguments used to start the Supervisor. They are only used the next time the
+/// Supervisor is restarted, which leaves running services untouched.
+fn set_supervisor_args(tx: &Sender, txn: &protocol::NetTxn, sup_args: &mut Vec<String>) {
+    match txn.decode::<protocol::SupervisorArgs>() {
+        Ok(msg) => {
+            debug!("Supervisor arguments changed from {:?} to {:?}",
+                   sup_args, msg.args);
+            *sup_args = msg.args;
+            if let Err(err) = send(tx, &protocol::NetOk::default()) {
+                error!("{}: replying, {}", txn.message_id(), err);
+            }
+        }
+        Err(err) => error!("{}: decoding, {}", txn.message_id(), err),
+    }
+}
+
 #[allow(clippy::mutex_atomic)] // A Mutex is required for Condvar::wait_timeout
 fn setup_connection(server: IpcOneShotServer<Vec<u8>>) -> Result<(Receiver, Sender)> {
     let pair = Arc::new((Mutex::new(false), Condvar::new()));
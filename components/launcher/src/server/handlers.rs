@@ -1,10 +1,12 @@
+mod heartbeat;
 mod pid;
 mod restart;
 mod spawn;
 mod terminate;
 mod version;
 
-pub use self::{pid::*,
+pub use self::{heartbeat::*,
+               pid::*,
                restart::*,
                spawn::*,
                terminate::*,
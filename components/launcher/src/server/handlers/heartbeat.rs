@@ -0,0 +1,18 @@
+use super::{HandleResult,
+            Handler};
+use crate::{core::os::process,
+            protocol,
+            server::ServiceTable};
+
+pub struct HeartbeatHandler;
+
+impl Handler for HeartbeatHandler {
+    type Message = protocol::Heartbeat;
+    type Reply = protocol::HeartbeatOk;
+
+    fn handle(_: Self::Message, services: &mut ServiceTable) -> HandleResult<Self::Reply> {
+        let reply = protocol::HeartbeatOk { launcher_pid: process::current_pid() as u32,
+                                            processes:    services.statuses(), };
+        Ok(reply)
+    }
+}
@@ -10,9 +10,16 @@ impl Handler for PidHandler {
     type Reply = protocol::PidIs;
 
     fn handle(msg: Self::Message, services: &mut ServiceTable) -> HandleResult<Self::Reply> {
-        let service_name = msg.service_name;
-        let pid = services.pid_of(&service_name);
-        let reply = protocol::PidIs { pid };
+        let reply = match services.find(&msg.service_name) {
+            Some(service) => {
+                protocol::PidIs { pid:        Some(service.id()),
+                                  started_at: Some(service.started_at()), }
+            }
+            None => {
+                protocol::PidIs { pid:        None,
+                                  started_at: None, }
+            }
+        };
         Ok(reply)
     }
 }
@@ -22,10 +22,12 @@ impl Handler for RestartHandler {
         service.kill();
         match service.wait() {
             Ok(_status) => {
+                services.terminate_orphans_of(service.name());
                 match service::run(service.take_args()) {
                     Ok(new_service) => {
                         let mut reply = protocol::SpawnOk::default();
                         reply.pid = new_service.id().into();
+                        reply.started_at = Some(new_service.started_at());
                         services.insert(new_service);
                         Ok(reply)
                     }
@@ -11,10 +11,15 @@ impl Handler for SpawnHandler {
     type Reply = protocol::SpawnOk;
 
     fn handle(msg: Self::Message, services: &mut ServiceTable) -> HandleResult<Self::Reply> {
-        match service::run(msg) {
+        #[cfg(unix)]
+        let spawned = service::run(msg, services.listen_fds());
+        #[cfg(windows)]
+        let spawned = service::run(msg);
+        match spawned {
             Ok(service) => {
                 let mut reply = protocol::SpawnOk::default();
                 reply.pid = service.id().into();
+                reply.started_at = Some(service.started_at());
                 services.insert(service);
                 Ok(reply)
             }
@@ -14,8 +14,10 @@ impl Handler for TerminateHandler {
             Some(service) => {
                 debug!("Terminating: {}", service.id());
                 let shutdown_method = service.kill();
+                let name = service.name().to_string();
                 match service.wait() {
                     Ok(status) => {
+                        services.terminate_orphans_of(&name);
                         let mut reply = protocol::TerminateOk::default();
                         reply.exit_code = status.code().unwrap_or(0);
                         reply.shutdown_method = shutdown_method;
@@ -3,9 +3,19 @@ use crate::protocol;
 use core::os::process::windows_child::{ChildStderr,
                                        ChildStdout,
                                        ExitStatus};
-use core::util::BufReadLossy;
-use habitat_common::output::{self,
-                             StructuredOutput};
+use core::{os::cgroup,
+           util::BufReadLossy};
+use habitat_common::{output::{self,
+                              OutputTargets,
+                              ServiceOutputFormat,
+                              ServiceOutputLine,
+                              ServiceStream,
+                              StructuredOutput},
+                     util::{log_forward::{LogForward,
+                                          LogForwarder,
+                                          Severity},
+                            rotating_log::{LogRotation,
+                                           RotatingLog}}};
 #[cfg(unix)]
 use std::process::{ChildStderr,
                    ChildStdout,
@@ -13,14 +23,28 @@ use std::process::{ChildStderr,
 use std::{fmt,
           io::{self,
                BufReader,
-               Read},
-          thread};
+               Read,
+               Write},
+          path::Path,
+          sync::{atomic::{AtomicBool,
+                          Ordering},
+                 mpsc::{self,
+                        Receiver,
+                        SyncSender,
+                        TrySendError},
+                 Arc,
+                 Mutex},
+          thread,
+          time::{Duration,
+                 SystemTime,
+                 UNIX_EPOCH}};
 
 pub use crate::sys::service::*;
 
 pub struct Service {
-    args:    protocol::Spawn,
-    process: Process,
+    args:       protocol::Spawn,
+    process:    Process,
+    started_at: SystemTime,
 }
 
 impl Service {
@@ -29,20 +53,21 @@ impl Service {
                stdout: Option<ChildStdout>,
                stderr: Option<ChildStderr>)
                -> Self {
+        let sink = OutputSink::new(&spawn, process.id());
         if let Some(stdout) = stdout {
-            let id = spawn.id.to_string();
+            let sink = sink.clone();
             thread::Builder::new().name(format!("{}-out", spawn.id))
-                                  .spawn(move || pipe_stdout(stdout, &id))
+                                  .spawn(move || pipe_output(stdout, ServiceStream::Stdout, &sink))
                                   .ok();
         }
         if let Some(stderr) = stderr {
-            let id = spawn.id.to_string();
             thread::Builder::new().name(format!("{}-err", spawn.id))
-                                  .spawn(move || pipe_stderr(stderr, &id))
+                                  .spawn(move || pipe_output(stderr, ServiceStream::Stderr, &sink))
                                   .ok();
         }
         Service { args: spawn,
-                  process }
+                  process,
+                  started_at: SystemTime::now() }
     }
 
     pub fn args(&self) -> &protocol::Spawn { &self.args }
@@ -55,6 +80,29 @@ impl Service {
 
     pub fn name(&self) -> &str { &self.args.id }
 
+    /// When the process was started, in seconds since the UNIX epoch.
+    pub fn started_at(&self) -> u64 {
+        self.started_at
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default()
+    }
+
+    /// The process's start time and the resource usage of its cgroup, if it has one.
+    pub fn status(&self) -> protocol::ProcessStatus {
+        let usage = if cgroup::is_available() {
+            cgroup::usage(self.name()).ok()
+        } else {
+            None
+        };
+        protocol::ProcessStatus { id:             self.name().to_string(),
+                                  pid:            self.id(),
+                                  started_at:     self.started_at(),
+                                  cpu_usage_usec: usage.map(|u| u.cpu_usage_usec),
+                                  memory_bytes:   usage.and_then(|u| u.memory_current_bytes),
+                                  adopted_pids:   Vec::new(), }
+    }
+
     pub fn take_args(self) -> protocol::Spawn { self.args }
 
     pub fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> { self.process.try_wait() }
@@ -68,42 +116,233 @@ impl fmt::Debug for Service {
     }
 }
 
-/// Consume output from a child process until EOF, then finish
-fn pipe_stdout<T>(out: T, id: &str)
-    where T: Read
-{
-    for line in BufReader::new(out).lines_lossy() {
-        match line {
-            Ok(line) => {
-                let so = StructuredOutput::succinct(&id, "O", output::get_format(), &line);
-                if let Err(e) = so.println() {
-                    println!("printing output: '{}' to stdout resulted in error: {}",
-                             &line, e);
+/// How many lines of a service's output may wait to be forwarded before further lines are
+/// dropped.
+const FORWARD_QUEUE_LEN: usize = 1024;
+
+type SharedLog = Option<Arc<Mutex<RotatingLog>>>;
+
+/// Hands the lines of a service to a thread of its own which sends them with a `LogForwarder`,
+/// so that a slow or unreachable receiver never holds up the reading of the service's output.
+/// Lines are dropped while the queue of that thread is full.
+#[derive(Clone)]
+struct Forwarder {
+    target:   LogForward,
+    tx:       SyncSender<(Severity, String)>,
+    dropping: Arc<AtomicBool>,
+}
+
+/// Set up forwarding of the service's output, if the Supervisor asked for it.
+fn open_forwarder(spawn: &protocol::Spawn, pid: u32) -> Option<Forwarder> {
+    let target = match spawn.log_forward.as_ref()?.parse::<LogForward>() {
+        Ok(target) => target,
+        Err(e) => {
+            warn!("Not forwarding the output of {}: {}", spawn.id, e);
+            return None;
+        }
+    };
+    let (tx, rx) = mpsc::sync_channel::<(Severity, String)>(FORWARD_QUEUE_LEN);
+    let mut forwarder = LogForwarder::new(target.clone(), &spawn.id, pid);
+    let id = spawn.id.clone();
+    let spawned = thread::Builder::new().name(format!("{}-fwd", spawn.id))
+                                        .spawn(move || forward_lines(&id, &mut forwarder, rx));
+    if let Err(e) = spawned {
+        warn!("Not forwarding the output of {}: {}", spawn.id, e);
+        return None;
+    }
+    Some(Forwarder { target,
+                     tx,
+                     dropping: Arc::new(AtomicBool::new(false)) })
+}
+
+/// Send the lines received on `rx` until every sender is gone. Only the first of a series of
+/// failures is reported, so an unreachable receiver doesn't produce an error for every line.
+fn forward_lines(id: &str, forwarder: &mut LogForwarder, rx: Receiver<(Severity, String)>) {
+    let mut failing = false;
+    for (severity, line) in rx {
+        match forwarder.send(severity, &line) {
+            Ok(()) => failing = false,
+            Err(e) if !failing => {
+                println!("forwarding output of {} to {} resulted in error: {}",
+                         id,
+                         forwarder.target(),
+                         e);
+                failing = true;
+            }
+            Err(_) => {}
+        }
+    }
+}
+
+/// Open one of the service's log files.
+fn open_log(spawn: &protocol::Spawn, path: &str) -> SharedLog {
+    let defaults = LogRotation::default();
+    let rotation = LogRotation { max_bytes: spawn.log_max_bytes.unwrap_or(defaults.max_bytes),
+                                 max_age:   spawn.log_max_age_secs.map(Duration::from_secs),
+                                 max_files: spawn.log_max_files.unwrap_or(defaults.max_files), };
+    if !rotation.is_enabled() {
+        return None;
+    }
+    match RotatingLog::open(Path::new(path), rotation) {
+        Ok(log) => Some(Arc::new(Mutex::new(log))),
+        Err(e) => {
+            warn!("Unable to open log file {} for {}: {}", path, spawn.id, e);
+            None
+        }
+    }
+}
+
+/// Open the log files for the service's stdout and stderr, if the Supervisor asked for them.
+/// Unless a separate file is given for stderr, both streams share the same file.
+fn open_logs(spawn: &protocol::Spawn) -> (SharedLog, SharedLog) {
+    let stdout = spawn.log_path
+                      .as_ref()
+                      .and_then(|path| open_log(spawn, path));
+    let stderr = match spawn.stderr_log_path {
+        Some(ref path) if Some(path) != spawn.log_path.as_ref() => open_log(spawn, path),
+        _ => stdout.clone(),
+    };
+    (stdout, stderr)
+}
+
+fn targets(bits: Option<u32>) -> OutputTargets {
+    bits.map(OutputTargets::from_bits_truncate)
+        .unwrap_or_default()
+}
+
+/// Where the lines captured from one of the service's output streams go.
+#[derive(Clone)]
+struct StreamSink {
+    targets: OutputTargets,
+    log:     SharedLog,
+}
+
+/// Where and how the lines captured from a service's stdout and stderr are written.
+#[derive(Clone)]
+struct OutputSink {
+    id:      String,
+    ident:   String,
+    pid:     u32,
+    format:  ServiceOutputFormat,
+    stdout:  StreamSink,
+    stderr:  StreamSink,
+    forward: Option<Forwarder>,
+}
+
+impl OutputSink {
+    fn new(spawn: &protocol::Spawn, pid: u32) -> Self {
+        let format = spawn.output_format
+                          .as_ref()
+                          .and_then(|f| f.parse().ok())
+                          .unwrap_or_default();
+        let (stdout_log, stderr_log) = open_logs(spawn);
+        let stdout = StreamSink { targets: targets(spawn.stdout_targets),
+                                  log:     stdout_log, };
+        let stderr = StreamSink { targets: targets(spawn.stderr_targets),
+                                  log:     stderr_log, };
+        let forward = if (stdout.targets | stderr.targets).contains(OutputTargets::FORWARD) {
+            open_forwarder(spawn, pid)
+        } else {
+            None
+        };
+        OutputSink { id: spawn.id.clone(),
+                     ident: spawn.pkg_ident.clone().unwrap_or_default(),
+                     pid,
+                     format,
+                     stdout,
+                     stderr,
+                     forward }
+    }
+
+    fn stream(&self, stream: ServiceStream) -> &StreamSink {
+        match stream {
+            ServiceStream::Stdout => &self.stdout,
+            ServiceStream::Stderr => &self.stderr,
+        }
+    }
+
+    /// The line as a JSON object, when JSON output was requested.
+    fn json(&self, stream: ServiceStream, line: &str) -> Option<String> {
+        match self.format {
+            ServiceOutputFormat::Json => Some(ServiceOutputLine::new(&self.ident,
+                                                                     &self.id,
+                                                                     stream.as_str(),
+                                                                     self.pid,
+                                                                     line).to_json()),
+            ServiceOutputFormat::Text => None,
+        }
+    }
+
+    /// Send a line read from `stream` to each of the stream's targets. The Supervisor publishes
+    /// events by following the log file, so that is written for either of `FILE` and `EVENTS`.
+    fn write(&self, stream: ServiceStream, line: &str) {
+        let targets = self.stream(stream).targets;
+        let json = self.json(stream, line);
+        if targets.intersects(OutputTargets::FILE | OutputTargets::EVENTS) {
+            self.write_log(stream, json.as_deref().unwrap_or(line));
+        }
+        if targets.contains(OutputTargets::FORWARD) {
+            let severity = match stream {
+                ServiceStream::Stdout => Severity::Info,
+                ServiceStream::Stderr => Severity::Error,
+            };
+            self.forward(severity, line);
+        }
+        if targets.contains(OutputTargets::CONSOLE) {
+            let result = match (stream, json) {
+                (ServiceStream::Stdout, Some(json)) => writeln!(io::stdout(), "{}", json),
+                (ServiceStream::Stderr, Some(json)) => writeln!(io::stderr(), "{}", json),
+                (ServiceStream::Stdout, None) => {
+                    StructuredOutput::succinct(&self.id, "O", output::get_format(), line).println()
+                }
+                (ServiceStream::Stderr, None) => {
+                    StructuredOutput::succinct(&self.id, "E", output::get_format(), line).eprintln()
                 }
+            };
+            if let Err(e) = result {
+                println!("printing output: '{}' to {} resulted in error: {}",
+                         line, stream, e);
             }
-            Err(e) => {
-                println!("reading output from to stdout resulted in error: {}", e);
-                break;
+        }
+    }
+
+    fn forward(&self, severity: Severity, line: &str) {
+        if let Some(ref forward) = self.forward {
+            match forward.tx.try_send((severity, line.to_string())) {
+                Ok(()) => forward.dropping.store(false, Ordering::Relaxed),
+                Err(TrySendError::Full(_)) => {
+                    if !forward.dropping.swap(true, Ordering::Relaxed) {
+                        println!("forwarding output of {} to {} is falling behind, dropping lines",
+                                 self.id, forward.target);
+                    }
+                }
+                Err(TrySendError::Disconnected(_)) => {}
+            }
+        }
+    }
+
+    fn write_log(&self, stream: ServiceStream, line: &str) {
+        if let Some(ref log) = self.stream(stream).log {
+            let mut log = log.lock().expect("Service log lock poisoned");
+            if let Err(e) = log.write_line(line) {
+                println!("writing output: '{}' to {} resulted in error: {}",
+                         line,
+                         log.path().display(),
+                         e);
             }
         }
     }
 }
 
-/// Consume standard error from a child process until EOF, then finish
-fn pipe_stderr<T>(err: T, id: &str)
+/// Consume one of a child process's output streams until EOF, then finish
+fn pipe_output<T>(reader: T, stream: ServiceStream, sink: &OutputSink)
     where T: Read
 {
-    for line in BufReader::new(err).lines_lossy() {
+    for line in BufReader::new(reader).lines_lossy() {
         match line {
-            Ok(line) => {
-                let so = StructuredOutput::succinct(&id, "E", output::get_format(), &line);
-                if let Err(e) = so.eprintln() {
-                    println!("printing output: '{}' to stderr resulted in error: {}",
-                             &line, e);
-                }
-            }
+            Ok(line) => sink.write(stream, &line),
             Err(e) => {
-                println!("reading output from to stderr resulted in error: {}", e);
+                println!("reading output from {} resulted in error: {}", stream, e);
                 break;
             }
         }
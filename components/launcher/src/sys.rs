@@ -5,3 +5,11 @@ pub mod service;
 #[cfg(windows)]
 #[path = "sys/windows/service.rs"]
 pub mod service;
+
+#[cfg(target_os = "linux")]
+#[path = "sys/linux/orphans.rs"]
+pub mod orphans;
+
+#[cfg(target_os = "linux")]
+#[path = "sys/linux/ports.rs"]
+pub mod ports;
@@ -0,0 +1,259 @@
+//! Adoption of the orphaned descendants of services.
+//!
+//! A service which double-forks leaves behind a grandchild whose parent has exited. Linux
+//! re-parents such a process to its closest ancestor marked as a child subreaper, which the
+//! Launcher makes itself, rather than to init. The Launcher then reaps it once it exits and
+//! terminates it along with the service it was started by, so it neither lingers as a zombie nor
+//! outlives its service.
+
+use crate::service::Service;
+use habitat_common::outputln;
+use habitat_core::os::{cgroup,
+                       process::{self,
+                                 signal,
+                                 Signal}};
+use std::{collections::HashMap,
+          fs,
+          io,
+          thread,
+          time::{Duration,
+                 Instant}};
+
+static LOGKEY: &str = "OR";
+
+/// How often the process table is scanned for descendants of services.
+const SCAN_INTERVAL: Duration = Duration::from_secs(1);
+/// How long orphans get to exit once they have been asked to terminate.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(8);
+/// How many ancestors of a process are considered when looking for its service.
+const MAX_DEPTH: usize = 64;
+
+/// Mark the Launcher as a child subreaper, so orphaned descendants of services are re-parented
+/// to it instead of to init.
+pub fn become_subreaper() -> io::Result<()> {
+    let res = unsafe { libc::prctl(libc::PR_SET_CHILD_SUBREAPER, 1, 0, 0, 0) };
+    if res == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+/// The fields of `/proc/<pid>/stat` used to find the service a process belongs to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Stat {
+    ppid:       u32,
+    pgrp:       u32,
+    session:    u32,
+    /// When the process started, in clock ticks since boot. Along with the PID, this identifies
+    /// a process even once its PID has been reused.
+    start_time: u64,
+}
+
+impl Stat {
+    fn read(pid: u32) -> Option<Stat> {
+        let content = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+        Self::parse(&content)
+    }
+
+    /// The command name in parentheses may itself contain spaces and parentheses, so the
+    /// remaining fields are found after the last closing parenthesis.
+    fn parse(content: &str) -> Option<Stat> {
+        let rest = &content[content.rfind(')')? + 1..];
+        let fields = rest.split_whitespace().collect::<Vec<_>>();
+        Some(Stat { ppid:       fields.get(1)?.parse().ok()?,
+                    pgrp:       fields.get(2)?.parse().ok()?,
+                    session:    fields.get(3)?.parse().ok()?,
+                    start_time: fields.get(19)?.parse().ok()?, })
+    }
+}
+
+/// Whether `pid` is still the process which started at `start_time`, rather than another one
+/// its PID was reused for.
+fn is_same_process(pid: u32, start_time: u64) -> bool {
+    Stat::read(pid).map_or(false, |stat| stat.start_time == start_time)
+}
+
+/// A descendant of a service.
+#[derive(Clone, Debug, PartialEq)]
+struct Descendant {
+    /// The `Spawn#id` of the service, e.g. "redis.default".
+    service:    String,
+    /// Whether the process was re-parented to the Launcher.
+    adopted:    bool,
+    start_time: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct Orphans {
+    descendants: HashMap<u32, Descendant>,
+    next_scan:   Option<Instant>,
+}
+
+impl Orphans {
+    /// Record the service each descendant of `services` belongs to, reporting those which were
+    /// re-parented to the Launcher since the last scan. This is a no-op until `SCAN_INTERVAL` has
+    /// passed since the last scan.
+    pub fn scan(&mut self, services: &HashMap<u32, Service>) {
+        let now = Instant::now();
+        if self.next_scan.map_or(false, |next| now < next) {
+            return;
+        }
+        self.next_scan = Some(now + SCAN_INTERVAL);
+
+        let stats = match all_stats() {
+            Ok(stats) => stats,
+            Err(err) => {
+                debug!("Unable to scan the process table for orphans: {}", err);
+                return;
+            }
+        };
+        let launcher = process::current_pid() as u32;
+        let mut descendants = HashMap::new();
+        for (&pid, stat) in &stats {
+            if services.contains_key(&pid) {
+                continue;
+            }
+            let service = match self.service_of(pid, stat, &stats, services) {
+                Some(service) => service,
+                None => continue,
+            };
+            let adopted = stat.ppid == launcher;
+            if adopted && !self.descendants.get(&pid).map_or(false, |d| d.adopted) {
+                outputln!(preamble service, "Adopted orphaned process {}", pid);
+            }
+            descendants.insert(pid,
+                               Descendant { service,
+                                            adopted,
+                                            start_time: stat.start_time });
+        }
+        self.descendants = descendants;
+    }
+
+    /// Find the service `pid` belongs to: the closest ancestor which is a service, or a known
+    /// descendant of one, or otherwise the service whose process group, session or cgroup it
+    /// is in.
+    fn service_of(&self,
+                  pid: u32,
+                  stat: &Stat,
+                  stats: &HashMap<u32, Stat>,
+                  services: &HashMap<u32, Service>)
+                  -> Option<String> {
+        let service_name = |pid: &u32| services.get(pid).map(|s| s.name().to_string());
+        let mut ancestor = stat.ppid;
+        for _ in 0..MAX_DEPTH {
+            if let Some(name) = service_name(&ancestor) {
+                return Some(name);
+            }
+            if let Some(descendant) = self.descendants.get(&ancestor) {
+                return Some(descendant.service.clone());
+            }
+            match stats.get(&ancestor) {
+                Some(stat) if ancestor > 1 => ancestor = stat.ppid,
+                _ => break,
+            }
+        }
+        if let Some(descendant) = self.descendants.get(&pid) {
+            return Some(descendant.service.clone());
+        }
+        let in_cgroup =
+            || cgroup::service_of(pid).filter(|id| services.values().any(|s| s.name() == id));
+        service_name(&stat.pgrp).or_else(|| service_name(&stat.session))
+                                .or_else(in_cgroup)
+    }
+
+    /// Forget a process the Launcher reaped, returning the service it belonged to.
+    pub fn reaped(&mut self, pid: u32) -> Option<String> {
+        self.descendants.remove(&pid).map(|d| d.service)
+    }
+
+    /// The orphaned processes of the service `id` the Launcher adopted.
+    pub fn adopted_by(&self, id: &str) -> Vec<u32> {
+        self.descendants
+            .iter()
+            .filter(|(_, d)| d.adopted && d.service == id)
+            .map(|(pid, _)| *pid)
+            .collect()
+    }
+
+    /// Terminate the remaining descendants of the service `id` after the service itself was
+    /// stopped, killing those which have not exited within `SHUTDOWN_TIMEOUT`. Each process is
+    /// checked to still be the one found by the last scan before it is signalled, so that a
+    /// process which reused the PID of an exited descendant is left alone.
+    pub fn terminate(&mut self, id: &str) {
+        let mut remaining = self.descendants
+                                .iter()
+                                .filter(|(_, d)| d.service == id)
+                                .map(|(pid, d)| (*pid, d.start_time))
+                                .filter(|(pid, start_time)| is_same_process(*pid, *start_time))
+                                .collect::<Vec<_>>();
+        self.descendants.retain(|_, d| d.service != id);
+        if remaining.is_empty() {
+            return;
+        }
+        outputln!(preamble id, "Stopping {} orphaned process(es)", remaining.len());
+        for (pid, _) in &remaining {
+            signal(*pid as process::Pid, Signal::TERM).ok();
+        }
+        let shutdown_start = Instant::now();
+        while !remaining.is_empty() && shutdown_start.elapsed() < SHUTDOWN_TIMEOUT {
+            remaining.retain(|(pid, start_time)| {
+                         !has_exited(*pid) && is_same_process(*pid, *start_time)
+                     });
+            thread::sleep(Duration::from_millis(50));
+        }
+        for (pid, start_time) in remaining {
+            if !is_same_process(pid, start_time) {
+                continue;
+            }
+            outputln!(preamble id, "Killing orphaned process {}", pid);
+            signal(pid as process::Pid, Signal::KILL).ok();
+            unsafe { libc::waitpid(pid as libc::pid_t, std::ptr::null_mut(), 0) };
+        }
+    }
+}
+
+/// Whether `pid` has exited, reaping it if it was re-parented to the Launcher.
+fn has_exited(pid: u32) -> bool {
+    match unsafe { libc::waitpid(pid as libc::pid_t, std::ptr::null_mut(), libc::WNOHANG) } {
+        0 => false,
+        res if res > 0 => true,
+        // Not a child of the Launcher, so it can't be reaped here
+        _ => !process::is_alive(pid as process::Pid),
+    }
+}
+
+fn all_stats() -> io::Result<HashMap<u32, Stat>> {
+    let mut stats = HashMap::new();
+    for entry in fs::read_dir("/proc")? {
+        let pid = match entry?.file_name()
+                              .to_str()
+                              .and_then(|name| name.parse().ok())
+        {
+            Some(pid) => pid,
+            None => continue,
+        };
+        // The process may have exited since the directory was listed.
+        if let Some(stat) = Stat::read(pid) {
+            stats.insert(pid, stat);
+        }
+    }
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stat_is_parsed_after_the_command_name() {
+        let content = "4242 (my (odd) cmd) S 4100 4242 4000 0 -1 4194560 120 0 0 0 3 1 0 0 20 0 1 \
+                       0 123456 10000 100";
+        assert_eq!(Stat::parse(content),
+                   Some(Stat { ppid:       4100,
+                               pgrp:       4242,
+                               session:    4000,
+                               start_time: 123456, }));
+        assert_eq!(Stat::parse("4242 (truncated"), None);
+    }
+}
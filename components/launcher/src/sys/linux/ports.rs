@@ -0,0 +1,114 @@
+//! Forwarding of host ports to services that run in a network namespace of their own.
+//!
+//! Such a namespace has nothing but a loopback interface, so the Launcher listens on each mapped
+//! host port itself. Every connection it accepts is relayed by a thread that has entered the
+//! service's namespace and connected to the service port on its loopback interface.
+
+use habitat_core::os::isolation::PortMapping;
+use std::{fs::File,
+          io::{self,
+               Write},
+          net::{Ipv4Addr,
+                Shutdown,
+                TcpListener,
+                TcpStream},
+          os::unix::io::AsRawFd,
+          sync::{atomic::{AtomicBool,
+                          Ordering},
+                 Arc},
+          thread,
+          time::Duration};
+
+/// How often a listener checks whether it should stop accepting connections.
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The forwarded ports of a service. They stop accepting connections once this is dropped;
+/// connections already accepted last until either end closes them.
+pub struct PortForwards {
+    stop: Arc<AtomicBool>,
+}
+
+impl Drop for PortForwards {
+    fn drop(&mut self) { self.stop.store(true, Ordering::Relaxed); }
+}
+
+/// Forward the host ports of `ports` to the service `id`, which runs as `pid` in a network
+/// namespace of its own. Either all ports are forwarded, or none.
+pub fn forward(id: &str, pid: u32, ports: &[PortMapping]) -> io::Result<PortForwards> {
+    let namespace = Arc::new(File::open(format!("/proc/{}/ns/net", pid))?);
+    let mut listeners = Vec::with_capacity(ports.len());
+    for mapping in ports {
+        let listener = bind(mapping.host)?;
+        listener.set_nonblocking(true)?;
+        listeners.push((listener, *mapping));
+    }
+    let stop = Arc::new(AtomicBool::new(false));
+    for (listener, mapping) in listeners {
+        let namespace = Arc::clone(&namespace);
+        let stop = Arc::clone(&stop);
+        thread::Builder::new().name(format!("{}-port-{}", id, mapping.host))
+                              .spawn(move || {
+                                  accept(&listener, &namespace, mapping.service, &stop)
+                              })?;
+    }
+    Ok(PortForwards { stop })
+}
+
+/// Listen on a host port. When a service is restarted, the listener of its previous process may
+/// take until its next check to close, so the port is retried for a little longer than that.
+fn bind(port: u16) -> io::Result<TcpListener> {
+    let mut attempts = 0;
+    loop {
+        match TcpListener::bind((Ipv4Addr::UNSPECIFIED, port)) {
+            Err(ref err) if err.kind() == io::ErrorKind::AddrInUse && attempts < 3 => {
+                attempts += 1;
+                thread::sleep(ACCEPT_INTERVAL);
+            }
+            result => return result,
+        }
+    }
+}
+
+fn accept(listener: &TcpListener, namespace: &Arc<File>, port: u16, stop: &AtomicBool) {
+    while !stop.load(Ordering::Relaxed) {
+        match listener.accept() {
+            Ok((client, _)) => {
+                let namespace = Arc::clone(namespace);
+                thread::spawn(move || {
+                    if let Err(err) = relay(client, &namespace, port) {
+                        debug!("Unable to relay a connection to port {}: {}", port, err);
+                    }
+                });
+            }
+            Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => {
+                thread::sleep(ACCEPT_INTERVAL)
+            }
+            Err(err) => {
+                debug!("Unable to accept a connection for port {}: {}", port, err);
+                thread::sleep(ACCEPT_INTERVAL)
+            }
+        }
+    }
+}
+
+/// Connect to `port` in the network namespace `namespace`, and copy the data between the
+/// connection and `client` in both directions until either end closes.
+fn relay(client: TcpStream, namespace: &File, port: u16) -> io::Result<()> {
+    client.set_nonblocking(false)?;
+    // Only the calling thread enters the namespace
+    if unsafe { libc::setns(namespace.as_raw_fd(), libc::CLONE_NEWNET) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let service = TcpStream::connect((Ipv4Addr::LOCALHOST, port))?;
+    let (mut client_reader, mut service_writer) = (client.try_clone()?, service.try_clone()?);
+    let upstream = thread::spawn(move || {
+        io::copy(&mut client_reader, &mut service_writer).ok();
+        service_writer.shutdown(Shutdown::Write).ok();
+    });
+    let (mut service_reader, mut client_writer) = (service, client);
+    io::copy(&mut service_reader, &mut client_writer)?;
+    client_writer.flush()?;
+    client_writer.shutdown(Shutdown::Write).ok();
+    upstream.join().ok();
+    Ok(())
+}
@@ -1,30 +1,52 @@
+#[cfg(target_os = "linux")]
+use crate::sys::ports::{self,
+                        PortForwards};
 use crate::{error::{Error,
                     Result},
             protocol::{self,
                        ShutdownMethod},
             service::Service};
 use habitat_core::os::{self,
+                       cgroup,
+                       isolation::{AllowedPath,
+                                   Isolation,
+                                   PortMapping},
                        process::{exec,
                                  signal,
-                                 Signal}};
+                                 validate_nice,
+                                 IoPriority,
+                                 ProcessSettings,
+                                 Signal,
+                                 Ulimit,
+                                 Umask},
+                       seccomp::SeccompProfile,
+                       socket_activation::ListenFd};
 use nix::unistd::{Gid,
                   Uid};
-use std::{io,
+use std::{convert::TryFrom,
+          io,
           ops::Neg,
+          path::PathBuf,
           process::{Child,
                     ExitStatus},
           time::{Duration,
                  Instant}};
 
-pub struct Process(Child);
+pub struct Process {
+    child: Child,
+    /// The host ports forwarded to a service in a network namespace of its own, for as long as
+    /// its process is around.
+    #[cfg(target_os = "linux")]
+    ports: Option<PortForwards>,
+}
 
 impl Process {
-    pub fn id(&self) -> u32 { self.0.id() }
+    pub fn id(&self) -> u32 { self.child.id() }
 
     /// Attempt to gracefully terminate a process and then forcefully kill it after
     /// 8 seconds if it has not terminated.
     pub fn kill(&mut self) -> ShutdownMethod {
-        let mut pid_to_kill = self.0.id() as i32;
+        let mut pid_to_kill = self.child.id() as i32;
         // check the group of the process being killed
         // if it is the root process of the process group
         // we send our signals to the entire process group
@@ -61,12 +83,13 @@ impl Process {
         }
     }
 
-    pub fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> { self.0.try_wait() }
+    pub fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> { self.child.try_wait() }
 
-    pub fn wait(&mut self) -> io::Result<ExitStatus> { self.0.wait() }
+    pub fn wait(&mut self) -> io::Result<ExitStatus> { self.child.wait() }
 }
 
-pub fn run(msg: protocol::Spawn) -> Result<Service> {
+/// Spawn the service `msg` asks for, handing it the sockets of `listen_fds` it names.
+pub fn run(msg: protocol::Spawn, listen_fds: &[ListenFd]) -> Result<Service> {
     debug!("launcher is spawning {}", msg.binary);
 
     // Favor explicitly set UID/GID over names when present
@@ -90,12 +113,187 @@ pub fn run(msg: protocol::Spawn) -> Result<Service> {
     };
     let gid = Gid::from_raw(group_id);
 
-    let mut cmd = exec::unix::hook_command(&msg.binary, &msg.env, Some((uid, gid)));
+    let cgroup = create_cgroup(&msg);
+    let isolation = isolation(&msg);
+    let mut cmd =
+        exec::unix::service_command(&msg.binary,
+                                    &msg.env,
+                                    Some((uid, gid)),
+                                    cgroup.as_deref(),
+                                    &isolation,
+                                    &process_settings(&msg),
+                                    &handed_over_fds(&msg, listen_fds)).map_err(Error::Spawn)?;
 
     let mut child = cmd.spawn().map_err(Error::Spawn)?;
     let stdout = child.stdout.take();
     let stderr = child.stderr.take();
-    let process = Process(child);
-    debug!(target: "pidfile_tracing", "Launcher spawned {} with PID = {}", msg.binary, process.id());
+    debug!(target: "pidfile_tracing", "Launcher spawned {} with PID = {}", msg.binary, child.id());
+    #[cfg(target_os = "linux")]
+    let process = Process { ports: forward_ports(&msg, &isolation, child.id()),
+                            child };
+    #[cfg(not(target_os = "linux"))]
+    let process = Process { child };
     Ok(Service::new(msg, process, stdout, stderr))
 }
+
+/// Create the cgroup of a service about to be spawned, applying any
+/// requested limits. The service joins it before it is exec'd, so
+/// neither it nor any process it forks escapes it. Failing to create
+/// it is not fatal; the service runs in the Launcher's cgroup.
+fn create_cgroup(msg: &protocol::Spawn) -> Option<PathBuf> {
+    let limits = cgroup::Limits { cpu_max:    msg.cpu_max.clone(),
+                                  memory_max: msg.memory_max.clone(),
+                                  io_weight:  msg.io_weight, };
+    if !cgroup::is_available() {
+        if !limits.is_empty() {
+            warn!("Resource limits for {} ignored; cgroup v2 is not available on this host",
+                  msg.id);
+        }
+        return None;
+    }
+    match cgroup::create(&msg.id, &limits) {
+        Ok(path) => Some(path),
+        Err(err) => {
+            warn!("Unable to create cgroup {} for {}: {}",
+                  cgroup::service_path(&msg.id).display(),
+                  msg.id,
+                  err);
+            None
+        }
+    }
+}
+
+/// The isolation requested for a service, without what this platform
+/// can't provide.
+fn isolation(msg: &protocol::Spawn) -> Isolation {
+    let ports = msg.ports
+                   .iter()
+                   .filter_map(|mapping| {
+                       mapping.parse::<PortMapping>()
+                              .map_err(|err| warn!("Ignoring port of {}: {}", msg.id, err))
+                              .ok()
+                   })
+                   .collect();
+    let seccomp_profile = match msg.seccomp_profile {
+        Some(ref profile) => {
+            match profile.parse::<SeccompProfile>() {
+                Ok(profile) => Some(profile),
+                Err(err) => {
+                    warn!("Ignoring seccomp profile of {}: {}", msg.id, err);
+                    None
+                }
+            }
+        }
+        None => None,
+    };
+    let allowed_paths =
+        msg.allowed_paths
+           .iter()
+           .filter_map(|path| {
+               path.parse::<AllowedPath>()
+                   .map_err(|err| warn!("Ignoring allowed path of {}: {}", msg.id, err))
+                   .ok()
+           })
+           .collect();
+    let isolation = Isolation { pid_namespace: msg.pid_namespace.unwrap_or(false),
+                                network_namespace: msg.network_namespace.unwrap_or(false),
+                                ports,
+                                seccomp_profile,
+                                filesystem_sandbox: msg.filesystem_sandbox.unwrap_or(false),
+                                allowed_paths };
+    if isolation.is_available() {
+        isolation
+    } else {
+        warn!("Isolation of {} ignored; namespaces, seccomp and filesystem sandboxes are only \
+               available on Linux",
+              msg.id);
+        Isolation::default()
+    }
+}
+
+/// How the process of a service is set up, without the settings that are invalid.
+fn process_settings(msg: &protocol::Spawn) -> ProcessSettings {
+    let umask = match msg.umask {
+        Some(mask) => {
+            match Umask::try_from(mask) {
+                Ok(umask) => Some(umask),
+                Err(err) => {
+                    warn!("Ignoring umask of {}: {}", msg.id, err);
+                    None
+                }
+            }
+        }
+        None => None,
+    };
+    let ulimits = msg.ulimits
+                     .iter()
+                     .filter_map(|ulimit| {
+                         ulimit.parse::<Ulimit>()
+                               .map_err(|err| warn!("Ignoring ulimit of {}: {}", msg.id, err))
+                               .ok()
+                     })
+                     .collect();
+    let nice = match msg.nice {
+        Some(nice) => {
+            match validate_nice(&nice.to_string()) {
+                Ok(()) => Some(nice),
+                Err(err) => {
+                    warn!("Ignoring niceness of {}: {}", msg.id, err);
+                    None
+                }
+            }
+        }
+        None => None,
+    };
+    let io_priority = match msg.io_priority {
+        Some(ref priority) => {
+            match priority.parse::<IoPriority>() {
+                Ok(io_priority) => Some(io_priority),
+                Err(err) => {
+                    warn!("Ignoring IO priority of {}: {}", msg.id, err);
+                    None
+                }
+            }
+        }
+        None => None,
+    };
+    ProcessSettings { umask,
+                      working_dir: msg.working_dir.as_ref().map(PathBuf::from),
+                      ulimits,
+                      nice,
+                      io_priority,
+                      listen_fds: msg.listen_fds.clone() }
+}
+
+/// The sockets of `listen_fds` named by the service, in the order it names them. A name may stand
+/// for several sockets.
+fn handed_over_fds(msg: &protocol::Spawn, listen_fds: &[ListenFd]) -> Vec<ListenFd> {
+    let mut fds = Vec::new();
+    for name in &msg.listen_fds {
+        let named = listen_fds.iter().filter(|fd| &fd.name == name);
+        let count = fds.len();
+        fds.extend(named.cloned());
+        if fds.len() == count {
+            warn!("No socket named {} was passed to the Launcher; {} does not get it",
+                  name, msg.id);
+        }
+    }
+    fds
+}
+
+/// Forward the mapped host ports of a service in a network namespace of
+/// its own. Failing to do so is not fatal; the service runs, but can't
+/// be reached.
+#[cfg(target_os = "linux")]
+fn forward_ports(msg: &protocol::Spawn, isolation: &Isolation, pid: u32) -> Option<PortForwards> {
+    if !isolation.network_namespace || isolation.ports.is_empty() {
+        return None;
+    }
+    match ports::forward(&msg.id, pid, &isolation.ports) {
+        Ok(forwards) => Some(forwards),
+        Err(err) => {
+            warn!("Unable to forward the ports of {}: {}", msg.id, err);
+            None
+        }
+    }
+}
@@ -3,10 +3,12 @@ use crate::{error::{Error,
             protocol::{self,
                        ShutdownMethod},
             service::Service};
-use core::{os::{process::{handle_from_pid,
+use core::{os::{cgroup,
+                process::{handle_from_pid,
                           windows_child::{Child,
                                           ExitStatus,
-                                          Handle}},
+                                          Handle,
+                                          UserProfile}},
                 users::get_current_username},
            util};
 use std::{collections::HashMap,
@@ -14,24 +16,38 @@ use std::{collections::HashMap,
           io,
           iter::FromIterator,
           mem,
+          ptr,
           time::{Duration,
                  Instant}};
 use winapi::{shared::{minwindef::{DWORD,
                                   LPDWORD,
+                                  LPVOID,
                                   MAX_PATH},
                       winerror::{ERROR_FILE_NOT_FOUND,
                                  WAIT_TIMEOUT}},
              um::{handleapi::{self,
                               INVALID_HANDLE_VALUE},
+                  jobapi2,
                   processthreadsapi,
                   synchapi,
+                  sysinfoapi::{self,
+                               SYSTEM_INFO},
                   tlhelp32::{self,
                              LPPROCESSENTRY32W,
                              PROCESSENTRY32W,
                              TH32CS_SNAPPROCESS},
                   winbase::{INFINITE,
                             WAIT_OBJECT_0},
-                  wincon}};
+                  wincon,
+                  winnt::{JobObjectCpuRateControlInformation,
+                          JobObjectExtendedLimitInformation,
+                          JOBOBJECTINFOCLASS,
+                          JOBOBJECT_CPU_RATE_CONTROL_INFORMATION,
+                          JOBOBJECT_EXTENDED_LIMIT_INFORMATION,
+                          JOB_OBJECT_CPU_RATE_CONTROL_ENABLE,
+                          JOB_OBJECT_CPU_RATE_CONTROL_HARD_CAP,
+                          JOB_OBJECT_LIMIT_JOB_MEMORY,
+                          JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE}}};
 
 const PROCESS_ACTIVE: u32 = 259;
 type ProcessTable = HashMap<DWORD, Vec<DWORD>>;
@@ -39,12 +55,19 @@ type ProcessTable = HashMap<DWORD, Vec<DWORD>>;
 pub struct Process {
     handle:      Handle,
     last_status: Option<ExitStatus>,
+    /// The job object the process runs in. Dropping it terminates any
+    /// process still left in the job.
+    job:         Option<Job>,
+    /// The profile loaded for the process, unloaded once it has exited.
+    profile:     Option<UserProfile>,
 }
 
 impl Process {
-    fn new(handle: Handle) -> Self {
+    fn new(handle: Handle, job: Option<Job>, profile: Option<UserProfile>) -> Self {
         Process { handle,
-                  last_status: None }
+                  last_status: None,
+                  job,
+                  profile }
     }
 
     pub fn id(&self) -> u32 { unsafe { processthreadsapi::GetProcessId(self.handle.raw()) as u32 } }
@@ -66,8 +89,13 @@ impl Process {
         let start_time = Instant::now();
         loop {
             if ret == 0 || start_time.elapsed() > shutdown_timeout {
-                let proc_table = build_proc_table();
-                terminate_process_descendants(&proc_table, self.id());
+                match self.job {
+                    Some(ref job) => job.terminate(),
+                    None => {
+                        let proc_table = build_proc_table();
+                        terminate_process_descendants(&proc_table, self.id());
+                    }
+                }
                 return ShutdownMethod::Killed;
             }
 
@@ -85,6 +113,7 @@ impl Process {
             }
             let mut status = 0;
             cvt(processthreadsapi::GetExitCodeProcess(self.handle.raw(), &mut status))?;
+            self.profile.take();
             Ok(ExitStatus::from(status))
         }
     }
@@ -98,6 +127,7 @@ impl Process {
             }
             let mut status = 0;
             cvt(processthreadsapi::GetExitCodeProcess(self.handle.raw(), &mut status))?;
+            self.profile.take();
             Ok(Some(ExitStatus::from(status)))
         }
     }
@@ -109,6 +139,7 @@ impl Process {
         match exit_code(&self.handle) {
             Some(PROCESS_ACTIVE) => None,
             Some(code) => {
+                self.profile.take();
                 self.last_status = Some(ExitStatus::from(code));
                 self.last_status
             }
@@ -138,6 +169,29 @@ pub fn run(msg: protocol::Spawn) -> Result<Service> {
 
 fn spawn_pwsh(ps_binary_name: &str, msg: protocol::Spawn) -> Result<Service> {
     debug!("launcher is spawning {}", msg.binary);
+    if msg.pid_namespace == Some(true)
+       || msg.network_namespace == Some(true)
+       || msg.seccomp_profile.is_some()
+       || msg.filesystem_sandbox == Some(true)
+    {
+        warn!("Isolation of {} ignored; namespaces, seccomp and filesystem sandboxes are only \
+               available on Linux",
+              msg.id);
+    }
+    if msg.umask.is_some()
+       || msg.working_dir.is_some()
+       || !msg.ulimits.is_empty()
+       || msg.nice.is_some()
+       || msg.io_priority.is_some()
+    {
+        warn!("Umask, working directory, ulimits and scheduling priorities of {} ignored; they \
+               are not available on Windows",
+              msg.id);
+    }
+    if !msg.listen_fds.is_empty() {
+        warn!("Sockets of {} ignored; socket activation is not available on Windows",
+              msg.id);
+    }
     let ps_cmd = format!("iex $(gc {} | out-string)", &msg.binary);
     let password = msg.svc_password.clone();
 
@@ -177,13 +231,91 @@ fn spawn_pwsh(ps_binary_name: &str, msg: protocol::Spawn) -> Result<Service> {
                        password)
     {
         Ok(child) => {
-            let process = Process::new(child.handle);
+            let job = assign_job(&msg, &child.handle);
+            let process = Process::new(child.handle, job, child.profile);
             Ok(Service::new(msg, process, child.stdout, child.stderr))
         }
         Err(_) => Err(Error::Spawn(io::Error::last_os_error())),
     }
 }
 
+/// A job object configured to kill all of its processes once its last
+/// handle is closed, so that children of a service cannot outlive it;
+/// the counterpart to the process groups used on Linux.
+struct Job(Handle);
+
+impl Job {
+    fn new(limits: &cgroup::Limits) -> io::Result<Self> {
+        let job = unsafe { jobapi2::CreateJobObjectW(ptr::null_mut(), ptr::null()) };
+        if job.is_null() {
+            return Err(io::Error::last_os_error());
+        }
+        let job = Job(Handle::new(job));
+
+        let mut info: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = unsafe { mem::zeroed() };
+        info.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+        if let Some(bytes) = limits.memory_max_bytes() {
+            info.BasicLimitInformation.LimitFlags |= JOB_OBJECT_LIMIT_JOB_MEMORY;
+            info.JobMemoryLimit = bytes as usize;
+        }
+        job.set_information(JobObjectExtendedLimitInformation, &mut info)?;
+
+        if let Some(cpus) = limits.cpu_fraction() {
+            // The rate is expressed in 1/100ths of a percent of the
+            // cycles of *all* processors.
+            let rate = (cpus / num_processors() as f64 * 10_000.0).round() as u32;
+            let mut info: JOBOBJECT_CPU_RATE_CONTROL_INFORMATION = unsafe { mem::zeroed() };
+            info.ControlFlags =
+                JOB_OBJECT_CPU_RATE_CONTROL_ENABLE | JOB_OBJECT_CPU_RATE_CONTROL_HARD_CAP;
+            unsafe { *info.u.CpuRate_mut() = rate.max(1).min(10_000) };
+            job.set_information(JobObjectCpuRateControlInformation, &mut info)?;
+        }
+        Ok(job)
+    }
+
+    fn set_information<T>(&self, class: JOBOBJECTINFOCLASS, info: &mut T) -> io::Result<()> {
+        cvt(unsafe {
+            jobapi2::SetInformationJobObject(self.0.raw(),
+                                             class,
+                                             info as *mut T as LPVOID,
+                                             mem::size_of::<T>() as DWORD)
+        }).map(|_| ())
+    }
+
+    fn assign(&self, process: &Handle) -> io::Result<()> {
+        cvt(unsafe { jobapi2::AssignProcessToJobObject(self.0.raw(), process.raw()) }).map(|_| ())
+    }
+
+    fn terminate(&self) {
+        if unsafe { jobapi2::TerminateJobObject(self.0.raw(), 1) } == 0 {
+            error!("Failed to call TerminateJobObject: {}",
+                   io::Error::last_os_error());
+        }
+    }
+}
+
+/// Run a freshly spawned service in its own job object, applying any
+/// requested limits. Failing to do so is not fatal; the service keeps
+/// running without the job's guarantees.
+fn assign_job(msg: &protocol::Spawn, process: &Handle) -> Option<Job> {
+    let limits = cgroup::Limits { cpu_max:    msg.cpu_max.clone(),
+                                  memory_max: msg.memory_max.clone(),
+                                  io_weight:  msg.io_weight, };
+    match Job::new(&limits).and_then(|job| job.assign(process).map(|_| job)) {
+        Ok(job) => Some(job),
+        Err(err) => {
+            warn!("Unable to run {} in a job object: {}", msg.id, err);
+            None
+        }
+    }
+}
+
+fn num_processors() -> u32 {
+    let mut info: SYSTEM_INFO = unsafe { mem::zeroed() };
+    unsafe { sysinfoapi::GetSystemInfo(&mut info) };
+    info.dwNumberOfProcessors.max(1)
+}
+
 fn build_proc_table() -> ProcessTable {
     let processes_snap_handle =
         unsafe { tlhelp32::CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0) };
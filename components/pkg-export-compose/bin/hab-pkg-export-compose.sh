@@ -0,0 +1,271 @@
+#!/bin/bash
+#
+# # Usage
+#
+# ```
+# $ hab-pkg-export-compose [OPTIONS] <PKG_IDENT>...
+# ```
+#
+# # Synopsis
+#
+# Create a Docker Compose file that runs several Habitat packages from the
+# container images exported with `hab pkg export container`, with the services
+# peered into one ring and bound to each other.
+
+# defaults for the Compose file
+: "${GROUP:="default"}"
+: "${OUTPUT:="docker-compose.yml"}"
+PKGS=()
+BINDS=()
+IMAGES=()
+
+# Fail if there are any unset variables and whenever a command returns a
+# non-zero exit code.
+set -eu
+
+# If the variable `$DEBUG` is set, then print the shell commands as we execute.
+if [ -n "${DEBUG:-}" ]; then
+  set -x
+  export DEBUG
+fi
+
+# ## Help
+
+# **Internal** Prints help
+print_help() {
+  echo -- "$program $version
+
+$author
+
+Habitat Package Export Compose - Create a Docker Compose file from a set of Habitat packages
+
+USAGE:
+  $program [FLAGS] [OPTIONS] <PKG_IDENT>...
+
+FLAGS:
+    --help                      Prints help information
+
+OPTIONS:
+    --bind=SERVICE:BIND:PROVIDER
+                                Bind the BIND of SERVICE to PROVIDER, where both
+                                are the names of packages being exported
+                                (ex: national-parks:database:mongodb; may be repeated)
+    --image=SERVICE=IMAGE       Container image of SERVICE
+                                (<origin>/<name>:latest is default; may be repeated)
+    --group=GROUP               Service group of the services (default is default)
+    --output=FILE               File to write (docker-compose.yml is default)
+
+ARGS:
+    <PKG_IDENT>...              Habitat package identifiers (ex: acme/redis)
+"
+}
+
+# **Internal** Exit the program with an error message and a status code.
+#
+# ```sh
+# exit_with "Something bad went down" 55
+# ```
+exit_with() {
+  if [ "${HAB_NOCOLORING:-}" = "true" ]; then
+    printf -- "ERROR: %s\n" "$1"
+  else
+    case "${TERM:-}" in
+      *term | xterm-* | rxvt | screen | screen-*)
+        printf -- "\033[1;31mERROR: \033[1;37m%s\033[0m\n" "$1"
+        ;;
+      *)
+        printf -- "ERROR: %s\n" "$1"
+        ;;
+    esac
+  fi
+  exit "$2"
+}
+
+# parse the CLI flags and options
+parse_options() {
+  for i in "$@"
+  do
+    case $i in
+      --help)
+        print_help
+        exit
+        ;;
+      --bind=*)
+        BINDS+=("${i#*=}")
+        ;;
+      --image=*)
+        IMAGES+=("${i#*=}")
+        ;;
+      --group=*)
+        GROUP="${i#*=}"
+        ;;
+      --output=*)
+        OUTPUT="${i#*=}"
+        ;;
+      -*)
+        print_help
+        exit_with "Unknown option: $i" 1
+        ;;
+      *)
+        PKGS+=("${i}")
+        ;;
+    esac
+  done
+  if [ "${#PKGS[@]}" -eq 0 ]; then
+    print_help
+    exit_with "You must specify one or more Habitat packages to export." 1
+  fi
+}
+
+# Install the packages if needed and read the name, origin and exposed ports of
+# each, keyed by the name that also names its Compose service
+declare -A SVC_ORIGIN SVC_EXPOSES
+SVCS=()
+load_packages() {
+  local pkg pkg_path pkg_origin pkg_name
+  for pkg in "${PKGS[@]}"; do
+    if ! pkg_path="$(hab pkg path "$pkg" 2> /dev/null)"; then
+      hab pkg install "$pkg" >&2
+      pkg_path="$(hab pkg path "$pkg")"
+    fi
+    # The IDENT file has no trailing newline, so `read` reports end of file
+    IFS=/ read -r pkg_origin pkg_name _ < "$pkg_path/IDENT" || true
+    if [ -n "${SVC_ORIGIN[$pkg_name]+set}" ]; then
+      exit_with "More than one package is named $pkg_name; each service needs its own name." 1
+    fi
+    SVCS+=("$pkg_name")
+    SVC_ORIGIN[$pkg_name]="$pkg_origin"
+    SVC_EXPOSES[$pkg_name]=""
+    if [ -f "$pkg_path/EXPOSES" ]; then
+      SVC_EXPOSES[$pkg_name]="$(cat "$pkg_path/EXPOSES")"
+    fi
+  done
+}
+
+# **Internal** Exit unless `$1` names one of the services being exported.
+ensure_service() {
+  if [ -z "${SVC_ORIGIN[$1]+set}" ]; then
+    exit_with "$1 in '$2' is not one of the packages being exported." 1
+  fi
+}
+
+# Exit unless every bind and image names services being exported
+validate_options() {
+  local bind service name provider image
+  for bind in "${BINDS[@]+"${BINDS[@]}"}"; do
+    IFS=: read -r service name provider <<< "$bind"
+    if [ -z "$service" ] || [ -z "$name" ] || [ -z "$provider" ]; then
+      exit_with "Binds look like SERVICE:BIND:PROVIDER, not '$bind'." 1
+    fi
+    ensure_service "$service" "--bind=$bind"
+    ensure_service "$provider" "--bind=$bind"
+  done
+  for image in "${IMAGES[@]+"${IMAGES[@]}"}"; do
+    ensure_service "${image%%=*}" "--image=$image"
+  done
+}
+
+# The image of a service, as given with `--image` or as tagged by the container
+# exporter
+image_for() {
+  local image
+  for image in "${IMAGES[@]+"${IMAGES[@]}"}"; do
+    if [ "${image%%=*}" == "$1" ]; then
+      echo "${image#*=}"
+      return
+    fi
+  done
+  echo "${SVC_ORIGIN[$1]}/$1:latest"
+}
+
+# The binds of a service in the form the Supervisor's `HAB_BIND` environment
+# variable takes them, separated by commas
+binds_for() {
+  local bind service name provider
+  local binds=()
+  for bind in "${BINDS[@]+"${BINDS[@]}"}"; do
+    IFS=: read -r service name provider <<< "$bind"
+    if [ "$service" == "$1" ]; then
+      binds+=("$name:$provider.$GROUP")
+    fi
+  done
+  local IFS=,
+  echo "${binds[*]+"${binds[*]}"}"
+}
+
+# The services that a service binds to, one per line
+providers_for() {
+  local bind service provider
+  for bind in "${BINDS[@]+"${BINDS[@]}"}"; do
+    IFS=: read -r service _ provider <<< "$bind"
+    if [ "$service" == "$1" ]; then
+      echo "$provider"
+    fi
+  done | sort -u
+}
+
+# https://docs.docker.com/compose/compose-file/compose-file-v3/
+#
+# Every Supervisor peers with the first service's, so the services share one
+# ring. The binds of each service reach its Supervisor through `HAB_BIND`, and
+# the services it binds to are started before it.
+create_compose_file() {
+  local svc binds providers provider port
+  local peer="${SVCS[0]}"
+  echo "version: '3.7'"
+  echo "services:"
+  for svc in "${SVCS[@]}"; do
+    echo "  $svc:"
+    echo "    image: \"$(image_for "$svc")\""
+    echo "    hostname: \"$svc\""
+    if [ "$svc" == "$peer" ]; then
+      echo "    command: [\"--group\", \"$GROUP\"]"
+    else
+      echo "    command: [\"--group\", \"$GROUP\", \"--peer\", \"$peer\"]"
+    fi
+    binds="$(binds_for "$svc")"
+    if [ -n "$binds" ]; then
+      echo "    environment:"
+      echo "      HAB_BIND: \"$binds\""
+    fi
+    providers="$(providers_for "$svc")"
+    if [ -n "$providers" ]; then
+      echo "    depends_on:"
+      for provider in $providers; do
+        echo "      - \"$provider\""
+      done
+    fi
+    if [ -n "${SVC_EXPOSES[$svc]}" ]; then
+      echo "    ports:"
+      for port in ${SVC_EXPOSES[$svc]}; do
+        echo "      - \"$port:$port\""
+      done
+    fi
+  done
+}
+
+# The root of the filesystem. If the program is running on a separate
+# filesystem or chroot environment, this environment variable may need to be
+# set.
+: "${FS_ROOT:=}"
+# The root path of the Habitat file system. If the `$HAB_ROOT_PATH` environment
+# variable is set, this value is overridden, otherwise it is set to its default
+: "${HAB_ROOT_PATH:=$FS_ROOT/hab}"
+
+# The current version of Habitat Studio
+version='@version@'
+# The author of this program
+author='@author@'
+# The short version of the program name which is used in logging output
+program=$(basename "$0")
+
+parse_options "$@"
+load_packages
+validate_options
+
+compose_file="$(create_compose_file)"
+echo "$compose_file" > "$OUTPUT"
+
+echo "Wrote $OUTPUT"
+echo "Start the services with:"
+echo "  docker-compose -f $OUTPUT up"
@@ -14,10 +14,11 @@ use clap::{self,
 #[cfg(unix)]
 use failure::SyncFailure;
 use hab::license;
-use habitat_common::{command::package::install::{InstallHookMode,
-                                                 InstallMode,
-                                                 InstallSource,
-                                                 LocalPackageUsage},
+use habitat_common::{command::package::{install::{InstallHookMode,
+                                                  InstallMode,
+                                                  InstallSource,
+                                                  LocalPackageUsage},
+                                        relocate::RelocateMode},
                      ui::{Status,
                           UIWriter,
                           UI},
@@ -56,6 +57,8 @@ use tempfile::TempDir;
 const DEFAULT_BASE_IMAGE: &str = "scratch";
 #[cfg(windows)]
 const DEFAULT_BASE_IMAGE: &str = "mcr.microsoft.com/windows/servercore";
+#[cfg(windows)]
+const NANOSERVER_BASE_IMAGE: &str = "mcr.microsoft.com/windows/nanoserver";
 
 const DEFAULT_HAB_IDENT: &str = "core/hab";
 const DEFAULT_LAUNCHER_IDENT: &str = "core/hab-launcher";
@@ -65,17 +68,26 @@ const DEFAULT_USER_AND_GROUP_ID: u32 = 42;
 const DEFAULT_HAB_UID: u32 = 84;
 const DEFAULT_HAB_GID: u32 = 84;
 
-fn default_base_image() -> Result<String> {
-    #[cfg(unix)]
-    {
-        Ok(DEFAULT_BASE_IMAGE.to_string())
+#[cfg(unix)]
+fn default_base_image(_m: &ArgMatches<'_>) -> Result<String> { Ok(DEFAULT_BASE_IMAGE.to_string()) }
+
+#[cfg(windows)]
+fn default_base_image(m: &ArgMatches<'_>) -> Result<String> {
+    let tag = docker::default_base_tag_for_host()?;
+    match m.value_of("BASE_IMAGE_FLAVOR") {
+        Some("nanoserver") => Ok(format!("{}:{}", NANOSERVER_BASE_IMAGE, nanoserver_tag(tag))),
+        _ => Ok(format!("{}:{}", DEFAULT_BASE_IMAGE, tag)),
     }
+}
 
-    #[cfg(windows)]
-    {
-        Ok(format!("{}:{}",
-                   DEFAULT_BASE_IMAGE,
-                   docker::default_base_tag_for_host()?))
+/// Nano Server images are not tagged with the LTSC names used for Server Core, so map the
+/// Server Core tag for the host to the equivalent Nano Server tag.
+#[cfg(windows)]
+fn nanoserver_tag(servercore_tag: &str) -> &str {
+    match servercore_tag {
+        "ltsc2019" => "1809",
+        "ltsc2016" => "sac2016",
+        other => other,
     }
 }
 
@@ -149,7 +161,7 @@ impl TryFrom<&ArgMatches<'_>> for BuildSpec {
                            m.value_of("BASE_IMAGE")
                             .map(str::to_string)
                             .unwrap_or_else(|| {
-                                default_base_image().expect("No base image supported")
+                                default_base_image(m).expect("No base image supported")
                             }),
                        multi_layer:        m.is_present("MULTI_LAYER"), })
     }
@@ -377,7 +389,8 @@ impl BuildSpec {
                                                      // TODO (CM): pass through and enable
                                                      // ignore-local mode
                                                      &LocalPackageUsage::default(),
-                                                     InstallHookMode::Ignore).await?;
+                                                     InstallHookMode::Ignore,
+                                                     RelocateMode::Preserve).await?;
 
         // TODO (CM): Ideally, the typing of PackageInstall would be
         // such that we'd automatically get a
@@ -440,7 +453,7 @@ pub struct BuildRootContext {
     /// The `bin` path which will be used for all program symlinking.
     bin_path:        PathBuf,
     /// A string representation of the build root's `PATH` environment variable value (i.e. a
-    /// colon-delimited `PATH` string).
+    /// colon-delimited `PATH` string, or a semicolon-delimited one on Windows).
     env_path:        String,
     /// The channel name which was used to install all user-provided Habitat service and library
     /// packages.
@@ -511,10 +524,26 @@ impl BuildRootContext {
 
         let bin_path = util::bin_path();
 
+        #[cfg(unix)]
+        let env_path = bin_path.to_string_lossy().into_owned();
+        // There is no `/bin` of symlinked binaries in a Windows image, so the binary paths of the
+        // provided packages are put on the `PATH` instead.
+        #[cfg(windows)]
+        let env_path = {
+            let mut paths = Vec::new();
+            for ident in idents.iter().map(PkgIdentType::ident) {
+                paths.extend(PackageInstall::load(ident, Some(&rootfs))?.paths()?);
+            }
+            paths.iter()
+                 .map(|p| p.to_string_lossy().into_owned())
+                 .collect::<Vec<_>>()
+                 .join(";")
+        };
+
         let context = BuildRootContext { idents,
                                          environment,
                                          bin_path: bin_path.into(),
-                                         env_path: bin_path.to_string_lossy().into_owned(),
+                                         env_path,
                                          channel: spec.channel.clone(),
                                          rootfs,
                                          base_image: spec.base_image.clone(),
@@ -727,6 +756,9 @@ impl BuildRootContext {
     /// Returns the base image used in the Dockerfile
     pub fn base_image(&self) -> &str { self.base_image.as_str() }
 
+    /// Returns true if the base image is a Windows Nano Server image.
+    pub fn is_nanoserver(&self) -> bool { self.base_image.contains("nanoserver") }
+
     pub fn multi_layer(&self) -> bool { self.multi_layer }
 
     fn validate(&self) -> Result<()> {
@@ -816,6 +848,14 @@ mod test {
                     multi_layer:        false, }
     }
 
+    #[test]
+    #[cfg(windows)]
+    fn nanoserver_tag_maps_ltsc_names() {
+        assert_eq!("1809", nanoserver_tag("ltsc2019"));
+        assert_eq!("sac2016", nanoserver_tag("ltsc2016"));
+        assert_eq!("1903", nanoserver_tag("1903"));
+    }
+
     struct FakePkg {
         ident:     String,
         bins:      Vec<String>,
@@ -297,7 +297,16 @@ impl<'a, 'b> Cli<'a, 'b> {
                                                        .help("Base image of the final exported \
                                                               image --base-image \
                                                               mcr.microsoft.com/windows/\
-                                                              servercore:ltsc2019"));
+                                                              servercore:ltsc2019"))
+                      .arg(Arg::with_name("BASE_IMAGE_FLAVOR").value_name("BASE_IMAGE_FLAVOR")
+                                                              .long("base-image-flavor")
+                                                              .possible_values(&["servercore",
+                                                                                 "nanoserver"])
+                                                              .conflicts_with("BASE_IMAGE")
+                                                              .help("Windows base image to use \
+                                                                     when --base-image is not \
+                                                                     given; the tag matches the \
+                                                                     host [default: servercore]"));
 
         Cli { app }
     }
@@ -228,6 +228,7 @@ impl BuildContext {
         let ctx = self.0.ctx();
         let json = json!({
             "base_image": ctx.base_image(),
+            "nanoserver": ctx.is_nanoserver(),
             "rootfs": ctx.rootfs().file_name().expect("file_name exists")
                 .to_string_lossy()
                 .as_ref(),
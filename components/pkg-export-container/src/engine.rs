@@ -8,7 +8,9 @@
 use crate::error::Result;
 use clap::{Arg,
            ArgMatches};
-use habitat_core::fs::find_command;
+use habitat_core::{fs::find_command,
+                   package::{target,
+                             PackageTarget}};
 use std::{convert::TryFrom,
           path::{Path,
                  PathBuf},
@@ -205,6 +207,18 @@ pub trait Engine {
     }
 }
 
+/// The platform images are built for, if it needs to be given explicitly.
+///
+/// Only aarch64 images declare their platform, so that engines too old to support `--platform`
+/// continue to work for x86_64 images.
+fn build_platform() -> Option<&'static str> {
+    if PackageTarget::active_target() == target::AARCH64_LINUX {
+        Some("linux/arm64")
+    } else {
+        None
+    }
+}
+
 /// General helper function for actually executing all these commands.
 ///
 /// Not part of the trait because nobody need to be calling this from
@@ -112,6 +112,9 @@ impl Engine for BuildahEngine {
         cmd.arg("--signature-policy");
         cmd.arg(&self.policy);
 
+        if let Some(platform) = super::build_platform() {
+            cmd.arg("--platform").arg(platform);
+        }
         if let Some(mem) = memory {
             cmd.arg("--memory").arg(mem);
         }
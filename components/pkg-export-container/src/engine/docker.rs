@@ -43,7 +43,7 @@ impl Engine for DockerEngine {
         cmd
     }
 
-    /// `docker build --force-rm --memory MEMORY [--tag TAG] .`
+    /// `docker build --force-rm [--platform PLATFORM] --memory MEMORY [--tag TAG] .`
     fn build_command(&self,
                      build_context: &Path,
                      tags: &[String],
@@ -54,6 +54,9 @@ impl Engine for DockerEngine {
         cmd.arg("build");
         cmd.arg("--force-rm");
 
+        if let Some(platform) = super::build_platform() {
+            cmd.arg("--platform").arg(platform);
+        }
         if let Some(mem) = memory {
             cmd.arg("--memory").arg(mem);
         }
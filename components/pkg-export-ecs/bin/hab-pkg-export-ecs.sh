@@ -0,0 +1,283 @@
+#!/bin/bash
+#
+# # Usage
+#
+# ```
+# $ hab-pkg-export-ecs [OPTIONS] <PKG_IDENT>
+# ```
+#
+# # Synopsis
+#
+# Create an Amazon ECS task definition that runs a Habitat package from a
+# container image exported with `hab pkg export container`.
+
+# defaults for the task definition
+: "${CPU:="256"}"
+: "${MEMORY:="512"}"
+: "${GROUP:="default"}"
+: "${NETWORK_MODE:="awsvpc"}"
+: "${LOG_DRIVER:="awslogs"}"
+: "${REGION:="${AWS_REGION:-${AWS_DEFAULT_REGION:-}}"}"
+: "${OUTPUT_DIR:="."}"
+: "${PKG:="unknown"}"
+IMAGE=""
+FAMILY=""
+LOG_GROUP=""
+EXECUTION_ROLE_ARN=""
+LOG_OPTS=()
+
+# The port the Supervisor's HTTP gateway listens on inside the container
+GATEWAY_PORT=9631
+
+# Fail if there are any unset variables and whenever a command returns a
+# non-zero exit code.
+set -eu
+
+# If the variable `$DEBUG` is set, then print the shell commands as we execute.
+if [ -n "${DEBUG:-}" ]; then
+  set -x
+  export DEBUG
+fi
+
+# ## Help
+
+# **Internal** Prints help
+print_help() {
+  echo -- "$program $version
+
+$author
+
+Habitat Package Export ECS - Create an Amazon ECS task definition from a Habitat package
+
+USAGE:
+  $program [FLAGS] [OPTIONS] <PKG_IDENT>
+
+FLAGS:
+    --help                    Prints help information
+
+OPTIONS:
+    --image=IMAGE             Container image exported from the package
+                              (<origin>/<name>:<version>-<release> is default)
+    --family=NAME             Family of the task definition (the package name is default)
+    --group=GROUP             Service group of the service (default is default)
+    --cpu=N                   CPU units for the task (256 is default)
+    --memory=N                Memory for the task (MiB, 512 is default)
+    --network-mode=MODE       Docker networking mode of the task (awsvpc is default)
+    --execution-role-arn=ARN  IAM role that ECS pulls the image and sends logs with
+    --log-driver=DRIVER       Log driver of the container (awslogs is default)
+    --log-group=GROUP         CloudWatch Logs group for the awslogs driver
+                              (/hab/<origin>/<name> is default)
+    --region=REGION           AWS region for the awslogs driver
+                              (\$AWS_REGION or \$AWS_DEFAULT_REGION is default)
+    --log-opt=KEY=VALUE       Option for the log driver (may be repeated)
+    --output-dir=DIR          Directory to write the definitions to (. is default)
+
+ARGS:
+    <PKG_IDENT>               Habitat package identifier (ex: acme/redis)
+"
+}
+
+# **Internal** Exit the program with an error message and a status code.
+#
+# ```sh
+# exit_with "Something bad went down" 55
+# ```
+exit_with() {
+  if [ "${HAB_NOCOLORING:-}" = "true" ]; then
+    printf -- "ERROR: %s\n" "$1"
+  else
+    case "${TERM:-}" in
+      *term | xterm-* | rxvt | screen | screen-*)
+        printf -- "\033[1;31mERROR: \033[1;37m%s\033[0m\n" "$1"
+        ;;
+      *)
+        printf -- "ERROR: %s\n" "$1"
+        ;;
+    esac
+  fi
+  exit "$2"
+}
+
+# parse the CLI flags and options
+parse_options() {
+  for i in "$@"
+  do
+    case $i in
+      --help)
+        print_help
+        exit
+        ;;
+      --image=*)
+        IMAGE="${i#*=}"
+        ;;
+      --family=*)
+        FAMILY="${i#*=}"
+        ;;
+      --group=*)
+        GROUP="${i#*=}"
+        ;;
+      --cpu=*)
+        CPU="${i#*=}"
+        ;;
+      --memory=*)
+        MEMORY="${i#*=}"
+        ;;
+      --network-mode=*)
+        NETWORK_MODE="${i#*=}"
+        ;;
+      --execution-role-arn=*)
+        EXECUTION_ROLE_ARN="${i#*=}"
+        ;;
+      --log-driver=*)
+        LOG_DRIVER="${i#*=}"
+        ;;
+      --log-group=*)
+        LOG_GROUP="${i#*=}"
+        ;;
+      --region=*)
+        REGION="${i#*=}"
+        ;;
+      --log-opt=*)
+        LOG_OPTS+=("${i#*=}")
+        ;;
+      --output-dir=*)
+        OUTPUT_DIR="${i#*=}"
+        ;;
+      -*)
+        print_help
+        exit_with "Unknown option: $i" 1
+        ;;
+      *)
+        PKG=${i}
+        ;;
+    esac
+  done
+  if [ "$PKG" == "unknown" ]; then
+    print_help
+    exit_with "You must specify a Habitat package to export." 1
+  fi
+  if [ "$LOG_DRIVER" == "awslogs" ] && [ -z "$REGION" ]; then
+    exit_with "The awslogs log driver needs a region; set --region or \$AWS_REGION." 1
+  fi
+}
+
+# Install the package if needed and read the metadata the task definition is
+# built from
+load_package() {
+  local pkg_path
+  if ! pkg_path="$(hab pkg path "$PKG" 2> /dev/null)"; then
+    hab pkg install "$PKG" >&2
+    pkg_path="$(hab pkg path "$PKG")"
+  fi
+
+  # The IDENT file has no trailing newline, so `read` reports end of file
+  IFS=/ read -r pkg_origin pkg_name pkg_version pkg_release < "$pkg_path/IDENT" || true
+  pkg_exposes=""
+  if [ -f "$pkg_path/EXPOSES" ]; then
+    pkg_exposes="$(cat "$pkg_path/EXPOSES")"
+  fi
+
+  : "${IMAGE:=$pkg_origin/$pkg_name:$pkg_version-$pkg_release}"
+  : "${FAMILY:=$pkg_name}"
+  : "${LOG_GROUP:=/hab/$pkg_origin/$pkg_name}"
+}
+
+# The options of the container's log driver. The awslogs driver is pointed at
+# the log group and region, and creates the group if it is missing.
+log_options() {
+  local opts="{}"
+  if [ "$LOG_DRIVER" == "awslogs" ]; then
+    opts="$(jq -n \
+      --arg group "$LOG_GROUP" \
+      --arg region "$REGION" \
+      --arg prefix "$pkg_name" \
+      '{"awslogs-group": $group,
+        "awslogs-region": $region,
+        "awslogs-stream-prefix": $prefix,
+        "awslogs-create-group": "true"}')"
+  fi
+  for opt in "${LOG_OPTS[@]+"${LOG_OPTS[@]}"}"; do
+    opts="$(jq --arg key "${opt%%=*}" --arg value "${opt#*=}" '. + {($key): $value}' <<< "$opts")"
+  done
+  echo "$opts"
+}
+
+# https://docs.aws.amazon.com/AmazonECS/latest/developerguide/task_definition_parameters.html#container_definitions
+#
+# The health check asks the Supervisor's HTTP gateway for the result of the
+# service's health check hook using the busybox that exported images carry. The
+# gateway answers with an error status when the service is critical or unknown.
+create_container_definition() {
+  jq -n \
+    --arg name "$pkg_name" \
+    --arg image "$IMAGE" \
+    --arg group "$GROUP" \
+    --arg exposes "$pkg_exposes" \
+    --arg health "wget -q -O /dev/null http://localhost:$GATEWAY_PORT/services/$pkg_name/$GROUP/health || exit 1" \
+    --arg driver "$LOG_DRIVER" \
+    --argjson log_options "$(log_options)" \
+    '{
+      name: $name,
+      image: $image,
+      essential: true,
+      command: ["--group", $group],
+      portMappings: [$exposes | splits(" +") | select(length > 0)
+                     | {containerPort: tonumber, protocol: "tcp"}],
+      healthCheck: {
+        command: ["CMD-SHELL", $health],
+        interval: 30,
+        timeout: 5,
+        retries: 3,
+        startPeriod: 60
+      },
+      logConfiguration: {logDriver: $driver, options: $log_options}
+    }'
+}
+
+# https://docs.aws.amazon.com/AmazonECS/latest/developerguide/task_definition_parameters.html
+create_task_definition() {
+  jq -n \
+    --arg family "$FAMILY" \
+    --arg network_mode "$NETWORK_MODE" \
+    --arg cpu "$CPU" \
+    --arg memory "$MEMORY" \
+    --arg execution_role_arn "$EXECUTION_ROLE_ARN" \
+    --argjson container "$1" \
+    '{
+      family: $family,
+      networkMode: $network_mode,
+      requiresCompatibilities: ["EC2", "FARGATE"],
+      cpu: $cpu,
+      memory: $memory,
+      containerDefinitions: [$container]
+    }
+    + if $execution_role_arn == "" then {} else {executionRoleArn: $execution_role_arn} end'
+}
+
+# The root of the filesystem. If the program is running on a separate
+# filesystem or chroot environment, this environment variable may need to be
+# set.
+: "${FS_ROOT:=}"
+# The root path of the Habitat file system. If the `$HAB_ROOT_PATH` environment
+# variable is set, this value is overridden, otherwise it is set to its default
+: "${HAB_ROOT_PATH:=$FS_ROOT/hab}"
+
+# The current version of Habitat Studio
+version='@version@'
+# The author of this program
+author='@author@'
+# The short version of the program name which is used in logging output
+program=$(basename "$0")
+
+parse_options "$@"
+load_package
+
+mkdir -p "$OUTPUT_DIR"
+container_definition="$(create_container_definition)"
+echo "$container_definition" > "$OUTPUT_DIR/$pkg_name-container-definition.json"
+create_task_definition "$container_definition" > "$OUTPUT_DIR/$pkg_name-task-definition.json"
+
+echo "Wrote $OUTPUT_DIR/$pkg_name-container-definition.json"
+echo "Wrote $OUTPUT_DIR/$pkg_name-task-definition.json"
+echo "Register the task definition with:"
+echo "  aws ecs register-task-definition --cli-input-json file://$OUTPUT_DIR/$pkg_name-task-definition.json"
@@ -0,0 +1,214 @@
+//! Creation of the signed package artifact and the build report.
+use crate::{error::Result,
+            hcore::crypto::{artifact::{self,
+                                       Compression},
+                            keys::SecretOriginSigningKey,
+                            provenance::{self,
+                                         ConfigSource,
+                                         DigestSet},
+                            Blake2bHash}};
+use sha2::{Digest,
+           Sha256};
+use std::{fs::{self,
+               File},
+          io::{self,
+               Write},
+          path::{Path,
+                 PathBuf},
+          process::Command};
+use tar::{Builder,
+          Header,
+          HeaderMode};
+use walkdir::WalkDir;
+use xz2::write::XzEncoder;
+
+/// Archive `prefix` into a tarball compressed with `compression` and sign it to produce
+/// `artifact`. With a `source_date_epoch` the artifact is reproducible: entries are sorted by
+/// name, ownership is dropped and all modification times are set to that timestamp.
+pub fn generate(prefix: &Path,
+                artifact: &Path,
+                key: &SecretOriginSigningKey,
+                compression: Compression,
+                compression_level: u32,
+                source_date_epoch: Option<u64>)
+                -> Result<()> {
+    let dir = artifact.parent().unwrap_or_else(|| Path::new("."));
+    fs::create_dir_all(dir)?;
+    let file_name = artifact.file_name()
+                            .map(|n| n.to_string_lossy().into_owned())
+                            .unwrap_or_default();
+    let extension = match compression {
+        Compression::Xz => "xz",
+        Compression::Zstd => "zst",
+    };
+    let tarball_path =
+        dir.join(format!(".{}.tar.{}", file_name.trim_end_matches(".hart"), extension));
+    if artifact.exists() {
+        fs::remove_file(artifact)?;
+    }
+
+    archive(prefix,
+            &tarball_path,
+            compression,
+            compression_level,
+            source_date_epoch)?;
+
+    artifact::sign_compressed(&tarball_path, artifact, key, compression)?;
+    fs::remove_file(&tarball_path)?;
+    Ok(())
+}
+
+fn archive(prefix: &Path,
+           dst: &Path,
+           compression: Compression,
+           compression_level: u32,
+           source_date_epoch: Option<u64>)
+           -> Result<()> {
+    let file = File::create(dst)?;
+    match compression {
+        Compression::Xz => {
+            let mut tar = Builder::new(XzEncoder::new(file, compression_level));
+            append_prefix(&mut tar, prefix, source_date_epoch)?;
+            tar.into_inner()?.finish()?;
+        }
+        Compression::Zstd => {
+            let encoder = zstd::stream::write::Encoder::new(file, compression_level as i32)?;
+            let mut tar = Builder::new(encoder);
+            append_prefix(&mut tar, prefix, source_date_epoch)?;
+            tar.into_inner()?.finish()?;
+        }
+    }
+    Ok(())
+}
+
+fn append_prefix<W: Write>(tar: &mut Builder<W>,
+                           prefix: &Path,
+                           source_date_epoch: Option<u64>)
+                           -> Result<()> {
+    tar.follow_symlinks(false);
+    // Entries are relative to the root directory, as `tar` would store them.
+    let name = prefix.strip_prefix("/").unwrap_or(prefix);
+    match source_date_epoch {
+        Some(mtime) => append_reproducible(tar, name, prefix, mtime)?,
+        None => tar.append_dir_all(name, prefix)?,
+    }
+    Ok(())
+}
+
+fn append_reproducible<W: Write>(tar: &mut Builder<W>,
+                                 name: &Path,
+                                 prefix: &Path,
+                                 mtime: u64)
+                                 -> Result<()> {
+    for entry in WalkDir::new(prefix).follow_links(false)
+                                     .sort_by(|a, b| a.file_name().cmp(b.file_name()))
+    {
+        let entry = entry?;
+        let path = name.join(entry.path().strip_prefix(prefix)?);
+        let metadata = entry.path().symlink_metadata()?;
+        let mut header = Header::new_gnu();
+        header.set_metadata_in_mode(&metadata, HeaderMode::Deterministic);
+        header.set_mtime(mtime);
+        if metadata.file_type().is_symlink() {
+            header.set_link_name(fs::read_link(entry.path())?)?;
+            tar.append_data(&mut header, &path, io::empty())?;
+        } else if metadata.is_file() {
+            tar.append_data(&mut header, &path, File::open(entry.path())?)?;
+        } else {
+            tar.append_data(&mut header, &path, io::empty())?;
+        }
+    }
+    Ok(())
+}
+
+/// Attest to the provenance of `artifact` as built by `builder_id`, recording the revision of
+/// the git checkout holding the Plan, if it is in one, as the source of the build.
+pub fn attest(artifact: &Path,
+              plan_context: &Path,
+              builder_id: &str,
+              key: &SecretOriginSigningKey)
+              -> Result<PathBuf> {
+    let config_source = match git(plan_context, &["rev-parse", "HEAD"]) {
+        Some(revision) => {
+            let mut digest = DigestSet::new();
+            digest.insert("sha1".to_string(), revision);
+            let uri = git(plan_context, &["config", "--get", "remote.origin.url"]);
+            Some(ConfigSource { uri,
+                                digest,
+                                entry_point: None })
+        }
+        None => None,
+    };
+    Ok(provenance::attest(artifact,
+                          builder_id,
+                          config_source,
+                          key)?)
+}
+
+/// The trimmed output of a successful git command run in `dir`.
+fn git(dir: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git").arg("-C")
+                                    .arg(dir)
+                                    .args(args)
+                                    .output()
+                                    .ok()?;
+    if output.status.success() {
+        Some(String::from_utf8_lossy(&output.stdout).trim().to_string()).filter(|s| !s.is_empty())
+    } else {
+        None
+    }
+}
+
+pub fn sha256sum(path: &Path) -> Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+pub fn blake2bsum(path: &Path) -> Result<String> { Ok(Blake2bHash::from_file(path)?.to_string()) }
+
+/// Set the owner of `path` to the owner of `reference`. Like `hab-plan-build` this is only
+/// best effort, a failure does not fail the build.
+#[cfg(unix)]
+pub fn chown_like(path: &Path, reference: &Path) {
+    use std::os::unix::fs::MetadataExt;
+
+    if let Ok(metadata) = fs::metadata(reference) {
+        let _ = Command::new("chown").arg(format!("{}:{}", metadata.uid(), metadata.gid()))
+                                     .arg(path)
+                                     .status();
+    }
+}
+
+#[cfg(not(unix))]
+pub fn chown_like(_path: &Path, _reference: &Path) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn populate(prefix: &Path, names: &[&str]) {
+        fs::create_dir_all(prefix.join("bin")).unwrap();
+        for name in names {
+            fs::write(prefix.join("bin").join(name), name).unwrap();
+        }
+        fs::write(prefix.join("IDENT"), "core/app/1.0.0/20200101000000\n").unwrap();
+    }
+
+    #[test]
+    fn reproducible_archives_are_identical() {
+        let tmp = TempDir::new().unwrap();
+        let prefix = tmp.path().join("pkgs/core/app/1.0.0/20200101000000");
+        let first = tmp.path().join("first.tar.xz");
+        let second = tmp.path().join("second.tar.xz");
+
+        populate(&prefix, &["zeta", "alpha", "mid"]);
+        archive(&prefix, &first, Compression::Xz, 6, Some(1_577_836_800)).unwrap();
+        fs::remove_dir_all(&prefix).unwrap();
+        populate(&prefix, &["mid", "alpha", "zeta"]);
+        archive(&prefix, &second, Compression::Xz, 6, Some(1_577_836_800)).unwrap();
+
+        assert_eq!(blake2bsum(&first).unwrap(), blake2bsum(&second).unwrap());
+    }
+}
@@ -0,0 +1,774 @@
+//! The main flow of a build, mirroring `hab-plan-build`.
+use crate::{artifact,
+            build_line,
+            deps::{Dependencies,
+                   Resolver},
+            error::{Error,
+                    Result},
+            hcore::{crypto::{self,
+                             artifact::Compression,
+                             keys::{KeyCache,
+                                    SecretOriginSigningKey},
+                             provenance},
+                    env as henv,
+                    fs::find_command,
+                    origin::Origin,
+                    package::{PackageIdent,
+                              PackageInstall,
+                              PackageTarget}},
+            metadata::{BuildFlags,
+                       Renderer},
+            phase::Phase,
+            plan::{self,
+                   Plan,
+                   PLAN_VARS,
+                   VERSION_UNSET},
+            shell::{quote,
+                    Shell,
+                    Vars}};
+use std::{env,
+          fs,
+          path::{Path,
+                 PathBuf},
+          str::FromStr,
+          time::Instant};
+use walkdir::WalkDir;
+
+/// The directory containing the `public.bash`, `shared.bash` and `environment.bash` libraries
+/// of `hab-plan-build`.
+pub const LIB_DIR_ENVVAR: &str = "HAB_PLAN_BUILD_LIB_DIR";
+/// The number of threads used to hash package files, defaulting to the number of CPUs.
+pub const JOBS_ENVVAR: &str = "HAB_PLAN_BUILD_JOBS";
+/// When set, the build is reproducible: the release and every timestamp in the artifact are
+/// derived from this number of seconds since the Unix epoch.
+pub const SOURCE_DATE_EPOCH_ENVVAR: &str = "SOURCE_DATE_EPOCH";
+
+const PLAN_BUILD_IDENT: &str = "core/hab-plan-build";
+const LIBRARIES: &[&str] = &["public.bash", "shared.bash", "environment.bash"];
+
+/// Variables set by the plan-build library which are needed by the native build.
+const BUILD_VARS: &[&str] = &["HAB_PKG_PATH",
+                              "HAB_CACHE_SRC_PATH",
+                              "HAB_CACHE_ARTIFACT_PATH",
+                              "HAB_CACHE_KEY_PATH",
+                              "HAB_BLDR_URL",
+                              "HAB_BLDR_CHANNEL",
+                              "HAB_BIN",
+                              "HAB_HART_COMPRESSION",
+                              "HAB_HART_COMPRESSION_LEVEL",
+                              "HAB_PROVENANCE",
+                              "HAB_PROVENANCE_BUILDER_ID",
+                              "INITIAL_PATH"];
+
+/// Variables which are derived from the Plan after it is loaded and which a Plan may update
+/// by calling `update_pkg_version`.
+const DERIVED_VARS: &[&str] = &["CACHE_PATH", "SRC_PATH", "pkg_output_path", "pkg_artifact"];
+
+const BUILD_FLAG_VARS: &[&str] = &["CFLAGS", "CPPFLAGS", "CXXFLAGS", "LDFLAGS", "LD_RUN_PATH"];
+
+/// Build the Plan found in `context`.
+pub fn build(context: &Path) -> Result<()> {
+    let started = Instant::now();
+    let target = match henv::var("BUILD_PKG_TARGET") {
+        Ok(target) => PackageTarget::from_str(&target)?,
+        Err(_) => PackageTarget::active_target(),
+    };
+    let initial_pwd = env::current_dir()?;
+    let src_path = initial_pwd.join(context);
+    let plan_path = plan::find(&src_path, target)?;
+    let plan_context = plan_path.parent()
+                                .expect("plan file has a parent directory")
+                                .to_path_buf();
+    let lib_dir = lib_dir()?;
+    let source_date_epoch = match henv::var(SOURCE_DATE_EPOCH_ENVVAR) {
+        Ok(epoch) if !epoch.is_empty() => {
+            Some(epoch.parse()
+                      .map_err(|_| Error::InvalidSourceDateEpoch(epoch.clone()))?)
+        }
+        _ => None,
+    };
+    let this_program = env::current_exe()?;
+
+    let mut shell = Shell::start(&plan_context,
+                                 &lib_dir,
+                                 &[("THIS_PROGRAM", this_program.to_string_lossy().into_owned()),
+                                   ("PLAN_CONTEXT", plan_context.to_string_lossy().into_owned()),
+                                   ("SRC_PATH", src_path.to_string_lossy().into_owned()),
+                                   ("INITIAL_PWD", initial_pwd.to_string_lossy().into_owned()),
+                                   ("pkg_target", target.to_string())])?;
+    build_line("hab-plan-build",
+               &format!("Loading {}", plan_path.display()));
+    shell.run("Loading the Plan",
+              &format!("source {}", quote(&plan_path.to_string_lossy())))
+         .map_err(|e| {
+             match e.downcast::<Error>() {
+                 Ok(Error::StepFailed(_, code)) => Error::PlanLoadFailed(plan_path.clone(), code),
+                 Ok(e) => e,
+                 Err(e) => Error::ShellProtocol(e.to_string()),
+             }
+         })?;
+    build_line("hab-plan-build", "Plan loaded");
+
+    let vars = shell.vars(BUILD_VARS)?;
+    let compression = match vars.scalar("HAB_HART_COMPRESSION") {
+        Some(compression) if !compression.is_empty() => compression.parse::<Compression>()?,
+        _ => Compression::Xz,
+    };
+    let provenance_builder_id = if vars.scalar("HAB_PROVENANCE") == Some("true") {
+        vars.scalar("HAB_PROVENANCE_BUILDER_ID")
+            .filter(|id| !id.is_empty())
+            .map(str::to_string)
+    } else {
+        None
+    };
+    let mut build = Build { shell,
+                            target,
+                            initial_pwd,
+                            plan_context,
+                            pkg_path: PathBuf::from(vars.string("HAB_PKG_PATH")),
+                            cache_src_path: PathBuf::from(vars.string("HAB_CACHE_SRC_PATH")),
+                            key_cache_path: PathBuf::from(vars.string("HAB_CACHE_KEY_PATH")),
+                            bldr_url: vars.string("HAB_BLDR_URL"),
+                            channel: vars.string("HAB_BLDR_CHANNEL"),
+                            hab_bin: Some(vars.string("HAB_BIN")).filter(|h| !h.is_empty())
+                                                                 .map(PathBuf::from),
+                            compression,
+                            compression_level: vars.scalar("HAB_HART_COMPRESSION_LEVEL")
+                                                   .and_then(|l| l.parse().ok())
+                                                   .unwrap_or(6),
+                            source_date_epoch,
+                            provenance_builder_id,
+                            initial_path: vars.string("INITIAL_PATH"),
+                            plan: Plan::default(),
+                            deps: Dependencies::default(),
+                            derived: Vars::default() };
+
+    let result = build.run();
+    let pkg_name = if build.plan.name.is_empty() {
+        "unknown".to_string()
+    } else {
+        build.plan.name.clone()
+    };
+    let elapsed = started.elapsed().as_secs();
+    build_line(&pkg_name,
+               &format!("Build time: {}m{}s", elapsed / 60, elapsed % 60));
+    match result {
+        Ok(()) => Ok(()),
+        Err(err) => {
+            build_line(&pkg_name, &format!("Exiting on error: {}", err));
+            build.final_callback("do_after_failure", &plan_path, &lib_dir);
+            Err(err)
+        }
+    }
+}
+
+/// Find the `hab-plan-build` Bash libraries: from `$HAB_PLAN_BUILD_LIB_DIR`, next to this
+/// program, or in the latest installed `core/hab-plan-build` package.
+fn lib_dir() -> Result<PathBuf> {
+    let exe_dir = env::current_exe()?.parent()
+                                     .map(Path::to_path_buf)
+                                     .unwrap_or_default();
+    let dir = if let Ok(dir) = henv::var(LIB_DIR_ENVVAR) {
+        PathBuf::from(dir)
+    } else if exe_dir.join(LIBRARIES[0]).is_file() {
+        exe_dir
+    } else {
+        PackageIdent::from_str(PLAN_BUILD_IDENT).and_then(|ident| {
+                                                    PackageInstall::load(&ident, None)
+                                                })
+                                                .map(|install| install.installed_path().join("bin"))
+                                                .unwrap_or(exe_dir)
+    };
+    for lib in LIBRARIES {
+        if !dir.join(lib).is_file() {
+            return Err(Error::LibraryNotFound(dir.display().to_string(), lib).into());
+        }
+    }
+    Ok(dir)
+}
+
+struct Build {
+    shell:                 Shell,
+    target:                PackageTarget,
+    initial_pwd:           PathBuf,
+    plan_context:          PathBuf,
+    pkg_path:              PathBuf,
+    cache_src_path:        PathBuf,
+    key_cache_path:        PathBuf,
+    bldr_url:              String,
+    channel:               String,
+    hab_bin:               Option<PathBuf>,
+    compression:           Compression,
+    compression_level:     u32,
+    source_date_epoch:     Option<u64>,
+    /// The builder to attest to the provenance of the artifact as, if one is attested to.
+    provenance_builder_id: Option<String>,
+    initial_path:          String,
+    plan:                  Plan,
+    deps:                  Dependencies,
+    derived:               Vars,
+}
+
+impl Build {
+    fn run(&mut self) -> Result<()> {
+        self.normalize_plan()?;
+        self.check_system_commands()?;
+
+        build_line("hab-plan-build", "hab-plan-build setup");
+        self.run_phase(Phase::Begin)?;
+        self.write_pre_build_file()?;
+        let key = self.origin_key()?;
+
+        self.resolve_dependencies()?;
+        self.shell
+            .run("Setting up the environment", "do_setup_environment_wrapper")?;
+        self.set_build_path()?;
+        fs::create_dir_all(&self.cache_src_path)?;
+
+        for phase in Phase::SOURCE {
+            self.run_phase(*phase)?;
+        }
+        self.reload_plan()?;
+
+        self.set_build_environment()?;
+        self.fix_libtool()?;
+        if self.plan.version == VERSION_UNSET {
+            return Err(Error::VersionUnset.into());
+        }
+        self.shell.set("_verify_vars", "true")?;
+        self.verify_hook_line_endings()?;
+
+        for phase in Phase::BUILD {
+            self.run_phase(*phase)?;
+        }
+        self.reload_plan()?;
+
+        self.build_metadata(&key)?;
+        let artifact_path = PathBuf::from(self.derived.string("pkg_artifact"));
+        build_line(&self.plan.name, "Generating package artifact");
+        if self.source_date_epoch.is_some() {
+            build_line(&self.plan.name,
+                       "SOURCE_DATE_EPOCH set: generating a reproducible artifact");
+        }
+        artifact::generate(&self.plan.prefix,
+                           &artifact_path,
+                           &key,
+                           self.compression,
+                           self.compression_level,
+                           self.source_date_epoch)?;
+        if let Some(builder_id) = &self.provenance_builder_id {
+            build_line(&self.plan.name, "Generating provenance attestation");
+            artifact::attest(&artifact_path, &self.plan_context, builder_id, &key)?;
+        }
+        let (sha256sum, blake2bsum) = self.prepare_build_outputs(&artifact_path)?;
+
+        build_line(&self.plan.name, "hab-plan-build cleanup");
+        self.run_phase(Phase::End)?;
+        self.final_callback_in_shell("do_after_success");
+
+        let output_path = PathBuf::from(self.derived.string("pkg_output_path"));
+        let artifact_name = artifact_path.file_name()
+                                         .map(|n| n.to_string_lossy().into_owned())
+                                         .unwrap_or_default();
+        let name = self.plan.name.clone();
+        build_line(&name, "");
+        build_line(&name,
+                   &format!("Source Path: {}", self.derived.string("SRC_PATH")));
+        build_line(&name,
+                   &format!("Installed Path: {}", self.plan.prefix.display()));
+        build_line(&name,
+                   &format!("Artifact: {}", output_path.join(&artifact_name).display()));
+        build_line(&name,
+                   &format!("Build Report: {}",
+                            output_path.join("last_build.env").display()));
+        build_line(&name, &format!("SHA256 Checksum: {}", sha256sum));
+        build_line(&name, &format!("Blake2b Checksum: {}", blake2bsum));
+        build_line(&name, "");
+        build_line(&name, "I love it when a plan.sh comes together.");
+        build_line(&name, "");
+        Ok(())
+    }
+
+    fn run_phase(&mut self, phase: Phase) -> Result<()> {
+        debug!("Running {}", phase);
+        self.shell.run(&phase.to_string(), &phase.entrypoint())
+    }
+
+    /// Validate the loaded Plan and compute the variables derived from it, as `hab-plan-build`
+    /// does once the Plan has been sourced.
+    fn normalize_plan(&mut self) -> Result<()> {
+        build_line("hab-plan-build", "Validating plan metadata");
+        if let Ok(origin) = henv::var("HAB_ORIGIN") {
+            if !origin.is_empty() {
+                self.shell.set("pkg_origin", &origin)?;
+            }
+        }
+        let vars = self.shell.vars(PLAN_VARS)?;
+        let plan = Plan::from_vars(&self.plan_context, &vars)?;
+
+        let mut script = Vec::new();
+        if plan.svc_run.contains("@__pkg_name__@") {
+            script.push(format!("pkg_svc_run={}",
+                                quote(&plan.svc_run.replace("@__pkg_name__@", &plan.name))));
+        }
+        let mut version = plan.version.clone();
+        if version.is_empty() {
+            if self.shell.has_function("pkg_version")? {
+                version = VERSION_UNSET.to_string();
+                script.push(format!("pkg_version={}", VERSION_UNSET));
+            } else {
+                return Err(Error::MissingPlanValue("pkg_version").into());
+            }
+        }
+        if !plan.source.is_empty() && vars.get("pkg_filename").is_none() {
+            let filename = plan.source.rsplit('/').next().unwrap_or_default();
+            script.push(format!("pkg_filename={}", quote(filename)));
+        }
+        let dirname = match vars.scalar("pkg_dirname") {
+            Some(dirname) => dirname.to_string(),
+            None => {
+                let dirname = format!("{}-{}", plan.name, version);
+                script.push(format!("pkg_dirname={}", quote(&dirname)));
+                script.push("_pkg_dirname_initially_unset=true".to_string());
+                dirname
+            }
+        };
+        if vars.get("pkg_prefix").is_none() {
+            let prefix = self.pkg_path
+                             .join(&plan.origin)
+                             .join(&plan.name)
+                             .join(&version)
+                             .join(&plan.release);
+            script.push(format!("pkg_prefix={}", quote(&prefix.to_string_lossy())));
+        }
+        let cache_path = self.cache_src_path.join(&dirname);
+        script.push(format!("CACHE_PATH={}", quote(&cache_path.to_string_lossy())));
+        if !plan.source.is_empty() {
+            script.push("SRC_PATH=\"$CACHE_PATH\"".to_string());
+        }
+        let output_path = match henv::var("HAB_OUTPUT_PATH") {
+            Ok(path) if !path.is_empty() => PathBuf::from(path),
+            _ => self.initial_pwd.join("results"),
+        };
+        script.push(format!("pkg_output_path={}", quote(&output_path.to_string_lossy())));
+        script.push("pkg_svc_path=\"$HAB_ROOT_PATH/svc/$pkg_name\"".to_string());
+        for (var, dir) in &[("pkg_svc_data_path", "data"),
+                            ("pkg_svc_files_path", "files"),
+                            ("pkg_svc_var_path", "var"),
+                            ("pkg_svc_config_path", "config"),
+                            ("pkg_svc_config_install_path", "config_install"),
+                            ("pkg_svc_static_path", "static")]
+        {
+            script.push(format!("{}=\"$pkg_svc_path/{}\"", var, dir));
+        }
+        script.push("pkg_artifact=\"$HAB_CACHE_ARTIFACT_PATH/\
+                     ${pkg_origin}-${pkg_name}-${pkg_version}-${pkg_release}-${pkg_target}.\
+                     ${_artifact_ext}\""
+                                        .to_string());
+        self.shell
+            .run("Setting the Plan variables", &script.join("\n"))?;
+        self.reload_plan()
+    }
+
+    /// Read the Plan back from the shell as the build phases may have changed it.
+    fn reload_plan(&mut self) -> Result<()> {
+        let vars = self.shell.vars(PLAN_VARS)?;
+        self.plan = Plan::from_vars(&self.plan_context, &vars)?;
+        self.derived = self.shell.vars(DERIVED_VARS)?;
+        Ok(())
+    }
+
+    fn check_system_commands(&mut self) -> Result<()> {
+        if henv::var("NO_INSTALL_DEPS").is_ok() {
+            build_line("hab-plan-build",
+                       "NO_INSTALL_DEPS set: no package dependencies will be installed");
+        } else if self.hab_bin.is_none() {
+            return Err(Error::MissingSystemCommand("hab").into());
+        }
+        if !self.plan.source.is_empty() {
+            if find_command("wget").is_none() {
+                return Err(Error::MissingSystemCommand("wget").into());
+            }
+            if find_command("sha256sum").is_none() && find_command("gsha256sum").is_none() {
+                return Err(Error::MissingSystemCommand("sha256sum").into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Write `pre_build.env` so that workers have metadata about a build even when it fails.
+    fn write_pre_build_file(&mut self) -> Result<()> {
+        build_line(&self.plan.name, "Writing pre_build file");
+        let output_path = PathBuf::from(self.derived.string("pkg_output_path"));
+        fs::create_dir_all(&output_path)?;
+        artifact::chown_like(&output_path, &self.plan.path());
+        let pre_build = output_path.join("pre_build.env");
+        fs::write(&pre_build, self.build_report(&[]))?;
+        artifact::chown_like(&pre_build, &self.plan.path());
+        Ok(())
+    }
+
+    fn build_report(&self, extra: &[(&str, String)]) -> String {
+        let plan = &self.plan;
+        let mut report = format!("pkg_origin={}\npkg_name={}\npkg_version={}\npkg_release={}\n",
+                                 plan.origin, plan.name, plan.version, plan.release);
+        if !extra.is_empty() {
+            report.push_str(&format!("pkg_target={}\n", self.target));
+        }
+        report.push_str(&format!("pkg_ident={}\n", plan.ident()));
+        for (key, value) in extra {
+            report.push_str(&format!("{}={}\n", key, value));
+        }
+        report
+    }
+
+    fn origin_key(&self) -> Result<SecretOriginSigningKey> {
+        crypto::init()?;
+        let origin = Origin::from_str(&self.plan.origin)?;
+        KeyCache::new(&self.key_cache_path).latest_secret_origin_signing_key(&origin)
+                                           .map_err(|_| {
+                                               Error::OriginKeyNotFound(self.plan.origin.clone(),
+                                                                        self.key_cache_path
+                                                                            .display()
+                                                                            .to_string()).into()
+                                           })
+    }
+
+    fn resolver(&self) -> Resolver<'_> {
+        Resolver { name:         &self.plan.name,
+                   hab:          if henv::var("NO_INSTALL_DEPS").is_ok() {
+                       None
+                   } else {
+                       self.hab_bin.as_deref()
+                   },
+                   bldr_url:     &self.bldr_url,
+                   channel:      &self.channel,
+                   ignore_local:
+                       henv::var("HAB_FEAT_IGNORE_LOCAL").map(|v| v.eq_ignore_ascii_case("true"))
+                                                         .unwrap_or(false),
+                   pkg_path:     &self.pkg_path, }
+    }
+
+    fn resolve_dependencies(&mut self) -> Result<()> {
+        if let Some(scaffolding) = self.plan.scaffolding.clone() {
+            build_line(&self.plan.name, "Resolving scaffolding dependencies");
+            let scaffolding_deps = self.resolver().resolve_scaffolding(&scaffolding)?;
+            let mut build_deps: Vec<String> = scaffolding_deps.iter()
+                                                              .map(|(ident, _)| ident.clone())
+                                                              .collect();
+            build_deps.extend(self.plan.build_deps.iter().cloned());
+            self.shell.set_array("pkg_build_deps", &build_deps)?;
+
+            // Mimic the state where all dependencies are known so that helpers such as
+            // `pkg_path_for` work when the Scaffolding is loaded.
+            let paths: Vec<PathBuf> = scaffolding_deps.into_iter().map(|(_, path)| path).collect();
+            self.deps = Dependencies { build_tdeps: self.resolver().with_tdeps(&paths),
+                                       build_deps: paths,
+                                       ..Default::default() };
+            self.push_dependencies()?;
+            self.shell
+                .run("Loading Scaffolding", "__hab_load_scaffolding")?;
+            self.reload_plan()?;
+        }
+
+        build_line(&self.plan.name, "Resolving build and run dependencies");
+        self.deps = self.resolver()
+                        .resolve_all(&self.plan.build_deps, &self.plan.deps)?;
+        self.push_dependencies()?;
+        self.deps.validate(&self.pkg_path)
+    }
+
+    fn push_dependencies(&mut self) -> Result<()> {
+        let arrays = [("pkg_build_deps_resolved", self.deps.build_deps.clone()),
+                      ("pkg_build_tdeps_resolved", self.deps.build_tdeps.clone()),
+                      ("pkg_deps_resolved", self.deps.deps.clone()),
+                      ("pkg_tdeps_resolved", self.deps.tdeps.clone()),
+                      ("pkg_all_deps_resolved", self.deps.all_deps()),
+                      ("pkg_all_tdeps_resolved", self.deps.all_tdeps())];
+        for (name, paths) in arrays.iter() {
+            let paths: Vec<String> = paths.iter()
+                                          .map(|p| p.to_string_lossy().into_owned())
+                                          .collect();
+            self.shell.set_array(name, &paths)?;
+        }
+        Ok(())
+    }
+
+    /// Set `PATH` to this package's binary directories followed by the `PATH` entries of all
+    /// dependencies, direct dependencies first, and finally the initial `PATH`.
+    fn set_build_path(&mut self) -> Result<()> {
+        let mut paths: Vec<String> =
+            self.plan
+                .bin_dirs
+                .iter()
+                .map(|dir| format!("{}/{}", self.plan.prefix.display(), dir))
+                .collect();
+        for dep in self.deps.all_tdeps() {
+            let dep_prefix = dep.to_string_lossy().into_owned();
+            if let Ok(data) = fs::read_to_string(dep.join("PATH")) {
+                for entry in data.trim().split(':') {
+                    if entry.starts_with(&dep_prefix) && !paths.iter().any(|p| p == entry) {
+                        paths.push(entry.to_string());
+                    }
+                }
+            }
+        }
+        paths.push(self.initial_path.clone());
+        let path = paths.join(":");
+        build_line(&self.plan.name, &format!("Setting PATH={}", path));
+        self.shell.export("PATH", &path)
+    }
+
+    /// Set the flags needed to compile and link against the direct dependencies.
+    fn set_build_environment(&mut self) -> Result<()> {
+        build_line(&self.plan.name, "Setting build environment");
+        let current = self.shell.vars(&["CFLAGS",
+                                         "CPPFLAGS",
+                                         "CXXFLAGS",
+                                         "LDFLAGS",
+                                         "PKG_CONFIG_PATH"])?;
+        let mut flags = Flags::new(&current);
+
+        let mut ld_run_path: Vec<String> =
+            self.plan
+                .lib_dirs
+                .iter()
+                .map(|lib| format!("{}/{}", self.plan.prefix.display(), lib))
+                .collect();
+        for dep in &self.deps.deps {
+            if let Some(data) = read_metadata(dep, "LD_RUN_PATH") {
+                ld_run_path.push(data);
+            }
+        }
+        for dep in self.deps.all_deps() {
+            let cflags = read_metadata(&dep, "CFLAGS");
+            if let Some(ref cflags) = cflags {
+                flags.append("CFLAGS", cflags, " ");
+            }
+            for var in &["CPPFLAGS", "CXXFLAGS"] {
+                match read_metadata(&dep, var) {
+                    Some(data) => flags.append(var, &data, " "),
+                    None => {
+                        if let Some(ref cflags) = cflags {
+                            flags.append(var, cflags, " ");
+                        }
+                    }
+                }
+            }
+            if let Some(data) = read_metadata(&dep, "LDFLAGS") {
+                flags.append("LDFLAGS", &data, " ");
+            }
+            if let Some(data) = read_metadata(&dep, "PKG_CONFIG_PATH") {
+                flags.append("PKG_CONFIG_PATH", &data, ":");
+            }
+        }
+        fs::create_dir_all(self.derived.string("CACHE_PATH"))?;
+
+        let mut script = vec!["export PREFIX=\"$pkg_prefix\"".to_string()];
+        if !ld_run_path.is_empty() {
+            script.push(format!("export LD_RUN_PATH={}", quote(&ld_run_path.join(":"))));
+        }
+        for (name, value) in &flags.0 {
+            script.push(format!("export {}={}", name, quote(value)));
+        }
+        for name in &["PREFIX",
+                      "LD_RUN_PATH",
+                      "CFLAGS",
+                      "CXXFLAGS",
+                      "CPPFLAGS",
+                      "LDFLAGS",
+                      "PKG_CONFIG_PATH"]
+        {
+            script.push(format!("build_line \"Setting {}=${}\"", name, name));
+        }
+        self.shell
+            .run("Setting the build environment", &script.join("\n"))
+    }
+
+    /// Remove the default system library search paths from any libtool scripts in the source.
+    fn fix_libtool(&self) -> Result<()> {
+        let src_path = self.derived.string("SRC_PATH");
+        for entry in WalkDir::new(&src_path).into_iter().filter_map(|e| e.ok()) {
+            if !entry.file_name()
+                     .to_string_lossy()
+                     .eq_ignore_ascii_case("ltmain.sh")
+            {
+                continue;
+            }
+            build_line(&self.plan.name,
+                       &format!("Fixing libtool script {}", entry.path().display()));
+            let content = fs::read_to_string(entry.path())?;
+            let fixed = content.lines()
+                               .map(|line| {
+                                   if line.starts_with("eval sys_lib_search_path=")
+                                      || line.starts_with("eval sys_lib_dlsearch_path=")
+                                   {
+                                       ""
+                                   } else {
+                                       line
+                                   }
+                               })
+                               .collect::<Vec<_>>()
+                               .join("\n");
+            fs::write(entry.path(), fixed + "\n")?;
+        }
+        Ok(())
+    }
+
+    fn verify_hook_line_endings(&self) -> Result<()> {
+        let hooks = self.plan_context.join("hooks");
+        let mut invalid = Vec::new();
+        if let Ok(entries) = fs::read_dir(&hooks) {
+            for entry in entries.filter_map(|e| e.ok()) {
+                if fs::read(entry.path()).map(|c| c.contains(&b'\r'))
+                                         .unwrap_or(false)
+                {
+                    invalid.push(entry.path().display().to_string());
+                }
+            }
+        }
+        if invalid.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::HookLineEndings(invalid.join(", ")).into())
+        }
+    }
+
+    fn build_metadata(&mut self, key: &SecretOriginSigningKey) -> Result<()> {
+        build_line(&self.plan.name, "Building package metadata");
+        let renderer = Renderer { plan:     &self.plan,
+                                  target:   self.target,
+                                  deps:     &self.deps,
+                                  pkg_path: &self.pkg_path, };
+        renderer.render()?;
+
+        // The environment metadata is computed by the Plan's `do_setup_environment` callback
+        // and is therefore rendered by the shell.
+        let mut script = Vec::new();
+        if let Some(runtime_path) = renderer.runtime_path() {
+            script.push(format!("__runtime_environment[PATH]={}", quote(&runtime_path)));
+        }
+        script.extend(["BUILDTIME_ENVIRONMENT",
+                       "BUILDTIME_ENVIRONMENT_PROVENANCE",
+                       "RUNTIME_ENVIRONMENT",
+                       "RUNTIME_ENVIRONMENT_PROVENANCE"].iter()
+                                                        .map(|f| {
+                                                            format!("_render_metadata_{}", f)
+                                                        }));
+        self.shell
+            .run("Rendering the environment metadata", &script.join("\n"))?;
+
+        let jobs = henv::var(JOBS_ENVVAR).ok()
+                                         .and_then(|j| j.parse().ok())
+                                         .unwrap_or_else(num_cpus::get);
+        renderer.render_files(key, Path::new(&self.derived.string("CACHE_PATH")), jobs)?;
+
+        build_line(&self.plan.name, "Creating manifest");
+        let vars = self.shell.vars(BUILD_FLAG_VARS)?;
+        let flags = BuildFlags { cflags:      vars.string("CFLAGS"),
+                                 cppflags:    vars.string("CPPFLAGS"),
+                                 cxxflags:    vars.string("CXXFLAGS"),
+                                 ldflags:     vars.string("LDFLAGS"),
+                                 ld_run_path: vars.string("LD_RUN_PATH"), };
+        renderer.render_manifest(&flags)
+    }
+
+    /// Copy the artifact to the output directory and write the `last_build.env` build report,
+    /// returning the SHA256 and Blake2b checksums of the artifact.
+    fn prepare_build_outputs(&self, artifact_path: &Path) -> Result<(String, String)> {
+        let sha256sum = artifact::sha256sum(artifact_path)?;
+        let blake2bsum = artifact::blake2bsum(artifact_path)?;
+        let plan_file = self.plan.path();
+        let output_path = PathBuf::from(self.derived.string("pkg_output_path"));
+        let artifact_name = artifact_path.file_name()
+                                         .map(|n| n.to_string_lossy().into_owned())
+                                         .unwrap_or_default();
+
+        fs::create_dir_all(&output_path)?;
+        artifact::chown_like(&output_path, &plan_file);
+        fs::copy(artifact_path, output_path.join(&artifact_name))?;
+        artifact::chown_like(&output_path.join(&artifact_name), &plan_file);
+        let provenance_path = provenance::provenance_path(artifact_path);
+        if provenance_path.is_file() {
+            let provenance_name = format!("{}{}", artifact_name, provenance::PROVENANCE_SUFFIX);
+            fs::copy(&provenance_path, output_path.join(&provenance_name))?;
+            artifact::chown_like(&output_path.join(&provenance_name), &plan_file);
+        }
+
+        let pre_build = output_path.join("pre_build.env");
+        if pre_build.exists() {
+            fs::remove_file(pre_build)?;
+        }
+        let last_build = output_path.join("last_build.env");
+        fs::write(&last_build,
+                  self.build_report(&[("pkg_artifact", artifact_name),
+                                      ("pkg_sha256sum", sha256sum.clone()),
+                                      ("pkg_blake2bsum", blake2bsum.clone())]))?;
+        artifact::chown_like(&last_build, &plan_file);
+        Ok((sha256sum, blake2bsum))
+    }
+
+    /// Run the `do_after_success` or `do_after_failure` callback if the Plan defines it. These
+    /// are best effort: a failing callback does not change the outcome of the build.
+    fn final_callback_in_shell(&mut self, callback: &str) -> bool {
+        let script = format!("if [[ \"$(type -t {cb})\" == \"function\" ]]; then\nbuild_line \
+                              \"'{cb}' callback function is defined; executing...\"\n( set -e; \
+                              {cb} ) || warn \"'{cb}' callback failed\"\nfi",
+                             cb = callback);
+        self.shell.run(callback, &script).is_ok()
+    }
+
+    /// Run a final callback after a failure. If the failure terminated the build shell, the Plan
+    /// is loaded into a fresh shell to run the callback.
+    fn final_callback(&mut self, callback: &str, plan_path: &Path, lib_dir: &Path) {
+        if self.final_callback_in_shell(callback) {
+            return;
+        }
+        let shell =
+            Shell::start(&self.plan_context,
+                         lib_dir,
+                         &[("PLAN_CONTEXT", self.plan_context.to_string_lossy().into_owned()),
+                           ("pkg_target", self.target.to_string())]).and_then(|mut shell| {
+                shell.run("Loading the Plan",
+                          &format!("source {}", quote(&plan_path.to_string_lossy())))?;
+                Ok(shell)
+            });
+        match shell {
+            Ok(shell) => {
+                self.shell = shell;
+                self.final_callback_in_shell(callback);
+            }
+            Err(e) => debug!("Unable to run {}: {}", callback, e),
+        }
+    }
+}
+
+/// Build flags which are appended to, in the order they are first set.
+struct Flags(Vec<(String, String)>);
+
+impl Flags {
+    fn new(current: &Vars) -> Self {
+        Flags(["CFLAGS",
+               "CPPFLAGS",
+               "CXXFLAGS",
+               "LDFLAGS",
+               "PKG_CONFIG_PATH"].iter()
+                                 .map(|name| (name.to_string(), current.string(name)))
+                                 .collect())
+    }
+
+    fn append(&mut self, name: &str, value: &str, separator: &str) {
+        if let Some((_, current)) = self.0.iter_mut().find(|(n, _)| n == name) {
+            if !current.is_empty() {
+                current.push_str(separator);
+            }
+            current.push_str(value);
+        }
+    }
+}
+
+/// The trimmed content of a dependency's metadata file, if it has one.
+fn read_metadata(dep_path: &Path, file: &str) -> Option<String> {
+    fs::read_to_string(dep_path.join(file)).ok()
+                                           .map(|data| data.trim().to_string())
+}
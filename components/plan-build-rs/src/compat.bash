@@ -0,0 +1,481 @@
+# shellcheck shell=bash
+#
+# Compatibility layer for running existing `plan.sh` files under the native
+# plan-build. This file is sourced once by a long-lived Bash process before the
+# Plan itself and provides:
+#
+# * the same default variables and build phase callbacks as `hab-plan-build`,
+#   so that a Plan may override any `do_*` callback and call back into the
+#   matching `do_default_*` implementation
+# * the public helper functions (`build_line`, `pkg_path_for`, `attach`, ...)
+#   from the `public.bash`, `shared.bash` and `environment.bash` libraries
+# * a handful of **internal** functions which let the native program read the
+#   state of the Plan back out of the shell
+#
+# Dependency resolution, metadata, the manifest and artifact generation are
+# implemented natively and are not part of this file.
+#
+# The following variables are set by the native program before this file is
+# sourced: `__hab_lib_dir`, `THIS_PROGRAM`, `PLAN_CONTEXT`, `pkg_target`,
+# `INITIAL_PWD`.
+
+# shellcheck disable=1090
+source "${__hab_lib_dir}/public.bash"
+# shellcheck disable=1090
+source "${__hab_lib_dir}/shared.bash"
+# shellcheck disable=1090
+source "${__hab_lib_dir}/environment.bash"
+
+if [[ -n "${DEBUG:-}" ]]; then
+  set -x
+fi
+
+# ## Default variables
+
+_program=hab-plan-build
+HAB_PLAN_BUILD=0.0.1
+: "${HAB_ROOT_PATH:=/hab}"
+HAB_CACHE_SRC_PATH=$HAB_ROOT_PATH/cache/src
+HAB_CACHE_ARTIFACT_PATH=$HAB_ROOT_PATH/cache/artifacts
+: "${HAB_CACHE_KEY_PATH:=$HAB_ROOT_PATH/cache/keys}"
+export HAB_CACHE_KEY_PATH
+HAB_PKG_PATH=$HAB_ROOT_PATH/pkgs
+: "${HAB_BLDR_URL:=https://bldr.habitat.sh}"
+export HAB_BLDR_URL
+: "${HAB_BLDR_CHANNEL:=stable}"
+export HAB_BLDR_CHANNEL
+FALLBACK_CHANNEL="stable"
+INITIAL_PATH="$PATH"
+: "${HAB_HART_COMPRESSION:=xz}"
+: "${HAB_HART_COMPRESSION_LEVEL:=6}"
+: "${HAB_PROVENANCE:=false}"
+: "${HAB_PROVENANCE_BUILDER_ID:=https://habitat.sh/builders/local}"
+pkg_origin=""
+if [[ -n "${SOURCE_DATE_EPOCH:-}" ]]; then
+  pkg_release=$(date -u -d "@$SOURCE_DATE_EPOCH" +%Y%m%d%H%M%S)
+else
+  pkg_release=$(date -u +%Y%m%d%H%M%S)
+fi
+pkg_build_deps=()
+pkg_deps=()
+pkg_lib_dirs=()
+pkg_bin_dirs=()
+pkg_include_dirs=()
+pkg_pconfig_dirs=()
+pkg_svc_run=''
+pkg_exposes=()
+declare -A pkg_exports
+declare -A pkg_binds
+declare -A pkg_binds_optional
+pkg_svc_user=hab
+pkg_svc_group=$pkg_svc_user
+pkg_svc_path="$HAB_ROOT_PATH/svc/@__pkg_name__@"
+pkg_svc_data_path="$pkg_svc_path/data"
+pkg_svc_files_path="$pkg_svc_path/files"
+pkg_svc_var_path="$pkg_svc_path/var"
+pkg_svc_config_path="$pkg_svc_path/config"
+pkg_svc_config_install_path="$pkg_svc_path/config_install"
+pkg_svc_static_path="$pkg_svc_path/static"
+pkg_build_deps_resolved=()
+pkg_build_tdeps_resolved=()
+pkg_deps_resolved=()
+pkg_tdeps_resolved=()
+pkg_all_deps_resolved=()
+pkg_all_tdeps_resolved=()
+
+umask 0022
+unset TERMINFO
+
+_artifact_ext="hart"
+
+# The system commands used by the public helper functions. Their presence is
+# verified by the native program before any build phase is run.
+_wget_cmd=$(command -v wget || true)
+if [[ "${HAB_NONINTERACTIVE:-}" == "true" ]]; then
+  _wget_cmd="$_wget_cmd --no-verbose"
+fi
+_shasum_cmd=$(command -v gsha256sum || command -v sha256sum || true)
+_tar_cmd=$(command -v tar || true)
+_hab_cmd=$(command -v hab || true)
+_rq_cmd=$(command -v rq || true)
+: "${HAB_BIN:=$_hab_cmd}"
+
+# ## Internal helper functions
+
+# **Internal** Writes the named variables to the file given as the first
+# argument for the native program to read. Each variable is written as its name
+# and kind (`s`calar, `a`rray or `A`ssociative array) followed by its values,
+# all NUL-terminated. Arrays are preceded by their length and associative
+# arrays are written as key/value pairs. Unset variables are skipped.
+__hab_dump_vars() {
+  local __out="$1"
+  shift
+  local __name __decl __key
+  : > "$__out"
+  for __name in "$@"; do
+    __decl="$(declare -p "$__name" 2> /dev/null || true)"
+    case "$__decl" in
+      "")
+        ;;
+      "declare -A"*)
+        declare -n __ref="$__name"
+        printf '%s\0A\0%s\0' "$__name" "${#__ref[@]}" >> "$__out"
+        for __key in "${!__ref[@]}"; do
+          printf '%s\0%s\0' "$__key" "${__ref[$__key]}" >> "$__out"
+        done
+        unset -n __ref
+        ;;
+      "declare -a"*)
+        declare -n __ref="$__name"
+        printf '%s\0a\0%s\0' "$__name" "${#__ref[@]}" >> "$__out"
+        if [[ ${#__ref[@]} -gt 0 ]]; then
+          printf '%s\0' "${__ref[@]}" >> "$__out"
+        fi
+        unset -n __ref
+        ;;
+      *)
+        printf '%s\0s\0%s\0' "$__name" "${!__name}" >> "$__out"
+        ;;
+    esac
+  done
+}
+
+# **Internal** Sets `__hab_result` to `1` if the named function is defined and
+# to `0` otherwise.
+__hab_function_defined() {
+  if [[ "$(type -t "$1")" == "function" ]]; then
+    __hab_result=1
+  else
+    __hab_result=0
+  fi
+}
+
+# **Internal** Exits the shell with the given status code or with the status
+# of the last command. Re-installed as a trap by `attach()`. Reporting the
+# failure and running `do_after_failure` is left to the native program.
+_on_exit() {
+  exit "${1:-$?}"
+}
+
+# **Internal** Returns the path for the desired build dependency on stdout
+# from the resolved dependency set. Used by `pkg_path_for`.
+_pkg_path_for_build_deps() {
+  local dep="$1"
+  local e
+  local cutn="$(($(echo $HAB_PKG_PATH | grep -o '/' | wc -l)+2))"
+  for e in "${pkg_build_deps_resolved[@]}"; do
+    if echo "$e" | cut -d "/" -f ${cutn}- | grep -E -q "(^|/)${dep}(/|$)"; then
+      echo "$e"
+      return 0
+    fi
+  done
+  return 1
+}
+
+# **Internal** Returns the path for the desired runtime dependency on stdout
+# from the resolved dependency set. Used by `pkg_path_for`.
+_pkg_path_for_deps() {
+  local dep="$1"
+  local e
+  local cutn="$(($(echo $HAB_PKG_PATH | grep -o '/' | wc -l)+2))"
+  for e in "${pkg_deps_resolved[@]}"; do
+    if echo "$e" | cut -d "/" -f ${cutn}- | grep -E -q "(^|/)${dep}(/|$)"; then
+      echo "$e"
+      return 0
+    fi
+  done
+  if [[ "${FUNCNAME[1]}" != "pkg_interpreter_for" ]]; then
+    warn "No runtime dependency found for '$dep'"
+    warn "Resolved runtime package set: ${pkg_deps_resolved[*]}"
+  fi
+  return 1
+}
+
+# **Internal** Prints the source file, line number, and lines of context
+# around the current debugging session context. Used by `attach()`.
+_attach_whereami() {
+  local context=${1:-10}
+  local lnum="${BASH_LINENO[1]}"
+  local src="${BASH_SOURCE[2]}"
+  echo
+  echo "From: $src @ line $lnum :"
+  echo
+  awk '{printf "%d: %s\n", NR, $0}' "$src" \
+    | sed -e "$((lnum - context)),$((lnum + context))!d" \
+      -e 's,^,    ,g' \
+    | sed -e "$((context +1))s/^   / =>/"
+  echo
+}
+
+# **Internal** Sources the Scaffolding implementation and runs its
+# `scaffolding_load()` function, if there is one.
+__hab_load_scaffolding() {
+  local lib
+  lib="$(_pkg_path_for_build_deps "$pkg_scaffolding")/lib/scaffolding.sh"
+  build_line "Loading Scaffolding $lib"
+  # shellcheck disable=1090
+  source "$lib"
+  if [[ "$(type -t scaffolding_load)" == "function" ]]; then
+    scaffolding_load
+  fi
+}
+
+# ## Build Phases
+#
+# Stub build phases, in the order they are executed. These can be overridden by
+# the `plan.sh` if needed.
+
+do_begin() {
+  do_default_begin
+  return $?
+}
+
+do_default_begin() {
+  return 0
+}
+
+do_before() {
+  do_default_before
+  return $?
+}
+
+do_default_before() {
+  return 0
+}
+
+do_download() {
+  do_default_download
+  return $?
+}
+
+do_default_download() {
+  if [[ -z "${pkg_source:-}" ]]; then
+    return 0
+  fi
+
+  # shellcheck disable=2154
+  download_file "$pkg_source" "$pkg_filename" "$pkg_shasum"
+}
+
+do_verify() {
+  do_default_verify
+  return $?
+}
+
+do_default_verify() {
+  if [[ -n "${pkg_filename:-}" ]]; then
+    verify_file "$pkg_filename" "$pkg_shasum"
+  fi
+}
+
+do_clean() {
+  do_default_clean
+  return $?
+}
+
+do_default_clean() {
+  build_line "Clean the cache"
+  rm -rf "$CACHE_PATH"
+  return 0
+}
+
+do_unpack() {
+  do_default_unpack
+  return $?
+}
+
+do_default_unpack() {
+  if [[ -n "${pkg_filename:-}" ]]; then
+    unpack_file "$pkg_filename"
+  fi
+}
+
+do_prepare_wrapper() {
+  build_line "Preparing to build"
+  pushd "$SRC_PATH" > /dev/null
+  do_prepare
+  popd > /dev/null
+}
+
+do_prepare() {
+  do_default_prepare
+  return $?
+}
+
+do_default_prepare() {
+  return 0
+}
+
+do_build_wrapper() {
+  build_line "Building"
+  pushd "$SRC_PATH" > /dev/null
+  do_build
+  popd > /dev/null
+}
+
+do_build() {
+  do_default_build
+  return $?
+}
+
+do_default_build() {
+  ./configure --prefix="$pkg_prefix"
+  make
+}
+
+do_check_wrapper() {
+  if [[ "$(type -t do_check)" = "function" && -n "${DO_CHECK:-}" ]]; then
+    build_line "Running post-compile tests"
+    pushd "$SRC_PATH" > /dev/null
+    do_check
+    popd > /dev/null
+  fi
+}
+
+do_install_wrapper() {
+  build_line "Installing"
+  mkdir -pv "$pkg_prefix"
+  for dir in "${pkg_lib_dirs[@]}" "${pkg_bin_dirs[@]}" "${pkg_include_dirs[@]}" "${pkg_pconfig_dirs[@]}"; do
+    mkdir -pv "$pkg_prefix/$dir"
+  done
+  pushd "$SRC_PATH" > /dev/null
+  do_install
+  popd > /dev/null
+}
+
+do_install() {
+  do_default_install
+  return $?
+}
+
+do_default_install() {
+  make install
+}
+
+do_build_config() {
+  do_default_build_config
+  return $?
+}
+
+do_default_build_config() {
+  build_line "Writing configuration"
+  _do_copy_templates "config"
+  _do_copy_templates "config_install"
+  if [[ -d "$PLAN_CONTEXT/hooks" ]]; then
+    mkdir -p "$pkg_prefix"/hooks
+    for file in "$PLAN_CONTEXT"/hooks/*
+    do
+      if [[ -e "$file" ]]; then
+        # The supervisor does not recognize extensions so all hooks are
+        # copied without extensions
+        local target
+        target="$pkg_prefix"/hooks/"$(basename "${file%.*}")"
+        if [[ -f "$target" ]]; then
+          exit_with "Multiple hook files found for $(basename "${file%.*}") hook. No more than one hook file permitted per lifecycle hook." 1
+        else
+          cp "$file" "$target"
+        fi
+      fi
+    done
+    chmod 755 "$pkg_prefix"/hooks
+  fi
+  if [[ -f "$PLAN_CONTEXT/default.toml" ]]; then
+    cp "$PLAN_CONTEXT/default.toml" "$pkg_prefix"
+  fi
+  return 0
+}
+
+_do_copy_templates() {
+  if [[ -d "$PLAN_CONTEXT/$1" ]]; then
+    if [[ -z "${HAB_CONFIG_EXCLUDE:-}" ]]; then
+      config_exclude_exts=("*.sw?" "*~" "*.bak")
+    else
+      IFS=',' read -r -a config_exclude_exts <<< "$HAB_CONFIG_EXCLUDE"
+    fi
+    find_exclusions=()
+    for ext in "${config_exclude_exts[@]}"; do
+      find_exclusions+=(! -name "$ext")
+    done
+    find "$PLAN_CONTEXT/$1" "${find_exclusions[@]}" | while read -r FILE
+    do
+      local plan_context_relative_path="$pkg_prefix${FILE#$PLAN_CONTEXT}"
+      if [[ -d "$FILE" ]]; then
+        mkdir -p "$plan_context_relative_path"
+      else
+        cp "$FILE" "$plan_context_relative_path"
+      fi
+    done
+    chmod 755 "$pkg_prefix/$1"
+  fi
+}
+
+do_build_service() {
+  do_default_build_service
+  return $?
+}
+
+do_default_build_service() {
+  build_line "Writing service management scripts"
+  if [[ -f "${PLAN_CONTEXT}/hooks/run" || -f "${PLAN_CONTEXT}/hooks/run.*" ]]; then
+    build_line "Using run hook ${PLAN_CONTEXT}/hooks/run"
+    return 0
+  else
+    if [[ -n "${pkg_svc_run}" ]]; then
+      # We use chpst to ensure that the script works outside `hab-sup`
+      # for debugging purposes
+      build_line "Writing ${pkg_prefix}/run script to run ${pkg_svc_run} as ${pkg_svc_user}:${pkg_svc_group}"
+      cat <<EOT >> "$pkg_prefix"/run
+#!/bin/sh
+export HOME=$pkg_svc_data_path
+cd $pkg_svc_path
+
+if [ "\$(whoami)" = "root" ]; then
+  exec chpst \\
+    -U ${pkg_svc_user}:${pkg_svc_group} \\
+    -u ${pkg_svc_user}:${pkg_svc_group} \\
+    ${pkg_svc_run} 2>&1
+else
+  exec ${pkg_svc_run} 2>&1
+fi
+EOT
+    fi
+  fi
+  return 0
+}
+
+do_strip() {
+  do_default_strip
+  return $?
+}
+
+do_default_strip() {
+  build_line "Stripping unneeded symbols from binaries and libraries"
+  find "$pkg_prefix" -type f -perm -u+w -print0 2> /dev/null \
+    | while read -rd '' f; do
+      case "$(file -bi "$f")" in
+        *application/x-executable*) strip --strip-all "$f";;
+        *application/x-pie-executable*) strip --strip-unneeded "$f";;
+        *application/x-sharedlib*) strip --strip-unneeded "$f";;
+        *application/x-archive*) strip --strip-debug "$f";;
+        *) continue;;
+      esac
+    done
+}
+
+do_after() {
+  do_default_after
+  return $?
+}
+
+do_default_after() {
+  return 0
+}
+
+do_end() {
+  do_default_end
+  return $?
+}
+
+do_default_end() {
+  return 0
+}
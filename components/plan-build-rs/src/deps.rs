@@ -0,0 +1,253 @@
+//! Resolution of the build and run dependencies of a Plan to installed packages.
+//!
+//! Several package-path sets are computed, matching the arrays of the same name which are made
+//! available to Plans:
+//!
+//! * `pkg_build_deps_resolved`: all direct build dependencies
+//! * `pkg_build_tdeps_resolved`: all direct build dependencies and their run dependencies
+//! * `pkg_deps_resolved`: all direct run dependencies
+//! * `pkg_tdeps_resolved`: all direct run dependencies and their run dependencies
+//! * `pkg_all_deps_resolved`: all direct build and run dependencies
+//! * `pkg_all_tdeps_resolved`: the ordered set of all of the above
+use crate::{build_line,
+            error::{Error,
+                    Result},
+            hcore::package::{PackageIdent,
+                             PackageInstall}};
+use std::{collections::BTreeMap,
+          fs,
+          path::{Path,
+                 PathBuf},
+          process::Command,
+          str::FromStr};
+
+const FALLBACK_CHANNEL: &str = "stable";
+
+/// Installs and locates dependencies.
+pub struct Resolver<'a> {
+    /// The name of the package being built, used to prefix output.
+    pub name:         &'a str,
+    /// The `hab` program used to install dependencies, `None` when `$NO_INSTALL_DEPS` is set.
+    pub hab:          Option<&'a Path>,
+    pub bldr_url:     &'a str,
+    pub channel:      &'a str,
+    pub ignore_local: bool,
+    pub pkg_path:     &'a Path,
+}
+
+impl<'a> Resolver<'a> {
+    /// Install `dep` on a best-effort basis and return the path of the latest installed release
+    /// which satisfies it.
+    pub fn resolve(&self, dep: &str, install_args: &[&str]) -> Result<PathBuf> {
+        if !dep.contains('/') {
+            warn!("Origin required for '{}' (example: acme/{})", dep, dep);
+            return Err(Error::DependencyNotFound(dep.to_string()).into());
+        }
+        self.install(dep, install_args);
+        let ident =
+            PackageIdent::from_str(dep).map_err(|_| Error::DependencyNotFound(dep.to_string()))?;
+        let install = PackageInstall::load(&ident, None).map_err(|_| {
+                          warn!("Could not find a suitable installed package for '{}'", dep);
+                          Error::DependencyNotFound(dep.to_string())
+                      })?;
+        Ok(install.installed_path().to_path_buf())
+    }
+
+    fn install(&self, dep: &str, install_args: &[&str]) {
+        let hab = match self.hab {
+            Some(hab) => hab,
+            None => return,
+        };
+        let mut channels = vec![self.channel];
+        if self.channel != FALLBACK_CHANNEL {
+            channels.push(FALLBACK_CHANNEL);
+        }
+        for channel in channels {
+            let mut cmd = Command::new(hab);
+            cmd.args(&["pkg", "install", "-u", self.bldr_url, "--channel", channel]);
+            if self.ignore_local {
+                cmd.arg("--ignore-local");
+            }
+            cmd.arg(dep).args(install_args);
+            debug!("Running: {:?}", cmd);
+            match cmd.status() {
+                Ok(status) if status.success() => return,
+                _ if channel != FALLBACK_CHANNEL => {
+                    build_line(self.name,
+                               &format!("Trying to install '{}' from '{}'", dep, FALLBACK_CHANNEL))
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// The path of an installed package given its fully qualified identifier.
+    fn path_of(&self, ident: &str) -> PathBuf { self.pkg_path.join(ident) }
+
+    /// The transitive run dependencies of an installed package, read from its `TDEPS` file.
+    fn tdeps_of(&self, pkg_path: &Path) -> Vec<PathBuf> { self.metadata_idents(pkg_path, "TDEPS") }
+
+    fn metadata_idents(&self, pkg_path: &Path, file: &str) -> Vec<PathBuf> {
+        fs::read_to_string(pkg_path.join(file)).map(|content| {
+                                                   content.lines()
+                                                          .map(str::trim)
+                                                          .filter(|l| !l.is_empty())
+                                                          .map(|l| self.path_of(l))
+                                                          .collect()
+                                               })
+                                               .unwrap_or_default()
+    }
+
+    /// Resolve a Scaffolding package, returning its fully qualified dependencies (the package
+    /// itself followed by its direct run dependencies) with their paths.
+    pub fn resolve_scaffolding(&self, scaffolding: &str) -> Result<Vec<(String, PathBuf)>> {
+        let path = self.resolve(scaffolding, &[])?;
+        build_line(self.name,
+                   &format!("Resolved scaffolding dependency '{}' to {}",
+                            scaffolding,
+                            path.display()));
+        let mut deps = vec![(scaffolding.to_string(), path.clone())];
+        for dep in self.metadata_idents(&path, "DEPS") {
+            let ident = dep.strip_prefix(self.pkg_path)
+                           .unwrap_or(&dep)
+                           .to_string_lossy()
+                           .into_owned();
+            deps.push((ident, dep));
+        }
+        Ok(deps)
+    }
+
+    pub fn resolve_all(&self, build_deps: &[String], deps: &[String]) -> Result<Dependencies> {
+        let mut resolved = Dependencies::default();
+        for dep in build_deps {
+            let path = self.resolve(dep, &[])?;
+            build_line(self.name,
+                       &format!("Resolved build dependency '{}' to {}", dep, path.display()));
+            resolved.build_deps.push(path);
+        }
+        resolved.build_tdeps = self.with_tdeps(&resolved.build_deps);
+
+        for dep in deps {
+            let path = self.resolve(dep, &["--ignore-install-hook"])?;
+            build_line(self.name,
+                       &format!("Resolved dependency '{}' to {}", dep, path.display()));
+            resolved.deps.push(path);
+        }
+        resolved.tdeps = self.with_tdeps(&resolved.deps);
+        Ok(resolved)
+    }
+
+    /// The given direct dependencies followed by their transitive run dependencies. A
+    /// dependency seen again is moved to the end so that dependencies always appear after
+    /// the packages which depend on them.
+    pub fn with_tdeps(&self, direct: &[PathBuf]) -> Vec<PathBuf> {
+        let mut result = direct.to_vec();
+        for dep in direct {
+            for tdep in self.tdeps_of(dep) {
+                result.retain(|d| *d != tdep);
+                result.push(tdep);
+            }
+        }
+        result
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Dependencies {
+    pub build_deps:  Vec<PathBuf>,
+    pub build_tdeps: Vec<PathBuf>,
+    pub deps:        Vec<PathBuf>,
+    pub tdeps:       Vec<PathBuf>,
+}
+
+impl Dependencies {
+    /// All direct build and run dependencies, build dependencies first.
+    pub fn all_deps(&self) -> Vec<PathBuf> {
+        self.build_deps
+            .iter()
+            .chain(self.deps.iter())
+            .cloned()
+            .collect()
+    }
+
+    /// All build and run dependencies, direct and transitive. Direct dependencies come first
+    /// so that they take priority when setting `PATH`.
+    pub fn all_tdeps(&self) -> Vec<PathBuf> {
+        let mut result = self.all_deps();
+        for dep in self.build_tdeps.iter().chain(self.tdeps.iter()) {
+            if !result.contains(dep) {
+                result.push(dep.clone());
+            }
+        }
+        result
+    }
+
+    /// Ensure the full runtime dependency set does not contain more than one release of the
+    /// same package, which would lead to conflicting shared libraries at runtime.
+    pub fn validate(&self, pkg_path: &Path) -> Result<()> {
+        let mut releases: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for dep in &self.tdeps {
+            let ident = dep.strip_prefix(pkg_path)
+                           .unwrap_or(dep)
+                           .to_string_lossy()
+                           .into_owned();
+            let origin_name = ident.splitn(3, '/').take(2).collect::<Vec<_>>().join("/");
+            releases.entry(origin_name).or_default().push(ident);
+        }
+        let dupes = releases.into_iter()
+                            .filter(|(_, idents)| idents.len() > 1)
+                            .map(|(name, idents)| format!("{} ( {} )", name, idents.join(" ")))
+                            .collect::<Vec<_>>();
+        if dupes.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::DuplicateDependencies(dupes.join(", ")).into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn install(pkg_path: &Path, ident: &str, tdeps: &[&str]) -> PathBuf {
+        let path = pkg_path.join(ident);
+        fs::create_dir_all(&path).unwrap();
+        fs::write(path.join("TDEPS"), tdeps.join("\n")).unwrap();
+        path
+    }
+
+    #[test]
+    fn transitive_dependencies_are_ordered() {
+        let pkgs = TempDir::new().unwrap();
+        let resolver = Resolver { name:         "test",
+                                  hab:          None,
+                                  bldr_url:     "",
+                                  channel:      "stable",
+                                  ignore_local: false,
+                                  pkg_path:     pkgs.path(), };
+        let glibc = install(pkgs.path(), "core/glibc/2.29/1", &[]);
+        let pcre = install(pkgs.path(), "core/pcre/8.42/1", &["core/glibc/2.29/1"]);
+        let zlib = install(pkgs.path(), "core/zlib/1.2/1", &["core/glibc/2.29/1"]);
+
+        let tdeps = resolver.with_tdeps(&[glibc.clone(), pcre.clone(), zlib.clone()]);
+        assert_eq!(tdeps, vec![pcre.clone(), zlib.clone(), glibc.clone()]);
+
+        let deps = Dependencies { build_deps:  vec![zlib.clone()],
+                                  build_tdeps: resolver.with_tdeps(&[zlib.clone()]),
+                                  deps:        vec![pcre.clone()],
+                                  tdeps:       resolver.with_tdeps(&[pcre.clone()]), };
+        assert_eq!(deps.all_tdeps(), vec![zlib, pcre, glibc]);
+        assert!(deps.validate(pkgs.path()).is_ok());
+    }
+
+    #[test]
+    fn duplicate_releases_are_rejected() {
+        let pkgs = TempDir::new().unwrap();
+        let deps = Dependencies { tdeps: vec![pkgs.path().join("core/glibc/2.29/1"),
+                                              pkgs.path().join("core/glibc/2.27/1"),],
+                                  ..Default::default() };
+        assert!(deps.validate(pkgs.path()).is_err());
+    }
+}
@@ -0,0 +1,62 @@
+use std::{path::PathBuf,
+          result};
+
+pub type Result<T> = result::Result<T, failure::Error>;
+
+#[derive(Debug, Fail)]
+pub enum Error {
+    #[fail(display = "Composite plans are no longer supported. For more details see \
+                      https://www.habitat.sh/blog/2018/10/shelving-composites/")]
+    CompositePlan,
+    #[fail(display = "Resolving '{}' failed, should this be built first?", _0)]
+    DependencyNotFound(String),
+    #[fail(display = "The following runtime dependencies have more than one version release in \
+                      the full dependency chain: {}. The duplicate dependency entries will need \
+                      to be resolved before this Plan can be built.",
+           _0)]
+    DuplicateDependencies(String),
+    #[fail(display = "Bad value in pkg_exposes; No pkg_export found matching key: {}",
+           _0)]
+    ExposeWithoutExport(String),
+    #[fail(display = "Incorrect CR+LF line ending detected in package hook file(s). For the \
+                      Plan to build, you must first convert these to Unix LF: {}",
+           _0)]
+    HookLineEndings(String),
+    #[fail(display = "Bad pkg_export in pkg_exposes; Value of key \"{}\" does not contain a \
+                      valid TCP or UDP port number: {}",
+           _0, _1)]
+    InvalidExposedPort(String, String),
+    #[fail(display = "Failed to build. Package {} '{}' contains invalid characters.",
+           _0, _1)]
+    InvalidPlanValue(&'static str, String),
+    #[fail(display = "SOURCE_DATE_EPOCH '{}' is not a number of seconds since the Unix epoch",
+           _0)]
+    InvalidSourceDateEpoch(String),
+    #[fail(display = "The plan-build library directory {} does not contain {}. Set \
+                      HAB_PLAN_BUILD_LIB_DIR to the directory containing the hab-plan-build \
+                      Bash libraries.",
+           _0, _1)]
+    LibraryNotFound(String, &'static str),
+    #[fail(display = "Failed to build. '{}' must be set.", _0)]
+    MissingPlanValue(&'static str),
+    #[fail(display = "We require {} to build packages; aborting", _0)]
+    MissingSystemCommand(&'static str),
+    #[fail(display = "A Plan file was found in the following paths: {}. Only one is allowed at \
+                      a time",
+           _0)]
+    MultiplePlans(String),
+    #[fail(display = "Signing origin key '{}' not found in {}, aborting", _0, _1)]
+    OriginKeyNotFound(String, String),
+    #[fail(display = "Plan file not found in any of these paths: {}", _0)]
+    PlanNotFound(String),
+    #[fail(display = "Failed to load Plan {}, the shell exited with code {}",
+           _0, _1)]
+    PlanLoadFailed(PathBuf, i32),
+    #[fail(display = "Could not read variables from the build shell: {}", _0)]
+    ShellProtocol(String),
+    #[fail(display = "{} failed with exit code {}", _0, _1)]
+    StepFailed(String, i32),
+    #[fail(display = "Plan did not set 'pkg_version' and did not call 'update_pkg_version()' \
+                      before the 'do_prepare()' build phase.")]
+    VersionUnset,
+}
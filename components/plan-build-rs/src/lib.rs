@@ -0,0 +1,39 @@
+//! A native implementation of `hab-plan-build`.
+//!
+//! The build of a Plan is driven from Rust: finding and validating the Plan, resolving its
+//! dependencies, setting up the build environment, rendering the package metadata and creating
+//! the signed artifact. The callbacks of an existing `plan.sh` are run unchanged by a Bash
+//! process which loads the same libraries as `hab-plan-build`, so existing Plans and
+//! Scaffoldings build without modification.
+use habitat_core as hcore;
+
+#[macro_use]
+extern crate failure_derive;
+
+#[macro_use]
+extern crate log;
+
+mod artifact;
+mod build;
+mod deps;
+mod error;
+mod metadata;
+mod phase;
+mod plan;
+mod shell;
+
+pub use crate::{build::{build,
+                        JOBS_ENVVAR,
+                        LIB_DIR_ENVVAR,
+                        SOURCE_DATE_EPOCH_ENVVAR},
+                error::{Error,
+                        Result},
+                phase::Phase,
+                plan::Plan};
+
+pub const VERSION: &str = include_str!(concat!(env!("OUT_DIR"), "/VERSION"));
+
+/// Print a line of build output prefixed with the package name, like `build_line` in Plans.
+pub(crate) fn build_line(name: &str, msg: &str) {
+    println!("   {}: {}", name, msg);
+}
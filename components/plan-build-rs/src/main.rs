@@ -0,0 +1,49 @@
+use habitat_common as common;
+use habitat_plan_build as plan_build;
+#[macro_use]
+extern crate log;
+
+use crate::{common::ui::{UIWriter,
+                         UI},
+            plan_build::Result};
+use clap::{App,
+           Arg};
+use std::{env,
+          path::Path};
+
+fn main() {
+    let mut ui = UI::default_with_env();
+    if let Err(e) = start() {
+        ui.fatal(e).unwrap();
+        std::process::exit(1)
+    }
+}
+
+fn start() -> Result<()> {
+    env_logger::init();
+    let m = cli().get_matches();
+    debug!("clap cli args: {:?}", m);
+
+    if let Some(url) = m.value_of("BLDR_URL") {
+        env::set_var("HAB_BLDR_URL", url);
+    }
+    plan_build::build(Path::new(m.value_of("PLAN_CONTEXT").unwrap_or(".")))
+}
+
+fn cli<'a, 'b>() -> App<'a, 'b> {
+    App::new("hab-plan-build-rs").version(plan_build::VERSION)
+                                 .about("Builds a Habitat package from a Plan")
+                                 .arg(Arg::with_name("BLDR_URL").short("u")
+                                                                .long("url")
+                                                                .takes_value(true)
+                                                                .help("Specify an alternate \
+                                                                       Builder endpoint used to \
+                                                                       install dependencies"))
+                                 .arg(Arg::with_name("PLAN_CONTEXT").default_value(".")
+                                                                    .help("A directory \
+                                                                           containing a plan.sh \
+                                                                           file or a habitat/ \
+                                                                           directory which \
+                                                                           contains the plan.sh \
+                                                                           file"))
+}
@@ -0,0 +1,408 @@
+//! Rendering of the package metadata files and the manifest.
+//!
+//! The format of each file matches what `hab-plan-build` writes, as it is read by the Supervisor,
+//! `hab pkg install` and Builder. The runtime and buildtime environment files are rendered by the
+//! build shell as they are computed there by the Plan's `do_setup_environment` callback.
+use crate::{build_line,
+            deps::Dependencies,
+            error::{Error,
+                    Result},
+            hcore::{crypto::{artifact,
+                             keys::SecretOriginSigningKey,
+                             Blake2bHash},
+                    package::PackageTarget},
+            plan::Plan};
+use std::{fs,
+          path::{Path,
+                 PathBuf},
+          sync::{Arc,
+                 Mutex},
+          thread};
+use walkdir::WalkDir;
+
+/// The default locations of pkg-config files, used when a Plan does not set
+/// `$pkg_pconfig_dirs`.
+const PKG_CONFIG_DIRS: &[&str] = &["lib/pkgconfig", "share/pkgconfig"];
+
+/// The build flags of the environment a package was built in, recorded in its manifest.
+#[derive(Debug, Default)]
+pub struct BuildFlags {
+    pub cflags:      String,
+    pub cppflags:    String,
+    pub cxxflags:    String,
+    pub ldflags:     String,
+    pub ld_run_path: String,
+}
+
+pub struct Renderer<'a> {
+    pub plan:     &'a Plan,
+    pub target:   PackageTarget,
+    pub deps:     &'a Dependencies,
+    pub pkg_path: &'a Path,
+}
+
+impl<'a> Renderer<'a> {
+    /// Write all natively rendered metadata files into the package prefix.
+    pub fn render(&self) -> Result<()> {
+        let plan = self.plan;
+        self.write_joined("LD_RUN_PATH", ":", self.prefixed(&plan.lib_dirs, ""))?;
+        self.write_joined("LDFLAGS", " ", self.prefixed(&plan.lib_dirs, "-L"))?;
+        for flags in &["CFLAGS", "CPPFLAGS", "CXXFLAGS"] {
+            self.write_joined(flags, " ", self.prefixed(&plan.include_dirs, "-I"))?;
+        }
+        self.write_joined("PKG_CONFIG_PATH", ":", self.pkg_config_path())?;
+        self.write_joined("PATH", ":", self.prefixed(&plan.bin_dirs, ""))?;
+        self.write_lines("EXPORTS", key_values(&plan.exports))?;
+        self.write_lines("BINDS", key_values(&plan.binds))?;
+        self.write_lines("BINDS_OPTIONAL", key_values(&plan.binds_optional))?;
+        self.write_joined("EXPOSES", " ", self.exposed_ports()?)?;
+        self.write_lines("INTERPRETERS", self.prefixed(&plan.interpreters, ""))?;
+        self.write_lines("BUILD_DEPS", self.idents(&self.deps.build_deps))?;
+        self.write_lines("BUILD_TDEPS", self.idents(&self.deps.build_tdeps))?;
+        self.write_lines("DEPS", self.idents(&self.deps.deps))?;
+        self.write_lines("TDEPS", self.idents(&self.deps.tdeps))?;
+        self.write_lines("TARGET", vec![self.target.to_string()])?;
+        self.write_lines("IDENT", vec![plan.ident()])?;
+        self.write_lines("RUNTIME_PATH", self.runtime_path().into_iter().collect())?;
+        self.write_lines("SHUTDOWN_SIGNAL",
+                         plan.shutdown_signal.iter().cloned().collect())?;
+        self.write_lines("SHUTDOWN_TIMEOUT",
+                         plan.shutdown_timeout.iter().cloned().collect())?;
+        if plan.is_service() {
+            self.write_lines("SVC_USER", vec![plan.svc_user.clone()])?;
+            self.write_lines("SVC_GROUP", vec![plan.svc_group.clone()])?;
+        }
+        Ok(())
+    }
+
+    /// The `PATH` used by the Supervisor when running this package: its own binary directories
+    /// followed by the `PATH` entries of its direct and then transitive run dependencies. `None`
+    /// if the path would be empty.
+    pub fn runtime_path(&self) -> Option<String> {
+        let mut paths = self.prefixed(&self.plan.bin_dirs, "");
+        for dep in self.deps.deps.iter().chain(self.deps.tdeps.iter()) {
+            let dep_prefix = dep.to_string_lossy();
+            if let Ok(data) = fs::read_to_string(dep.join("PATH")) {
+                for entry in data.trim().split(':') {
+                    if entry.starts_with(dep_prefix.as_ref()) && !paths.iter().any(|p| p == entry) {
+                        paths.push(entry.to_string());
+                    }
+                }
+            }
+        }
+        if paths.is_empty() {
+            None
+        } else {
+            Some(paths.join(":"))
+        }
+    }
+
+    /// Write the `MANIFEST` of the package.
+    pub fn render_manifest(&self, flags: &BuildFlags) -> Result<()> {
+        let plan = self.plan;
+        let or = |value: &str, default: &str| {
+            if value.is_empty() {
+                default.to_string()
+            } else {
+                value.to_string()
+            }
+        };
+        let linked = |url: &str, default: &str| {
+            if url.is_empty() {
+                default.to_string()
+            } else {
+                format!("[{}]({})", url, url)
+            }
+        };
+        let listed = |items: &[String], default: &str| {
+            if items.iter().all(String::is_empty) {
+                default.to_string()
+            } else {
+                format!("`{}`", spaced(items))
+            }
+        };
+        let plan_source = fs::read_to_string(plan.path())?;
+
+        let manifest =
+            format!("# {origin} / {name}\n{description}\n\n* __Maintainer__: {maintainer}\n* \
+                     __Version__: {version}\n* __Release__: {release}\n* __Target__: {target}\n* \
+                     __Upstream URL__: {upstream}\n* __License__: {license}\n* __Source__: \
+                     {source}\n* __SHA__: {sha}\n* __Path__: `{prefix}`\n* __Build \
+                     Dependencies__: {build_deps}\n* __Dependencies__: {deps}\n* \
+                     __Interpreters__: {interpreters}\n\n# Plan\n\n## Build \
+                     Flags\n\n```bash\nCFLAGS: {cflags}\nCPPFLAGS: {cppflags}\nCXXFLAGS: \
+                     {cxxflags}\nLDFLAGS: {ldflags}\nLD_RUN_PATH: {ld_run_path}\n```\n\n## Plan \
+                     Source\n\n```bash\n{plan_source}\n```\n",
+                    origin = plan.origin,
+                    name = plan.name,
+                    description = plan.description,
+                    maintainer = plan.maintainer,
+                    version = plan.version,
+                    release = plan.release,
+                    target = self.target,
+                    upstream = linked(&plan.upstream_url,
+                                      "upstream project's website or home page is not defined"),
+                    license = spaced(&plan.license),
+                    source = linked(&plan.source, "source URL not provided or required"),
+                    sha = if plan.shasum.is_empty() {
+                        "SHA256 checksum not provided or required".to_string()
+                    } else {
+                        format!("`{}`", plan.shasum)
+                    },
+                    prefix = plan.prefix.display(),
+                    build_deps = listed(&plan.build_deps, "no build dependencies or undefined"),
+                    deps = listed(&plan.deps, "no runtime dependencies or undefined"),
+                    interpreters = listed(&plan.interpreters, "no interpreters or undefined"),
+                    cflags = or(&flags.cflags, "no CFLAGS"),
+                    cppflags = or(&flags.cppflags, "no CPPFLAGS"),
+                    cxxflags = or(&flags.cxxflags, "no CXXFLAGS"),
+                    ldflags = or(&flags.ldflags, "no LDFLAGS"),
+                    ld_run_path = or(&flags.ld_run_path, "no LD_RUN_PATH"),
+                    plan_source = plan_source.trim_end_matches('\n'));
+        fs::write(plan.prefix.join("MANIFEST"), manifest)?;
+        Ok(())
+    }
+
+    /// Write the signed `FILES` metadata file containing the blake2b checksum of every file in
+    /// the package. This must be the last metadata file rendered as it lists all the others.
+    pub fn render_files(&self,
+                        key: &SecretOriginSigningKey,
+                        cache_path: &Path,
+                        jobs: usize)
+                        -> Result<()> {
+        build_line(&self.plan.name,
+                   "Generating blake2b hashes of all files in the package");
+        let sums = hash_files(&self.plan.prefix, jobs)?;
+        fs::create_dir_all(cache_path)?;
+        let sums_path = cache_path.join(format!("{}_blake2bsums", self.plan.name));
+        fs::write(&sums_path, sums)?;
+
+        build_line(&self.plan.name, "Generating signed metadata FILES");
+        artifact::sign(&sums_path, &self.plan.prefix.join("FILES"), key)?;
+        Ok(())
+    }
+
+    fn prefixed(&self, dirs: &[String], flag: &str) -> Vec<String> {
+        dirs.iter()
+            .map(|dir| format!("{}{}/{}", flag, self.plan.prefix.display(), dir))
+            .collect()
+    }
+
+    fn pkg_config_path(&self) -> Vec<String> {
+        if self.plan.pconfig_dirs.is_empty() {
+            PKG_CONFIG_DIRS.iter()
+                           .map(|dir| self.plan.prefix.join(dir))
+                           .filter(|path| path.is_dir())
+                           .map(|path| path.display().to_string())
+                           .collect()
+        } else {
+            self.prefixed(&self.plan.pconfig_dirs, "")
+        }
+    }
+
+    /// The ports named by `$pkg_exposes`, looked up through `$pkg_exports` in `default.toml`.
+    fn exposed_ports(&self) -> Result<Vec<String>> {
+        if self.plan.exposes.is_empty() {
+            return Ok(Vec::new());
+        }
+        let default_toml = fs::read_to_string(self.plan.context.join("default.toml"))?;
+        let config: toml::Value = toml::from_str(&default_toml)?;
+        let mut ports = Vec::new();
+        for expose in &self.plan.exposes {
+            let key = self.plan
+                          .exports
+                          .get(expose)
+                          .ok_or_else(|| Error::ExposeWithoutExport(expose.clone()))?;
+            let value = key.split('.')
+                           .try_fold(&config, |value, part| value.get(part))
+                           .map(|value| {
+                               match value {
+                                   toml::Value::String(s) => s.clone(),
+                                   other => other.to_string(),
+                               }
+                           })
+                           .unwrap_or_default();
+            match value.parse::<u16>() {
+                Ok(port) if port > 0 => ports.push(port.to_string()),
+                _ => return Err(Error::InvalidExposedPort(key.clone(), value).into()),
+            }
+        }
+        Ok(ports)
+    }
+
+    fn idents(&self, paths: &[PathBuf]) -> Vec<String> {
+        paths.iter()
+             .map(|path| {
+                 path.strip_prefix(self.pkg_path)
+                     .unwrap_or(path)
+                     .to_string_lossy()
+                     .into_owned()
+             })
+             .collect()
+    }
+
+    /// Write `values` joined by `separator`. Nothing is written if there are no values.
+    fn write_joined(&self, file: &str, separator: &str, values: Vec<String>) -> Result<()> {
+        if values.is_empty() {
+            debug!("Would have rendered {}, but there was no data for it", file);
+            return Ok(());
+        }
+        self.write_lines(file, vec![values.join(separator)])
+    }
+
+    /// Write one value per line. Nothing is written if there are no values.
+    fn write_lines(&self, file: &str, values: Vec<String>) -> Result<()> {
+        if values.is_empty() {
+            debug!("Would have rendered {}, but there was no data for it", file);
+            return Ok(());
+        }
+        debug!("Rendering {} metadata file", file);
+        let mut content = values.join("\n");
+        content.push('\n');
+        fs::write(self.plan.prefix.join(file), content)?;
+        Ok(())
+    }
+}
+
+fn key_values(map: &std::collections::BTreeMap<String, String>) -> Vec<String> {
+    map.iter().map(|(k, v)| format!("{}={}", k, v)).collect()
+}
+
+/// Each item followed by a space, as `printf "%s " "${items[@]}"` would print them.
+fn spaced(items: &[String]) -> String { items.iter().map(|i| format!("{} ", i)).collect() }
+
+/// Hash every regular file under `prefix` using `jobs` threads, returning lines of
+/// `<blake2b>  <path>` sorted by path.
+fn hash_files(prefix: &Path, jobs: usize) -> Result<String> {
+    let mut files = Vec::new();
+    for entry in WalkDir::new(prefix) {
+        let entry = entry?;
+        if entry.file_type().is_file() {
+            files.push(entry.into_path());
+        }
+    }
+    files.sort();
+
+    let queue = Arc::new(Mutex::new(files.into_iter().enumerate()));
+    let results = Arc::new(Mutex::new(Vec::new()));
+    let workers =
+        (0..jobs.max(1)).map(|_| {
+                            let queue = Arc::clone(&queue);
+                            let results = Arc::clone(&results);
+                            thread::spawn(move || -> Result<()> {
+                                loop {
+                                    let next = queue.lock().expect("hash queue poisoned").next();
+                                    let (index, path) = match next {
+                                        Some(next) => next,
+                                        None => return Ok(()),
+                                    };
+                                    let hash = Blake2bHash::from_file(&path)?;
+                                    results.lock()
+                                           .expect("hash results poisoned")
+                                           .push((index, format!("{}  {}", hash, path.display())));
+                                }
+                            })
+                        })
+                        .collect::<Vec<_>>();
+    for worker in workers {
+        worker.join().expect("hash worker panicked")?;
+    }
+
+    let mut results = Arc::try_unwrap(results).expect("hash workers finished")
+                                              .into_inner()
+                                              .expect("hash results poisoned");
+    results.sort_by_key(|(index, _)| *index);
+    Ok(results.into_iter().map(|(_, line)| line + "\n").collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hcore::package::target;
+    use tempfile::TempDir;
+
+    fn plan(prefix: &Path) -> Plan {
+        Plan { name: "nginx".to_string(),
+               origin: "core".to_string(),
+               version: "1.17.4".to_string(),
+               release: "20200101000000".to_string(),
+               prefix: prefix.to_path_buf(),
+               lib_dirs: vec!["lib".to_string()],
+               bin_dirs: vec!["bin".to_string(), "sbin".to_string()],
+               include_dirs: vec!["include".to_string()],
+               ..Default::default() }
+    }
+
+    #[test]
+    fn render_link_metadata() {
+        let prefix = TempDir::new().unwrap();
+        let pkgs = TempDir::new().unwrap();
+        let plan = plan(prefix.path());
+        let deps = Dependencies { deps: vec![pkgs.path().join("core/pcre/8.42/1")],
+                                  ..Default::default() };
+        let renderer = Renderer { plan:     &plan,
+                                  target:   target::X86_64_LINUX,
+                                  deps:     &deps,
+                                  pkg_path: pkgs.path(), };
+        renderer.render().unwrap();
+
+        let read = |file: &str| fs::read_to_string(prefix.path().join(file)).unwrap();
+        let p = prefix.path().display();
+        assert_eq!(read("PATH"), format!("{}/bin:{}/sbin\n", p, p));
+        assert_eq!(read("LDFLAGS"), format!("-L{}/lib\n", p));
+        assert_eq!(read("CFLAGS"), format!("-I{}/include\n", p));
+        assert_eq!(read("DEPS"), "core/pcre/8.42/1\n");
+        assert_eq!(read("IDENT"), "core/nginx/1.17.4/20200101000000\n");
+        assert_eq!(read("TARGET"), "x86_64-linux\n");
+        assert!(!prefix.path().join("TDEPS").exists());
+        assert!(!prefix.path().join("SVC_USER").exists());
+    }
+
+    #[test]
+    fn runtime_path_includes_dependency_paths() {
+        let prefix = TempDir::new().unwrap();
+        let pkgs = TempDir::new().unwrap();
+        let pcre = pkgs.path().join("core/pcre/8.42/1");
+        fs::create_dir_all(&pcre).unwrap();
+        fs::write(pcre.join("PATH"),
+                  format!("{}/bin:/usr/bin\n", pcre.display())).unwrap();
+        let plan = plan(prefix.path());
+        let deps = Dependencies { deps: vec![pcre.clone()],
+                                  tdeps: vec![pcre.clone()],
+                                  ..Default::default() };
+        let renderer = Renderer { plan:     &plan,
+                                  target:   target::X86_64_LINUX,
+                                  deps:     &deps,
+                                  pkg_path: pkgs.path(), };
+        assert_eq!(renderer.runtime_path().unwrap(),
+                   format!("{}/bin:{}/sbin:{}/bin",
+                           prefix.path().display(),
+                           prefix.path().display(),
+                           pcre.display()));
+    }
+
+    #[test]
+    fn exposes_are_read_from_default_toml() {
+        let prefix = TempDir::new().unwrap();
+        let context = TempDir::new().unwrap();
+        fs::write(context.path().join("default.toml"), "[http]\nport = 8080\n").unwrap();
+        let mut plan = plan(prefix.path());
+        plan.context = context.path().to_path_buf();
+        plan.exports
+            .insert("port".to_string(), "http.port".to_string());
+        plan.exposes = vec!["port".to_string()];
+        let deps = Dependencies::default();
+        let renderer = Renderer { plan:     &plan,
+                                  target:   target::X86_64_LINUX,
+                                  deps:     &deps,
+                                  pkg_path: Path::new("/hab/pkgs"), };
+        assert_eq!(renderer.exposed_ports().unwrap(), vec!["8080".to_string()]);
+
+        plan.exposes = vec!["missing".to_string()];
+        let renderer = Renderer { plan:     &plan,
+                                  target:   target::X86_64_LINUX,
+                                  deps:     &deps,
+                                  pkg_path: Path::new("/hab/pkgs"), };
+        assert!(renderer.exposed_ports().is_err());
+    }
+}
@@ -0,0 +1,89 @@
+use std::fmt;
+
+/// The build phases whose callbacks a Plan may override, in the order in which they are run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Phase {
+    Begin,
+    Before,
+    Download,
+    Verify,
+    Clean,
+    Unpack,
+    Prepare,
+    Build,
+    Check,
+    Install,
+    BuildConfig,
+    BuildService,
+    Strip,
+    After,
+    End,
+}
+
+impl Phase {
+    /// The phases which build and install the software, run once the build environment is set.
+    pub const BUILD: &'static [Phase] = &[Phase::Prepare,
+                                          Phase::Build,
+                                          Phase::Check,
+                                          Phase::Install,
+                                          Phase::BuildConfig,
+                                          Phase::BuildService,
+                                          Phase::Strip,
+                                          Phase::After];
+    /// The phases which download and unpack the source of a Plan, run after dependencies are
+    /// resolved and before the build environment is set.
+    pub const SOURCE: &'static [Phase] = &[Phase::Before,
+                                           Phase::Download,
+                                           Phase::Verify,
+                                           Phase::Clean,
+                                           Phase::Unpack];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Phase::Begin => "begin",
+            Phase::Before => "before",
+            Phase::Download => "download",
+            Phase::Verify => "verify",
+            Phase::Clean => "clean",
+            Phase::Unpack => "unpack",
+            Phase::Prepare => "prepare",
+            Phase::Build => "build",
+            Phase::Check => "check",
+            Phase::Install => "install",
+            Phase::BuildConfig => "build_config",
+            Phase::BuildService => "build_service",
+            Phase::Strip => "strip",
+            Phase::After => "after",
+            Phase::End => "end",
+        }
+    }
+
+    /// The shell function which runs this phase. Some phases are run through a wrapper which
+    /// changes into `$SRC_PATH` before calling the Plan's `do_<phase>` callback.
+    pub fn entrypoint(self) -> String {
+        match self {
+            Phase::Prepare | Phase::Build | Phase::Check | Phase::Install => {
+                format!("do_{}_wrapper", self.name())
+            }
+            _ => format!("do_{}", self.name()),
+        }
+    }
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "The '{}' phase (do_{})", self.name(), self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entrypoints() {
+        assert_eq!(Phase::Begin.entrypoint(), "do_begin");
+        assert_eq!(Phase::Build.entrypoint(), "do_build_wrapper");
+        assert_eq!(Phase::BuildConfig.entrypoint(), "do_build_config");
+    }
+}
@@ -0,0 +1,247 @@
+//! Locating a Plan and reading its metadata back out of the build shell.
+use crate::{error::{Error,
+                    Result},
+            hcore::package::PackageTarget,
+            shell::Vars};
+use std::{collections::BTreeMap,
+          path::{Path,
+                 PathBuf}};
+
+pub const PLAN_FILE: &str = "plan.sh";
+
+/// The placeholder version of a Plan which computes its version with a `pkg_version()`
+/// function and has not yet called `update_pkg_version()`.
+pub const VERSION_UNSET: &str = "__pkg__version__unset__";
+
+/// The Plan variables which are read back from the build shell.
+pub const PLAN_VARS: &[&str] = &["pkg_name",
+                                 "pkg_origin",
+                                 "pkg_version",
+                                 "pkg_release",
+                                 "pkg_type",
+                                 "pkg_maintainer",
+                                 "pkg_description",
+                                 "pkg_upstream_url",
+                                 "pkg_license",
+                                 "pkg_source",
+                                 "pkg_shasum",
+                                 "pkg_filename",
+                                 "pkg_dirname",
+                                 "pkg_prefix",
+                                 "pkg_deps",
+                                 "pkg_build_deps",
+                                 "pkg_scaffolding",
+                                 "pkg_lib_dirs",
+                                 "pkg_bin_dirs",
+                                 "pkg_include_dirs",
+                                 "pkg_pconfig_dirs",
+                                 "pkg_interpreters",
+                                 "pkg_exports",
+                                 "pkg_exposes",
+                                 "pkg_binds",
+                                 "pkg_binds_optional",
+                                 "pkg_svc_run",
+                                 "pkg_svc_user",
+                                 "pkg_svc_group",
+                                 "pkg_shutdown_signal",
+                                 "pkg_shutdown_timeout_sec"];
+
+/// Find the Plan file for `target` relative to `context`. There are 4 candidate locations:
+///
+/// * `./plan.sh`
+/// * `./habitat/plan.sh`
+/// * `./$pkg_target/plan.sh`
+/// * `./habitat/$pkg_target/plan.sh`
+///
+/// A Plan found in a target directory takes precedence over one outside of it, which is reported
+/// with a warning. Finding more than one Plan at the same level is an error.
+pub fn find(context: &Path, target: PackageTarget) -> Result<PathBuf> {
+    let target_candidates = vec![context.join(target.as_ref()).join(PLAN_FILE),
+                                 context.join("habitat")
+                                        .join(target.as_ref())
+                                        .join(PLAN_FILE),];
+    let candidates = vec![context.join(PLAN_FILE),
+                          context.join("habitat").join(PLAN_FILE),];
+
+    let target_paths: Vec<&PathBuf> = target_candidates.iter().filter(|p| p.is_file()).collect();
+    let paths: Vec<&PathBuf> = candidates.iter().filter(|p| p.is_file()).collect();
+
+    if !target_paths.is_empty() && !paths.is_empty() {
+        warn!("There is a plan.sh inside {} and outside as well. Using the plan in {}.",
+              target, target);
+    }
+    let found = if target_paths.is_empty() {
+        paths
+    } else {
+        target_paths
+    };
+    match found.as_slice() {
+        [path] => Ok((*path).clone()),
+        [] => {
+            Err(Error::PlanNotFound(join_paths(candidates.iter()
+                                                         .chain(target_candidates.iter()))).into())
+        }
+        _ => Err(Error::MultiplePlans(join_paths(found.into_iter())).into()),
+    }
+}
+
+fn join_paths<'a>(paths: impl Iterator<Item = &'a PathBuf>) -> String {
+    paths.map(|p| p.display().to_string())
+         .collect::<Vec<_>>()
+         .join(",")
+}
+
+/// Ensure that a package name or origin only contains valid characters.
+pub fn validate_ident_part(field: &'static str, value: &str) -> Result<()> {
+    if value.is_empty() {
+        return Err(Error::MissingPlanValue(field).into());
+    }
+    if !value.chars()
+             .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        return Err(Error::InvalidPlanValue(field, value.to_string()).into());
+    }
+    Ok(())
+}
+
+/// The metadata of a loaded Plan.
+#[derive(Debug, Default)]
+pub struct Plan {
+    /// The directory containing the `plan.sh`, `$PLAN_CONTEXT` in the shell.
+    pub context:          PathBuf,
+    pub name:             String,
+    pub origin:           String,
+    pub version:          String,
+    pub release:          String,
+    pub maintainer:       String,
+    pub description:      String,
+    pub upstream_url:     String,
+    pub license:          Vec<String>,
+    pub source:           String,
+    pub shasum:           String,
+    pub prefix:           PathBuf,
+    pub deps:             Vec<String>,
+    pub build_deps:       Vec<String>,
+    pub scaffolding:      Option<String>,
+    pub lib_dirs:         Vec<String>,
+    pub bin_dirs:         Vec<String>,
+    pub include_dirs:     Vec<String>,
+    pub pconfig_dirs:     Vec<String>,
+    pub interpreters:     Vec<String>,
+    pub exports:          BTreeMap<String, String>,
+    pub exposes:          Vec<String>,
+    pub binds:            BTreeMap<String, String>,
+    pub binds_optional:   BTreeMap<String, String>,
+    pub svc_run:          String,
+    pub svc_user:         String,
+    pub svc_group:        String,
+    pub shutdown_signal:  Option<String>,
+    pub shutdown_timeout: Option<String>,
+}
+
+impl Plan {
+    pub fn from_vars(context: &Path, vars: &Vars) -> Result<Self> {
+        let name = vars.string("pkg_name");
+        let origin = vars.string("pkg_origin");
+        validate_ident_part("pkg_name", &name)?;
+        validate_ident_part("pkg_origin", &origin)?;
+        if vars.scalar("pkg_type") == Some("composite") {
+            return Err(Error::CompositePlan.into());
+        }
+        let optional = |name: &str| {
+            vars.scalar(name)
+                .filter(|v| !v.is_empty())
+                .map(String::from)
+        };
+
+        Ok(Plan { context: context.to_path_buf(),
+                  name,
+                  origin,
+                  version: vars.string("pkg_version"),
+                  release: vars.string("pkg_release"),
+                  maintainer: vars.string("pkg_maintainer"),
+                  description: vars.string("pkg_description"),
+                  upstream_url: vars.string("pkg_upstream_url"),
+                  license: vars.array("pkg_license"),
+                  source: vars.string("pkg_source"),
+                  shasum: vars.string("pkg_shasum"),
+                  prefix: PathBuf::from(vars.string("pkg_prefix")),
+                  deps: vars.array("pkg_deps"),
+                  build_deps: vars.array("pkg_build_deps"),
+                  scaffolding: optional("pkg_scaffolding"),
+                  lib_dirs: vars.array("pkg_lib_dirs"),
+                  bin_dirs: vars.array("pkg_bin_dirs"),
+                  include_dirs: vars.array("pkg_include_dirs"),
+                  pconfig_dirs: vars.array("pkg_pconfig_dirs"),
+                  interpreters: vars.array("pkg_interpreters"),
+                  exports: vars.map("pkg_exports"),
+                  exposes: vars.array("pkg_exposes"),
+                  binds: vars.map("pkg_binds"),
+                  binds_optional: vars.map("pkg_binds_optional"),
+                  svc_run: vars.string("pkg_svc_run"),
+                  svc_user: vars.string("pkg_svc_user"),
+                  svc_group: vars.string("pkg_svc_group"),
+                  shutdown_signal: optional("pkg_shutdown_signal"),
+                  shutdown_timeout: optional("pkg_shutdown_timeout_sec") })
+    }
+
+    /// The fully qualified identifier of the package being built.
+    pub fn ident(&self) -> String {
+        format!("{}/{}/{}/{}",
+                self.origin, self.name, self.version, self.release)
+    }
+
+    /// The Plan file itself.
+    pub fn path(&self) -> PathBuf { self.context.join(PLAN_FILE) }
+
+    /// A package is a service if it has a run hook or sets `$pkg_svc_run`.
+    pub fn is_service(&self) -> bool {
+        self.context.join("hooks").join("run").is_file() || !self.svc_run.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hcore::package::target;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn find_plan_in_habitat_dir() {
+        let context = TempDir::new().unwrap();
+        fs::create_dir(context.path().join("habitat")).unwrap();
+        fs::write(context.path().join("habitat").join(PLAN_FILE), "").unwrap();
+        assert_eq!(find(context.path(), target::X86_64_LINUX).unwrap(),
+                   context.path().join("habitat").join(PLAN_FILE));
+    }
+
+    #[test]
+    fn target_plan_takes_precedence() {
+        let context = TempDir::new().unwrap();
+        let target_dir = context.path().join(target::X86_64_LINUX.as_ref());
+        fs::create_dir(&target_dir).unwrap();
+        fs::write(target_dir.join(PLAN_FILE), "").unwrap();
+        fs::write(context.path().join(PLAN_FILE), "").unwrap();
+        assert_eq!(find(context.path(), target::X86_64_LINUX).unwrap(),
+                   target_dir.join(PLAN_FILE));
+    }
+
+    #[test]
+    fn multiple_or_missing_plans_are_errors() {
+        let context = TempDir::new().unwrap();
+        assert!(find(context.path(), target::X86_64_LINUX).is_err());
+
+        fs::create_dir(context.path().join("habitat")).unwrap();
+        fs::write(context.path().join("habitat").join(PLAN_FILE), "").unwrap();
+        fs::write(context.path().join(PLAN_FILE), "").unwrap();
+        assert!(find(context.path(), target::X86_64_LINUX).is_err());
+    }
+
+    #[test]
+    fn ident_parts_are_validated() {
+        assert!(validate_ident_part("pkg_name", "my-app_2").is_ok());
+        assert!(validate_ident_part("pkg_name", "").is_err());
+        assert!(validate_ident_part("pkg_origin", "core/x").is_err());
+    }
+}
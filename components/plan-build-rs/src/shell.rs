@@ -0,0 +1,275 @@
+//! The compatibility mode which runs the callbacks of an existing `plan.sh`.
+//!
+//! A single Bash process lives for the duration of the build so that any state a Plan sets up in
+//! one phase is visible in the next, exactly as with `hab-plan-build`. Commands are written to the
+//! shell's standard input and each one is followed by a marker which the shell echoes once the
+//! command has succeeded. Commands run with `set -e`, so a failing command terminates the shell
+//! and its exit code is reported as the failure of that step. All other output is forwarded as it
+//! is produced.
+use crate::error::{Error,
+                   Result};
+use std::{collections::{BTreeMap,
+                        HashMap},
+          fs,
+          io::{self,
+               BufRead,
+               BufReader,
+               Write},
+          path::Path,
+          process::{Child,
+                    ChildStdin,
+                    ChildStdout,
+                    Command,
+                    Stdio}};
+use tempfile::TempDir;
+
+/// The Bash library providing the default build phase callbacks and helper functions.
+const COMPAT_LIB: &str = include_str!("compat.bash");
+const DONE_MARKER: &str = "__HAB_PLAN_BUILD_STEP_DONE__";
+
+/// The value of a shell variable.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Scalar(String),
+    Array(Vec<String>),
+    Map(BTreeMap<String, String>),
+}
+
+/// A set of shell variables, as written by `__hab_dump_vars`.
+#[derive(Debug, Default)]
+pub struct Vars(HashMap<String, Value>);
+
+impl Vars {
+    fn parse(data: &[u8]) -> Result<Self> {
+        let mut vars = HashMap::new();
+        if data.is_empty() {
+            return Ok(Vars(vars));
+        }
+        let data = if data.last() == Some(&0) {
+            &data[..data.len() - 1]
+        } else {
+            data
+        };
+        let mut fields = data.split(|b| *b == 0)
+                             .map(|f| String::from_utf8_lossy(f).into_owned());
+        while let Some(name) = fields.next() {
+            let value = match next_field(&mut fields)?.as_str() {
+                "s" => Value::Scalar(next_field(&mut fields)?),
+                "a" => {
+                    let len = next_len(&mut fields)?;
+                    let values = (0..len).map(|_| next_field(&mut fields))
+                                         .collect::<Result<_>>()?;
+                    Value::Array(values)
+                }
+                "A" => {
+                    let len = next_len(&mut fields)?;
+                    let mut map = BTreeMap::new();
+                    for _ in 0..len {
+                        map.insert(next_field(&mut fields)?, next_field(&mut fields)?);
+                    }
+                    Value::Map(map)
+                }
+                kind => {
+                    return Err(Error::ShellProtocol(format!("unknown kind '{}' of variable {}",
+                                                            kind, name)).into())
+                }
+            };
+            vars.insert(name, value);
+        }
+        Ok(Vars(vars))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Value> { self.0.get(name) }
+
+    /// The value of a scalar, or the first element of an array, as Bash would expand `$name`.
+    pub fn scalar(&self, name: &str) -> Option<&str> {
+        match self.0.get(name) {
+            Some(Value::Scalar(s)) => Some(s),
+            Some(Value::Array(a)) => a.first().map(String::as_str),
+            _ => None,
+        }
+    }
+
+    pub fn string(&self, name: &str) -> String { self.scalar(name).unwrap_or_default().to_string() }
+
+    /// The elements of an array, as Bash would expand `"${name[@]}"`.
+    pub fn array(&self, name: &str) -> Vec<String> {
+        match self.0.get(name) {
+            Some(Value::Scalar(s)) if !s.is_empty() => vec![s.clone()],
+            Some(Value::Array(a)) => a.clone(),
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn map(&self, name: &str) -> BTreeMap<String, String> {
+        match self.0.get(name) {
+            Some(Value::Map(m)) => m.clone(),
+            _ => BTreeMap::new(),
+        }
+    }
+}
+
+fn next_field(fields: &mut impl Iterator<Item = String>) -> Result<String> {
+    fields.next()
+          .ok_or_else(|| Error::ShellProtocol("unexpected end of variable data".to_string()).into())
+}
+
+fn next_len(fields: &mut impl Iterator<Item = String>) -> Result<usize> {
+    let len = next_field(fields)?;
+    len.parse()
+       .map_err(|_| Error::ShellProtocol(format!("invalid array length '{}'", len)).into())
+}
+
+/// Quote a value so that Bash reads it back verbatim.
+pub fn quote(value: &str) -> String { format!("'{}'", value.replace('\'', "'\\''")) }
+
+pub struct Shell {
+    child:   Child,
+    stdin:   ChildStdin,
+    stdout:  BufReader<ChildStdout>,
+    workdir: TempDir,
+}
+
+impl Shell {
+    /// Start a Bash process in `cwd` and load the compatibility library from `lib_dir`. The
+    /// given variables are set before the library is loaded.
+    pub fn start(cwd: &Path, lib_dir: &Path, vars: &[(&str, String)]) -> Result<Self> {
+        let workdir = TempDir::new()?;
+        let compat_lib = workdir.path().join("compat.bash");
+        fs::write(&compat_lib, COMPAT_LIB)?;
+
+        let mut child = Command::new("bash").arg("--noprofile")
+                                            .arg("--norc")
+                                            .current_dir(cwd)
+                                            .stdin(Stdio::piped())
+                                            .stdout(Stdio::piped())
+                                            .spawn()
+                                            .map_err(|_| Error::MissingSystemCommand("bash"))?;
+        let stdin = child.stdin.take().expect("bash stdin is piped");
+        let stdout = BufReader::new(child.stdout.take().expect("bash stdout is piped"));
+        let mut shell = Shell { child,
+                                stdin,
+                                stdout,
+                                workdir };
+
+        let mut script = format!("__hab_lib_dir={}\n", quote(&lib_dir.to_string_lossy()));
+        for (name, value) in vars {
+            script.push_str(&format!("{}={}\n", name, quote(value)));
+        }
+        script.push_str(&format!("source {}", quote(&compat_lib.to_string_lossy())));
+        shell.run("Loading the plan-build library", &script)?;
+        Ok(shell)
+    }
+
+    /// Run `script`, returning an error naming `step` if any command in it fails. The script
+    /// does not have access to standard input as that is used to drive the shell.
+    pub fn run(&mut self, step: &str, script: &str) -> Result<()> {
+        debug!("{}: {}", step, script);
+        // A failed write means the shell has already exited, which is reported below.
+        let _ = writeln!(self.stdin,
+                         "set -e\n{{\n{}\n}} < /dev/null\nset +e\necho {}",
+                         script, DONE_MARKER).and_then(|_| self.stdin.flush());
+
+        let stdout = io::stdout();
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            if self.stdout.read_until(b'\n', &mut line)? == 0 {
+                let status = self.child.wait()?;
+                return Err(Error::StepFailed(step.to_string(), status.code().unwrap_or(1)).into());
+            }
+            let content = if line.ends_with(b"\n") {
+                &line[..line.len() - 1]
+            } else {
+                &line[..]
+            };
+            if content.ends_with(DONE_MARKER.as_bytes()) {
+                let output = &content[..content.len() - DONE_MARKER.len()];
+                if !output.is_empty() {
+                    let mut out = stdout.lock();
+                    out.write_all(output)?;
+                    out.write_all(b"\n")?;
+                }
+                return Ok(());
+            }
+            let mut out = stdout.lock();
+            out.write_all(&line)?;
+            out.flush()?;
+        }
+    }
+
+    pub fn set(&mut self, name: &str, value: &str) -> Result<()> {
+        self.run(&format!("Setting {}", name),
+                 &format!("{}={}", name, quote(value)))
+    }
+
+    pub fn export(&mut self, name: &str, value: &str) -> Result<()> {
+        self.run(&format!("Setting {}", name),
+                 &format!("export {}={}", name, quote(value)))
+    }
+
+    pub fn set_array<S: AsRef<str>>(&mut self, name: &str, values: &[S]) -> Result<()> {
+        let values = values.iter()
+                           .map(|v| quote(v.as_ref()))
+                           .collect::<Vec<_>>()
+                           .join(" ");
+        self.run(&format!("Setting {}", name),
+                 &format!("{}=({})", name, values))
+    }
+
+    /// Read the given variables from the shell. Unset variables are omitted from the result.
+    pub fn vars(&mut self, names: &[&str]) -> Result<Vars> {
+        let dump = self.workdir.path().join("vars");
+        self.run("Reading Plan variables",
+                 &format!("__hab_dump_vars {} {}",
+                          quote(&dump.to_string_lossy()),
+                          names.join(" ")))?;
+        Vars::parse(&fs::read(&dump)?)
+    }
+
+    pub fn has_function(&mut self, name: &str) -> Result<bool> {
+        self.run(&format!("Looking up function {}", name),
+                 &format!("__hab_function_defined {}", quote(name)))?;
+        Ok(self.vars(&["__hab_result"])?.scalar("__hab_result") == Some("1"))
+    }
+}
+
+impl Drop for Shell {
+    fn drop(&mut self) {
+        let _ = writeln!(self.stdin, "exit 0");
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quote_values() {
+        assert_eq!(quote("plain"), "'plain'");
+        assert_eq!(quote("it's $HOME"), "'it'\\''s $HOME'");
+    }
+
+    #[test]
+    fn parse_vars() {
+        let data = b"pkg_name\0s\0nginx\0pkg_deps\0a\x002\0core/glibc\0core/pcre\0\
+                     pkg_exports\0A\x001\0port\0http.port\0pkg_bin_dirs\0a\x000\0";
+        let vars = Vars::parse(data).unwrap();
+        assert_eq!(vars.scalar("pkg_name"), Some("nginx"));
+        assert_eq!(vars.array("pkg_deps"),
+                   vec!["core/glibc".to_string(), "core/pcre".to_string()]);
+        assert_eq!(vars.scalar("pkg_deps"), Some("core/glibc"));
+        assert_eq!(vars.map("pkg_exports").get("port"),
+                   Some(&"http.port".to_string()));
+        assert!(vars.array("pkg_bin_dirs").is_empty());
+        assert!(vars.get("pkg_svc_run").is_none());
+        assert_eq!(vars.array("pkg_name"), vec!["nginx".to_string()]);
+    }
+
+    #[test]
+    fn parse_truncated_vars() {
+        assert!(Vars::parse(b"pkg_deps\0a\x002\0core/glibc\0").is_err());
+        assert!(Vars::parse(b"pkg_deps\0x\0").is_err());
+    }
+}
@@ -0,0 +1,66 @@
+#![cfg(not(windows))]
+
+//! Builds a Plan with provenance attestation enabled and checks the attestation that is written
+//! next to the artifact.
+use habitat_core::crypto::{self,
+                           keys::KeyCache,
+                           provenance};
+use habitat_plan_build::LIB_DIR_ENVVAR;
+use std::{env,
+          fs,
+          path::{Path,
+                 PathBuf}};
+use tempfile::TempDir;
+
+const PLAN: &str = r#"pkg_name=provenance
+pkg_origin=habitat-testing
+pkg_version=0.0.0
+
+do_build() { :; }
+do_install() { :; }
+"#;
+
+/// The value of `key` in a `last_build.env` build report.
+fn report_value(report: &Path, key: &str) -> String {
+    let prefix = format!("{}=", key);
+    fs::read_to_string(report).unwrap()
+                              .lines()
+                              .find(|line| line.starts_with(&prefix))
+                              .map(|line| line[prefix.len()..].to_string())
+                              .unwrap_or_else(|| panic!("{} not found in build report", key))
+}
+
+#[test]
+fn builds_with_provenance_write_an_attestation() {
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path().join("hab");
+    let key_cache_path = root.join("cache").join("keys");
+    let plan_context = tmp.path().join("plan");
+    let output_path = tmp.path().join("results");
+
+    fs::create_dir_all(&key_cache_path).unwrap();
+    fs::create_dir_all(&plan_context).unwrap();
+    fs::write(plan_context.join("plan.sh"), PLAN).unwrap();
+    crypto::init().unwrap();
+    let cache = KeyCache::new(&key_cache_path);
+    cache.new_signing_pair(&"habitat-testing".parse().unwrap())
+         .unwrap();
+
+    let lib_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../plan-build/bin");
+    env::set_var(LIB_DIR_ENVVAR, &lib_dir);
+    env::set_var("HAB_ROOT_PATH", &root);
+    env::set_var("HAB_CACHE_KEY_PATH", &key_cache_path);
+    env::set_var("HAB_OUTPUT_PATH", &output_path);
+    env::set_var("HAB_PROVENANCE", "true");
+    env::set_var("HAB_PROVENANCE_BUILDER_ID", "https://ci.example.com");
+    env::set_var("NO_INSTALL_DEPS", "true");
+    env::remove_var("HAB_ORIGIN");
+
+    habitat_plan_build::build(&plan_context).unwrap();
+
+    let artifact_name = report_value(&output_path.join("last_build.env"), "pkg_artifact");
+    let artifact = root.join("cache").join("artifacts").join(&artifact_name);
+    let (_, statement) = provenance::verify(&artifact, &cache).unwrap();
+    assert_eq!(statement.predicate.builder.id, "https://ci.example.com");
+    assert!(provenance::provenance_path(&output_path.join(&artifact_name)).is_file());
+}
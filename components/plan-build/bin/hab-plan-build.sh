@@ -361,14 +361,30 @@ FALLBACK_CHANNEL="stable"
 INITIAL_PATH="$PATH"
 # The value of `pwd` on initial start of this program
 INITIAL_PWD="$(pwd)"
+# The compression of harts: `xz`, which every Habitat release can install, or
+# `zstd`, which installs faster but needs a Habitat release reading `HART-2`
+# artifacts
+: "${HAB_HART_COMPRESSION:=xz}"
 # The compression level to use when compression harts (0..9)
 : "${HAB_HART_COMPRESSION_LEVEL:=6}"
+# Whether to attest to the provenance of the artifact (`true` or `false`),
+# writing an in-toto statement signed with the origin key next to it
+: "${HAB_PROVENANCE:=false}"
+# The identity of the builder named by the provenance attestation
+: "${HAB_PROVENANCE_BUILDER_ID:=https://habitat.sh/builders/local}"
 # The full target tuple this plan will be built for
 pkg_target='@@pkg_target@@'
 # The package's origin (i.e. acme)
 pkg_origin=""
-# Each release is a timestamp - `YYYYMMDDhhmmss`
-pkg_release=$(date -u +%Y%m%d%H%M%S)
+# Each release is a timestamp - `YYYYMMDDhhmmss`. When `$SOURCE_DATE_EPOCH` is
+# set the build is reproducible: the release and all timestamps in the artifact
+# are derived from it, so building the same plan with the same dependencies
+# twice yields identical artifacts.
+if [[ -n "${SOURCE_DATE_EPOCH:-}" ]]; then
+  pkg_release=$(date -u -d "@$SOURCE_DATE_EPOCH" +%Y%m%d%H%M%S)
+else
+  pkg_release=$(date -u +%Y%m%d%H%M%S)
+fi
 # The default build deps setting - an empty array
 pkg_build_deps=()
 # The default runtime deps setting - an empty array
@@ -632,6 +648,22 @@ _find_system_commands() {
   fi
   debug "Setting _xz_cmd=$_xz_cmd"
 
+  case "$HAB_HART_COMPRESSION" in
+    xz)
+      ;;
+    zstd)
+      if exists zstd; then
+        _zstd_cmd="$(command -v zstd) --quiet"
+      else
+        exit_with "We require zstd to compress artifacts with zstd; aborting" 1
+      fi
+      debug "Setting _zstd_cmd=$_zstd_cmd"
+      ;;
+    *)
+      exit_with "HAB_HART_COMPRESSION must be xz or zstd, not '$HAB_HART_COMPRESSION'; aborting" 1
+      ;;
+  esac
+
   if exists hab; then
     _hab_cmd=$(command -v hab)
   else
@@ -1987,6 +2019,7 @@ _build_metadata() {
   _render_metadata_RUNTIME_ENVIRONMENT_PROVENANCE
   _render_metadata_SHUTDOWN_SIGNAL
   _render_metadata_SHUTDOWN_TIMEOUT
+  _render_metadata_SECCOMP_PROFILE
 
   # Only generate `SVC_USER` & `SVC_GROUP` files if this package is a service.
   # We determine this by checking if there is a `hooks/run` script and/or
@@ -2268,14 +2301,58 @@ _generate_artifact() {
   build_line "Generating package artifact"
   local tarf
   tarf="$(dirname "$pkg_artifact")/.$(basename "${pkg_artifact/%.${_artifact_ext}/.tar}")"
-  local xzf="${tarf}.xz"
+  local compressedf threads
+  if [[ "$HAB_HART_COMPRESSION" == "zstd" ]]; then
+    compressedf="${tarf}.zst"
+  else
+    compressedf="${tarf}.xz"
+  fi
 
   mkdir -pv "$(dirname "$pkg_artifact")"
-  rm -fv "$tarf" "$xzf" "$pkg_artifact"
-  $_tar_cmd -cf "$tarf" "$pkg_prefix"
-  $_xz_cmd --compress -${HAB_HART_COMPRESSION_LEVEL} --threads=0 "$tarf"
-  $HAB_BIN pkg sign --origin $pkg_origin "$xzf" "$pkg_artifact"
-  rm -f "$tarf" "$xzf"
+  rm -fv "$tarf" "$compressedf" "$pkg_artifact"
+  if [[ -n "${SOURCE_DATE_EPOCH:-}" ]]; then
+    build_line "SOURCE_DATE_EPOCH set: generating a reproducible artifact"
+    # Sort the entries, drop ownership and access times and clamp modification
+    # times. Multi-threaded compression depends on the number of CPUs, so a
+    # single thread is used.
+    $_tar_cmd --sort=name \
+      --mtime="@$SOURCE_DATE_EPOCH" \
+      --owner=0 --group=0 --numeric-owner \
+      --pax-option=exthdr.name=%d/PaxHeaders/%f,delete=atime,delete=ctime \
+      -cf "$tarf" "$pkg_prefix"
+    threads=1
+  else
+    $_tar_cmd -cf "$tarf" "$pkg_prefix"
+    threads=0
+  fi
+  # `hab pkg sign` writes a `HART-2` artifact for a tarball ending in `.zst`
+  if [[ "$HAB_HART_COMPRESSION" == "zstd" ]]; then
+    $_zstd_cmd -${HAB_HART_COMPRESSION_LEVEL} --threads=$threads --rm "$tarf"
+  else
+    $_xz_cmd --compress -${HAB_HART_COMPRESSION_LEVEL} --threads=$threads "$tarf"
+  fi
+  $HAB_BIN pkg sign --origin $pkg_origin "$compressedf" "$pkg_artifact"
+  rm -f "$tarf" "$compressedf"
+}
+
+# **Internal** Attest to the provenance of the package artifact with
+# `hab pkg attest`, when `$HAB_PROVENANCE` is `true`. The revision of the Plan's
+# git checkout, if it is in one, is recorded as the source of the build.
+_generate_provenance() {
+  if [[ "$HAB_PROVENANCE" != "true" ]]; then
+    return 0
+  fi
+  build_line "Generating provenance attestation"
+  local revision remote
+  local -a source_args=()
+  if revision="$(git -C "$PLAN_CONTEXT" rev-parse HEAD 2> /dev/null)"; then
+    source_args+=(--source-revision "$revision")
+    if remote="$(git -C "$PLAN_CONTEXT" config --get remote.origin.url 2> /dev/null)"; then
+      source_args+=(--source-uri "$remote")
+    fi
+  fi
+  $HAB_BIN pkg attest --builder-id "$HAB_PROVENANCE_BUILDER_ID" \
+    ${source_args[@]+"${source_args[@]}"} "$pkg_artifact"
 }
 
 _prepare_build_outputs() {
@@ -2291,6 +2368,10 @@ _prepare_build_outputs() {
   chown "$plan_owner" "$pkg_output_path" || true
   cp -v "$pkg_artifact" "$pkg_output_path"/
   chown "$plan_owner" "$pkg_output_path/$(basename "$pkg_artifact")" || true
+  if [[ -f "${pkg_artifact}.intoto.json" ]]; then
+    cp -v "${pkg_artifact}.intoto.json" "$pkg_output_path"/
+    chown "$plan_owner" "$pkg_output_path/$(basename "$pkg_artifact").intoto.json" || true
+  fi
 
   # At this point, we know it built successfully, so delete the pre_build file
   pre_build_file="$pkg_output_path/pre_build.env"
@@ -2719,6 +2800,9 @@ _build_manifest
 # Generate the artifact and write to artifact cache
 _generate_artifact
 
+# Attest to the provenance of the artifact, if asked to
+_generate_provenance
+
 # Copy produced artifact to a local relative directory
 _prepare_build_outputs
 
@@ -89,7 +89,7 @@ _render_metadata_FILES() {
 
   # shellcheck disable=2154
   find "$pkg_prefix" -type f \
-    | sort \
+    | LC_ALL=C sort \
     | $HAB_BIN pkg hash > "${pkg_name}"_blake2bsums
 
   build_line "Generating signed metadata FILES"
@@ -260,6 +260,14 @@ _render_metadata_SHUTDOWN_TIMEOUT() {
   fi
 }
 
+_render_metadata_SECCOMP_PROFILE() {
+  if [[ -n "${pkg_seccomp_profile:-}" ]]; then
+    debug "Rendering SECCOMP_PROFILE metadata file"
+    # shellcheck disable=2154
+    echo "$pkg_seccomp_profile" > "$pkg_prefix"/SECCOMP_PROFILE
+  fi
+}
+
 _render_metadata_TARGET() {
   debug "Rendering TARGET metadata file"
   # shellcheck disable=2154
@@ -287,7 +295,8 @@ _render_associative_array_file() {
 
   if [[ ${#assoc_arr[@]} -gt 0 ]]; then
     debug "Rendering ${metadata_file_name} metadata file"
-    for key in "${!assoc_arr[@]}"; do
+    # Keys are sorted so that the file is the same from one build to the next.
+    for key in $(printf '%s\n' "${!assoc_arr[@]}" | LC_ALL=C sort); do
       echo "${key}=${assoc_arr[${key}]}" >> "${prefix}"/"${metadata_file_name}"
     done
   else
@@ -70,7 +70,9 @@ COMMON FLAGS:
 COMMON OPTIONS:
     -a <ARTIFACT_PATH>    Sets the source artifact cache path (default: /hab/cache/artifacts)
     -c <CERT_PATH>        Sets the SSL certs cache path (default: /hab/cache/ssl)
+    -e <VAR>              Passes an environment variable into the Studio (may be repeated)
     -k <HAB_ORIGIN_KEYS>  Installs secret origin keys (default:\$HAB_ORIGIN )
+    -m <SRC:DST[:ro]>     Mounts a host path into the Studio, optionally read-only (may be repeated)
     -r <HAB_STUDIO_ROOT>  Sets a Studio root (default: /hab/studios/<DIR_NAME>)
     -s <SRC_PATH>         Sets the source path (default: \$PWD)
     -t <STUDIO_TYPE>      Sets a Studio type when creating (default: default)
@@ -462,6 +464,8 @@ new_studio() {
         $bb mount $v --bind "$CERT_PATH" "$studio_cert_path"
       fi
     fi
+
+    mount_custom_paths
   fi
 
   # Create root filesystem
@@ -486,7 +490,7 @@ new_studio() {
   done
   # If the system is 64-bit, a few symlinks will be required
   case $($bb uname -m) in
-  x86_64)
+  x86_64|aarch64)
     $bb ln -sf $v lib "$HAB_STUDIO_ROOT/lib64"
     $bb ln -sf $v lib "$HAB_STUDIO_ROOT/usr/lib64"
     ;;
@@ -585,6 +589,35 @@ EOF
   fi
 }
 
+# **Internal** Bind mounts each of the `-m` mounts into the Studio. The mount
+# points are recorded in `$studio_mounts` so they can be unmounted when the
+# Studio is removed, even by a later invocation without the `-m` options.
+mount_custom_paths() {
+  if [ -z "${STUDIO_MOUNTS}" ]; then
+    return 0
+  fi
+
+  echo "$STUDIO_MOUNTS" | while IFS=: read -r mount_src mount_dst mount_mode; do
+    if [ -z "$mount_src" ] || [ -z "$mount_dst" ]; then
+      continue
+    fi
+    studio_mount_path="${HAB_STUDIO_ROOT}${mount_dst}"
+    if ! $bb mount | $bb grep -q "on $studio_mount_path type"; then
+      if [ -d "$mount_src" ]; then
+        $bb mkdir -p $v "$studio_mount_path"
+      else
+        $bb mkdir -p $v "$($bb dirname "$studio_mount_path")"
+        $bb touch "$studio_mount_path"
+      fi
+      $bb mount $v --bind "$mount_src" "$studio_mount_path"
+      if [ "$mount_mode" = "ro" ]; then
+        $bb mount $v -o remount,bind,ro "$studio_mount_path"
+      fi
+      echo "$studio_mount_path" >> "$studio_mounts"
+    fi
+  done
+}
+
 # **Internal** Interactively enter a Studio.
 enter_studio() {
   exit_if_no_studio_config
@@ -863,6 +896,11 @@ chroot_env() {
   if [ -n "${DO_CHECK:-}" ]; then
     env="$env DO_CHECK=$DO_CHECK"
   fi
+  # If SOURCE_DATE_EPOCH is set, then propagate it into the Studio's
+  # environment so that packages are built reproducibly.
+  if [ -n "${SOURCE_DATE_EPOCH:-}" ]; then
+    env="$env SOURCE_DATE_EPOCH=$SOURCE_DATE_EPOCH"
+  fi
 
   # If HTTP proxy variables are detected in the current environment, propagate
   # them into the Studio's environment.
@@ -891,6 +929,13 @@ chroot_env() {
     fi
   fi
 
+  # Pass through any variables requested with `-e`
+  for var in $STUDIO_ENV_PASS; do
+    if [ -n "$(eval echo "\${$var:-}")" ]; then
+      env="$env $var=$(eval echo "\$$var")"
+    fi
+  done
+
   env="$env $(load_secrets)"
 
   echo "$env"
@@ -1064,6 +1109,13 @@ is_fs_mounted() {
 # convergent on success and fast fail on failures, this can be safely run
 # multiple times across differnt program invocations.
 unmount_filesystems() {
+  if [ -f "$studio_mounts" ]; then
+    while read -r studio_mount_path; do
+      umount_fs $v -l "$studio_mount_path"
+    done < "$studio_mounts"
+    $bb rm -f "$studio_mounts"
+  fi
+
   umount_fs $v -l "$HAB_STUDIO_ROOT/src"
 
   studio_artifact_path="${HAB_STUDIO_ROOT}${HAB_CACHE_ARTIFACT_PATH}"
@@ -1213,7 +1265,7 @@ ensure_root
 # ## CLI Argument Parsing
 
 # Parse command line flags and options.
-while getopts ":nNMa:c:k:r:s:t:D:vqVh" opt; do
+while getopts ":nNMa:c:e:k:m:r:s:t:D:vqVh" opt; do
   case $opt in
     a)
       ARTIFACT_PATH=$OPTARG
@@ -1230,9 +1282,16 @@ while getopts ":nNMa:c:k:r:s:t:D:vqVh" opt; do
     M)
       NO_CERT_PATH=true
       ;;
+    e)
+      STUDIO_ENV_PASS="${STUDIO_ENV_PASS:-} $OPTARG"
+      ;;
     k)
       HAB_ORIGIN_KEYS=$OPTARG
       ;;
+    m)
+      STUDIO_MOUNTS="${STUDIO_MOUNTS:+$STUDIO_MOUNTS
+}$OPTARG"
+      ;;
     r)
       HAB_STUDIO_ROOT=$OPTARG
       ;;
@@ -1300,6 +1359,12 @@ dir_name="$(echo "$SRC_PATH" | $bb sed -e 's,^/$,root,' -e 's,^/,,' -e 's,/,--,g
 # extra environment variables, etc. Note that a valid Studio will have this
 # file at the root of its filesystem.
 studio_config="$HAB_STUDIO_ROOT/.studio"
+# The file recording the mount points of any `-m` mounts in the Studio.
+studio_mounts="$HAB_STUDIO_ROOT/.studio_mounts"
+# Host paths to mount into the Studio, one `SRC:DST[:ro]` entry per line.
+: "${STUDIO_MOUNTS:=}"
+# Names of environment variables to pass into the Studio.
+: "${STUDIO_ENV_PASS:=}"
 # The type (flavor, variant, etc.) of Studio. Such types include `default`,
 # `stage1`, and `busybox` among others.
 : "${STUDIO_TYPE:=}"
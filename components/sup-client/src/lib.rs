@@ -37,26 +37,92 @@ extern crate log;
 use crate::{common::types::ListenCtlAddr,
             protocol::{codec::*,
                        net::NetErr}};
-use futures::{sink::SinkExt,
-              stream::{Stream,
+use futures::{future,
+              sink::SinkExt,
+              stream::{self,
+                       Stream,
                        StreamExt}};
 use habitat_common::{self as common,
-                     cli::CTL_SECRET_ENVVAR,
+                     cli::{CTL_SECRET_ENVVAR,
+                           CTL_TLS_FINGERPRINT_ENVVAR,
+                           CTL_TLS_SERVER_NAME_ENVVAR},
                      cli_config::{CliConfig,
                                   Error as CliConfigError}};
-use habitat_core::env as henv;
+use habitat_core::{env as henv,
+                   os::{net,
+                        users}};
+use rustls::{Certificate,
+             ClientConfig,
+             RootCertStore,
+             ServerCertVerified,
+             ServerCertVerifier,
+             TLSError};
 use std::{error,
           fmt,
+          fs,
           io,
           path::PathBuf,
+          sync::{atomic::{AtomicU64,
+                          Ordering},
+                 Arc},
           time::Duration};
 use tokio::{net::TcpStream,
             time};
+use tokio_rustls::TlsConnector;
 use tokio_util::codec::Framed;
 
 /// Time to wait in milliseconds for a client connection to timeout.
 pub const REQ_TIMEOUT: u64 = 10_000;
 
+/// The name asked for when the certificate is pinned by its fingerprint. It is never checked.
+const PINNED_SERVER_NAME: &str = "supervisor.habitat";
+
+/// Seconds to wait for the complete reply to a request, or 0 to wait as long as it takes.
+static REQUEST_TIMEOUT: AtomicU64 = AtomicU64::new(0);
+
+/// Give up on requests whose reply isn't complete after `timeout`, cancelling them, or wait as
+/// long as they take with `None`. Closing the connection tells the Supervisor to stop working on
+/// the request.
+pub fn set_request_timeout(timeout: Option<Duration>) {
+    REQUEST_TIMEOUT.store(timeout.map_or(0, |t| t.as_secs()), Ordering::Relaxed)
+}
+
+fn request_timeout() -> Option<Duration> {
+    match REQUEST_TIMEOUT.load(Ordering::Relaxed) {
+        0 => None,
+        secs => Some(Duration::from_secs(secs)),
+    }
+}
+
+/// End `replies` with a `TimedOut` error once the request timeout, if any, has passed.
+fn with_request_timeout<S>(replies: S) -> impl Stream<Item = Result<SrvMessage, io::Error>> + Unpin
+    where S: Stream<Item = Result<SrvMessage, io::Error>> + Unpin
+{
+    let timeout = request_timeout();
+    let deadline = timeout.map(|timeout| time::Instant::now() + timeout);
+    Box::pin(stream::unfold(Some(replies), move |replies| {
+                 async move {
+                     let mut replies = replies?;
+                     let deadline = match deadline {
+                         Some(deadline) => deadline,
+                         None => return replies.next().await.map(|reply| (reply, Some(replies))),
+                     };
+                     match time::timeout_at(deadline, replies.next()).await {
+                         Ok(reply) => reply.map(|reply| (reply, Some(replies))),
+                         Err(_) => {
+                             let secs = timeout.map_or(0, |t| t.as_secs());
+                             let err = io::Error::new(io::ErrorKind::TimedOut,
+                                                      format!("No complete reply from the \
+                                                               Supervisor within {}s, the \
+                                                               request was cancelled",
+                                                              secs));
+                             Some((Err(err), None))
+                         }
+                     }
+                 }
+             }))
+}
+
 /// Error types returned by a [`SrvClient`].
 #[derive(Debug)]
 pub enum SrvClientError {
@@ -75,6 +141,8 @@ pub enum SrvClientError {
     NetErr(NetErr),
     /// A parse error from an Invalid Color string
     ParseColor(termcolor::ParseColorError),
+    /// The name given to verify the Supervisor's certificate with is not a valid DNS name.
+    InvalidTlsServerName(String),
 }
 
 impl error::Error for SrvClientError {}
@@ -106,6 +174,11 @@ impl fmt::Display for SrvClientError {
             SrvClientError::Io(ref err) => format!("{}", err),
             SrvClientError::NetErr(ref err) => format!("{}", err),
             SrvClientError::ParseColor(ref err) => format!("{}", err),
+            SrvClientError::InvalidTlsServerName(ref name) => {
+                format!("{} is not a valid DNS name to verify the Supervisor's certificate with, \
+                         check {}",
+                        name, CTL_TLS_SERVER_NAME_ENVVAR)
+            }
         };
         write!(f, "{}", content)
     }
@@ -149,15 +222,51 @@ impl SrvClient {
         address: &ListenCtlAddr,
         request: impl Into<SrvMessage> + fmt::Debug)
         -> Result<impl Stream<Item = Result<SrvMessage, io::Error>>, SrvClientError> {
+        let mut socket = Self::connect(address, false).await?;
+
+        // Send the actual request message
+        let mut current_transaction = SrvTxn::default();
+        current_transaction.increment();
+        let mut message = request.into();
+        message.set_transaction(current_transaction);
+        trace!("Sending SrvMessage -> {:?}", message);
+        socket.send(message).await?;
+
+        // Return the socket for use as a Stream of responses
+        Ok(with_request_timeout(socket))
+    }
+
+    /// Connect to the remote server with the given secret_key for a session of several requests,
+    /// made one after the other with [`SrvSession::request`].
+    pub async fn session(address: &ListenCtlAddr) -> Result<SrvSession, SrvClientError> {
+        let socket = Self::connect(address, true).await?;
+        Ok(SrvSession { socket,
+                        current_transaction: SrvTxn::default() })
+    }
+
+    /// Connect to the remote server and complete the handshake with it.
+    async fn connect(address: &ListenCtlAddr, session: bool) -> Result<SrvStream, SrvClientError> {
         let socket = TcpStream::connect(address.as_ref()).await?;
+        let socket: Box<dyn SrvIo> = match Self::tls_config()? {
+            Some((config, server_name)) => {
+                let server_name = webpki::DNSNameRef::try_from_ascii_str(&server_name)
+                    .map_err(|_| SrvClientError::InvalidTlsServerName(server_name.clone()))?;
+                let connector = TlsConnector::from(Arc::new(config));
+                Box::new(connector.connect(server_name, socket).await?)
+            }
+            None => Box::new(socket),
+        };
         let mut socket = Framed::new(socket, SrvCodec::new());
-        let mut current_transaction = SrvTxn::default();
 
         // Send the handshake message to the server
         let mut handshake = protocol::ctl::Handshake::default();
-        handshake.secret_key = Some(Self::ctl_secret_key()?);
+        handshake.secret_key = Some(Self::ctl_secret_key(address)?);
+        handshake.client = Some(Self::client_identity());
+        if session {
+            handshake.session = Some(true);
+        }
         let mut message = SrvMessage::from(handshake);
-        message.set_transaction(current_transaction);
+        message.set_transaction(SrvTxn::default());
         socket.send(message).await?;
 
         // Verify the handshake response. There are three kinds of errors we could encounter:
@@ -173,25 +282,49 @@ impl SrvClient {
                                               })?
                                               .ok_or(SrvClientError::ConnectionClosed)??;
         handshake_reply.try_ok()?;
+        Ok(socket)
+    }
 
-        // Send the actual request message
-        current_transaction.increment();
-        let mut message = request.into();
-        message.set_transaction(current_transaction);
-        trace!("Sending SrvMessage -> {:?}", message);
-        socket.send(message).await?;
+    /// The TLS configuration and the name to verify the Supervisor's certificate with, if the
+    /// CtlGateway is to be connected to over TLS.
+    fn tls_config() -> Result<Option<(ClientConfig, String)>, SrvClientError> {
+        let mut config = ClientConfig::new();
+        if let Ok(fingerprint) = henv::var(CTL_TLS_FINGERPRINT_ENVVAR) {
+            config.dangerous()
+                  .set_certificate_verifier(Arc::new(PinnedCertVerifier(fingerprint)));
+            return Ok(Some((config, PINNED_SERVER_NAME.to_string())));
+        }
+        if let Ok(server_name) = henv::var(CTL_TLS_SERVER_NAME_ENVVAR) {
+            config.root_store
+                  .add_server_trust_anchors(&webpki_roots::TLS_SERVER_ROOTS);
+            return Ok(Some((config, server_name)));
+        }
+        Ok(None)
+    }
 
-        // Return the socket for use as a Stream of responses
-        Ok(socket)
+    /// The identity the client presents to the Supervisor, which attributes its requests to it.
+    fn client_identity() -> String {
+        let user = users::get_current_username().ok()
+                                                .flatten()
+                                                .unwrap_or_else(|| "unknown".to_string());
+        let host = net::fqdn().unwrap_or_else(|| "unknown".to_string());
+        format!("{}@{}", user, host)
     }
 
-    /// Check if the `HAB_CTL_SECRET` env var is set. If not, check the CLI config to see if there
-    /// is a ctl secret set. If not, read CTL_SECRET
-    fn ctl_secret_key() -> Result<String, SrvClientError> {
+    /// Check if the Supervisor at `address` was added with `hab remote add` with a secret file.
+    /// If not, check if the `HAB_CTL_SECRET` env var is set. If not, check the CLI config to see
+    /// if there is a ctl secret set. If not, read CTL_SECRET
+    fn ctl_secret_key(address: &ListenCtlAddr) -> Result<String, SrvClientError> {
+        let config = CliConfig::load()?;
+        if let Some(secret_file) = Self::remote_secret_file(&config, address) {
+            let secret = fs::read_to_string(secret_file).map_err(|_| {
+                             SrvClientError::CtlSecretNotFound(secret_file.clone())
+                         })?;
+            return Ok(secret.trim().to_string());
+        }
         match henv::var(CTL_SECRET_ENVVAR) {
             Ok(v) => Ok(v),
             Err(_) => {
-                let config = CliConfig::load()?;
                 match config.ctl_secret {
                     Some(v) => Ok(v),
                     None => SrvClient::ctl_secret_key_from_file(),
@@ -200,9 +333,83 @@ impl SrvClient {
         }
     }
 
+    /// The secret file of the remote Supervisor at `address`, if it has one.
+    fn remote_secret_file<'a>(config: &'a CliConfig,
+                              address: &ListenCtlAddr)
+                              -> Option<&'a PathBuf> {
+        config.remotes
+              .values()
+              .filter(|remote| {
+                  let remote_address = ListenCtlAddr::resolve_listen_ctl_addr(&remote.address);
+                  remote_address.ok().as_ref() == Some(address)
+              })
+              .find_map(|remote| remote.secret_file.as_ref())
+    }
+
     pub fn ctl_secret_key_from_file() -> Result<String, SrvClientError> {
         let mut buf = String::new();
         protocol::read_secret_key(protocol::sup_root(None), &mut buf)?;
         Ok(buf)
     }
 }
+
+/// A connection to a server over which several requests are made one after the other, sparing
+/// a connection and handshake for each.
+pub struct SrvSession {
+    socket:              SrvStream,
+    current_transaction: SrvTxn,
+}
+
+impl SrvSession {
+    /// Make a request once the reply to the previous one is complete.
+    ///
+    /// Returns a stream of `SrvMessage`'s representing the server response, which ends with the
+    /// reply that completes it.
+    pub async fn request(
+        &mut self,
+        request: impl Into<SrvMessage> + fmt::Debug)
+        -> Result<impl Stream<Item = Result<SrvMessage, io::Error>> + '_, SrvClientError> {
+        self.current_transaction.increment();
+        let mut message = request.into();
+        message.set_transaction(self.current_transaction);
+        trace!("Sending SrvMessage -> {:?}", message);
+        self.socket.send(message).await?;
+
+        let replies = self.socket.by_ref().scan(false, |complete, reply| {
+                                              if *complete {
+                                                  return future::ready(None);
+                                              }
+                                              *complete =
+                                                  reply.as_ref()
+                                                       .map_or(true, SrvMessage::is_complete);
+                                              future::ready(Some(reply))
+                                          });
+        Ok(with_request_timeout(replies))
+    }
+}
+
+/// Trusts only the certificate with the given fingerprint, such as the self-signed certificate of
+/// a Supervisor.
+struct PinnedCertVerifier(String);
+
+impl ServerCertVerifier for PinnedCertVerifier {
+    fn verify_server_cert(&self,
+                          _roots: &RootCertStore,
+                          presented_certs: &[Certificate],
+                          _dns_name: webpki::DNSNameRef<'_>,
+                          _ocsp_response: &[u8])
+                          -> Result<ServerCertVerified, TLSError> {
+        match presented_certs.first() {
+            Some(cert) if protocol::tls::fingerprint_matches(&self.0, &cert.0) => {
+                Ok(ServerCertVerified::assertion())
+            }
+            Some(cert) => {
+                Err(TLSError::General(format!("The Supervisor's certificate has the fingerprint \
+                                               {}, not {}",
+                                              protocol::tls::fingerprint(&cert.0),
+                                              self.0)))
+            }
+            None => Err(TLSError::NoCertificatesPresented),
+        }
+    }
+}
@@ -53,7 +53,8 @@ use std::{fmt,
           io::{self,
                Cursor},
           str};
-use tokio::net::TcpStream;
+use tokio::io::{AsyncRead,
+                AsyncWrite};
 use tokio_util::codec::{Decoder,
                         Encoder,
                         Framed};
@@ -71,9 +72,15 @@ const RESPONSE_MASK: u32 = 0x1;
 const COMPLETE_OFFSET: u32 = 30;
 const COMPLETE_MASK: u32 = 0x1;
 
-/// A `TcpStream` framed with `SrvCodec`. This is the base socket connection that the CtlGateway
+/// A connection between a CtlGateway client and server, either a plain `TcpStream` or a TLS
+/// stream over one.
+pub trait SrvIo: AsyncRead + AsyncWrite + Send + Unpin {}
+
+impl<T> SrvIo for T where T: AsyncRead + AsyncWrite + Send + Unpin {}
+
+/// A `SrvIo` framed with `SrvCodec`. This is the base socket connection that the CtlGateway
 /// client and server speak.
-pub type SrvStream = Framed<TcpStream, SrvCodec>;
+pub type SrvStream = Framed<Box<dyn SrvIo>, SrvCodec>;
 
 // The type of the transaction id.
 pub type TxnId = u32;
@@ -16,6 +16,30 @@ impl message::MessageStatic for Handshake {
     const MESSAGE_ID: &'static str = "Handshake";
 }
 
+impl message::MessageStatic for MemberList {
+    const MESSAGE_ID: &'static str = "MemberList";
+}
+
+impl message::MessageStatic for PortMappingList {
+    const MESSAGE_ID: &'static str = "PortMappingList";
+}
+
+impl message::MessageStatic for AllowedPathList {
+    const MESSAGE_ID: &'static str = "AllowedPathList";
+}
+
+impl message::MessageStatic for EnvFileList {
+    const MESSAGE_ID: &'static str = "EnvFileList";
+}
+
+impl message::MessageStatic for UlimitList {
+    const MESSAGE_ID: &'static str = "UlimitList";
+}
+
+impl message::MessageStatic for ListenFdList {
+    const MESSAGE_ID: &'static str = "ListenFdList";
+}
+
 impl message::MessageStatic for ServiceBindList {
     const MESSAGE_ID: &'static str = "ServiceBindList";
 }
@@ -24,10 +48,22 @@ impl message::MessageStatic for SupDepart {
     const MESSAGE_ID: &'static str = "SupDepart";
 }
 
+impl message::MessageStatic for SupPurgeDeparted {
+    const MESSAGE_ID: &'static str = "SupPurgeDeparted";
+}
+
 impl message::MessageStatic for SupRestart {
     const MESSAGE_ID: &'static str = "SupRestart";
 }
 
+impl message::MessageStatic for SupStatus {
+    const MESSAGE_ID: &'static str = "SupStatus";
+}
+
+impl message::MessageStatic for SupMembers {
+    const MESSAGE_ID: &'static str = "SupMembers";
+}
+
 impl message::MessageStatic for SvcFilePut {
     const MESSAGE_ID: &'static str = "SvcFilePut";
 }
@@ -68,6 +104,12 @@ impl message::MessageStatic for SvcStatus {
     const MESSAGE_ID: &'static str = "SvcStatus";
 }
 
+impl message::MessageStatic for SvcHealthHistory {
+    const MESSAGE_ID: &'static str = "SvcHealthHistory";
+}
+impl message::MessageStatic for SvcConfigAudit {
+    const MESSAGE_ID: &'static str = "SvcConfigAudit";
+}
 impl message::MessageStatic for ConsoleLine {
     const MESSAGE_ID: &'static str = "ConsoleLine";
 }
@@ -41,6 +41,7 @@ pub mod codec;
 pub mod ctl;
 pub mod message;
 pub mod net;
+pub mod tls;
 pub mod types;
 
 use crate::{core::env as henv,
@@ -0,0 +1,43 @@
+//! Fingerprints of the certificates a CtlGateway serves TLS with.
+//!
+//! A Supervisor with a self-signed certificate shows its fingerprint when it starts, and clients
+//! trust the certificate only if it has the fingerprint they were given.
+
+use ring::digest;
+
+/// The SHA-256 fingerprint of a DER encoded certificate, as colon separated pairs of uppercase
+/// hex digits, as `openssl x509 -fingerprint -sha256` shows it.
+pub fn fingerprint(der: &[u8]) -> String {
+    digest::digest(&digest::SHA256, der).as_ref()
+                                        .iter()
+                                        .map(|b| format!("{:02X}", b))
+                                        .collect::<Vec<_>>()
+                                        .join(":")
+}
+
+/// Whether the certificate has the given fingerprint, ignoring case and separators.
+pub fn fingerprint_matches(expected: &str, der: &[u8]) -> bool {
+    let normalize = |s: &str| {
+        s.chars()
+         .filter(char::is_ascii_hexdigit)
+         .map(|c| c.to_ascii_uppercase())
+         .collect::<String>()
+    };
+    normalize(expected) == normalize(&fingerprint(der))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fingerprints_match_regardless_of_format() {
+        let fp = fingerprint(b"not really a certificate");
+        assert_eq!(fp.len(), 32 * 3 - 1);
+        assert!(fingerprint_matches(&fp, b"not really a certificate"));
+        assert!(fingerprint_matches(&fp.replace(":", "").to_lowercase(),
+                                    b"not really a certificate"));
+        assert!(!fingerprint_matches(&fp, b"another certificate"));
+        assert!(!fingerprint_matches("", b"not really a certificate"));
+    }
+}
@@ -38,6 +38,18 @@ impl message::MessageStatic for ServiceStatus {
 impl message::MessageStatic for HealthCheckInterval {
     const MESSAGE_ID: &'static str = "HealthCheckInterval";
 }
+impl message::MessageStatic for HealthCheckHistory {
+    const MESSAGE_ID: &'static str = "HealthCheckHistory";
+}
+impl message::MessageStatic for SupervisorStatus {
+    const MESSAGE_ID: &'static str = "SupervisorStatus";
+}
+impl message::MessageStatic for SupervisorMember {
+    const MESSAGE_ID: &'static str = "SupervisorMember";
+}
+impl message::MessageStatic for ConfigAudit {
+    const MESSAGE_ID: &'static str = "ConfigAudit";
+}
 
 impl ServiceGroup {
     pub fn validate(value: &str) -> core::Result<()> {
@@ -51,6 +63,19 @@ impl fmt::Display for BindingMode {
         let value = match *self {
             BindingMode::Relaxed => "relaxed",
             BindingMode::Strict => "strict",
+            BindingMode::Optional => "optional",
+        };
+        write!(f, "{}", value)
+    }
+}
+
+impl fmt::Display for HealthCheckResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = match *self {
+            HealthCheckResult::Ok => "OK",
+            HealthCheckResult::Warning => "WARNING",
+            HealthCheckResult::Critical => "CRITICAL",
+            HealthCheckResult::Unknown => "UNKNOWN",
         };
         write!(f, "{}", value)
     }
@@ -96,10 +121,11 @@ impl FromStr for BindingMode {
         match value.to_lowercase().as_ref() {
             "relaxed" => Ok(BindingMode::Relaxed),
             "strict" => Ok(BindingMode::Strict),
+            "optional" => Ok(BindingMode::Optional),
             _ => {
                 Err(net::err(ErrCode::InvalidPayload,
                              format!("Invalid binding mode \"{}\", must be \
-                                      `relaxed` or `strict`.",
+                                      `relaxed`, `strict` or `optional`.",
                                      value)))
             }
         }
@@ -198,6 +224,15 @@ impl From<core::service::HealthCheckInterval> for HealthCheckInterval {
     fn from(h: core::service::HealthCheckInterval) -> Self { Self { seconds: h.into() } }
 }
 
+impl From<core::os::cgroup::Usage> for ResourceUsage {
+    fn from(usage: core::os::cgroup::Usage) -> Self {
+        Self { cpu_usage_usec:       Some(usage.cpu_usage_usec),
+               memory_current_bytes: usage.memory_current_bytes,
+               io_read_bytes:        usage.io_read_bytes,
+               io_write_bytes:       usage.io_write_bytes, }
+    }
+}
+
 impl From<package::PackageIdent> for PackageIdent {
     fn from(ident: package::PackageIdent) -> Self {
         Self { origin:  ident.origin,
@@ -227,6 +262,7 @@ impl From<core::service::BindingMode> for BindingMode {
         match mode {
             core::service::BindingMode::Strict => BindingMode::Strict,
             core::service::BindingMode::Relaxed => BindingMode::Relaxed,
+            core::service::BindingMode::Optional => BindingMode::Optional,
         }
     }
 }
@@ -236,6 +272,7 @@ impl Into<core::service::BindingMode> for BindingMode {
         match self {
             BindingMode::Strict => core::service::BindingMode::Strict,
             BindingMode::Relaxed => core::service::BindingMode::Relaxed,
+            BindingMode::Optional => core::service::BindingMode::Optional,
         }
     }
 }
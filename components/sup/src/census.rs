@@ -410,6 +410,10 @@ impl CensusGroup {
     }
 
     fn update_from_service_rumors(&mut self, rumors: &HashMap<String, ServiceRumor>) {
+        // Members whose rumors were purged after they departed are
+        // dropped from the census along with them.
+        self.population
+            .retain(|member_id, _| rumors.contains_key(member_id));
         for (member_id, service_rumor) in rumors.iter() {
             // Yeah - we are ourself - we're alive.
             let is_self = member_id == &self.local_member_id;
@@ -63,6 +63,8 @@ pub struct CtlRequest {
     tx:                 Option<server::CtlSender>,
     /// Transaction for the given request.
     transaction:        Option<habitat_sup_protocol::codec::SrvTxn>,
+    /// The identity the client claimed in its handshake, and the address it connected from.
+    client:             Option<String>,
     current_color_spec: ColorSpec,
     is_new_line:        bool,
 }
@@ -75,6 +77,7 @@ impl CtlRequest {
                -> Self {
         CtlRequest { tx: Some(tx),
                      transaction,
+                     client: None,
                      current_color_spec: ColorSpec::new(),
                      is_new_line: true }
     }
@@ -95,9 +98,21 @@ impl CtlRequest {
         self.send_msg(msg, true);
     }
 
+    /// Attribute the request to the given client.
+    pub fn set_client(&mut self, client: String) { self.client = Some(client); }
+
+    /// The client that made the request, or `None` for a bare request.
+    pub fn client(&self) -> Option<&str> { self.client.as_deref() }
+
     /// Returns true if the request is transactional and false if not.
     pub fn transactional(&self) -> bool { self.transaction.is_some() && self.tx.is_some() }
 
+    /// Returns true if the client is no longer waiting for the reply, because it disconnected or
+    /// the request timed out. Writing output for the request then fails, which stops long
+    /// operations such as package installs at their next progress update, and commands check it
+    /// before they take effect.
+    pub fn is_cancelled(&self) -> bool { self.tx.as_ref().map_or(false, |tx| tx.is_closed()) }
+
     fn send_msg<T>(&mut self, msg: T, complete: bool)
         where T: Into<habitat_sup_protocol::codec::SrvMessage> + fmt::Debug
     {
@@ -154,6 +169,9 @@ impl Write for CtlRequest {
     fn flush(&mut self) -> io::Result<()> { Ok(()) }
 
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.is_cancelled() {
+            return Err(cancelled());
+        }
         let line = String::from_utf8(buf.to_vec()).expect("CtlRequest buffer valid utf8");
 
         // The protocol reply is destined for the client, so (for now,
@@ -216,6 +234,8 @@ impl Write for CtlRequest {
     }
 }
 
+fn cancelled() -> io::Error { io::Error::new(io::ErrorKind::Other, "request cancelled") }
+
 fn color_to_string(color: Option<&Color>) -> Option<String> {
     match color {
         Some(c) => Some(format!("{:?}", c)),
@@ -246,6 +266,9 @@ impl DisplayProgress for NetProgressBar {
 
 impl io::Write for NetProgressBar {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.req.is_cancelled() {
+            return Err(cancelled());
+        }
         self.inner.position += buf.len() as u64;
         self.req.reply_partial(self.inner.clone());
         Ok(buf.len())
@@ -280,6 +303,19 @@ pub fn readgen_secret_key<T>(sup_root: T) -> Result<String>
     }
 }
 
+/// Reads the secret key clients of an additional ctl listener authenticate with from `path`.
+pub fn read_secret_key_file<T>(path: T) -> Result<String>
+    where T: AsRef<Path>
+{
+    let secret_key =
+        fs::read_to_string(&path).map_err(|e| Error::CtlSecretIo(path.as_ref().to_path_buf(), e))?;
+    let secret_key = secret_key.trim();
+    if secret_key.is_empty() {
+        return Err(Error::EmptyCtlSecret(path.as_ref().to_path_buf()));
+    }
+    Ok(secret_key.to_string())
+}
+
 #[cfg(not(windows))]
 fn set_permissions<T: AsRef<Path>>(path: T) -> habitat_core::error::Result<()> {
     use habitat_core::util::posix_perm;
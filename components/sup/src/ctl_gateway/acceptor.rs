@@ -2,11 +2,11 @@
 //! CtlGateway in the Supervisor.
 
 use super::handler::CtlHandler;
-use crate::{ctl_gateway::server::MgrReceiver,
+use crate::{ctl_gateway::server::{MgrReceiver,
+                                  ShutdownTrigger},
             manager::{action::ActionSender,
                       ManagerState}};
-use futures::{channel::oneshot,
-              future::FutureExt,
+use futures::{future::FutureExt,
               stream::{Stream,
                        StreamExt},
               task::{Context,
@@ -26,7 +26,7 @@ pub struct CtlAcceptor {
     /// Signaling channel for the intention to shut down. A message
     /// received on this channel will cause the `CtlAcceptor` future
     /// stream to terminate.
-    shutdown_trigger: oneshot::Receiver<()>,
+    shutdown_trigger: ShutdownTrigger,
     /// Communication channel back into the main Supervisor loop. This
     /// is passed into any generated command handlers as a way to
     /// send actions into the Supervisor.
@@ -36,7 +36,7 @@ pub struct CtlAcceptor {
 impl CtlAcceptor {
     pub fn new(state: Arc<ManagerState>,
                mgr_receiver: MgrReceiver,
-               shutdown_trigger: oneshot::Receiver<()>,
+               shutdown_trigger: ShutdownTrigger,
                action_sender: ActionSender)
                -> Self {
         CtlAcceptor { state,
@@ -13,8 +13,12 @@ use super::{CtlRequest,
 use crate::manager::{action::ActionSender,
                      commands,
                      ManagerState};
-use futures::{channel::mpsc,
+use futures::{channel::{mpsc,
+                        oneshot},
               executor,
+              future::{self,
+                       Either,
+                       Shared},
               prelude::*,
               ready,
               task::{Context,
@@ -22,6 +26,7 @@ use futures::{channel::mpsc,
 use habitat_core::crypto;
 use habitat_sup_protocol::{self as protocol,
                            codec::{SrvCodec,
+                                   SrvIo,
                                    SrvMessage,
                                    SrvStream,
                                    SrvTxn},
@@ -33,6 +38,7 @@ use pin_project::pin_project;
 use prometheus::{HistogramTimer,
                  HistogramVec,
                  IntCounterVec};
+use rustls::ServerConfig;
 use std::{error,
           fmt,
           io,
@@ -44,6 +50,7 @@ use std::{error,
 use tokio::{net::TcpListener,
             task,
             time};
+use tokio_rustls::TlsAcceptor;
 use tokio_util::codec::Decoder;
 
 lazy_static! {
@@ -68,6 +75,8 @@ pub type CtlReceiver = mpsc::UnboundedReceiver<SrvMessage>;
 pub type MgrSender = mpsc::UnboundedSender<CtlCommand>;
 /// Receiver on the Manager for the sender on the CtlGateway to receive control commands.
 pub type MgrReceiver = mpsc::UnboundedReceiver<CtlCommand>;
+/// Signal for the ctl gateway to shut down, shared by the `CtlAcceptor` and every listener.
+pub type ShutdownTrigger = Shared<oneshot::Receiver<()>>;
 
 #[derive(Debug)]
 pub enum HandlerError {
@@ -142,31 +151,37 @@ impl CtlCommand {
 /// Server's client representation. Each new connection will allocate a new Client.
 struct Client {
     state: Arc<Mutex<SrvState>>,
+    addr:  SocketAddr,
 }
 
 impl Client {
     /// Serve the client from the given framed socket stream.
     pub async fn serve(self, mut socket: SrvStream) -> Result<(), HandlerError> {
-        let mgr_sender = self.state
-                             .lock()
-                             .expect("SrvState mutex poisoned")
-                             .mgr_sender
-                             .clone();
+        let (mgr_sender, request_timeout) = {
+            let state = self.state.lock().expect("SrvState mutex poisoned");
+            (state.mgr_sender.clone(), state.request_timeout)
+        };
         let handshake_with_timeout = time::timeout(Duration::from_millis(REQ_TIMEOUT),
                                                    self.handshake(&mut socket));
-        handshake_with_timeout.await
-                              .map_err(|_| {
-                                  io::Error::new(io::ErrorKind::TimedOut, "client timed out")
-                              })??;
-        SrvHandler::new(socket, mgr_sender).await
+        let (client, session) =
+            handshake_with_timeout.await
+                                  .map_err(|_| {
+                                      io::Error::new(io::ErrorKind::TimedOut, "client timed out")
+                                  })??;
+        SrvHandler::new(socket, mgr_sender, client, session, request_timeout).await
     }
 
     /// Initiate a handshake with the connected client before allowing future requests. A failed
     /// handshake will close the connection.
-    async fn handshake(&self, socket: &mut SrvStream) -> Result<(), HandlerError> {
+    ///
+    /// Returns the identity the client claimed along with its address, to attribute its requests
+    /// to, and whether it asked for a session of several requests.
+    async fn handshake(&self, socket: &mut SrvStream) -> Result<(String, bool), HandlerError> {
         let message = socket.next()
                             .await
                             .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))??;
+        let mut client = None;
+        let mut session = false;
         let success = if message.message_id() != "Handshake" {
             debug!("No handshake");
             return Err(HandlerError::from(io::Error::from(io::ErrorKind::ConnectionAborted)));
@@ -182,6 +197,8 @@ impl Client {
                                          .secret_key
                                          .to_string();
                     let decoded_key = decoded.secret_key.unwrap_or_default();
+                    client = decoded.client;
+                    session = decoded.session.unwrap_or(false);
                     crypto::secure_eq(decoded_key, secret_key)
                 }
                 Err(err) => {
@@ -190,8 +207,9 @@ impl Client {
                 }
             }
         };
+        let client = format!("{} ({})", client.as_deref().unwrap_or("unknown"), self.addr);
         let (mut reply, result) = if success {
-            (SrvMessage::from(net::ok()), Ok(()))
+            (SrvMessage::from(net::ok()), Ok((client, session)))
         } else {
             (SrvMessage::from(net::err(ErrCode::Unauthorized, "secret key mismatch")),
              Err(HandlerError::from(io::Error::new(io::ErrorKind::ConnectionAborted,
@@ -266,16 +284,29 @@ mod util {
 #[pin_project]
 struct SrvHandler {
     #[pin]
-    io:           SrvStream,
-    state:        SrvHandlerState,
-    mgr_sender:   MgrSender,
-    ctl_receiver: CtlReceiver,
-    ctl_sender:   CtlSender,
-    timer:        Option<HistogramTimer>,
+    io:              SrvStream,
+    state:           SrvHandlerState,
+    mgr_sender:      MgrSender,
+    ctl_receiver:    CtlReceiver,
+    ctl_sender:      CtlSender,
+    timer:           Option<HistogramTimer>,
+    /// The client the connection was made by, see `Client::handshake`.
+    client:          String,
+    /// Whether to receive another request once the reply to the last one is sent.
+    session:         bool,
+    /// How long a request may take before it is cancelled, if there is a limit.
+    request_timeout: Option<Duration>,
+    /// When the request being replied to times out, and its transaction.
+    deadline:        Option<(time::Delay, Option<SrvTxn>)>,
 }
 
 impl SrvHandler {
-    fn new(io: SrvStream, mgr_sender: MgrSender) -> Self {
+    fn new(io: SrvStream,
+           mgr_sender: MgrSender,
+           client: String,
+           session: bool,
+           request_timeout: Option<Duration>)
+           -> Self {
         let (ctl_sender, ctl_receiver) = mpsc::unbounded();
 
         SrvHandler { io,
@@ -283,7 +314,30 @@ impl SrvHandler {
                      mgr_sender,
                      ctl_receiver,
                      ctl_sender,
-                     timer: None }
+                     timer: None,
+                     client,
+                     session,
+                     request_timeout,
+                     deadline: None }
+    }
+
+    /// Cancel the request being replied to, which took longer than the request timeout, by
+    /// closing the channel its replies are sent over. The client is told in its place.
+    fn time_out_request(&mut self, txn: Option<SrvTxn>) {
+        let secs = self.request_timeout.map_or(0, |t| t.as_secs());
+        warn!("Cancelling a request from {} which took longer than {}s",
+              self.client, secs);
+        let (ctl_sender, ctl_receiver) = mpsc::unbounded();
+        if let Some(txn) = txn {
+            let mut reply = SrvMessage::from(net::err(ErrCode::Timeout,
+                                                      format!("The request took longer than \
+                                                               {}s and was cancelled",
+                                                              secs)));
+            reply.reply_for(txn, true);
+            ctl_sender.unbounded_send(reply).ok();
+        }
+        self.ctl_sender = ctl_sender;
+        self.ctl_receiver = ctl_receiver;
     }
 
     /// # Locking (see locking.md)
@@ -323,8 +377,19 @@ impl SrvHandler {
             "SvcStart" => util::to_command(msg, ctl_sender, commands::service_start),
             "SvcStop" => util::to_supervisor_command(msg, ctl_sender, commands::service_stop),
             "SvcStatus" => util::to_command(msg, ctl_sender, commands::service_status_gsr),
+            "SvcHealthHistory" => {
+                util::to_command(msg, ctl_sender, commands::service_health_history_gsr)
+            }
+            "SvcConfigAudit" => util::to_command(msg, ctl_sender, commands::service_config_audit),
             "SupDepart" => util::to_command(msg, ctl_sender, commands::supervisor_depart),
+            "SupPurgeDeparted" => {
+                util::to_command(msg,
+                                 ctl_sender,
+                                 commands::supervisor_purge_departed_rsw_mlw_rhw)
+            }
             "SupRestart" => util::to_command(msg, ctl_sender, commands::supervisor_restart),
+            "SupStatus" => util::to_command(msg, ctl_sender, commands::supervisor_status_gsr),
+            "SupMembers" => util::to_command(msg, ctl_sender, commands::supervisor_members_rsr_mlr),
             _ => {
                 warn!("Unhandled message, {}", msg.message_id());
                 Err(HandlerError::from(io::Error::from(io::ErrorKind::InvalidData)))
@@ -361,17 +426,23 @@ impl Future for SrvHandler {
                             let fut =
                                 Self::command_from_message_gsr_msr(&msg, self.ctl_sender.clone());
                             tokio::pin!(fut);
-                            let cmd = match futures::ready!(fut.poll_unpin(cx)) {
+                            let mut cmd = match futures::ready!(fut.poll_unpin(cx)) {
                                 Ok(cmd) => cmd,
                                 Err(_) => {
                                     break;
                                 }
                             };
+                            cmd.req.set_client(self.client.clone());
                             if let Err(err) = futures::ready!(self.mgr_sender.poll_ready(cx)) {
                                 return Poll::Ready(Err(HandlerError::from(err)));
                             }
                             match self.mgr_sender.start_send(cmd) {
                                 Ok(()) => {
+                                    self.deadline =
+                                        self.request_timeout
+                                            .map(|timeout| {
+                                                (time::delay_for(timeout), msg.transaction())
+                                            });
                                     self.state = SrvHandlerState::Sending;
                                     continue;
                                 }
@@ -396,6 +467,29 @@ impl Future for SrvHandler {
                     }
                 }
                 SrvHandlerState::Sending => {
+                    // The client closing the connection cancels the request, as does it taking
+                    // longer than the request timeout
+                    match self.io.poll_next_unpin(cx) {
+                        Poll::Ready(None) | Poll::Ready(Some(Err(_))) => {
+                            debug!("{} went away, cancelling its request", self.client);
+                            break;
+                        }
+                        Poll::Ready(Some(Ok(msg))) => {
+                            warn!("Unexpected message from {} while replying to its request, {}",
+                                  self.client,
+                                  msg.message_id());
+                            let err = io::Error::from(io::ErrorKind::InvalidData);
+                            return Poll::Ready(Err(HandlerError::from(err)));
+                        }
+                        Poll::Pending => {}
+                    }
+                    if let Some((delay, txn)) = self.deadline.as_mut() {
+                        if delay.poll_unpin(cx).is_ready() {
+                            let txn = *txn;
+                            self.deadline = None;
+                            self.time_out_request(txn);
+                        }
+                    }
                     match futures::ready!(self.ctl_receiver.poll_next_unpin(cx)) {
                         Some(msg) => {
                             trace!("MgrSender -> SrvHandler, {:?}", msg);
@@ -426,10 +520,15 @@ impl Future for SrvHandler {
                     }
                 }
                 SrvHandlerState::Sent => {
+                    self.deadline = None;
                     if let Some(timer) = self.timer.take() {
                         timer.observe_duration();
                     }
                     trace!("OnMessage complete");
+                    if self.session {
+                        self.state = SrvHandlerState::Receiving;
+                        continue;
+                    }
                     break;
                 }
             }
@@ -444,28 +543,40 @@ enum SrvHandlerState {
     /// Handler has sent a request to the Manager and is streaming replies back to the client
     /// socket.
     Sending,
-    /// All messages have been sent to the client and the Handler is now flushing the connection.
+    /// All messages have been sent to the client and the Handler is now flushing the connection,
+    /// or waiting for the next request of a session.
     Sent,
 }
 
 struct SrvState {
-    secret_key: String,
-    mgr_sender: MgrSender,
+    secret_key:      String,
+    mgr_sender:      MgrSender,
+    request_timeout: Option<Duration>,
 }
 
 /// Start a new thread which will run the CtlGateway server.
 ///
 /// New connections will be authenticated using `secret_key`. Messages from the main thread
-/// will be sent over the channel `mgr_sender`.
-pub async fn run(listen_addr: SocketAddr, secret_key: String, mgr_sender: MgrSender) {
+/// will be sent over the channel `mgr_sender`. Requests taking longer than `request_timeout` are
+/// cancelled. `listening` is notified once `listener` accepts connections, which it stops doing
+/// when `shutdown` fires.
+pub async fn run(mut listener: TcpListener,
+                 secret_key: String,
+                 tls_config: Option<Arc<ServerConfig>>,
+                 request_timeout: Option<Duration>,
+                 mgr_sender: MgrSender,
+                 listening: oneshot::Sender<()>,
+                 mut shutdown: ShutdownTrigger) {
     let state = SrvState { secret_key,
-                           mgr_sender };
+                           mgr_sender,
+                           request_timeout };
     let state = Arc::new(Mutex::new(state));
-    let mut listner =
-        TcpListener::bind(&listen_addr).await
-                                       .expect("Could not bind ctl gateway listen address!");
-    let mut incoming = listner.incoming();
-    while let Some(tcp_stream) = incoming.next().await {
+    let tls_acceptor = tls_config.map(TlsAcceptor::from);
+    listening.send(()).ok();
+    let mut incoming = listener.incoming();
+    while let Either::Left((Some(tcp_stream), _)) =
+        future::select(incoming.next(), &mut shutdown).await
+    {
         match tcp_stream {
             Ok(tcp_stream) => {
                 let addr = match tcp_stream.peer_addr() {
@@ -475,9 +586,25 @@ pub async fn run(listen_addr: SocketAddr, secret_key: String, mgr_sender: MgrSen
                         continue;
                     }
                 };
-                let io = SrvCodec::new().framed(tcp_stream);
-                let client = Client { state: Arc::clone(&state), };
+                let client = Client { state: Arc::clone(&state),
+                                      addr };
+                let tls_acceptor = tls_acceptor.clone();
                 tokio::spawn(async move {
+                    // The TLS handshake happens here rather than in the accept loop so that a
+                    // slow client can't hold up the others.
+                    let socket: Box<dyn SrvIo> = match tls_acceptor {
+                        Some(acceptor) => {
+                            match acceptor.accept(tcp_stream).await {
+                                Ok(tls_stream) => Box::new(tls_stream),
+                                Err(e) => {
+                                    debug!("TLS handshake with {} failed, err {}", addr, e);
+                                    return;
+                                }
+                            }
+                        }
+                        None => Box::new(tcp_stream),
+                    };
+                    let io = SrvCodec::new().framed(socket);
                     let res = client.serve(io).await;
                     debug!("DISCONNECTED from {:?} with result {:?}", addr, res);
                 });
@@ -1,4 +1,5 @@
-use crate::event;
+use crate::{event,
+            secrets};
 use futures::channel::oneshot;
 use habitat_core::{self,
                    os::process::Pid,
@@ -38,13 +39,17 @@ pub enum Error {
     TestBootFail,
     ButterflyError(habitat_butterfly::error::Error),
     CtlSecretIo(PathBuf, io::Error),
+    EmptyCtlSecret(PathBuf),
+    DryRunProblems(usize),
     APIClient(habitat_api_client::Error),
+    EnvFile(PathBuf, io::Error),
     EnvJoinPathsError(env::JoinPathsError),
     EnvVarError(env::VarError),
     ExecCommandNotFound(String),
     EventError(event::Error),
     FileNotFound(String),
     FileWatcherFileIsRoot,
+    GatewayCert(String),
     GroupNotFound(String),
     Hab(hab::error::Error),
     HabitatCommon(habitat_common::Error),
@@ -80,9 +85,11 @@ pub enum Error {
     ProcessLockIO(PathBuf, io::Error),
     RecvError(mpsc::RecvError),
     RecvTimeoutError(mpsc::RecvTimeoutError),
+    SecretsError(secrets::Error),
     ServiceDeserializationError(serde_json::Error),
     ServiceNotLoaded(package::PackageIdent),
     ServiceSerializationError(serde_json::Error),
+    ServiceSpecDecrypt(PathBuf, habitat_core::Error),
     ServiceSpecFileIO(PathBuf, io::Error),
     ServiceSpecParse(toml::de::Error),
     ServiceSpecRender(toml::ser::Error),
@@ -147,6 +154,10 @@ impl fmt::Display for Error {
             }
             Error::BadStartStyle(ref style) => format!("Unknown service start style '{}'", style),
             Error::BindTimeout(ref err) => format!("Timeout waiting to bind to {}", err),
+            Error::DryRunProblems(count) => {
+                format!("The dry run found {} problem(s) with the services to run",
+                        count)
+            }
             Error::LockPoisoned => "A mutex or read/write lock has failed.".to_string(),
             Error::TestBootFail => "Simulated boot failure".to_string(),
             Error::ButterflyError(ref err) => format!("Butterfly error: {}", err),
@@ -155,6 +166,14 @@ impl fmt::Display for Error {
                         path.display(),
                         err)
             }
+            Error::EmptyCtlSecret(ref path) => {
+                format!("The ctl secret in {} is empty", path.display())
+            }
+            Error::EnvFile(ref path, ref err) => {
+                format!("Unable to read the environment file {}, {}",
+                        path.display(),
+                        err)
+            }
             Error::ExecCommandNotFound(ref c) => {
                 format!("`{}' was not found on the filesystem or in PATH", c)
             }
@@ -167,6 +186,9 @@ impl fmt::Display for Error {
             Error::EnvVarError(ref err) => err.to_string(),
             Error::FileNotFound(ref e) => format!("File not found at: {}", e),
             Error::FileWatcherFileIsRoot => "Watched file is root".to_string(),
+            Error::GatewayCert(ref e) => {
+                format!("Unable to provide a certificate for the gateways, {}", e)
+            }
             Error::GroupNotFound(ref e) => format!("No GID for group '{}' could be found", e),
             Error::InvalidBinds(ref e) => format!("Invalid bind(s), {}", e.join(", ")),
             Error::InvalidCertFile(ref path) => format!("Invalid cert file: {}", path.display()),
@@ -223,6 +245,7 @@ impl fmt::Display for Error {
             }
             Error::RecvError(ref err) => err.to_string(),
             Error::RecvTimeoutError(ref err) => err.to_string(),
+            Error::SecretsError(ref err) => err.to_string(),
             Error::ServiceDeserializationError(ref e) => {
                 format!("Can't deserialize service status: {}", e)
             }
@@ -230,6 +253,11 @@ impl fmt::Display for Error {
             Error::ServiceSerializationError(ref e) => {
                 format!("Can't serialize service to file: {}", e)
             }
+            Error::ServiceSpecDecrypt(ref path, ref err) => {
+                format!("Unable to decrypt the service spec file at {}, {}",
+                        path.display(),
+                        err)
+            }
             Error::ServiceSpecFileIO(ref path, ref err) => {
                 format!("Unable to write or read to a service spec file at {}, {}",
                         path.display(),
@@ -277,6 +305,7 @@ impl error::Error for Error {
         match self {
             // Nothing else implements source yet
             Error::EventError(ref e) => e.source(),
+            Error::SecretsError(ref e) => e.source(),
             _ => None,
         }
     }
@@ -293,7 +322,7 @@ impl From<habitat_api_client::Error> for Error {
 impl From<Error> for habitat_sup_protocol::net::NetErr {
     fn from(err: Error) -> habitat_sup_protocol::net::NetErr {
         match err {
-            Error::MissingRequiredBind(_) | Error::InvalidBinds(_) => {
+            Error::MissingRequiredBind(_) | Error::InvalidBinds(_) | Error::EnvFile(..) => {
                 habitat_sup_protocol::net::err(habitat_sup_protocol::net::ErrCode::InvalidPayload,
                                                err)
             }
@@ -386,6 +415,10 @@ impl From<event::Error> for Error {
     fn from(err: event::Error) -> Error { Error::EventError(err) }
 }
 
+impl From<secrets::Error> for Error {
+    fn from(err: secrets::Error) -> Error { Error::SecretsError(err) }
+}
+
 impl From<env::VarError> for Error {
     fn from(err: env::VarError) -> Error { Error::EnvVarError(err) }
 }
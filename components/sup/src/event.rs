@@ -11,29 +11,37 @@
 //!
 //! [1]:https://github.com/nats-io/nats-server
 
+mod census;
 mod error;
 mod nats_message_stream;
 mod types;
 
+pub use self::census::CensusTracker;
 pub(crate) use self::types::ServiceMetadata;
 use self::types::{EventMessage,
                   EventMetadata,
                   HealthCheckEvent,
+                  HookFailedEvent,
+                  ServiceOutputEvent,
+                  ServiceResourceUsageEvent,
                   ServiceStartedEvent,
                   ServiceStoppedEvent,
                   ServiceUpdateStartedEvent};
-use crate::manager::{service::{HealthCheckHookStatus,
-                               HealthCheckResult,
-                               ProcessOutput,
-                               Service,
-                               StandardStreams},
-                     sys::Sys};
+use crate::{manager::{service::{HealthCheckHookStatus,
+                                HealthCheckResult,
+                                HookFailure,
+                                ProcessOutput,
+                                Service,
+                                StandardStreams},
+                      sys::Sys},
+            sys::ServiceResourceUsage};
 pub use error::{Error,
                 Result};
-use habitat_common::types::{EventStreamConnectMethod,
-                            EventStreamMetadata,
-                            EventStreamServerCertificate,
-                            EventStreamToken};
+use habitat_common::{output::ServiceStream,
+                     types::{EventStreamConnectMethod,
+                             EventStreamMetadata,
+                             EventStreamServerCertificate,
+                             EventStreamToken}};
 use habitat_core::{package::ident::PackageIdent,
                    service::HealthCheckInterval};
 use nats_message_stream::{NatsMessage,
@@ -58,6 +66,18 @@ lazy_static! {
         "habitat.event.service_update_started".parse().expect("valid NATS subject");
     static ref HEALTHCHECK_SUBJECT: Subject =
         "habitat.event.healthcheck".parse().expect("valid NATS subject");
+    static ref SERVICE_OUTPUT_SUBJECT: Subject =
+        "habitat.event.service_output".parse().expect("valid NATS subject");
+    static ref HOOK_FAILED_SUBJECT: Subject =
+        "habitat.event.hook_failed".parse().expect("valid NATS subject");
+    static ref SERVICE_RESOURCE_USAGE_SUBJECT: Subject =
+        "habitat.event.service_resource_usage".parse().expect("valid NATS subject");
+    static ref CENSUS_SNAPSHOT_SUBJECT: Subject =
+        "habitat.event.census_snapshot".parse().expect("valid NATS subject");
+    static ref MEMBERSHIP_CHANGED_SUBJECT: Subject =
+        "habitat.event.membership_changed".parse().expect("valid NATS subject");
+    static ref ELECTION_FINISHED_SUBJECT: Subject =
+        "habitat.event.election_finished".parse().expect("valid NATS subject");
 
     /// Reference to the event stream.
     static ref NATS_MESSAGE_STREAM: Storage<NatsMessageStream> = Storage::new();
@@ -158,6 +178,47 @@ pub fn health_check(metadata: ServiceMetadata,
     }
 }
 
+/// Send a line of a service's output. Like `health_check`, this takes metadata directly, as the
+/// output is followed from a task that doesn't hold on to the `Service`.
+pub fn service_output(metadata: ServiceMetadata, stream: ServiceStream, line: String) {
+    if initialized() {
+        publish(&SERVICE_OUTPUT_SUBJECT,
+                ServiceOutputEvent { event_metadata: None,
+                                     service_metadata: Some(metadata),
+                                     stream: stream.to_string(),
+                                     line });
+    }
+}
+
+/// Send an event for a failed invocation of one of a service's lifecycle hooks. Like
+/// `health_check`, this takes metadata directly, as the `init` hook is run from a task that
+/// doesn't hold on to the `Service`.
+pub fn hook_failed(metadata: ServiceMetadata, failure: &HookFailure) {
+    if initialized() {
+        publish(&HOOK_FAILED_SUBJECT,
+                HookFailedEvent { event_metadata:   None,
+                                  service_metadata: Some(metadata),
+                                  hook:             failure.hook.clone(),
+                                  exit_status:      failure.exit_status,
+                                  error:            failure.error.clone(),
+                                  stdout:           failure.stdout.clone(),
+                                  stderr:           failure.stderr.clone(), });
+    }
+}
+
+/// Send a sample of the resources used by a service's processes.
+pub fn resource_usage(metadata: ServiceMetadata, usage: &ServiceResourceUsage) {
+    if initialized() {
+        publish(&SERVICE_RESOURCE_USAGE_SUBJECT,
+                ServiceResourceUsageEvent { event_metadata:   None,
+                                            service_metadata: Some(metadata),
+                                            cpu_time_ms:      usage.cpu_time_ms,
+                                            memory_bytes:     usage.memory_bytes,
+                                            open_files:       usage.open_files,
+                                            child_processes:  usage.child_processes, });
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////
 
 /// A collection of data that will be present in all events. Rather
@@ -197,6 +258,12 @@ impl EventCore {
     }
 }
 
+/// The number of events waiting to be published, if the event stream has been initialized.
+pub fn backlog() -> Option<usize> {
+    NATS_MESSAGE_STREAM.try_get()
+                       .map(NatsMessageStream::backlog)
+}
+
 /// Internal helper function to know whether or not to go to the trouble of
 /// creating event structures. If the event stream hasn't been
 /// initialized, then we shouldn't need to do anything.
@@ -0,0 +1,142 @@
+//! Events that let an external control plane mirror the census of the ring: periodic snapshots
+//! of the whole census, and the changes to its membership and elections in between.
+
+use super::{initialized,
+            publish,
+            types::{CensusMember as CensusMemberMessage,
+                    CensusSnapshotEvent,
+                    ElectionFinishedEvent,
+                    MemberHealth,
+                    MembershipChange,
+                    MembershipChangedEvent},
+            CENSUS_SNAPSHOT_SUBJECT,
+            ELECTION_FINISHED_SUBJECT,
+            MEMBERSHIP_CHANGED_SUBJECT};
+use crate::census::{CensusGroup,
+                    CensusMember,
+                    CensusRing};
+use std::{collections::HashMap,
+          time::{Duration,
+                 Instant}};
+
+habitat_core::env_config_duration!(
+    /// How often the whole census is published to the event stream.
+    CensusSnapshotPeriod,
+    HAB_EVENT_CENSUS_SNAPSHOT_SECS => from_secs,
+    Duration::from_secs(300));
+
+/// Members are told apart by the service group they are a member of, and their member ID.
+type MemberKey = (String, String);
+
+/// Tracks the census as last published, to publish what changed about it.
+pub struct CensusTracker {
+    members:        HashMap<MemberKey, CensusMemberMessage>,
+    /// The leader of each service group, and of its updates, as last published.
+    leaders:        HashMap<(String, bool), String>,
+    last_snapshot:  Option<Instant>,
+    snapshot_every: Duration,
+}
+
+impl Default for CensusTracker {
+    fn default() -> Self {
+        CensusTracker { members:        HashMap::new(),
+                        leaders:        HashMap::new(),
+                        last_snapshot:  None,
+                        snapshot_every: CensusSnapshotPeriod::configured_value().into(), }
+    }
+}
+
+impl CensusTracker {
+    /// Publish the changes to the census since it was last observed, and a snapshot of it when
+    /// one is due.
+    pub fn observe(&mut self, census_ring: &CensusRing) {
+        if !initialized() {
+            return;
+        }
+        let snapshot_due = self.last_snapshot
+                               .map_or(true, |at| at.elapsed() >= self.snapshot_every);
+        if !census_ring.changed() && !snapshot_due {
+            return;
+        }
+
+        let groups = census_ring.groups();
+        let members: HashMap<MemberKey, CensusMemberMessage> =
+            groups.iter()
+                  .flat_map(|group| group.members().map(move |member| (*group, member)))
+                  .map(|(group, member)| {
+                      ((group.service_group.to_string(), member.member_id.clone()),
+                       census_member(group, member))
+                  })
+                  .collect();
+
+        for (key, member) in &members {
+            let change = match self.members.get(key) {
+                None => MembershipChange::Joined,
+                Some(previous) if previous.health != member.health => {
+                    MembershipChange::HealthChanged
+                }
+                Some(_) => continue,
+            };
+            membership_changed(member.clone(), change);
+        }
+        for (key, member) in self.members.drain() {
+            if !members.contains_key(&key) {
+                membership_changed(member, MembershipChange::Left);
+            }
+        }
+
+        for group in &groups {
+            let service_group = group.service_group.to_string();
+            for &(update, leader) in &[(false, group.leader()), (true, group.update_leader())] {
+                let leader = match leader {
+                    Some(leader) => leader,
+                    None => continue,
+                };
+                let key = (service_group.clone(), update);
+                if self.leaders.get(&key) != Some(&leader.member_id) {
+                    publish(&ELECTION_FINISHED_SUBJECT,
+                            ElectionFinishedEvent { event_metadata: None,
+                                                    service_group: service_group.clone(),
+                                                    leader_member_id: leader.member_id.clone(),
+                                                    update });
+                    self.leaders.insert(key, leader.member_id.clone());
+                }
+            }
+        }
+
+        if snapshot_due {
+            publish(&CENSUS_SNAPSHOT_SUBJECT,
+                    CensusSnapshotEvent { event_metadata: None,
+                                          members:        members.values().cloned().collect(), });
+            self.last_snapshot = Some(Instant::now());
+        }
+        self.members = members;
+    }
+}
+
+fn membership_changed(member: CensusMemberMessage, change: MembershipChange) {
+    publish(&MEMBERSHIP_CHANGED_SUBJECT,
+            MembershipChangedEvent { event_metadata: None,
+                                     member:         Some(member),
+                                     change:         change.into(), });
+}
+
+fn census_member(group: &CensusGroup, member: &CensusMember) -> CensusMemberMessage {
+    let health = if member.alive() {
+        MemberHealth::Alive
+    } else if member.suspect() {
+        MemberHealth::Suspect
+    } else if member.confirmed() {
+        MemberHealth::Confirmed
+    } else {
+        MemberHealth::Departed
+    };
+    CensusMemberMessage { member_id:     member.member_id.clone(),
+                          service_group: group.service_group.to_string(),
+                          package_ident: member.pkg.to_string(),
+                          ip:            member.sys.ip.clone(),
+                          hostname:      member.sys.hostname.clone(),
+                          health:        health.into(),
+                          leader:        member.leader,
+                          update_leader: member.update_leader, }
+}
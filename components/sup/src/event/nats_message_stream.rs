@@ -8,8 +8,13 @@ use rants::{error::Error as RantsError,
             native_tls::TlsConnector,
             Client,
             Subject};
+use std::sync::atomic::{AtomicUsize,
+                        Ordering};
 use tokio::time;
 
+/// The number of messages queued to be published but not yet handed to the client.
+static BACKLOG: AtomicUsize = AtomicUsize::new(0);
+
 /// The subject and payload of a NATS message.
 #[derive(Debug)]
 pub struct NatsMessage {
@@ -78,6 +83,7 @@ impl NatsMessageStream {
         // Spawn a task to handle publishing received messages
         tokio::spawn(async move {
             while let Some(packet) = rx.next().await {
+                BACKLOG.fetch_sub(1, Ordering::Relaxed);
                 if let Err(e) = client.publish(packet.subject, packet.payload()).await {
                     // We do not retry any messages. If we are not connected when the message is
                     // processed or there is an error in publishing the message, the message will
@@ -100,8 +106,13 @@ impl NatsMessageStream {
     /// Queues a NATS message to be published
     pub fn send(&self, event_packet: NatsMessage) {
         trace!("Queueing message: {:?}", event_packet);
+        BACKLOG.fetch_add(1, Ordering::Relaxed);
         if let Err(e) = self.0.unbounded_send(event_packet) {
+            BACKLOG.fetch_sub(1, Ordering::Relaxed);
             error!("Failed to queue message, err: {}", e);
         }
     }
+
+    /// The number of messages queued to be published which have not been published yet.
+    pub fn backlog(&self) -> usize { BACKLOG.load(Ordering::Relaxed) }
 }
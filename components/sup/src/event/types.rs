@@ -104,3 +104,9 @@ event_msg_impl!(ServiceStartedEvent);
 event_msg_impl!(ServiceStoppedEvent);
 event_msg_impl!(ServiceUpdateStartedEvent);
 event_msg_impl!(HealthCheckEvent);
+event_msg_impl!(ServiceOutputEvent);
+event_msg_impl!(HookFailedEvent);
+event_msg_impl!(ServiceResourceUsageEvent);
+event_msg_impl!(CensusSnapshotEvent);
+event_msg_impl!(MembershipChangedEvent);
+event_msg_impl!(ElectionFinishedEvent);
@@ -0,0 +1,453 @@
+//! Certificates for the TLS of the HTTP and ctl gateways.
+//!
+//! Besides certificates read from files, the Supervisor can provide its own:
+//!
+//! * A self-signed certificate, generated the first time the Supervisor needs it and kept in its
+//!   data directory. Its fingerprint is shown so that clients can be told to trust it.
+//! * A certificate from an ACME CA, such as Let's Encrypt. The Supervisor answers the CA's HTTP-01
+//!   challenges itself, and renews the certificate when it nears its expiry. A renewed certificate
+//!   is served to new connections straight away, without restarting the gateways. A still valid
+//!   certificate ordered before is served without contacting the CA, which is only asked for a
+//!   renewal in the background, so the Supervisor starts even while the CA is unreachable.
+
+use crate::error::{Error,
+                   Result};
+use acme_lib::{persist::{FilePersist,
+                         Persist,
+                         PersistKey,
+                         PersistKind},
+               Directory,
+               DirectoryUrl};
+use habitat_common::outputln;
+use habitat_sup_protocol::tls::fingerprint;
+use rustls::{internal::pemfile,
+             sign::{self,
+                    CertifiedKey},
+             ClientHello,
+             PrivateKey,
+             ResolvesServerCert};
+use std::{fs,
+          io::{BufRead,
+               BufReader,
+               Read,
+               Write},
+          net::{SocketAddr,
+                TcpListener},
+          path::{Path,
+                 PathBuf},
+          sync::{atomic::{AtomicBool,
+                          Ordering},
+                 Arc,
+                 RwLock},
+          thread,
+          time::{Duration,
+                 SystemTime,
+                 UNIX_EPOCH}};
+
+static LOGKEY: &str = "GC";
+
+/// ACME certificates are renewed when they have fewer days than this left.
+const ACME_RENEW_DAYS_LEFT: i64 = 30;
+/// How often to check whether the ACME certificate needs renewing.
+const ACME_RENEWAL_CHECK_PERIOD: Duration = Duration::from_secs(12 * 60 * 60);
+/// How often to poll the ACME CA while it validates a challenge or issues a certificate.
+const ACME_POLL_INTERVAL_MS: u64 = 5_000;
+
+const CERTS_DIR: &str = "gateway-certs";
+const SELF_SIGNED_CERT_FILE: &str = "self-signed-cert.pem";
+const SELF_SIGNED_KEY_FILE: &str = "self-signed-key.pem";
+const ACME_DIR: &str = "acme";
+const ACME_VALID_UNTIL_EXTENSION: &str = "valid-until";
+const ACME_CHALLENGE_PATH: &str = "/.well-known/acme-challenge/";
+
+/// Where the certificate of the gateways comes from, when it is not read from files.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CertSource {
+    SelfSigned,
+    Acme(AcmeConfig),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AcmeConfig {
+    /// The domains the certificate is for. The first is its subject.
+    pub domains:          Vec<String>,
+    /// The contact address of the ACME account
+    pub email:            String,
+    /// The URL of the directory of the ACME CA
+    pub directory:        String,
+    /// Where to answer HTTP-01 challenges. The CA connects to port 80 of each domain.
+    pub challenge_listen: SocketAddr,
+}
+
+/// The certificate the gateways currently serve, which it hands to rustls for every new
+/// connection.
+pub struct GatewayCerts {
+    source:  Option<(CertSource, PathBuf)>,
+    current: RwLock<CertifiedKey>,
+}
+
+impl ResolvesServerCert for GatewayCerts {
+    fn resolve(&self, _client_hello: ClientHello) -> Option<CertifiedKey> {
+        Some(self.current
+                 .read()
+                 .expect("GatewayCerts lock poisoned")
+                 .clone())
+    }
+}
+
+impl GatewayCerts {
+    /// Serve the certificates and key in the given files.
+    pub fn from_files(cert_path: &Path, key_path: &Path) -> Result<Self> {
+        let current = read_certified_key(cert_path, key_path)?;
+        Ok(GatewayCerts { source:  None,
+                          current: RwLock::new(current), })
+    }
+
+    /// Provide a certificate from the given source, using the one kept in `data_path` from
+    /// before if there is one and it's still good.
+    pub fn provide(source: CertSource, data_path: &Path, hostname: &str) -> Result<Self> {
+        let dir = data_path.join(CERTS_DIR);
+        fs::create_dir_all(&dir).map_err(|e| Error::BadDataPath(dir.clone(), e))?;
+        let current = match source {
+            CertSource::SelfSigned => self_signed(&dir, hostname)?,
+            CertSource::Acme(ref config) => {
+                match cached_acme(config, &dir)? {
+                    Some(kept) => kept,
+                    None => acme(config, &dir)?.1,
+                }
+            }
+        };
+        let certs = GatewayCerts { source:  Some((source, dir)),
+                                   current: RwLock::new(current), };
+        outputln!("Serving the gateways with the certificate with SHA-256 fingerprint {}",
+                  certs.fingerprint());
+        Ok(certs)
+    }
+
+    /// The fingerprint of the certificate currently being served.
+    pub fn fingerprint(&self) -> String {
+        let current = self.current.read().expect("GatewayCerts lock poisoned");
+        fingerprint(&current.cert[0].0)
+    }
+
+    /// Renew the certificate in the background whenever it nears its expiry, checking straight
+    /// away in case it was kept from before. Only ACME certificates are renewed; the others are
+    /// served until they are replaced by hand.
+    pub fn start_renewal(self: Arc<Self>) -> Result<()> {
+        let config = match self.source {
+            Some((CertSource::Acme(ref config), ref dir)) => (config.clone(), dir.clone()),
+            _ => return Ok(()),
+        };
+        thread::Builder::new().name("gateway-certs".to_string())
+                              .spawn(move || {
+                                  loop {
+                                      self.renew(&config.0, &config.1);
+                                      thread::sleep(ACME_RENEWAL_CHECK_PERIOD);
+                                  }
+                              })?;
+        Ok(())
+    }
+
+    fn renew(&self, config: &AcmeConfig, dir: &Path) {
+        match acme(config, dir) {
+            Ok((true, renewed)) => {
+                *self.current.write().expect("GatewayCerts lock poisoned") = renewed;
+                outputln!("Renewed the gateway certificate, now serving the one with SHA-256 \
+                           fingerprint {}",
+                          self.fingerprint());
+            }
+            Ok((false, _)) => {}
+            Err(err) => outputln!("Failed to renew the gateway certificate, {}", err),
+        }
+    }
+}
+
+/// The self-signed certificate kept in `dir`, generated if there is none yet. It is kept for as
+/// long as it exists so that its fingerprint stays the same.
+fn self_signed(dir: &Path, hostname: &str) -> Result<CertifiedKey> {
+    let cert_path = dir.join(SELF_SIGNED_CERT_FILE);
+    let key_path = dir.join(SELF_SIGNED_KEY_FILE);
+    if cert_path.is_file() && key_path.is_file() {
+        return read_certified_key(&cert_path, &key_path);
+    }
+    outputln!("Generating a self-signed certificate for the gateways");
+    let names = vec![hostname.to_string(), "localhost".to_string()];
+    let cert = rcgen::generate_simple_self_signed(names).map_err(cert_error)?;
+    let cert_pem = cert.serialize_pem().map_err(cert_error)?;
+    let key_pem = cert.serialize_private_key_pem();
+    write_private(&key_path, &key_pem)?;
+    fs::write(&cert_path, &cert_pem).map_err(|e| Error::BadDataFile(cert_path.clone(), e))?;
+    read_certified_key(&cert_path, &key_path)
+}
+
+/// The ACME certificate kept in `dir`, if it was ordered before and is still valid, without
+/// contacting the CA.
+fn cached_acme(config: &AcmeConfig, dir: &Path) -> Result<Option<CertifiedKey>> {
+    let acme_dir = dir.join(ACME_DIR);
+    let (primary, _) = config.domains
+                             .split_first()
+                             .expect("ACME certificates need at least one domain");
+    let valid_until: u64 = match fs::read_to_string(valid_until_path(&acme_dir, primary)) {
+        Ok(content) => content.trim().parse().unwrap_or(0),
+        Err(_) => 0,
+    };
+    if valid_until <= unix_now() {
+        return Ok(None);
+    }
+    let persist = FilePersist::new(&acme_dir);
+    let cert = persist.get(&PersistKey::new(&config.email, PersistKind::Certificate, primary))
+                      .map_err(cert_error)?;
+    let key = persist.get(&PersistKey::new(&config.email, PersistKind::PrivateKey, primary))
+                     .map_err(cert_error)?;
+    match (cert, key) {
+        (Some(cert), Some(key)) => Ok(certified_key(&cert, &key).ok()),
+        _ => Ok(None),
+    }
+}
+
+/// The ACME certificate kept in `dir`, ordered from the CA if there is none or it nears its
+/// expiry. Returns whether the certificate was newly ordered along with it.
+fn acme(config: &AcmeConfig, dir: &Path) -> Result<(bool, CertifiedKey)> {
+    let acme_dir = dir.join(ACME_DIR);
+    fs::create_dir_all(&acme_dir).map_err(|e| Error::BadDataPath(acme_dir.clone(), e))?;
+    let persist = FilePersist::new(&acme_dir);
+    let directory =
+        Directory::from_url(persist, DirectoryUrl::Other(&config.directory)).map_err(cert_error)?;
+    let account = directory.account(&config.email).map_err(cert_error)?;
+    let (primary, alt_names) = config.domains
+                                     .split_first()
+                                     .expect("ACME certificates need at least one domain");
+
+    if let Some(cert) = account.certificate(primary).map_err(cert_error)? {
+        record_valid_until(&acme_dir, primary, cert.valid_days_left())?;
+        if cert.valid_days_left() > ACME_RENEW_DAYS_LEFT {
+            let kept = acme_certified_key(cert.certificate(), cert.private_key())?;
+            return Ok((false, kept));
+        }
+        outputln!("The gateway certificate for {} expires in {} days, renewing it",
+                  primary,
+                  cert.valid_days_left());
+    }
+
+    outputln!("Ordering a certificate for the gateways from {}",
+              config.directory);
+    let alt_names: Vec<&str> = alt_names.iter().map(String::as_str).collect();
+    let mut order = account.new_order(primary, &alt_names).map_err(cert_error)?;
+    let order = loop {
+        if let Some(order) = order.confirm_validations() {
+            break order;
+        }
+        for auth in order.authorizations().map_err(cert_error)? {
+            if !auth.need_challenge() {
+                continue;
+            }
+            let challenge = auth.http_challenge();
+            let responder = ChallengeResponder::start(config.challenge_listen,
+                                                      challenge.http_token(),
+                                                      challenge.http_proof())?;
+            let validated = challenge.validate(ACME_POLL_INTERVAL_MS);
+            responder.stop();
+            validated.map_err(cert_error)?;
+        }
+        order.refresh().map_err(cert_error)?;
+    };
+    let key = acme_lib::create_p384_key();
+    let cert = order.finalize_pkey(key, ACME_POLL_INTERVAL_MS)
+                    .and_then(|order| order.download_and_save_cert())
+                    .map_err(cert_error)?;
+    outputln!("Obtained a certificate for {}, valid for {} days",
+              primary,
+              cert.valid_days_left());
+    record_valid_until(&acme_dir, primary, cert.valid_days_left())?;
+    Ok((true, acme_certified_key(cert.certificate(), cert.private_key())?))
+}
+
+/// Record until when the ACME certificate for `primary` is valid, so that it can be served again
+/// without asking the CA.
+fn record_valid_until(acme_dir: &Path, primary: &str, days_left: i64) -> Result<()> {
+    let valid_until = unix_now() + days_left.max(0) as u64 * 24 * 60 * 60;
+    let path = valid_until_path(acme_dir, primary);
+    fs::write(&path, valid_until.to_string()).map_err(|e| Error::BadDataFile(path, e))
+}
+
+fn valid_until_path(acme_dir: &Path, primary: &str) -> PathBuf {
+    acme_dir.join(format!("{}.{}", primary, ACME_VALID_UNTIL_EXTENSION))
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH)
+                     .map(|d| d.as_secs())
+                     .unwrap_or(0)
+}
+
+fn acme_certified_key(certs: &str, key: &str) -> Result<CertifiedKey> {
+    certified_key(certs.as_bytes(), key.as_bytes()).map_err(|_| {
+                                                       Error::GatewayCert("the ACME CA issued a \
+                                                                           certificate that can't \
+                                                                           be served"
+                                                                                     .to_string())
+                                                   })
+}
+
+/// Answers the HTTP-01 challenge of an ACME CA for one token, until it is stopped.
+struct ChallengeResponder {
+    stop:   Arc<AtomicBool>,
+    handle: thread::JoinHandle<()>,
+}
+
+impl ChallengeResponder {
+    fn start(listen: SocketAddr, token: &str, proof: String) -> Result<Self> {
+        let listener =
+            TcpListener::bind(listen).map_err(|_| Error::BadAddress(listen.to_string()))?;
+        listener.set_nonblocking(true)?;
+        let path = format!("{}{}", ACME_CHALLENGE_PATH, token);
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = Arc::clone(&stop);
+        let handle =
+            thread::Builder::new().name("acme-challenge".to_string())
+                                  .spawn(move || {
+                                      while !stopped.load(Ordering::Relaxed) {
+                                          match listener.accept() {
+                                              Ok((stream, _)) => respond(stream, &path, &proof),
+                                              Err(_) => thread::sleep(Duration::from_millis(100)),
+                                          }
+                                      }
+                                  })?;
+        Ok(ChallengeResponder { stop, handle })
+    }
+
+    fn stop(self) {
+        self.stop.store(true, Ordering::Relaxed);
+        self.handle.join().ok();
+    }
+}
+
+/// Answer one HTTP request with the proof if it asks for the challenge's path.
+fn respond(stream: std::net::TcpStream, path: &str, proof: &str) {
+    stream.set_nonblocking(false).ok();
+    stream.set_read_timeout(Some(Duration::from_secs(5))).ok();
+    let mut request_line = String::new();
+    let mut reader = BufReader::new(&stream);
+    if reader.by_ref()
+             .take(8192)
+             .read_line(&mut request_line)
+             .is_err()
+    {
+        return;
+    }
+    let requested = request_line.split_whitespace().nth(1);
+    let response = if requested == Some(path) {
+        format!("HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: \
+                 close\r\n\r\n{}",
+                proof.len(),
+                proof)
+    } else {
+        "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+    };
+    (&stream).write_all(response.as_bytes()).ok();
+}
+
+fn read_certified_key(cert_path: &Path, key_path: &Path) -> Result<CertifiedKey> {
+    let certs = fs::read(cert_path).map_err(|e| Error::BadDataFile(cert_path.into(), e))?;
+    let key = fs::read(key_path).map_err(|e| Error::BadDataFile(key_path.into(), e))?;
+    certified_key(&certs, &key).map_err(|e| {
+                                   match e {
+                                       Invalid::Certs => Error::InvalidCertFile(cert_path.into()),
+                                       Invalid::Key => Error::InvalidKeyFile(key_path.into()),
+                                   }
+                               })
+}
+
+enum Invalid {
+    Certs,
+    Key,
+}
+
+/// The certificates and key in the given PEM, ready to be served.
+fn certified_key(certs: &[u8], key: &[u8]) -> std::result::Result<CertifiedKey, Invalid> {
+    let certs = pemfile::certs(&mut &certs[..]).ok()
+                                               .filter(|c| !c.is_empty())
+                                               .ok_or(Invalid::Certs)?;
+    let key = private_key(key).ok_or(Invalid::Key)?;
+    let key = sign::any_supported_type(&key).map_err(|_| Invalid::Key)?;
+    Ok(CertifiedKey::new(certs, Arc::new(key)))
+}
+
+fn private_key(pem: &[u8]) -> Option<PrivateKey> {
+    let mut keys = pemfile::pkcs8_private_keys(&mut &pem[..]).unwrap_or_default();
+    if keys.is_empty() {
+        keys = pemfile::rsa_private_keys(&mut &pem[..]).unwrap_or_default();
+    }
+    keys.pop()
+}
+
+/// Write a private key readable only by the Supervisor.
+fn write_private(path: &Path, content: &str) -> Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path)
+           .and_then(|mut file| file.write_all(content.as_bytes()))
+           .map_err(|e| Error::BadDataFile(path.into(), e))
+}
+
+fn cert_error(err: impl std::fmt::Display) -> Error { Error::GatewayCert(err.to_string()) }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn self_signed_certificates_are_kept() {
+        let data_path = TempDir::new().unwrap();
+        let certs =
+            GatewayCerts::provide(CertSource::SelfSigned, data_path.path(), "sup.local").unwrap();
+        let again =
+            GatewayCerts::provide(CertSource::SelfSigned, data_path.path(), "sup.local").unwrap();
+        assert_eq!(certs.fingerprint(), again.fingerprint());
+
+        let cert_path = data_path.path().join(CERTS_DIR).join(SELF_SIGNED_CERT_FILE);
+        let key_path = data_path.path().join(CERTS_DIR).join(SELF_SIGNED_KEY_FILE);
+        let from_files = GatewayCerts::from_files(&cert_path, &key_path).unwrap();
+        assert_eq!(certs.fingerprint(), from_files.fingerprint());
+        assert!(matches!(GatewayCerts::from_files(&key_path, &key_path),
+                         Err(Error::InvalidCertFile(_))));
+        assert!(matches!(GatewayCerts::from_files(&cert_path, &cert_path),
+                         Err(Error::InvalidKeyFile(_))));
+    }
+
+    #[test]
+    fn valid_acme_certificates_are_served_without_the_ca() {
+        let dir = TempDir::new().unwrap();
+        let acme_dir = dir.path().join(ACME_DIR);
+        fs::create_dir_all(&acme_dir).unwrap();
+        // Nothing that answers at this address
+        let config = AcmeConfig { domains:          vec!["sup.example.com".to_string()],
+                                  email:            "ops@example.com".to_string(),
+                                  directory:        "http://127.0.0.1:1/directory".to_string(),
+                                  challenge_listen: "127.0.0.1:0".parse().unwrap(), };
+        assert!(cached_acme(&config, dir.path()).unwrap().is_none());
+
+        let cert = rcgen::generate_simple_self_signed(config.domains.clone()).unwrap();
+        let cert_pem = cert.serialize_pem().unwrap();
+        let persist = FilePersist::new(&acme_dir);
+        let primary = &config.domains[0];
+        persist.put(&PersistKey::new(&config.email, PersistKind::Certificate, primary),
+                    cert_pem.as_bytes())
+               .unwrap();
+        persist.put(&PersistKey::new(&config.email, PersistKind::PrivateKey, primary),
+                    cert.serialize_private_key_pem().as_bytes())
+               .unwrap();
+        record_valid_until(&acme_dir, primary, 60).unwrap();
+        let certs =
+            GatewayCerts::provide(CertSource::Acme(config.clone()), dir.path(), "sup").unwrap();
+        let served = pemfile::certs(&mut cert_pem.as_bytes()).unwrap();
+        assert_eq!(certs.fingerprint(), fingerprint(&served[0].0));
+
+        // An expired certificate must be ordered again
+        record_valid_until(&acme_dir, primary, 0).unwrap();
+        assert!(cached_acme(&config, dir.path()).unwrap().is_none());
+    }
+}
@@ -13,6 +13,7 @@ use actix_web::{dev::{Body,
                       Path},
                 App,
                 Error,
+                HttpRequest,
                 HttpResponse,
                 HttpServer,
                 Scope};
@@ -44,7 +45,8 @@ use std::{self,
           sync::{Arc,
                  Condvar,
                  Mutex},
-          thread};
+          thread,
+          time::Duration};
 
 const APIDOCS: &str = include_str!(concat!(env!("OUT_DIR"), "/api.html"));
 pub const HTTP_THREADS_ENVVAR: &str = "HAB_SUP_HTTP_THREADS";
@@ -78,6 +80,27 @@ habitat_core::env_config!(
     s,
     Ok(GatewayAuthenticationToken(Some(String::from(s)))));
 
+habitat_core::env_config_duration!(
+    /// How long the Supervisor's main loop may go without running before the `/live` endpoint
+    /// reports it as stuck.
+    LivenessTimeout,
+    HAB_SUP_LIVENESS_TIMEOUT_SECS => from_secs,
+    Duration::from_secs(60));
+
+habitat_core::env_config_duration!(
+    /// How long the `/census` and `/services` documents may be cached. The Supervisor refreshes
+    /// them at most this often, and clients and proxies may reuse them for this long. With the
+    /// default of 0, they are refreshed on every change and clients must revalidate them.
+    HttpCacheTtl,
+    HAB_SUP_HTTP_CACHE_SECS => from_secs,
+    Duration::from_secs(0));
+
+/// How long the `/census` and `/services` documents may be cached, see `HttpCacheTtl`.
+pub fn cache_ttl() -> Duration { HttpCacheTtl::configured_value().into() }
+
+/// Endpoints used to probe the Supervisor itself, which don't require authentication.
+const PROBE_PATHS: &[&str] = &["/live", "/ready"];
+
 #[derive(Default, Serialize)]
 struct HealthCheckBody {
     status: String,
@@ -100,6 +123,7 @@ struct AppState {
     authentication_token: Option<String>,
     timer:                Cell<Option<HistogramTimer>>,
     feature_flags:        FeatureFlag,
+    cache_ttl:            Duration,
 }
 
 impl AppState {
@@ -112,7 +136,8 @@ impl AppState {
                    // GatewayAuthenticationToken type has done its job by this point.
                    authentication_token: authentication_token.0,
                    timer: Cell::new(None),
-                   feature_flags }
+                   feature_flags,
+                   cache_ttl: cache_ttl() }
     }
 }
 
@@ -133,6 +158,11 @@ fn authentication_middleware<S>(req: ServiceRequest,
         return Either::Left(srv.call(req));
     };
 
+    // Probes from systemd, load balancers and the like can't be expected to carry a token.
+    if PROBE_PATHS.contains(&req.path()) {
+        return Either::Left(srv.call(req));
+    }
+
     // From this point forward, we know that we have an
     // authentication token in the state. Therefore, anything
     // short of a fully formed Authorization header (yes,
@@ -286,11 +316,15 @@ fn services_routes() -> Scope {
                                   web::get().to(config_without_org_gsr))
                            .route("/{svc}/{group}/health",
                                   web::get().to(health_without_org_gsr))
+                           .route("/{svc}/{group}/health/history",
+                                  web::get().to(health_history_without_org_gsr))
                            .route("/{svc}/{group}/{org}", web::get().to(service_with_org_gsr))
                            .route("/{svc}/{group}/{org}/config",
                                   web::get().to(config_with_org_gsr))
                            .route("/{svc}/{group}/{org}/health",
                                   web::get().to(health_with_org_gsr))
+                           .route("/{svc}/{group}/{org}/health/history",
+                                  web::get().to(health_history_with_org_gsr))
 }
 
 fn routes() -> Scope {
@@ -301,6 +335,8 @@ fn routes() -> Scope {
                    .service(web::resource("/census").route(web::get().to(census_gsr))
                                                     .wrap_fn(redact_http_middleware))
                    .route("/metrics", web::get().to(metrics))
+                   .route("/live", web::get().to(live_gsr))
+                   .route("/ready", web::get().to(ready_gsr))
 }
 
 fn json_response(data: String) -> HttpResponse {
@@ -308,6 +344,46 @@ fn json_response(data: String) -> HttpResponse {
                       .body(data)
 }
 
+/// Respond with a JSON document and its entity tag, or with 304 Not Modified if the client
+/// already has the document, as told by its `If-None-Match` header.
+fn cached_json_response(req: &HttpRequest,
+                        data: &str,
+                        etag: &str,
+                        cache_ttl: Duration)
+                        -> HttpResponse {
+    let cache_control = if cache_ttl.as_secs() == 0 {
+        String::from("no-cache")
+    } else {
+        format!("max-age={}", cache_ttl.as_secs())
+    };
+    let not_modified = req.headers()
+                          .get(http::header::IF_NONE_MATCH)
+                          .and_then(|hv| hv.to_str().ok())
+                          .map_or(false, |if_none_match| etag_matches(if_none_match, etag));
+    let mut response = if not_modified {
+        HttpResponse::NotModified()
+    } else {
+        HttpResponse::Ok()
+    };
+    response.header(http::header::ETAG, etag)
+            .header(http::header::CACHE_CONTROL, cache_control);
+    if not_modified {
+        response.finish()
+    } else {
+        response.content_type("application/json")
+                .body(data.to_string())
+    }
+}
+
+/// Whether an `If-None-Match` header matches an entity tag, using the weak comparison RFC 7232
+/// requires for it.
+fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    let etag = etag.trim_start_matches("W/");
+    if_none_match.split(',')
+                 .map(str::trim)
+                 .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+}
+
 // Begin route handlers
 
 /// # Locking (see locking.md)
@@ -321,17 +397,49 @@ fn butterfly_gsr(state: Data<AppState>) -> HttpResponse {
 /// # Locking (see locking.md)
 /// * `GatewayState::inner` (read)
 #[allow(clippy::needless_pass_by_value)]
-fn census_gsr(state: Data<AppState>) -> HttpResponse {
-    let data = state.gateway_state.lock_gsr().census_data().to_string();
-    json_response(data)
+fn live_gsr(state: Data<AppState>) -> HttpResponse {
+    if state.gateway_state
+            .lock_gsr()
+            .is_alive(LivenessTimeout::configured_value().into())
+    {
+        HttpResponse::Ok().finish()
+    } else {
+        HttpResponse::ServiceUnavailable().finish()
+    }
 }
 
 /// # Locking (see locking.md)
 /// * `GatewayState::inner` (read)
 #[allow(clippy::needless_pass_by_value)]
-fn services_gsr(state: Data<AppState>) -> HttpResponse {
-    let data = state.gateway_state.lock_gsr().services_data().to_string();
-    json_response(data)
+fn ready_gsr(state: Data<AppState>) -> HttpResponse {
+    let readiness = state.gateway_state.lock_gsr().readiness();
+    if readiness.is_ready() {
+        HttpResponse::Ok().json(readiness)
+    } else {
+        HttpResponse::ServiceUnavailable().json(readiness)
+    }
+}
+
+/// # Locking (see locking.md)
+/// * `GatewayState::inner` (read)
+#[allow(clippy::needless_pass_by_value)]
+fn census_gsr(req: HttpRequest, state: Data<AppState>) -> HttpResponse {
+    let gateway_state = state.gateway_state.lock_gsr();
+    cached_json_response(&req,
+                         gateway_state.census_data(),
+                         gateway_state.census_etag(),
+                         state.cache_ttl)
+}
+
+/// # Locking (see locking.md)
+/// * `GatewayState::inner` (read)
+#[allow(clippy::needless_pass_by_value)]
+fn services_gsr(req: HttpRequest, state: Data<AppState>) -> HttpResponse {
+    let gateway_state = state.gateway_state.lock_gsr();
+    cached_json_response(&req,
+                         gateway_state.services_data(),
+                         gateway_state.services_etag(),
+                         state.cache_ttl)
 }
 
 /// # Locking (see locking.md)
@@ -418,6 +526,50 @@ fn health_gsr(svc: String, group: String, org: Option<&str>, state: &AppState) -
     }
 }
 
+/// # Locking (see locking.md)
+/// * `GatewayState::inner` (read)
+#[allow(clippy::needless_pass_by_value)]
+fn health_history_with_org_gsr(path: Path<(String, String, String)>,
+                               state: Data<AppState>)
+                               -> HttpResponse {
+    let (svc, group, org) = path.into_inner();
+    health_history_gsr(svc, group, Some(&org), &state)
+}
+
+/// # Locking (see locking.md)
+/// * `GatewayState::inner` (read)
+#[allow(clippy::needless_pass_by_value)]
+fn health_history_without_org_gsr(path: Path<(String, String)>,
+                                  state: Data<AppState>)
+                                  -> HttpResponse {
+    let (svc, group) = path.into_inner();
+    health_history_gsr(svc, group, None, &state)
+}
+
+/// # Locking (see locking.md)
+/// * `GatewayState::inner` (read)
+fn health_history_gsr(svc: String,
+                      group: String,
+                      org: Option<&str>,
+                      state: &AppState)
+                      -> HttpResponse {
+    let service_group = match ServiceGroup::new(svc, group, org) {
+        Ok(sg) => sg,
+        Err(_) => return HttpResponse::BadRequest().finish(),
+    };
+
+    if let Some(history) = state.gateway_state
+                                .lock_gsr()
+                                .health_history_of(&service_group)
+    {
+        HttpResponse::Ok().json(history)
+    } else {
+        debug!("Didn't find any health history for service group {:?}",
+               &service_group);
+        HttpResponse::NotFound().finish()
+    }
+}
+
 /// # Locking (see locking.md)
 /// * `GatewayState::inner` (read)
 #[allow(clippy::needless_pass_by_value)]
@@ -488,6 +640,7 @@ fn service_from_services(service_group: &ServiceGroup, services_json: &str) -> O
 
 #[cfg(test)]
 mod tests {
+    use super::etag_matches;
     use crate::test_helpers::*;
     use habitat_butterfly::{member::Member,
                             server::{Server,
@@ -515,6 +668,16 @@ mod tests {
         assert_valid(&json, schema);
     }
 
+    #[test]
+    fn if_none_match_is_compared_weakly() {
+        let etag = "\"0123456789abcdef\"";
+        assert!(etag_matches("\"0123456789abcdef\"", etag));
+        assert!(etag_matches("W/\"0123456789abcdef\"", etag));
+        assert!(etag_matches("\"fedcba9876543210\", \"0123456789abcdef\"", etag));
+        assert!(etag_matches("*", etag));
+        assert!(!etag_matches("\"fedcba9876543210\"", etag));
+    }
+
     #[test]
     fn sample_census_file_is_valid() {
         validate_sample_file_against_schema("sample-census-output.json",
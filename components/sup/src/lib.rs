@@ -60,9 +60,12 @@ pub mod command;
 pub mod ctl_gateway;
 pub mod error;
 pub mod event;
+pub mod gateway_certs;
 pub mod http_gateway;
 pub mod logger; // must be pub if used in the `hab-sup` binary
 pub mod manager;
+pub mod secrets;
+pub mod statsd;
 mod sys;
 #[cfg(test)]
 pub mod test_helpers;
@@ -17,15 +17,22 @@ use crate::sup::{cli::cli,
                  error::{Error,
                          Result},
                  event::EventStreamConfig,
+                 gateway_certs::{AcmeConfig,
+                                 CertSource},
                  logger,
-                 manager::{Manager,
+                 manager::{dry_run,
+                           service::ServiceOutputConfig,
+                           Manager,
                            ManagerConfig,
                            TLSConfig,
                            PROC_LOCK_FILE},
+                 secrets::VaultConfig,
+                 statsd::StatsdConfig,
                  util};
 use configopt::ConfigOpt;
 use hab::cli::hab::{sup::SupRun,
                     svc};
+use habitat_butterfly::tls::TlsConfig as GossipTlsConfig;
 use habitat_common::{command::package::install::InstallSource,
                      liveliness_checker,
                      output::{self,
@@ -35,6 +42,7 @@ use habitat_common::{command::package::install::InstallSource,
                      types::GossipListenAddr,
                      ui::{self,
                           UI},
+                     util::rotating_log::LogRotation,
                      FeatureFlag};
 use habitat_core::{self,
                    crypto::{self,
@@ -52,7 +60,8 @@ use std::{convert::TryInto,
           net::{IpAddr,
                 Ipv4Addr},
           process,
-          str::{self}};
+          str::{self},
+          time::Duration};
 use tokio::{self,
             runtime::Builder as RuntimeBuilder};
 
@@ -176,6 +185,9 @@ async fn start_rsr_imlw_mlw_gsw_smw_rhw_msw(feature_flags: FeatureFlag) -> Resul
                     }
                 }
             };
+            if sup_run.dry_run {
+                return sub_run_dry_run(sup_run, feature_flags).await;
+            }
             let launcher = launcher.ok_or(Error::NoLauncher)?;
             sub_run_rsr_imlw_mlw_gsw_smw_rhw_msw(sup_run, launcher, feature_flags).await
         }
@@ -200,26 +212,18 @@ async fn sub_run_rsr_imlw_mlw_gsw_smw_rhw_msw(sup_run: SupRun,
                                               feature_flags: FeatureFlag)
                                               -> Result<()> {
     set_supervisor_logging_options(&sup_run);
-
-    let mut svc_load_msgs = if feature_flags.contains(FeatureFlag::SERVICE_CONFIG_FILES) {
-        svc::svc_loads_from_paths(&sup_run.svc_config_paths)?.into_iter()
-                                                             .map(|svc_load| {
-                                                                 Ok(svc_load.try_into()?)
-                                                             })
-                                                             .collect::<Result<Vec<_>>>()?
-    } else {
-        vec![]
-    };
-
-    let (manager_cfg, maybe_svc_load_msg) = split_apart_sup_run(sup_run, feature_flags).await?;
-    if let Some(svc_load_msg) = maybe_svc_load_msg {
-        svc_load_msgs.push(svc_load_msg);
-    }
+    let (manager_cfg, svc_load_msgs) = manager_cfg_and_svc_loads(sup_run, feature_flags).await?;
     let manager = Manager::load_imlw(manager_cfg, launcher).await?;
     manager.run_rsw_imlw_mlw_gsw_smw_rhw_msw(svc_load_msgs)
            .await
 }
 
+async fn sub_run_dry_run(sup_run: SupRun, feature_flags: FeatureFlag) -> Result<()> {
+    set_supervisor_logging_options(&sup_run);
+    let (manager_cfg, svc_load_msgs) = manager_cfg_and_svc_loads(sup_run, feature_flags).await?;
+    dry_run::run(&manager_cfg, svc_load_msgs).await
+}
+
 async fn sub_sh() -> Result<()> { command::shell::sh().await }
 
 fn sub_term() -> Result<()> {
@@ -239,6 +243,29 @@ fn sub_term() -> Result<()> {
 // Internal Implementation Details
 ////////////////////////////////////////////////////////////////////////
 
+/// The config of the Supervisor, and the services to load on startup: those of the service config
+/// files, then the one given on the command line, if any.
+async fn manager_cfg_and_svc_loads(sup_run: SupRun,
+                                   feature_flags: FeatureFlag)
+                                   -> Result<(ManagerConfig, Vec<sup_proto::ctl::SvcLoad>)> {
+    let mut svc_load_msgs = if feature_flags.contains(FeatureFlag::SERVICE_CONFIG_FILES) {
+        let svc_loads = svc::svc_loads_from_paths(&sup_run.svc_config_paths,
+                                                  &sup_run.svc_config_exclude,
+                                                  sup_run.svc_config_order_file.as_deref())?;
+        svc_loads.into_iter()
+                 .map(|svc_load| Ok(svc_load.try_into()?))
+                 .collect::<Result<Vec<_>>>()?
+    } else {
+        vec![]
+    };
+
+    let (manager_cfg, maybe_svc_load_msg) = split_apart_sup_run(sup_run, feature_flags).await?;
+    if let Some(svc_load_msg) = maybe_svc_load_msg {
+        svc_load_msgs.push(svc_load_msg);
+    }
+    Ok((manager_cfg, svc_load_msgs))
+}
+
 async fn split_apart_sup_run(sup_run: SupRun,
                              feature_flags: FeatureFlag)
                              -> Result<(ManagerConfig, Option<sup_proto::ctl::SvcLoad>)> {
@@ -268,6 +295,23 @@ async fn split_apart_sup_run(sup_run: SupRun,
         None
     };
 
+    let vault_config = if let Some(url) = sup_run.vault_url {
+        Some(VaultConfig { url:   url.to_string(),
+                           token: sup_run.vault_token
+                                         .expect("Required option for Vault secrets"), })
+    } else {
+        None
+    };
+
+    let statsd_config = if let Some(host) = sup_run.statsd_host {
+        Some(StatsdConfig { host,
+                            port: sup_run.statsd_port,
+                            prefix: sup_run.statsd_prefix,
+                            tags: sup_run.statsd_tag })
+    } else {
+        None
+    };
+
     let tls_config = if let Some(key_file) = sup_run.key_file {
         let cert_path =
             sup_run.cert_file
@@ -279,22 +323,68 @@ async fn split_apart_sup_run(sup_run: SupRun,
         None
     };
 
+    let gateway_cert_source = if sup_run.self_signed_certs {
+        Some(CertSource::SelfSigned)
+    } else if !sup_run.acme_domain.is_empty() {
+        let email =
+            sup_run.acme_email
+                   .expect("`acme_email` should always have a value if `acme_domain` has a value.");
+        Some(CertSource::Acme(AcmeConfig { domains: sup_run.acme_domain,
+                                           email,
+                                           directory: sup_run.acme_directory,
+                                           challenge_listen: sup_run.acme_challenge_listen }))
+    } else {
+        None
+    };
+
+    let gossip_tls_config = if let Some(key_path) = sup_run.gossip_key_file {
+        let cert_path =
+            sup_run.gossip_cert_file
+                   .expect("`gossip_cert_file` should always have a value if `gossip_key_file` \
+                            has a value.");
+        let ca_cert_path =
+            sup_run.gossip_ca_cert_file
+                   .expect("`gossip_ca_cert_file` should always have a value if \
+                            `gossip_key_file` has a value.");
+        Some(GossipTlsConfig { cert_path,
+                               key_path,
+                               ca_cert_path,
+                               revoked_certs_path: sup_run.gossip_revoked_certs_file })
+    } else {
+        None
+    };
+
     let bldr_url = habitat_core::url::bldr_url(shared_load.bldr_url.as_ref());
 
     let key_cache = KeyCache::new(sup_run.cache_key_path.cache_key_path);
     key_cache.setup()?;
 
-    let cfg = ManagerConfig { auto_update: sup_run.auto_update,
+    let log_rotation = LogRotation { max_bytes: sup_run.service_log_max_bytes,
+                                     max_age:   sup_run.service_log_max_age.map(Duration::from),
+                                     max_files: sup_run.service_log_max_files, };
+    let service_output = ServiceOutputConfig { log_rotation,
+                                               format: sup_run.service_log_format,
+                                               forward: sup_run.service_log_forward,
+                                               stdout_targets: sup_run.service_stdout_to,
+                                               stderr_targets: sup_run.service_stderr_to };
+
+    let ctl_request_timeout = sup_run.ctl_request_timeout.map(Duration::from);
+    let cfg = ManagerConfig { auto_update: sup_run.auto_update
+                                           || sup_run.auto_update_sup_channel.is_some(),
                               auto_update_period: sup_run.auto_update_period.into(),
+                              auto_update_window: sup_run.auto_update_window,
                               service_update_period: sup_run.service_update_period.into(),
                               custom_state_path: None, // remove entirely?
                               key_cache,
                               update_url: bldr_url.clone(),
-                              update_channel: shared_load.channel.clone(),
+                              update_channel:
+                                  sup_run.auto_update_sup_channel
+                                         .unwrap_or_else(|| shared_load.channel.clone()),
                               http_disable: sup_run.http_disable,
                               organization: sup_run.organization,
                               gossip_permanent: sup_run.permanent_peer,
                               ring_key,
+                              gossip_tls_config,
                               gossip_peers: sup_run.peer,
                               watch_peer_file: sup_run.peer_watch_file
                                                       .map(|p| p.to_string_lossy().to_string()),
@@ -306,9 +396,21 @@ async fn split_apart_sup_run(sup_run: SupRun,
                               ctl_listen: sup_run.listen_ctl,
                               http_listen: sup_run.listen_http,
                               tls_config,
+                              gateway_cert_source,
+                              ctl_gateway_tls: sup_run.ctl_tls,
+                              ctl_listeners: sup_run.ctl_listener,
+                              ctl_request_timeout,
                               feature_flags,
                               event_stream_config,
+                              vault_config,
+                              statsd_config,
+                              encrypt_specs: sup_run.encrypt_specs,
                               keep_latest_packages: sup_run.keep_latest_packages,
+                              tags: sup_run.tag,
+                              sys_cloud_metadata: sup_run.sys_cloud_metadata,
+                              departure_timeout: sup_run.departure_timeout.into(),
+                              departed_retention: sup_run.departed_retention.map(Duration::from),
+                              service_output,
                               sys_ip: sup_run.sys_ip_address
                                              .or_else(|| {
                                                  let result_ip = habitat_core::util::sys::ip();
@@ -435,7 +537,9 @@ mod test {
         use super::*;
         use configopt::ConfigOpt;
         use futures::executor;
-        use habitat_common::types::EventStreamConnectMethod;
+        use habitat_common::{output::{OutputTargets,
+                                      ServiceOutputFormat},
+                             types::EventStreamConnectMethod};
         #[cfg(windows)]
         use habitat_core::crypto::dpapi::decrypt;
         use habitat_core::{crypto::keys::{Key,
@@ -502,6 +606,14 @@ mod test {
             assert_eq!(config.auto_update, false);
         }
 
+        #[test]
+        fn auto_update_sup_channel_should_enable_auto_update() {
+            let config = config_from_cmd_str("hab-sup run --channel unstable \
+                                              --auto-update-sup-channel stable");
+            assert_eq!(config.auto_update, true);
+            assert_eq!(config.update_channel, ChannelIdent::stable());
+        }
+
         #[test]
         fn update_url_should_be_set() {
             let config = config_from_cmd_str("hab-sup run -u http://fake.example.url");
@@ -631,6 +743,36 @@ mod test {
             assert_eq!(config.gossip_peers, expected_peers);
         }
 
+        #[test]
+        fn peers_can_be_ipv6() {
+            let config = config_from_cmd_str("hab-sup run --peer [fe80::1] [fe80::2]:9000 ::3");
+            let expected_peers: Vec<SocketAddr> = vec!["[fe80::1]:9638".parse().unwrap(),
+                                                       "[fe80::2]:9000".parse().unwrap(),
+                                                       "[::3]:9638".parse().unwrap()];
+            assert_eq!(config.gossip_peers, expected_peers);
+        }
+
+        #[test]
+        fn gossip_tls_config_should_be_set() {
+            let config = config_from_cmd_str("hab-sup run --gossip-key key.pem --gossip-certs \
+                                              certs.pem --gossip-ca-certs ca.pem \
+                                              --gossip-revoked-certs revoked.pem");
+            assert_eq!(config.gossip_tls_config,
+                       Some(GossipTlsConfig { cert_path:          "certs.pem".into(),
+                                              key_path:           "key.pem".into(),
+                                              ca_cert_path:       "ca.pem".into(),
+                                              revoked_certs_path: Some("revoked.pem".into()), }));
+
+            let config = config_from_cmd_str("hab-sup run");
+            assert_eq!(config.gossip_tls_config, None);
+        }
+
+        #[test]
+        fn fs_root_should_be_set() {
+            let sup_run = sup_run_from_cmd_str("hab-sup run --fs-root /srv/tenant-a");
+            assert_eq!(sup_run.fs_root, Some(PathBuf::from("/srv/tenant-a")));
+        }
+
         #[test]
         fn watch_peer_file_should_be_set() {
             let config = config_from_cmd_str("hab-sup run --peer-watch-file foobar");
@@ -715,6 +857,7 @@ gpoVMSncu2jMIDZX63IkQII=
             let config = config_from_cmd_str("hab-sup run");
             assert_eq!(ManagerConfig { auto_update:           false,
                                        auto_update_period:    Duration::from_secs(60),
+                                       auto_update_window:    None,
                                        service_update_period: Duration::from_secs(60),
                                        custom_state_path:     None,
                                        key_cache:             KeyCache::new(&*CACHE_KEY_PATH),
@@ -730,12 +873,25 @@ gpoVMSncu2jMIDZX63IkQII=
                                        ring_key:              None,
                                        organization:          None,
                                        watch_peer_file:       None,
+                                       gossip_tls_config:     None,
                                        tls_config:            None,
+                                       gateway_cert_source:   None,
+                                       ctl_gateway_tls:       false,
+                                       ctl_listeners:         vec![],
+                                       ctl_request_timeout:   None,
                                        feature_flags:         FeatureFlag::empty(),
                                        event_stream_config:   None,
+                                       vault_config:          None,
+                                       statsd_config:         None,
+                                       encrypt_specs:         false,
                                        keep_latest_packages:  None,
                                        sys_ip:
-                                           habitat_core::util::sys::ip().unwrap(), },
+                                           habitat_core::util::sys::ip().unwrap(),
+                                       tags:                  Vec::new(),
+                                       sys_cloud_metadata:    None,
+                                       departure_timeout:     Duration::from_secs(259_200),
+                                       departed_retention:    None,
+                                       service_output:        ServiceOutputConfig::default(), },
                        config);
 
             let maybe_service_load = maybe_service_load_from_cmd_str("hab-sup run");
@@ -768,23 +924,39 @@ gpoVMSncu2jMIDZX63IkQII=
             let ca_cert_path_str = ca_cert_path.to_str().unwrap();
             File::create(&ca_cert_path).unwrap();
 
-            let args = format!("hab-sup run --listen-gossip=1.2.3.4:4321 \
-                                --listen-http=5.5.5.5:11111 --http-disable \
-                                --listen-ctl=7.8.9.1:12 --org=MY_ORG --peer 1.1.1.1:1111 \
-                                2.2.2.2:2222 3.3.3.3 --permanent-peer --ring tester \
-                                --cache-key-path={} --auto-update --auto-update-period 90 \
-                                --service-update-period 30 --key={} --certs={} --ca-certs {} \
-                                --keep-latest-packages=5 --sys-ip-address 7.8.9.0",
-                               temp_dir_str, key_path_str, cert_path_str, ca_cert_path_str);
+            let args =
+                format!("hab-sup run --listen-gossip=1.2.3.4:4321 --listen-http=5.5.5.5:11111 \
+                         --http-disable --listen-ctl=7.8.9.1:12 --org=MY_ORG --peer 1.1.1.1:1111 \
+                         2.2.2.2:2222 3.3.3.3 --permanent-peer --ring tester --cache-key-path={} \
+                         --auto-update --auto-update-period 90 --auto-update-window 02:00-04:00 \
+                         --service-update-period 30 --key={} --certs={} --ca-certs {} \
+                         --keep-latest-packages=5 --sys-ip-address 7.8.9.0 --service-log-format \
+                         json --service-log-forward syslog+tcp://logs.example.com \
+                         --service-stdout-to console,events --service-stderr-to file --vault-url \
+                         https://vault.example.com:8200 --vault-token s.t0k3n --encrypt-specs \
+                         --tag primary-dc --tag rack-7 --departure-timeout 3600 \
+                         --departed-retention 86400 --statsd-host statsd.example.com \
+                         --statsd-prefix hab --statsd-tag env:prod",
+                        temp_dir_str, key_path_str, cert_path_str, ca_cert_path_str);
 
             let gossip_peers = vec!["1.1.1.1:1111".parse().unwrap(),
                                     "2.2.2.2:2222".parse().unwrap(),
                                     format!("3.3.3.3:{}", GossipListenAddr::DEFAULT_PORT).parse()
                                                                                          .unwrap()];
 
+            let forward = "syslog+tcp://logs.example.com:601".parse().unwrap();
+            let service_output = ServiceOutputConfig { log_rotation:   LogRotation::default(),
+                                                       format:         ServiceOutputFormat::Json,
+                                                       forward:        Some(forward),
+                                                       stdout_targets: OutputTargets::CONSOLE
+                                                                       | OutputTargets::EVENTS,
+                                                       stderr_targets: OutputTargets::FILE, };
+            let vault_config = VaultConfig { url:   "https://vault.example.com:8200/".to_string(),
+                                             token: "s.t0k3n".to_string(), };
             let config = config_from_cmd_str(&args);
             assert_eq!(ManagerConfig { auto_update: true,
                                        auto_update_period: Duration::from_secs(90),
+                                       auto_update_window: Some("02:00-04:00".parse().unwrap()),
                                        service_update_period: Duration::from_secs(30),
                                        custom_state_path: None,
                                        key_cache: KeyCache::new(temp_dir_str),
@@ -802,14 +974,35 @@ gpoVMSncu2jMIDZX63IkQII=
                                        ring_key: Some(ring_key),
                                        organization: Some(String::from("MY_ORG")),
                                        watch_peer_file: None,
+                                       gossip_tls_config: None,
                                        tls_config: Some(TLSConfig { cert_path,
                                                                     key_path,
                                                                     ca_cert_path:
                                                                         Some(ca_cert_path) }),
+                                       gateway_cert_source: None,
+                                       ctl_gateway_tls: false,
+                                       ctl_listeners: vec![],
+                                       ctl_request_timeout: None,
                                        feature_flags: FeatureFlag::empty(),
                                        event_stream_config: None,
+                                       vault_config: Some(vault_config),
+                                       statsd_config:
+                                           Some(StatsdConfig { host:
+                                                                   String::from("statsd.example.\
+                                                                                 com"),
+                                                               port:   8125,
+                                                               prefix: String::from("hab"),
+                                                               tags:
+                                                                   vec![String::from("env:prod")], }),
+                                       encrypt_specs: true,
                                        keep_latest_packages: Some(5),
-                                       sys_ip: "7.8.9.0".parse().unwrap() },
+                                       sys_ip: "7.8.9.0".parse().unwrap(),
+                                       tags: vec!["primary-dc".to_string(),
+                                                  "rack-7".to_string()],
+                                       sys_cloud_metadata: None,
+                                       departure_timeout: Duration::from_secs(3600),
+                                       departed_retention: Some(Duration::from_secs(86400)),
+                                       service_output },
                        config);
         }
 
@@ -823,6 +1016,7 @@ gpoVMSncu2jMIDZX63IkQII=
             let config = config_from_cmd_str(args);
             assert_eq!(ManagerConfig { auto_update:           false,
                                        auto_update_period:    Duration::from_secs(60),
+                                       auto_update_window:    None,
                                        service_update_period: Duration::from_secs(60),
                                        custom_state_path:     None,
                                        key_cache:             KeyCache::new("/cache/key/path"),
@@ -839,12 +1033,25 @@ gpoVMSncu2jMIDZX63IkQII=
                                        ring_key:              None,
                                        organization:          None,
                                        watch_peer_file:       None,
+                                       gossip_tls_config:     None,
                                        tls_config:            None,
+                                       gateway_cert_source:   None,
+                                       ctl_gateway_tls:       false,
+                                       ctl_listeners:         vec![],
+                                       ctl_request_timeout:   None,
                                        feature_flags:         FeatureFlag::empty(),
                                        event_stream_config:   None,
+                                       vault_config:          None,
+                                       statsd_config:         None,
+                                       encrypt_specs:         false,
                                        keep_latest_packages:  None,
                                        sys_ip:
-                                           habitat_core::util::sys::ip().unwrap(), },
+                                           habitat_core::util::sys::ip().unwrap(),
+                                       tags:                  Vec::new(),
+                                       sys_cloud_metadata:    None,
+                                       departure_timeout:     Duration::from_secs(259_200),
+                                       departed_retention:    None,
+                                       service_output:        ServiceOutputConfig::default(), },
                        config);
         }
 
@@ -858,6 +1065,7 @@ gpoVMSncu2jMIDZX63IkQII=
             let config = config_from_cmd_str(args);
             assert_eq!(ManagerConfig { auto_update:           false,
                                        auto_update_period:    Duration::from_secs(60),
+                                       auto_update_window:    None,
                                        service_update_period: Duration::from_secs(60),
                                        custom_state_path:     None,
                                        key_cache:             KeyCache::new(&*CACHE_KEY_PATH),
@@ -873,12 +1081,25 @@ gpoVMSncu2jMIDZX63IkQII=
                                        ring_key:              None,
                                        organization:          None,
                                        watch_peer_file:       Some(String::from("/some/path")),
+                                       gossip_tls_config:     None,
                                        tls_config:            None,
+                                       gateway_cert_source:   None,
+                                       ctl_gateway_tls:       false,
+                                       ctl_listeners:         vec![],
+                                       ctl_request_timeout:   None,
                                        feature_flags:         FeatureFlag::empty(),
                                        event_stream_config:   None,
+                                       vault_config:          None,
+                                       statsd_config:         None,
+                                       encrypt_specs:         false,
                                        keep_latest_packages:  None,
                                        sys_ip:
-                                           habitat_core::util::sys::ip().unwrap(), },
+                                           habitat_core::util::sys::ip().unwrap(),
+                                       tags:                  Vec::new(),
+                                       sys_cloud_metadata:    None,
+                                       departure_timeout:     Duration::from_secs(259_200),
+                                       departed_retention:    None,
+                                       service_output:        ServiceOutputConfig::default(), },
                        config);
         }
 
@@ -897,6 +1118,23 @@ gpoVMSncu2jMIDZX63IkQII=
             assert!(m.json_logging);
         }
 
+        #[test]
+        fn test_hab_sup_run_cli_service_log_rotation() {
+            let lock = lock_var();
+            lock.unset();
+
+            let config = config_from_cmd_str("hab-sup run");
+            assert_eq!(config.service_output.log_rotation, LogRotation::default());
+
+            let args = "hab-sup run --service-log-max-bytes 1024 --service-log-max-age 86400 \
+                        --service-log-max-files 2";
+            let config = config_from_cmd_str(args);
+            assert_eq!(config.service_output.log_rotation,
+                       LogRotation { max_bytes: 1024,
+                                     max_age:   Some(Duration::from_secs(86_400)),
+                                     max_files: 2, });
+        }
+
         #[test]
         fn test_hab_sup_run_cli_event_stream() {
             let lock = lock_var();
@@ -925,6 +1163,7 @@ gpoVMSncu2jMIDZX63IkQII=
             meta.insert(String::from("keyA"), String::from("valA"));
             assert_eq!(ManagerConfig { auto_update:          false,
                                        auto_update_period:   Duration::from_secs(60),
+                                       auto_update_window: None,
                                        service_update_period:   Duration::from_secs(60),
                                        custom_state_path:    None,
                                        key_cache:       KeyCache::new(&*CACHE_KEY_PATH),
@@ -940,7 +1179,12 @@ gpoVMSncu2jMIDZX63IkQII=
                                        ring_key:             None,
                                        organization:         None,
                                        watch_peer_file:      None,
+                                       gossip_tls_config:    None,
                                        tls_config:           None,
+                                       gateway_cert_source:  None,
+                                       ctl_gateway_tls:      false,
+                                       ctl_listeners:    vec![],
+                                       ctl_request_timeout: None,
                                        feature_flags:        FeatureFlag::empty(),
                                        event_stream_config:  Some(EventStreamConfig {
                                         environment: String::from("MY_ENV"),
@@ -952,8 +1196,16 @@ gpoVMSncu2jMIDZX63IkQII=
                                         connect_method: EventStreamConnectMethod::Timeout {secs: 5},
                                         server_certificate: Some(certificate_path_str.parse().unwrap()),
                                        }),
+                                       vault_config: None,
+                                       statsd_config: None,
+                                       encrypt_specs: false,
                                        keep_latest_packages: None,
-                                       sys_ip:               habitat_core::util::sys::ip().unwrap(), },
+                                       sys_ip:               habitat_core::util::sys::ip().unwrap(),
+                                       tags: Vec::new(),
+                                       sys_cloud_metadata: None,
+                                       departure_timeout: Duration::from_secs(259_200),
+                                       departed_retention: None,
+                                       service_output: ServiceOutputConfig::default(), },
                        config,);
         }
 
@@ -981,28 +1233,56 @@ gpoVMSncu2jMIDZX63IkQII=
                                                      Some("core/redis".parse::<PackageIdent>()
                                                                       .unwrap()
                                                                       .into()),
-                                                 application_environment: None,
-                                                 binds:                   Some(binds),
-                                                 binding_mode:            Some(0),
+                                                 application_environment:  None,
+                                                 binds:                    Some(binds),
+                                                 binding_mode:             Some(0),
                                                  bldr_url:
                                                      Some(String::from("http://my_url.com/")),
                                                  bldr_channel:
                                                      Some(String::from("my_channel")),
                                                  config_from:
                                                      Some(String::from(temp_dir_str)),
-                                                 force:                   Some(true),
+                                                 force:                    Some(true),
                                                  group:
                                                      Some(String::from("MyGroup")),
-                                                 svc_encrypted_password:  None,
+                                                 svc_encrypted_password:   None,
                                                  topology:
                                                      Some(Topology::Leader.into()),
                                                  update_strategy:
                                                      Some(UpdateStrategy::Rolling.into()),
                                                  health_check_interval:
                                                      Some(health_check_interval),
-                                                 shutdown_timeout:        Some(12),
+                                                 shutdown_timeout:         Some(12),
                                                  update_condition:
-                                                     Some(UpdateCondition::TrackChannel.into()), },
+                                                     Some(UpdateCondition::TrackChannel.into()),
+                                                 cpu_max:                  None,
+                                                 memory_max:               None,
+                                                 io_weight:                None,
+                                                 svc_user:                 None,
+                                                 svc_group:                None,
+                                                 log_max_bytes:            None,
+                                                 log_max_age:              None,
+                                                 log_max_files:            None,
+                                                 log_forward:              None,
+                                                 stdout_to:                None,
+                                                 stderr_to:                None,
+                                                 health_check_timeout:     None,
+                                                 health_check_max_backoff: None,
+                                                 preferred_leaders:        None,
+                                                 excluded_leaders:         None,
+                                                 pid_namespace:            Some(false),
+                                                 network_namespace:        Some(false),
+                                                 ports:                    None,
+                                                 seccomp_profile:          None,
+                                                 filesystem_sandbox:       Some(false),
+                                                 fs_allow:                 None,
+                                                 umask:                    None,
+                                                 working_dir:              None,
+                                                 env_files:                None,
+                                                 ulimits:                  None,
+                                                 nice:                     None,
+                                                 io_priority:              None,
+                                                 listen_fds:               None, },
                        service_load);
         }
 
@@ -1035,6 +1315,24 @@ gpoVMSncu2jMIDZX63IkQII=
                        "keep_it_secret_keep_it_safe");
         }
 
+        #[cfg(not(windows))]
+        #[test]
+        fn test_hab_sup_run_cli_password() {
+            use habitat_core::crypto::svc_password;
+
+            let key_cache = TempDir::new().expect("Could not create tempdir");
+            let lock = lock_var();
+            lock.set(key_cache.path());
+
+            let args = "hab-sup run --password keep_it_secret_keep_it_safe core/redis";
+            let service_load = service_load_from_cmd_str(&args);
+            let encrypted = service_load.svc_encrypted_password.unwrap();
+            assert!(!encrypted.contains("keep_it_secret_keep_it_safe"));
+            svc_password::init(KeyCache::new(key_cache.path()));
+            assert_eq!(svc_password::decrypt(&encrypted).unwrap(),
+                       "keep_it_secret_keep_it_safe");
+        }
+
         #[test]
         fn test_hab_sup_run_config_file_1() {
             let lock = lock_var();
@@ -1076,12 +1374,23 @@ ring = "tester"
 cache_key_path = "{}"
 auto_update = true
 auto_update_period = 3600
+auto_update_window = "23:00-01:00"
 service_update_period = 1_000
 key_file = "{}"
 cert_file = "{}"
 ca_cert_file = "{}"
 keep_latest_packages = 5
 sys_ip_address = "7.8.9.0"
+service_log_format = "json"
+service_log_forward = "syslog+tcp://logs.example.com:601"
+service_stdout_to = "console,events"
+service_stderr_to = "file"
+vault_url = "https://vault.example.com:8200"
+vault_token = "s.t0k3n"
+encrypt_specs = true
+tag = ["primary-dc", "rack-7"]
+departure_timeout = 3600
+departed_retention = 86400
     "#,
                                           temp_dir_str.replace("\\", "/"),
                                           key_path_str.replace("\\", "/"),
@@ -1100,9 +1409,19 @@ sys_ip_address = "7.8.9.0"
                                     format!("3.3.3.3:{}", GossipListenAddr::DEFAULT_PORT).parse()
                                                                                          .unwrap()];
 
+            let forward = "syslog+tcp://logs.example.com:601".parse().unwrap();
+            let service_output = ServiceOutputConfig { log_rotation:   LogRotation::default(),
+                                                       format:         ServiceOutputFormat::Json,
+                                                       forward:        Some(forward),
+                                                       stdout_targets: OutputTargets::CONSOLE
+                                                                       | OutputTargets::EVENTS,
+                                                       stderr_targets: OutputTargets::FILE, };
+            let vault_config = VaultConfig { url:   "https://vault.example.com:8200/".to_string(),
+                                             token: "s.t0k3n".to_string(), };
             let config = config_from_cmd_str(&args);
             assert_eq!(ManagerConfig { auto_update: true,
                                        auto_update_period: Duration::from_secs(3600),
+                                       auto_update_window: Some("23:00-01:00".parse().unwrap()),
                                        service_update_period: Duration::from_secs(1_000),
                                        custom_state_path: None,
                                        key_cache: KeyCache::new(temp_dir_str),
@@ -1120,14 +1439,28 @@ sys_ip_address = "7.8.9.0"
                                        ring_key: Some(ring_key),
                                        organization: Some(String::from("MY_ORG")),
                                        watch_peer_file: None,
+                                       gossip_tls_config: None,
                                        tls_config: Some(TLSConfig { cert_path,
                                                                     key_path,
                                                                     ca_cert_path:
                                                                         Some(ca_cert_path) }),
+                                       gateway_cert_source: None,
+                                       ctl_gateway_tls: false,
+                                       ctl_listeners: vec![],
+                                       ctl_request_timeout: None,
                                        feature_flags: FeatureFlag::empty(),
                                        event_stream_config: None,
+                                       vault_config: Some(vault_config),
+                                       statsd_config: None,
+                                       encrypt_specs: true,
                                        keep_latest_packages: Some(5),
-                                       sys_ip: "7.8.9.0".parse().unwrap() },
+                                       sys_ip: "7.8.9.0".parse().unwrap(),
+                                       tags: vec!["primary-dc".to_string(),
+                                                  "rack-7".to_string()],
+                                       sys_cloud_metadata: None,
+                                       departure_timeout: Duration::from_secs(3600),
+                                       departed_retention: Some(Duration::from_secs(86400)),
+                                       service_output },
                        config);
         }
 
@@ -1150,6 +1483,7 @@ sys_ip_address = "7.8.9.0"
             let config = config_from_cmd_str(&args);
             assert_eq!(ManagerConfig { auto_update:           false,
                                        auto_update_period:    Duration::from_secs(60),
+                                       auto_update_window:    None,
                                        service_update_period: Duration::from_secs(60),
                                        custom_state_path:     None,
                                        key_cache:             KeyCache::new("/cache/key/path"),
@@ -1166,12 +1500,25 @@ sys_ip_address = "7.8.9.0"
                                        ring_key:              None,
                                        organization:          None,
                                        watch_peer_file:       None,
+                                       gossip_tls_config:     None,
                                        tls_config:            None,
+                                       gateway_cert_source:   None,
+                                       ctl_gateway_tls:       false,
+                                       ctl_listeners:         vec![],
+                                       ctl_request_timeout:   None,
                                        feature_flags:         FeatureFlag::empty(),
                                        event_stream_config:   None,
+                                       vault_config:          None,
+                                       statsd_config:         None,
+                                       encrypt_specs:         false,
                                        keep_latest_packages:  None,
                                        sys_ip:
-                                           habitat_core::util::sys::ip().unwrap(), },
+                                           habitat_core::util::sys::ip().unwrap(),
+                                       tags:                  Vec::new(),
+                                       sys_cloud_metadata:    None,
+                                       departure_timeout:     Duration::from_secs(259_200),
+                                       departed_retention:    None,
+                                       service_output:        ServiceOutputConfig::default(), },
                        config);
         }
 
@@ -1194,6 +1541,7 @@ sys_ip_address = "7.8.9.0"
             let config = config_from_cmd_str(&args);
             assert_eq!(ManagerConfig { auto_update:           false,
                                        auto_update_period:    Duration::from_secs(60),
+                                       auto_update_window:    None,
                                        service_update_period: Duration::from_secs(60),
                                        custom_state_path:     None,
                                        key_cache:             KeyCache::new(&*CACHE_KEY_PATH),
@@ -1209,12 +1557,25 @@ sys_ip_address = "7.8.9.0"
                                        ring_key:              None,
                                        organization:          None,
                                        watch_peer_file:       Some(String::from("/some/path")),
+                                       gossip_tls_config:     None,
                                        tls_config:            None,
+                                       gateway_cert_source:   None,
+                                       ctl_gateway_tls:       false,
+                                       ctl_listeners:         vec![],
+                                       ctl_request_timeout:   None,
                                        feature_flags:         FeatureFlag::empty(),
                                        event_stream_config:   None,
+                                       vault_config:          None,
+                                       statsd_config:         None,
+                                       encrypt_specs:         false,
                                        keep_latest_packages:  None,
                                        sys_ip:
-                                           habitat_core::util::sys::ip().unwrap(), },
+                                           habitat_core::util::sys::ip().unwrap(),
+                                       tags:                  Vec::new(),
+                                       sys_cloud_metadata:    None,
+                                       departure_timeout:     Duration::from_secs(259_200),
+                                       departed_retention:    None,
+                                       service_output:        ServiceOutputConfig::default(), },
                        config);
         }
 
@@ -1258,6 +1619,34 @@ json_logging = false
             assert!(!m.json_logging);
         }
 
+        #[test]
+        fn test_hab_sup_run_config_file_service_log_rotation() {
+            let lock = lock_var();
+            lock.unset();
+
+            let temp_dir = TempDir::new().expect("Could not create tempdir");
+
+            let config_path = temp_dir.path().join("config.toml");
+            let config_path_str = config_path.to_str().unwrap();
+
+            // Setup config file
+            let config_contents = r#"
+service_log_max_bytes = 1024
+service_log_max_age = 86400
+service_log_max_files = 0
+"#;
+            let mut config_file = File::create(&config_path).unwrap();
+            write!(config_file, "{}", config_contents).expect("to write config file contents");
+
+            let args = format!("hab-sup run --config-files {}", config_path_str);
+
+            let config = config_from_cmd_str(&args);
+            assert_eq!(config.service_output.log_rotation,
+                       LogRotation { max_bytes: 1024,
+                                     max_age:   Some(Duration::from_secs(86_400)),
+                                     max_files: 0, });
+        }
+
         #[test]
         fn test_hab_sup_run_config_file_event_stream() {
             let lock = lock_var();
@@ -1298,6 +1687,7 @@ event_stream_server_certificate = "{}"
             meta.insert(String::from("keyA"), String::from("valA"));
             assert_eq!(ManagerConfig { auto_update:          false,
                 auto_update_period:   Duration::from_secs(60),
+                auto_update_window: None,
                 service_update_period:   Duration::from_secs(60),
                                        custom_state_path:    None,
                                        key_cache:       KeyCache::new(&*CACHE_KEY_PATH),
@@ -1313,7 +1703,12 @@ event_stream_server_certificate = "{}"
                                        ring_key:             None,
                                        organization:         None,
                                        watch_peer_file:      None,
+                                       gossip_tls_config:    None,
                                        tls_config:           None,
+                                       gateway_cert_source:  None,
+                                       ctl_gateway_tls:      false,
+                                       ctl_listeners:    vec![],
+                                       ctl_request_timeout: None,
                                        feature_flags:        FeatureFlag::empty(),
                                        event_stream_config:  Some(EventStreamConfig {
                                         environment: String::from("MY_ENV"),
@@ -1325,8 +1720,16 @@ event_stream_server_certificate = "{}"
                                         connect_method: EventStreamConnectMethod::Timeout {secs: 5},
                                         server_certificate: Some(certificate_path_str.parse().unwrap()),
                                        }),
+                                       vault_config: None,
+                                       statsd_config: None,
+                                       encrypt_specs: false,
                                        keep_latest_packages: None,
-                                       sys_ip:               habitat_core::util::sys::ip().unwrap(), },
+                                       sys_ip:               habitat_core::util::sys::ip().unwrap(),
+                                       tags: Vec::new(),
+                                       sys_cloud_metadata: None,
+                                       departure_timeout: Duration::from_secs(259_200),
+                                       departed_retention: None,
+                                       service_output: ServiceOutputConfig::default(), },
                        config,);
         }
 
@@ -1372,28 +1775,56 @@ pkg_ident_or_artifact = "core/redis"
                                                      Some("core/redis".parse::<PackageIdent>()
                                                                       .unwrap()
                                                                       .into()),
-                                                 application_environment: None,
-                                                 binds:                   Some(binds),
-                                                 binding_mode:            Some(0),
+                                                 application_environment:  None,
+                                                 binds:                    Some(binds),
+                                                 binding_mode:             Some(0),
                                                  bldr_url:
                                                      Some(String::from("http://my_url.com/")),
                                                  bldr_channel:
                                                      Some(String::from("my_channel")),
                                                  config_from:
                                                      Some(temp_dir_str.replace("\\", "/")),
-                                                 force:                   Some(true),
+                                                 force:                    Some(true),
                                                  group:
                                                      Some(String::from("MyGroup")),
-                                                 svc_encrypted_password:  None,
+                                                 svc_encrypted_password:   None,
                                                  topology:
                                                      Some(Topology::Standalone.into()),
                                                  update_strategy:
                                                      Some(UpdateStrategy::AtOnce.into()),
                                                  health_check_interval:
                                                      Some(health_check_interval),
-                                                 shutdown_timeout:        Some(12),
+                                                 shutdown_timeout:         Some(12),
                                                  update_condition:
-                                                     Some(UpdateCondition::TrackChannel.into()), },
+                                                     Some(UpdateCondition::TrackChannel.into()),
+                                                 cpu_max:                  None,
+                                                 memory_max:               None,
+                                                 io_weight:                None,
+                                                 svc_user:                 None,
+                                                 svc_group:                None,
+                                                 log_max_bytes:            None,
+                                                 log_max_age:              None,
+                                                 log_max_files:            None,
+                                                 log_forward:              None,
+                                                 stdout_to:                None,
+                                                 stderr_to:                None,
+                                                 health_check_timeout:     None,
+                                                 health_check_max_backoff: None,
+                                                 preferred_leaders:        None,
+                                                 excluded_leaders:         None,
+                                                 pid_namespace:            Some(false),
+                                                 network_namespace:        Some(false),
+                                                 ports:                    None,
+                                                 seccomp_profile:          None,
+                                                 filesystem_sandbox:       Some(false),
+                                                 fs_allow:                 None,
+                                                 umask:                    None,
+                                                 working_dir:              None,
+                                                 env_files:                None,
+                                                 ulimits:                  None,
+                                                 nice:                     None,
+                                                 io_priority:              None,
+                                                 listen_fds:               None, },
                        service_load);
         }
 
@@ -1482,6 +1913,7 @@ organization = "MY_ORG_FROM_SECOND_CONFG"
             let config = config_from_cmd_str(&args);
             assert_eq!(ManagerConfig { auto_update:           false,
                                        auto_update_period:    Duration::from_secs(60),
+                                       auto_update_window:    None,
                                        service_update_period: Duration::from_secs(60),
                                        custom_state_path:     None,
                                        key_cache:             KeyCache::new(&*CACHE_KEY_PATH),
@@ -1501,12 +1933,25 @@ organization = "MY_ORG_FROM_SECOND_CONFG"
                                        organization:
                                            Some(String::from("MY_ORG_FROM_SECOND_CONFG")),
                                        watch_peer_file:       None,
+                                       gossip_tls_config:     None,
                                        tls_config:            None,
+                                       gateway_cert_source:   None,
+                                       ctl_gateway_tls:       false,
+                                       ctl_listeners:         vec![],
+                                       ctl_request_timeout:   None,
                                        feature_flags:         FeatureFlag::empty(),
                                        event_stream_config:   None,
+                                       vault_config:          None,
+                                       statsd_config:         None,
+                                       encrypt_specs:         false,
                                        keep_latest_packages:  None,
                                        sys_ip:
-                                           habitat_core::util::sys::ip().unwrap(), },
+                                           habitat_core::util::sys::ip().unwrap(),
+                                       tags:                  Vec::new(),
+                                       sys_cloud_metadata:    None,
+                                       departure_timeout:     Duration::from_secs(259_200),
+                                       departed_retention:    None,
+                                       service_output:        ServiceOutputConfig::default(), },
                        config);
         }
 
@@ -1534,6 +1979,11 @@ organization = "MY_ORG_FROM_SECOND_CONFG"
             assert_eq!(i32::from(BindingMode::Relaxed),
                        svc_load.binding_mode.unwrap());
 
+            let args = "hab-sup run --binding-mode optional core/redis";
+            let svc_load = service_load_from_cmd_str(&args);
+            assert_eq!(i32::from(BindingMode::Optional),
+                       svc_load.binding_mode.unwrap());
+
             let args = "hab-sup run --strategy rolling core/redis";
             let svc_load = service_load_from_cmd_str(&args);
             assert_eq!(i32::from(UpdateStrategy::Rolling),
@@ -3,26 +3,36 @@ pub mod service;
 #[macro_use]
 mod debug;
 pub mod commands;
+mod config_audit;
+pub mod dry_run;
 mod file_watcher;
+mod launcher_heartbeat;
 mod peer_watcher;
 mod self_updater;
 mod service_updater;
 mod spec_dir;
 mod spec_watcher;
 pub(crate) mod sys;
+mod systemd_notify;
 mod user_config_watcher;
 
 use self::{action::{ShutdownInput,
                     SupervisorAction},
-           peer_watcher::PeerWatcher,
+           config_audit::ConfigAuditLog,
+           launcher_heartbeat::LauncherHeartbeat,
+           peer_watcher::{PeerChanges,
+                          PeerWatcher},
            self_updater::{SelfUpdater,
                           SUP_PKG_IDENT},
-           service::{spec::{RefreshOperation,
+           service::{spec::{self,
+                            RefreshOperation,
                             ServiceOperation},
                      ConfigRendering,
                      DesiredState,
+                     HealthCheckRecord,
                      HealthCheckResult,
                      Service,
+                     ServiceOutputConfig,
                      ServiceProxy,
                      ServiceSpec,
                      Topology},
@@ -30,6 +40,7 @@ use self::{action::{ShutdownInput,
            spec_dir::SpecDir,
            spec_watcher::SpecWatcher,
            sys::Sys,
+           systemd_notify::SystemdNotifier,
            user_config_watcher::UserConfigWatcher};
 use crate::{census::{CensusRing,
                      CensusRingProxy},
@@ -40,7 +51,13 @@ use crate::{census::{CensusRing,
                     Result},
             event::{self,
                     EventStreamConfig},
+            gateway_certs::{CertSource,
+                            GatewayCerts},
             http_gateway,
+            secrets::{self,
+                      VaultConfig},
+            statsd::{self,
+                     StatsdConfig},
             util::pkg,
             VERSION};
 use cpu_time::ProcessTime;
@@ -52,19 +69,25 @@ use futures::{channel::{mpsc as fut_mpsc,
 use habitat_butterfly::{member::Member,
                         server::{timing::Timing,
                                  ServerProxy,
-                                 Suitability}};
+                                 Suitability},
+                        tls::{GossipTls,
+                              TlsConfig as GossipTlsConfig}};
 use habitat_common::{liveliness_checker,
                      outputln,
-                     types::{GossipListenAddr,
+                     types::{CloudProvider,
+                             CtlListener,
+                             GossipListenAddr,
                              HttpListenAddr,
-                             ListenCtlAddr},
+                             ListenCtlAddr,
+                             UpdateWindow},
                      FeatureFlag};
 #[cfg(unix)]
 use habitat_core::os::{process::{ShutdownSignal,
                                  Signal},
                        signals};
 use habitat_core::{crypto::keys::{KeyCache,
-                                  RingKey},
+                                  RingKey,
+                                  HOST_KEY_NAME},
                    env,
                    env::Config,
                    fs::FS_ROOT_PATH,
@@ -86,13 +109,13 @@ use parking_lot::{Mutex,
 use prometheus::{HistogramVec,
                  IntGauge,
                  IntGaugeVec};
-use rustls::{internal::pemfile,
-             AllowAnyAuthenticatedClient,
+use rustls::{AllowAnyAuthenticatedClient,
              NoClientAuth,
              RootCertStore,
              ServerConfig};
 use std::{collections::{HashMap,
-                        HashSet},
+                        HashSet,
+                        VecDeque},
           ffi::OsStr,
           fs::{self,
                File,
@@ -118,6 +141,8 @@ use std::{collections::{HashMap,
           time::{Duration,
                  Instant,
                  SystemTime}};
+use tokio::{net::TcpListener,
+            task};
 #[cfg(windows)]
 use winapi::{shared::minwindef::PDWORD,
              um::processthreadsapi};
@@ -259,6 +284,8 @@ impl FsCfg {
 pub struct ManagerConfig {
     pub auto_update:           bool,
     pub auto_update_period:    Duration,
+    /// The daily window during which the Supervisor may restart into an update
+    pub auto_update_window:    Option<UpdateWindow>,
     pub service_update_period: Duration,
     pub custom_state_path:     Option<PathBuf>,
     pub key_cache:             KeyCache,
@@ -271,16 +298,42 @@ pub struct ManagerConfig {
     pub gossip_peers:          Vec<SocketAddr>,
     pub gossip_permanent:      bool,
     pub ring_key:              Option<RingKey>,
+    /// Certificates for gossiping with other Supervisors over mutual TLS.
+    pub gossip_tls_config:     Option<GossipTlsConfig>,
     pub organization:          Option<String>,
     pub watch_peer_file:       Option<String>,
     pub tls_config:            Option<TLSConfig>,
+    /// Where to get the certificate of the gateways from, when it isn't in `tls_config`.
+    pub gateway_cert_source:   Option<CertSource>,
+    /// Whether to serve the ctl gateway over TLS with the certificate of the HTTP gateway.
+    pub ctl_gateway_tls:       bool,
+    /// Additional addresses for the ctl gateway to listen on, each with its own auth settings.
+    pub ctl_listeners:         Vec<CtlListener>,
+    /// How long a ctl gateway request may take before it is cancelled, if there is a limit.
+    pub ctl_request_timeout:   Option<Duration>,
     pub feature_flags:         FeatureFlag,
     pub event_stream_config:   Option<EventStreamConfig>,
+    /// The Vault server to read the secrets referenced in service configuration from.
+    pub vault_config:          Option<VaultConfig>,
+    /// The StatsD server to push metrics to.
+    pub statsd_config:         Option<StatsdConfig>,
+    /// Whether to encrypt service spec files at rest with the Supervisor's host key.
+    pub encrypt_specs:         bool,
     /// If this field is `Some`, keep the indicated number of latest packages and uninstall all
     /// others during service start. If this field is `None`, automatic package cleanup is
     /// disabled.
     pub keep_latest_packages:  Option<usize>,
     pub sys_ip:                IpAddr,
+    /// Tags describing this Supervisor, matched against services' leader affinity.
+    pub tags:                  Vec<String>,
+    /// The cloud provider whose instance metadata is queried for `sys.cloud`, if any.
+    pub sys_cloud_metadata:    Option<CloudProvider>,
+    /// How long members confirmed dead linger before they are considered departed.
+    pub departure_timeout:     Duration,
+    /// How long departed members are kept before they are purged, if at all.
+    pub departed_retention:    Option<Duration>,
+    /// How the Launcher handles the output of services.
+    pub service_output:        ServiceOutputConfig,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -385,13 +438,23 @@ pub struct ManagerState {
     services:       Arc<sync::ManagerServices>,
     gateway_state:  Arc<sync::GatewayState>,
     should_restart: AtomicBool,
+    /// The arguments the restarted Supervisor should run with, if they are to change.
+    restart_args:   Mutex<Option<Vec<String>>>,
+    /// When the Supervisor started, for its uptime.
+    started:        Instant,
+    /// The gossip server, for the commands that act on the ring's membership directly.
+    butterfly:      habitat_butterfly::Server,
+    /// The record of configuration and files applied through the control gateway.
+    config_audit:   ConfigAuditLog,
 }
 
 pub(crate) mod sync {
     use super::*;
+    use crate::manager::service::HEALTH_CHECK_HISTORY_LEN;
     use habitat_common::sync::{Lock,
                                ReadGuard,
                                WriteGuard};
+    use habitat_core::crypto::Blake2bHash;
 
     pub struct GatewayStateReadGuard<'a>(ReadGuard<'a, GatewayStateInner>);
 
@@ -402,11 +465,39 @@ pub(crate) mod sync {
 
         pub fn census_data(&self) -> &str { &self.0.census_data }
 
+        /// The entity tag of the /census JSON, which changes whenever the JSON does.
+        pub fn census_etag(&self) -> &str { &self.0.census_etag }
+
         pub fn services_data(&self) -> &str { &self.0.services_data }
 
+        /// The entity tag of the /services JSON, which changes whenever the JSON does.
+        pub fn services_etag(&self) -> &str { &self.0.services_etag }
+
         pub fn health_of(&self, service_group: &ServiceGroup) -> Option<HealthCheckResult> {
             self.0.health_check_data.get(service_group).copied()
         }
+
+        /// The most recent health check results of a service, oldest first.
+        pub fn health_history_of(&self,
+                                 service_group: &ServiceGroup)
+                                 -> Option<&VecDeque<HealthCheckRecord>> {
+            self.0.health_check_history.get(service_group)
+        }
+
+        pub fn readiness(&self) -> Readiness { self.0.readiness }
+
+        /// The number of alive members of the gossip ring, not counting this Supervisor.
+        pub fn gossip_peers(&self) -> usize { self.0.gossip_peers }
+
+        /// When the specs on disk were last reconciled with the running services.
+        pub fn specs_reconciled_at(&self) -> Option<SystemTime> { self.0.specs_reconciled_at }
+
+        /// Whether the Supervisor's main loop has run within `timeout`.
+        pub fn is_alive(&self, timeout: Duration) -> bool {
+            self.0
+                .last_alive
+                .map_or(false, |last_alive| last_alive.elapsed() < timeout)
+        }
     }
 
     pub struct GatewayStateWriteGuard<'a>(WriteGuard<'a, GatewayStateInner>);
@@ -414,19 +505,75 @@ pub(crate) mod sync {
     impl<'a> GatewayStateWriteGuard<'a> {
         fn new(lock: &'a Lock<GatewayStateInner>) -> Self { Self(lock.write()) }
 
-        pub fn set_census_data(&mut self, new_data: String) { self.0.census_data = new_data }
+        pub fn set_census_data(&mut self, new_data: String) {
+            self.0.census_etag = etag(&new_data);
+            self.0.census_data = new_data;
+        }
 
         pub fn set_butterfly_data(&mut self, new_data: String) { self.0.butterfly_data = new_data }
 
-        pub fn set_services_data(&mut self, new_data: String) { self.0.services_data = new_data }
+        pub fn set_services_data(&mut self, new_data: String) {
+            self.0.services_etag = etag(&new_data);
+            self.0.services_data = new_data;
+        }
 
         pub fn remove(&mut self, service_group: &ServiceGroup) {
             self.0.health_check_data.remove(service_group);
+            self.0.health_check_history.remove(service_group);
         }
 
         pub fn set_health_of(&mut self, service_group: ServiceGroup, value: HealthCheckResult) {
             self.0.health_check_data.insert(service_group, value);
         }
+
+        /// Add a health check result to the history of a service, dropping the oldest one once
+        /// `HEALTH_CHECK_HISTORY_LEN` results are kept.
+        pub fn record_health_of(&mut self, service_group: ServiceGroup, record: HealthCheckRecord) {
+            let history = self.0
+                              .health_check_history
+                              .entry(service_group)
+                              .or_default();
+            if history.len() >= HEALTH_CHECK_HISTORY_LEN {
+                history.pop_front();
+            }
+            history.push_back(record);
+        }
+
+        pub fn set_gossip_joined(&mut self, joined: bool) {
+            self.0.readiness.gossip_joined = joined
+        }
+
+        pub fn set_gossip_peers(&mut self, peers: usize) { self.0.gossip_peers = peers }
+
+        pub fn set_specs_reconciled(&mut self) {
+            self.0.readiness.specs_reconciled = true;
+            self.0.specs_reconciled_at = Some(SystemTime::now());
+        }
+
+        pub fn set_ctl_gateway_listening(&mut self) {
+            self.0.readiness.ctl_gateway_listening = true
+        }
+
+        pub fn mark_alive(&mut self) { self.0.last_alive = Some(Instant::now()) }
+    }
+
+    /// The conditions that must hold before the Supervisor reports itself as ready on the HTTP
+    /// gateway's `/ready` endpoint.
+    #[derive(Clone, Copy, Debug, Default, Serialize)]
+    pub struct Readiness {
+        /// The Supervisor has found an alive member of the gossip ring, or has no peers to
+        /// find.
+        pub gossip_joined:         bool,
+        /// The specs on disk have been reconciled with the running services at least once.
+        pub specs_reconciled:      bool,
+        /// The ctl gateway has bound its listen address.
+        pub ctl_gateway_listening: bool,
+    }
+
+    impl Readiness {
+        pub fn is_ready(&self) -> bool {
+            self.gossip_joined && self.specs_reconciled && self.ctl_gateway_listening
+        }
     }
 
     /// All the data that is ultimately served from the Supervisor's HTTP
@@ -449,14 +596,37 @@ pub(crate) mod sync {
     #[derive(Debug, Default)]
     struct GatewayStateInner {
         /// JSON returned by the /census endpoint
-        census_data:       String,
+        census_data:          String,
+        /// Entity tag of `census_data`
+        census_etag:          String,
         /// JSON returned by the /butterfly endpoint
-        butterfly_data:    String,
+        butterfly_data:       String,
         /// JSON returned by the /services endpoint
-        services_data:     String,
+        services_data:        String,
+        /// Entity tag of `services_data`
+        services_etag:        String,
         /// Data returned by /services/<SERVICE_NAME>/<GROUP_NAME>/health
         /// endpoint
-        health_check_data: HashMap<ServiceGroup, HealthCheckResult>,
+        health_check_data:    HashMap<ServiceGroup, HealthCheckResult>,
+        /// Data returned by /services/<SERVICE_NAME>/<GROUP_NAME>/health/history
+        /// endpoint
+        health_check_history: HashMap<ServiceGroup, VecDeque<HealthCheckRecord>>,
+        /// Data returned by the /ready endpoint
+        readiness:            Readiness,
+        /// When the Supervisor's main loop last ran, for the /live
+        /// endpoint
+        last_alive:           Option<Instant>,
+        /// The number of alive gossip peers, for `hab sup status`
+        gossip_peers:         usize,
+        /// When the specs were last reconciled, for `hab sup status`
+        specs_reconciled_at:  Option<SystemTime>,
+    }
+
+    /// A strong entity tag for a JSON document served by the HTTP gateway, so clients polling it
+    /// can skip downloading it again when it hasn't changed.
+    fn etag(data: &str) -> String {
+        let hash = Blake2bHash::from_bytes(data).to_string();
+        format!("\"{}\"", &hash[..16])
     }
 
     type ManagerServicesInner = HashMap<PackageIdent, Service>;
@@ -525,7 +695,7 @@ pub(crate) mod sync {
             self.lock_msr()
                 .services()
                 .find(|svc| svc.service_group.as_ref() == service_group)
-                .and_then(Service::suitability)
+                .map(Service::leader_suitability)
                 .unwrap_or_else(u64::min_value)
         }
     }
@@ -535,8 +705,11 @@ pub struct Manager {
     pub state:           Arc<ManagerState>,
     butterfly:           habitat_butterfly::Server,
     census_ring:         Arc<RwLock<CensusRing>>,
+    census_tracker:      event::CensusTracker,
     fs_cfg:              Arc<FsCfg>,
     launcher:            LauncherCli,
+    launcher_heartbeat:  LauncherHeartbeat,
+    systemd_notifier:    SystemdNotifier,
     service_updater:     Arc<Mutex<ServiceUpdater>>,
     peer_watcher:        Option<PeerWatcher>,
     spec_watcher:        SpecWatcher,
@@ -560,10 +733,12 @@ pub struct Manager {
     /// "snapshot marker"... if any of those time markers change
     /// between service checks, that means that something has happened
     /// to one of the services (it was up, but now it's down; it was
-    /// up, then down, then up; etc).
+    /// up, then down, then up; etc). The time the resources used by
+    /// each service were last sampled is tracked as well, so the new
+    /// sample is published.
     ///
     /// Feel free to refactor to something different!
-    service_states:      HashMap<PackageIdent, SystemTime>,
+    service_states:      HashMap<PackageIdent, (SystemTime, Option<Instant>)>,
 
     /// Collects the identifiers of all services that are currently
     /// doing something asynchronously (like shutting down, or running
@@ -580,9 +755,18 @@ pub struct Manager {
     // the different operations.
     busy_services:                Arc<Mutex<HashSet<PackageIdent>>>,
     services_need_reconciliation: ReconciliationFlag,
-
-    feature_flags: FeatureFlag,
-    pid_source:    ServicePidSource,
+    /// The package each service that is being restarted for an update is updated from, so its
+    /// `post-update` hook can be run once it is started again, and when the update started.
+    updated_from:                 HashMap<PackageIdent, (PackageIdent, Instant)>,
+    /// Whether the census or the services changed since the HTTP gateway's documents were last
+    /// refreshed, and when that was.
+    gateway_state_stale:          bool,
+    gateway_state_persisted_at:   Option<Instant>,
+    http_cache_ttl:               Duration,
+
+    feature_flags:  FeatureFlag,
+    pid_source:     ServicePidSource,
+    service_output: ServiceOutputConfig,
 }
 
 impl Manager {
@@ -630,7 +814,8 @@ impl Manager {
                 Some(SelfUpdater::new(&*THIS_SUPERVISOR_IDENT,
                                       cfg.update_url,
                                       cfg.update_channel,
-                                      cfg.auto_update_period))
+                                      cfg.auto_update_period,
+                                      cfg.auto_update_window))
             } else {
                 warn!("Supervisor version not fully qualified, unable to start self-updater");
                 None
@@ -643,17 +828,24 @@ impl Manager {
                                cfg.ctl_listen,
                                cfg.http_listen,
                                cfg.sys_ip);
+        sys.tags = cfg.tags.clone();
+        if let Some(provider) = cfg.sys_cloud_metadata {
+            sys.load_cloud_metadata(provider).await;
+        }
         let member = Self::load_member(&mut sys, &fs_cfg)?;
         let services = Arc::default();
         let suitability_lookup = Arc::clone(&services) as Arc<dyn Suitability>;
 
-        let server = habitat_butterfly::Server::new(sys.gossip_listen(),
-                                                    sys.gossip_listen(),
-                                                    member,
-                                                    cfg.ring_key,
-                                                    None,
-                                                    Some(&fs_cfg.data_path),
-                                                    suitability_lookup)?;
+        let mut server = habitat_butterfly::Server::new(sys.gossip_listen(),
+                                                        sys.gossip_listen(),
+                                                        member,
+                                                        cfg.ring_key,
+                                                        None,
+                                                        Some(&fs_cfg.data_path),
+                                                        suitability_lookup)?;
+        if let Some(ref gossip_tls_config) = cfg.gossip_tls_config {
+            server.set_gossip_tls(GossipTls::new(gossip_tls_config)?);
+        }
         outputln!("Supervisor Member-ID {}", sys.member_id);
         for peer_addr in &cfg.gossip_peers {
             let mut peer = Member::default();
@@ -669,6 +861,23 @@ impl Manager {
             None
         };
 
+        // Even with encryption turned off, the host key is needed to read spec files and rumors
+        // that were encrypted before.
+        let host_key = match cfg.key_cache.latest_host_key_revision(HOST_KEY_NAME) {
+            Ok(host_key) => Some(host_key),
+            Err(_) if cfg.encrypt_specs => {
+                outputln!("Generating a host key to encrypt service specs with");
+                Some(cfg.key_cache.new_host_key(HOST_KEY_NAME)?)
+            }
+            Err(_) => None,
+        };
+        if let Some(host_key) = host_key {
+            server.set_host_key(host_key.clone(), cfg.encrypt_specs);
+            spec::init_host_key(host_key, cfg.encrypt_specs);
+        }
+        #[cfg(not(windows))]
+        habitat_core::crypto::svc_password::init(cfg.key_cache.clone());
+
         let spec_dir = SpecDir::new(&fs_cfg.specs_path)?;
         spec_dir.migrate_specs();
 
@@ -682,21 +891,41 @@ impl Manager {
             event::init(&sys, fqdn, config).await?;
         }
 
+        if let Some(config) = cfg.vault_config {
+            outputln!("Reading secrets from Vault at {}", config.url);
+            secrets::init(config)?;
+        }
+
+        if let Some(config) = cfg.statsd_config {
+            outputln!("Sending metrics to StatsD at {}:{}",
+                      config.host,
+                      config.port);
+            statsd::init(config)?;
+        }
+
         let pid_source = ServicePidSource::determine_source(&launcher);
+        let config_audit = ConfigAuditLog::new(&fs_cfg.data_path);
 
         let census_ring = Arc::new(RwLock::new(CensusRing::new(sys.member_id.clone())));
         Ok(Manager { state: Arc::new(ManagerState { cfg: cfg_static,
                                                     services,
                                                     gateway_state: Arc::default(),
-                                                    should_restart: AtomicBool::default() }),
+                                                    should_restart: AtomicBool::default(),
+                                                    restart_args: Mutex::default(),
+                                                    started: Instant::now(),
+                                                    butterfly: server.clone(),
+                                                    config_audit }),
                      self_updater,
                      service_updater:
                          Arc::new(Mutex::new(ServiceUpdater::new(server.clone(),
                                                                  Arc::clone(&census_ring),
                                                                  cfg.service_update_period))),
                      census_ring,
+                     census_tracker: event::CensusTracker::default(),
                      butterfly: server,
                      launcher,
+                     launcher_heartbeat: LauncherHeartbeat::new(),
+                     systemd_notifier: SystemdNotifier::from_env(launcher_pid()),
                      peer_watcher,
                      spec_watcher,
                      user_config_watcher: UserConfigWatcher::new(),
@@ -708,8 +937,13 @@ impl Manager {
                      http_disable: cfg.http_disable,
                      busy_services: Arc::default(),
                      services_need_reconciliation: ReconciliationFlag::new(false),
+                     updated_from: HashMap::new(),
+                     gateway_state_stale: false,
+                     gateway_state_persisted_at: None,
+                     http_cache_ttl: http_gateway::cache_ttl(),
                      feature_flags: cfg.feature_flags,
-                     pid_source })
+                     pid_source,
+                     service_output: cfg.service_output })
     }
 
     /// Load the initial Butterly Member which is used in initializing the Butterfly server. This
@@ -818,10 +1052,15 @@ impl Manager {
                                              self.organization.as_deref(),
                                              self.state.gateway_state.clone(),
                                              self.pid_source,
-                                             self.feature_flags).await
+                                             self.feature_flags,
+                                             self.service_output.clone()).await
         {
-            Ok(service) => {
+            Ok(mut service) => {
                 outputln!("Starting {} ({})", ident, service.pkg.ident);
+                if let Some((previous, update_started)) = self.updated_from.remove(&ident) {
+                    statsd::service_updated(&service.service_group, update_started.elapsed());
+                    service.set_updated_from(previous);
+                }
                 service
             }
             Err(err) => {
@@ -879,6 +1118,7 @@ impl Manager {
         self.service_updater.lock().register(&service);
 
         event::service_started(&service);
+        statsd::service_started(&service.service_group);
 
         self.state
             .services
@@ -915,11 +1155,12 @@ impl Manager {
         // you get the gist.
         let (mgr_sender, mgr_receiver) = fut_mpsc::unbounded();
         let (ctl_shutdown_tx, ctl_shutdown_rx) = oneshot::channel();
+        let ctl_shutdown_rx = ctl_shutdown_rx.shared();
         let (action_sender, action_receiver) = std_mpsc::channel();
 
         let ctl_handler = CtlAcceptor::new(self.state.clone(),
                                            mgr_receiver,
-                                           ctl_shutdown_rx,
+                                           ctl_shutdown_rx.clone(),
                                            action_sender).for_each(move |handler| {
                                                              tokio::spawn(handler);
                                                              future::ready(())
@@ -936,20 +1177,73 @@ impl Manager {
         // are gossiped and preventing them from unwanted purging.
         outputln!("Starting gossip-listener on {}",
                   self.butterfly.gossip_addr());
-        self.butterfly
-            .start_rsw_mlw_smw_rhw_msr(&Timing::default())?;
+        let timing = Timing::default().with_departure(self.state.cfg.departure_timeout)
+                                      .with_departed_retention(self.state.cfg.departed_retention);
+        self.butterfly.start_rsw_mlw_smw_rhw_msr(&timing)?;
         debug!("gossip-listener started");
 
         // This serves to start up any services that need starting
         // (which will be all of them at this point!)
         self.maybe_spawn_service_futures_rsw_mlw_gsw_rhw_msw().await;
+        self.state.gateway_state.lock_gsw().set_specs_reconciled();
+        self.systemd_notifier.ready();
 
         self.persist_state_rsr_mlr_gsw_msr().await;
         let http_listen_addr = self.sys.http_listen();
         let ctl_listen_addr = self.sys.ctl_listen();
         let ctl_secret_key = ctl_gateway::readgen_secret_key(&self.fs_cfg.sup_root)?;
+        let gateway_certs = self.gateway_certs()?;
+        let ctl_tls_config = match gateway_certs {
+            Some(ref certs) if self.state.cfg.ctl_gateway_tls => {
+                Some(Arc::new(ctl_tls_config(Arc::clone(certs))))
+            }
+            _ => None,
+        };
+        // Every listener is bound before any is started, so that the Supervisor fails to start
+        // rather than going without one of them
+        let mut ctl_listeners = Vec::new();
+        for listener in &self.state.cfg.ctl_listeners {
+            let secret_key = match listener.secret_file {
+                Some(ref path) => ctl_gateway::read_secret_key_file(path)?,
+                None => ctl_secret_key.clone(),
+            };
+            let tls_config = match gateway_certs {
+                Some(ref certs) if listener.tls => {
+                    Some(Arc::new(ctl_tls_config(Arc::clone(certs))))
+                }
+                _ => None,
+            };
+            let tcp_listener = bind_ctl_listener(listener.addr).await?;
+            ctl_listeners.push((listener, tcp_listener, secret_key, tls_config));
+        }
+        let ctl_tcp_listener = bind_ctl_listener(ctl_listen_addr).await?;
+        for (listener, tcp_listener, secret_key, tls_config) in ctl_listeners {
+            outputln!("Starting ctl-gateway on {}", listener);
+            // Only the main listener marks the ctl gateway as listening
+            let (listening_tx, _) = oneshot::channel();
+            tokio::spawn(ctl_gateway::server::run(tcp_listener,
+                                                  secret_key,
+                                                  tls_config,
+                                                  self.state.cfg.ctl_request_timeout,
+                                                  mgr_sender.clone(),
+                                                  listening_tx,
+                                                  ctl_shutdown_rx.clone()));
+        }
         outputln!("Starting ctl-gateway on {}", &ctl_listen_addr);
-        tokio::spawn(ctl_gateway::server::run(ctl_listen_addr, ctl_secret_key, mgr_sender));
+        let (ctl_listening_tx, ctl_listening_rx) = oneshot::channel();
+        tokio::spawn(ctl_gateway::server::run(ctl_tcp_listener,
+                                              ctl_secret_key,
+                                              ctl_tls_config,
+                                              self.state.cfg.ctl_request_timeout,
+                                              mgr_sender,
+                                              ctl_listening_tx,
+                                              ctl_shutdown_rx));
+        let gateway_state = self.state.gateway_state.clone();
+        tokio::spawn(async move {
+            if ctl_listening_rx.await.is_ok() {
+                gateway_state.lock_gsw().set_ctl_gateway_listening();
+            }
+        });
         debug!("ctl-gateway started");
 
         if self.http_disable {
@@ -958,13 +1252,14 @@ impl Manager {
             // First let's check and see if we're going to use TLS. If so, we'll generate the
             // appropriate config here, where it's easy to propagate errors, vs in a separate
             // thread, where that process is more cumbersome.
-
-            let tls_server_config = match &self.state.cfg.tls_config {
-                Some(c) => {
-                    match tls_config(c) {
-                        Ok(c) => Some(c),
-                        Err(e) => return Err(e),
-                    }
+            let tls_server_config = match gateway_certs {
+                Some(certs) => {
+                    let ca_cert_path = self.state
+                                           .cfg
+                                           .tls_config
+                                           .as_ref()
+                                           .and_then(|c| c.ca_cert_path.as_ref());
+                    Some(http_tls_config(ca_cert_path, certs)?)
                 }
                 None => None,
             };
@@ -1055,6 +1350,7 @@ impl Manager {
             }
 
             track_memory_stats();
+            self.update_readiness_imlr_mlr_gsw();
 
             if self.feature_flags.contains(FeatureFlag::TEST_EXIT) {
                 if let Ok(exit_file_path) = env::var("HAB_FEAT_TEST_EXIT") {
@@ -1075,6 +1371,8 @@ impl Manager {
             if self.launcher.is_stopping() {
                 break ShutdownMode::Normal;
             }
+            self.launcher_heartbeat.check(&self.launcher);
+            self.systemd_notifier.check_watchdog();
             if self.check_for_departure() {
                 break ShutdownMode::Departed;
             }
@@ -1141,9 +1439,10 @@ impl Manager {
                 // another service finishes shutting down).
                 self.services_need_reconciliation.toggle_if_set();
                 self.maybe_spawn_service_futures_rsw_mlw_gsw_rhw_msw().await;
+                self.state.gateway_state.lock_gsw().set_specs_reconciled();
             }
 
-            self.update_peers_from_watch_file_mlr_imlw()?;
+            self.update_peers_from_watch_file_mlw_imlw_rhw()?;
             self.update_running_services_from_user_config_watcher_msw();
 
             // Restart all services that need it
@@ -1160,7 +1459,17 @@ impl Manager {
                                             &self.butterfly.service_config_store,
                                             &self.butterfly.service_file_store);
 
+            self.census_tracker.observe(&self.census_ring.read());
+
             if self.check_for_changed_services_msr() || self.census_ring.read().changed() {
+                self.gateway_state_stale = true;
+            }
+            // The documents served by the HTTP gateway are refreshed at most once per cache TTL,
+            // so large rings don't re-serialize them on every change.
+            if self.gateway_state_stale
+               && self.gateway_state_persisted_at
+                      .map_or(true, |at| at.elapsed() >= self.http_cache_ttl)
+            {
                 self.persist_state_rsr_mlr_gsw_msr().await;
             }
 
@@ -1209,12 +1518,22 @@ impl Manager {
 
         match shutdown_mode {
             ShutdownMode::Restarting => {
+                self.systemd_notifier.reloading();
+                if let Some(args) = self.state.restart_args.lock().take() {
+                    outputln!("Supervisor will restart with arguments: {}", args.join(" "));
+                    if let Err(err) = self.launcher.set_supervisor_args(args) {
+                        outputln!("Unable to change the Supervisor arguments, restarting with \
+                                   the current ones: {}",
+                                  err);
+                    }
+                }
                 outputln!("Preparing services for Supervisor restart");
                 for svc in self.state.services.lock_msw().services() {
                     svc.detach();
                 }
             }
             ShutdownMode::Normal | ShutdownMode::Departed => {
+                self.systemd_notifier.stopping();
                 outputln!("Gracefully departing from butterfly network.");
                 self.butterfly.set_departed_mlw_smw_rhw();
 
@@ -1264,11 +1583,15 @@ impl Manager {
             if let Some(new_ident) = service_updater.has_update(&service.service_group) {
                 outputln!("Restarting {} with package {}", ident, new_ident);
                 event::service_update_started(&service, &new_ident);
+                statsd::service_restarted(&service.service_group, "update");
+                self.updated_from.insert(ident.clone(),
+                                         (service.pkg.ident.clone().into(), Instant::now()));
                 // The supervisor always runs the latest package on disk. When we have an update
                 // ensure that the lastest package on disk is the package we updated to.
                 idents_to_restart_and_latest_desired_on_restart.push((ident.clone(),
                                                                       Some(new_ident)));
             } else if service.needs_restart {
+                statsd::service_restarted(&service.service_group, "restart");
                 idents_to_restart_and_latest_desired_on_restart.push((ident.clone(), None));
             } else {
                 trace!("No restart required for {}", ident);
@@ -1311,6 +1634,25 @@ impl Manager {
 
     fn check_for_departure(&self) -> bool { self.butterfly.is_departed() }
 
+    /// Record that the main loop is still running, and whether the Supervisor has joined the
+    /// gossip ring, for the HTTP gateway's `/live` and `/ready` endpoints. A Supervisor without
+    /// any peers has nothing to join. The number of alive peers is recorded for `hab sup status`.
+    ///
+    /// # Locking (see locking.md)
+    /// * `MemberList::initial_members` (read)
+    /// * `MemberList::entries` (read)
+    /// * `GatewayState::inner` (write)
+    fn update_readiness_imlr_mlr_gsw(&self) {
+        let member_list = &self.butterfly.member_list;
+        let gossip_joined = member_list.len_initial_members_imlr() == 0
+                            || member_list.has_alive_peer_mlr(self.butterfly.member_id());
+        let gossip_peers = member_list.alive_peer_count_mlr(self.butterfly.member_id());
+        let mut gsw = self.state.gateway_state.lock_gsw();
+        gsw.set_gossip_joined(gossip_joined);
+        gsw.set_gossip_peers(gossip_peers);
+        gsw.mark_alive();
+    }
+
     fn check_for_restart(&self) -> bool {
         let should_restart = self.state.should_restart.load(Ordering::Relaxed);
         #[cfg(unix)]
@@ -1329,7 +1671,8 @@ impl Manager {
         let mut service_states = HashMap::new();
         let mut active_services = Vec::new();
         for service in self.state.services.lock_msr().services() {
-            service_states.insert(service.spec_ident(), service.last_state_change());
+            service_states.insert(service.spec_ident(),
+                                  (service.last_state_change(), service.resource_usage_sampled()));
             active_services.push(service.spec_ident());
         }
 
@@ -1344,7 +1687,7 @@ impl Manager {
             //
             // TODO (CM): why do we bother tracking loaded but not
             // running services at all?
-            service_states.insert(loaded.ident.clone(), SystemTime::UNIX_EPOCH);
+            service_states.insert(loaded.ident.clone(), (SystemTime::UNIX_EPOCH, None));
         }
 
         if service_states != self.service_states {
@@ -1361,6 +1704,8 @@ impl Manager {
     /// * `GatewayState::inner` (write)
     /// * `ManagerServices::inner` (read)
     async fn persist_state_rsr_mlr_gsw_msr(&mut self) {
+        self.gateway_state_stale = false;
+        self.gateway_state_persisted_at = Some(Instant::now());
         debug!("Updating census state");
         self.persist_census_state_gsw();
         debug!("Updating butterfly state");
@@ -1416,7 +1761,8 @@ impl Manager {
                                           self.organization.as_deref(),
                                           self.state.gateway_state.clone(),
                                           self.pid_source,
-                                          self.feature_flags).await;
+                                          self.feature_flags,
+                                          self.service_output.clone()).await;
                 match result {
                     Ok(result) => watched_services.push(result),
                     Err(ref e) => warn!("Failed to create service '{}' from spec: {:?}", ident, e),
@@ -1487,6 +1833,7 @@ impl Manager {
         let stop_it = async move {
             service.stop_gsw(shutdown_config).await;
             event::service_stopped(&service);
+            statsd::service_stopped(&service.service_group);
             user_config_watcher.remove(&service);
             service_updater.lock().remove(&service.service_group);
             // At this point the service process is stopped but the package is still loaded by the
@@ -1643,12 +1990,22 @@ impl Manager {
                     // Relies on spec.ident not having changed, which
                     // ServiceSpec#reconcile must guarantee.
                     if let Some(s) = services.get_mut(&spec.ident) {
-                        s.set_spec(spec);
+                        // Swapping the spec in while holding the
+                        // services lock means the next tick sees the
+                        // new binds together with the request to
+                        // re-resolve them.
+                        if ops.contains(&RefreshOperation::Rebind) {
+                            s.rebind(spec);
+                        } else {
+                            s.set_spec(spec);
+                        }
                         for op in ops {
                             match op {
                                 RefreshOperation::RestartUpdater => {
                                     self.service_updater.lock().register(&s);
                                 }
+                                // Handled when the spec was swapped in above
+                                RefreshOperation::Rebind => {}
                             }
                         }
                     } else {
@@ -1727,18 +2084,31 @@ impl Manager {
                   .collect()
     }
 
+    /// Peers added to the peer watch file become initial members, which are pinged until they
+    /// join. Members at the addresses of peers removed from it are suspected, so that they are
+    /// confirmed and eventually departed unless they are still alive.
+    ///
     /// # Locking (see locking.md)
-    /// * `MemberList::entries` (read)
+    /// * `MemberList::entries` (write)
     /// * `MemberList::initial_members` (write)
-    fn update_peers_from_watch_file_mlr_imlw(&mut self) -> Result<()> {
-        if !self.butterfly.need_peer_seeding_mlr() {
-            return Ok(());
-        }
+    /// * `RumorHeat::inner` (write)
+    fn update_peers_from_watch_file_mlw_imlw_rhw(&mut self) -> Result<()> {
         match self.peer_watcher {
             None => Ok(()),
-            Some(ref watcher) => {
+            Some(ref mut watcher) => {
                 if watcher.has_fs_events() {
-                    let members = watcher.get_members()?;
+                    let PeerChanges { members, removed } = watcher.get_changes()?;
+                    for peer in removed {
+                        if self.butterfly
+                               .suspect_members_at_mlw_rhw(&peer.address, peer.swim_port)
+                           > 0
+                        {
+                            outputln!("Peer {}:{} was removed from the peer watch file; checking \
+                                       whether it is gone",
+                                      peer.address,
+                                      peer.swim_port);
+                        }
+                    }
                     self.butterfly.member_list.set_initial_members_imlw(members);
                 }
                 Ok(())
@@ -1746,6 +2116,28 @@ impl Manager {
         }
     }
 
+    /// The certificate of the gateways, read from files or provided by the Supervisor itself,
+    /// if they are to use TLS.
+    fn gateway_certs(&self) -> Result<Option<Arc<GatewayCerts>>> {
+        let cfg = &self.state.cfg;
+        let certs = if let Some(ref tls_config) = cfg.tls_config {
+            GatewayCerts::from_files(&tls_config.cert_path, &tls_config.key_path)?
+        } else if let Some(ref source) = cfg.gateway_cert_source {
+            // Ordering a certificate from an ACME CA can take a while
+            task::block_in_place(|| {
+                GatewayCerts::provide(source.clone(), &self.fs_cfg.data_path, &self.sys.hostname)
+            })?
+        } else if cfg.ctl_gateway_tls || cfg.ctl_listeners.iter().any(|l| l.tls) {
+            let reason = "the ctl gateway needs the certificate of the HTTP gateway to use TLS";
+            return Err(Error::GatewayCert(reason.to_string()));
+        } else {
+            return Ok(None);
+        };
+        let certs = Arc::new(certs);
+        Arc::clone(&certs).start_renewal()?;
+        Ok(Some(certs))
+    }
+
     /// # Locking (see locking.md)
     /// * `ManagerServices::inner` (write)
     fn update_running_services_from_user_config_watcher_msw(&mut self) {
@@ -1760,8 +2152,12 @@ impl Manager {
 
 ////////////////////////////////////////////////////////////////////////
 
-fn tls_config(config: &TLSConfig) -> Result<rustls::ServerConfig> {
-    let client_auth = match &config.ca_cert_path {
+/// The TLS configuration of the HTTP gateway, which authenticates clients with the CA certificate
+/// when there is one.
+fn http_tls_config(ca_cert_path: Option<&PathBuf>,
+                   certs: Arc<GatewayCerts>)
+                   -> Result<rustls::ServerConfig> {
+    let client_auth = match ca_cert_path {
         Some(path) => {
             let mut root_store = RootCertStore::empty();
             let ca_file = &mut BufReader::new(File::open(path)?);
@@ -1779,26 +2175,25 @@ fn tls_config(config: &TLSConfig) -> Result<rustls::ServerConfig> {
     };
 
     let mut server_config = ServerConfig::new(client_auth);
-    let key_file = &mut BufReader::new(File::open(&config.key_path)?);
-    let cert_file = &mut BufReader::new(File::open(&config.cert_path)?);
-
-    // Note that we must explicitly map these errors because rustls returns () as the error from
-    // both pemfile::certs() as well as pemfile::rsa_private_keys() and we want to return
-    // different errors for each.
-    let cert_chain =
-        pemfile::certs(cert_file).and_then(|c| if c.is_empty() { Err(()) } else { Ok(c) })
-                                 .map_err(|_| Error::InvalidCertFile(config.cert_path.clone()))?;
-
-    let key = pemfile::rsa_private_keys(key_file).and_then(|mut k| k.pop().ok_or(()))
-                                                 .map_err(|_| {
-                                                     Error::InvalidKeyFile(config.key_path.clone())
-                                                 })?;
-
-    server_config.set_single_cert(cert_chain, key)?;
+    server_config.cert_resolver = certs;
     server_config.ignore_client_order = true;
     Ok(server_config)
 }
 
+/// Bind a listener of the ctl gateway to `addr`.
+async fn bind_ctl_listener(addr: SocketAddr) -> Result<TcpListener> {
+    TcpListener::bind(addr).await
+                           .map_err(|_| Error::BadAddress(addr.to_string()))
+}
+
+/// The TLS configuration of the ctl gateway. Clients authenticate with the ctl secret instead of
+/// certificates.
+fn ctl_tls_config(certs: Arc<GatewayCerts>) -> rustls::ServerConfig {
+    let mut server_config = ServerConfig::new(NoClientAuth::new());
+    server_config.cert_resolver = certs;
+    server_config
+}
+
 fn obtain_process_lock(fs_cfg: &FsCfg) -> Result<()> {
     match write_process_lock(&fs_cfg.proc_lock_file) {
         Ok(()) => Ok(()),
@@ -1857,6 +2252,12 @@ fn release_process_lock(fs_cfg: &FsCfg) {
     }
 }
 
+/// The PID of the Launcher the Supervisor runs under, if any.
+fn launcher_pid() -> Option<u32> {
+    env::var(LAUNCHER_PID_ENV).ok()
+                              .and_then(|pid| pid.parse().ok())
+}
+
 fn write_process_lock<T>(lock_path: T) -> Result<()>
     where T: AsRef<Path>
 {
@@ -1981,6 +2382,7 @@ mod test {
         fn default() -> Self {
             ManagerConfig { auto_update:           false,
                             auto_update_period:    Duration::from_secs(60),
+                            auto_update_window:    None,
                             service_update_period: Duration::from_secs(60),
                             custom_state_path:     None,
                             key_cache:             KeyCache::new(&*CACHE_KEY_PATH),
@@ -1995,11 +2397,24 @@ mod test {
                             ring_key:              None,
                             organization:          None,
                             watch_peer_file:       None,
+                            gossip_tls_config:     None,
                             tls_config:            None,
+                            gateway_cert_source:   None,
+                            ctl_gateway_tls:       false,
+                            ctl_listeners:         vec![],
+                            ctl_request_timeout:   None,
                             feature_flags:         FeatureFlag::empty(),
                             event_stream_config:   None,
+                            vault_config:          None,
+                            statsd_config:         None,
+                            encrypt_specs:         false,
                             keep_latest_packages:  None,
-                            sys_ip:                IpAddr::V4(Ipv4Addr::LOCALHOST), }
+                            sys_ip:                IpAddr::V4(Ipv4Addr::LOCALHOST),
+                            tags:                  Vec::new(),
+                            sys_cloud_metadata:    None,
+                            departure_timeout:     Duration::from_secs(259_200),
+                            departed_retention:    None,
+                            service_output:        ServiceOutputConfig::default(), }
         }
     }
 
@@ -2029,4 +2444,23 @@ mod test {
 
         assert_eq!(PathBuf::from("/tmp/partay"), path);
     }
+
+    #[test]
+    fn gateway_state_is_ready_once_every_condition_holds() {
+        let gateway_state = sync::GatewayState::default();
+        assert!(!gateway_state.lock_gsr().readiness().is_ready());
+        assert!(!gateway_state.lock_gsr().is_alive(Duration::from_secs(60)));
+
+        gateway_state.lock_gsw().set_gossip_joined(true);
+        gateway_state.lock_gsw().set_specs_reconciled();
+        assert!(!gateway_state.lock_gsr().readiness().is_ready());
+
+        gateway_state.lock_gsw().set_ctl_gateway_listening();
+        gateway_state.lock_gsw().mark_alive();
+        assert!(gateway_state.lock_gsr().readiness().is_ready());
+        assert!(gateway_state.lock_gsr().is_alive(Duration::from_secs(60)));
+
+        gateway_state.lock_gsw().set_gossip_joined(false);
+        assert!(!gateway_state.lock_gsr().readiness().is_ready());
+    }
 }
@@ -2,28 +2,45 @@
 
 use crate::{ctl_gateway::CtlRequest,
             error::Error,
+            event,
             manager::{action::{ActionSender,
                                SupervisorAction},
+                      config_audit::ConfigAuditEntry,
                       service::{spec::ServiceSpec,
                                 DesiredState,
+                                HealthCheckRecord,
+                                HealthCheckResult,
+                                HookFailure,
                                 ProcessState},
+                      spec_dir::SpecDir,
                       ManagerState},
+            sys::{self,
+                  ServiceResourceUsage},
             util};
-use habitat_butterfly as butterfly;
+use configopt::ConfigOpt;
+use hab::cli::hab::sup::SupRun;
+use habitat_butterfly::{self as butterfly,
+                        member::Health};
 use habitat_common::{command::package::install::InstallSource,
                      outputln,
                      templating::package::Pkg,
                      ui::UIWriter};
-use habitat_core::{package::{Identifiable,
+use habitat_core::{os::cgroup,
+                   package::{Identifiable,
                              PackageIdent,
                              PackageTarget},
                    service::ServiceGroup};
 use habitat_sup_protocol::{self as protocol,
                            net::{self,
                                  ErrCode,
-                                 NetResult}};
-use std::{convert::TryFrom,
+                                 NetResult},
+                           types::SupervisorMember};
+use std::{collections::BTreeMap,
+          convert::TryFrom,
           fmt,
+          iter,
+          net::{IpAddr,
+                SocketAddr},
           result,
           sync::atomic::Ordering,
           time::{Duration,
@@ -125,21 +142,24 @@ pub fn service_cfg_set(mgr: &ManagerState,
     outputln!("Setting new configuration version {} for {}",
               version,
               service_group,);
-    let mut client =
-        match butterfly::client::Client::new(&mgr.cfg.gossip_listen.local_addr().to_string(),
-                                             mgr.cfg.ring_key.clone())
-        {
-            Ok(client) => client,
-            Err(err) => {
-                outputln!("Failed to connect to own gossip server, {}", err);
-                return Err(net::err(ErrCode::Internal, err.to_string()));
-            }
-        };
-    client.send_service_config(service_group, version, &cfg, is_encrypted)
-          .map_err(|e| net::err(ErrCode::Internal, e.to_string()))
-          .map(|_| {
-              req.reply_complete(net::ok());
-          })
+    let mut client = match gossip_client(mgr) {
+        Ok(client) => client,
+        Err(err) => {
+            outputln!("Failed to connect to own gossip server, {}", err);
+            return Err(net::err(ErrCode::Internal, err.to_string()));
+        }
+    };
+    client.send_service_config(service_group.clone(), version, &cfg, is_encrypted)
+          .map_err(|e| net::err(ErrCode::Internal, e.to_string()))?;
+    let entry = ConfigAuditEntry::new(service_group,
+                                      None,
+                                      version,
+                                      &cfg,
+                                      is_encrypted,
+                                      req.client());
+    record_config_audit(mgr, &entry)?;
+    req.reply_complete(net::ok());
+    Ok(())
 }
 
 pub fn service_file_put(mgr: &ManagerState,
@@ -158,21 +178,43 @@ pub fn service_file_put(mgr: &ManagerState,
               version,
               filename,
               service_group,);
-    let mut client =
-        match butterfly::client::Client::new(&mgr.cfg.gossip_listen.local_addr().to_string(),
-                                             mgr.cfg.ring_key.clone())
-        {
-            Ok(client) => client,
-            Err(err) => {
-                outputln!("Failed to connect to own gossip server, {}", err);
-                return Err(net::err(ErrCode::Internal, err.to_string()));
-            }
-        };
-    client.send_service_file(service_group, filename, version, &content, is_encrypted)
-          .map_err(|e| net::err(ErrCode::Internal, e.to_string()))
-          .map(|_| {
-              req.reply_complete(net::ok());
-          })
+    let mut client = match gossip_client(mgr) {
+        Ok(client) => client,
+        Err(err) => {
+            outputln!("Failed to connect to own gossip server, {}", err);
+            return Err(net::err(ErrCode::Internal, err.to_string()));
+        }
+    };
+    client.send_service_file(service_group.clone(),
+                             filename.clone(),
+                             version,
+                             &content,
+                             is_encrypted)
+          .map_err(|e| net::err(ErrCode::Internal, e.to_string()))?;
+    let entry = ConfigAuditEntry::new(service_group,
+                                      Some(filename),
+                                      version,
+                                      &content,
+                                      is_encrypted,
+                                      req.client());
+    record_config_audit(mgr, &entry)?;
+    req.reply_complete(net::ok());
+    Ok(())
+}
+
+pub fn service_config_audit(mgr: &ManagerState,
+                            req: &mut CtlRequest,
+                            opts: protocol::ctl::SvcConfigAudit)
+                            -> NetResult<()> {
+    let service_group: Option<ServiceGroup> = opts.service_group.map(Into::into);
+    let limit = opts.limit.map(|limit| limit as usize);
+    let entries = mgr.config_audit
+                     .entries(service_group.as_ref(), limit)
+                     .map_err(|e| net::err(ErrCode::Internal, e.to_string()))?;
+    req.reply_complete(protocol::types::ConfigAudit { entries: entries.into_iter()
+                                                                      .map(Into::into)
+                                                                      .collect(), });
+    Ok(())
 }
 
 pub async fn service_load(mgr: &ManagerState,
@@ -198,6 +240,12 @@ pub async fn service_load(mgr: &ManagerState,
     };
 
     let package = util::pkg::satisfy_or_install(req, &source, &spec.bldr_url, &spec.channel).await?;
+    // A client that stopped waiting was told the load was cancelled, so it must not take effect
+    if req.is_cancelled() {
+        return Err(net::err(ErrCode::Timeout,
+                            format!("Loading the {} service was cancelled",
+                                    spec.ident)));
+    }
     spec.validate(&package)?;
     mgr.cfg.save_spec_for(&spec)?;
 
@@ -213,7 +261,7 @@ pub fn service_update(mgr: &ManagerState,
                       -> NetResult<()> {
     let ident: PackageIdent = opts.ident.clone().ok_or_else(err_update_client)?.into();
     if let Some(mut service_spec) = mgr.cfg.spec_for_ident(&ident) {
-        service_spec.merge_svc_update(opts);
+        service_spec.merge_svc_update(opts)?;
         let action = SupervisorAction::UpdateService { service_spec };
         send_action(action, action_sender)?;
 
@@ -308,16 +356,13 @@ pub fn supervisor_depart(mgr: &ManagerState,
                          opts: protocol::ctl::SupDepart)
                          -> NetResult<()> {
     let member_id = opts.member_id.ok_or_else(err_update_client)?;
-    let mut client =
-        match butterfly::client::Client::new(&mgr.cfg.gossip_listen.local_addr().to_string(),
-                                             mgr.cfg.ring_key.clone())
-        {
-            Ok(client) => client,
-            Err(err) => {
-                outputln!("Failed to connect to own gossip server, {}", err);
-                return Err(net::err(ErrCode::Internal, err.to_string()));
-            }
-        };
+    let mut client = match gossip_client(mgr) {
+        Ok(client) => client,
+        Err(err) => {
+            outputln!("Failed to connect to own gossip server, {}", err);
+            return Err(net::err(ErrCode::Internal, err.to_string()));
+        }
+    };
     outputln!("Attempting to depart member: {}", member_id);
     match client.send_departure(&member_id) {
         Ok(()) => {
@@ -328,15 +373,141 @@ pub fn supervisor_depart(mgr: &ManagerState,
     }
 }
 
-#[allow(clippy::needless_pass_by_value)]
+/// Purge the members that departed the gossip ring, along with the service rumors they started,
+/// so that they are no longer part of the census or the persisted rumors.
+///
+/// # Locking (see locking.md)
+/// * `RumorStore::list` (write)
+/// * `MemberList::entries` (write)
+/// * `RumorHeat::inner` (write)
+pub fn supervisor_purge_departed_rsw_mlw_rhw(mgr: &ManagerState,
+                                             req: &mut CtlRequest,
+                                             opts: protocol::ctl::SupPurgeDeparted)
+                                             -> NetResult<()> {
+    let older_than = Duration::from_secs(opts.older_than.unwrap_or_default());
+    let purged = mgr.butterfly.purge_departed_rsw_mlw_rhw(older_than);
+    outputln!("Purged {} departed members", purged.len());
+    req.info(format!("Purged {} departed members", purged.len()))?;
+    req.reply_complete(net::ok());
+    Ok(())
+}
+
+/// Restart the Supervisor without stopping its services. When new arguments are given they are
+/// validated here, as a restarted Supervisor failing to parse them would take every service
+/// down with it.
 pub fn supervisor_restart(mgr: &ManagerState,
                           _req: &mut CtlRequest,
-                          _opts: protocol::ctl::SupRestart)
+                          opts: protocol::ctl::SupRestart)
                           -> NetResult<()> {
+    if !opts.args.is_empty() {
+        let args: Vec<String> = iter::once(String::from("run")).chain(opts.args).collect();
+        if let Err(err) = SupRun::try_from_iter_with_configopt(&args) {
+            return Err(net::err(ErrCode::InvalidPayload,
+                                format!("Invalid Supervisor arguments: {}",
+                                        err.message)));
+        }
+        *mgr.restart_args.lock() = Some(args);
+    }
     mgr.should_restart.store(true, Ordering::Relaxed);
     Ok(())
 }
 
+/// Report the health and resource usage of the Supervisor itself.
+///
+/// # Locking (see locking.md)
+/// * `GatewayState::inner` (read)
+pub fn supervisor_status_gsr(mgr: &ManagerState,
+                             req: &mut CtlRequest,
+                             _opts: protocol::ctl::SupStatus)
+                             -> NetResult<()> {
+    let (gossip_peers, specs_reconciled_at) = {
+        let gsr = mgr.gateway_state.lock_gsr();
+        (gsr.gossip_peers(), gsr.specs_reconciled_at())
+    };
+    let spec_reconciliation_lag =
+        match (SpecDir::new(mgr.cfg.sup_root().join("specs")), specs_reconciled_at) {
+            (Ok(spec_dir), Some(reconciled_at)) => spec_dir.reconciliation_lag(reconciled_at),
+            _ => Duration::default(),
+        };
+    let usage = sys::process::own_resource_usage();
+    let cpu_time_ms = usage.cpu_time.map(|cpu_time| cpu_time.as_millis() as u64);
+    let event_stream_backlog = event::backlog().map(|backlog| backlog as u64);
+    let msg =
+        protocol::types::SupervisorStatus { uptime_seconds: mgr.started.elapsed().as_secs(),
+                                            memory_bytes: usage.resident_memory_bytes,
+                                            cpu_time_ms,
+                                            gossip_peers: gossip_peers as u64,
+                                            spec_reconciliation_lag_seconds:
+                                                spec_reconciliation_lag.as_secs(),
+                                            event_stream_backlog };
+    req.reply_complete(msg);
+    Ok(())
+}
+
+/// The members of the gossip ring running services, with the address of their Control Gateway, so
+/// that a client can ask each of them about their services.
+///
+/// # Locking (see locking.md)
+/// * `RumorStore::list` (read)
+/// * `MemberList::entries` (read)
+pub fn supervisor_members_rsr_mlr(mgr: &ManagerState,
+                                  req: &mut CtlRequest,
+                                  _opts: protocol::ctl::SupMembers)
+                                  -> NetResult<()> {
+    let local_member_id = mgr.butterfly.member_id();
+    let mut members = BTreeMap::new();
+    for (service_group, rumors) in mgr.butterfly.service_store.lock_rsr().iter() {
+        for (member_id, rumor) in rumors.iter() {
+            let member = members.entry(member_id.clone()).or_insert_with(|| {
+                                                             new_member(member_id,
+                                                                        &rumor.sys,
+                                                                        local_member_id)
+                                                         });
+            member.service_groups.push(service_group.clone());
+        }
+    }
+    for member in members.values_mut() {
+        member.alive = mgr.butterfly
+                          .member_list
+                          .health_of_by_id_mlr(&member.member_id)
+                       == Some(Health::Alive);
+    }
+
+    if members.is_empty() {
+        req.reply_complete(net::ok());
+    } else {
+        let mut list = members.into_iter().map(|(_, member)| member).peekable();
+        while let Some(member) = list.next() {
+            if list.peek().is_some() {
+                req.reply_partial(member);
+            } else {
+                req.reply_complete(member);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The member that started a service rumor with `sys`. Its Control Gateway is given with the IP
+/// address it advertises when it listens on a loopback or unspecified address, and left out when
+/// that address isn't valid either.
+fn new_member(member_id: &str,
+              sys: &butterfly::rumor::service::SysInfo,
+              local_member_id: &str)
+              -> SupervisorMember {
+    let ip = match sys.ctl_gateway_ip.parse::<IpAddr>() {
+        Ok(ip) if !ip.is_loopback() && !ip.is_unspecified() => Some(ip),
+        _ => sys.ip.parse().ok(),
+    };
+    let ctl_gateway = ip.map(|ip| SocketAddr::new(ip, sys.ctl_gateway_port as u16).to_string());
+    SupervisorMember { member_id: member_id.to_string(),
+                       hostname: sys.hostname.clone(),
+                       ctl_gateway,
+                       alive: false,
+                       local: member_id == local_member_id,
+                       service_groups: Vec::new() }
+}
+
 /// # Locking (see locking.md)
 /// * `GatewayState::inner` (read)
 pub fn service_status_gsr(mgr: &ManagerState,
@@ -374,16 +545,79 @@ pub fn service_status_gsr(mgr: &ManagerState,
     Ok(())
 }
 
+/// # Locking (see locking.md)
+/// * `GatewayState::inner` (read)
+pub fn service_health_history_gsr(mgr: &ManagerState,
+                                  req: &mut CtlRequest,
+                                  opts: protocol::ctl::SvcHealthHistory)
+                                  -> NetResult<()> {
+    let gsr = mgr.gateway_state.lock_gsr();
+    let statuses: Vec<ServiceStatus> =
+        serde_json::from_str(gsr.services_data()).map_err(Error::ServiceDeserializationError)?;
+
+    let mut histories = statuses.into_iter()
+                                .filter(|status| {
+                                    opts.ident
+                                        .as_ref()
+                                        .map_or(true, |ident| status.pkg.ident.satisfies(ident))
+                                })
+                                .map(|status| {
+                                    let records =
+                                        gsr.health_history_of(&status.service_group)
+                                           .map(|history| history.iter().map(Into::into).collect())
+                                           .unwrap_or_default();
+                                    protocol::types::HealthCheckHistory {
+                                        ident: PackageIdent::from(status.pkg.ident).into(),
+                                        service_group: status.service_group.into(),
+                                        records,
+                                    }
+                                })
+                                .collect::<Vec<_>>();
+
+    match (histories.pop(), opts.ident) {
+        (Some(last), _) => {
+            for history in histories {
+                req.reply_partial(history);
+            }
+            req.reply_complete(last);
+            Ok(())
+        }
+        (None, Some(ident)) => {
+            Err(net::err(ErrCode::NotFound, format!("Service not loaded, {}", ident)))
+        }
+        (None, None) => {
+            req.reply_complete(net::ok());
+            Ok(())
+        }
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////
 // Private helper functions
+/// Record an applied configuration or file. The change has been gossiped by the time it is
+/// recorded, so a failure to record it is reported to the client as such.
+fn record_config_audit(mgr: &ManagerState, entry: &ConfigAuditEntry) -> NetResult<()> {
+    if let Err(err) = mgr.config_audit.record(entry) {
+        outputln!("Failed to record change to {}, {}",
+                  entry.service_group,
+                  err);
+        return Err(net::err(ErrCode::Internal,
+                            format!("Applied, but failed to record the \
+                                     change in the audit log, {}",
+                                    err)));
+    }
+    Ok(())
+}
+
 fn err_update_client() -> net::NetErr { net::err(ErrCode::UpdateClient, "client out of date") }
 
 #[derive(Deserialize)]
 struct ServiceStatus {
-    pkg:           Pkg,
-    process:       ProcessStatus,
-    service_group: ServiceGroup,
-    desired_state: DesiredState,
+    pkg:               Pkg,
+    process:           ProcessStatus,
+    service_group:     ServiceGroup,
+    desired_state:     DesiredState,
+    last_hook_failure: Option<HookFailure>,
 }
 
 impl From<ServiceStatus> for protocol::types::ServiceStatus {
@@ -393,10 +627,43 @@ impl From<ServiceStatus> for protocol::types::ServiceStatus {
         proto.process = Some(other.process.into());
         proto.service_group = other.service_group.into();
         proto.desired_state = Some(other.desired_state.into());
+        proto.last_hook_failure = other.last_hook_failure.map(Into::into);
         proto
     }
 }
 
+impl From<HookFailure> for protocol::types::HookFailure {
+    fn from(other: HookFailure) -> Self {
+        protocol::types::HookFailure { hook:        other.hook,
+                                       timestamp:   other.timestamp,
+                                       exit_status: other.exit_status,
+                                       error:       other.error,
+                                       stdout:      other.stdout,
+                                       stderr:      other.stderr, }
+    }
+}
+
+impl From<HealthCheckResult> for protocol::types::HealthCheckResult {
+    fn from(other: HealthCheckResult) -> Self {
+        match other {
+            HealthCheckResult::Ok => protocol::types::HealthCheckResult::Ok,
+            HealthCheckResult::Warning => protocol::types::HealthCheckResult::Warning,
+            HealthCheckResult::Critical => protocol::types::HealthCheckResult::Critical,
+            HealthCheckResult::Unknown => protocol::types::HealthCheckResult::Unknown,
+        }
+    }
+}
+
+impl From<&HealthCheckRecord> for protocol::types::HealthCheckRecord {
+    fn from(other: &HealthCheckRecord) -> Self {
+        let result: protocol::types::HealthCheckResult = other.result.into();
+        protocol::types::HealthCheckRecord { timestamp:   other.timestamp,
+                                             result:      result as i32,
+                                             duration_ms: other.duration_ms,
+                                             output:      other.output.clone(), }
+    }
+}
+
 // NOTE: This effectively the inverse of
 // habitat_sup::manager::service::supervisor::Supervisor's `Serialize`
 // implementation. When you trace the code, we're basically
@@ -410,9 +677,13 @@ impl From<ServiceStatus> for protocol::types::ServiceStatus {
 struct ProcessStatus {
     #[serde(deserialize_with = "duration_from_epoch_offset",
             rename = "state_entered")]
-    elapsed: Duration,
-    pid:     Option<u32>,
-    state:   ProcessState,
+    elapsed:        Duration,
+    pid:            Option<u32>,
+    state:          ProcessState,
+    #[serde(default)]
+    resource_usage: Option<cgroup::Usage>,
+    #[serde(default)]
+    usage:          Option<ServiceResourceUsage>,
 }
 
 impl From<ProcessStatus> for protocol::types::ProcessStatus {
@@ -423,10 +694,21 @@ impl From<ProcessStatus> for protocol::types::ProcessStatus {
         if let Some(pid) = other.pid {
             proto.pid = Some(pid);
         }
+        proto.resource_usage = other.resource_usage.map(Into::into);
+        proto.usage = other.usage.map(Into::into);
         proto
     }
 }
 
+impl From<ServiceResourceUsage> for protocol::types::ProcessUsage {
+    fn from(other: ServiceResourceUsage) -> Self {
+        protocol::types::ProcessUsage { cpu_time_ms:     other.cpu_time_ms,
+                                        memory_bytes:    other.memory_bytes,
+                                        open_files:      other.open_files,
+                                        child_processes: other.child_processes, }
+    }
+}
+
 fn duration_from_epoch_offset<'de, D>(d: D) -> result::Result<Duration, D::Error>
     where D: serde::Deserializer<'de>
 {
@@ -469,3 +751,12 @@ fn send_action(action: SupervisorAction, sender: &ActionSender) -> NetResult<()>
         Ok(())
     }
 }
+
+/// Connect to this Supervisor's own gossip server, over mutual TLS if the ring uses it.
+fn gossip_client(mgr: &ManagerState) -> butterfly::error::Result<butterfly::client::Client> {
+    let addr = *mgr.cfg.gossip_listen.local_addr();
+    match mgr.butterfly.gossip_tls() {
+        Some(tls) => butterfly::client::Client::new_tls(addr, mgr.cfg.ring_key.clone(), tls),
+        None => butterfly::client::Client::new(&addr.to_string(), mgr.cfg.ring_key.clone()),
+    }
+}
@@ -0,0 +1,182 @@
+//! An append-only record of the configuration and files applied to service groups through the
+//! control gateway of this Supervisor, so that changes made with `hab config apply` and `hab file
+//! upload` can be attributed to whoever made them.
+//!
+//! Each change is a line of JSON in the audit log in the Supervisor's data directory. Lines are
+//! only ever appended; the log is never rewritten or truncated by the Supervisor.
+
+use crate::error::{Error,
+                   Result};
+use habitat_common::outputln;
+use habitat_core::{crypto::Blake2bHash,
+                   service::ServiceGroup};
+use habitat_sup_protocol as protocol;
+use std::{fs::{File,
+               OpenOptions},
+          io::{BufRead,
+               BufReader,
+               ErrorKind,
+               Write},
+          path::{Path,
+                 PathBuf},
+          sync::Mutex,
+          time::SystemTime};
+
+static LOGKEY: &str = "CA";
+pub const CONFIG_AUDIT_FILE: &str = "config_audit.log";
+
+/// A configuration or file applied to a service group.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ConfigAuditEntry {
+    /// When the change was applied, in seconds since the UNIX epoch
+    pub timestamp:     u64,
+    pub service_group: ServiceGroup,
+    /// The name of the uploaded file, or `None` for configuration
+    pub filename:      Option<String>,
+    pub incarnation:   u64,
+    /// The hex encoded hash of the content as it was received
+    pub hash:          String,
+    pub encrypted:     bool,
+    /// The identity the client claimed and the address it connected from
+    pub client:        String,
+}
+
+impl ConfigAuditEntry {
+    pub fn new(service_group: ServiceGroup,
+               filename: Option<String>,
+               incarnation: u64,
+               content: &[u8],
+               encrypted: bool,
+               client: Option<&str>)
+               -> Self {
+        let timestamp = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)
+                                         .map(|d| d.as_secs())
+                                         .unwrap_or_default();
+        ConfigAuditEntry { timestamp,
+                           service_group,
+                           filename,
+                           incarnation,
+                           hash: Blake2bHash::from_bytes(content).to_string(),
+                           encrypted,
+                           client: client.unwrap_or("unknown").to_string() }
+    }
+}
+
+impl From<ConfigAuditEntry> for protocol::types::ConfigAuditEntry {
+    fn from(other: ConfigAuditEntry) -> Self {
+        protocol::types::ConfigAuditEntry { timestamp:     other.timestamp,
+                                            service_group: other.service_group.into(),
+                                            filename:      other.filename,
+                                            incarnation:   other.incarnation,
+                                            hash:          other.hash,
+                                            encrypted:     other.encrypted,
+                                            client:        other.client, }
+    }
+}
+
+#[derive(Debug)]
+pub struct ConfigAuditLog {
+    path: PathBuf,
+    /// Serializes appends so that the lines of concurrent changes are never interleaved.
+    lock: Mutex<()>,
+}
+
+impl ConfigAuditLog {
+    pub fn new<P>(data_path: P) -> Self
+        where P: AsRef<Path>
+    {
+        ConfigAuditLog { path: data_path.as_ref().join(CONFIG_AUDIT_FILE),
+                         lock: Mutex::default(), }
+    }
+
+    /// Append a change to the log, making sure it reached the disk.
+    pub fn record(&self, entry: &ConfigAuditEntry) -> Result<()> {
+        let _guard = self.lock.lock().expect("ConfigAuditLog lock poisoned");
+        let mut line = serde_json::to_string(entry).map_err(Error::ServiceSerializationError)?;
+        line.push('\n');
+        let mut file = OpenOptions::new().create(true)
+                                         .append(true)
+                                         .open(&self.path)
+                                         .map_err(|e| self.error(e))?;
+        file.write_all(line.as_bytes())
+            .and_then(|_| file.sync_data())
+            .map_err(|e| self.error(e))
+    }
+
+    /// The changes applied to the given service group, or to any service group when `None`,
+    /// oldest first. Only the most recent `limit` changes are returned when given.
+    pub fn entries(&self,
+                   service_group: Option<&ServiceGroup>,
+                   limit: Option<usize>)
+                   -> Result<Vec<ConfigAuditEntry>> {
+        let file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(ref e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(self.error(e)),
+        };
+        let mut entries = Vec::new();
+        for line in BufReader::new(file).lines() {
+            let line = line.map_err(|e| self.error(e))?;
+            match serde_json::from_str::<ConfigAuditEntry>(&line) {
+                Ok(entry) => {
+                    if service_group.map_or(true, |sg| *sg == entry.service_group) {
+                        entries.push(entry);
+                    }
+                }
+                // A line cut short by a crash shouldn't hide the rest of the log
+                Err(e) => {
+                    outputln!("Skipping unreadable entry in {}, {}",
+                              self.path.display(),
+                              e)
+                }
+            }
+        }
+        if let Some(limit) = limit {
+            let skip = entries.len().saturating_sub(limit);
+            entries.drain(..skip);
+        }
+        Ok(entries)
+    }
+
+    fn error(&self, err: std::io::Error) -> Error { Error::BadDataFile(self.path.clone(), err) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn entry(service_group: &str, filename: Option<&str>, incarnation: u64) -> ConfigAuditEntry {
+        ConfigAuditEntry::new(service_group.parse().unwrap(),
+                              filename.map(str::to_string),
+                              incarnation,
+                              b"port = 6379",
+                              false,
+                              Some("ops@example.com (127.0.0.1:50000)"))
+    }
+
+    #[test]
+    fn entries_are_appended_and_filtered() {
+        let data_path = TempDir::new().unwrap();
+        let log = ConfigAuditLog::new(data_path.path());
+        assert!(log.entries(None, None).unwrap().is_empty());
+
+        let first = entry("redis.default", None, 1);
+        let second = entry("nginx.default", Some("cert.pem"), 7);
+        let third = entry("redis.default", None, 2);
+        for e in &[&first, &second, &third] {
+            log.record(e).unwrap();
+        }
+
+        // A fresh handle sees everything recorded before
+        let log = ConfigAuditLog::new(data_path.path());
+        assert_eq!(log.entries(None, None).unwrap(),
+                   vec![first.clone(), second.clone(), third.clone()]);
+        assert_eq!(log.entries(Some(&"redis.default".parse().unwrap()), None)
+                      .unwrap(),
+                   vec![first, third.clone()]);
+        assert_eq!(log.entries(None, Some(1)).unwrap(), vec![third]);
+        assert_eq!(second.hash,
+                   Blake2bHash::from_bytes(b"port = 6379").to_string());
+    }
+}
@@ -0,0 +1,296 @@
+//! A dry run of `hab sup run`: the services the Supervisor would run are reconciled with the
+//! specs on disk, resolved against the installed packages and rendered with a census made of this
+//! Supervisor alone, without starting any service or joining the ring.
+
+use super::{service::{Cfg,
+                      DesiredState,
+                      Pkg,
+                      RenderContext,
+                      Service,
+                      ServiceSpec,
+                      Topology},
+            spec_dir::SpecDir,
+            FsCfg,
+            ManagerConfig,
+            Sys};
+use crate::{census::CensusRing,
+            error::{Error,
+                    Result}};
+use habitat_butterfly::{member::{Health,
+                                 Member,
+                                 MemberList},
+                        rumor::{service::Service as ServiceRumor,
+                                RumorStore}};
+use habitat_common::{outputln,
+                     templating::config::CfgRenderer};
+use habitat_core::{fs::FS_ROOT_PATH,
+                   package::{metadata::Bind,
+                             PackageInstall},
+                   service::ServiceGroup};
+use habitat_sup_protocol::{ctl::SvcLoad,
+                           types::BindingMode};
+use std::{collections::{BTreeMap,
+                        HashSet},
+          convert::TryFrom,
+          fs,
+          path::Path};
+
+static LOGKEY: &str = "DR";
+
+/// A service the Supervisor would start, with everything needed to render its templates.
+struct Planned {
+    spec:               ServiceSpec,
+    pkg:                Pkg,
+    cfg:                Cfg,
+    service_group:      ServiceGroup,
+    all_pkg_binds:      Vec<Bind>,
+    optional_pkg_binds: HashSet<String>,
+}
+
+/// Print what the Supervisor would do when started with `cfg` and told to load `svc_loads`.
+///
+/// Returns `Error::DryRunProblems` if any service would fail to load, start or render.
+pub async fn run(cfg: &ManagerConfig, svc_loads: Vec<SvcLoad>) -> Result<()> {
+    let fs_cfg = FsCfg::new(cfg.sup_root());
+    let mut problems = 0;
+
+    let mut specs: BTreeMap<String, ServiceSpec> = match SpecDir::new(&fs_cfg.specs_path) {
+        Ok(spec_dir) => {
+            spec_dir.specs()
+                    .into_iter()
+                    .map(|spec| (spec.ident.name.clone(), spec))
+                    .collect()
+        }
+        Err(_) => BTreeMap::new(),
+    };
+    outputln!("Dry run: {} service(s) loaded in {}",
+              specs.len(),
+              fs_cfg.specs_path.display());
+
+    // Loading a service behaves like `hab svc load` against the Supervisor's ctl gateway.
+    for svc_load in svc_loads {
+        let force = svc_load.force.unwrap_or(false);
+        let loaded = svc_load.ident
+                             .as_ref()
+                             .and_then(|ident| specs.get(&ident.name))
+                             .cloned();
+        let result = match loaded {
+            Some(loaded) if !force => {
+                outputln!("{} is already loaded; loading it again requires --force",
+                          loaded.ident);
+                problems += 1;
+                continue;
+            }
+            Some(loaded) => {
+                match loaded.clone().merge_svc_load(svc_load) {
+                    Ok(spec) if spec == loaded => Ok((spec, "keep the unchanged spec of")),
+                    result => result.map(|spec| (spec, "update the spec of")),
+                }
+            }
+            None => ServiceSpec::try_from(svc_load).map(|spec| (spec, "load")),
+        };
+        match result {
+            Ok((spec, change)) => {
+                outputln!("Would {} {}", change, spec.ident);
+                specs.insert(spec.ident.name.clone(), spec);
+            }
+            Err(err) => {
+                outputln!("Unable to load a service: {}", err);
+                problems += 1;
+            }
+        }
+    }
+
+    let mut planned = Vec::new();
+    for spec in specs.values() {
+        if spec.desired_state == DesiredState::Down {
+            outputln!("Would not start {}, which is down", spec.ident);
+            continue;
+        }
+        let package = match PackageInstall::load(&spec.ident, Some(Path::new(&*FS_ROOT_PATH))) {
+            Ok(package) => package,
+            Err(_) => {
+                outputln!("Would install {} from {} in the {} channel; its templates are not \
+                           rendered",
+                          spec.ident,
+                          spec.bldr_url,
+                          spec.channel);
+                continue;
+            }
+        };
+        match plan(cfg, &package, spec.clone()).await {
+            Ok(service) => planned.push(service),
+            Err(err) => {
+                outputln!("Unable to start {}: {}", package.ident, err);
+                problems += 1;
+            }
+        }
+    }
+
+    let mut sys = Sys::new(cfg.gossip_permanent,
+                           cfg.gossip_listen,
+                           cfg.ctl_listen,
+                           cfg.http_listen,
+                           cfg.sys_ip);
+    sys.tags = cfg.tags.clone();
+    sys.member_id =
+        fs::read_to_string(&fs_cfg.member_id_file).unwrap_or_else(|_| Member::default().id);
+    let census_ring = census_ring(cfg, &sys, &planned);
+
+    for service in &planned {
+        outputln!(preamble service.service_group, "Would start {}", service.pkg.ident);
+        if service.spec.topology == Topology::Leader
+           && cfg.gossip_peers.is_empty()
+           && cfg.watch_peer_file.is_none()
+        {
+            outputln!(preamble service.service_group,
+                      "The leader topology needs at least 3 members; without peers, the service \
+                       would wait for other Supervisors to join");
+        }
+        problems += check_binds(service, &census_ring);
+        problems += render(service, &sys, &census_ring);
+    }
+
+    if problems > 0 {
+        return Err(Error::DryRunProblems(problems));
+    }
+    outputln!("Dry run: {} service(s) would start", planned.len());
+    Ok(())
+}
+
+/// Resolve what a service needs to render its templates, like the Supervisor does when starting
+/// it, except that the service's user and group are not created.
+async fn plan(cfg: &ManagerConfig, package: &PackageInstall, spec: ServiceSpec) -> Result<Planned> {
+    spec.validate(package)?;
+    let mut pkg = Pkg::from_install(package).await?;
+    if let Some(ref user) = spec.svc_user {
+        pkg.svc_user = user.clone();
+    }
+    if let Some(ref group) = spec.svc_group {
+        pkg.svc_group = group.clone();
+    }
+    let cfg_data = Cfg::new(&pkg, spec.config_from.as_ref())?;
+    let service_group = ServiceGroup::new(&pkg.name, &spec.group, cfg.organization.as_deref())?;
+    let optional_pkg_binds = package.binds_optional()?
+                                    .into_iter()
+                                    .map(|b| b.service)
+                                    .collect();
+    Ok(Planned { all_pkg_binds: package.all_binds()?,
+                 optional_pkg_binds,
+                 spec,
+                 pkg,
+                 cfg: cfg_data,
+                 service_group })
+}
+
+/// The census of a ring made of this Supervisor alone, running the planned services.
+fn census_ring(cfg: &ManagerConfig, sys: &Sys, planned: &[Planned]) -> CensusRing {
+    let service_rumors = RumorStore::default();
+    for service in planned {
+        service_rumors.insert_rsw(ServiceRumor::new(sys.member_id.as_str(),
+                                                    &service.pkg.ident,
+                                                    service.service_group.clone(),
+                                                    sys.as_sys_info(),
+                                                    service.cfg.to_exported(&service.pkg).ok()));
+    }
+    let member_list = MemberList::new();
+    let mut member = Member::default();
+    member.id = sys.member_id.clone();
+    member.persistent = sys.permanent;
+    member_list.insert_mlw(member, Health::Alive);
+
+    let mut census_ring = CensusRing::new(sys.member_id.clone());
+    census_ring.update_from_rumors_rsr_mlr(&cfg.key_cache,
+                                           &service_rumors,
+                                           &RumorStore::default(),
+                                           &RumorStore::default(),
+                                           &member_list,
+                                           &RumorStore::default(),
+                                           &RumorStore::default());
+    census_ring
+}
+
+/// Check the binds of a service against the services of this Supervisor, returning the number of
+/// binds they cannot satisfy. Binds to other service groups are left to the rest of the ring.
+fn check_binds(service: &Planned, census_ring: &CensusRing) -> usize {
+    let mut problems = 0;
+    for bind in &service.spec.binds {
+        let group = match census_ring.census_group_for(bind.service_group()) {
+            Some(group) => group,
+            None if service.spec.binding_mode == BindingMode::Optional
+                    && service.optional_pkg_binds.contains(bind.name()) =>
+            {
+                continue;
+            }
+            None => {
+                outputln!(preamble service.service_group,
+                          "The service group '{}' for binding '{}' is not run by this \
+                           Supervisor, and must be provided by another member of the ring",
+                          bind.service_group(),
+                          bind.name());
+                continue;
+            }
+        };
+        let required = service.all_pkg_binds
+                              .iter()
+                              .find(|b| b.service == bind.name())
+                              .map(|b| b.exports.iter().collect::<HashSet<_>>())
+                              .unwrap_or_default();
+        let exported = group.group_exports().unwrap_or_default();
+        let mut missing = required.difference(&exported).collect::<Vec<_>>();
+        if !missing.is_empty() {
+            missing.sort();
+            outputln!(preamble service.service_group,
+                      "The group '{}' cannot satisfy the `{}` bind because it does not export \
+                       the following required fields: {:?}",
+                      bind.service_group(),
+                      bind.name(),
+                      missing);
+            problems += 1;
+        }
+    }
+    problems
+}
+
+/// Render the configuration templates and hooks of a service, returning the number of templates
+/// that failed to load or render.
+fn render(service: &Planned, sys: &Sys, census_ring: &CensusRing) -> usize {
+    let ctx = RenderContext::new(&service.service_group,
+                                 sys,
+                                 &service.pkg,
+                                 &service.cfg,
+                                 census_ring,
+                                 service.spec.binds.iter());
+    let config_from = service.spec.config_from.as_ref();
+    let roots = [("configuration file", Service::config_root(&service.pkg, config_from)),
+                 ("hook", Service::hooks_root(&service.pkg, config_from))];
+
+    let mut problems = 0;
+    for (kind, root) in roots.iter() {
+        let renderer = match CfgRenderer::new(root) {
+            Ok(renderer) => renderer,
+            Err(err) => {
+                outputln!(preamble service.service_group,
+                          "Failed to load the templates in {}: {}",
+                          root.display(),
+                          err);
+                problems += 1;
+                continue;
+            }
+        };
+        for (name, rendered) in renderer.render(&ctx) {
+            match rendered {
+                Ok(_) => outputln!(preamble service.service_group, "Rendered {} {}", kind, name),
+                Err(err) => {
+                    outputln!(preamble service.service_group,
+                              "Failed to render {} {}: {}",
+                              kind,
+                              name,
+                              err);
+                    problems += 1;
+                }
+            }
+        }
+    }
+    problems
+}
@@ -0,0 +1,73 @@
+//! Makes sure the Launcher is still responsive.
+//!
+//! Every request the Supervisor sends the Launcher blocks until it is answered, so a wedged
+//! Launcher would otherwise only be noticed once a service needed to be started or stopped.
+
+use habitat_common::outputln;
+use habitat_launcher_client::{Error,
+                              LauncherCli};
+use habitat_launcher_protocol::{self as protocol,
+                                Error as ProtocolError};
+use std::time::{Duration,
+                Instant};
+
+static LOGKEY: &str = "LH";
+
+habitat_core::env_config_duration!(
+    /// How often the Supervisor sends the Launcher a heartbeat.
+    LauncherHeartbeatInterval,
+    HAB_LAUNCHER_HEARTBEAT_INTERVAL_SECS => from_secs,
+    Duration::from_secs(10));
+
+pub struct LauncherHeartbeat {
+    interval:  Duration,
+    next:      Instant,
+    /// Cleared once the Launcher turns out to be too old to know about heartbeats.
+    supported: bool,
+    /// How many heartbeats in a row went unanswered.
+    missed:    u32,
+}
+
+impl LauncherHeartbeat {
+    pub fn new() -> Self {
+        let interval = LauncherHeartbeatInterval::configured_value().into();
+        LauncherHeartbeat { interval,
+                            next: Instant::now() + interval,
+                            supported: true,
+                            missed: 0 }
+    }
+
+    /// Send a heartbeat if one is due, and report a Launcher that doesn't answer it in time.
+    pub fn check(&mut self, launcher: &LauncherCli) {
+        let now = Instant::now();
+        if !self.supported || now < self.next {
+            return;
+        }
+        self.next = now + self.interval;
+        match launcher.heartbeat() {
+            Ok(reply) => {
+                if self.missed > 0 {
+                    outputln!("The Launcher is responsive again after {} missed heartbeat(s)",
+                              self.missed);
+                }
+                self.missed = 0;
+                trace!("Launcher {} is running {} process(es)",
+                       reply.launcher_pid,
+                       reply.processes.len());
+            }
+            Err(Error::Protocol(ProtocolError::NetErr(ref err)))
+                if err.code == protocol::ErrCode::UnknownMessage =>
+            {
+                debug!("The Launcher does not support heartbeats: {}", err.msg);
+                self.supported = false;
+            }
+            Err(Error::Timeout) => {
+                self.missed += 1;
+                outputln!("The Launcher has not answered {} heartbeat(s) in a row; it may be \
+                           wedged",
+                          self.missed);
+            }
+            Err(err) => warn!("Unable to send a heartbeat to the Launcher: {}", err),
+        }
+    }
+}
@@ -5,7 +5,8 @@ use crate::{error::{Error,
 use habitat_butterfly::member::Member;
 use habitat_common::{liveliness_checker,
                      outputln,
-                     types::GossipListenAddr};
+                     types::{self,
+                             GossipListenAddr}};
 use std::{fs::File,
           io::{BufRead,
                BufReader},
@@ -32,9 +33,18 @@ impl Callbacks for PeerCallbacks {
     fn file_disappeared(&mut self, _: &Path) { self.have_events.store(true, Ordering::Relaxed) }
 }
 
+/// The peers listed in the watched file, along with those that were
+/// listed the last time it was read but no longer are.
+pub struct PeerChanges {
+    pub members: Vec<Member>,
+    pub removed: Vec<Member>,
+}
+
 pub struct PeerWatcher {
-    path:        PathBuf,
-    have_events: Arc<AtomicBool>,
+    path:         PathBuf,
+    have_events:  Arc<AtomicBool>,
+    /// The peers listed in the file the last time it was read.
+    last_members: Vec<Member>,
 }
 
 impl PeerWatcher {
@@ -44,7 +54,9 @@ impl PeerWatcher {
         let path = path.into();
         let have_events = Self::setup_watcher(path.clone())?;
 
-        Ok(PeerWatcher { path, have_events })
+        Ok(PeerWatcher { path,
+                         have_events,
+                         last_members: Vec::new() })
     }
 
     fn setup_watcher(path: PathBuf) -> Result<Arc<AtomicBool>> {
@@ -109,11 +121,7 @@ impl PeerWatcher {
         let mut members: Vec<Member> = Vec::new();
         for line in reader.lines() {
             if let Ok(peer) = line {
-                let peer_addr = if peer.find(':').is_some() {
-                    peer
-                } else {
-                    format!("{}:{}", peer, GossipListenAddr::DEFAULT_PORT)
-                };
+                let peer_addr = types::with_default_port(&peer, GossipListenAddr::DEFAULT_PORT);
                 let addrs: Vec<SocketAddr> = match peer_addr.to_socket_addrs() {
                     Ok(addrs) => addrs.collect(),
                     Err(e) => {
@@ -132,6 +140,24 @@ impl PeerWatcher {
         self.have_events.store(false, Ordering::Relaxed);
         Ok(members)
     }
+
+    /// Read the peers listed in the file, noting those that were removed
+    /// from it since it was last read.
+    pub fn get_changes(&mut self) -> Result<PeerChanges> {
+        let members = self.get_members()?;
+        let removed = self.last_members
+                          .drain(..)
+                          .filter(|old| !members.iter().any(|new| same_peer(old, new)))
+                          .collect();
+        self.last_members = members.clone();
+        Ok(PeerChanges { members, removed })
+    }
+}
+
+/// Peers are read with a new random ID each time, so they are told
+/// apart by the address they listen at.
+fn same_peer(a: &Member, b: &Member) -> bool {
+    a.address == b.address && a.swim_port == b.swim_port
 }
 
 #[cfg(test)]
@@ -191,4 +217,23 @@ mod tests {
         }
         assert_eq!(expected_members, members);
     }
+
+    #[test]
+    fn changes_include_removed_peers() {
+        let tmpdir = TempDir::new().unwrap();
+        let path = tmpdir.path().join("some_file");
+        std::fs::write(&path, "1.2.3.4:5\n4.3.2.1\n").unwrap();
+        let mut watcher = PeerWatcher::run(path.clone()).unwrap();
+
+        let changes = watcher.get_changes().unwrap();
+        assert_eq!(changes.members.len(), 2);
+        assert!(changes.removed.is_empty());
+
+        std::fs::write(&path, "4.3.2.1\n").unwrap();
+        let changes = watcher.get_changes().unwrap();
+        assert_eq!(changes.members.len(), 1);
+        assert_eq!(changes.removed.len(), 1);
+        assert_eq!(changes.removed[0].address, "1.2.3.4");
+        assert_eq!(changes.removed[0].swim_port, 5);
+    }
 }
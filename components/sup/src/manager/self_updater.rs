@@ -2,7 +2,9 @@
 //! itself.
 
 use crate::util;
-use habitat_common::command::package::install::InstallSource;
+use habitat_common::{command::package::install::InstallSource,
+                     outputln,
+                     types::UpdateWindow};
 use habitat_core::{package::{PackageIdent,
                              PackageInstall},
                    ChannelIdent};
@@ -16,6 +18,8 @@ use tokio::{self,
                             Sender},
             time as tokiotime};
 
+static LOGKEY: &str = "SUU";
+
 pub const SUP_PKG_IDENT: &str = "core/hab-sup";
 
 // TODO (DM): Remove this deprecated env var
@@ -43,6 +47,7 @@ pub struct SelfUpdater {
     update_url:     String,
     update_channel: ChannelIdent,
     period:         Duration,
+    window:         Option<UpdateWindow>,
 }
 
 /// The subset of data from `SelfUpdater` needed to spawn the updater task.
@@ -51,6 +56,7 @@ struct Runner {
     update_url:     String,
     update_channel: ChannelIdent,
     period:         Duration,
+    window:         Option<UpdateWindow>,
 }
 
 impl<T: Borrow<SelfUpdater>> From<T> for Runner {
@@ -59,7 +65,8 @@ impl<T: Borrow<SelfUpdater>> From<T> for Runner {
         Self { current:        other.current.clone(),
                update_url:     other.update_url.clone(),
                update_channel: other.update_channel.clone(),
-               period:         other.period, }
+               period:         other.period,
+               window:         other.window, }
     }
 }
 
@@ -67,18 +74,21 @@ impl SelfUpdater {
     pub fn new(current: &PackageIdent,
                update_url: String,
                update_channel: ChannelIdent,
-               period: Duration)
+               period: Duration,
+               window: Option<UpdateWindow>)
                -> Self {
         let runner = Runner { current: current.clone(),
                               update_url: update_url.clone(),
                               update_channel: update_channel.clone(),
-                              period };
+                              period,
+                              window };
         let rx = Self::init(runner);
         SelfUpdater { rx,
                       current: current.clone(),
                       update_url,
                       update_channel,
-                      period }
+                      period,
+                      window }
     }
 
     /// Spawn a new Supervisor updater task.
@@ -95,7 +105,8 @@ impl SelfUpdater {
         let Runner { current,
                      update_url,
                      update_channel,
-                     period, } = runner;
+                     period,
+                     window, } = runner;
         let period = SelfUpdatePeriod::get().unwrap_or(period);
         let splay = Duration::from_secs(rand::thread_rng().gen_range(0, period.as_secs()));
         debug!("Starting self updater with current package {} in {}s",
@@ -108,6 +119,17 @@ impl SelfUpdater {
                     if &current < package.ident() {
                         debug!("Self updater installing newer Supervisor, {}",
                                package.ident());
+                        if let Some(window) = window {
+                            let wait = window.until_open();
+                            if wait > Duration::from_secs(0) {
+                                outputln!("Supervisor update to {} will be applied during the \
+                                           update window {} UTC, in {}s",
+                                          package.ident(),
+                                          window,
+                                          wait.as_secs());
+                                tokiotime::delay_for(wait).await;
+                            }
+                        }
                         tx.send(package).expect("Main thread has gone away!");
                         break;
                     } else {
@@ -12,37 +12,47 @@ mod context;
 mod health;
 mod hook_runner;
 mod hooks;
+mod output_events;
 #[cfg(windows)]
 mod pipe_hook_client;
 pub mod spec;
 mod supervisor;
 mod terminator;
 
-use self::{context::RenderContext,
-           hook_runner::HookRunner,
-           hooks::{HookCompileTable,
-                   HookTable},
-           supervisor::Supervisor};
-pub use self::{health::{HealthCheckBundle,
+pub(super) use self::context::RenderContext;
+pub use self::{health::{HealthCheck,
+                        HealthCheckBundle,
                         HealthCheckHookStatus,
-                        HealthCheckResult},
+                        HealthCheckRecord,
+                        HealthCheckResult,
+                        HEALTH_CHECK_HISTORY_LEN},
                hooks::{HealthCheckHook,
+                       HookFailure,
                        ProcessOutput,
                        StandardStreams},
                spec::{DesiredState,
                       ServiceSpec}};
+use self::{hook_runner::HookRunner,
+           hooks::{HookCompileTable,
+                   HookTable},
+           spec::LeaderAffinity,
+           supervisor::Supervisor};
 use crate::{census::{CensusGroup,
                      CensusRing,
                      ElectionStatus,
                      ServiceFile},
             error::{Error,
                     Result},
+            event::ServiceMetadata,
             manager::{event,
                       sync::GatewayState,
                       FsCfg,
                       ServicePidSource,
                       ShutdownConfig,
-                      Sys}};
+                      Sys},
+            secrets::{self,
+                      Resolved},
+            statsd};
 use futures::future::{self,
                       AbortHandle};
 use habitat_butterfly::rumor::service::Service as ServiceRumor;
@@ -53,25 +63,37 @@ pub use habitat_common::templating::{config::{Cfg,
                                      package::{Env,
                                                Pkg,
                                                PkgProxy}};
-use habitat_common::{outputln,
+use habitat_common::{output::{OutputTargets,
+                              ServiceOutputFormat,
+                              ServiceStream},
+                     outputln,
                      templating::{config::CfgRenderer,
                                   hooks::Hook},
+                     util::{log_forward::LogForward,
+                            rotating_log::LogRotation},
                      FeatureFlag};
-#[cfg(windows)]
-use habitat_core::os::users;
 use habitat_core::{crypto::Blake2bHash,
                    fs::{atomic_write,
+                        pkg_install_path,
+                        pkg_root_path,
                         svc_hooks_path,
+                        svc_logs_path,
                         SvcDir,
                         FS_ROOT_PATH},
-                   os::process::ShutdownTimeout,
+                   os::{isolation::{AllowedPath,
+                                    Isolation,
+                                    PortMapping},
+                        process::ShutdownTimeout,
+                        users},
                    package::{metadata::Bind,
+                             Identifiable,
                              PackageIdent,
                              PackageInstall},
                    service::{ServiceBind,
                              ServiceGroup},
                    ChannelIdent};
-use habitat_launcher_client::LauncherCli;
+use habitat_launcher_client::{LauncherCli,
+                              ServiceOutput};
 use habitat_sup_protocol::types::BindingMode;
 pub use habitat_sup_protocol::types::{ProcessState,
                                       Topology,
@@ -79,12 +101,14 @@ pub use habitat_sup_protocol::types::{ProcessState,
                                       UpdateStrategy};
 use parking_lot::RwLock;
 use prometheus::{HistogramTimer,
-                 HistogramVec};
+                 HistogramVec,
+                 IntGaugeVec};
 use serde::{ser::SerializeStruct,
             Serialize,
             Serializer};
 use std::{self,
           collections::HashSet,
+          env,
           fmt,
           fs,
           ops::Deref,
@@ -93,10 +117,31 @@ use std::{self,
           result,
           sync::{Arc,
                  Mutex},
-          time::SystemTime};
+          time::{Duration,
+                 Instant,
+                 SystemTime}};
 
 static LOGKEY: &str = "SR";
 
+/// How often the resources used by a service's processes are sampled.
+const RESOURCE_USAGE_SAMPLE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The files in a service's `logs` directory that the Launcher writes the service's stdout and
+/// stderr to.
+const SERVICE_LOG_FILE_NAME: &str = "service.log";
+const SERVICE_ERR_LOG_FILE_NAME: &str = "service.err.log";
+
+/// Supervisor-wide settings for how the Launcher handles the output of services. Everything but
+/// the format can be overridden in a service's spec.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ServiceOutputConfig {
+    pub log_rotation:   LogRotation,
+    pub format:         ServiceOutputFormat,
+    pub forward:        Option<LogForward>,
+    pub stdout_targets: OutputTargets,
+    pub stderr_targets: OutputTargets,
+}
+
 #[cfg(not(windows))]
 pub const GOSSIP_FILE_PERMISSIONS: u32 = 0o640;
 
@@ -105,6 +150,18 @@ lazy_static! {
         register_histogram_vec!("hab_sup_hook_duration_seconds",
                                 "The time it takes for a hook to run",
                                 &["hook"]).unwrap();
+    static ref SERVICE_CPU_USAGE: IntGaugeVec =
+        register_int_gauge_vec!("hab_sup_service_cpu_usage_microseconds",
+                                "CPU time consumed by a service's cgroup",
+                                &["service_group"]).unwrap();
+    static ref SERVICE_MEMORY_USAGE: IntGaugeVec =
+        register_int_gauge_vec!("hab_sup_service_memory_bytes",
+                                "Memory currently used by a service's cgroup",
+                                &["service_group"]).unwrap();
+    static ref SERVICE_IO_USAGE: IntGaugeVec =
+        register_int_gauge_vec!("hab_sup_service_io_bytes",
+                                "Bytes read and written by a service's cgroup",
+                                &["service_group", "direction"]).unwrap();
 }
 
 /// When evaluating whether a particular service group can satisfy a
@@ -191,6 +248,23 @@ pub struct Service {
     // migrated away from the event loop architecture to an architecture that had a top level
     // `Service` future. See https://github.com/habitat-sh/habitat/issues/7112
     initialization_state:    Arc<RwLock<InitializationState>>,
+    /// The package release this service was updated from, until its `post-update` hook has been
+    /// started.
+    updated_from:            Option<PackageIdent>,
+    /// The most recent failure of one of the service's `init`, `reload`, `reconfigure` or
+    /// `suitability` hooks, until that hook next succeeds.
+    last_hook_failure:       Arc<RwLock<Option<HookFailure>>>,
+    /// The service's configuration with the secrets it references substituted in, if it
+    /// references any. Templates are rendered with this rather than `cfg`, which is what gets
+    /// gossiped and so must never hold secrets.
+    resolved_cfg:            Option<Cfg>,
+    /// The generation of the secrets `resolved_cfg` was resolved against.
+    secrets_generation:      u64,
+    /// When the resources used by the service's processes were last sampled.
+    resource_usage_sampled:  Option<Instant>,
+    /// Whether some of the secrets the service's configuration references have not been read
+    /// yet.
+    secrets_pending:         bool,
 
     config_renderer:      CfgRenderer,
     // Note: This field is really only needed for serializing a
@@ -219,22 +293,43 @@ pub struct Service {
     /// requisite exports, because no live members of the group exist,
     /// or because the group itself does not exist in the census.
     ///
-    /// We don't serialize because this is purely runtime information
-    /// that should be reconciled against the current state of the
-    /// census.
+    /// This is purely runtime information that should be reconciled
+    /// against the current state of the census, so while it is
+    /// reported through the HTTP gateway, it is never persisted.
     unsatisfied_binds:    HashSet<ServiceBind>,
+    /// The names of the binds the current service package declares
+    /// as optional.
+    optional_pkg_binds:   HashSet<String>,
+    /// Optional binds specified by the user that are currently mapped
+    /// to service groups with no live members, when the service uses
+    /// the optional binding mode. These are left out of the render
+    /// context, so templates fall back to the plan's defaults, but are
+    /// not considered unsatisfied.
+    absent_binds:         HashSet<ServiceBind>,
+    /// Whether the binds were pointed at other service groups since
+    /// the templates were last rendered, in which case they must be
+    /// re-resolved and rendered again even if the census did not
+    /// change.
+    binds_updated:        bool,
     hooks:                HookTable,
     manager_fs_cfg:       Arc<FsCfg>,
     supervisor:           Arc<Mutex<Supervisor>>,
+    /// The Supervisor-wide output settings, which the service's spec
+    /// may partially override.
+    output_config:        ServiceOutputConfig,
 
     gateway_state: Arc<GatewayState>,
 
     /// A "handle" to the never-ending future that periodically runs
     /// health checks on this service. This is the means by which we
     /// can stop that future.
-    health_check_handle: Option<AbortHandle>,
-    post_run_handle:     Option<AbortHandle>,
-    initialize_handle:   Option<AbortHandle>,
+    health_check_handle:   Option<AbortHandle>,
+    post_run_handle:       Option<AbortHandle>,
+    post_update_handle:    Option<AbortHandle>,
+    initialize_handle:     Option<AbortHandle>,
+    /// Handles to the futures that publish the service's output to
+    /// the event stream.
+    output_events_handles: Vec<AbortHandle>,
 }
 
 impl Service {
@@ -259,6 +354,25 @@ impl Service {
         self.spec = spec
     }
 
+    /// Swap in a spec whose binds point at different service groups.
+    ///
+    /// The binds are re-resolved against the census on the next tick,
+    /// re-rendering the templates so that the service is reconfigured,
+    /// or restarted if it has no `reconfigure` hook.
+    pub(crate) fn rebind(&mut self, spec: ServiceSpec) {
+        let binds = spec.binds.iter().collect::<HashSet<_>>();
+        self.unsatisfied_binds.retain(|b| binds.contains(b));
+        self.absent_binds.retain(|b| binds.contains(b));
+        for bind in spec.binds.iter().filter(|b| !self.spec.binds.contains(b)) {
+            outputln!(preamble self.service_group,
+                      "Rebinding `{}` to the group '{}'",
+                      bind.name(),
+                      bind.service_group());
+        }
+        self.set_spec(spec);
+        self.binds_updated = true;
+    }
+
     #[allow(clippy::too_many_arguments)]
     async fn with_package(sys: Arc<Sys>,
                           package: &PackageInstall,
@@ -267,10 +381,15 @@ impl Service {
                           organization: Option<&str>,
                           gateway_state: Arc<GatewayState>,
                           pid_source: ServicePidSource,
-                          feature_flags: FeatureFlag)
+                          feature_flags: FeatureFlag,
+                          output_config: ServiceOutputConfig)
                           -> Result<Service> {
         spec.validate(&package)?;
         let all_pkg_binds = package.all_binds()?;
+        let optional_pkg_binds = package.binds_optional()?
+                                        .into_iter()
+                                        .map(|b| b.service)
+                                        .collect();
         let pkg = Self::resolve_pkg(&package, &spec).await?;
         let spec_file = manager_fs_cfg.specs_path.join(spec.file());
         let service_group = ServiceGroup::new(&pkg.name, &spec.group, organization)?;
@@ -291,6 +410,12 @@ impl Service {
                      needs_restart: false,
                      initialization_state:
                          Arc::new(RwLock::new(InitializationState::Uninitialized)),
+                     updated_from: None,
+                     last_hook_failure: Arc::new(RwLock::new(None)),
+                     resolved_cfg: None,
+                     secrets_generation: 0,
+                     secrets_pending: false,
+                     resource_usage_sampled: None,
                      manager_fs_cfg,
                      supervisor: Arc::new(Mutex::new(Supervisor::new(&service_group,
                                                                      pid_source))),
@@ -298,10 +423,16 @@ impl Service {
                      service_group,
                      all_pkg_binds,
                      unsatisfied_binds: HashSet::new(),
+                     optional_pkg_binds,
+                     absent_binds: HashSet::new(),
+                     binds_updated: false,
                      spec_file,
                      gateway_state,
+                     output_config,
                      health_check_handle: None,
+                     output_events_handles: Vec::new(),
                      post_run_handle: None,
+                     post_update_handle: None,
                      initialize_handle: None })
     }
 
@@ -320,7 +451,9 @@ impl Service {
     #[cfg(windows)]
     async fn resolve_pkg(package: &PackageInstall, spec: &ServiceSpec) -> Result<Pkg> {
         let mut pkg = Pkg::from_install(&package).await?;
-        if spec.svc_encrypted_password.is_none() && pkg.svc_user == DEFAULT_USER {
+        if let Some(ref user) = spec.svc_user {
+            pkg.svc_user = user.clone();
+        } else if spec.svc_encrypted_password.is_none() && pkg.svc_user == DEFAULT_USER {
             if let Some(user) = users::get_current_username()? {
                 pkg.svc_user = user;
             }
@@ -328,32 +461,52 @@ impl Service {
         Ok(pkg)
     }
 
+    /// The user and group given when loading the service take
+    /// precedence over those of the package. When running as root, they
+    /// are created as system accounts if they don't exist yet, so that
+    /// each service can run under its own account.
     #[cfg(unix)]
-    async fn resolve_pkg(package: &PackageInstall, _spec: &ServiceSpec) -> Result<Pkg> {
-        Ok(Pkg::from_install(&package).await?)
+    async fn resolve_pkg(package: &PackageInstall, spec: &ServiceSpec) -> Result<Pkg> {
+        let mut pkg = Pkg::from_install(&package).await?;
+        if spec.svc_user.is_none() && spec.svc_group.is_none() {
+            return Ok(pkg);
+        }
+        if let Some(ref user) = spec.svc_user {
+            pkg.svc_user = user.clone();
+        }
+        if let Some(ref group) = spec.svc_group {
+            pkg.svc_group = group.clone();
+        }
+        if users::get_effective_uid() == 0 {
+            users::ensure_system_group(&pkg.svc_group)?;
+            users::ensure_system_user(&pkg.svc_user, &pkg.svc_group)?;
+        }
+        Ok(pkg)
     }
 
     /// Returns the config root given the package and optional config-from path.
-    fn config_root(package: &Pkg, config_from: Option<&PathBuf>) -> PathBuf {
+    pub(super) fn config_root(package: &Pkg, config_from: Option<&PathBuf>) -> PathBuf {
         config_from.map(PathBuf::as_path)
                    .unwrap_or(&package.path)
                    .join("config")
     }
 
     /// Returns the hooks root given the package and optional config-from path.
-    fn hooks_root(package: &Pkg, config_from: Option<&PathBuf>) -> PathBuf {
+    pub(super) fn hooks_root(package: &Pkg, config_from: Option<&PathBuf>) -> PathBuf {
         config_from.map(PathBuf::as_path)
                    .unwrap_or(&package.path)
                    .join("hooks")
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub async fn new(sys: Arc<Sys>,
                      spec: ServiceSpec,
                      manager_fs_cfg: Arc<FsCfg>,
                      organization: Option<&str>,
                      gateway_state: Arc<GatewayState>,
                      pid_source: ServicePidSource,
-                     feature_flags: FeatureFlag)
+                     feature_flags: FeatureFlag,
+                     output_config: ServiceOutputConfig)
                      -> Result<Service> {
         // The package for a spec should already be installed.
         let fs_root_path = Path::new(&*FS_ROOT_PATH);
@@ -365,7 +518,8 @@ impl Service {
                               organization,
                               gateway_state,
                               pid_source,
-                              feature_flags).await?)
+                              feature_flags,
+                              output_config).await?)
     }
 
     /// Create the service path for this package.
@@ -377,17 +531,42 @@ impl Service {
 
     fn start(&mut self, launcher: &LauncherCli) {
         debug!("Starting service {}", self.pkg.ident);
+        let logs_path = svc_logs_path(&self.pkg.name);
+        let stdout_log = logs_path.join(SERVICE_LOG_FILE_NAME);
+        let stderr_log = logs_path.join(SERVICE_ERR_LOG_FILE_NAME);
+        let log_forward = self.spec.log_forward.clone().or_else(|| {
+                                                           self.output_config
+                                                               .forward
+                                                               .as_ref()
+                                                               .map(LogForward::to_string)
+                                                       });
+        let pkg_ident = self.pkg.ident.to_string();
+        let output = ServiceOutput { pkg_ident:      &pkg_ident,
+                                     format:         self.output_config.format,
+                                     stdout_log:     Some(&stdout_log),
+                                     stderr_log:     Some(&stderr_log),
+                                     rotation:
+                                         self.spec.log_rotation(self.output_config.log_rotation),
+                                     forward:        log_forward.as_deref(),
+                                     stdout_targets: self.output_targets(ServiceStream::Stdout),
+                                     stderr_targets: self.output_targets(ServiceStream::Stderr), };
         let result = self.supervisor
                          .lock()
                          .expect("Couldn't lock supervisor")
                          .start(&self.pkg,
                                 &self.service_group,
                                 launcher,
-                                self.spec.svc_encrypted_password.as_deref());
+                                self.spec.svc_encrypted_password.as_deref(),
+                                &self.spec.env_vars,
+                                &self.spec.limits(),
+                                &self.isolation(),
+                                &self.spec.process_settings(),
+                                &output);
         match result {
             Ok(_) => {
                 self.needs_restart = false;
                 self.start_health_checks();
+                self.restart_output_events();
             }
             Err(e) => {
                 outputln!(preamble self.service_group, "Service start failed: {}", e);
@@ -395,6 +574,75 @@ impl Service {
         }
     }
 
+    /// How the service's process is isolated, falling back to the package's seccomp profile when
+    /// the service's spec doesn't set one. A filesystem sandbox always allows what the service
+    /// needs to run.
+    fn isolation(&self) -> Isolation {
+        let mut isolation = self.spec.isolation();
+        if isolation.seccomp_profile.is_none() {
+            isolation.seccomp_profile = self.pkg.seccomp_profile.clone();
+        }
+        if isolation.filesystem_sandbox {
+            let mut allowed_paths = self.sandbox_paths();
+            allowed_paths.append(&mut isolation.allowed_paths);
+            isolation.allowed_paths = allowed_paths;
+        }
+        isolation
+    }
+
+    /// The paths of the host that the service needs in a filesystem sandbox: its package, the
+    /// packages it depends on and those on its `PATH`, read-only, and its service directory.
+    fn sandbox_paths(&self) -> Vec<AllowedPath> {
+        let pkg_root = pkg_root_path(Some(&*FS_ROOT_PATH));
+        let mut paths = vec![AllowedPath::read_only(&self.pkg.path)];
+        for dep in self.pkg.deps.iter().filter(|dep| dep.fully_qualified()) {
+            paths.push(AllowedPath::read_only(pkg_install_path(dep, Some(&*FS_ROOT_PATH))));
+        }
+        if let Some(path) = self.pkg.env.get("PATH") {
+            paths.extend(env::split_paths(path).filter(|path| path.starts_with(&pkg_root))
+                                               .map(AllowedPath::read_only));
+        }
+        paths.push(AllowedPath::writable(&self.pkg.svc_path));
+        paths
+    }
+
+    /// Where one of the service's output streams goes, preferring the service's spec to the
+    /// Supervisor-wide setting.
+    fn output_targets(&self, stream: ServiceStream) -> OutputTargets {
+        let (spec_targets, default) = match stream {
+            ServiceStream::Stdout => (&self.spec.stdout_to, self.output_config.stdout_targets),
+            ServiceStream::Stderr => (&self.spec.stderr_to, self.output_config.stderr_targets),
+        };
+        spec_targets.as_ref()
+                    .and_then(|targets| targets.parse().ok())
+                    .unwrap_or(default)
+    }
+
+    /// Start publishing each of the service's output streams that is directed to the event
+    /// stream, stopping any previous publishers first.
+    fn restart_output_events(&mut self) {
+        self.stop_output_events();
+        for &(stream, file_name) in &[(ServiceStream::Stdout, SERVICE_LOG_FILE_NAME),
+                                      (ServiceStream::Stderr, SERVICE_ERR_LOG_FILE_NAME)]
+        {
+            if self.output_targets(stream).contains(OutputTargets::EVENTS) {
+                debug!("Publishing {} of {} to the event stream",
+                       stream, self.pkg.ident);
+                let path = svc_logs_path(&self.pkg.name).join(file_name);
+                let f = output_events::publish(path, stream, self.to_service_metadata());
+                let (f, handle) = future::abortable(f);
+                self.output_events_handles.push(handle);
+                tokio::spawn(f);
+            }
+        }
+    }
+
+    fn stop_output_events(&mut self) {
+        for handle in self.output_events_handles.drain(..) {
+            handle.abort();
+        }
+    }
+
     fn initialized(&self) -> bool {
         *self.initialization_state.read() == InitializationState::Initialized
     }
@@ -405,14 +653,19 @@ impl Service {
     /// * Cache the health check result for this service
     /// * Set the health check result for this service in the gateway state
     /// * Send a `HealthCheckEvent` over the event stream
+    /// * Send the result, and whether it changed, to StatsD
     fn start_health_checks(&mut self) {
         debug!("Starting health checks for {}", self.pkg.ident);
-        let mut rx = health::check_repeatedly(Arc::clone(&self.supervisor),
-                                              self.hooks.health_check.clone(),
-                                              self.spec.health_check_interval,
-                                              self.service_group.clone(),
-                                              self.pkg.clone(),
-                                              self.spec.svc_encrypted_password.clone());
+        let mut rx =
+            health::check_repeatedly(Arc::clone(&self.supervisor),
+                                     self.spec.health_check.clone(),
+                                     self.hooks.health_check.clone(),
+                                     self.spec.health_check_interval,
+                                     self.service_group.clone(),
+                                     self.pkg.clone(),
+                                     self.spec.svc_encrypted_password.clone(),
+                                     self.spec.health_check_timeout.map(Duration::from_secs),
+                                     self.spec.health_check_max_backoff.map(Duration::from_secs));
 
         let service_group = self.service_group.clone();
         let service_event_metadata = self.to_service_metadata();
@@ -428,13 +681,22 @@ impl Service {
             {
                 debug!("Caching HealthCheckResult = '{}' for '{}'",
                        result, service_group);
-                *service_health_result.lock()
-                                      .expect("Could not unlock service_health_result") = result;
-
-                gateway_state.lock_gsw()
-                             .set_health_of(service_group.clone(), result);
+                let previous = {
+                    let mut cached =
+                        service_health_result.lock()
+                                             .expect("Could not unlock service_health_result");
+                    std::mem::replace(&mut *cached, result)
+                };
+
+                let record = HealthCheckRecord::new(&status, result);
+                {
+                    let mut gsw = gateway_state.lock_gsw();
+                    gsw.set_health_of(service_group.clone(), result);
+                    gsw.record_health_of(service_group.clone(), record);
+                }
 
                 event::health_check(service_event_metadata.clone(), result, status, interval);
+                statsd::health_check(&service_group, previous, result);
             }
         };
         let (f, handle) = future::abortable(f);
@@ -471,6 +733,7 @@ impl Service {
         outputln!("Reattaching to {}", self.service_group);
         *self.initialization_state.write() = InitializationState::Initialized;
         self.restart_health_checks();
+        self.restart_output_events();
         // We intentionally do not restart the `post_run` retry future. Currently, there is not
         // a way to track if `post_run` ran successfully following a Supervisor restart.
         // See https://github.com/habitat-sh/habitat/issues/6739
@@ -491,7 +754,9 @@ impl Service {
         debug!("Detatching service {}", self.pkg.ident);
         self.stop_initialize();
         self.stop_post_run();
+        self.stop_post_update();
         self.stop_health_checks();
+        self.stop_output_events();
     }
 
     /// Return a future that will shut down a service, performing any
@@ -501,6 +766,7 @@ impl Service {
     pub async fn stop_gsw(&mut self, shutdown_config: ShutdownConfig) {
         debug!("Stopping service {}", self.pkg.ident);
         self.detach();
+        secrets::release(&self.service_group);
 
         let service_group = self.service_group.clone();
         let gs = Arc::clone(&self.gateway_state);
@@ -531,12 +797,15 @@ impl Service {
     ///
     /// Returns `true` if the service was marked to be restarted or reconfigured.
     pub fn tick(&mut self, census_ring: &CensusRing, launcher: &LauncherCli) -> bool {
+        self.record_resource_usage();
+        self.sample_resource_usage();
+
         // We may need to block the service from starting until all
         // its binds are satisfied
         if !self.initialized() {
             match self.spec.binding_mode {
                 BindingMode::Relaxed => (),
-                BindingMode::Strict => {
+                BindingMode::Strict | BindingMode::Optional => {
                     self.validate_binds(census_ring);
                     if !self.unsatisfied_binds.is_empty() {
                         outputln!(preamble self.service_group, "Waiting for service binds...");
@@ -549,7 +818,7 @@ impl Service {
         // Binds may become unsatisfied as a service is running (e.g.,
         // service members disappear, etc.) This can affect the data
         // we pass to templates, so we must account for it here.
-        if census_ring.changed() {
+        if census_ring.changed() || self.binds_updated {
             self.validate_binds(census_ring);
         }
 
@@ -557,6 +826,9 @@ impl Service {
         // not account for changes in the census ring. This is needed because when we restart a
         // service, we do not correctly produce the initial gossip message.
         let (template_data_changed, template_update) = self.update_templates(census_ring);
+        if self.secrets_pending && !self.initialized() {
+            return template_data_changed;
+        }
         if self.update_service_files(census_ring) {
             self.file_updated();
         }
@@ -623,8 +895,13 @@ impl Service {
     fn validate_binds(&mut self, census_ring: &CensusRing) {
         for bind in self.spec.binds.iter() {
             let mut bind_is_unsatisfied = true;
+            let mut bind_is_absent = false;
+            let bind_may_be_absent = self.bind_may_be_absent(bind);
 
             match self.current_bind_status(census_ring, bind) {
+                BindStatus::NotPresent | BindStatus::Empty if bind_may_be_absent => {
+                    bind_is_absent = true;
+                }
                 BindStatus::NotPresent => {
                     outputln!(preamble self.service_group,
                                   "The specified service group '{}' for binding '{}' is not (yet?) present \
@@ -665,10 +942,23 @@ impl Service {
                 }
             };
 
+            if bind_is_absent {
+                self.unsatisfied_binds.remove(bind);
+                if self.absent_binds.insert(bind.clone()) {
+                    outputln!(preamble self.service_group,
+                              "The group '{}' for the optional `{}` bind has no active members; \
+                               falling back to the plan's defaults",
+                              bind.service_group(),
+                              bind.name());
+                }
+                continue;
+            }
+
+            let bind_was_absent = self.absent_binds.remove(bind);
             if bind_is_unsatisfied {
                 // TODO (CM): use Entry API to clone only when necessary
                 self.unsatisfied_binds.insert((bind).clone())
-            } else if self.unsatisfied_binds.remove(bind) {
+            } else if self.unsatisfied_binds.remove(bind) || bind_was_absent {
                 // We'll log if the bind was previously
                 // unsatisfied, but now it is satisfied.
                 outputln!(preamble self.service_group,
@@ -682,6 +972,14 @@ impl Service {
         }
     }
 
+    /// Whether `bind` may go without live members without being
+    /// unsatisfied, which is the case for the package's optional binds
+    /// when the service uses the optional binding mode.
+    fn bind_may_be_absent(&self, bind: &ServiceBind) -> bool {
+        self.spec.binding_mode == BindingMode::Optional
+        && self.optional_pkg_binds.contains(bind.name())
+    }
+
     /// Evaluate the suitability of the given `ServiceBind` based on
     /// current census information.
     fn current_bind_status<'a>(&'a self,
@@ -748,6 +1046,55 @@ impl Service {
             .map(|b| b.exports.iter().collect())
     }
 
+    /// Export the resource usage of the service's cgroup, if any, as
+    /// metrics.
+    fn record_resource_usage(&self) {
+        let usage = match self.supervisor
+                              .lock()
+                              .expect("Couldn't lock supervisor")
+                              .resource_usage()
+        {
+            Some(usage) => usage,
+            None => return,
+        };
+        let labels = [self.service_group.as_ref()];
+        SERVICE_CPU_USAGE.with_label_values(&labels)
+                         .set(usage.cpu_usage_usec as i64);
+        if let Some(bytes) = usage.memory_current_bytes {
+            SERVICE_MEMORY_USAGE.with_label_values(&labels)
+                                .set(bytes as i64);
+        }
+        if let Some(bytes) = usage.io_read_bytes {
+            SERVICE_IO_USAGE.with_label_values(&[labels[0], "read"])
+                            .set(bytes as i64);
+        }
+        if let Some(bytes) = usage.io_write_bytes {
+            SERVICE_IO_USAGE.with_label_values(&[labels[0], "write"])
+                            .set(bytes as i64);
+        }
+    }
+
+    /// Sample the resources used by the service's processes, at most once every
+    /// `RESOURCE_USAGE_SAMPLE_INTERVAL`, and send them to the event stream.
+    fn sample_resource_usage(&mut self) {
+        if self.resource_usage_sampled
+               .map_or(false, |at| at.elapsed() < RESOURCE_USAGE_SAMPLE_INTERVAL)
+        {
+            return;
+        }
+        self.resource_usage_sampled = Some(Instant::now());
+        let usage = self.supervisor
+                        .lock()
+                        .expect("Couldn't lock supervisor")
+                        .sample_usage();
+        if let Some(usage) = usage {
+            event::resource_usage(self.to_service_metadata(), &usage);
+        }
+    }
+
+    /// When the resources used by the service's processes were last sampled.
+    pub fn resource_usage_sampled(&self) -> Option<Instant> { self.resource_usage_sampled }
+
     /// Updates the process state of the service's supervisor
     fn check_process(&mut self, launcher: &LauncherCli) -> bool {
         self.supervisor
@@ -791,11 +1138,36 @@ impl Service {
             self.user_config_updated = false;
         }
 
-        let template_update = if template_data_changed || census_ring.changed() {
-            let ctx = self.render_context(census_ring);
-            TemplateUpdate::new(self.compile_hooks(&ctx),
-                                self.compile_configuration(&ctx),
-                                self.hooks.reconfigure.is_some() || self.hooks.reload.is_some())
+        // Secrets are re-read in the background, so any change to them since the templates were
+        // last rendered calls for rendering them again.
+        let secrets_generation = secrets::generation();
+        let secrets_changed = secrets_generation != self.secrets_generation;
+
+        let template_update = if template_data_changed
+                                 || census_ring.changed()
+                                 || secrets_changed
+                                 || self.binds_updated
+        {
+            self.binds_updated = false;
+            self.secrets_generation = secrets_generation;
+            let was_pending = self.secrets_pending;
+            self.secrets_pending = false;
+            match secrets::resolve(&self.service_group, &self.cfg) {
+                Resolved::NoReferences => self.resolved_cfg = None,
+                Resolved::Cfg(cfg) => self.resolved_cfg = Some(cfg),
+                Resolved::Pending => self.secrets_pending = true,
+            }
+            if self.secrets_pending {
+                if !was_pending {
+                    outputln!(preamble self.service_group, "Waiting for secrets...");
+                }
+                TemplateUpdate::default()
+            } else {
+                let ctx = self.render_context(census_ring);
+                TemplateUpdate::new(self.compile_hooks(&ctx),
+                                    self.compile_configuration(&ctx),
+                                    self.hooks.reconfigure.is_some() || self.hooks.reload.is_some())
+            }
         } else {
             TemplateUpdate::default()
         };
@@ -804,7 +1176,10 @@ impl Service {
 
     pub fn to_rumor(&self, incarnation: u64) -> ServiceRumor {
         let exported = match self.cfg.to_exported(&self.pkg) {
-            Ok(exported) => Some(exported),
+            Ok(mut exported) => {
+                advertise_host_ports(&mut exported, &self.spec.port_mappings());
+                Some(exported)
+            }
             Err(err) => {
                 outputln!(preamble self.service_group,
                           "Failed to generate exported cfg for service rumor: {}",
@@ -831,23 +1206,37 @@ impl Service {
                                               self.pkg.clone(),
                                               self.spec.svc_encrypted_password.clone());
             // These clones are unfortunate. async/await will make this much better.
+            let hook = Arc::clone(&hook);
             let service_group = self.service_group.clone();
+            let service_event_metadata = self.to_service_metadata();
+            let last_hook_failure = Arc::clone(&self.last_hook_failure);
             let initialization_state = Arc::clone(&self.initialization_state);
             let initialization_state_for_err = Arc::clone(&self.initialization_state);
             let f = async move {
-                match hook_runner.into_future().await {
+                let failure = match hook_runner.into_future().await {
                     Ok((exit_value, _)) => {
                         *initialization_state.write() = if exit_value {
                             InitializationState::InitializerFinished
                         } else {
                             InitializationState::Uninitialized
                         };
+                        if exit_value {
+                            None
+                        } else {
+                            Some(HookFailure::new(&*hook, None))
+                        }
                     }
                     Err(e) => {
                         outputln!(preamble service_group, "Service initialization failed: {}", e);
                         *initialization_state_for_err.write() = InitializationState::Uninitialized;
+                        Some(HookFailure::from_error(&*hook, e))
                     }
-                }
+                };
+                record_hook_outcome(&last_hook_failure,
+                                    &service_group,
+                                    service_event_metadata,
+                                    hooks::InitHook::FILE_NAME,
+                                    failure);
             };
             let (f, handle) = future::abortable(f);
             self.initialize_handle = Some(handle);
@@ -868,17 +1257,19 @@ impl Service {
         let _timer = hook_timer("reconfigure");
 
         if let Some(ref hook) = self.hooks.reload {
-            hook.run(&self.service_group,
-                     &self.pkg,
-                     self.spec.svc_encrypted_password.as_ref())
-                .ok();
+            let result = hook.run(&self.service_group,
+                                  &self.pkg,
+                                  self.spec.svc_encrypted_password.as_ref());
+            self.record_hook_outcome(hooks::ReloadHook::FILE_NAME,
+                                     HookFailure::from_exit_code(&**hook, result));
         }
 
         if let Some(ref hook) = self.hooks.reconfigure {
-            hook.run(&self.service_group,
-                     &self.pkg,
-                     self.spec.svc_encrypted_password.as_ref())
-                .ok();
+            let result = hook.run(&self.service_group,
+                                  &self.pkg,
+                                  self.spec.svc_encrypted_password.as_ref());
+            self.record_hook_outcome(hooks::ReconfigureHook::FILE_NAME,
+                                     HookFailure::from_exit_code(&**hook, result));
             // The intention here is to do a health check soon after a service's configuration
             // changes, as a way to (among other things) detect potential impacts when bound
             // services change exported configuration.
@@ -907,6 +1298,43 @@ impl Service {
         }
     }
 
+    /// Record that the service is started with a new package release by the service updater, so
+    /// its `post-update` hook is run once it is up.
+    pub(crate) fn set_updated_from(&mut self, previous: PackageIdent) {
+        self.updated_from = Some(previous);
+    }
+
+    fn post_update(&mut self) {
+        let previous = match self.updated_from.take() {
+            Some(previous) => previous,
+            None => return,
+        };
+        if let Some(ref hook) = self.hooks.post_update {
+            outputln!(preamble self.service_group,
+                      "Running post-update hook after updating from {}", previous);
+            let hook_runner = HookRunner::new(Arc::clone(&hook),
+                                              self.service_group.clone(),
+                                              self.pkg.clone(),
+                                              self.spec.svc_encrypted_password.clone());
+            let service_group = self.service_group.clone();
+            let f = async move {
+                if let Err(e) = hook_runner.into_future().await {
+                    outputln!(preamble service_group, "Service update failed: {}", e);
+                }
+            };
+            let (f, handle) = future::abortable(f);
+            self.post_update_handle = Some(handle);
+            tokio::spawn(f);
+        }
+    }
+
+    /// Stop the `post-update` hook future, if it is still running.
+    fn stop_post_update(&mut self) {
+        if let Some(h) = self.post_update_handle.take() {
+            h.abort();
+        }
+    }
+
     fn post_stop(&self) -> Option<HookRunner<hooks::PostStopHook>> {
         self.hooks.post_stop.as_ref().map(|hook| {
                                          HookRunner::new(Arc::clone(&hook),
@@ -923,16 +1351,45 @@ impl Service {
             return None;
         }
 
-        self.hooks
-            .suitability
-            .as_ref()
-            .and_then(|hook| {
-                hook.run(&self.service_group,
-                         &self.pkg,
-                         self.spec.svc_encrypted_password.as_ref())
-                    .ok()
-            })
-            .unwrap_or(None)
+        let hook = self.hooks.suitability.as_ref()?;
+        let (suitability, failure) = match hook.run(&self.service_group,
+                                                    &self.pkg,
+                                                    self.spec.svc_encrypted_password.as_ref())
+        {
+            Ok(Some(suitability)) => (Some(suitability), None),
+            Ok(None) => (None, Some(HookFailure::new(&**hook, None))),
+            Err(e) => (None, Some(HookFailure::from_error(&**hook, e))),
+        };
+        self.record_hook_outcome(hooks::SuitabilityHook::FILE_NAME, failure);
+        suitability
+    }
+
+    /// This member's suitability to lead the service group: the result of the suitability hook,
+    /// adjusted by the leader affinity of the service. Preferred members always outrank other
+    /// members and excluded members are outranked by all others.
+    pub fn leader_suitability(&self) -> u64 {
+        match self.spec
+                  .leader_affinity(&self.sys.member_id, &self.sys.tags)
+        {
+            None => self.suitability().unwrap_or_else(u64::min_value),
+            Some(LeaderAffinity::Preferred) => u64::max_value(),
+            Some(LeaderAffinity::Neutral) => {
+                self.suitability()
+                    .unwrap_or_else(u64::min_value)
+                    .saturating_add(1)
+                    .min(u64::max_value() - 1)
+            }
+            Some(LeaderAffinity::Excluded) => u64::min_value(),
+        }
+    }
+
+    /// Keep the outcome of an invocation of the hook named `hook`. See `record_hook_outcome`.
+    fn record_hook_outcome(&self, hook: &str, failure: Option<HookFailure>) {
+        record_hook_outcome(&self.last_hook_failure,
+                            &self.service_group,
+                            self.to_service_metadata(),
+                            hook,
+                            failure);
     }
 
     /// Helper for compiling configuration templates into configuration files.
@@ -1031,6 +1488,7 @@ impl Service {
             InitializationState::InitializerFinished => {
                 self.start(launcher);
                 self.post_run();
+                self.post_update();
                 *self.initialization_state.write() = InitializationState::Initialized;
             }
             InitializationState::Initialized => {
@@ -1126,18 +1584,22 @@ impl Service {
 
     /// Helper for constructing a new render context for the service.
     fn render_context<'a>(&'a self, census: &'a CensusRing) -> RenderContext<'a> {
-        // Unsatisfied binds are filtered out; you only get bind
-        // information in the render context if they actually satisfy
-        // the contract!
+        // Unsatisfied and absent binds are filtered out; you only get
+        // bind information in the render context if they actually
+        // satisfy the contract!
+        let binds =
+            self.spec
+                .binds
+                .iter()
+                .filter(move |b| {
+                    !self.unsatisfied_binds.contains(b) && !self.absent_binds.contains(b)
+                });
         RenderContext::new(&self.service_group,
                            &self.sys,
                            &self.pkg,
-                           &self.cfg,
+                           self.resolved_cfg.as_ref().unwrap_or(&self.cfg),
                            census,
-                           self.spec
-                               .binds
-                               .iter()
-                               .filter(|b| !self.unsatisfied_binds.contains(b)))
+                           binds)
     }
 
     // Returns `false` if the write fails.
@@ -1228,6 +1690,29 @@ fn hook_timer(name: &str) -> HistogramTimer {
     HOOK_DURATION.with_label_values(&[name]).start_timer()
 }
 
+/// Keep the outcome of an invocation of the hook named `hook`. A failure replaces the service's
+/// last hook failure and is sent to the event stream and StatsD, while a success clears an
+/// earlier failure of the same hook.
+fn record_hook_outcome(last_hook_failure: &RwLock<Option<HookFailure>>,
+                       service_group: &ServiceGroup,
+                       service_event_metadata: ServiceMetadata,
+                       hook: &str,
+                       failure: Option<HookFailure>) {
+    let mut last_hook_failure = last_hook_failure.write();
+    match failure {
+        Some(failure) => {
+            event::hook_failed(service_event_metadata, &failure);
+            statsd::hook_failed(service_group, &failure.hook);
+            *last_hook_failure = Some(failure);
+        }
+        None => {
+            if last_hook_failure.as_ref().map_or(false, |f| f.hook == hook) {
+                *last_hook_failure = None;
+            }
+        }
+    }
+}
+
 /// This enum represents whether or not we want to render config information when we serialize this
 /// service via the ServiceProxy struct below. Choosing ConfigRendering::Full will render the
 /// config, and choosing ConfigRendering::Redacted will not render it. This matches up to the
@@ -1264,13 +1749,14 @@ impl<'a> Serialize for ServiceProxy<'a> {
         where S: Serializer
     {
         let num_fields: usize = if self.config_rendering == ConfigRendering::Full {
-            27
+            30
         } else {
-            26
+            29
         };
 
         let s = &self.service;
         let mut strukt = serializer.serialize_struct("service", num_fields)?;
+        strukt.serialize_field("absent_binds", &s.absent_binds)?;
         strukt.serialize_field("all_pkg_binds", &s.all_pkg_binds)?;
         strukt.serialize_field("binding_mode", &s.spec.binding_mode)?;
         strukt.serialize_field("binds", &s.spec.binds)?;
@@ -1287,6 +1773,7 @@ impl<'a> Serialize for ServiceProxy<'a> {
         strukt.serialize_field("hooks", &s.hooks)?;
         strukt.serialize_field("initialized", &s.initialized())?;
         strukt.serialize_field("last_election_status", &s.last_election_status)?;
+        strukt.serialize_field("last_hook_failure", s.last_hook_failure.read().deref())?;
         strukt.serialize_field("manager_fs_cfg", &s.manager_fs_cfg)?;
 
         let pkg_proxy = PkgProxy::new(&s.pkg);
@@ -1306,6 +1793,7 @@ impl<'a> Serialize for ServiceProxy<'a> {
         strukt.serialize_field("health_check_interval", &s.spec.health_check_interval)?;
         strukt.serialize_field("sys", &s.sys)?;
         strukt.serialize_field("topology", &s.spec.topology)?;
+        strukt.serialize_field("unsatisfied_binds", &s.unsatisfied_binds)?;
         strukt.serialize_field("update_strategy", &s.spec.update_strategy)?;
         strukt.serialize_field("update_condition", &s.spec.update_condition)?;
         strukt.serialize_field("user_config_updated", &s.user_config_updated)?;
@@ -1313,6 +1801,44 @@ impl<'a> Serialize for ServiceProxy<'a> {
     }
 }
 
+/// Replace the exported ports a service listens on in its network namespace by the host ports
+/// forwarded to them, which is where other members can reach the service. Only the values of
+/// keys named like ports (`port`, `ssl_port`, `admin-port`, ...) are replaced, in nested tables
+/// too, so that an unrelated value which happens to equal a mapped port is left alone.
+fn advertise_host_ports(exported: &mut toml::value::Table, ports: &[PortMapping]) {
+    for (key, value) in exported.iter_mut() {
+        match value {
+            toml::Value::Table(table) => advertise_host_ports(table, ports),
+            _ if is_port_key(key) => advertise_host_port(value, ports),
+            _ => {}
+        }
+    }
+}
+
+fn is_port_key(key: &str) -> bool {
+    let key = key.to_lowercase();
+    key == "port" || key.ends_with("_port") || key.ends_with("-port")
+}
+
+fn advertise_host_port(value: &mut toml::Value, ports: &[PortMapping]) {
+    let port = match value {
+        toml::Value::Integer(port) => Some(*port),
+        toml::Value::String(port) => port.parse().ok(),
+        _ => None,
+    };
+    let mapping = match port.and_then(|port| {
+                                ports.iter()
+                                     .find(|mapping| i64::from(mapping.service) == port)
+                            }) {
+        Some(mapping) => mapping,
+        None => return,
+    };
+    *value = match value {
+        toml::Value::String(_) => toml::Value::String(mapping.host.to_string()),
+        _ => toml::Value::Integer(i64::from(mapping.host)),
+    };
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1362,9 +1888,10 @@ mod tests {
                               Some("haha"),
                               gs,
                               ServicePidSource::Launcher,
-                              FeatureFlag::empty()).await
-                                                   .expect("I wanted a service to load, but it \
-                                                            didn't")
+                              FeatureFlag::empty(),
+                              ServiceOutputConfig::default()).await
+                                                             .expect("I wanted a service to load, \
+                                                                      but it didn't")
     }
 
     #[tokio::test]
@@ -1389,4 +1916,45 @@ mod tests {
                                                                    JSON but failed");
         assert_valid(&json_without_config, "http_gateway_services_schema.json");
     }
+
+    #[test]
+    fn mapped_ports_are_advertised_as_host_ports() {
+        let mut exported = toml::value::Table::new();
+        exported.insert("port".to_string(), toml::Value::Integer(80));
+        exported.insert("ssl_port".to_string(),
+                        toml::Value::String("443".to_string()));
+        exported.insert("admin_port".to_string(), toml::Value::Integer(9000));
+        exported.insert("host".to_string(),
+                        toml::Value::String("localhost".to_string()));
+        let ports = vec!["8080:80".parse().unwrap(), "8443:443".parse().unwrap()];
+
+        advertise_host_ports(&mut exported, &ports);
+
+        assert_eq!(exported["port"], toml::Value::Integer(8080));
+        assert_eq!(exported["ssl_port"],
+                   toml::Value::String("8443".to_string()));
+        assert_eq!(exported["admin_port"], toml::Value::Integer(9000));
+        assert_eq!(exported["host"],
+                   toml::Value::String("localhost".to_string()));
+    }
+
+    #[test]
+    fn only_ports_are_advertised_as_host_ports() {
+        let mut listen = toml::value::Table::new();
+        listen.insert("port".to_string(), toml::Value::Integer(80));
+        listen.insert("backlog".to_string(), toml::Value::Integer(80));
+        let mut exported = toml::value::Table::new();
+        exported.insert("workers".to_string(), toml::Value::Integer(80));
+        exported.insert("version".to_string(),
+                        toml::Value::String("443".to_string()));
+        exported.insert("listen".to_string(), toml::Value::Table(listen));
+        let ports = vec!["8080:80".parse().unwrap(), "8443:443".parse().unwrap()];
+
+        advertise_host_ports(&mut exported, &ports);
+
+        assert_eq!(exported["workers"], toml::Value::Integer(80));
+        assert_eq!(exported["version"], toml::Value::String("443".to_string()));
+        assert_eq!(exported["listen"]["port"], toml::Value::Integer(8080));
+        assert_eq!(exported["listen"]["backlog"], toml::Value::Integer(80));
+    }
 }
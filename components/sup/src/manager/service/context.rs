@@ -37,7 +37,8 @@ use crate::{census::{CensusGroup,
                      CensusMemberProxy,
                      CensusRing,
                      ElectionStatus},
-            manager::Sys};
+            manager::{sys::CloudMetadata,
+                      Sys}};
 use habitat_common::templating::{config::Cfg,
                                  package::{Env,
                                            Pkg}};
@@ -125,6 +126,10 @@ struct SystemInfo<'a> {
     member_id:         Cow<'a, String>,
     ip:                Cow<'a, IpAddr>,
     hostname:          Cow<'a, String>,
+    fqdn:              Cow<'a, String>,
+    cpu_count:         Cow<'a, usize>,
+    memory_bytes:      Cow<'a, Option<u64>>,
+    cloud:             Cow<'a, Option<CloudMetadata>>,
     gossip_ip:         Cow<'a, IpAddr>,
     gossip_port:       Cow<'a, u16>,
     http_gateway_ip:   Cow<'a, IpAddr>,
@@ -140,6 +145,10 @@ impl<'a> SystemInfo<'a> {
                      member_id:         Cow::Borrowed(&sys.member_id),
                      ip:                Cow::Borrowed(&sys.ip),
                      hostname:          Cow::Borrowed(&sys.hostname),
+                     fqdn:              Cow::Borrowed(&sys.fqdn),
+                     cpu_count:         Cow::Borrowed(&sys.cpu_count),
+                     memory_bytes:      Cow::Borrowed(&sys.memory_bytes),
+                     cloud:             Cow::Borrowed(&sys.cloud),
                      gossip_ip:         Cow::Borrowed(&sys.gossip_ip),
                      gossip_port:       Cow::Borrowed(&sys.gossip_port),
                      http_gateway_ip:   Cow::Borrowed(&sys.http_gateway_ip),
@@ -491,6 +500,10 @@ two = 2
                          member_id:         Cow::Owned("MEMBER_ID".into()),
                          ip:                Cow::Owned(IpAddr::V4(Ipv4Addr::LOCALHOST)),
                          hostname:          Cow::Owned("MY_HOSTNAME".into()),
+                         fqdn:              Cow::Owned("MY_HOSTNAME.example.com".into()),
+                         cpu_count:         Cow::Owned(4),
+                         memory_bytes:      Cow::Owned(Some(8_589_934_592)),
+                         cloud:             Cow::Owned(None),
                          gossip_ip:         Cow::Owned(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0))),
                          gossip_port:       Cow::Owned(1234),
                          http_gateway_ip:   Cow::Owned(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0))),
@@ -3,19 +3,26 @@ use crate::{error::Error,
                                hooks::HealthCheckHook,
                                supervisor::Supervisor,
                                ProcessOutput,
-                               ProcessState}};
+                               ProcessState,
+                               StandardStreams}};
 use habitat_common::{outputln,
                      templating::package::Pkg};
 use habitat_core::service::{HealthCheckInterval,
                             ServiceGroup};
 use rand::Rng;
+use reqwest::{Client,
+              Url};
 use std::{cmp,
           convert::TryFrom,
           fmt,
           sync::{Arc,
                  Mutex},
-          time::Duration};
-use tokio::{sync::mpsc::{self,
+          time::{Duration,
+                 Instant,
+                 SystemTime,
+                 UNIX_EPOCH}};
+use tokio::{net::TcpStream,
+            sync::mpsc::{self,
                          UnboundedReceiver},
             time};
 
@@ -57,23 +64,151 @@ impl fmt::Display for HealthCheckResult {
     }
 }
 
+/// A health check the Supervisor performs itself, declared in the service spec, instead of
+/// running the package's health check hook.
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum HealthCheck {
+    /// Request `http` and compare the status code of the response to `expected_status`.
+    Http {
+        http:            String,
+        #[serde(default = "HealthCheck::default_expected_status")]
+        expected_status: u16,
+    },
+    /// Open a TCP connection to `host` on `port`, waiting at most `timeout` seconds.
+    Tcp {
+        #[serde(default = "HealthCheck::default_host")]
+        host:    String,
+        port:    u16,
+        timeout: Option<u64>,
+    },
+}
+
+impl HealthCheck {
+    fn default_expected_status() -> u16 { 200 }
+
+    fn default_host() -> String { "localhost".to_string() }
+}
+
+/// How long a built-in health check may take when no health check timeout is configured.
+const DEFAULT_PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Performs a `HealthCheck`.
+enum Probe {
+    Http {
+        client:          Client,
+        url:             Url,
+        expected_status: u16,
+    },
+    Tcp {
+        host:    String,
+        port:    u16,
+        timeout: Option<Duration>,
+    },
+}
+
+impl Probe {
+    fn new(health_check: &HealthCheck) -> habitat_http_client::Result<Self> {
+        match health_check {
+            HealthCheck::Http { http,
+                                expected_status, } => {
+                // The probed endpoint is normally local to the service, so it must not be
+                // requested through a proxy configured for the Supervisor's own traffic.
+                let client = Client::builder().no_proxy().build()?;
+                Ok(Probe::Http { client,
+                                 url: Url::parse(http)?,
+                                 expected_status: *expected_status })
+            }
+            HealthCheck::Tcp { host,
+                               port,
+                               timeout, } => {
+                Ok(Probe::Tcp { host:    host.clone(),
+                                port:    *port,
+                                timeout: timeout.map(Duration::from_secs), })
+            }
+        }
+    }
+
+    async fn check(&self, service_group: &ServiceGroup, timeout: Duration) -> HealthCheckResult {
+        match self {
+            Probe::Http { client,
+                          url,
+                          expected_status, } => {
+                match client.get(url.clone()).timeout(timeout).send().await {
+                    Ok(response) => http_result(response.status().as_u16(), *expected_status),
+                    Err(e) => {
+                        debug!("HTTP health check for {} failed: {}", service_group, e);
+                        HealthCheckResult::Critical
+                    }
+                }
+            }
+            Probe::Tcp { host,
+                         port,
+                         timeout: tcp_timeout, } => {
+                let connect = TcpStream::connect((host.as_str(), *port));
+                match time::timeout(tcp_timeout.unwrap_or(timeout), connect).await {
+                    Ok(Ok(_)) => HealthCheckResult::Ok,
+                    Ok(Err(e)) => {
+                        debug!("TCP health check of {}:{} for {} failed: {}",
+                               host, port, service_group, e);
+                        HealthCheckResult::Critical
+                    }
+                    Err(_) => {
+                        debug!("TCP health check of {}:{} for {} timed out",
+                               host, port, service_group);
+                        HealthCheckResult::Critical
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The expected status code is healthy, and any other status code is a warning, unless it is a
+/// server error.
+fn http_result(status: u16, expected_status: u16) -> HealthCheckResult {
+    if status == expected_status {
+        HealthCheckResult::Ok
+    } else if status >= 500 {
+        HealthCheckResult::Critical
+    } else {
+        HealthCheckResult::Warning
+    }
+}
+
 /// The possible statuses from running a health check hook.
 pub enum HealthCheckHookStatus {
     Ran(ProcessOutput, Duration),
     FailedToRun(Duration),
+    /// The hook was killed after running longer than the configured timeout.
+    TimedOut(Duration),
+    /// A built-in health check from the service spec was performed instead of a hook.
+    Probed(HealthCheckResult, Duration),
     FailedToStart,
     NoHook,
 }
 
 impl HealthCheckHookStatus {
     pub fn maybe_duration(&self) -> Option<Duration> {
-        if let Self::Ran(_, duration) | Self::FailedToRun(duration) = self {
+        if let Self::Ran(_, duration)
+        | Self::FailedToRun(duration)
+        | Self::TimedOut(duration)
+        | Self::Probed(_, duration) = self
+        {
             Some(*duration)
         } else {
             None
         }
     }
 
+    pub fn process_output(&self) -> Option<&ProcessOutput> {
+        if let Self::Ran(output, _) = self {
+            Some(output)
+        } else {
+            None
+        }
+    }
+
     pub fn maybe_process_output(self) -> Option<ProcessOutput> {
         if let Self::Ran(output, _) = self {
             Some(output)
@@ -95,21 +230,72 @@ pub struct HealthCheckBundle {
     pub interval: HealthCheckInterval,
 }
 
-/// Run the health check hook and get the hook status and result.
+/// How many health check results are kept for each service.
+pub const HEALTH_CHECK_HISTORY_LEN: usize = 64;
+
+/// A past health check, kept so flapping services can be diagnosed after the fact.
+#[derive(Clone, Debug, Serialize)]
+pub struct HealthCheckRecord {
+    /// When the health check finished, in seconds since the UNIX epoch.
+    pub timestamp:   u64,
+    pub result:      HealthCheckResult,
+    /// How long the health check took, in milliseconds.
+    pub duration_ms: Option<u64>,
+    /// The combined stdout and stderr of the health check hook.
+    pub output:      Option<String>,
+}
+
+impl HealthCheckRecord {
+    pub fn new(status: &HealthCheckHookStatus, result: HealthCheckResult) -> Self {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)
+                                         .map(|d| d.as_secs())
+                                         .unwrap_or_default();
+        let output = status.process_output().map(|output| {
+                                                let StandardStreams { stdout, stderr } =
+                                                    output.standard_streams_ref();
+                                                let mut combined = String::new();
+                                                for stream in stdout.iter().chain(stderr) {
+                                                    combined.push_str(stream);
+                                                }
+                                                combined
+                                            });
+        HealthCheckRecord { timestamp,
+                            result,
+                            duration_ms: status.maybe_duration().map(|d| d.as_millis() as u64),
+                            output }
+    }
+}
+
+/// Perform the built-in health check, or run the health check hook if there isn't one, and get
+/// the hook status and result.
 async fn check(supervisor: Arc<Mutex<Supervisor>>,
+               probe: Option<&Probe>,
                hook: Option<Arc<HealthCheckHook>>,
                service_group: ServiceGroup,
                package: Pkg,
-               password: Option<String>)
+               password: Option<String>,
+               timeout: Option<Duration>)
                -> (HealthCheckHookStatus, HealthCheckResult) {
-    let status = if let Some(hook) = hook {
+    let status = if let Some(probe) = probe {
+        let start = Instant::now();
+        let result = probe.check(&service_group, timeout.unwrap_or(DEFAULT_PROBE_TIMEOUT))
+                          .await;
+        HealthCheckHookStatus::Probed(result, start.elapsed())
+    } else if let Some(hook) = hook {
         let result = hook_runner::HookRunner::new(hook,
                                                   service_group.clone(),
                                                   package.clone(),
-                                                  password).into_future()
+                                                  password).with_timeout(timeout)
+                                                           .into_future()
                                                            .await;
         match result {
             Ok((output, duration)) => HealthCheckHookStatus::Ran(output, duration),
+            Err(Error::WithDuration(e, duration))
+                if matches!(*e,
+                            Error::HabitatCommon(habitat_common::Error::HookTimedOut(..))) =>
+            {
+                HealthCheckHookStatus::TimedOut(duration)
+            }
             Err(Error::WithDuration(e, duration)) => {
                 error!("Error running health check hook for {}: {:?}",
                        service_group, e);
@@ -147,6 +333,11 @@ async fn check(supervisor: Arc<Mutex<Supervisor>>,
             // unknown.
             HealthCheckResult::Unknown
         }
+        HealthCheckHookStatus::TimedOut(_) => {
+            // The hook hung and had to be killed, which counts as a failed health check.
+            HealthCheckResult::Critical
+        }
+        HealthCheckHookStatus::Probed(result, _) => *result,
         HealthCheckHookStatus::NoHook => {
             //  There was no hook to run. Use the supervisor status as a healthcheck.
             match supervisor.lock()
@@ -167,11 +358,14 @@ async fn check(supervisor: Arc<Mutex<Supervisor>>,
 /// `HealthCheckBundle`s. When this receiving end is dropped or closed health checking will be
 /// stopped.
 pub fn check_repeatedly(supervisor: Arc<Mutex<Supervisor>>,
+                        health_check: Option<HealthCheck>,
                         hook: Option<Arc<HealthCheckHook>>,
                         nominal_interval: HealthCheckInterval,
                         service_group: ServiceGroup,
                         package: Pkg,
-                        password: Option<String>)
+                        password: Option<String>,
+                        timeout: Option<Duration>,
+                        max_backoff: Option<Duration>)
                         -> UnboundedReceiver<HealthCheckBundle> {
     // TODO (CM): If we wanted to keep track of how many times
     // a health check has failed in the past X executions, or
@@ -182,13 +376,30 @@ pub fn check_repeatedly(supervisor: Arc<Mutex<Supervisor>>,
     let (tx, rx) = mpsc::unbounded_channel();
 
     tokio::spawn(async move {
+        let probe = match health_check.as_ref().map(Probe::new) {
+            Some(Ok(probe)) => Some(probe),
+            Some(Err(e)) => {
+                outputln!(preamble service_group,
+                          "Unable to set up the health check from the service spec, {}", e);
+                None
+            }
+            None => None,
+        };
         let mut first_ok_health_check_recorded = false;
+        let mut consecutive_critical = 0;
         loop {
             let (status, result) = check(Arc::clone(&supervisor),
+                                         probe.as_ref(),
                                          hook.as_ref().map(Arc::clone),
                                          service_group.clone(),
                                          package.clone(),
-                                         password.clone()).await;
+                                         password.clone(),
+                                         timeout).await;
+            if result == HealthCheckResult::Critical {
+                consecutive_critical += 1;
+            } else {
+                consecutive_critical = 0;
+            }
 
             let interval = if result == HealthCheckResult::Ok {
                 if !first_ok_health_check_recorded {
@@ -207,12 +418,11 @@ pub fn check_repeatedly(supervisor: Arc<Mutex<Supervisor>>,
                     nominal_interval
                 }
             } else {
-                // TODO (DM): Implment exponential backoff
-                // https://github.com/habitat-sh/habitat/issues/7265
-                // Until exponential backoff is implmented never wait longer than the default
-                // interval following a failing health check. If the configured interval is less
-                // than the default interval use it instead.
-                cmp::min(nominal_interval, HealthCheckInterval::default())
+                // Never wait longer than the default interval following a failing health check,
+                // unless backing off from consecutive critical results. If the configured
+                // interval is less than the default interval use it instead.
+                let interval = cmp::min(nominal_interval, HealthCheckInterval::default());
+                backoff(interval.into(), consecutive_critical, max_backoff).into()
             };
 
             // This can only fail if the receiving end is closed or dropped indicating to stop
@@ -236,3 +446,45 @@ pub fn check_repeatedly(supervisor: Arc<Mutex<Supervisor>>,
 
     rx
 }
+
+/// Double `interval` for each consecutive critical result after the first, up to `max_backoff`.
+/// Without a `max_backoff` there is no backoff at all.
+fn backoff(interval: Duration,
+           consecutive_critical: u32,
+           max_backoff: Option<Duration>)
+           -> Duration {
+    match max_backoff {
+        Some(max_backoff) if consecutive_critical > 1 => {
+            let factor = 2u32.saturating_pow(consecutive_critical - 1);
+            let backed_off = interval.checked_mul(factor).unwrap_or(max_backoff);
+            cmp::max(interval, cmp::min(backed_off, max_backoff))
+        }
+        _ => interval,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_the_max() {
+        let interval = Duration::from_secs(10);
+        let max = Some(Duration::from_secs(60));
+
+        assert_eq!(backoff(interval, 0, max), interval);
+        assert_eq!(backoff(interval, 1, max), interval);
+        assert_eq!(backoff(interval, 2, max), Duration::from_secs(20));
+        assert_eq!(backoff(interval, 3, max), Duration::from_secs(40));
+        assert_eq!(backoff(interval, 4, max), Duration::from_secs(60));
+        assert_eq!(backoff(interval, 100, max), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn backoff_is_disabled_without_a_max() {
+        let interval = Duration::from_secs(10);
+
+        assert_eq!(backoff(interval, 5, None), interval);
+        assert_eq!(backoff(interval, 5, Some(Duration::from_secs(1))), interval);
+    }
+}
@@ -25,6 +25,7 @@ pub struct HookRunner<H: Hook + Sync> {
     service_group: ServiceGroup,
     pkg:           Pkg,
     passwd:        Option<String>,
+    timeout:       Option<Duration>,
 }
 
 // We cannot use `#[derive(Clone)]` here because it unnecessarily requires `H` to be
@@ -34,7 +35,8 @@ impl<H: Hook + Sync> Clone for HookRunner<H> {
         Self { hook:          self.hook.clone(),
                service_group: self.service_group.clone(),
                pkg:           self.pkg.clone(),
-               passwd:        self.passwd.clone(), }
+               passwd:        self.passwd.clone(),
+               timeout:       self.timeout, }
     }
 }
 
@@ -48,7 +50,14 @@ impl<H> HookRunner<H> where H: Hook + Sync + 'static
         HookRunner { hook,
                      service_group,
                      pkg,
-                     passwd }
+                     passwd,
+                     timeout: None }
+    }
+
+    /// Kill the hook if it is still running after `timeout`.
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
     }
 
     pub async fn retryable_future(self) {
@@ -67,16 +76,24 @@ impl<H> HookRunner<H> where H: Hook + Sync + 'static
     }
 
     pub async fn into_future(self) -> Result<(H::ExitValue, Duration)> {
-        // TODO (CM): May want to consider adding a configurable
-        // timeout to how long this hook is allowed to run.
         task::spawn_blocking(move || {
             // _timer is for Prometheus metrics, but we also want
             // the runtime for other purposes. Unfortunately,
             // we're not able to use the same timer for both :(
             let _timer = hook_timer(H::FILE_NAME);
             let start = Instant::now();
-            let result = self.hook
-                             .run(&self.service_group, &self.pkg, self.passwd.as_ref());
+            let result = match self.timeout {
+                Some(timeout) => {
+                    self.hook.run_with_timeout(&self.service_group,
+                                               &self.pkg,
+                                               self.passwd.as_ref(),
+                                               timeout)
+                }
+                None => {
+                    self.hook
+                        .run(&self.service_group, &self.pkg, self.passwd.as_ref())
+                }
+            };
             let run_time = start.elapsed();
             let exit_value = result.map_err(|e| Error::from(e).with_duration(run_time))?;
             Ok((exit_value, run_time))
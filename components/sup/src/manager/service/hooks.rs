@@ -12,14 +12,19 @@ use habitat_common::{error::Result,
                      FeatureFlag};
 #[cfg(windows)]
 use habitat_core::os::process::windows_child::ExitStatus;
-use serde::Serialize;
+use serde::{Deserialize,
+            Serialize};
 #[cfg(not(windows))]
 use std::process::ExitStatus;
+#[cfg(windows)]
+use std::time::Duration;
 use std::{self,
+          fmt,
           io::BufRead,
           path::{Path,
                  PathBuf},
-          sync::Arc};
+          sync::Arc,
+          time::SystemTime};
 
 static LOGKEY: &str = "HK";
 
@@ -50,6 +55,60 @@ impl ProcessOutput {
     pub fn exit_status(&self) -> ExitStatus { self.exit_status }
 
     pub fn standard_streams(self) -> StandardStreams { self.standard_streams }
+
+    pub fn standard_streams_ref(&self) -> &StandardStreams { &self.standard_streams }
+}
+
+/// A failed invocation of a lifecycle hook, along with what it wrote to its standard streams, so
+/// the reason for the failure can be reported without searching through the Supervisor's output.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct HookFailure {
+    /// The name of the hook, e.g. `init`
+    pub hook:        String,
+    /// When the hook finished, in seconds since the UNIX epoch
+    pub timestamp:   u64,
+    /// The exit status of the hook, when it ran to completion and reported one
+    pub exit_status: Option<i32>,
+    /// Why the hook could not be run, when it could not
+    pub error:       Option<String>,
+    pub stdout:      Option<String>,
+    pub stderr:      Option<String>,
+}
+
+impl HookFailure {
+    /// A failure of a hook which ran to completion.
+    pub fn new<H: Hook>(hook: &H, exit_status: Option<i32>) -> Self {
+        Self::with_output(hook, exit_status, None)
+    }
+
+    /// A failure of a hook which could not be run.
+    pub fn from_error<H: Hook>(hook: &H, error: impl fmt::Display) -> Self {
+        Self::with_output(hook, None, Some(error.to_string()))
+    }
+
+    /// The failure, if any, of a hook which reports its exit code.
+    pub fn from_exit_code<H>(hook: &H, result: Result<ExitCode>) -> Option<Self>
+        where H: Hook<ExitValue = ExitCode>
+    {
+        match result {
+            Ok(ExitCode(0)) => None,
+            Ok(ExitCode(code)) => Some(Self::new(hook, Some(code))),
+            Err(e) => Some(Self::from_error(hook, e)),
+        }
+    }
+
+    fn with_output<H: Hook>(hook: &H, exit_status: Option<i32>, error: Option<String>) -> Self {
+        let timestamp = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)
+                                         .map(|d| d.as_secs())
+                                         .unwrap_or_default();
+        let hook_output = HookOutput::new(hook.stdout_log_path(), hook.stderr_log_path());
+        Self { hook: H::FILE_NAME.to_string(),
+               timestamp,
+               exit_status,
+               error,
+               stdout: hook_output.stdout_str().ok(),
+               stderr: hook_output.stderr_str().ok() }
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -152,7 +211,25 @@ impl Hook for HealthCheckHook {
                 }
             }
         } else {
-            self.run_impl(service_group, pkg, svc_encrypted_password)
+            self.run_impl(service_group, pkg, svc_encrypted_password, None)
+        }
+    }
+
+    /// Hooks run through the named pipe share a long-lived PowerShell process, which can't be
+    /// killed on its own; the timeout only applies when the pipe is not used.
+    #[cfg(windows)]
+    fn run_with_timeout<T>(&self,
+                           service_group: &str,
+                           pkg: &Pkg,
+                           svc_encrypted_password: Option<T>,
+                           timeout: Duration)
+                           -> Result<Self::ExitValue>
+        where T: ToString
+    {
+        if self.pipe_client.is_some() {
+            self.run(service_group, pkg, svc_encrypted_password)
+        } else {
+            self.run_impl(service_group, pkg, svc_encrypted_password, Some(timeout))
         }
     }
 
@@ -513,6 +590,51 @@ impl Hook for PostStopHook {
     fn stderr_log_path(&self) -> &Path { &self.stderr_log_path }
 }
 
+/// Runs once a service has been started with a new package release by the service updater, so
+/// migrations or cache warming can be tied to version changes rather than every restart.
+#[derive(Debug, Serialize)]
+pub struct PostUpdateHook {
+    render_pair:     RenderPair,
+    stdout_log_path: PathBuf,
+    stderr_log_path: PathBuf,
+}
+
+impl Hook for PostUpdateHook {
+    type ExitValue = bool;
+
+    const FILE_NAME: &'static str = "post-update";
+
+    fn new(package_name: &str, pair: RenderPair, _feature_flags: FeatureFlag) -> Self {
+        PostUpdateHook { render_pair:     pair,
+                         stdout_log_path: hooks::stdout_log_path::<Self>(package_name),
+                         stderr_log_path: hooks::stderr_log_path::<Self>(package_name), }
+    }
+
+    fn handle_exit<'a>(&self, pkg: &Pkg, _: &'a HookOutput, status: ExitStatus) -> Self::ExitValue {
+        let pkg_name = &pkg.name;
+        match status.code() {
+            Some(0) => true,
+            Some(code) => {
+                outputln!(preamble pkg_name, "Post update failed! '{}' exited with \
+                    status code {}", Self::FILE_NAME, code);
+                false
+            }
+            None => {
+                Self::output_termination_message(pkg_name, status);
+                false
+            }
+        }
+    }
+
+    fn path(&self) -> &Path { &self.render_pair.path }
+
+    fn renderer(&self) -> &TemplateRenderer { &self.render_pair.renderer }
+
+    fn stdout_log_path(&self) -> &Path { &self.stdout_log_path }
+
+    fn stderr_log_path(&self) -> &Path { &self.stderr_log_path }
+}
+
 /// A lookup of hooks that have changed after compilation.
 #[derive(Default)]
 pub struct HookCompileTable {
@@ -525,6 +647,7 @@ pub struct HookCompileTable {
     run:          bool,
     post_run:     bool,
     post_stop:    bool,
+    post_update:  bool,
 }
 
 impl HookCompileTable {
@@ -547,7 +670,8 @@ impl HookCompileTable {
                    suitability,
                    run,
                    post_run,
-                   post_stop, } = self;
+                   post_stop,
+                   post_update, } = self;
         *health_check
         || *init
         || *file_updated
@@ -557,6 +681,7 @@ impl HookCompileTable {
         || *run
         || *post_run
         || *post_stop
+        || *post_update
     }
 }
 
@@ -573,6 +698,7 @@ pub struct HookTable {
     pub run:          Option<RunHook>,
     pub post_run:     Option<Arc<PostRunHook>>,
     pub post_stop:    Option<Arc<PostStopHook>>,
+    pub post_update:  Option<Arc<PostUpdateHook>>,
 }
 
 impl HookTable {
@@ -610,6 +736,10 @@ impl HookTable {
                                                      &hooks_path,
                                                      &templates,
                                                      feature_flags).map(Arc::new);
+                table.post_update = PostUpdateHook::load(package_name,
+                                                         &hooks_path,
+                                                         &templates,
+                                                         feature_flags).map(Arc::new);
             }
         }
         debug!("{}, Hooks loaded, destination={}, templates={}",
@@ -652,6 +782,9 @@ impl HookTable {
         if let Some(ref hook) = self.post_stop {
             changed.post_stop = self.compile_one(hook.as_ref(), service_group, ctx);
         }
+        if let Some(ref hook) = self.post_update {
+            changed.post_update = self.compile_one(hook.as_ref(), service_group, ctx);
+        }
         changed
     }
 
@@ -728,7 +861,8 @@ mod tests {
                       ReconfigureHook
                       RunHook
                       SuitabilityHook
-                      PostStopHook);
+                      PostStopHook
+                      PostUpdateHook);
 
     fn hook_templates_path() -> PathBuf {
         PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests")
@@ -981,4 +1115,27 @@ mod tests {
                        .unwrap()
                        .contains("Named pipe created"));
     }
+
+    #[test]
+    fn hook_failure_from_exit_code() {
+        let tmp_root = rendered_hooks_path();
+        let render_pair = RenderPair::new(tmp_root.path().join("reconfigure"),
+                                          hook_templates_path().join("init"),
+                                          ReconfigureHook::FILE_NAME).expect("create render pair");
+        let hook =
+            ReconfigureHook { render_pair,
+                              stdout_log_path: tmp_root.path().join("reconfigure.stdout.log"),
+                              stderr_log_path: tmp_root.path().join("reconfigure.stderr.log") };
+        create_with_content(&hook.stdout_log_path, "Reloading config");
+        create_with_content(&hook.stderr_log_path, "Invalid port");
+
+        assert_eq!(HookFailure::from_exit_code(&hook, Ok(ExitCode(0))), None);
+
+        let failure = HookFailure::from_exit_code(&hook, Ok(ExitCode(2))).expect("a failure");
+        assert_eq!(failure.hook, "reconfigure");
+        assert_eq!(failure.exit_status, Some(2));
+        assert_eq!(failure.error, None);
+        assert_eq!(failure.stdout.as_deref(), Some("Reloading config"));
+        assert_eq!(failure.stderr.as_deref(), Some("Invalid port"));
+    }
 }
@@ -0,0 +1,136 @@
+//! Publishes the output of a service to the event stream.
+//!
+//! The Launcher, rather than the Supervisor, captures a service's output, so the Supervisor
+//! follows the log file the Launcher writes each stream to and sends a `ServiceOutputEvent` for
+//! every new line.
+
+use crate::event::{self,
+                   ServiceMetadata};
+use habitat_common::output::ServiceStream;
+use std::{fs::File,
+          io::{self,
+               Read,
+               Seek,
+               SeekFrom},
+          path::PathBuf,
+          time::Duration};
+use tokio::time;
+
+/// How often the log file is checked for new output.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Keeps track of how much of a log file has been read.
+struct LogFollower {
+    path:    PathBuf,
+    /// The offset just past the last complete line that was read.
+    offset:  u64,
+    /// A trailing line that hasn't been completed yet.
+    partial: String,
+}
+
+impl LogFollower {
+    /// Start following `path` from its current end, so only output written from now on is
+    /// reported.
+    fn new(path: PathBuf) -> Self {
+        let offset = path.metadata().map(|m| m.len()).unwrap_or(0);
+        LogFollower { path,
+                      offset,
+                      partial: String::new() }
+    }
+
+    /// Read the lines that were completed since the last call. When the file has shrunk, it was
+    /// rotated and is read again from the start.
+    fn read_lines(&mut self) -> io::Result<Vec<String>> {
+        let mut file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let len = file.metadata()?.len();
+        if len < self.offset + self.partial.len() as u64 {
+            self.offset = 0;
+            self.partial.clear();
+        }
+        file.seek(SeekFrom::Start(self.offset + self.partial.len() as u64))?;
+        let mut buf = Vec::new();
+        file.read_to_end(&mut buf)?;
+        self.partial.push_str(&String::from_utf8_lossy(&buf));
+
+        let mut lines = Vec::new();
+        while let Some(end) = self.partial.find('\n') {
+            let line = self.partial.drain(..=end).collect::<String>();
+            self.offset += line.len() as u64;
+            lines.push(line.trim_end_matches(&['\r', '\n'][..]).to_string());
+        }
+        Ok(lines)
+    }
+}
+
+/// Follow the log file of one of a service's output streams until the returned future is
+/// dropped, publishing each new line to the event stream.
+pub async fn publish(path: PathBuf, stream: ServiceStream, metadata: ServiceMetadata) {
+    let mut follower = LogFollower::new(path);
+    loop {
+        match follower.read_lines() {
+            Ok(lines) => {
+                for line in lines {
+                    event::service_output(metadata.clone(), stream, line);
+                }
+            }
+            Err(e) => {
+                debug!("Unable to read {} output from {}: {}",
+                       stream,
+                       follower.path.display(),
+                       e)
+            }
+        }
+        time::delay_for(POLL_INTERVAL).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{fs::{self,
+                   OpenOptions},
+              io::Write};
+    use tempfile::TempDir;
+
+    fn append(path: &PathBuf, content: &str) {
+        let mut file = OpenOptions::new().create(true)
+                                         .append(true)
+                                         .open(path)
+                                         .unwrap();
+        file.write_all(content.as_bytes()).unwrap();
+    }
+
+    #[test]
+    fn log_follower_reads_complete_new_lines() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("service.log");
+        append(&path, "written before following\n");
+
+        let mut follower = LogFollower::new(path.clone());
+        assert!(follower.read_lines().unwrap().is_empty());
+
+        append(&path, "first\nsecond\nthi");
+        assert_eq!(follower.read_lines().unwrap(), vec!["first", "second"]);
+
+        append(&path, "rd\n");
+        assert_eq!(follower.read_lines().unwrap(), vec!["third"]);
+    }
+
+    #[test]
+    fn log_follower_restarts_after_rotation() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("service.log");
+        append(&path, "a fairly long line before rotation\n");
+        let mut follower = LogFollower::new(path.clone());
+
+        fs::rename(&path, dir.path().join("service.log.1")).unwrap();
+        assert!(follower.read_lines().unwrap().is_empty());
+
+        append(&path, "after\n");
+        assert_eq!(follower.read_lines().unwrap(), vec!["after"]);
+    }
+}
@@ -4,7 +4,8 @@ use habitat_common::{error::{Error,
                      outputln,
                      templating::package::Pkg};
 use habitat_core::{env as henv,
-                   os::process::windows_child::Child,
+                   os::process::windows_child::{Child,
+                                                UserProfile},
                    util::{self,
                           BufReadLossy}};
 use mio::{Events,
@@ -28,6 +29,7 @@ use std::{self,
                         io::*},
           path::PathBuf,
           process,
+          sync::Mutex,
           thread,
           time::{Duration,
                  Instant}};
@@ -52,6 +54,8 @@ pub struct PipeHookClient {
     pipe_name:       String,
     stdout_log_file: PathBuf,
     stderr_log_file: PathBuf,
+    /// The profile loaded for the powershell server, kept loaded for as long as it runs.
+    profile:         Mutex<Option<UserProfile>>,
 }
 
 impl PipeHookClient {
@@ -65,7 +69,8 @@ impl PipeHookClient {
                hook_path,
                pipe_name,
                stdout_log_file,
-               stderr_log_file }
+               stderr_log_file,
+               profile: Mutex::default() }
     }
 
     pub fn exec_hook<T>(&self,
@@ -174,6 +179,9 @@ impl PipeHookClient {
         let out_path = self.stdout_log_file.clone();
         let err_path = self.stderr_log_file.clone();
         let handle = child.handle;
+        *self.profile
+             .lock()
+             .expect("Pipe server profile lock poisoned") = child.profile;
 
         // The above powershell will run for the lifetime of this client which is the same
         // duration as the service itself and will thus service each hook run. So we will
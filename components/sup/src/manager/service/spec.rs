@@ -1,11 +1,21 @@
 use super::{BindingMode,
+            HealthCheck,
             Topology,
             UpdateCondition,
             UpdateStrategy};
 use crate::error::{Error,
                    Result};
-use habitat_core::{fs::atomic_write,
-                   os::process::ShutdownTimeout,
+use habitat_common::util::{env_file,
+                           rotating_log::LogRotation};
+use habitat_core::{crypto::keys::{HostBox,
+                                  HostKey},
+                   fs::atomic_write,
+                   os::{cgroup,
+                        isolation::{AllowedPath,
+                                    Isolation,
+                                    PortMapping},
+                        process::{ProcessSettings,
+                                  ShutdownTimeout}},
                    package::{PackageIdent,
                              PackageInstall},
                    service::{HealthCheckInterval,
@@ -17,7 +27,9 @@ use habitat_sup_protocol::{self,
                            net};
 use serde::{self,
             Deserialize};
-use std::{collections::HashSet,
+use state::Storage;
+use std::{collections::{BTreeMap,
+                        HashSet},
           convert::TryFrom,
           fmt,
           fs::{self,
@@ -28,11 +40,23 @@ use std::{collections::HashSet,
           path::{Path,
                  PathBuf},
           result,
-          str::FromStr};
+          str::FromStr,
+          time::Duration};
 
 static DEFAULT_GROUP: &str = "default";
 const SPEC_FILE_EXT: &str = "spec";
 
+lazy_static! {
+    /// The Supervisor's host key, if it has one, and whether spec files should be encrypted with
+    /// it when they are written.
+    static ref HOST_KEY: Storage<(HostKey, bool)> = Storage::new();
+}
+
+/// Use `host_key` to read spec files that were encrypted at rest and, if `encrypt` is set, to
+/// encrypt spec files as they are written. Spec files written before are encrypted or decrypted
+/// accordingly when the spec directory is next migrated.
+pub fn init_host_key(host_key: HostKey, encrypt: bool) { HOST_KEY.set((host_key, encrypt)); }
+
 #[derive(Copy, Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
 pub enum DesiredState {
     Down,
@@ -74,24 +98,90 @@ impl From<DesiredState> for i32 {
     }
 }
 
+/// How a member stands with respect to the preferred and excluded leaders of a service.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LeaderAffinity {
+    Preferred,
+    Neutral,
+    Excluded,
+}
+
 #[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
 #[serde(default = "ServiceSpec::deserialization_base")]
 pub struct ServiceSpec {
     #[serde(with = "util::serde::string")]
-    pub ident:                  PackageIdent,
-    pub group:                  String,
-    pub bldr_url:               String,
-    pub channel:                ChannelIdent,
-    pub topology:               Topology,
-    pub update_strategy:        UpdateStrategy,
-    pub update_condition:       UpdateCondition,
-    pub binds:                  Vec<ServiceBind>,
-    pub binding_mode:           BindingMode,
-    pub config_from:            Option<PathBuf>,
+    pub ident:                    PackageIdent,
+    pub group:                    String,
+    pub bldr_url:                 String,
+    pub channel:                  ChannelIdent,
+    pub topology:                 Topology,
+    pub update_strategy:          UpdateStrategy,
+    pub update_condition:         UpdateCondition,
+    pub binds:                    Vec<ServiceBind>,
+    pub binding_mode:             BindingMode,
+    pub config_from:              Option<PathBuf>,
     #[serde(with = "util::serde::string")]
-    pub desired_state:          DesiredState,
-    pub shutdown_timeout:       Option<ShutdownTimeout>,
-    pub svc_encrypted_password: Option<String>,
+    pub desired_state:            DesiredState,
+    pub shutdown_timeout:         Option<ShutdownTimeout>,
+    pub svc_encrypted_password:   Option<String>,
+    pub cpu_max:                  Option<String>,
+    pub memory_max:               Option<String>,
+    pub io_weight:                Option<u32>,
+    pub svc_user:                 Option<String>,
+    pub svc_group:                Option<String>,
+    pub log_max_bytes:            Option<u64>,
+    // In seconds
+    pub log_max_age:              Option<u64>,
+    pub log_max_files:            Option<u32>,
+    pub log_forward:              Option<String>,
+    pub stdout_to:                Option<String>,
+    pub stderr_to:                Option<String>,
+    // In seconds
+    pub health_check_timeout:     Option<u64>,
+    // In seconds
+    pub health_check_max_backoff: Option<u64>,
+    /// Member IDs or Supervisor tags preferred as the leader in a leader topology
+    pub preferred_leaders:        Vec<String>,
+    /// Member IDs or Supervisor tags never elected leader while another member can be
+    pub excluded_leaders:         Vec<String>,
+    /// Whether the service runs in a PID namespace of its own
+    pub pid_namespace:            bool,
+    /// Whether the service runs in a network namespace of its own
+    pub network_namespace:        bool,
+    /// Host ports forwarded to the service in its network namespace, as
+    /// `HOST_PORT:SERVICE_PORT`
+    pub ports:                    Vec<String>,
+    /// The seccomp profile restricting the system calls of the service, overriding the
+    /// package's: `default`, or the path of a profile file
+    pub seccomp_profile:          Option<String>,
+    /// Whether the service runs in a filesystem sandbox
+    pub filesystem_sandbox:       bool,
+    /// Further paths of the host visible in the service's filesystem sandbox, as an absolute
+    /// path optionally followed by `:ro`
+    pub fs_allow:                 Vec<String>,
+    /// The file mode creation mask of the service, in octal
+    pub umask:                    Option<String>,
+    /// The absolute path of the working directory of the service
+    pub working_dir:              Option<String>,
+    /// Environment files on the Supervisor's host, in the dotenv format
+    pub env_files:                Vec<String>,
+    /// Resource limits of the service, as `RESOURCE=SOFT[:HARD]`
+    pub ulimits:                  Vec<String>,
+    /// The niceness of the service, from -20 to 19
+    pub nice:                     Option<i32>,
+    /// The IO scheduling class and priority of the service, as `CLASS[:LEVEL]`
+    pub io_priority:              Option<String>,
+    /// The names of the sockets passed to the Launcher by systemd socket activation that the
+    /// service takes over
+    pub listen_fds:               Vec<String>,
+    // The variables of `env_files`, as read when the service was last loaded or updated.
+    // Serialized as a table, so like the health check below it has to follow all individual
+    // values.
+    pub env_vars:                 BTreeMap<String, String>,
+    // Serialized as a table, so like the health check interval below it has to follow all
+    // individual values.
+    #[serde(rename = "health-check")]
+    pub health_check:             Option<HealthCheck>,
     // it is important that the health check interval
     // is the last field to be serialized because it
     // is serialized as a table. Individual values
@@ -100,7 +190,7 @@ pub struct ServiceSpec {
     // Note that there is an issue to ultimately fix this:
     // https://github.com/habitat-sh/habitat/issues/6469
     // and eliminate the need to keep this field last.
-    pub health_check_interval:  HealthCheckInterval,
+    pub health_check_interval:    HealthCheckInterval,
 }
 
 impl ServiceSpec {
@@ -118,7 +208,123 @@ impl ServiceSpec {
                desired_state: DesiredState::default(),
                health_check_interval: HealthCheckInterval::default(),
                svc_encrypted_password: None,
-               shutdown_timeout: None }
+               shutdown_timeout: None,
+               cpu_max: None,
+               memory_max: None,
+               io_weight: None,
+               svc_user: None,
+               svc_group: None,
+               log_max_bytes: None,
+               log_max_age: None,
+               log_max_files: None,
+               log_forward: None,
+               stdout_to: None,
+               stderr_to: None,
+               health_check_timeout: None,
+               health_check_max_backoff: None,
+               preferred_leaders: Vec::new(),
+               excluded_leaders: Vec::new(),
+               pid_namespace: false,
+               network_namespace: false,
+               ports: Vec::new(),
+               seccomp_profile: None,
+               filesystem_sandbox: false,
+               fs_allow: Vec::new(),
+               umask: None,
+               working_dir: None,
+               env_files: Vec::new(),
+               ulimits: Vec::new(),
+               nice: None,
+               io_priority: None,
+               listen_fds: Vec::new(),
+               env_vars: BTreeMap::new(),
+               health_check: None }
+    }
+
+    /// The leader affinity of the member with the given ID and Supervisor tags, or `None` if the
+    /// service has no leader preferences. Exclusion takes precedence over preference.
+    pub fn leader_affinity(&self, member_id: &str, tags: &[String]) -> Option<LeaderAffinity> {
+        if self.preferred_leaders.is_empty() && self.excluded_leaders.is_empty() {
+            return None;
+        }
+        let matches = |entries: &[String]| {
+            entries.iter()
+                   .any(|entry| entry == member_id || tags.contains(entry))
+        };
+        if matches(&self.excluded_leaders) {
+            Some(LeaderAffinity::Excluded)
+        } else if matches(&self.preferred_leaders) {
+            Some(LeaderAffinity::Preferred)
+        } else {
+            Some(LeaderAffinity::Neutral)
+        }
+    }
+
+    /// The cgroup limits the service's process should run under.
+    pub fn limits(&self) -> cgroup::Limits {
+        cgroup::Limits { cpu_max:    self.cpu_max.clone(),
+                         memory_max: self.memory_max.clone(),
+                         io_weight:  self.io_weight, }
+    }
+
+    /// How the service's process should be isolated from the rest of the host, before the
+    /// package's own seccomp profile and the paths of the service itself are taken into account.
+    pub fn isolation(&self) -> Isolation {
+        Isolation { pid_namespace:      self.pid_namespace,
+                    network_namespace:  self.network_namespace,
+                    ports:              self.port_mappings(),
+                    seccomp_profile:    self.seccomp_profile
+                                            .as_ref()
+                                            .and_then(|profile| profile.parse().ok()),
+                    filesystem_sandbox: self.filesystem_sandbox,
+                    allowed_paths:      self.allowed_paths(), }
+    }
+
+    /// How the service's process should be set up by the Launcher.
+    pub fn process_settings(&self) -> ProcessSettings {
+        ProcessSettings { umask:       self.umask.as_ref().and_then(|umask| umask.parse().ok()),
+                          working_dir: self.working_dir.as_ref().map(PathBuf::from),
+                          ulimits:     self.ulimits
+                                           .iter()
+                                           .filter_map(|ulimit| ulimit.parse().ok())
+                                           .collect(),
+                          nice:        self.nice,
+                          io_priority: self.io_priority
+                                           .as_ref()
+                                           .and_then(|priority| priority.parse().ok()),
+                          listen_fds:  self.listen_fds.clone(), }
+    }
+
+    /// The further paths of the host visible to the service, if it runs in a filesystem sandbox.
+    pub fn allowed_paths(&self) -> Vec<AllowedPath> {
+        if !self.filesystem_sandbox {
+            return Vec::new();
+        }
+        self.fs_allow
+            .iter()
+            .filter_map(|path| path.parse().ok())
+            .collect()
+    }
+
+    /// The host ports forwarded to the service, if it runs in a network namespace of its own.
+    pub fn port_mappings(&self) -> Vec<PortMapping> {
+        if !self.network_namespace {
+            return Vec::new();
+        }
+        self.ports
+            .iter()
+            .filter_map(|mapping| mapping.parse().ok())
+            .collect()
+    }
+
+    /// How the service's log file is rotated, falling back to the Supervisor's `defaults` for
+    /// anything not set on the service itself.
+    pub fn log_rotation(&self, defaults: LogRotation) -> LogRotation {
+        LogRotation { max_bytes: self.log_max_bytes.unwrap_or(defaults.max_bytes),
+                      max_age:   self.log_max_age
+                                     .map(Duration::from_secs)
+                                     .or(defaults.max_age),
+                      max_files: self.log_max_files.unwrap_or(defaults.max_files), }
     }
 
     // This should only be used to provide a default value when deserializing. We intentially do not
@@ -141,7 +347,10 @@ impl ServiceSpec {
         let mut buf = String::new();
         file.read_to_string(&mut buf)
             .map_err(|err| Error::ServiceSpecFileIO(path.as_ref().to_path_buf(), err))?;
-        Self::from_str(&buf)
+        let toml = open_spec(HOST_KEY.try_get().map(|(key, _)| key), buf).map_err(|err| {
+                       Error::ServiceSpecDecrypt(path.as_ref().to_path_buf(), err)
+                   })?;
+        Self::from_str(&toml)
     }
 
     pub fn to_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
@@ -154,7 +363,10 @@ impl ServiceSpec {
         fs::create_dir_all(dst_path).map_err(|err| {
                                         Error::ServiceSpecFileIO(path.as_ref().to_path_buf(), err)
                                     })?;
-        let toml = self.to_toml_string()?;
+        let toml = match HOST_KEY.try_get() {
+            Some((key, true)) => key.seal(self.to_toml_string()?.as_bytes()).to_string(),
+            _ => self.to_toml_string()?,
+        };
         atomic_write(path.as_ref(), toml).map_err(|err| {
                                              Error::ServiceSpecFileIO(path.as_ref().to_path_buf(),
                                                                       err)
@@ -276,10 +488,99 @@ impl ServiceSpec {
         if let Some(shutdown_timeout) = svc_load.shutdown_timeout {
             self.shutdown_timeout = Some(ShutdownTimeout::from(shutdown_timeout));
         }
+        if let Some(cpu_max) = svc_load.cpu_max {
+            self.cpu_max = Some(cpu_max);
+        }
+        if let Some(memory_max) = svc_load.memory_max {
+            self.memory_max = Some(memory_max);
+        }
+        if let Some(io_weight) = svc_load.io_weight {
+            self.io_weight = Some(io_weight);
+        }
+        if let Some(svc_user) = svc_load.svc_user {
+            self.svc_user = Some(svc_user);
+        }
+        if let Some(svc_group) = svc_load.svc_group {
+            self.svc_group = Some(svc_group);
+        }
+        if let Some(log_max_bytes) = svc_load.log_max_bytes {
+            self.log_max_bytes = Some(log_max_bytes);
+        }
+        if let Some(log_max_age) = svc_load.log_max_age {
+            self.log_max_age = Some(log_max_age);
+        }
+        if let Some(log_max_files) = svc_load.log_max_files {
+            self.log_max_files = Some(log_max_files);
+        }
+        if let Some(log_forward) = svc_load.log_forward {
+            self.log_forward = Some(log_forward);
+        }
+        if let Some(stdout_to) = svc_load.stdout_to {
+            self.stdout_to = Some(stdout_to);
+        }
+        if let Some(stderr_to) = svc_load.stderr_to {
+            self.stderr_to = Some(stderr_to);
+        }
+        if let Some(health_check_timeout) = svc_load.health_check_timeout {
+            self.health_check_timeout = Some(health_check_timeout);
+        }
+        if let Some(health_check_max_backoff) = svc_load.health_check_max_backoff {
+            self.health_check_max_backoff = Some(health_check_max_backoff);
+        }
+        if let Some(preferred_leaders) = svc_load.preferred_leaders {
+            self.preferred_leaders = preferred_leaders.members;
+        }
+        if let Some(excluded_leaders) = svc_load.excluded_leaders {
+            self.excluded_leaders = excluded_leaders.members;
+        }
+        if let Some(pid_namespace) = svc_load.pid_namespace {
+            self.pid_namespace = pid_namespace;
+        }
+        if let Some(network_namespace) = svc_load.network_namespace {
+            self.network_namespace = network_namespace;
+        }
+        if let Some(ports) = svc_load.ports {
+            self.ports = ports.mappings;
+        }
+        if let Some(seccomp_profile) = svc_load.seccomp_profile {
+            self.seccomp_profile = Some(seccomp_profile);
+        }
+        if let Some(filesystem_sandbox) = svc_load.filesystem_sandbox {
+            self.filesystem_sandbox = filesystem_sandbox;
+        }
+        if let Some(fs_allow) = svc_load.fs_allow {
+            self.fs_allow = fs_allow.paths;
+        }
+        if let Some(umask) = svc_load.umask {
+            self.umask = Some(umask);
+        }
+        if let Some(working_dir) = svc_load.working_dir {
+            self.working_dir = Some(working_dir);
+        }
+        if let Some(env_files) = svc_load.env_files {
+            self.env_files = env_files.paths;
+        }
+        if let Some(ulimits) = svc_load.ulimits {
+            self.ulimits = ulimits.ulimits;
+        }
+        if let Some(nice) = svc_load.nice {
+            self.nice = Some(nice);
+        }
+        if let Some(io_priority) = svc_load.io_priority {
+            self.io_priority = Some(io_priority);
+        }
+        if let Some(listen_fds) = svc_load.listen_fds {
+            self.listen_fds = listen_fds.names;
+        }
+        self.env_vars = read_env_files(&self.env_files)?;
         Ok(self)
     }
 
-    pub fn merge_svc_update(&mut self, svc_update: habitat_sup_protocol::ctl::SvcUpdate) {
+    /// Merge an update into the spec. The environment files of the service are read again, even
+    /// if the update doesn't change them.
+    pub fn merge_svc_update(&mut self,
+                            svc_update: habitat_sup_protocol::ctl::SvcUpdate)
+                            -> Result<()> {
         if let Some(group) = svc_update.group {
             self.group = group;
         }
@@ -337,6 +638,92 @@ impl ServiceSpec {
         if let Some(shutdown_timeout) = svc_update.shutdown_timeout {
             self.shutdown_timeout = Some(ShutdownTimeout::from(shutdown_timeout));
         }
+        if let Some(cpu_max) = svc_update.cpu_max {
+            self.cpu_max = Some(cpu_max);
+        }
+        if let Some(memory_max) = svc_update.memory_max {
+            self.memory_max = Some(memory_max);
+        }
+        if let Some(io_weight) = svc_update.io_weight {
+            self.io_weight = Some(io_weight);
+        }
+        if let Some(svc_user) = svc_update.svc_user {
+            self.svc_user = Some(svc_user);
+        }
+        if let Some(svc_group) = svc_update.svc_group {
+            self.svc_group = Some(svc_group);
+        }
+        if let Some(log_max_bytes) = svc_update.log_max_bytes {
+            self.log_max_bytes = Some(log_max_bytes);
+        }
+        if let Some(log_max_age) = svc_update.log_max_age {
+            self.log_max_age = Some(log_max_age);
+        }
+        if let Some(log_max_files) = svc_update.log_max_files {
+            self.log_max_files = Some(log_max_files);
+        }
+        if let Some(log_forward) = svc_update.log_forward {
+            self.log_forward = Some(log_forward);
+        }
+        if let Some(stdout_to) = svc_update.stdout_to {
+            self.stdout_to = Some(stdout_to);
+        }
+        if let Some(stderr_to) = svc_update.stderr_to {
+            self.stderr_to = Some(stderr_to);
+        }
+        if let Some(health_check_timeout) = svc_update.health_check_timeout {
+            self.health_check_timeout = Some(health_check_timeout);
+        }
+        if let Some(health_check_max_backoff) = svc_update.health_check_max_backoff {
+            self.health_check_max_backoff = Some(health_check_max_backoff);
+        }
+        if let Some(preferred_leaders) = svc_update.preferred_leaders {
+            self.preferred_leaders = preferred_leaders.members;
+        }
+        if let Some(excluded_leaders) = svc_update.excluded_leaders {
+            self.excluded_leaders = excluded_leaders.members;
+        }
+        if let Some(pid_namespace) = svc_update.pid_namespace {
+            self.pid_namespace = pid_namespace;
+        }
+        if let Some(network_namespace) = svc_update.network_namespace {
+            self.network_namespace = network_namespace;
+        }
+        if let Some(ports) = svc_update.ports {
+            self.ports = ports.mappings;
+        }
+        if let Some(seccomp_profile) = svc_update.seccomp_profile {
+            self.seccomp_profile = Some(seccomp_profile);
+        }
+        if let Some(filesystem_sandbox) = svc_update.filesystem_sandbox {
+            self.filesystem_sandbox = filesystem_sandbox;
+        }
+        if let Some(fs_allow) = svc_update.fs_allow {
+            self.fs_allow = fs_allow.paths;
+        }
+        if let Some(umask) = svc_update.umask {
+            self.umask = Some(umask);
+        }
+        if let Some(working_dir) = svc_update.working_dir {
+            self.working_dir = Some(working_dir);
+        }
+        if let Some(env_files) = svc_update.env_files {
+            self.env_files = env_files.paths;
+        }
+        if let Some(ulimits) = svc_update.ulimits {
+            self.ulimits = ulimits.ulimits;
+        }
+        if let Some(nice) = svc_update.nice {
+            self.nice = Some(nice);
+        }
+        if let Some(io_priority) = svc_update.io_priority {
+            self.io_priority = Some(io_priority);
+        }
+        if let Some(listen_fds) = svc_update.listen_fds {
+            self.listen_fds = listen_fds.names;
+        }
+        self.env_vars = read_env_files(&self.env_files)?;
+        Ok(())
     }
 
     /// Given an `old` and a `new` spec, figure out what operations
@@ -428,6 +815,36 @@ impl ServiceSpec {
                         desired_state: _,
                         shutdown_timeout,
                         svc_encrypted_password,
+                        cpu_max,
+                        memory_max,
+                        io_weight,
+                        svc_user,
+                        svc_group,
+                        log_max_bytes,
+                        log_max_age,
+                        log_max_files,
+                        log_forward,
+                        stdout_to,
+                        stderr_to,
+                        health_check_timeout,
+                        health_check_max_backoff,
+                        preferred_leaders,
+                        excluded_leaders,
+                        pid_namespace,
+                        network_namespace,
+                        ports,
+                        seccomp_profile,
+                        filesystem_sandbox,
+                        fs_allow,
+                        umask,
+                        working_dir,
+                        env_files,
+                        ulimits,
+                        nice,
+                        io_priority,
+                        listen_fds,
+                        env_vars,
+                        health_check,
                         health_check_interval,
                     } = &running_spec;
 
@@ -447,15 +864,49 @@ impl ServiceSpec {
                         || group != &disk_spec.group
                         // TODO (CM): This *might* not need to be here
                         || topology != &disk_spec.topology
-                        // TODO (CM): Bind information *may* be able
-                        // to be dynamically changed, but that will
-                        // need to be investigated more deeply.
-                        || binds != &disk_spec.binds
+                        // Binds that are merely pointed at another
+                        // service group are swapped into the running
+                        // service; adding or removing binds is not.
+                        || !same_bind_names(binds, &disk_spec.binds)
                         || binding_mode != &disk_spec.binding_mode
                         || config_from != &disk_spec.config_from
                         // TODO (CM): This probably doesn't need to be here
                         || shutdown_timeout != &disk_spec.shutdown_timeout
                         || svc_encrypted_password != &disk_spec.svc_encrypted_password
+                        // Limits are applied by the Launcher when spawning
+                        || cpu_max != &disk_spec.cpu_max
+                        || memory_max != &disk_spec.memory_max
+                        || io_weight != &disk_spec.io_weight
+                        || svc_user != &disk_spec.svc_user
+                        || svc_group != &disk_spec.svc_group
+                        || log_max_bytes != &disk_spec.log_max_bytes
+                        || log_max_age != &disk_spec.log_max_age
+                        || log_max_files != &disk_spec.log_max_files
+                        || log_forward != &disk_spec.log_forward
+                        || stdout_to != &disk_spec.stdout_to
+                        || stderr_to != &disk_spec.stderr_to
+                        || health_check_timeout != &disk_spec.health_check_timeout
+                        || health_check_max_backoff != &disk_spec.health_check_max_backoff
+                        // Suitability is computed from the running service's spec
+                        || preferred_leaders != &disk_spec.preferred_leaders
+                        || excluded_leaders != &disk_spec.excluded_leaders
+                        // Isolation is applied by the Launcher when spawning
+                        || pid_namespace != &disk_spec.pid_namespace
+                        || network_namespace != &disk_spec.network_namespace
+                        || ports != &disk_spec.ports
+                        || seccomp_profile != &disk_spec.seccomp_profile
+                        || filesystem_sandbox != &disk_spec.filesystem_sandbox
+                        || fs_allow != &disk_spec.fs_allow
+                        // Process settings are applied by the Launcher when spawning
+                        || umask != &disk_spec.umask
+                        || working_dir != &disk_spec.working_dir
+                        || env_files != &disk_spec.env_files
+                        || ulimits != &disk_spec.ulimits
+                        || nice != &disk_spec.nice
+                        || io_priority != &disk_spec.io_priority
+                        || listen_fds != &disk_spec.listen_fds
+                        || env_vars != &disk_spec.env_vars
+                        || health_check != &disk_spec.health_check
                         // TODO (CM): This probably doesn't need to be here, either
                         || health_check_interval != &disk_spec.health_check_interval
                     {
@@ -472,6 +923,9 @@ impl ServiceSpec {
                         {
                             ops.insert(RefreshOperation::RestartUpdater);
                         }
+                        if binds != &disk_spec.binds {
+                            ops.insert(RefreshOperation::Rebind);
+                        }
 
                         // We should have *something* to do down
                         // here, but if we don't, let's be explicit
@@ -498,6 +952,20 @@ pub(crate) enum RefreshOperation {
     /// This can happen if a user wants to change the channel a
     /// service is updating from, for instance.
     RestartUpdater,
+    /// Re-resolve the service's binds against the census.
+    ///
+    /// This happens when a bind is pointed at a different service
+    /// group, for instance after every member of the group it was
+    /// bound to departed.
+    Rebind,
+}
+
+/// Whether both lists bind the same names, regardless of the service
+/// groups they are bound to.
+fn same_bind_names(old: &[ServiceBind], new: &[ServiceBind]) -> bool {
+    old.len() == new.len()
+    && old.iter().map(ServiceBind::name).collect::<HashSet<_>>()
+       == new.iter().map(ServiceBind::name).collect()
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -522,6 +990,35 @@ pub(crate) enum ServiceOperation {
     },
 }
 
+/// Decrypt the contents of a spec file if they were encrypted with a host key.
+/// Read the variables of environment files, those of later files overriding those of earlier ones.
+fn read_env_files(paths: &[String]) -> Result<BTreeMap<String, String>> {
+    let mut env = BTreeMap::new();
+    for path in paths {
+        let vars = env_file::read(Path::new(path)).map_err(|err| {
+                                                      Error::EnvFile(PathBuf::from(path), err)
+                                                  })?;
+        env.extend(vars);
+    }
+    Ok(env)
+}
+
+fn open_spec(host_key: Option<&HostKey>,
+             content: String)
+             -> result::Result<String, habitat_core::Error> {
+    if !HostBox::is_host_box(&content) {
+        return Ok(content);
+    }
+    let message: HostBox = content.parse()?;
+    let host_key = host_key.ok_or_else(|| {
+                               habitat_core::Error::CryptoError(format!("Spec file was encrypted \
+                                                                         with host key {}, which \
+                                                                         could not be found",
+                                                                        message.key()))
+                           })?;
+    Ok(String::from_utf8(host_key.open(&message)?)?)
+}
+
 impl FromStr for ServiceSpec {
     type Err = Error;
 
@@ -617,6 +1114,44 @@ mod test {
                    HealthCheckInterval::from_str("5").unwrap());
     }
 
+    #[test]
+    fn service_spec_with_http_health_check() {
+        let toml = r#"
+            ident = "origin/name/1.2.3/20170223130020"
+            health-check = { http = "http://localhost:8080/healthz" }
+
+            [health_check_interval]
+            secs = 5
+            nanos = 0
+            "#;
+        let mut spec = ServiceSpec::from_str(toml).unwrap();
+
+        let expected = HealthCheck::Http { http:
+                                               "http://localhost:8080/healthz".to_string(),
+                                           expected_status: 200, };
+        assert_eq!(spec.health_check, Some(expected));
+
+        let custom = HealthCheck::Http { http:            "http://localhost/".to_string(),
+                                         expected_status: 204, };
+        spec.health_check = Some(custom);
+        let toml = spec.to_toml_string().unwrap();
+        assert_eq!(ServiceSpec::from_str(&toml).unwrap(), spec);
+    }
+
+    #[test]
+    fn service_spec_with_tcp_health_check() {
+        let toml = r#"
+            ident = "origin/name/1.2.3/20170223130020"
+            health-check = { port = 5432, timeout = 3 }
+            "#;
+        let spec = ServiceSpec::from_str(toml).unwrap();
+
+        assert_eq!(spec.health_check,
+                   Some(HealthCheck::Tcp { host:    "localhost".to_string(),
+                                           port:    5432,
+                                           timeout: Some(3), }));
+    }
+
     #[test]
     fn service_spec_from_str_missing_ident() {
         let toml = r#""#;
@@ -672,24 +1207,56 @@ mod test {
     #[test]
     fn service_spec_to_toml_string() {
         let spec =
-            ServiceSpec { ident:                  PackageIdent::from_str("origin/name/1.2.3/\
-                                                                          20170223130020").unwrap(),
-                          group:                  String::from("jobs"),
-                          bldr_url:               String::from("http://example.com/depot"),
-                          channel:                ChannelIdent::unstable(),
-                          topology:               Topology::Leader,
-                          update_strategy:        UpdateStrategy::AtOnce,
-                          update_condition:       UpdateCondition::Latest,
-                          binds:                  vec![ServiceBind::from_str("cache:redis.cache@\
+            ServiceSpec { ident:
+                              PackageIdent::from_str("origin/name/1.2.3/20170223130020").unwrap(),
+                          group:                    String::from("jobs"),
+                          bldr_url:                 String::from("http://example.com/depot"),
+                          channel:                  ChannelIdent::unstable(),
+                          topology:                 Topology::Leader,
+                          update_strategy:          UpdateStrategy::AtOnce,
+                          update_condition:         UpdateCondition::Latest,
+                          binds:                    vec![ServiceBind::from_str("cache:redis.cache@\
                                                                               acmecorp").unwrap(),
                                                        ServiceBind::from_str("db:postgres.app@\
                                                                               acmecorp").unwrap(),],
-                          binding_mode:           BindingMode::Relaxed,
-                          health_check_interval:  HealthCheckInterval::from_str("123").unwrap(),
-                          config_from:            Some(PathBuf::from("/only/for/development")),
-                          desired_state:          DesiredState::Down,
-                          svc_encrypted_password: None,
-                          shutdown_timeout:       Some(ShutdownTimeout::from_str("10").unwrap()), };
+                          binding_mode:             BindingMode::Relaxed,
+                          health_check_interval:    HealthCheckInterval::from_str("123").unwrap(),
+                          config_from:              Some(PathBuf::from("/only/for/development")),
+                          desired_state:            DesiredState::Down,
+                          svc_encrypted_password:   None,
+                          shutdown_timeout:         Some(ShutdownTimeout::from_str("10").unwrap()),
+                          cpu_max:                  Some(String::from("50000 100000")),
+                          memory_max:               Some(String::from("512M")),
+                          io_weight:                None,
+                          svc_user:                 Some(String::from("tenant")),
+                          svc_group:                None,
+                          log_max_bytes:            None,
+                          log_max_age:              None,
+                          log_max_files:            None,
+                          log_forward:              None,
+                          stdout_to:                None,
+                          stderr_to:                None,
+                          health_check_timeout:     None,
+                          health_check_max_backoff: None,
+                          preferred_leaders:        Vec::new(),
+                          excluded_leaders:         Vec::new(),
+                          pid_namespace:            true,
+                          network_namespace:        true,
+                          ports:                    vec![String::from("8080:80")],
+                          seccomp_profile:          Some(String::from("default")),
+                          filesystem_sandbox:       true,
+                          fs_allow:                 vec![String::from("/srv/media:ro")],
+                          umask:                    Some(String::from("0027")),
+                          working_dir:              Some(String::from("/srv/app")),
+                          env_files:                vec![String::from("/srv/app/.env")],
+                          ulimits:                  vec![String::from("nofile=65536")],
+                          nice:                     Some(-5),
+                          io_priority:              Some(String::from("best-effort:7")),
+                          listen_fds:               vec![String::from("http")],
+                          env_vars:
+                              BTreeMap::from_iter(vec![(String::from("DB_HOST"),
+                                                        String::from("db.example.com"))]),
+                          health_check:             None, };
         let toml = spec.to_toml_string().unwrap();
 
         assert!(toml.contains(r#"ident = "origin/name/1.2.3/20170223130020""#,));
@@ -707,6 +1274,23 @@ mod test {
         assert!(toml.contains(r#"secs = 123"#));
         assert!(toml.contains(r#"nanos = 0"#));
         assert!(toml.contains(r#"shutdown_timeout = 10"#));
+        assert!(toml.contains(r#"cpu_max = "50000 100000""#));
+        assert!(toml.contains(r#"memory_max = "512M""#));
+        assert!(toml.contains(r#"svc_user = "tenant""#));
+        assert!(toml.contains(r#"pid_namespace = true"#));
+        assert!(toml.contains(r#"network_namespace = true"#));
+        assert!(toml.contains(r#""8080:80""#));
+        assert!(toml.contains(r#"seccomp_profile = "default""#));
+        assert!(toml.contains(r#"filesystem_sandbox = true"#));
+        assert!(toml.contains(r#""/srv/media:ro""#));
+        assert!(toml.contains(r#"umask = "0027""#));
+        assert!(toml.contains(r#"working_dir = "/srv/app""#));
+        assert!(toml.contains(r#""/srv/app/.env""#));
+        assert!(toml.contains(r#""nofile=65536""#));
+        assert!(toml.contains(r#"nice = -5"#));
+        assert!(toml.contains(r#"io_priority = "best-effort:7""#));
+        assert!(toml.contains(r#""http""#));
+        assert!(toml.contains(r#"DB_HOST = "db.example.com""#));
     }
 
     #[test]
@@ -841,24 +1425,54 @@ mod test {
         let tmpdir = TempDir::new().unwrap();
         let path = tmpdir.path().join("name.spec");
         let spec =
-            ServiceSpec { ident:                  PackageIdent::from_str("origin/name/1.2.3/\
-                                                                          20170223130020").unwrap(),
-                          group:                  String::from("jobs"),
-                          bldr_url:               String::from("http://example.com/depot"),
-                          channel:                ChannelIdent::unstable(),
-                          topology:               Topology::Leader,
-                          update_strategy:        UpdateStrategy::AtOnce,
-                          update_condition:       UpdateCondition::Latest,
-                          binds:                  vec![ServiceBind::from_str("cache:redis.cache@\
+            ServiceSpec { ident:
+                              PackageIdent::from_str("origin/name/1.2.3/20170223130020").unwrap(),
+                          group:                    String::from("jobs"),
+                          bldr_url:                 String::from("http://example.com/depot"),
+                          channel:                  ChannelIdent::unstable(),
+                          topology:                 Topology::Leader,
+                          update_strategy:          UpdateStrategy::AtOnce,
+                          update_condition:         UpdateCondition::Latest,
+                          binds:                    vec![ServiceBind::from_str("cache:redis.cache@\
                                                                               acmecorp").unwrap(),
                                                        ServiceBind::from_str("db:postgres.app@\
                                                                               acmecorp").unwrap(),],
-                          binding_mode:           BindingMode::Relaxed,
-                          health_check_interval:  HealthCheckInterval::from_str("23").unwrap(),
-                          config_from:            Some(PathBuf::from("/only/for/development")),
-                          desired_state:          DesiredState::Down,
-                          svc_encrypted_password: None,
-                          shutdown_timeout:       Some(ShutdownTimeout::default()), };
+                          binding_mode:             BindingMode::Relaxed,
+                          health_check_interval:    HealthCheckInterval::from_str("23").unwrap(),
+                          config_from:              Some(PathBuf::from("/only/for/development")),
+                          desired_state:            DesiredState::Down,
+                          svc_encrypted_password:   None,
+                          shutdown_timeout:         Some(ShutdownTimeout::default()),
+                          cpu_max:                  None,
+                          memory_max:               None,
+                          io_weight:                None,
+                          svc_user:                 None,
+                          svc_group:                None,
+                          log_max_bytes:            None,
+                          log_max_age:              None,
+                          log_max_files:            None,
+                          log_forward:              None,
+                          stdout_to:                None,
+                          stderr_to:                None,
+                          health_check_timeout:     None,
+                          health_check_max_backoff: None,
+                          preferred_leaders:        Vec::new(),
+                          excluded_leaders:         Vec::new(),
+                          pid_namespace:            false,
+                          network_namespace:        false,
+                          ports:                    Vec::new(),
+                          seccomp_profile:          None,
+                          filesystem_sandbox:       false,
+                          fs_allow:                 Vec::new(),
+                          umask:                    None,
+                          working_dir:              None,
+                          env_files:                Vec::new(),
+                          ulimits:                  Vec::new(),
+                          nice:                     None,
+                          io_priority:              None,
+                          listen_fds:               Vec::new(),
+                          env_vars:                 BTreeMap::new(),
+                          health_check:             None, };
         spec.to_file(&path).unwrap();
         let toml = string_from_file(path);
 
@@ -878,6 +1492,23 @@ mod test {
         assert!(toml.contains(r#"nanos = 0"#));
     }
 
+    #[test]
+    fn open_spec_passes_plaintext_through() {
+        let toml = "ident = \"origin/name\"\n".to_string();
+        assert_eq!(open_spec(None, toml.clone()).unwrap(), toml);
+    }
+
+    #[test]
+    fn open_spec_decrypts_with_host_key() {
+        let host_key = HostKey::new("host");
+        let toml = "ident = \"origin/name\"\n";
+        let content = host_key.seal(toml.as_bytes()).to_string();
+        assert!(!content.contains("origin/name"));
+        assert_eq!(open_spec(Some(&host_key), content.clone()).unwrap(), toml);
+        assert!(open_spec(None, content.clone()).is_err());
+        assert!(open_spec(Some(&HostKey::new("other")), content).is_err());
+    }
+
     #[test]
     fn service_spec_to_file_invalid_ident() {
         let tmpdir = TempDir::new().unwrap();
@@ -1030,6 +1661,50 @@ mod test {
                    HealthCheckInterval::from_str("5").unwrap());
     }
 
+    #[test]
+    fn leader_affinity_matches_member_ids_and_tags() {
+        let mut spec = ServiceSpec::new(PackageIdent::from_str("origin/name").unwrap());
+        let tags = vec!["primary-dc".to_string()];
+        assert_eq!(spec.leader_affinity("abc123", &tags), None);
+
+        spec.preferred_leaders = vec!["primary-dc".to_string()];
+        spec.excluded_leaders = vec!["def456".to_string()];
+        assert_eq!(spec.leader_affinity("abc123", &tags),
+                   Some(LeaderAffinity::Preferred));
+        assert_eq!(spec.leader_affinity("abc123", &[]),
+                   Some(LeaderAffinity::Neutral));
+        assert_eq!(spec.leader_affinity("def456", &tags),
+                   Some(LeaderAffinity::Excluded));
+    }
+
+    #[test]
+    fn env_files_are_read_again_on_update() {
+        use habitat_sup_protocol::ctl::{EnvFileList,
+                                        SvcUpdate};
+
+        let tmpdir = TempDir::new().unwrap();
+        let path = tmpdir.path().join("app.env");
+        file_from_str(&path, "DB_HOST=db.example.com\nDB_PORT=5432\n");
+        let mut spec = ServiceSpec::new(PackageIdent::from_str("origin/name").unwrap());
+        let paths = vec![path.to_string_lossy().to_string()];
+        let update = SvcUpdate { env_files: Some(EnvFileList { paths }),
+                                 ..Default::default() };
+        spec.merge_svc_update(update).unwrap();
+        assert_eq!(spec.env_vars["DB_HOST"], "db.example.com");
+        assert_eq!(spec.env_vars["DB_PORT"], "5432");
+
+        file_from_str(&path, "DB_HOST=db2.example.com\n");
+        spec.merge_svc_update(SvcUpdate::default()).unwrap();
+        assert_eq!(spec.env_vars["DB_HOST"], "db2.example.com");
+        assert!(!spec.env_vars.contains_key("DB_PORT"));
+
+        file_from_str(&path, "not an assignment\n");
+        match spec.merge_svc_update(SvcUpdate::default()) {
+            Err(EnvFile(..)) => (),
+            other => panic!("Expected an environment file error, got {:?}", other),
+        }
+    }
+
     mod reconcile {
         use super::*;
 
@@ -1153,11 +1828,157 @@ mod test {
                    restart,
                    svc_encrypted_password,
                    Some("monkeys".to_string()));
+        reconcile!(cpu_max_causes_restart,
+                   restart,
+                   cpu_max,
+                   Some("max".to_string()));
+        reconcile!(memory_max_causes_restart,
+                   restart,
+                   memory_max,
+                   Some("512M".to_string()));
+        reconcile!(io_weight_causes_restart, restart, io_weight, Some(500));
+        reconcile!(svc_user_causes_restart,
+                   restart,
+                   svc_user,
+                   Some("tenant".to_string()));
+        reconcile!(svc_group_causes_restart,
+                   restart,
+                   svc_group,
+                   Some("tenants".to_string()));
+        reconcile!(log_max_bytes_causes_restart,
+                   restart,
+                   log_max_bytes,
+                   Some(1024));
+        reconcile!(log_max_age_causes_restart, restart, log_max_age, Some(3600));
+        reconcile!(log_max_files_causes_restart,
+                   restart,
+                   log_max_files,
+                   Some(0));
+        reconcile!(log_forward_causes_restart,
+                   restart,
+                   log_forward,
+                   Some("journald".to_string()));
+        reconcile!(stdout_to_causes_restart,
+                   restart,
+                   stdout_to,
+                   Some("events".to_string()));
+        reconcile!(stderr_to_causes_restart,
+                   restart,
+                   stderr_to,
+                   Some("file".to_string()));
+        reconcile!(health_check_timeout_causes_restart,
+                   restart,
+                   health_check_timeout,
+                   Some(5));
+        reconcile!(health_check_max_backoff_causes_restart,
+                   restart,
+                   health_check_max_backoff,
+                   Some(300));
+        reconcile!(preferred_leaders_causes_restart,
+                   restart,
+                   preferred_leaders,
+                   vec!["primary-dc".to_string()]);
+        reconcile!(excluded_leaders_causes_restart,
+                   restart,
+                   excluded_leaders,
+                   vec!["backup-dc".to_string()]);
+        reconcile!(pid_namespace_causes_restart, restart, pid_namespace, true);
+        reconcile!(network_namespace_causes_restart,
+                   restart,
+                   network_namespace,
+                   true);
+        reconcile!(ports_causes_restart,
+                   restart,
+                   ports,
+                   vec!["8080:80".to_string()]);
+        reconcile!(seccomp_profile_causes_restart,
+                   restart,
+                   seccomp_profile,
+                   Some("default".to_string()));
+        reconcile!(filesystem_sandbox_causes_restart,
+                   restart,
+                   filesystem_sandbox,
+                   true);
+        reconcile!(fs_allow_causes_restart,
+                   restart,
+                   fs_allow,
+                   vec!["/srv/media:ro".to_string()]);
+        reconcile!(umask_causes_restart,
+                   restart,
+                   umask,
+                   Some("0027".to_string()));
+        reconcile!(working_dir_causes_restart,
+                   restart,
+                   working_dir,
+                   Some("/srv/app".to_string()));
+        reconcile!(env_files_causes_restart,
+                   restart,
+                   env_files,
+                   vec!["/srv/app/.env".to_string()]);
+        reconcile!(ulimits_causes_restart,
+                   restart,
+                   ulimits,
+                   vec!["nofile=65536".to_string()]);
+        reconcile!(nice_causes_restart, restart, nice, Some(-5));
+        reconcile!(io_priority_causes_restart,
+                   restart,
+                   io_priority,
+                   Some("idle".to_string()));
+        reconcile!(listen_fds_causes_restart,
+                   restart,
+                   listen_fds,
+                   vec!["http".to_string()]);
+        reconcile!(env_vars_causes_restart,
+                   restart,
+                   env_vars,
+                   BTreeMap::from_iter(vec![("DB_HOST".to_string(),
+                                             "db.example.com".to_string())]));
+        reconcile!(health_check_causes_restart,
+                   restart,
+                   health_check,
+                   Some(HealthCheck::Http { http:            "http://localhost/".to_string(),
+                                            expected_status: 200, }));
         reconcile!(health_check_interval_causes_restart,
                    restart,
                    health_check_interval,
                    10000.into());
 
+        #[test]
+        fn rebinding_to_another_group_causes_update() {
+            let running = {
+                let mut s = spec("core/blah", DesiredState::Up);
+                s.binds = vec![ServiceBind::new("db", "postgres.default".parse().unwrap())];
+                s
+            };
+            let disk = {
+                let mut s = running.clone();
+                s.binds = vec![ServiceBind::new("db", "postgres.replacement".parse().unwrap())];
+                s
+            };
+
+            let ops = HashSet::from_iter(vec![RefreshOperation::Rebind]);
+            assert_eq!(ServiceSpec::reconcile(Some(running), Some(disk.clone())),
+                       Some(ServiceOperation::Update(disk, ops)));
+        }
+
+        #[test]
+        fn renaming_a_bind_causes_restart() {
+            let running = {
+                let mut s = spec("core/blah", DesiredState::Up);
+                s.binds = vec![ServiceBind::new("db", "postgres.default".parse().unwrap())];
+                s
+            };
+            let disk = {
+                let mut s = running.clone();
+                s.binds = vec![ServiceBind::new("database", "postgres.default".parse().unwrap())];
+                s
+            };
+
+            assert_eq!(ServiceSpec::reconcile(Some(running.clone()), Some(disk.clone())),
+                       Some(ServiceOperation::Restart { to_stop:  running,
+                                                        to_start: disk, }));
+        }
+
         reconcile!(bldr_url_causes_update,
                    update,
                    bldr_url,
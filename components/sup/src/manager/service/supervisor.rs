@@ -8,21 +8,29 @@ use super::{terminator,
 use crate::{error::{Error,
                     Result},
             manager::{ServicePidSource,
-                      ShutdownConfig}};
+                      ShutdownConfig},
+            sys::{self,
+                  ServiceResourceUsage}};
 use habitat_common::{outputln,
                      templating::package::Pkg,
                      types::UserInfo};
+#[cfg(not(windows))]
+use habitat_core::crypto::svc_password;
 #[cfg(unix)]
 use habitat_core::os::users;
 use habitat_core::{fs,
                    fs::{AtomicWriter,
                         Permissions},
-                   os::process::{self,
-                                 Pid},
+                   os::{cgroup,
+                        isolation::Isolation,
+                        process::{self,
+                                  Pid,
+                                  ProcessSettings}},
                    service::ServiceGroup};
 #[cfg(windows)]
 use habitat_launcher_client::Error as launcher_error;
-use habitat_launcher_client::LauncherCli;
+use habitat_launcher_client::{LauncherCli,
+                              ServiceOutput};
 #[cfg(windows)]
 use habitat_launcher_protocol::{self as protocol,
                                 Error as launcher_protocol_error};
@@ -31,7 +39,8 @@ use serde::{ser::SerializeStruct,
             Serializer};
 #[cfg(windows)]
 use std::env;
-use std::{fs::File,
+use std::{collections::BTreeMap,
+          fs::File,
           io::{BufRead,
                BufReader,
                Write},
@@ -77,6 +86,9 @@ pub struct Supervisor {
     /// Regardless of the value of `pid_source`, the current PID will
     /// always be written to this path, for use by service hooks.
     pid_file:      PathBuf,
+    /// The resources used by the service's processes when they were last sampled, while the
+    /// process has stayed up.
+    usage:         Option<ServiceResourceUsage>,
 }
 
 impl Supervisor {
@@ -93,18 +105,29 @@ impl Supervisor {
                      state_entered: SystemTime::now(),
                      pid_source,
                      pid: None,
-                     pid_file }
+                     pid_file,
+                     usage: None }
     }
 
     /// Check if the child process is running
+    ///
+    /// When the process was started by the Launcher before this Supervisor did, its state is
+    /// considered to have been entered when the Launcher started it, so the uptime we report
+    /// survives a Supervisor restart.
     pub fn check_process(&mut self, launcher: &LauncherCli) -> bool {
+        let mut started_at = None;
         self.pid = self.pid
                        .or_else(|| {
                            if self.pid_source == ServicePidSource::Files {
                                read_pid(&self.pid_file)
                            } else {
-                               match launcher.pid_of(&self.service_group.to_string()) {
-                                   Ok(maybe_pid) => maybe_pid,
+                               match launcher.process_of(&self.service_group.to_string()) {
+                                   Ok(maybe_process) => {
+                                       maybe_process.map(|process| {
+                                                        started_at = process.started_at;
+                                                        process.pid
+                                                    })
+                                   }
                                    Err(e) => {
                                        error!("Error getting pid from launcher: {:?}", e);
                                        None
@@ -123,6 +146,9 @@ impl Supervisor {
 
         if self.pid.is_some() {
             self.change_state(ProcessState::Up);
+            if let Some(started_at) = started_at {
+                self.state_entered = started_at;
+            }
         } else {
             self.change_state(ProcessState::Down);
             Self::cleanup_pidfile(&self.pid_file);
@@ -233,11 +259,17 @@ impl Supervisor {
                       ..Default::default() })
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn start(&mut self,
                  pkg: &Pkg,
                  group: &ServiceGroup,
                  launcher: &LauncherCli,
-                 svc_password: Option<&str>)
+                 svc_password: Option<&str>,
+                 env_vars: &BTreeMap<String, String>,
+                 limits: &cgroup::Limits,
+                 isolation: &Isolation,
+                 settings: &ProcessSettings,
+                 output: &ServiceOutput)
                  -> Result<()> {
         let user_info = self.user_info(&pkg, launcher)?;
         outputln!(preamble self.service_group,
@@ -264,11 +296,31 @@ impl Supervisor {
         // Launcher versions on Linux (and current Windows versions)
         // will use these, while newer versions will prefer the UID
         // and GID, ignoring the names.
+
+        // The variables of the service's environment files are only
+        // handed to the service itself, rather than added to the
+        // package's environment, which is exposed by the HTTP gateway.
+        let mut env = (*pkg.env).clone();
+        env.extend(env_vars.iter().map(|(k, v)| (k.clone(), v.clone())));
+        // Outside of Windows, the password isn't for a user the service runs as but a secret
+        // handed to it through its environment.
+        #[cfg(not(windows))]
+        let svc_password = {
+            if let Some(password) = svc_password {
+                env.insert(svc_password::SVC_PASSWORD_ENVVAR.to_string(),
+                           svc_password::decrypt(password)?);
+            }
+            None
+        };
         let pid = launcher.spawn(&group,
                                  &pkg.svc_run,
                                  user_info,
                                  svc_password, // Windows optional
-                                 (*pkg.env).clone())?;
+                                 env,
+                                 limits,
+                                 isolation,
+                                 settings,
+                                 output)?;
         if pid == 0 {
             warn!(target: "pidfile_tracing", "Spawned service for {} has a PID of 0!", group);
         }
@@ -281,6 +333,26 @@ impl Supervisor {
     /// Is the process up or down?
     pub fn status(&self) -> ProcessState { self.state }
 
+    /// The resource usage of the service, if it is running in its own
+    /// cgroup.
+    pub fn resource_usage(&self) -> Option<cgroup::Usage> {
+        if self.state == ProcessState::Up && cgroup::is_available() {
+            cgroup::usage(&self.service_group).ok()
+        } else {
+            None
+        }
+    }
+
+    /// Sample the resources used by the service's processes, if it is running. The sample is
+    /// kept to be reported along with the state of the process.
+    pub fn sample_usage(&mut self) -> Option<ServiceResourceUsage> {
+        self.usage = match (self.state, self.pid) {
+            (ProcessState::Up, Some(pid)) => sys::process::service_resource_usage(pid),
+            _ => None,
+        };
+        self.usage.clone()
+    }
+
     /// Returns a future that stops a service asynchronously.
     pub fn stop(&self, shutdown_config: ShutdownConfig) {
         let service_group = self.service_group.clone();
@@ -347,6 +419,7 @@ impl Supervisor {
         }
         self.state = state;
         self.state_entered = SystemTime::now();
+        self.usage = None;
     }
 
     pub fn state_entered(&self) -> SystemTime { self.state_entered }
@@ -367,8 +440,10 @@ impl Serialize for Supervisor {
     {
         let mut strukt = serializer.serialize_struct("supervisor", 5)?;
         strukt.serialize_field("pid", &self.pid)?;
+        strukt.serialize_field("resource_usage", &self.resource_usage())?;
         strukt.serialize_field("state", &self.state)?;
         strukt.serialize_field("state_entered", &self.since_epoch().as_secs())?;
+        strukt.serialize_field("usage", &self.usage)?;
         strukt.end()
     }
 }
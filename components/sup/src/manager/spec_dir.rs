@@ -3,9 +3,12 @@ use crate::error::{Error,
                    Result};
 use habitat_common::outputln;
 use std::{ffi::OsStr,
+          fs,
           iter::IntoIterator,
           path::{Path,
-                 PathBuf}};
+                 PathBuf},
+          time::{Duration,
+                 SystemTime}};
 
 static LOGKEY: &str = "SD";
 const SPEC_FILE_EXT: &str = "spec";
@@ -92,6 +95,19 @@ impl SpecDir {
         specs
     }
 
+    /// How long the oldest spec file changed after `reconciled_at` has been waiting to be
+    /// reconciled with the running services.
+    pub fn reconciliation_lag(&self, reconciled_at: SystemTime) -> Duration {
+        let now = SystemTime::now();
+        self.spec_files()
+            .into_iter()
+            .filter_map(|p| fs::metadata(p).and_then(|m| m.modified()).ok())
+            .filter(|modified| *modified > reconciled_at)
+            .filter_map(|modified| now.duration_since(modified).ok())
+            .max()
+            .unwrap_or_default()
+    }
+
     /// Return the list of all spec files in the directory
     fn spec_files(&self) -> impl IntoIterator<Item = PathBuf> {
         glob::glob(&self.0.join(SPEC_FILE_GLOB).display().to_string())
@@ -1,7 +1,12 @@
-use crate::VERSION;
+mod cloud;
+
+pub use self::cloud::CloudMetadata;
+use crate::{sys::process,
+            VERSION};
 use habitat_butterfly::rumor::service::SysInfo;
 use habitat_common::{outputln,
-                     types::{GossipListenAddr,
+                     types::{CloudProvider,
+                             GossipListenAddr,
                              HttpListenAddr,
                              ListenCtlAddr}};
 use std::{net::{IpAddr,
@@ -16,6 +21,18 @@ pub struct Sys {
     pub member_id:         String,
     pub ip:                IpAddr,
     pub hostname:          String,
+    /// The fully qualified domain name of the host, or its hostname if it has none
+    #[serde(default)]
+    pub fqdn:              String,
+    /// The number of logical CPUs of the host
+    #[serde(default)]
+    pub cpu_count:         usize,
+    /// The total physical memory of the host, in bytes, if it can be determined
+    #[serde(default)]
+    pub memory_bytes:      Option<u64>,
+    /// The metadata of the cloud instance the host is, when `--sys-cloud-metadata` is given
+    #[serde(default)]
+    pub cloud:             Option<CloudMetadata>,
     pub gossip_ip:         IpAddr,
     pub gossip_port:       u16,
     pub ctl_gateway_ip:    IpAddr,
@@ -23,6 +40,9 @@ pub struct Sys {
     pub http_gateway_ip:   IpAddr,
     pub http_gateway_port: u16,
     pub permanent:         bool,
+    /// Tags describing this Supervisor, matched against services' leader affinity
+    #[serde(default)]
+    pub tags:              Vec<String>,
 }
 
 impl Sys {
@@ -40,17 +60,32 @@ impl Sys {
                                                                   e);
                                                         host
                                                     });
+        let fqdn = habitat_core::os::net::fqdn().unwrap_or_else(|| host.clone());
         Self { version: VERSION.to_string(),
                member_id: "unloaded".to_string(),
                ip,
                hostname: host,
+               fqdn,
+               cpu_count: num_cpus::get(),
+               memory_bytes: process::total_memory_bytes(),
+               cloud: None,
                gossip_ip: gossip.ip(),
                gossip_port: gossip.port(),
                ctl_gateway_ip: ctl.ip(),
                ctl_gateway_port: ctl.port(),
                http_gateway_ip: http.ip(),
                http_gateway_port: http.port(),
-               permanent }
+               permanent,
+               tags: Vec::new() }
+    }
+
+    /// Read the metadata of the cloud instance the Supervisor runs on into `cloud`.
+    pub async fn load_cloud_metadata(&mut self, provider: CloudProvider) {
+        self.cloud = cloud::metadata(provider).await;
+        if self.cloud.is_none() {
+            outputln!("Unable to read the {} instance metadata; sys.cloud is not set",
+                      provider);
+        }
     }
 
     pub fn as_sys_info(&self) -> SysInfo {
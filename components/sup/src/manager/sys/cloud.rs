@@ -0,0 +1,111 @@
+//! The metadata of the cloud instance the Supervisor runs on, read from the instance metadata
+//! service of its cloud provider for the `sys.cloud` template data.
+
+use habitat_common::types::CloudProvider;
+use habitat_http_client::ApiClient;
+use serde_json::Value as Json;
+use std::time::Duration;
+
+/// How long to wait for an instance metadata service. They answer quickly, but only on instances
+/// of their cloud.
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+const AWS_ENDPOINT: &str = "http://169.254.169.254/latest/";
+const AZURE_ENDPOINT: &str =
+    "http://169.254.169.254/metadata/instance/compute?api-version=2021-02-01";
+const GCP_ENDPOINT: &str = "http://metadata.google.internal/computeMetadata/v1/instance/";
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct CloudMetadata {
+    pub provider:          CloudProvider,
+    pub instance_id:       String,
+    pub instance_type:     String,
+    pub region:            String,
+    pub availability_zone: String,
+}
+
+/// Read the metadata of the instance from the metadata service of `provider`, or `None` if it
+/// can't be read.
+pub async fn metadata(provider: CloudProvider) -> Option<CloudMetadata> {
+    match provider {
+        CloudProvider::Aws => aws().await,
+        CloudProvider::Azure => azure().await,
+        CloudProvider::Gcp => gcp().await,
+    }
+}
+
+fn client(endpoint: &str) -> Option<ApiClient> {
+    ApiClient::new(endpoint, crate::PRODUCT, crate::VERSION, None).map_err(|e| debug!("{}", e))
+                                                                  .ok()
+}
+
+/// GET `path` with a header, returning the body of a successful response.
+async fn get(client: &ApiClient, path: &str, header: (&str, &str)) -> Option<String> {
+    let response =
+        client.get(path)
+              .header(header.0, header.1)
+              .timeout(TIMEOUT)
+              .send()
+              .await
+              .map_err(|e| debug!("Instance metadata request for {} failed: {}", path, e))
+              .ok()?;
+    if !response.status().is_success() {
+        debug!("Instance metadata request for {} failed: {}",
+               path,
+               response.status());
+        return None;
+    }
+    response.text().await.ok()
+}
+
+/// Read the metadata of an EC2 instance with IMDSv2, which requires a session token.
+async fn aws() -> Option<CloudMetadata> {
+    let client = client(AWS_ENDPOINT)?;
+    let response = client.put("api/token")
+                         .header("X-aws-ec2-metadata-token-ttl-seconds", "60")
+                         .timeout(TIMEOUT)
+                         .send()
+                         .await
+                         .ok()?;
+    let token = response.text().await.ok()?;
+    let header = ("X-aws-ec2-metadata-token", token.as_str());
+    let instance_id = get(&client, "meta-data/instance-id", header).await?;
+    let instance_type = get(&client, "meta-data/instance-type", header).await?;
+    let region = get(&client, "meta-data/placement/region", header).await?;
+    let availability_zone = get(&client, "meta-data/placement/availability-zone", header).await?;
+    Some(CloudMetadata { provider: CloudProvider::Aws,
+                         instance_id,
+                         instance_type,
+                         region,
+                         availability_zone })
+}
+
+async fn azure() -> Option<CloudMetadata> {
+    let client = client(AZURE_ENDPOINT)?;
+    // The API version is part of the endpoint, as the path of a request can't carry a query.
+    let compute = get(&client, "", ("Metadata", "true")).await?;
+    let compute: Json = serde_json::from_str(&compute).ok()?;
+    let field = |name: &str| compute[name].as_str().unwrap_or_default().to_string();
+    Some(CloudMetadata { provider:          CloudProvider::Azure,
+                         instance_id:       field("vmId"),
+                         instance_type:     field("vmSize"),
+                         region:            field("location"),
+                         availability_zone: field("zone"), })
+}
+
+async fn gcp() -> Option<CloudMetadata> {
+    let client = client(GCP_ENDPOINT)?;
+    let header = ("Metadata-Flavor", "Google");
+    // The machine type and zone are given as paths, e.g.
+    // `projects/123/zones/us-central1-a`.
+    let last_segment = |path: String| path.rsplit('/').next().unwrap_or_default().to_string();
+    let zone = last_segment(get(&client, "zone", header).await?);
+    let region = zone.rsplitn(2, '-').nth(1).unwrap_or_default().to_string();
+    let instance_id = get(&client, "id", header).await?;
+    let instance_type = last_segment(get(&client, "machine-type", header).await?);
+    Some(CloudMetadata { provider: CloudProvider::Gcp,
+                         instance_id,
+                         instance_type,
+                         region,
+                         availability_zone: zone })
+}
@@ -0,0 +1,112 @@
+//! Tells systemd how the Supervisor is doing, when it runs in a unit of `Type=notify`.
+//!
+//! The Supervisor reports `READY=1` once the specs on disk were first reconciled with the running
+//! services, `RELOADING=1` before restarting, and `STOPPING=1` before stopping its services. When
+//! the unit has a `WatchdogSec=`, it also sends `WATCHDOG=1` from its main loop at half that
+//! interval, so that systemd restarts a Supervisor whose main loop hangs.
+//!
+//! The Supervisor runs as a child of the Launcher, the unit's main process, so the unit needs
+//! `NotifyAccess=all` for systemd to accept its notifications.
+
+#[cfg(unix)]
+use std::os::unix::net::UnixDatagram;
+use std::{env,
+          io,
+          path::{Path,
+                 PathBuf},
+          time::{Duration,
+                 Instant}};
+
+/// The socket systemd listens for notifications on.
+const NOTIFY_SOCKET_ENV: &str = "NOTIFY_SOCKET";
+/// The watchdog interval of the unit, in microseconds.
+const WATCHDOG_USEC_ENV: &str = "WATCHDOG_USEC";
+/// The process the watchdog interval is meant for.
+const WATCHDOG_PID_ENV: &str = "WATCHDOG_PID";
+
+pub struct SystemdNotifier {
+    socket:   Option<PathBuf>,
+    watchdog: Option<Duration>,
+    next:     Instant,
+}
+
+impl SystemdNotifier {
+    /// A notifier for the unit the Supervisor runs in, which does nothing when it doesn't run
+    /// under systemd. `launcher_pid` is the PID of the Launcher, if the Supervisor runs under one.
+    pub fn from_env(launcher_pid: Option<u32>) -> Self {
+        let socket = match env::var_os(NOTIFY_SOCKET_ENV) {
+            Some(socket) if socket.to_string_lossy().starts_with('@') => {
+                warn!("Not notifying systemd; abstract notification sockets are not supported");
+                None
+            }
+            Some(socket) if !socket.is_empty() => Some(PathBuf::from(socket)),
+            _ => None,
+        };
+        let watchdog = socket.as_ref().and(watchdog_interval(launcher_pid));
+        SystemdNotifier { socket,
+                          watchdog,
+                          next: Instant::now() }
+    }
+
+    /// Tell systemd that the Supervisor is up and its services were started.
+    pub fn ready(&self) { self.notify("READY=1"); }
+
+    /// Tell systemd that the Supervisor is restarting and will be ready again shortly.
+    pub fn reloading(&self) { self.notify("RELOADING=1"); }
+
+    /// Tell systemd that the Supervisor is stopping its services and shutting down.
+    pub fn stopping(&self) { self.notify("STOPPING=1"); }
+
+    /// Send systemd a watchdog heartbeat, if one is due.
+    pub fn check_watchdog(&mut self) {
+        let interval = match self.watchdog {
+            Some(interval) => interval,
+            None => return,
+        };
+        let now = Instant::now();
+        if now < self.next {
+            return;
+        }
+        self.next = now + interval / 2;
+        self.notify("WATCHDOG=1");
+    }
+
+    fn notify(&self, state: &str) {
+        if let Some(ref socket) = self.socket {
+            if let Err(err) = send(socket, state) {
+                warn!("Unable to notify systemd of {} at {}: {}",
+                      state,
+                      socket.display(),
+                      err);
+            }
+        }
+    }
+}
+
+/// The watchdog interval of the unit, if it has one meant for the Supervisor or its Launcher.
+fn watchdog_interval(launcher_pid: Option<u32>) -> Option<Duration> {
+    let usec = env::var(WATCHDOG_USEC_ENV).ok()?.parse::<u64>().ok()?;
+    if usec == 0 {
+        return None;
+    }
+    if let Ok(pid) = env::var(WATCHDOG_PID_ENV) {
+        let pid = pid.parse::<u32>().ok()?;
+        if pid != std::process::id() && Some(pid) != launcher_pid {
+            return None;
+        }
+    }
+    Some(Duration::from_micros(usec))
+}
+
+#[cfg(unix)]
+fn send(socket: &Path, state: &str) -> io::Result<()> {
+    UnixDatagram::unbound()?.send_to(state.as_bytes(), socket)
+                            .map(|_| ())
+}
+
+#[cfg(windows)]
+fn send(_socket: &Path, _state: &str) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Other,
+                       "systemd notifications are not available \
+                        on Windows"))
+}
@@ -0,0 +1,332 @@
+//! Secrets that services reference from their configuration.
+//!
+//! Any string value in a service's default or user configuration may refer to a secret stored in
+//! [Vault][1] with a value of the form `vault:<path>#<field>`, for example
+//! `vault:secret/data/postgres#password`. When the Supervisor has been given a Vault server with
+//! `--vault-url`, it reads the referenced secrets and substitutes them into the configuration
+//! the service's templates are rendered with. Secrets are only ever part of that rendered
+//! configuration; the configuration a Supervisor gossips to its peers, or serves from its HTTP
+//! gateway, still holds the references. References in configuration applied through gossip are
+//! left alone, as any member of the ring could otherwise make a service read any secret the
+//! Supervisor has access to.
+//!
+//! Each secret is kept fresh in the background, being read again before its lease runs out (or
+//! periodically, if it isn't leased), for as long as a loaded service refers to it. Whenever a
+//! secret changes, the services that refer to it have their configuration re-rendered.
+//!
+//! [1]:https://www.vaultproject.io
+
+mod error;
+mod vault;
+
+use self::vault::VaultClient;
+pub use self::{error::{Error,
+                       Result},
+               vault::VaultConfig};
+use futures::future::{self,
+                      AbortHandle};
+use habitat_common::templating::config::Cfg;
+use habitat_core::service::ServiceGroup;
+use parking_lot::Mutex;
+use state::Storage;
+use std::{collections::{HashMap,
+                        HashSet},
+          sync::{atomic::{AtomicU64,
+                          Ordering},
+                 Arc},
+          time::Duration};
+use tokio::time;
+
+/// The prefix marking a configuration value as a reference to a secret in Vault.
+const VAULT_PREFIX: &str = "vault:";
+/// How often a secret that isn't leased is read again.
+const DEFAULT_REFRESH_INTERVAL: Duration = Duration::from_secs(300);
+/// How long to wait before trying to read a secret again, after failing to.
+const RETRY_INTERVAL: Duration = Duration::from_secs(10);
+
+lazy_static! {
+    static ref SECRETS: Storage<Secrets> = Storage::new();
+}
+
+/// The fields of each referenced secret, by path. A path that maps to `None` has not been read
+/// yet.
+type Cache = Arc<Mutex<HashMap<String, Option<HashMap<String, String>>>>>;
+
+struct Secrets {
+    client:     Arc<VaultClient>,
+    cache:      Cache,
+    /// Bumped every time the fields of any secret change.
+    generation: Arc<AtomicU64>,
+    /// The paths of the secrets each service refers to.
+    references: Mutex<HashMap<ServiceGroup, HashSet<String>>>,
+    /// The background tasks keeping each secret fresh, by path.
+    refreshes:  Mutex<HashMap<String, AbortHandle>>,
+}
+
+/// Sets up reading the secrets referenced in service configuration from the given Vault server.
+pub fn init(config: VaultConfig) -> Result<()> {
+    // Only initialize once
+    if SECRETS.try_get().is_none() {
+        let client = VaultClient::new(config)?;
+        SECRETS.set(Secrets { client:     Arc::new(client),
+                              cache:      Arc::default(),
+                              generation: Arc::default(),
+                              references: Mutex::default(),
+                              refreshes:  Mutex::default(), });
+    }
+    Ok(())
+}
+
+/// A number that changes whenever any secret does, so callers can tell whether configuration
+/// resolved earlier has gone stale.
+pub fn generation() -> u64 {
+    SECRETS.try_get()
+           .map(|secrets| secrets.generation.load(Ordering::Relaxed))
+           .unwrap_or_default()
+}
+
+/// The result of substituting secrets into a service's configuration.
+pub enum Resolved {
+    /// The configuration doesn't refer to any secrets (or no secrets provider was configured),
+    /// so it can be used as is.
+    NoReferences,
+    /// The configuration, with every secret reference replaced by the secret.
+    Cfg(Cfg),
+    /// Some referenced secrets haven't been read yet.
+    Pending,
+}
+
+/// Substitute the secrets referenced in the default and user layers of `cfg`, the configuration
+/// of `service_group`, starting to read any that haven't been read yet.
+pub fn resolve(service_group: &ServiceGroup, cfg: &Cfg) -> Resolved {
+    let secrets = match SECRETS.try_get() {
+        Some(secrets) => secrets,
+        None => return Resolved::NoReferences,
+    };
+    let mut cfg = cfg.clone();
+    let mut paths = HashSet::new();
+    let mut unread = Vec::new();
+    let mut pending = false;
+    {
+        let mut cache = secrets.cache.lock();
+        let mut lookup = |reference: &SecretRef| {
+            paths.insert(reference.path.to_string());
+            match cache.get(reference.path) {
+                Some(Some(fields)) => {
+                    let value = fields.get(reference.field).cloned();
+                    if value.is_none() {
+                        warn!("The secret at '{}' has no field '{}'",
+                              reference.path, reference.field);
+                    }
+                    value
+                }
+                Some(None) => {
+                    pending = true;
+                    None
+                }
+                None => {
+                    cache.insert(reference.path.to_string(), None);
+                    unread.push(reference.path.to_string());
+                    pending = true;
+                    None
+                }
+            }
+        };
+        for layer in vec![&mut cfg.default, &mut cfg.user].into_iter().flatten() {
+            for value in layer.values_mut() {
+                resolve_value(value, &mut lookup);
+            }
+        }
+    }
+    let found_reference = !paths.is_empty();
+    secrets.references
+           .lock()
+           .insert(service_group.clone(), paths);
+    secrets.prune();
+    for path in unread {
+        secrets.keep_fresh(path);
+    }
+    if pending {
+        Resolved::Pending
+    } else if found_reference {
+        Resolved::Cfg(cfg)
+    } else {
+        Resolved::NoReferences
+    }
+}
+
+/// Stop keeping fresh the secrets only `service_group` referred to, once it has been stopped.
+pub fn release(service_group: &ServiceGroup) {
+    if let Some(secrets) = SECRETS.try_get() {
+        secrets.references.lock().remove(service_group);
+        secrets.prune();
+    }
+}
+
+impl Secrets {
+    /// Read the secret at `path` now, and again whenever it needs refreshing, until no service
+    /// refers to it any more.
+    fn keep_fresh(&self, path: String) {
+        let client = Arc::clone(&self.client);
+        let cache = Arc::clone(&self.cache);
+        let generation = Arc::clone(&self.generation);
+        let refresh_path = path.clone();
+        let (refresh, abort_handle) = future::abortable(async move {
+            loop {
+                let wait = match client.read(&path).await {
+                    Ok(secret) => {
+                        let fields = Some(secret.fields);
+                        let mut cache = cache.lock();
+                        // The secret may have been pruned while it was being read.
+                        let cached = match cache.get_mut(&path) {
+                            Some(cached) => cached,
+                            None => return,
+                        };
+                        if *cached != fields {
+                            debug!("Read a new version of the secret at '{}'", path);
+                            *cached = fields;
+                            generation.fetch_add(1, Ordering::Relaxed);
+                        }
+                        // Read the secret again well before its lease runs out.
+                        secret.lease
+                              .map(|lease| lease * 2 / 3)
+                              .unwrap_or(DEFAULT_REFRESH_INTERVAL)
+                    }
+                    Err(e) => {
+                        warn!("{}", e);
+                        RETRY_INTERVAL
+                    }
+                };
+                time::delay_for(wait).await;
+            }
+        });
+        self.refreshes.lock().insert(refresh_path, abort_handle);
+        tokio::spawn(refresh);
+    }
+
+    /// Stop keeping fresh, and forget, the secrets no service refers to any more.
+    fn prune(&self) {
+        let referenced = self.references
+                             .lock()
+                             .values()
+                             .flatten()
+                             .cloned()
+                             .collect::<HashSet<_>>();
+        let mut unreferenced = Vec::new();
+        self.refreshes.lock().retain(|path, abort_handle| {
+                                 if referenced.contains(path) {
+                                     true
+                                 } else {
+                                     abort_handle.abort();
+                                     unreferenced.push(path.clone());
+                                     false
+                                 }
+                             });
+        let mut cache = self.cache.lock();
+        for path in unreferenced {
+            debug!("No service refers to the secret at '{}' any more", path);
+            cache.remove(&path);
+        }
+    }
+}
+
+/// A reference to a field of a secret in Vault.
+#[derive(Debug, PartialEq)]
+struct SecretRef<'a> {
+    path:  &'a str,
+    field: &'a str,
+}
+
+impl<'a> SecretRef<'a> {
+    /// Parse a configuration value of the form `vault:<path>#<field>`.
+    fn parse(value: &'a str) -> Option<Self> {
+        if !value.starts_with(VAULT_PREFIX) {
+            return None;
+        }
+        let mut parts = value[VAULT_PREFIX.len()..].rsplitn(2, '#');
+        let field = parts.next()?;
+        let path = parts.next()?.trim_matches('/');
+        if path.is_empty() || field.is_empty() {
+            warn!("Ignoring malformed secret reference '{}'; expected 'vault:<path>#<field>'",
+                  value);
+            return None;
+        }
+        Some(SecretRef { path, field })
+    }
+}
+
+/// Replace every secret reference in `value` with the secret `lookup` gives for it. References
+/// that `lookup` has no secret for are left in place.
+fn resolve_value<F>(value: &mut toml::Value, lookup: &mut F)
+    where F: FnMut(&SecretRef) -> Option<String>
+{
+    match value {
+        toml::Value::String(s) => {
+            if let Some(secret) = SecretRef::parse(s).and_then(|reference| lookup(&reference)) {
+                *s = secret;
+            }
+        }
+        toml::Value::Array(values) => {
+            for value in values {
+                resolve_value(value, lookup);
+            }
+        }
+        toml::Value::Table(table) => {
+            for value in table.values_mut() {
+                resolve_value(value, lookup);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_secret_reference() {
+        assert_eq!(SecretRef::parse("vault:secret/data/postgres#password"),
+                   Some(SecretRef { path:  "secret/data/postgres",
+                                    field: "password", }));
+        assert_eq!(SecretRef::parse("vault:/secret/db/#user"),
+                   Some(SecretRef { path:  "secret/db",
+                                    field: "user", }));
+        assert_eq!(SecretRef::parse("secret/data/postgres#password"), None);
+        assert_eq!(SecretRef::parse("vault:secret/data/postgres"), None);
+        assert_eq!(SecretRef::parse("vault:#password"), None);
+        assert_eq!(SecretRef::parse("vault:secret/data/postgres#"), None);
+    }
+
+    #[test]
+    fn resolve_value_replaces_references_at_any_depth() {
+        let mut value: toml::Value = toml::from_str(
+                                                    r#"
+            port = 5432
+            user = "vault:secret/db#user"
+            [auth]
+            password = "vault:secret/db#password"
+            tokens = ["vault:secret/api#token", "plain"]
+            missing = "vault:secret/db#nope"
+        "#,
+        ).expect("valid toml");
+        let mut lookups = Vec::new();
+        resolve_value(&mut value, &mut |reference: &SecretRef| {
+            lookups.push(format!("{}#{}", reference.path, reference.field));
+            match reference.field {
+                "user" => Some("admin".to_string()),
+                "password" => Some("hunter2".to_string()),
+                "token" => Some("t0k3n".to_string()),
+                _ => None,
+            }
+        });
+        assert_eq!(lookups.len(), 4);
+        assert_eq!(value["port"].as_integer(), Some(5432));
+        assert_eq!(value["user"].as_str(), Some("admin"));
+        assert_eq!(value["auth"]["password"].as_str(), Some("hunter2"));
+        assert_eq!(value["auth"]["tokens"][0].as_str(), Some("t0k3n"));
+        assert_eq!(value["auth"]["tokens"][1].as_str(), Some("plain"));
+        assert_eq!(value["auth"]["missing"].as_str(),
+                   Some("vault:secret/db#nope"));
+    }
+}
@@ -0,0 +1,51 @@
+//! Secrets subsystem-specific error handling
+
+use std::{error,
+          fmt,
+          result};
+
+pub type Result<T> = result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    HabitatHttpClient(habitat_http_client::Error),
+    /// A request for the secret at the given path could not be made, for the given reason.
+    Request(String, String),
+    /// Vault answered a request for the secret at the given path with an unsuccessful status.
+    Status(String, u16),
+    /// Vault's answer to a request for the secret at the given path could not be parsed.
+    Json(String, serde_json::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::HabitatHttpClient(e) => format!("{}", e).fmt(f),
+            Error::Request(path, e) => {
+                format!("Unable to read the secret at '{}' from Vault: {}", path, e).fmt(f)
+            }
+            Error::Status(path, status) => {
+                format!("Vault answered the request for the secret at '{}' with status {}",
+                        path, status).fmt(f)
+            }
+            Error::Json(path, e) => {
+                format!("Unable to parse the secret at '{}' read from Vault: {}",
+                        path, e).fmt(f)
+            }
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::HabitatHttpClient(ref e) => Some(e),
+            Error::Json(_, ref e) => Some(e),
+            Error::Request(..) | Error::Status(..) => None,
+        }
+    }
+}
+
+impl From<habitat_http_client::Error> for Error {
+    fn from(error: habitat_http_client::Error) -> Self { Error::HabitatHttpClient(error) }
+}
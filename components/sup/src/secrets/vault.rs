@@ -0,0 +1,133 @@
+//! Reading secrets through Vault's HTTP API.
+
+use super::{Error,
+            Result};
+use habitat_http_client::ApiClient;
+use serde_json::{Map,
+                 Value};
+use std::{collections::HashMap,
+          fmt,
+          time::Duration};
+
+/// The header Vault expects its token in.
+const TOKEN_HEADER: &str = "X-Vault-Token";
+
+/// Where the Vault server is, and how to authenticate to it.
+#[derive(Clone, PartialEq)]
+pub struct VaultConfig {
+    pub url:   String,
+    pub token: String,
+}
+
+// The token is left out, so it doesn't end up in the Supervisor's debug output.
+impl fmt::Debug for VaultConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VaultConfig")
+         .field("url", &self.url)
+         .finish()
+    }
+}
+
+/// A secret read from Vault.
+#[derive(Debug, PartialEq)]
+pub(super) struct Secret {
+    pub fields: HashMap<String, String>,
+    /// How long the secret is valid for, when it is leased.
+    pub lease:  Option<Duration>,
+}
+
+pub(super) struct VaultClient {
+    client: ApiClient,
+    token:  String,
+}
+
+impl VaultClient {
+    pub fn new(config: VaultConfig) -> Result<Self> {
+        let client = ApiClient::new(config.url.as_str(), crate::PRODUCT, crate::VERSION, None)?;
+        Ok(VaultClient { client,
+                         token: config.token })
+    }
+
+    /// Read the secret at `path`, e.g. `secret/data/postgres`.
+    pub async fn read(&self, path: &str) -> Result<Secret> {
+        let response = self.client
+                           .get(&format!("v1/{}", path))
+                           .header(TOKEN_HEADER, self.token.as_str())
+                           .send()
+                           .await
+                           .map_err(|e| Error::Request(path.to_string(), e.to_string()))?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(Error::Status(path.to_string(), status.as_u16()));
+        }
+        let body = response.text()
+                           .await
+                           .map_err(|e| Error::Request(path.to_string(), e.to_string()))?;
+        parse_secret(&body).map_err(|e| Error::Json(path.to_string(), e))
+    }
+}
+
+#[derive(Deserialize)]
+struct SecretResponse {
+    #[serde(default)]
+    lease_duration: u64,
+    data:           Map<String, Value>,
+}
+
+fn parse_secret(body: &str) -> serde_json::Result<Secret> {
+    let response: SecretResponse = serde_json::from_str(body)?;
+    // Version 2 of the key/value secrets engine nests the fields of a secret under `data`, next
+    // to its `metadata`.
+    let data = match (response.data.get("data"), response.data.get("metadata")) {
+        (Some(Value::Object(data)), Some(_)) => data.clone(),
+        _ => response.data,
+    };
+    let fields = data.into_iter()
+                     .map(|(name, value)| {
+                         match value {
+                             Value::String(value) => (name, value),
+                             value => (name, value.to_string()),
+                         }
+                     })
+                     .collect();
+    let lease = if response.lease_duration > 0 {
+        Some(Duration::from_secs(response.lease_duration))
+    } else {
+        None
+    };
+    Ok(Secret { fields, lease })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_kv_version_1_secret() {
+        let body = r#"{"request_id": "5e2b4b4e", "lease_id": "", "renewable": false,
+                       "lease_duration": 2764800,
+                       "data": {"password": "hunter2", "port": 5432}}"#;
+        let secret = parse_secret(body).expect("parse secret");
+        assert_eq!(secret.fields.get("password").map(String::as_str),
+                   Some("hunter2"));
+        assert_eq!(secret.fields.get("port").map(String::as_str), Some("5432"));
+        assert_eq!(secret.lease, Some(Duration::from_secs(2_764_800)));
+    }
+
+    #[test]
+    fn parse_kv_version_2_secret() {
+        let body = r#"{"lease_duration": 0,
+                       "data": {"data": {"password": "hunter2"},
+                                "metadata": {"version": 3}}}"#;
+        let secret = parse_secret(body).expect("parse secret");
+        assert_eq!(secret.fields.len(), 1);
+        assert_eq!(secret.fields.get("password").map(String::as_str),
+                   Some("hunter2"));
+        assert_eq!(secret.lease, None);
+    }
+
+    #[test]
+    fn parse_secret_without_data_fails() {
+        assert!(parse_secret(r#"{"errors": ["permission denied"]}"#).is_err());
+    }
+}
@@ -0,0 +1,235 @@
+//! Push Supervisor and service metrics to a [StatsD][1] server.
+//!
+//! Besides serving Prometheus metrics from its HTTP gateway, the Supervisor can send metrics over
+//! UDP to a StatsD server (or anything that speaks its protocol, such as the Datadog agent or
+//! Telegraf) when it is given one with `--statsd-host`. Every metric name is prefixed with
+//! `--statsd-prefix`, and tags are sent in the [DogStatsD][2] form. The tags given with
+//! `--statsd-tag` are sent with every metric, and service metrics are also tagged with the
+//! `service` and `group` of the service.
+//!
+//! * `supervisor.start` (counter): the Supervisor started
+//! * `service.start` and `service.stop` (counters): a service started or stopped
+//! * `service.restart` (counter): a service is being restarted, tagged with the `reason`
+//! * `service.health` (gauge): the result of a service's last health check, as the exit code of a
+//!   health check hook reporting it
+//! * `service.health_transition` (counter): the health of a service changed, tagged with the
+//!   results it changed `from` and `to`
+//! * `service.hook_failure` (counter): one of a service's lifecycle hooks failed, tagged with the
+//!   `hook`
+//! * `service.update.duration` (timer): how long a service took to be loaded again with an update
+//!
+//! Metrics are sent as they happen, and are lost when the server can't be reached.
+//!
+//! [1]:https://github.com/statsd/statsd
+//! [2]:https://docs.datadoghq.com/developers/dogstatsd/datagram_shell/
+
+use crate::{error::{Error,
+                    Result},
+            manager::service::HealthCheckResult};
+use habitat_core::service::ServiceGroup;
+use state::Storage;
+use std::{fmt,
+          io,
+          net::{SocketAddr,
+                ToSocketAddrs,
+                UdpSocket},
+          time::Duration};
+
+lazy_static! {
+    static ref STATSD: Storage<Statsd> = Storage::new();
+}
+
+/// The StatsD server to send metrics to, and how to name and tag them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StatsdConfig {
+    pub host:   String,
+    pub port:   u16,
+    pub prefix: String,
+    /// Tags of the form `key:value` (or just `key`) sent with every metric.
+    pub tags:   Vec<String>,
+}
+
+struct Statsd {
+    socket: UdpSocket,
+    prefix: String,
+    tags:   Vec<String>,
+}
+
+/// The kinds of StatsD metrics the Supervisor sends.
+#[derive(Clone, Copy, Debug)]
+enum Kind {
+    Counter,
+    Gauge,
+    Timer,
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self {
+            Kind::Counter => "c",
+            Kind::Gauge => "g",
+            Kind::Timer => "ms",
+        };
+        write!(f, "{}", kind)
+    }
+}
+
+/// Resolves the StatsD server and stashes a socket for sending it metrics in a global static
+/// reference.
+pub fn init(config: StatsdConfig) -> Result<()> {
+    // Only initialize once
+    if STATSD.try_get().is_none() {
+        let mut addresses = (config.host.as_str(), config.port).to_socket_addrs()
+                                                               .map_err(Error::NameLookup)?;
+        let address = match addresses.next() {
+            Some(address) => address,
+            None => {
+                let msg = format!("no addresses found for {}", config.host);
+                return Err(Error::NameLookup(io::Error::new(io::ErrorKind::NotFound, msg)));
+            }
+        };
+        let local: SocketAddr = if address.is_ipv4() {
+            "0.0.0.0:0".parse().expect("valid socket address")
+        } else {
+            "[::]:0".parse().expect("valid socket address")
+        };
+        let socket = UdpSocket::bind(local).map_err(Error::Io)?;
+        socket.connect(address).map_err(Error::Io)?;
+        // Never hold up the Supervisor for the sake of a metric
+        socket.set_nonblocking(true).map_err(Error::Io)?;
+        STATSD.set(Statsd { socket,
+                            prefix: config.prefix,
+                            tags: config.tags });
+        send("supervisor.start", 1, Kind::Counter, &[]);
+    }
+    Ok(())
+}
+
+/// Count the start of a service.
+pub fn service_started(service_group: &ServiceGroup) {
+    send("service.start",
+         1,
+         Kind::Counter,
+         &service_tags(service_group));
+}
+
+/// Count the stop of a service.
+pub fn service_stopped(service_group: &ServiceGroup) {
+    send("service.stop",
+         1,
+         Kind::Counter,
+         &service_tags(service_group));
+}
+
+/// Count the restart of a service, either to run an `"update"` or because it needed a
+/// `"restart"`.
+pub fn service_restarted(service_group: &ServiceGroup, reason: &str) {
+    let mut tags = service_tags(service_group);
+    tags.push(format!("reason:{}", reason));
+    send("service.restart", 1, Kind::Counter, &tags);
+}
+
+/// Report the result of a health check of a service, counting it as a transition when it differs
+/// from the `previous` one.
+pub fn health_check(service_group: &ServiceGroup,
+                    previous: HealthCheckResult,
+                    result: HealthCheckResult) {
+    let tags = service_tags(service_group);
+    send("service.health", health_value(result), Kind::Gauge, &tags);
+    if previous != result {
+        let mut tags = tags;
+        tags.push(format!("from:{}", previous.to_string().to_lowercase()));
+        tags.push(format!("to:{}", result.to_string().to_lowercase()));
+        send("service.health_transition", 1, Kind::Counter, &tags);
+    }
+}
+
+/// Count a failed invocation of one of a service's lifecycle hooks.
+pub fn hook_failed(service_group: &ServiceGroup, hook: &str) {
+    let mut tags = service_tags(service_group);
+    tags.push(format!("hook:{}", hook));
+    send("service.hook_failure", 1, Kind::Counter, &tags);
+}
+
+/// Time how long a service took from the start of its update until it was loaded again with the
+/// new package.
+pub fn service_updated(service_group: &ServiceGroup, duration: Duration) {
+    send("service.update.duration",
+         duration.as_millis(),
+         Kind::Timer,
+         &service_tags(service_group));
+}
+
+fn service_tags(service_group: &ServiceGroup) -> Vec<String> {
+    vec![format!("service:{}", service_group.service()),
+         format!("group:{}", service_group.group())]
+}
+
+/// The health check result as the exit code of a health check hook reporting it.
+fn health_value(result: HealthCheckResult) -> u8 {
+    match result {
+        HealthCheckResult::Ok => 0,
+        HealthCheckResult::Warning => 1,
+        HealthCheckResult::Critical => 2,
+        HealthCheckResult::Unknown => 3,
+    }
+}
+
+/// Render a metric as a DogStatsD datagram.
+fn datagram(prefix: &str,
+            name: &str,
+            value: impl fmt::Display,
+            kind: Kind,
+            tags: &[String])
+            -> String {
+    let mut datagram = if prefix.is_empty() {
+        format!("{}:{}|{}", name, value, kind)
+    } else {
+        format!("{}.{}:{}|{}", prefix, name, value, kind)
+    };
+    if !tags.is_empty() {
+        datagram.push_str("|#");
+        datagram.push_str(&tags.join(","));
+    }
+    datagram
+}
+
+/// Send a metric, if `init` has been called.
+fn send(name: &str, value: impl fmt::Display, kind: Kind, tags: &[String]) {
+    if let Some(statsd) = STATSD.try_get() {
+        let tags = statsd.tags.iter().chain(tags).cloned().collect::<Vec<_>>();
+        let datagram = datagram(&statsd.prefix, name, value, kind, &tags);
+        trace!("Sending to StatsD: {}", datagram);
+        if let Err(e) = statsd.socket.send(datagram.as_bytes()) {
+            debug!("Failed to send metric to StatsD: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn datagrams_are_prefixed_and_tagged() {
+        assert_eq!(datagram("habitat", "service.start", 1, Kind::Counter, &[]),
+                   "habitat.service.start:1|c");
+        assert_eq!(datagram("", "service.health", 2, Kind::Gauge, &[]),
+                   "service.health:2|g");
+        let tags = vec!["env:prod".to_string(), "service:redis".to_string()];
+        assert_eq!(datagram("habitat",
+                            "service.update.duration",
+                            1500,
+                            Kind::Timer,
+                            &tags),
+                   "habitat.service.update.duration:1500|ms|#env:prod,service:redis");
+    }
+
+    #[test]
+    fn health_is_reported_as_a_hook_exit_code() {
+        assert_eq!(health_value(HealthCheckResult::Ok), 0);
+        assert_eq!(health_value(HealthCheckResult::Warning), 1);
+        assert_eq!(health_value(HealthCheckResult::Critical), 2);
+        assert_eq!(health_value(HealthCheckResult::Unknown), 3);
+    }
+}
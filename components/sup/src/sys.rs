@@ -8,7 +8,8 @@ mod windows;
 #[cfg(windows)]
 pub use self::windows::*;
 
-use std::fmt;
+use std::{fmt,
+          time::Duration};
 
 #[derive(Debug)]
 pub enum ShutdownMethod {
@@ -27,3 +28,24 @@ impl fmt::Display for ShutdownMethod {
         write!(f, "{}", msg)
     }
 }
+
+/// The CPU time and memory used by the Supervisor process, where they can be determined.
+#[derive(Debug, Default)]
+pub struct ResourceUsage {
+    pub cpu_time:              Option<Duration>,
+    pub resident_memory_bytes: Option<u64>,
+}
+
+/// The resources used by the processes of a service: its main process and all of that process's
+/// descendants. Each figure is `None` where it can't be determined.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct ServiceResourceUsage {
+    /// The CPU time used by the processes, in milliseconds.
+    pub cpu_time_ms:     Option<u64>,
+    /// The resident memory of the processes, in bytes.
+    pub memory_bytes:    Option<u64>,
+    /// The files the processes hold open. On Windows, this counts all of their open handles.
+    pub open_files:      Option<u64>,
+    /// The number of descendants of the main process.
+    pub child_processes: u64,
+}
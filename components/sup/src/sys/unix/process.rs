@@ -0,0 +1,179 @@
+use crate::sys::{ResourceUsage,
+                 ServiceResourceUsage};
+use habitat_core::os::process::Pid;
+#[cfg(target_os = "linux")]
+use std::fs;
+use std::{mem,
+          time::Duration};
+
+/// The CPU time and memory used by the current process.
+pub fn own_resource_usage() -> ResourceUsage {
+    ResourceUsage { cpu_time:              cpu_time(),
+                    resident_memory_bytes: resident_memory_bytes(), }
+}
+
+#[allow(clippy::unnecessary_cast)]
+fn cpu_time() -> Option<Duration> {
+    let mut usage: libc::rusage = unsafe { mem::zeroed() };
+    if unsafe { libc::getrusage(libc::RUSAGE_SELF, &mut usage) } != 0 {
+        return None;
+    }
+    let duration = |time: libc::timeval| {
+        Duration::from_secs(time.tv_sec as u64) + Duration::from_micros(time.tv_usec as u64)
+    };
+    Some(duration(usage.ru_utime) + duration(usage.ru_stime))
+}
+
+/// The resident set size of the current process, from the second field of `/proc/self/statm`.
+#[cfg(target_os = "linux")]
+#[allow(clippy::unnecessary_cast)]
+fn resident_memory_bytes() -> Option<u64> {
+    let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+    let pages = statm.split_whitespace().nth(1)?.parse::<u64>().ok()?;
+    match unsafe { libc::sysconf(libc::_SC_PAGESIZE) } {
+        page_size if page_size > 0 => Some(pages * page_size as u64),
+        _ => None,
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn resident_memory_bytes() -> Option<u64> { None }
+
+/// The total physical memory of the machine, in bytes.
+#[allow(clippy::unnecessary_cast)]
+pub fn total_memory_bytes() -> Option<u64> {
+    let pages = unsafe { libc::sysconf(libc::_SC_PHYS_PAGES) };
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    if pages > 0 && page_size > 0 {
+        Some(pages as u64 * page_size as u64)
+    } else {
+        None
+    }
+}
+
+/// The resources used by the process `pid` and all of its descendants, read from `/proc`. `None`
+/// when the process no longer exists.
+#[cfg(target_os = "linux")]
+#[allow(clippy::unnecessary_cast)]
+pub fn service_resource_usage(pid: Pid) -> Option<ServiceResourceUsage> {
+    let stats = fs::read_dir("/proc").ok()?
+                                     .filter_map(|entry| entry.ok())
+                                     .filter_map(|entry| entry.file_name().to_str()?.parse().ok())
+                                     .filter_map(ProcStat::read)
+                                     .collect::<Vec<_>>();
+    let tree = process_tree(&stats, pid);
+    if tree.is_empty() {
+        return None;
+    }
+    let ticks_per_second = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+    let cpu_time_ms = if ticks_per_second > 0 {
+        Some(tree.iter().map(|s| s.cpu_ticks).sum::<u64>() * 1000 / ticks_per_second as u64)
+    } else {
+        None
+    };
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    let memory_bytes = if page_size > 0 {
+        Some(tree.iter().map(|s| s.rss_pages).sum::<u64>() * page_size as u64)
+    } else {
+        None
+    };
+    // Reading the open files of another user's processes takes privileges the Supervisor may
+    // not have
+    let open_files = tree.iter().map(|s| open_files(s.pid)).sum::<Option<u64>>();
+    Some(ServiceResourceUsage { cpu_time_ms,
+                                memory_bytes,
+                                open_files,
+                                child_processes: tree.len() as u64 - 1 })
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn service_resource_usage(_pid: Pid) -> Option<ServiceResourceUsage> { None }
+
+/// The fields of `/proc/<pid>/stat` that resource usage is determined from.
+#[cfg(target_os = "linux")]
+#[derive(Debug, PartialEq)]
+struct ProcStat {
+    pid:       Pid,
+    ppid:      Pid,
+    /// User and system CPU time, in clock ticks
+    cpu_ticks: u64,
+    rss_pages: u64,
+}
+
+#[cfg(target_os = "linux")]
+impl ProcStat {
+    fn read(pid: Pid) -> Option<Self> {
+        Self::parse(&fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?)
+    }
+
+    /// The command in the second field is in parentheses and may itself hold spaces and
+    /// parentheses, so the fields after it are found from the last `)`. Fields are numbered as in
+    /// proc(5), which starts the fields after the command at 3.
+    fn parse(stat: &str) -> Option<Self> {
+        let pid = stat.split(' ').next()?.parse().ok()?;
+        let fields = stat[stat.rfind(')')? + 1..].split_whitespace()
+                                                 .collect::<Vec<_>>();
+        let field = |n: usize| fields.get(n - 3).and_then(|f| f.parse::<u64>().ok());
+        Some(ProcStat { pid,
+                        ppid: fields.get(4 - 3)?.parse().ok()?,
+                        cpu_ticks: field(14)? + field(15)?,
+                        rss_pages: field(24)? })
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn open_files(pid: Pid) -> Option<u64> {
+    fs::read_dir(format!("/proc/{}/fd", pid)).ok()
+                                             .map(|fds| fds.count() as u64)
+}
+
+/// The process `root` followed by all of its descendants, or nothing when `root` is not running.
+#[cfg(target_os = "linux")]
+fn process_tree(stats: &[ProcStat], root: Pid) -> Vec<&ProcStat> {
+    let mut tree = stats.iter().filter(|s| s.pid == root).collect::<Vec<_>>();
+    let mut i = 0;
+    while i < tree.len() {
+        let parent = tree[i].pid;
+        tree.extend(stats.iter().filter(|s| s.ppid == parent && s.pid != root));
+        i += 1;
+    }
+    tree
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn proc_stat_is_parsed_past_the_command() {
+        let stat = "4242 (my (odd) cmd) S 1 4242 4242 0 -1 4194560 1234 0 0 0 150 50 0 0 20 0 3 0 \
+                    123456 104857600 2560 18446744073709551615 1 1 0 0 0 0 0 4096 0 0 0 0 17 2 0 \
+                    0 0 0 0";
+        assert_eq!(ProcStat::parse(stat),
+                   Some(ProcStat { pid:       4242,
+                                   ppid:      1,
+                                   cpu_ticks: 200,
+                                   rss_pages: 2560, }));
+        assert_eq!(ProcStat::parse("4242 (truncated) S 1"), None);
+    }
+
+    #[test]
+    fn process_tree_holds_all_descendants() {
+        let stat = |pid, ppid| {
+            ProcStat { pid,
+                       ppid,
+                       cpu_ticks: 0,
+                       rss_pages: 0 }
+        };
+        let stats = vec![stat(1, 0),
+                         stat(10, 1),
+                         stat(11, 10),
+                         stat(12, 11),
+                         stat(13, 1)];
+        let tree = process_tree(&stats, 10).iter()
+                                           .map(|s| s.pid)
+                                           .collect::<Vec<_>>();
+        assert_eq!(tree, vec![10, 11, 12]);
+        assert!(process_tree(&stats, 99).is_empty());
+    }
+}
@@ -0,0 +1,90 @@
+use super::service::build_proc_table;
+use crate::sys::{ResourceUsage,
+                 ServiceResourceUsage};
+use habitat_core::os::process::{handle_from_pid,
+                                windows_child::Handle,
+                                Pid};
+use std::mem;
+use winapi::{shared::minwindef::{DWORD,
+                                 FILETIME},
+             um::{processthreadsapi,
+                  psapi::{self,
+                          PROCESS_MEMORY_COUNTERS},
+                  sysinfoapi::{self,
+                               MEMORYSTATUSEX}}};
+
+/// The CPU time and memory used by the current process. Neither is determined on Windows yet.
+pub fn own_resource_usage() -> ResourceUsage { ResourceUsage::default() }
+
+/// The total physical memory of the machine, in bytes.
+pub fn total_memory_bytes() -> Option<u64> {
+    let mut status: MEMORYSTATUSEX = unsafe { mem::zeroed() };
+    status.dwLength = mem::size_of::<MEMORYSTATUSEX>() as DWORD;
+    if unsafe { sysinfoapi::GlobalMemoryStatusEx(&mut status) } == 0 {
+        return None;
+    }
+    Some(status.ullTotalPhys)
+}
+
+/// The resources used by the process `pid` and all of its descendants. `None` when the process
+/// no longer exists.
+pub fn service_resource_usage(pid: Pid) -> Option<ServiceResourceUsage> {
+    let table = build_proc_table();
+    let mut tree = vec![pid];
+    let mut i = 0;
+    while i < tree.len() {
+        if let Some(children) = table.get(&tree[i]) {
+            tree.extend(children.iter().filter(|&&child| child != pid));
+        }
+        i += 1;
+    }
+    // The processes that exited since the table was built are left out
+    let processes = tree.iter()
+                        .filter_map(|&pid| handle_from_pid(pid).map(Handle::new))
+                        .collect::<Vec<_>>();
+    if processes.is_empty() {
+        return None;
+    }
+    Some(ServiceResourceUsage { cpu_time_ms:     processes.iter().map(cpu_time_ms).sum(),
+                                memory_bytes:    processes.iter().map(working_set_bytes).sum(),
+                                open_files:      processes.iter().map(handle_count).sum(),
+                                child_processes: processes.len() as u64 - 1, })
+}
+
+fn cpu_time_ms(process: &Handle) -> Option<u64> {
+    let mut creation: FILETIME = unsafe { mem::zeroed() };
+    let mut exit: FILETIME = unsafe { mem::zeroed() };
+    let mut kernel: FILETIME = unsafe { mem::zeroed() };
+    let mut user: FILETIME = unsafe { mem::zeroed() };
+    if unsafe {
+        processthreadsapi::GetProcessTimes(process.raw(),
+                                           &mut creation,
+                                           &mut exit,
+                                           &mut kernel,
+                                           &mut user)
+    } == 0
+    {
+        return None;
+    }
+    // FILETIMEs count 100 nanosecond intervals
+    let intervals =
+        |time: FILETIME| (u64::from(time.dwHighDateTime) << 32) | u64::from(time.dwLowDateTime);
+    Some((intervals(kernel) + intervals(user)) / 10_000)
+}
+
+fn working_set_bytes(process: &Handle) -> Option<u64> {
+    let mut counters: PROCESS_MEMORY_COUNTERS = unsafe { mem::zeroed() };
+    let size = mem::size_of::<PROCESS_MEMORY_COUNTERS>() as DWORD;
+    if unsafe { psapi::GetProcessMemoryInfo(process.raw(), &mut counters, size) } == 0 {
+        return None;
+    }
+    Some(counters.WorkingSetSize as u64)
+}
+
+fn handle_count(process: &Handle) -> Option<u64> {
+    let mut count: DWORD = 0;
+    if unsafe { processthreadsapi::GetProcessHandleCount(process.raw(), &mut count) } == 0 {
+        return None;
+    }
+    Some(u64::from(count))
+}
@@ -23,7 +23,8 @@ use winapi::{shared::minwindef::{DWORD,
                   wincon}};
 
 const PROCESS_ACTIVE: u32 = 259;
-type ProcessTable = HashMap<DWORD, Vec<DWORD>>;
+/// The child processes of each process, by its ID.
+pub(crate) type ProcessTable = HashMap<DWORD, Vec<DWORD>>;
 
 /// Kill a service process
 pub fn kill(pid: Pid, shutdown_config: &ShutdownConfig) -> ShutdownMethod {
@@ -104,7 +105,7 @@ impl Process {
     }
 }
 
-fn build_proc_table() -> ProcessTable {
+pub(crate) fn build_proc_table() -> ProcessTable {
     let processes_snap_handle =
         unsafe { tlhelp32::CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0) };
 
@@ -9,11 +9,12 @@ use hab::{command::pkg::{self,
           error::Result as HabResult};
 use habitat_api_client::BuilderAPIClient;
 use habitat_common::{cli_config::CliConfig,
-                     command::package::install::{self as install_cmd,
-                                                 InstallHookMode,
-                                                 InstallMode,
-                                                 InstallSource,
-                                                 LocalPackageUsage},
+                     command::package::{install::{self as install_cmd,
+                                                  InstallHookMode,
+                                                  InstallMode,
+                                                  InstallSource,
+                                                  LocalPackageUsage},
+                                        relocate::RelocateMode},
                      outputln,
                      ui::{NullUi,
                           UIWriter}};
@@ -59,8 +60,10 @@ pub async fn install<T>(ui: &mut T,
                        // Install hooks are run when the supervisor
                        // loads the package in add_service so it is
                        // repetitive to run them here
-                       InstallHookMode::Ignore).await
-                                               .map_err(Error::from)
+                       InstallHookMode::Ignore,
+                       // Services run straight out of the Supervisor's filesystem root
+                       RelocateMode::Relocate).await
+                                              .map_err(Error::from)
 }
 
 // `install` but with no ui output and the benefit of thread safety
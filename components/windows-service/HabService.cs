@@ -1,12 +1,16 @@
 ﻿using System;
+using System.Collections.Generic;
 using System.Configuration;
 using System.Diagnostics;
 using System.IO;
 using System.Management;
+using System.Net.Http;
 using System.Reflection;
 using System.Runtime.InteropServices;
 using System.ServiceProcess;
+using System.Threading;
 using System.Xml;
+using Newtonsoft.Json.Linq;
 
 namespace HabService
 {
@@ -37,6 +41,12 @@ namespace HabService
         }
 
         private Process proc = null;
+        private Timer healthTimer = null;
+        private int unhealthyPolls = 0;
+        private bool stopping = false;
+        private readonly Dictionary<string, string> serviceHealth = new Dictionary<string, string>();
+        private readonly List<string> pausedServices = new List<string>();
+        private static readonly HttpClient http = new HttpClient();
         private static readonly log4net.ILog log = log4net.LogManager.GetLogger(typeof(HabService));
 
         /// <summary>
@@ -67,7 +77,10 @@ namespace HabService
         {
             ServiceName = "HabService";
             CanStop = true;
+            CanShutdown = true;
+            CanPauseAndContinue = true;
             AutoLog = true;
+            http.Timeout = TimeSpan.FromSeconds(10);
         }
 
         protected override void OnStart(string[] args)
@@ -106,10 +119,202 @@ namespace HabService
                 proc.Start();
                 proc.BeginErrorReadLine();
                 proc.BeginOutputReadLine();
+
+                // Stay in START_PENDING until the Supervisor answers on its HTTP
+                // gateway so that the SCM only reports the service as running
+                // once it can actually do something.
+                WaitForSupervisor();
+
+                var interval = SettingSeconds("healthCheckInterval", 30);
+                if (interval > 0)
+                {
+                    healthTimer = new Timer(PollHealth, null, interval * 1000, interval * 1000);
+                }
             }
             catch(Exception e)
             {
                 log.Error("Error occured in OnStart", e);
+                // A non-zero exit code lets the SCM apply its recovery actions
+                ExitCode = 1;
+                throw;
+            }
+        }
+
+        private void WaitForSupervisor()
+        {
+            var timeout = SettingSeconds("startTimeout", 60);
+            var deadline = DateTime.Now.AddSeconds(timeout);
+            while (DateTime.Now < deadline)
+            {
+                if (proc.HasExited)
+                {
+                    throw new InvalidOperationException("Chef Habitat Supervisor exited during startup");
+                }
+                RequestAdditionalTime(5000);
+                if (FetchServices() != null)
+                {
+                    log.Info("Chef Habitat Supervisor is up and answering on its HTTP gateway");
+                    return;
+                }
+                Thread.Sleep(1000);
+            }
+            log.Warn(String.Format("Chef Habitat Supervisor did not answer on {0} within {1} seconds; reporting the service as running anyway", GatewayUrl, timeout));
+        }
+
+        private JArray FetchServices()
+        {
+            try
+            {
+                var response = http.GetAsync(String.Format("{0}/services", GatewayUrl)).Result;
+                if (!response.IsSuccessStatusCode)
+                {
+                    return null;
+                }
+                return JArray.Parse(response.Content.ReadAsStringAsync().Result);
+            }
+            catch(Exception e)
+            {
+                log.Debug("Unable to query the Supervisor HTTP gateway", e);
+                return null;
+            }
+        }
+
+        // Polls the health of every loaded service. Health transitions are
+        // logged and, when 'unhealthyThreshold' is set, a service that stays
+        // critical for that many consecutive polls stops this Windows service
+        // with a failure exit code so that the configured SCM recovery actions
+        // (restart by default) are applied.
+        private void PollHealth(object state)
+        {
+            if (stopping)
+            {
+                return;
+            }
+            var services = FetchServices();
+            if (services == null)
+            {
+                log.Warn("Unable to retrieve service health from the Chef Habitat Supervisor");
+                return;
+            }
+
+            var critical = new List<string>();
+            lock (serviceHealth)
+            {
+                foreach (var svc in services)
+                {
+                    var group = (string)svc["service_group"];
+                    var health = (string)svc["health_check"];
+                    string previous;
+                    if (serviceHealth.TryGetValue(group, out previous) && previous != health)
+                    {
+                        log.Info(String.Format("Health of {0} changed from {1} to {2}", group, previous, health));
+                    }
+                    serviceHealth[group] = health;
+                    if (health == "Critical")
+                    {
+                        critical.Add(group);
+                    }
+                }
+            }
+
+            var threshold = SettingSeconds("unhealthyThreshold", 0);
+            unhealthyPolls = critical.Count > 0 ? unhealthyPolls + 1 : 0;
+            if (threshold > 0 && unhealthyPolls >= threshold)
+            {
+                log.Error(String.Format("Services have been critical for {0} consecutive health checks: {1}", unhealthyPolls, String.Join(", ", critical)));
+                ExitCode = 1;
+                Stop();
+            }
+        }
+
+        protected override void OnPause()
+        {
+            // Pausing stops every running service while leaving the Supervisor
+            // itself, and therefore its gossip membership, in place.
+            healthTimer?.Change(Timeout.Infinite, Timeout.Infinite);
+            var services = FetchServices();
+            if (services == null)
+            {
+                log.Error("Unable to query loaded services; not pausing");
+                throw new InvalidOperationException("Chef Habitat Supervisor is not answering on its HTTP gateway");
+            }
+            pausedServices.Clear();
+            foreach (var svc in services)
+            {
+                if ((string)svc["desired_state"] == "Up")
+                {
+                    var ident = (string)svc["spec_identifier"];
+                    RequestAdditionalTime(30000);
+                    if (RunHab(String.Format("svc stop {0}", ident)))
+                    {
+                        pausedServices.Add(ident);
+                    }
+                }
+            }
+            log.Info(String.Format("Chef Habitat service paused; stopped {0} service(s)", pausedServices.Count));
+        }
+
+        protected override void OnContinue()
+        {
+            foreach (var ident in pausedServices)
+            {
+                RequestAdditionalTime(30000);
+                RunHab(String.Format("svc start {0}", ident));
+            }
+            log.Info(String.Format("Chef Habitat service continued; started {0} service(s)", pausedServices.Count));
+            pausedServices.Clear();
+            var interval = SettingSeconds("healthCheckInterval", 30);
+            if (interval > 0)
+            {
+                healthTimer?.Change(interval * 1000, interval * 1000);
+            }
+        }
+
+        private bool RunHab(string args)
+        {
+            var hab = new Process();
+            hab.StartInfo.UseShellExecute = false;
+            hab.StartInfo.CreateNoWindow = true;
+            hab.StartInfo.FileName = HabPath;
+            hab.StartInfo.Arguments = args;
+            hab.Start();
+            if (!hab.WaitForExit(30000))
+            {
+                log.Error(String.Format("'hab {0}' did not complete within 30 seconds", args));
+                hab.Kill();
+                return false;
+            }
+            if (hab.ExitCode != 0)
+            {
+                log.Error(String.Format("'hab {0}' failed with exit code {1}", args, hab.ExitCode));
+                return false;
+            }
+            return true;
+        }
+
+        private static int SettingSeconds(string key, int defaultValue)
+        {
+            int value;
+            if (ConfigurationManager.AppSettings[key] != null && Int32.TryParse(ConfigurationManager.AppSettings[key], out value))
+            {
+                return value;
+            }
+            return defaultValue;
+        }
+
+        private static string GatewayUrl
+        {
+            get
+            {
+                return (ConfigurationManager.AppSettings["gatewayUrl"] ?? "http://localhost:9631").TrimEnd('/');
+            }
+        }
+
+        private static string HabPath
+        {
+            get
+            {
+                return ConfigurationManager.AppSettings["habPath"] ?? "hab.exe";
             }
         }
 
@@ -187,14 +392,32 @@ namespace HabService
                 log.Info("Waiting for Supervisor to exit...");
                 sup.WaitForExit();
             }
+            // The Supervisor was not asked to stop, so report a failure and let
+            // the SCM recovery actions decide whether to restart us.
+            ExitCode = proc.ExitCode != 0 ? proc.ExitCode : 1;
             Stop();
         }
 
+        protected override void OnShutdown()
+        {
+            StopSupervisor();
+        }
+
         protected override void OnStop()
+        {
+            // Keep the SCM in STOP_PENDING while the Supervisor shuts its services down
+            RequestAdditionalTime(65000);
+            StopSupervisor();
+        }
+
+        private void StopSupervisor()
         {
             try
             {
+                stopping = true;
+                healthTimer?.Dispose();
                 if(!proc.HasExited) {
+
                     // unregister exit handler so we don't trigger it here
                     proc.Exited -= ExitHandler;
 
@@ -25,7 +25,13 @@ function Install-HabService {
     if($LASTEXITCODE -ne 0) {
         Write-Error "Failed to install the Habitat Service!"
     } else {
-        &$env:systemroot\system32\sc.exe description Habitat "The Habitat Supervisor service"
+        &$env:systemroot\system32\sc.exe description Habitat "Runs the Chef Habitat Supervisor, which manages the lifecycle, configuration and health of Habitat services on this machine"
+        # Restart after the first two failures and give up after the third
+        # within a day. The failure flag makes the SCM also apply these actions
+        # when the service stops itself with a non-zero exit code, which is how
+        # an exited Supervisor or persistently critical services are reported.
+        &$env:systemroot\system32\sc.exe failure Habitat reset= 86400 actions= restart/60000/restart/60000/none/0
+        &$env:systemroot\system32\sc.exe failureflag Habitat 1
         Write-Host "Congratulations! The Habitat Service has succesfully been installed!"
     }
 }